@@ -0,0 +1,168 @@
+//! Programmatically generated PDF fixtures for the benchmarks.
+//!
+//! Every fixture is a structurally valid PDF with correct cross-reference
+//! offsets, so both the `/Type /Page` scan and the object-index (structured)
+//! path can be measured on the same bytes.
+
+/// Incrementally writes PDF objects while recording their offsets.
+struct PdfWriter {
+    out: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self { out: b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec(), offsets: Vec::new() }
+    }
+
+    /// Appends object `id` (ids must be written in order starting at 1).
+    fn object(&mut self, id: usize, body: &[u8]) {
+        assert_eq!(id, self.offsets.len() + 1, "objects must be written in order");
+        self.offsets.push(self.out.len());
+        self.out.extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+        self.out.extend_from_slice(body);
+        self.out.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// Finishes the file with a classic `xref` table and trailer.
+    fn finish_classic(mut self, root: usize) -> Vec<u8> {
+        let xref = self.out.len();
+        let count = self.offsets.len() + 1;
+        self.out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", count).as_bytes());
+        for offset in &self.offsets {
+            self.out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        self.out.extend_from_slice(
+            format!("trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n", count, root, xref)
+                .as_bytes(),
+        );
+        self.out
+    }
+
+    /// Finishes the file with an uncompressed cross-reference stream.
+    fn finish_xref_stream(mut self, root: usize) -> Vec<u8> {
+        let xref_id = self.offsets.len() + 1;
+        let xref_offset = self.out.len();
+        self.offsets.push(xref_offset);
+
+        // W [1 4 2]: type, offset, generation
+        let mut data = vec![0u8, 0, 0, 0, 0, 0xFF, 0xFF];
+        for offset in &self.offsets {
+            data.push(1);
+            data.extend_from_slice(&(*offset as u32).to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+        }
+        let size = xref_id + 1;
+        self.out.extend_from_slice(
+            format!(
+                "{} 0 obj\n<< /Type /XRef /Size {} /W [1 4 2] /Root {} 0 R /Length {} >>\nstream\n",
+                xref_id,
+                size,
+                root,
+                data.len()
+            )
+            .as_bytes(),
+        );
+        self.out.extend_from_slice(&data);
+        self.out.extend_from_slice(
+            format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref_offset).as_bytes(),
+        );
+        self.out
+    }
+}
+
+/// Writes catalog, page tree, pages, and content streams; returns the writer and catalog id.
+///
+/// Object layout: 1 catalog, 2 page tree, then a page and its content stream
+/// per page, then `extra` objects supplied by the caller.
+fn write_pages(
+    pages: usize,
+    content: &[u8],
+    catalog_extra: &str,
+    extra: impl FnOnce(&mut PdfWriter, usize),
+) -> PdfWriter {
+    let mut w = PdfWriter::new();
+    w.object(1, format!("<< /Type /Catalog /Pages 2 0 R {} >>", catalog_extra).as_bytes());
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    w.object(
+        2,
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes(),
+    );
+    for i in 0..pages {
+        let page_id = 3 + i * 2;
+        w.object(
+            page_id,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents {} 0 R >>",
+                page_id + 1
+            )
+            .as_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content);
+        stream.extend_from_slice(b"\nendstream");
+        w.object(page_id + 1, &stream);
+    }
+    let next_id = 3 + pages * 2;
+    extra(&mut w, next_id);
+    w
+}
+
+const PAGE_CONTENT: &[u8] = b"BT /F1 12 Tf 72 720 Td (Benchmark page) Tj ET\n0 0 0 rg 72 72 450 600 re f";
+
+/// A small PDF with a classic `xref` table.
+pub fn small_classic(pages: usize) -> Vec<u8> {
+    write_pages(pages, PAGE_CONTENT, "", |_, _| {}).finish_classic(1)
+}
+
+/// A PDF whose cross-reference data is stored in an xref stream.
+pub fn xref_stream(pages: usize) -> Vec<u8> {
+    write_pages(pages, PAGE_CONTENT, "", |_, _| {}).finish_xref_stream(1)
+}
+
+/// A PDF with a flat outline of `bookmarks` entries pointing at its pages.
+pub fn bookmark_heavy(pages: usize, bookmarks: usize) -> Vec<u8> {
+    let outlines_id = 3 + pages * 2;
+    write_pages(pages, PAGE_CONTENT, &format!("/Outlines {} 0 R", outlines_id), |w, first_id| {
+        let first_item = first_id + 1;
+        let last_item = first_id + bookmarks;
+        w.object(
+            first_id,
+            format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                first_item, last_item, bookmarks
+            )
+            .as_bytes(),
+        );
+        for i in 0..bookmarks {
+            let id = first_item + i;
+            let mut item = format!(
+                "<< /Title (Section {}) /Parent {} 0 R /Dest [{} 0 R /Fit]",
+                i + 1,
+                first_id,
+                3 + (i % pages) * 2
+            );
+            if i > 0 {
+                item.push_str(&format!(" /Prev {} 0 R", id - 1));
+            }
+            if i + 1 < bookmarks {
+                item.push_str(&format!(" /Next {} 0 R", id + 1));
+            }
+            item.push_str(" >>");
+            w.object(id, item.as_bytes());
+        }
+    })
+    .finish_classic(1)
+}
+
+/// A PDF of roughly `target_bytes`, made of pages with large content streams.
+pub fn large(target_bytes: usize) -> Vec<u8> {
+    const PAGES: usize = 2000;
+    let per_page = target_bytes / PAGES;
+    let mut content = Vec::with_capacity(per_page);
+    while content.len() + PAGE_CONTENT.len() + 1 < per_page {
+        content.extend_from_slice(PAGE_CONTENT);
+        content.push(b'\n');
+    }
+    write_pages(PAGES, &content, "", |_, _| {}).finish_classic(1)
+}
@@ -0,0 +1,77 @@
+//! Benchmarks for the PDF page counting strategies.
+//!
+//! Run with `cargo bench --bench pdf`. The large fixture defaults to 100 MB and
+//! can be resized with `PDF_BENCH_LARGE_MB`.
+//!
+//! The regression checks against recorded baselines (structured path vs scan
+//! time, bytes examined) live in `tests/pdf_regression.rs`, on the same
+//! fixtures.
+
+mod fixtures;
+
+use criterion::{black_box, criterion_group, BenchmarkId, Criterion, Throughput};
+use page_counter_wasm::assembly::estimate_bytes;
+use page_counter_wasm::estimators::estimate_pdf_pages;
+use page_counter_wasm::pdf::{PdfCounter, PdfStrategy};
+use page_counter_wasm::pdf_color::analyze_page_colors;
+use page_counter_wasm::schema::EstimateOptions;
+use std::time::Duration;
+
+fn large_fixture_bytes() -> usize {
+    std::env::var("PDF_BENCH_LARGE_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100)
+        * 1024
+        * 1024
+}
+
+fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("small_classic", fixtures::small_classic(10)),
+        ("xref_stream", fixtures::xref_stream(10)),
+        ("bookmark_heavy", fixtures::bookmark_heavy(200, 5000)),
+        ("large", fixtures::large(large_fixture_bytes())),
+    ]
+}
+
+fn structured_page_count(bytes: &[u8]) -> usize {
+    PdfCounter::new(vec![PdfStrategy::PageTree]).count(bytes).count
+}
+
+fn bench_pdf(c: &mut Criterion) {
+    let options = EstimateOptions::default();
+    let count_only = EstimateOptions { include_page_sizes: Some(false), ..Default::default() };
+    let mut group = c.benchmark_group("pdf");
+    group.sample_size(10).measurement_time(Duration::from_secs(10));
+
+    for (name, bytes) in fixtures() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::new("scan", name), &bytes, |b, bytes| {
+            b.iter(|| estimate_pdf_pages(black_box(bytes), &options))
+        });
+        group.bench_with_input(BenchmarkId::new("structured", name), &bytes, |b, bytes| {
+            b.iter(|| structured_page_count(black_box(bytes)))
+        });
+        group.bench_with_input(BenchmarkId::new("count_scan", name), &bytes, |b, bytes| {
+            b.iter(|| PdfCounter::new(vec![PdfStrategy::CountScan]).count(black_box(bytes)))
+        });
+        group.bench_with_input(BenchmarkId::new("color", name), &bytes, |b, bytes| {
+            b.iter(|| analyze_page_colors(black_box(bytes)))
+        });
+        group.bench_with_input(BenchmarkId::new("estimate_document", name), &bytes, |b, bytes| {
+            b.iter(|| estimate_bytes(black_box(bytes), Some("fixture.pdf"), &options))
+        });
+        group.bench_with_input(BenchmarkId::new("count_only", name), &bytes, |b, bytes| {
+            b.iter(|| estimate_bytes(black_box(bytes), Some("fixture.pdf"), &count_only))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pdf);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,169 @@
+//! Benchmarks for reading XLSX cell widths.
+//!
+//! Run with `cargo bench --bench xlsx`. The generated workbook has 500,000
+//! rows by default and can be resized with `XLSX_BENCH_ROWS`.
+//!
+//! Column widths are sampled from the first `COLUMN_WIDTH_SAMPLE_ROWS`
+//! populated rows, either through calamine, which materializes every shared
+//! string when the workbook is opened, or through the lazy shared strings
+//! index. Before the criterion groups, a check asserts that both give the same
+//! widths. `estimate_document` is measured on the same workbook for scale.
+
+use calamine::{Data, Reader, Xlsx};
+use criterion::{black_box, criterion_group, Criterion, Throughput};
+use page_counter_wasm::assembly::estimate_bytes;
+use page_counter_wasm::file_utils::open_zip_archive;
+use page_counter_wasm::schema::EstimateOptions;
+use page_counter_wasm::text_layout::char_width;
+use page_counter_wasm::xlsx_strings::{sample_column_widths, SharedStringIndex, COLUMN_WIDTH_SAMPLE_ROWS};
+use std::io::{Cursor, Write};
+use std::time::Duration;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const SHEET_PART: &str = "xl/worksheets/sheet1.xml";
+
+fn bench_rows() -> usize {
+    std::env::var("XLSX_BENCH_ROWS").ok().and_then(|v| v.parse().ok()).unwrap_or(500_000)
+}
+
+/// Builds a one-sheet workbook of `rows` rows: a unique shared string, a
+/// number, and one of a few repeated shared strings per row.
+fn workbook(rows: usize) -> Vec<u8> {
+    const REPEATED: [&str; 4] = ["open", "closed", "pending review", "東京都の支店"];
+    let mut strings = String::new();
+    for item in REPEATED {
+        strings.push_str(&format!("<si><t>{}</t></si>", item));
+    }
+    let mut sheet = String::new();
+    for row in 1..=rows {
+        strings.push_str(&format!("<si><t>Customer &amp; account {}</t></si>", row));
+        sheet.push_str(&format!(
+            concat!(
+                r#"<row r="{0}"><c r="A{0}" t="s"><v>{1}</v></c><c r="B{0}"><v>{2}</v></c>"#,
+                r#"<c r="C{0}" t="s"><v>{3}</v></c></row>"#
+            ),
+            row,
+            REPEATED.len() + row - 1,
+            row * 37,
+            row % REPEATED.len()
+        ));
+    }
+    let main = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+    let relationships = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+    let package_relationships = "http://schemas.openxmlformats.org/package/2006/relationships";
+    let parts = [
+        (
+            "[Content_Types].xml",
+            concat!(
+                r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+                r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+                r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+                r#"<Override PartName="/xl/workbook.xml" "#,
+                r#"ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+                "</Types>"
+            )
+            .to_string(),
+        ),
+        (
+            "_rels/.rels",
+            format!(
+                concat!(
+                    r#"<Relationships xmlns="{}">"#,
+                    r#"<Relationship Id="rId1" Type="{}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#
+                ),
+                package_relationships, relationships
+            ),
+        ),
+        (
+            "xl/workbook.xml",
+            format!(
+                concat!(
+                    r#"<workbook xmlns="{}" xmlns:r="{}">"#,
+                    r#"<sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#
+                ),
+                main, relationships
+            ),
+        ),
+        (
+            "xl/_rels/workbook.xml.rels",
+            format!(
+                concat!(
+                    r#"<Relationships xmlns="{0}">"#,
+                    r#"<Relationship Id="rId1" Type="{1}/worksheet" Target="worksheets/sheet1.xml"/>"#,
+                    r#"<Relationship Id="rId2" Type="{1}/sharedStrings" Target="sharedStrings.xml"/>"#,
+                    "</Relationships>"
+                ),
+                package_relationships, relationships
+            ),
+        ),
+        ("xl/sharedStrings.xml", format!(r#"<sst xmlns="{}">{}</sst>"#, main, strings)),
+        (SHEET_PART, format!(r#"<worksheet xmlns="{}"><sheetData>{}</sheetData></worksheet>"#, main, sheet)),
+    ];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, xml) in parts {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(xml.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// Column widths through calamine, which resolves every shared string up front.
+fn materialized_widths(bytes: &[u8]) -> Vec<usize> {
+    let mut xlsx: Xlsx<_> = Xlsx::new(Cursor::new(bytes)).unwrap();
+    let range = xlsx.worksheet_range("Sheet1").unwrap();
+    let first_column = range.start().map_or(0, |(_, column)| column as usize);
+    let mut widths = Vec::new();
+    let populated = range.rows().filter(|row| row.iter().any(|cell| !matches!(cell, Data::Empty)));
+    for row in populated.take(COLUMN_WIDTH_SAMPLE_ROWS) {
+        for (i, cell) in row.iter().enumerate() {
+            let width: usize = match cell {
+                Data::Empty => continue,
+                cell => cell.to_string().chars().map(char_width).sum(),
+            };
+            let column = first_column + i;
+            if widths.len() <= column {
+                widths.resize(column + 1, 0);
+            }
+            widths[column] = usize::max(widths[column], width);
+        }
+    }
+    widths
+}
+
+/// Column widths through the lazy shared strings index.
+fn lazy_widths(bytes: &[u8]) -> Vec<usize> {
+    let mut archive = open_zip_archive(bytes, "XLSX").unwrap();
+    let strings = SharedStringIndex::read(&mut archive);
+    sample_column_widths(&mut archive, SHEET_PART, strings.as_ref(), COLUMN_WIDTH_SAMPLE_ROWS)
+}
+
+fn check_identical(bytes: &[u8]) {
+    let widths = lazy_widths(bytes);
+    assert_eq!(materialized_widths(bytes), widths, "lazy column widths differ from calamine's");
+    println!("column widths identical: {:?}", widths);
+}
+
+fn bench_xlsx(c: &mut Criterion) {
+    let bytes = workbook(bench_rows());
+    check_identical(&bytes);
+    let mut group = c.benchmark_group("xlsx");
+    group.sample_size(10).measurement_time(Duration::from_secs(10));
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("materialized_widths", |b| b.iter(|| materialized_widths(black_box(&bytes))));
+    group.bench_function("lazy_widths", |b| b.iter(|| lazy_widths(black_box(&bytes))));
+    group.bench_function("index_only", |b| {
+        b.iter(|| SharedStringIndex::read(&mut open_zip_archive(black_box(&bytes), "XLSX").unwrap()))
+    });
+    group.bench_function("estimate_document", |b| {
+        b.iter(|| estimate_bytes(black_box(&bytes), Some("bench.xlsx"), &EstimateOptions::default()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_xlsx);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,40 @@
+//! Arbitrary bytes through the top-level router under every filename hint.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use page_counter_wasm::assembly::estimate_bytes;
+use page_counter_wasm::schema::EstimateOptions;
+use page_counter_wasm::validation::validate_bytes;
+
+/// Filename hints covering each routed format, plus no hint (content sniffing).
+const FILENAMES: &[Option<&str>] = &[
+    None,
+    Some("fuzz.pdf"),
+    Some("fuzz.docx"),
+    Some("fuzz.pptx"),
+    Some("fuzz.xlsx"),
+    Some("fuzz.txt"),
+    Some("fuzz.md"),
+    Some("fuzz.rst"),
+    Some("fuzz.adoc"),
+    Some("fuzz.tex"),
+    Some("fuzz.rs"),
+    Some("fuzz.eml"),
+    Some("fuzz.zip"),
+    Some("fuzz.tar"),
+    Some("fuzz.gz"),
+];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((selector, data)) = data.split_first() else { return };
+    let filename = FILENAMES[*selector as usize % FILENAMES.len()];
+    let options = EstimateOptions {
+        chars_per_line: (selector & 0x10 != 0).then_some(60),
+        return_boundaries: Some(true),
+        archive_recurse_nested: Some(true),
+        ..Default::default()
+    };
+    let _ = estimate_bytes(data, filename, &options);
+    let _ = validate_bytes(data, filename);
+});
@@ -0,0 +1,18 @@
+//! Arbitrary bytes through the DOCX, PPTX, XLSX, XPS, and iWork estimators.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use page_counter_wasm::estimators::{
+    estimate_docx_pages, estimate_iwork_pages, estimate_pptx_pages, estimate_xlsx_pages, estimate_xps_pages,
+};
+use page_counter_wasm::schema::EstimateOptions;
+
+fuzz_target!(|data: &[u8]| {
+    let options = EstimateOptions::default();
+    let _ = estimate_docx_pages(data, &options);
+    let _ = estimate_pptx_pages(data, &options);
+    let _ = estimate_xlsx_pages(data, &options);
+    let _ = estimate_xps_pages(data, &options);
+    let _ = estimate_iwork_pages(data, &options);
+});
@@ -0,0 +1,61 @@
+//! Well-formed ZIP packages with arbitrary part contents.
+//!
+//! Raw bytes almost never get past the ZIP central directory, so this target
+//! builds a valid package around fuzzed XML to reach the part parsers.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use page_counter_wasm::estimators::{estimate_docx_pages, estimate_pptx_pages, estimate_xlsx_pages};
+use page_counter_wasm::schema::EstimateOptions;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Part names the estimators read.
+const PART_NAMES: &[&str] = &[
+    "[Content_Types].xml",
+    "docProps/app.xml",
+    "docProps/core.xml",
+    "word/document.xml",
+    "ppt/presentation.xml",
+    "ppt/slides/slide1.xml",
+    "ppt/slides/slide2.xml",
+    "ppt/_rels/presentation.xml.rels",
+    "xl/workbook.xml",
+    "xl/_rels/workbook.xml.rels",
+    "xl/worksheets/sheet1.xml",
+    "xl/sharedStrings.xml",
+];
+
+#[derive(Arbitrary, Debug)]
+struct Part {
+    name: u8,
+    deflate: bool,
+    content: String,
+}
+
+fuzz_target!(|parts: Vec<Part>| {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for part in parts.iter().take(PART_NAMES.len()) {
+        let method = if part.deflate { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+        let name = PART_NAMES[part.name as usize % PART_NAMES.len()];
+        // duplicate names are rejected by the writer; skip them
+        if writer.start_file(name, SimpleFileOptions::default().compression_method(method)).is_err() {
+            continue;
+        }
+        let _ = writer.write_all(part.content.as_bytes());
+    }
+    let Ok(cursor) = writer.finish() else { return };
+    let bytes = cursor.into_inner();
+
+    let options = EstimateOptions::default();
+    let _ = estimate_docx_pages(&bytes, &options);
+    let _ = estimate_pptx_pages(&bytes, &options);
+    let _ = estimate_xlsx_pages(&bytes, &options);
+    // truncated packages take the entry recovery path
+    let half = &bytes[..bytes.len() / 2];
+    let _ = estimate_docx_pages(half, &options);
+    let _ = estimate_pptx_pages(half, &options);
+});
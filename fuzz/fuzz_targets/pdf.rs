@@ -0,0 +1,25 @@
+//! Arbitrary bytes through every PDF entry point.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use page_counter_wasm::estimators::estimate_pdf_pages;
+use page_counter_wasm::pdf::{PdfCounter, PdfStrategy};
+use page_counter_wasm::pdf_color::analyze_page_colors;
+use page_counter_wasm::pdf_utils::ObjectIndex;
+use page_counter_wasm::schema::EstimateOptions;
+use page_counter_wasm::validation::validate_bytes;
+
+fuzz_target!(|data: &[u8]| {
+    let options = EstimateOptions {
+        analyze_color: Some(true),
+        extract_pdf_text_metrics: Some(true),
+        ..Default::default()
+    };
+    let _ = estimate_pdf_pages(data, &options);
+    let _ = ObjectIndex::build(data).page_refs();
+    let _ = PdfCounter::new(vec![PdfStrategy::PageTree, PdfStrategy::CountScan, PdfStrategy::TypeScan]).count(data);
+    let _ = PdfCounter::default().page_sizes(data);
+    let _ = analyze_page_colors(data);
+    let _ = validate_bytes(data, Some("fuzz.pdf"));
+});
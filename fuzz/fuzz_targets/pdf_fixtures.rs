@@ -0,0 +1,90 @@
+//! Structure-aware mutations of the generated benchmark PDFs.
+//!
+//! Starting from a valid file keeps the object index, page tree, and
+//! cross-reference paths reachable, which raw bytes rarely get to.
+
+#![no_main]
+
+#[allow(dead_code)]
+#[path = "../../benches/fixtures/mod.rs"]
+mod fixtures;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use page_counter_wasm::estimators::estimate_pdf_pages;
+use page_counter_wasm::pdf_utils::ObjectIndex;
+use page_counter_wasm::schema::EstimateOptions;
+use page_counter_wasm::validation::validate_bytes;
+
+/// Tokens that steer mutations into the parts of the file the parsers look at.
+const TOKENS: &[&[u8]] = &[
+    b"<<", b">>", b"[", b"]", b" obj", b"endobj", b"stream\n", b"endstream", b" 0 R",
+    b"/Type /Page", b"/Type /Pages", b"/Kids [", b"/Parent", b"/Resources", b"/XObject",
+    b"/Subtype /Form", b"/ColorSpace", b"/Filter /FlateDecode", b"/Root", b"startxref",
+    b"xref", b"%%EOF", b"/Encrypt",
+];
+
+#[derive(Arbitrary, Debug)]
+enum Fixture {
+    Classic(u8),
+    XrefStream(u8),
+    Bookmarks(u8, u8),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Edit {
+    /// Overwrites the byte at a position.
+    Set(u32, u8),
+    /// Inserts one of `TOKENS` at a position.
+    Insert(u32, u8),
+    /// Removes a range starting at a position.
+    Remove(u32, u16),
+    /// Cuts the file short.
+    Truncate(u32),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    fixture: Fixture,
+    edits: Vec<Edit>,
+    analyze_color: bool,
+}
+
+fuzz_target!(|input: Input| {
+    // keep the page count small so each run stays fast
+    let mut bytes = match input.fixture {
+        Fixture::Classic(pages) => fixtures::small_classic(1 + pages as usize % 8),
+        Fixture::XrefStream(pages) => fixtures::xref_stream(1 + pages as usize % 8),
+        Fixture::Bookmarks(pages, bookmarks) => {
+            fixtures::bookmark_heavy(1 + pages as usize % 8, bookmarks as usize % 32)
+        }
+    };
+
+    for edit in input.edits.iter().take(16) {
+        let at = |pos: u32, len: usize| pos as usize % (len + 1);
+        match *edit {
+            Edit::Set(pos, byte) => {
+                if !bytes.is_empty() {
+                    let i = pos as usize % bytes.len();
+                    bytes[i] = byte;
+                }
+            }
+            Edit::Insert(pos, token) => {
+                let i = at(pos, bytes.len());
+                let token = TOKENS[token as usize % TOKENS.len()];
+                bytes.splice(i..i, token.iter().copied());
+            }
+            Edit::Remove(pos, len) => {
+                let i = at(pos, bytes.len());
+                let end = (i + len as usize).min(bytes.len());
+                bytes.drain(i..end);
+            }
+            Edit::Truncate(pos) => bytes.truncate(at(pos, bytes.len())),
+        }
+    }
+
+    let options = EstimateOptions { analyze_color: Some(input.analyze_color), ..Default::default() };
+    let _ = estimate_pdf_pages(&bytes, &options);
+    let _ = ObjectIndex::build(&bytes).page_refs();
+    let _ = validate_bytes(&bytes, Some("fuzz.pdf"));
+});
@@ -0,0 +1,13 @@
+/**
+ * Web Worker shim for handle_worker_message
+ * Start it as a module worker; it answers each request message with one response message
+ * (see src/worker.rs for the protocol)
+ */
+import init, { handle_worker_message } from '../pkg/page_counter_wasm.js';
+
+const ready = init();
+
+self.onmessage = async (event) => {
+    await ready;
+    self.postMessage(await handle_worker_message(event.data));
+};
@@ -0,0 +1,30 @@
+/**
+ * Main-thread helper for js_bridges/estimate_worker.js
+ * Sends requests with their ArrayBuffer transferred, so the document isn't copied into the worker
+ */
+
+let nextId = 0;
+
+/**
+ * Send one request to the estimation worker
+ * @param {Worker} worker - A module worker running estimate_worker.js
+ * @param {"estimate"|"detect"|"validate"} op - The operation
+ * @param {ArrayBuffer} bytes - The document; detached (unusable here) once sent
+ * @param {string} [filename] - Optional filename, used as a type hint
+ * @param {Object|string} [options] - Optional estimation options
+ * @returns {Promise<{id: number, ok: boolean, result?: Object, error?: Object}>} The worker's response
+ */
+export function requestEstimate(worker, op, bytes, filename, options) {
+    const id = ++nextId;
+    return new Promise((resolve) => {
+        const onMessage = (event) => {
+            if (event.data.id !== id) {
+                return;
+            }
+            worker.removeEventListener('message', onMessage);
+            resolve(event.data);
+        };
+        worker.addEventListener('message', onMessage);
+        worker.postMessage({id, op, bytes, filename, options}, [bytes]);
+    });
+}
@@ -0,0 +1,22 @@
+/**
+ * Iteration helpers for PdfPageSizeIterator
+ * wasm-bindgen classes can't define [Symbol.iterator], so for...of goes through here
+ */
+
+/**
+ * Yield the page sizes of a PDF lazily
+ * @param {Function} PdfPageSizeIterator - The class exported by the WASM module
+ * @param {Uint8Array} bytes - The PDF file bytes
+ * @returns {Generator<{index: number, width_mm: number, height_mm: number}>} One entry per page
+ */
+export function* pdfPageSizes(PdfPageSizeIterator, bytes) {
+    const iterator = new PdfPageSizeIterator(bytes);
+    try {
+        for (let step = iterator.next(); !step.done; step = iterator.next()) {
+            yield step.value;
+        }
+    } finally {
+        // release the WASM memory even when the loop exits early
+        iterator.free();
+    }
+}
@@ -9,6 +9,11 @@
 //! - `estimate_document_base64`: Accepts base64-encoded document data
 //! - `estimate_document`: Accepts raw byte arrays
 //!
+//! `estimate_archive` additionally estimates every document inside a ZIP or TAR archive
+//! or an email message (`.eml`), and gzip-compressed inputs are transparently decompressed.
+//! `merge_results` combines several results into one, e.g. for a batch printed as one job.
+//! `estimate_document_cached` serves repeated estimates of the same input from a small cache.
+//!
 //! Both functions automatically detect the document type (PDF, XLSX, DOCX, PPTX, TXT, Markdown) and
 //! apply the appropriate estimation algorithm.
 //!
@@ -20,15 +25,68 @@
 //! - **PPTX**: Counts slides in PowerPoint presentations
 //! - **TXT**: Estimates pages based on character count and formatting
 //! - **Markdown**: Estimates pages considering markdown formatting
+//! - **reStructuredText / AsciiDoc**: Estimates pages from the rendered lines
+//! - **Source code**: Line-based estimate with monospace defaults
+//! - **LaTeX**: Rough estimate from the prose left after stripping commands
+//!
+//! `validate_document` performs only cheap structural checks and reports whether a
+//! file is intact and encrypted, without estimating it.
+//!
+//! ## Schema Version
+//!
+//! Every result, report, and error object starts with `schema_version`
+//! (`SCHEMA_VERSION`), which is bumped whenever a serialized field changes
+//! incompatibly; adding a field isn't such a change. `parse_result` reads
+//! stored results of the current and earlier versions.
+//!
+//! ## Printing
+//!
+//! When printing options (`duplex`, `pages_per_sheet`, `price_per_page`) are supplied,
+//! the result also carries a `print_summary` with the sheet count and estimated cost.
 
 use crate::estimators::{
-    count_pdf_pages_js, estimate_markdown_pages, estimate_pdf_pages, estimate_text_pages,
-    estimate_xlsx_pages, estimate_docx_pages, estimate_pptx_pages,
+    count_pdf_pages_js, estimate_asciidoc_pages, estimate_code_pages, estimate_data_pages, estimate_docbook_pages,
+    estimate_latex_pages, estimate_markdown_pages, estimate_mht_pages, estimate_pdf_pages, estimate_rst_pages,
+    estimate_text_pages,
+    estimate_xlsx_pages, estimate_docx_pages, estimate_pptx_pages, estimate_xps_pages, estimate_postscript_pages,
+    estimate_cbz_pages, estimate_iwork_pages, estimate_notebook_pages, estimate_records_pages, CODE_CHARS_PER_LINE,
+    CODE_LINES_PER_PAGE, DEFAULT_CHARS_PER_PAGE, DEFAULT_CONTACTS_PER_PAGE, DEFAULT_DISPLAY_EQUATION_LINES,
+    DEFAULT_EVENTS_PER_PAGE, DEFAULT_IMAGE_HEIGHT_MM, DEFAULT_INLINE_EQUATION_LINES, DEFAULT_OBJECT_PAGE_EQUIVALENT,
+    MARKUP_CHARS_PER_LINE, MAX_EQUATION_LINES, MAX_OBJECT_PAGE_EQUIVALENT,
+};
+use crate::cache::{with_estimate_cache, CacheKey};
+use crate::deadline::{with_deadline, DEADLINE_CONFIDENCE, DEADLINE_WARNING};
+use crate::diagnostics::{collect_diagnostics, media_note};
+use crate::fallback::{describe_js_error, run_fallback_estimator};
+use crate::file_utils::{
+    decompress_gzip, detect_format, detect_type, is_system_entry, looks_like_google_stub, looks_like_locator,
+    mm_from_pt, note_zip_entry, open_zip_archive, paper_for_locale, parse_paper, read_tar_entries,
+    strip_gzip_extension, Detection,
+};
+use crate::formats::FORMATS;
+use crate::language::DEFAULT_LANGUAGE_MIN_WORDS;
+use crate::logging::{log, LogLevel};
+use crate::mime::{extension_for_content_type, html_to_text, parse_mime, MimePart};
+use crate::notes::{apply_verbosity, limit_notes, verbosity};
+use crate::pdf_portfolio::{read_embedded_file, scan_portfolio};
+use crate::pdf_utils::{ObjectIndex, DEFAULT_SCAN_BUDGET_FACTOR};
+use crate::printing::{apply_page_split, apply_print_summary, DEFAULT_OVERSIZED_PAGE_RATIO};
+use crate::reading::{reading_minutes, ReadingMedia, DEFAULT_READING_WPM};
+use crate::stats::{note_cache_lookup, EstimateScope};
+use crate::text_layout::{DEFAULT_LINES_PER_PAGE, DEFAULT_TAB_WIDTH};
+use crate::validation::validate_bytes;
+use crate::verification::apply_expected_pages;
+use crate::schema::{
+    ArchiveEntryResult, ArchiveEstimate, EstimateOptions, EstimateResult, EstimatorError, PageRounding, PageSizePt,
+    Unit, SCHEMA_VERSION,
 };
-use crate::file_utils::{detect_type, mm_from_pt};
-use crate::schema::{EstimateOptions, EstimateResult, PageSizeMm};
-use base64::Engine;
-use serde_json::json;
+use std::io::Read;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::{alphabet, Engine};
+use schemars::schema_for;
+use serde::Serialize;
+use serde_json::{json, Value};
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
@@ -41,7 +99,8 @@ use wasm_bindgen::JsValue;
 /// # Parameters
 ///
 /// * `base64_bytes` - A base64-encoded string representing the document's binary data.
-///                    Must be valid base64 encoding using the standard alphabet.
+///                    Either the standard or the URL-safe (base64url) alphabet,
+///                    with or without `=` padding.
 ///
 /// * `filename` - Optional filename including extension (e.g., "document.pdf").
 ///                The extension is used as a hint for file type detection if provided.
@@ -75,7 +134,7 @@ use wasm_bindgen::JsValue;
 /// # Errors
 ///
 /// Returns an error object if:
-/// - The base64 string cannot be decoded
+/// - The base64 string cannot be decoded (`invalid_input`)
 /// - The document format is unsupported
 /// - The document is corrupted or invalid
 #[wasm_bindgen]
@@ -85,14 +144,28 @@ pub fn estimate_document_base64(
     options_json: Option<String>,
 ) -> JsValue {
     // convenience wrapper to allow passing base64 bytes from JS (where typed arrays may not be handy)
-    match base64::engine::general_purpose::STANDARD.decode(base64_bytes) {
+    match decode_base64(base64_bytes) {
         Ok(bytes) => estimate_document(&bytes, filename, options_json),
-        Err(e) => JsValue::from_str(
-            &json!({"error": format!("base64 decode failed: {:?}", e)}).to_string(),
-        ),
+        Err(err) => JsValue::from_str(&with_schema_version(
+            &json!({"error": err.to_string(), "error_code": error_code(&err), "detected": "unknown"}).to_string(),
+        )),
     }
 }
 
+/// Decodes base64 in the standard or the URL-safe alphabet, padded or not.
+///
+/// # Errors
+///
+/// `InvalidInput` naming what couldn't be decoded.
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, EstimatorError> {
+    const CONFIG: GeneralPurposeConfig =
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    const STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, CONFIG);
+    const URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, CONFIG);
+    let engine = if text.contains(['-', '_']) { &URL_SAFE } else { &STANDARD };
+    engine.decode(text).map_err(|err| EstimatorError::InvalidInput(format!("base64 decode failed: {}", err)))
+}
+
 /// Estimates the number of pages in a document from raw byte data.
 ///
 /// This is the core estimation function that processes document bytes directly.
@@ -131,6 +204,8 @@ pub fn estimate_document_base64(
 ///
 /// Returns a JSON object containing:
 /// - `error` (string): Human-readable error message
+/// - `error_code` (string): Stable machine-readable code (e.g. `"zip_error"`,
+///   `"encrypted_document"`, `"invalid_options"`; see `error_code`)
 /// - `detected` (string): The detected format (for debugging)
 ///
 /// # Type Detection
@@ -183,53 +258,1298 @@ pub fn estimate_document(
     filename: Option<String>,
     options_json: Option<String>,
 ) -> JsValue {
-    // parse options
-    let options: EstimateOptions = match options_json {
-        Some(s) => serde_json::from_str(&s).unwrap_or_default(),
-        None => EstimateOptions::default(),
+    let (options, warnings) = parse_options_checked(options_json.as_deref());
+    match estimate_to_json(bytes, filename.as_deref(), &options, &warnings) {
+        Ok(s) | Err(s) => JsValue::from_str(&s),
+    }
+}
+
+/// Estimates a document like `estimate_document` and returns a one-line summary for logs.
+///
+/// The line is built straight from the result, without serializing it to JSON,
+/// so it is cheap enough to call for every processed file.
+///
+/// # Parameters
+///
+/// Same as `estimate_document`; `validate_only` is ignored.
+///
+/// # Returns
+///
+/// The line from `EstimateResult::summary`, e.g. `pdf pages=42 size=210x297mm warnings=0`
+/// (option warnings are counted), or `<format> error=<error_code>` when the
+/// estimate fails. The format is stable and only ever extended at the end.
+#[wasm_bindgen]
+pub fn estimate_document_summary(
+    bytes: &[u8],
+    filename: Option<String>,
+    options_json: Option<String>,
+) -> String {
+    let (options, warnings) = parse_options_checked(options_json.as_deref());
+    let (detected, result) = estimate_bytes(bytes, filename.as_deref(), &options);
+    match result {
+        Ok(mut est) => {
+            est.notes.splice(0..0, warnings);
+            est.summary(&detected)
+        }
+        Err(err) => format!("{} error={}", detected, error_code(&err)),
+    }
+}
+
+/// Estimates a document like `estimate_document`, reusing the result of an
+/// earlier call with the same bytes, filename, and options.
+///
+/// Results are kept in a small least-recently-used cache inside the module
+/// (see `configure_estimate_cache` and `clear_estimate_cache`). Errors, and
+/// results cut short by `deadline_ms`, are not cached.
+///
+/// # Parameters
+///
+/// Same as `estimate_document`.
+///
+/// # Returns
+///
+/// The same JSON as `estimate_document`; results served from the cache
+/// additionally carry `"cached": true`.
+#[wasm_bindgen]
+pub fn estimate_document_cached(
+    bytes: &[u8],
+    filename: Option<String>,
+    options_json: Option<String>,
+) -> JsValue {
+    let (options, warnings) = parse_options_checked(options_json.as_deref());
+    // re-serializing gives the same key for equivalent option JSON (spacing, key order, defaults);
+    // the warnings are part of the result, so they are part of the key too
+    let canonical = serde_json::to_string(&(&options, &warnings)).unwrap_or_default();
+    let key = CacheKey::new(bytes, filename.as_deref(), canonical);
+    let cached = with_estimate_cache(|cache| cache.get(&key));
+    note_cache_lookup(cached.is_some());
+    if let Some(json) = cached {
+        return JsValue::from_str(&mark_cached(&json));
+    }
+    match estimate_to_json(bytes, filename.as_deref(), &options, &warnings) {
+        // a result cut short by its deadline may finish next time
+        Ok(json) if json.contains(DEADLINE_WARNING) => JsValue::from_str(&json),
+        Ok(json) => {
+            with_estimate_cache(|cache| cache.insert(key, json.clone()));
+            JsValue::from_str(&json)
+        }
+        Err(json) => JsValue::from_str(&json),
+    }
+}
+
+/// Adds `"cached": true` to a JSON object.
+fn mark_cached(json: &str) -> String {
+    prepend_field(json, "\"cached\":true")
+}
+
+/// Adds `"schema_version"` (see `SCHEMA_VERSION`) as the first key of a JSON object.
+pub fn with_schema_version(json: &str) -> String {
+    prepend_field(json, &format!("\"schema_version\":{}", SCHEMA_VERSION))
+}
+
+/// Serializes a result or report with `with_schema_version`.
+///
+/// # Returns
+///
+/// `Ok` with the JSON, or `Err` with the (versioned) error JSON when serialization fails.
+pub fn to_versioned_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value)
+        .map(|json| with_schema_version(&json))
+        .map_err(|_| with_schema_version(&json!({"error":"serialization failed"}).to_string()))
+}
+
+/// Inserts a `"key":value` field at the start of a JSON object.
+fn prepend_field(json: &str, field: &str) -> String {
+    match json.strip_prefix('{') {
+        Some(rest) if rest.trim_start() != "}" => format!("{{{},{}", field, rest),
+        Some(_) => format!("{{{}}}", field),
+        None => json.to_string(),
+    }
+}
+
+/// Runs `estimate_document` for parsed options.
+///
+/// The options are resolved for the detected format and reported as
+/// `effective_options`, and the warnings from parsing them lead the notes.
+///
+/// # Returns
+///
+/// `Ok` with the result (or validation report) JSON, or `Err` with the error JSON.
+pub fn estimate_to_json(
+    bytes: &[u8],
+    filename: Option<&str>,
+    options: &EstimateOptions,
+    warnings: &[String],
+) -> Result<String, String> {
+    if options.validate_only.unwrap_or(false) {
+        return to_versioned_json(&validate_bytes(bytes, filename));
+    }
+    let (detected, result) = estimate_bytes(bytes, filename, options);
+
+    match result {
+        Ok(mut est) => {
+            est.effective_options = Some(resolve_options(options, &detected));
+            est.notes.splice(0..0, warnings.iter().cloned());
+            to_versioned_json(&est)
+        }
+        Err(err) => {
+            let mut body = json!({"error": err.to_string(), "error_code": error_code(&err), "detected": detected});
+            if let EstimatorError::ParseBudgetExceeded { budget, pages_found } = err {
+                body["diagnostics"] = json!({"scan_budget_bytes": budget, "pages_found": pages_found});
+            }
+            Err(with_schema_version(&body.to_string()))
+        }
+    }
+}
+
+/// Checks that a document is what it claims to be and structurally intact,
+/// without estimating its page count.
+///
+/// Only structural checks are performed (container headers, PDF trailer and
+/// cross-reference offset, presence of the main OOXML part), so this is much
+/// cheaper than `estimate_document` on large files. The same report is returned
+/// by `estimate_document` when the `validate_only` option is set.
+///
+/// # Parameters
+///
+/// * `bytes` - The raw document bytes
+///
+/// * `filename` - Optional filename including extension, used as a type hint
+///
+/// # Returns
+///
+/// A `JsValue` containing a JSON string with:
+/// - `format`: Detected document format
+/// - `valid`: Whether the structure is intact
+/// - `encrypted`: Whether the document is password-protected
+/// - `issues`: Array of structural problems found
+#[wasm_bindgen]
+pub fn validate_document(bytes: &[u8], filename: Option<String>) -> JsValue {
+    match to_versioned_json(&validate_bytes(bytes, filename.as_deref())) {
+        Ok(s) | Err(s) => JsValue::from_str(&s),
+    }
+}
+
+/// Lists the document formats the estimator supports.
+///
+/// # Returns
+///
+/// A `JsValue` containing a JSON array of `{id, name, extensions, mime_types}`
+/// objects, where `extensions` are lowercase and without the leading dot. File
+/// pickers can build their `accept` list from it.
+#[wasm_bindgen]
+pub fn supported_formats() -> JsValue {
+    match serde_json::to_string(FORMATS) {
+        Ok(s) => JsValue::from_str(&s),
+        Err(_) => JsValue::from_str(&json!({"error":"serialization failed"}).to_string()),
+    }
+}
+
+/// Describes the estimation options as a JSON Schema (draft 2020-12).
+///
+/// # Returns
+///
+/// A `JsValue` containing the schema as a JSON string. It is derived from
+/// `EstimateOptions`, so every option is listed with its description, type,
+/// default, allowed values, and the numeric bounds enforced before estimation.
+/// Settings forms can be generated from it and inputs validated before calling.
+#[wasm_bindgen]
+pub fn options_schema() -> JsValue {
+    match serde_json::to_string(&schema_for!(EstimateOptions)) {
+        Ok(s) => JsValue::from_str(&s),
+        Err(_) => JsValue::from_str(&json!({"error":"serialization failed"}).to_string()),
+    }
+}
+
+/// Parses the options JSON, falling back to defaults when absent or invalid.
+pub fn parse_options(options_json: Option<String>) -> EstimateOptions {
+    parse_options_checked(options_json.as_deref()).0
+}
+
+/// Parses the options JSON like `parse_options`, explaining what was ignored.
+///
+/// # Returns
+///
+/// The options, and one warning per unknown key (a misspelled option would
+/// otherwise silently have no effect), or a single warning when the JSON
+/// couldn't be parsed and the defaults were used instead.
+pub fn parse_options_checked(options_json: Option<&str>) -> (EstimateOptions, Vec<String>) {
+    let Some(json) = options_json else { return (EstimateOptions::default(), Vec::new()) };
+    let options = match serde_json::from_str(json) {
+        Ok(options) => options,
+        Err(e) => {
+            let warning = format!("⚠ Options could not be parsed ({}); defaults used", e);
+            return (EstimateOptions::default(), vec![warning]);
+        }
+    };
+    // every field serializes (unset ones as null), so the defaults list the known keys
+    let known = serde_json::to_value(EstimateOptions::default()).unwrap_or_default();
+    let warnings = match serde_json::from_str::<serde_json::Value>(json) {
+        Ok(serde_json::Value::Object(given)) => given
+            .keys()
+            .filter(|key| known.get(key.as_str()).is_none())
+            .map(|key| format!("⚠ Unknown option '{}' ignored", key))
+            .collect(),
+        _ => Vec::new(),
     };
+    (options, warnings)
+}
 
-    let detected = detect_type(filename.as_deref(), bytes);
+/// Fills in the defaults the estimator for `detected` applies to unset options.
+///
+/// This is what `effective_options` reports. Estimators resolve their defaults
+/// themselves, so the result only describes the estimate; it isn't passed back in.
+///
+/// # Notes
+///
+/// - The paper is resolved (custom size, paper name, then locale) into
+///   `custom_paper_mm`, and its name into `default_paper` unless a custom size was given
+/// - Text layout options are filled in only for the text formats that use them:
+///   `chars_per_page` for character-based text, the line metrics otherwise,
+///   and both for notebooks (Markdown cells, then code and outputs)
+/// - Options whose default depends on the document (LaTeX `chars_per_page`,
+///   XLSX `rows_per_page` and margins, which come from the sheets) stay unset
+pub fn resolve_options(options: &EstimateOptions, detected: &str) -> EstimateOptions {
+    let mut resolved = options.clone();
+    let paper = options.paper();
+    // a custom size overrides the paper name, which is then left as given
+    if options.custom_paper_mm.is_none() {
+        resolved.default_paper = Some(match (&options.default_paper, &options.locale) {
+            (Some(name), _) if name.eq_ignore_ascii_case("letter") => "Letter".to_string(),
+            (Some(_), _) => "A4".to_string(),
+            (None, Some(locale)) => paper_for_locale(locale).name.to_string(),
+            (None, None) => "A4".to_string(),
+        });
+    }
+    resolved.custom_paper_mm = Some(paper);
+
+    let line_based = match detected {
+        "txt" | "markdown" | "mht" => options.chars_per_line.is_some(),
+        "code" | "data" | "records" | "rst" | "asciidoc" => true,
+        _ => false,
+    };
+    if line_based {
+        let (chars_per_line, lines_per_page) = match detected {
+            "code" | "data" | "records" => (CODE_CHARS_PER_LINE, CODE_LINES_PER_PAGE),
+            "rst" | "asciidoc" => (MARKUP_CHARS_PER_LINE, DEFAULT_LINES_PER_PAGE),
+            _ => (0, DEFAULT_LINES_PER_PAGE),
+        };
+        resolved.chars_per_line = options.chars_per_line.or(Some(chars_per_line));
+        resolved.lines_per_page = options.lines_per_page.or(Some(lines_per_page));
+        resolved.tab_width = options.tab_width.or(Some(DEFAULT_TAB_WIDTH));
+        resolved.line_spacing = options.line_spacing.or(Some(1.0));
+        resolved.paragraph_spacing_lines = options.paragraph_spacing_lines.or(Some(0.0));
+    } else if matches!(detected, "txt" | "markdown" | "mht") {
+        resolved.chars_per_page = options.chars_per_page.or(Some(DEFAULT_CHARS_PER_PAGE));
+    }
+    if detected == "code" {
+        resolved.skip_blank_lines = options.skip_blank_lines.or(Some(false));
+    }
+    if detected == "data" {
+        resolved.pretty_print_minified = options.pretty_print_minified.or(Some(false));
+    }
+    if detected == "notebook" {
+        resolved.chars_per_page = options.chars_per_page.or(Some(DEFAULT_CHARS_PER_PAGE));
+        resolved.chars_per_line = options.chars_per_line.or(Some(CODE_CHARS_PER_LINE));
+        resolved.lines_per_page = options.lines_per_page.or(Some(CODE_LINES_PER_PAGE));
+        resolved.tab_width = options.tab_width.or(Some(DEFAULT_TAB_WIDTH));
+        resolved.line_spacing = options.line_spacing.or(Some(1.0));
+        resolved.paragraph_spacing_lines = options.paragraph_spacing_lines.or(Some(0.0));
+        resolved.image_height_mm = options.image_height_mm.or(Some(DEFAULT_IMAGE_HEIGHT_MM));
+    }
+    if detected == "records" {
+        resolved.contacts_per_page = options.contacts_per_page.or(Some(DEFAULT_CONTACTS_PER_PAGE));
+        resolved.events_per_page = options.events_per_page.or(Some(DEFAULT_EVENTS_PER_PAGE));
+    }
+    if matches!(
+        detected,
+        "txt" | "markdown" | "mht" | "code" | "data" | "notebook" | "records" | "rst" | "asciidoc" | "xlsx"
+            | "docx"
+    ) {
+        resolved.rounding = Some(options.rounding().as_str().to_string());
+    }
+
+    resolved.duplex = options.duplex.or(Some(false));
+    resolved.pages_per_sheet = options.pages_per_sheet.or(Some(1));
+    resolved.analyze_color = options.analyze_color.or(Some(false));
+    resolved.exclude_blank_pages = options.exclude_blank_pages.or(Some(false));
+    resolved.estimate_attachments = options.estimate_attachments.or(Some(false));
+    if detected == "xlsx" {
+        resolved.xlsx_min_pages_per_visible_sheet = options.xlsx_min_pages_per_visible_sheet.or(Some(0));
+    }
+    if detected == "docx" {
+        resolved.docx_object_page_equivalent =
+            options.docx_object_page_equivalent.or(Some(DEFAULT_OBJECT_PAGE_EQUIVALENT));
+        resolved.docx_display_equation_lines =
+            options.docx_display_equation_lines.or(Some(DEFAULT_DISPLAY_EQUATION_LINES));
+        resolved.docx_inline_equation_lines =
+            options.docx_inline_equation_lines.or(Some(DEFAULT_INLINE_EQUATION_LINES));
+    }
+    resolved.extended_pdf_info = options.extended_pdf_info.or(Some(false));
+    resolved.archive_max_entry_bytes = Some(max_entry_bytes(options) as usize);
+    resolved.archive_recurse_nested = options.archive_recurse_nested.or(Some(false));
+    resolved.archive_include_system_entries = options.archive_include_system_entries.or(Some(false));
+    resolved.validate_only = options.validate_only.or(Some(false));
+    resolved.return_boundaries = options.return_boundaries.or(Some(false));
+    resolved.extract_text_metrics = options.extract_text_metrics.or(Some(false));
+    resolved.extract_pdf_text_metrics = options.extract_pdf_text_metrics.or(Some(false));
+    resolved.detect_language = options.detect_language.or(Some(false));
+    if resolved.detect_language == Some(true) {
+        resolved.language_min_words = options.language_min_words.or(Some(DEFAULT_LANGUAGE_MIN_WORDS));
+    }
+    resolved.reading_wpm = options.reading_wpm.or(Some(DEFAULT_READING_WPM));
+    resolved.verbosity = Some(verbosity(options));
+    resolved.allow_tiny_text = options.allow_tiny_text.or(Some(false));
+    // results are stored and logged, so the password never leaves the estimate
+    resolved.pdf_password = None;
+    if options.expected_pages.is_some() {
+        resolved.expected_tolerance = options.expected_tolerance.or(Some(0.0));
+    }
+    if detected == "pdf" {
+        resolved.pdf_scan_budget_factor = options.pdf_scan_budget_factor.or(Some(DEFAULT_SCAN_BUDGET_FACTOR));
+    }
+    resolved.debug = options.debug.or(Some(false));
+    resolved.include_page_sizes = Some(options.wants_page_sizes());
+    resolved.include_point_sizes = options.include_point_sizes.or(Some(false));
+    resolved.split_oversized_pages = options.split_oversized_pages.or(Some(false));
+    if resolved.split_oversized_pages == Some(true) {
+        resolved.oversized_page_ratio = options.oversized_page_ratio.or(Some(DEFAULT_OVERSIZED_PAGE_RATIO));
+    }
+    resolved
+}
+
+/// Returns the stable machine-readable code reported as `error_code` for an error.
+///
+/// Codes never change once published, so callers can branch on them instead
+/// of on the human-readable `error` message.
+pub fn error_code(err: &EstimatorError) -> &'static str {
+    match err {
+        EstimatorError::UnsupportedFormat => "unsupported_format",
+        EstimatorError::PdfError(_) => "pdf_error",
+        EstimatorError::XlsxError(_) => "xlsx_error",
+        EstimatorError::DocxError(_) => "docx_error",
+        EstimatorError::PptxError(_) => "pptx_error",
+        EstimatorError::XpsError(_) => "xps_error",
+        EstimatorError::PostScriptError(_) => "postscript_error",
+        EstimatorError::ZipError(_) => "zip_error",
+        EstimatorError::XmlError(_) => "xml_error",
+        EstimatorError::EncryptedDocument => "encrypted_document",
+        EstimatorError::WrongPassword => "wrong_password",
+        EstimatorError::EmptyInput => "empty_input",
+        EstimatorError::TooLarge { .. } => "too_large",
+        EstimatorError::NetworkError(_) => "network_error",
+        EstimatorError::HttpError { .. } => "http_error",
+        EstimatorError::InvalidOptions(_) => "invalid_options",
+        EstimatorError::CustomEstimatorError(_) => "custom_estimator_error",
+        EstimatorError::InvalidInput(_) => "invalid_input",
+        EstimatorError::NotExported(_) => "not_exported",
+        EstimatorError::ParseBudgetExceeded { .. } => "parse_budget_exceeded",
+        EstimatorError::DeadlineExceeded { .. } => "deadline_exceeded",
+        EstimatorError::InvalidResult(_) => "invalid_result",
+        EstimatorError::General(_) => "general",
+    }
+}
+
+/// Rejects option values that no estimator can use (zero page or line sizes).
+///
+/// The same bounds are declared with `schemars` attributes on `EstimateOptions`,
+/// so that `options_schema` accepts nothing this function rejects.
+fn validate_options(options: &EstimateOptions) -> Result<(), EstimatorError> {
+    let zero_counts = [
+        ("chars_per_page", options.chars_per_page),
+        ("chars_per_line", options.chars_per_line),
+        ("lines_per_page", options.lines_per_page),
+        ("rows_per_page", options.rows_per_page),
+        ("columns_per_page", options.columns_per_page),
+        ("reading_wpm", options.reading_wpm),
+        ("language_min_words", options.language_min_words),
+        ("contacts_per_page", options.contacts_per_page),
+        ("events_per_page", options.events_per_page),
+        ("pdf_scan_budget_factor", options.pdf_scan_budget_factor),
+        ("deadline_ms", options.deadline_ms.map(|ms| ms as usize)),
+    ];
+    if let Some((name, _)) = zero_counts.iter().find(|(_, value)| *value == Some(0)) {
+        return Err(EstimatorError::InvalidOptions(format!("{} must be greater than 0", name)));
+    }
+    if let Some(rounding) = options.rounding.as_deref()
+        && PageRounding::from_name(rounding).is_none()
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "rounding must be \"ceil\", \"nearest\", or \"floor\", got \"{}\"",
+            rounding
+        )));
+    }
+    if let Some(tolerance) = options.expected_tolerance
+        && !(tolerance.is_finite() && tolerance >= 0.0)
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "expected_tolerance must be at least 0, got {}",
+            tolerance
+        )));
+    }
+    if let Some(ratio) = options.oversized_page_ratio
+        && !(ratio.is_finite() && ratio >= 1.0)
+    {
+        return Err(EstimatorError::InvalidOptions(format!("oversized_page_ratio must be at least 1, got {}", ratio)));
+    }
+    if let Some(pages) = options.docx_object_page_equivalent
+        && !(0.0..=MAX_OBJECT_PAGE_EQUIVALENT).contains(&pages)
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "docx_object_page_equivalent must be from 0 to {}, got {}",
+            MAX_OBJECT_PAGE_EQUIVALENT, pages
+        )));
+    }
+    let equation_lines = [
+        ("docx_display_equation_lines", options.docx_display_equation_lines),
+        ("docx_inline_equation_lines", options.docx_inline_equation_lines),
+    ];
+    if let Some((name, Some(lines))) =
+        equation_lines.iter().find(|(_, value)| value.is_some_and(|lines| !(0.0..=MAX_EQUATION_LINES).contains(&lines)))
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "{} must be from 0 to {}, got {}",
+            name, MAX_EQUATION_LINES, lines
+        )));
+    }
+    if let Some((first, last)) = options.page_range
+        && (first == 0 || first > last)
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "page_range must be 1-based with the first page not after the last, got [{}, {}]",
+            first, last
+        )));
+    }
+    if let Some(height) = options.image_height_mm
+        && !(height.is_finite() && height >= 0.0)
+    {
+        return Err(EstimatorError::InvalidOptions(format!("image_height_mm must not be negative, got {}", height)));
+    }
+    if let Some(spacing) = options.line_spacing
+        && !(spacing.is_finite() && spacing > 0.0)
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "line_spacing must be a positive number, got {}",
+            spacing
+        )));
+    }
+    if let Some((w, h)) = options.custom_paper_mm
+        && !(w.is_finite() && h.is_finite() && w > 0.0 && h > 0.0)
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "custom_paper_mm must be positive, got {} × {}",
+            w, h
+        )));
+    }
+    if let Some((top, bottom)) = options.xlsx_margins_mm
+        && !(top.is_finite() && bottom.is_finite() && top >= 0.0 && bottom >= 0.0)
+    {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "xlsx_margins_mm must not be negative, got {} / {}",
+            top, bottom
+        )));
+    }
+    let sheet_papers = options.xlsx_sheet_paper.iter().flatten();
+    if let Some((sheet, paper)) = sheet_papers.clone().find(|(_, paper)| parse_paper(paper).is_none()) {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "xlsx_sheet_paper for sheet '{}' must be a paper name or a \"WxH\" size in millimeters, got \"{}\"",
+            sheet, paper
+        )));
+    }
+    Ok(())
+}
+
+/// Detects the document type and runs the matching estimator.
+///
+/// Returns the detected format together with the estimate or an error message.
+/// This is the shared core of every exported estimation entry point, and the
+/// entry point for native (non-wasm) callers such as the benchmarks, since it
+/// doesn't touch `JsValue`.
+///
+/// Inputs no built-in estimator supports are passed to the fallback estimator
+/// registered from JavaScript, if there is one (see `register_fallback_estimator`).
+///
+/// The print summary is added and the notes are trimmed to the requested
+/// `verbosity` after the format-specific estimator has run.
+pub fn estimate_bytes(
+    bytes: &[u8],
+    filename: Option<&str>,
+    options: &EstimateOptions,
+) -> (String, Result<EstimateResult, EstimatorError>) {
+    let scope = EstimateScope::begin(bytes.len(), options);
+    let Detection { format: detected, warning: detection_warning } =
+        detect_format(filename, options.mime_hint.as_deref(), bytes);
+    if let Err(err) = validate_options(options) {
+        return (detected, Err(err));
+    }
+    if let Some(limit) = options.max_input_bytes
+        && bytes.len() > limit
+    {
+        return (detected, Err(EstimatorError::TooLarge { limit, actual: bytes.len() }));
+    }
+    if !options.allow_tiny_text.unwrap_or(false)
+        && let Some(kind) = looks_like_locator(bytes)
+    {
+        let message = format!(
+            concat!(
+                "the input is a {}, not the content of a document. Pass the file's bytes ",
+                "(e.g. from File.arrayBuffer() or Response.arrayBuffer()), use estimate_url for URLs ",
+                "(with the fetch feature), or set allow_tiny_text to estimate the text itself"
+            ),
+            kind
+        );
+        return (detected, Err(EstimatorError::InvalidInput(message)));
+    }
+    // a stub is never the document, whatever its filename says
+    if let Some(stub) = looks_like_google_stub(bytes) {
+        let message = format!(
+            "the input is a {} for a {} file, not the file itself. Export it as {} first (File > Download)",
+            stub.kind, stub.application, stub.formats
+        );
+        return (detected, Err(EstimatorError::NotExported(message)));
+    }
+    // the hint describes this input, not the documents inside it
+    let unhinted;
+    let estimator_options = match options.mime_hint {
+        Some(_) => {
+            unhinted = EstimateOptions { mime_hint: None, ..options.clone() };
+            &unhinted
+        }
+        None => options,
+    };
+    let ((detected, result), deadline_exceeded) = with_deadline(options.deadline_ms, || {
+        let (detected, mut result) = run_estimator(bytes, detected, filename, estimator_options, 0);
+        if matches!(result, Err(EstimatorError::UnsupportedFormat))
+            && let Some(custom) = run_fallback_estimator(bytes, &detected, filename)
+        {
+            result = custom;
+        }
+        (detected, result)
+    });
+
+    let result = result.and_then(|mut est| {
+        if let Some(warning) = detection_warning {
+            est.notes.insert(0, warning);
+        }
+        if deadline_exceeded {
+            flag_deadline_exceeded(&mut est, options);
+        }
+        if options.debug.unwrap_or(false) {
+            est.diagnostics = match (est.diagnostics.take(), collect_diagnostics(bytes, &detected)) {
+                // keep what the estimator reported (the language guess) next to the collected details
+                (Some(Value::Object(mut own)), Some(Value::Object(collected))) => {
+                    own.extend(collected);
+                    Some(Value::Object(own))
+                }
+                (own, collected) => collected.or(own),
+            };
+            est.notes.extend(est.diagnostics.as_ref().and_then(media_note));
+        }
+        apply_expected_pages(&mut est, bytes, &detected, options);
+        // estimators without hooks read the whole input
+        est.bytes_examined = Some(match scope.bytes_examined() {
+            0 => bytes.len(),
+            examined => examined,
+        });
+        apply_point_sizes(&mut est, options);
+        apply_page_split(&mut est, options);
+        apply_reading_time(&mut est, options);
+        apply_page_range(&mut est, bytes.len(), options)?;
+        apply_print_summary(&mut est, options);
+        apply_verbosity(&mut est, options);
+        Ok(est)
+    });
+    if cfg!(debug_assertions)
+        && let Ok(est) = &result
+        && let Err(violation) = est.validate_invariants()
+    {
+        panic!("{} estimate breaks an invariant: {}", detected, violation);
+    }
+
+    (detected, result)
+}
+
+/// Warns that `deadline_ms` cut the estimate short and caps its confidence
+/// at `DEADLINE_CONFIDENCE` (see the `deadline` module).
+fn flag_deadline_exceeded(result: &mut EstimateResult, options: &EstimateOptions) {
+    result.notes.insert(
+        0,
+        format!(
+            "{}: stopped after the {} ms deadline; the count covers only what was read by then",
+            DEADLINE_WARNING,
+            options.deadline_ms.unwrap_or_default()
+        ),
+    );
+    result.confidence = Some(result.confidence.map_or(DEADLINE_CONFIDENCE, |c| c.min(DEADLINE_CONFIDENCE)));
+}
+
+/// Fills `reading_time_minutes` from the word count in `text_metrics`, unless
+/// the estimator set it itself with image and code block times (Markdown, MHT).
+fn apply_reading_time(result: &mut EstimateResult, options: &EstimateOptions) {
+    if result.reading_time_minutes.is_none()
+        && let Some(metrics) = &result.text_metrics
+    {
+        let wpm = options.reading_wpm.unwrap_or(DEFAULT_READING_WPM);
+        result.reading_time_minutes = Some(reading_minutes(metrics.word_count, ReadingMedia::default(), wpm));
+    }
+}
+
+/// Counts the pages of `page_range`, clamped to the last page, and finds the
+/// byte span of the range when the estimator returned page boundaries (text
+/// and Markdown). The boundaries are dropped again unless `return_boundaries` is set.
+///
+/// # Errors
+///
+/// Returns `EstimatorError::InvalidOptions` when the range starts past the last page.
+fn apply_page_range(
+    result: &mut EstimateResult,
+    input_len: usize,
+    options: &EstimateOptions,
+) -> Result<(), EstimatorError> {
+    let Some((first, last)) = options.page_range else { return Ok(()) };
+    if first > result.page_count {
+        return Err(EstimatorError::InvalidOptions(format!(
+            "page_range starts at page {}, but the document has {} pages",
+            first, result.page_count
+        )));
+    }
+    let last = last.min(result.page_count);
+    result.range_page_count = Some(last + 1 - first);
+    if let Some(boundaries) = &result.page_boundaries
+        && let Some(&start) = boundaries.get(first - 1)
+    {
+        result.range_char_start = Some(start);
+        result.range_char_end = Some(boundaries.get(last).copied().unwrap_or(input_len));
+    }
+    if !options.return_boundaries.unwrap_or(false) {
+        result.page_boundaries = None;
+    }
+    result.notes.push(if first == last {
+        format!("Range: page {} of {}", first, result.page_count)
+    } else {
+        format!("Range: pages {}–{} of {}", first, last, result.page_count)
+    });
+    Ok(())
+}
+
+/// Fills `page_sizes_pt` from `page_sizes` when `include_point_sizes` is set
+/// and the estimator didn't read point sizes itself (as the PDF estimator does).
+fn apply_point_sizes(result: &mut EstimateResult, options: &EstimateOptions) {
+    if options.include_point_sizes.unwrap_or(false) && options.wants_page_sizes() && result.page_sizes_pt.is_none() {
+        result.page_sizes_pt = Some(result.page_sizes.iter().map(PageSizePt::from_mm).collect());
+    }
+}
+
+/// Routes a document to the estimator for its `detected` type.
+///
+/// `gzip_layers` counts the gzip layers already taken off `bytes`; input
+/// compressed more than `MAX_GZIP_LAYERS` times over is refused.
+fn run_estimator(
+    bytes: &[u8],
+    detected: String,
+    filename: Option<&str>,
+    options: &EstimateOptions,
+    gzip_layers: usize,
+) -> (String, Result<EstimateResult, EstimatorError>) {
+    if bytes.is_empty() {
+        return (detected, Err(EstimatorError::EmptyInput));
+    }
 
     let result = match detected.as_str() {
-        "pdf" => match estimate_pdf_pages(bytes, &options) {
-            Ok(r) => Ok(r),
-            Err(err) => Err(err.to_string()),
-        },
-        "xlsx" => match estimate_xlsx_pages(bytes, &options) {
-            Ok(r) => Ok(r),
-            Err(err) => Err(err.to_string()),
-        },
-        "docx" => match estimate_docx_pages(bytes, &options) {
-            Ok(r) => Ok(r),
-            Err(err) => Err(err.to_string()),
-        },
-        "pptx" => match estimate_pptx_pages(bytes, &options) {
-            Ok(r) => Ok(r),
-            Err(err) => Err(err.to_string()),
-        },
-        "txt" => Ok(estimate_text_pages(bytes, &options)),
-        "markdown" => Ok(estimate_markdown_pages(bytes, &options)),
-        other => Err(format!("Unsupported or unrecognized format: {}", other)),
+        "pdf" => estimate_pdf_pages(bytes, options).map(|mut est| {
+            if options.estimate_attachments.unwrap_or(false) {
+                add_embedded_documents(bytes, &mut est, options);
+            }
+            est
+        }),
+        "xlsx" => estimate_xlsx_pages(bytes, options),
+        "docx" => estimate_docx_pages(bytes, options),
+        "pptx" => estimate_pptx_pages(bytes, options),
+        "xps" => estimate_xps_pages(bytes, options),
+        "postscript" => estimate_postscript_pages(bytes, options),
+        "cbz" => estimate_cbz_pages(bytes, options),
+        "iwork" => estimate_iwork_pages(bytes, options),
+        "rar" => Err(EstimatorError::General(
+            "RAR archives (including .cbr comics) are not supported; repackage as ZIP (.cbz)".into(),
+        )),
+        "txt" => Ok(estimate_text_pages(bytes, options)),
+        "markdown" => Ok(estimate_markdown_pages(bytes, options)),
+        "mht" => estimate_mht_pages(bytes, options),
+        "code" => Ok(estimate_code_pages(bytes, options)),
+        "data" => Ok(estimate_data_pages(bytes, options)),
+        "notebook" => estimate_notebook_pages(bytes, options),
+        "records" => Ok(estimate_records_pages(bytes, options)),
+        "latex" => Ok(estimate_latex_pages(bytes, options)),
+        "rst" => Ok(estimate_rst_pages(bytes, options)),
+        "asciidoc" => Ok(estimate_asciidoc_pages(bytes, options)),
+        "docbook" => Ok(estimate_docbook_pages(bytes, options)),
+        "gzip" if gzip_layers >= MAX_GZIP_LAYERS => Err(EstimatorError::InvalidInput(format!(
+            "gzip input is compressed more than {} times over",
+            MAX_GZIP_LAYERS
+        ))),
+        "gzip" => {
+            return match decompress_gzip(bytes, max_entry_bytes(options)) {
+                Ok(inner) => {
+                    let inner_name = filename.map(strip_gzip_extension);
+                    let inner_detected = detect_type(inner_name.as_deref(), &inner);
+                    let (inner_detected, result) =
+                        run_estimator(&inner, inner_detected, inner_name.as_deref(), options, gzip_layers + 1);
+                    let result = result.map(|mut est| {
+                        est.notes
+                            .push(format!("Decompressed gzip input ({} bytes)", inner.len()));
+                        est
+                    });
+                    (inner_detected, result)
+                }
+                Err(err) => (detected, Err(err)),
+            };
+        }
+        "eml" => {
+            let mut estimate = ArchiveEstimate::default();
+            collect_email_entries(bytes, "", options, 0, &mut estimate);
+            Ok(combine_entries(estimate))
+        }
+        "zip" | "tar" => Err(EstimatorError::General(format!(
+            "{} archive is not a single document; use estimate_archive instead",
+            detected.to_uppercase()
+        ))),
+        _ => Err(EstimatorError::UnsupportedFormat),
     };
 
-    match result {
-        Ok(est) => match serde_json::to_string(&est) {
-            Ok(s) => JsValue::from_str(&s),
-            Err(_) => JsValue::from_str(&json!({"error":"serialization failed"}).to_string()),
+    (detected, result)
+}
+
+/// Estimates the files embedded in a PDF and adds their pages to its result.
+///
+/// Embedded documents are estimated with `estimate_attachments` turned off, so
+/// portfolios nested in a portfolio aren't expanded further. Files that can't
+/// be read, exceed `archive_max_entry_bytes`, or fail to estimate are noted
+/// and skipped. Color and orientation counts are kept only when every embedded
+/// document has them.
+fn add_embedded_documents(bytes: &[u8], result: &mut EstimateResult, options: &EstimateOptions) {
+    let index = ObjectIndex::build(bytes);
+    let scan = scan_portfolio(&index);
+    if scan.embedded_files.is_empty() {
+        return;
+    }
+    let inner_options = EstimateOptions { estimate_attachments: Some(false), ..options.clone() };
+    let (mut min, mut max) = result.page_count_range();
+    let mut ranged = result.page_count_min.is_some();
+    let mut added = 0;
+    for (i, file) in scan.embedded_files.iter().enumerate() {
+        let name = file.name.clone().unwrap_or_else(|| format!("embedded-{}", i + 1));
+        let Some(contents) = read_embedded_file(&index, file) else {
+            result.notes.push(format!("⚠ Embedded file '{}' could not be read", name));
+            continue;
+        };
+        if let Some(reason) = skip_reason(&name, contents.len() as u64, options) {
+            result.notes.push(reason);
+            continue;
+        }
+        let detected = detect_type(Some(&name), &contents);
+        match run_estimator(&contents, detected, Some(&name), &inner_options, 0).1 {
+            Ok(embedded) => {
+                result.notes.push(format!("Embedded '{}': {} pages", name, embedded.page_count));
+                added += embedded.page_count;
+                let (embedded_min, embedded_max) = embedded.page_count_range();
+                min += embedded_min;
+                max += embedded_max;
+                ranged |= embedded.page_count_min.is_some();
+                if let Some(sizes) = result.page_sizes_pt.as_mut() {
+                    match embedded.page_sizes_pt {
+                        Some(more) => sizes.extend(more),
+                        None => sizes.extend(embedded.page_sizes.iter().map(PageSizePt::from_mm)),
+                    }
+                }
+                result.page_sizes.extend(embedded.page_sizes);
+                match (result.color_page_flags.as_mut(), embedded.color_page_flags) {
+                    (Some(flags), Some(more)) => flags.extend(more),
+                    _ => result.color_page_flags = None,
+                }
+                result.portrait_pages = result.portrait_pages.zip(embedded.portrait_pages).map(|(a, b)| a + b);
+                result.landscape_pages = result.landscape_pages.zip(embedded.landscape_pages).map(|(a, b)| a + b);
+                result.square_pages = result.square_pages.zip(embedded.square_pages).map(|(a, b)| a + b);
+            }
+            Err(err) => result.notes.push(format!("⚠ Embedded '{}' skipped: {}", name, err)),
+        }
+    }
+    if added > 0 {
+        result.notes.push(format!("Added {} pages from embedded documents", added));
+    }
+    result.page_count += added;
+    if ranged {
+        result.page_count_min = Some(min);
+        result.page_count_max = Some(max);
+    }
+    let color = result.color_page_flags.as_ref().map(|flags| flags.iter().filter(|c| **c).count());
+    result.color_pages = color;
+    result.grayscale_pages = color.zip(result.color_page_flags.as_ref()).map(|(c, flags)| flags.len() - c);
+}
+
+/// Estimates every document contained in an archive (ZIP, TAR, or gzip-compressed TAR).
+///
+/// Clients often upload a single archive holding a mix of PDFs, Office documents,
+/// and text files. Each entry is run through the same detection and estimation
+/// as `estimate_document`, using the entry name as the filename hint.
+///
+/// # Parameters
+///
+/// * `bytes` - The raw archive bytes (`.zip`, `.tar`, `.tar.gz`/`.tgz`)
+///
+/// * `options_json` - Optional JSON string containing estimation options.
+///                    Applied to every entry. `archive_max_entry_bytes` caps the
+///                    size of entries that are estimated (and of decompressed gzip
+///                    data), and `archive_recurse_nested` enables estimating archives
+///                    nested one level deep.
+///
+/// # Returns
+///
+/// A `JsValue` containing a JSON string with:
+/// - `entries`: Array of `{path, result}` or `{path, error}` objects, in archive order
+/// - `total_page_count`: Sum of the page counts of all successfully estimated entries
+/// - `notes`: Entries that were skipped and why
+///
+/// If the archive itself cannot be opened, returns a JSON object with an `error` field.
+///
+/// # Skipped Entries
+///
+/// - Directories and hidden files (any path component starting with `.`)
+/// - Entries added by the operating system (`__MACOSX/`, `._` files, `.DS_Store`,
+///   `Thumbs.db`, `desktop.ini`), unless `archive_include_system_entries` is set
+/// - Entries larger than the per-entry size cap (default: 100 MB)
+/// - Nested archives, unless `archive_recurse_nested` is set (one level only)
+/// - Sparse TAR entries
+#[wasm_bindgen]
+pub fn estimate_archive(bytes: &[u8], options_json: Option<String>) -> JsValue {
+    let options = parse_options(options_json);
+    let (detected, result) = estimate_archive_bytes(bytes, &options);
+    let json = match result {
+        Ok(estimate) => match to_versioned_json(&estimate) {
+            Ok(s) | Err(s) => s,
         },
-        Err(err_msg) => {
-            JsValue::from_str(&json!({"error": err_msg, "detected": detected}).to_string())
+        Err(err) => with_schema_version(
+            &json!({"error": err.to_string(), "error_code": error_code(&err), "detected": detected}).to_string(),
+        ),
+    };
+    JsValue::from_str(&json)
+}
+
+/// Estimates every document in an archive, as `estimate_archive` does.
+///
+/// This is the native entry point behind `estimate_archive`, as
+/// `estimate_bytes` is behind `estimate_document`.
+///
+/// # Returns
+///
+/// The detected container type, and the per-entry estimate or the error that
+/// kept the archive from being read.
+pub fn estimate_archive_bytes(
+    bytes: &[u8],
+    options: &EstimateOptions,
+) -> (String, Result<ArchiveEstimate, EstimatorError>) {
+    let mut options = options.clone();
+    // entries are files, so a short one that names a URL or a path is real content
+    options.allow_tiny_text = Some(true);
+    let _scope = EstimateScope::begin(bytes.len(), &options);
+    let mut estimate = ArchiveEstimate::default();
+
+    // the hint describes the archive, not its entries
+    let detected = detect_format(None, options.mime_hint.take().as_deref(), bytes).format;
+    if let Err(err) = collect_container_entries(bytes, &detected, "", &options, 0, &mut estimate) {
+        return (detected, Err(err));
+    }
+
+    estimate.total_page_count = estimate
+        .entries
+        .iter()
+        .filter_map(|e| e.result.as_ref())
+        .map(|r| r.page_count)
+        .sum();
+    limit_notes(&mut estimate.notes, verbosity(&options));
+    (detected, Ok(estimate))
+}
+
+/// Combines several estimate results into one (see `EstimateResult::merge`).
+///
+/// Useful after estimating a batch of files, or the entries of an archive,
+/// that are printed as one job.
+///
+/// # Parameters
+///
+/// * `results_json` - JSON array of result objects as returned by `estimate_document`,
+///                    in the order their pages should appear
+///
+/// * `options_json` - Optional JSON string containing estimation options. When it
+///                    has printing options, the print summary is computed for the
+///                    combined page count; `verbosity` trims the merged notes.
+///
+/// # Returns
+///
+/// A `JsValue` containing the combined result as a JSON string, or a JSON object
+/// with `error` and `error_code` fields if `results_json` is not an array of results.
+#[wasm_bindgen]
+pub fn merge_results(results_json: String, options_json: Option<String>) -> JsValue {
+    let results: Vec<EstimateResult> = match serde_json::from_str(&results_json) {
+        Ok(results) => results,
+        Err(e) => {
+            let err = EstimatorError::General(format!("results must be an array of estimate results: {}", e));
+            return JsValue::from_str(&with_schema_version(
+                &json!({"error": err.to_string(), "error_code": error_code(&err)}).to_string(),
+            ));
+        }
+    };
+    let options = parse_options(options_json);
+    let mut merged = EstimateResult::merge(&results);
+    apply_print_summary(&mut merged, &options);
+    apply_verbosity(&mut merged, &options);
+
+    match to_versioned_json(&merged) {
+        Ok(s) | Err(s) => JsValue::from_str(&s),
+    }
+}
+
+/// Reads a stored estimate result, as returned by `estimate_document`.
+///
+/// # Arguments
+///
+/// * `json` - The result JSON, of the current `schema_version` or an earlier one
+///   (results without `schema_version` are version 1)
+///
+/// # Returns
+///
+/// The result in the current shape.
+///
+/// # Errors
+///
+/// `EstimatorError::InvalidResult` when the JSON isn't a result (e.g. a stored
+/// error object) or has a `schema_version` newer than `SCHEMA_VERSION`.
+pub fn parse_result(json: &str) -> Result<EstimateResult, EstimatorError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| EstimatorError::InvalidResult(e.to_string()))?;
+    if let Some(error) = value.get("error").and_then(Value::as_str)
+        && value.get("page_count").is_none()
+    {
+        return Err(EstimatorError::InvalidResult(format!("the JSON is an error object: {}", error)));
+    }
+    let version = match value.get("schema_version") {
+        None => 1,
+        Some(version) => version.as_u64().filter(|v| *v >= 1).ok_or_else(|| {
+            EstimatorError::InvalidResult(format!("schema_version must be a positive integer, got {}", version))
+        })?,
+    };
+    if version > SCHEMA_VERSION as u64 {
+        return Err(EstimatorError::InvalidResult(format!(
+            "schema_version {} is newer than this build reads ({})",
+            version, SCHEMA_VERSION
+        )));
+    }
+    // version 1 only lacks schema_version; upgrades for later breaking changes go here, oldest first
+    serde_json::from_value(value).map_err(|e| EstimatorError::InvalidResult(e.to_string()))
+}
+
+/// Reads a stored estimate result and returns it in the current shape (see `parse_result`).
+///
+/// # Parameters
+///
+/// * `json` - A result JSON string as returned by `estimate_document`, possibly
+///            stored by an earlier version
+///
+/// # Returns
+///
+/// A `JsValue` containing the result as a JSON string with the current
+/// `schema_version`, or a JSON object with `error` and `error_code`
+/// (`invalid_result`) fields.
+#[wasm_bindgen]
+pub fn parse_result_json(json: String) -> JsValue {
+    let output = match parse_result(&json) {
+        Ok(result) => to_versioned_json(&result),
+        Err(err) => Err(with_schema_version(
+            &json!({"error": err.to_string(), "error_code": error_code(&err)}).to_string(),
+        )),
+    };
+    match output {
+        Ok(s) | Err(s) => JsValue::from_str(&s),
+    }
+}
+
+/// Reports the page sizes of a stored result in another unit (see `EstimateResult::convert_units`).
+///
+/// # Parameters
+///
+/// * `json` - A result JSON string as returned by `estimate_document`, possibly
+///            stored by an earlier version
+/// * `unit` - `"mm"`, `"pt"` (fills `page_sizes_pt`), or `"in"` (fills `page_sizes_in`)
+///
+/// # Returns
+///
+/// A `JsValue` containing the converted result as a JSON string, or a JSON
+/// object with `error` and `error_code` fields (`invalid_result` for an
+/// unreadable result, `invalid_options` for an unknown unit).
+#[wasm_bindgen]
+pub fn convert_result_units(json: String, unit: &str) -> JsValue {
+    let converted = Unit::parse(unit)
+        .ok_or_else(|| {
+            EstimatorError::InvalidOptions(format!("unit must be \"mm\", \"pt\", or \"in\", got \"{}\"", unit))
+        })
+        .and_then(|unit| parse_result(&json).map(|result| result.convert_units(unit)));
+    let output = match converted {
+        Ok(result) => to_versioned_json(&result),
+        Err(err) => Err(with_schema_version(
+            &json!({"error": err.to_string(), "error_code": error_code(&err)}).to_string(),
+        )),
+    };
+    match output {
+        Ok(s) | Err(s) => JsValue::from_str(&s),
+    }
+}
+
+/// Most gzip layers `estimate_document` takes off a single input (`.gz.gz` is two).
+const MAX_GZIP_LAYERS: usize = 4;
+
+/// Default per-entry size cap for archive estimation and gzip decompression (100 MB).
+const DEFAULT_MAX_ENTRY_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Container formats that hold other documents rather than being one.
+fn is_container(detected: &str) -> bool {
+    matches!(detected, "zip" | "tar" | "gzip" | "eml")
+}
+
+/// Size cap for an embedded or archived document (`archive_max_entry_bytes`, default 100 MB).
+pub fn max_entry_bytes(options: &EstimateOptions) -> u64 {
+    options
+        .archive_max_entry_bytes
+        .map_or(DEFAULT_MAX_ENTRY_BYTES, |n| n as u64)
+}
+
+/// Estimates the entries of a container, appending them to `estimate`.
+fn collect_container_entries(
+    bytes: &[u8],
+    detected: &str,
+    prefix: &str,
+    options: &EstimateOptions,
+    depth: usize,
+    estimate: &mut ArchiveEstimate,
+) -> Result<(), EstimatorError> {
+    match detected {
+        "gzip" => {
+            let inner = decompress_gzip(bytes, max_entry_bytes(options))?;
+            match detect_type(None, &inner).as_str() {
+                "tar" => collect_container_entries(&inner, "tar", prefix, options, depth, estimate),
+                _ => Err(EstimatorError::General(
+                    "gzip data does not contain a TAR archive; use estimate_document instead".into(),
+                )),
+            }
+        }
+        "eml" => {
+            collect_email_entries(bytes, prefix, options, depth, estimate);
+            Ok(())
+        }
+        "tar" => {
+            let (entries, notes) = read_tar_entries(bytes)?;
+            estimate.notes.extend(notes);
+            for entry in entries {
+                let path = format!("{}{}", prefix, entry.path);
+                if let Some(reason) = skip_reason(&path, entry.data.len() as u64, options) {
+                    estimate.notes.push(reason);
+                    continue;
+                }
+                estimate_archive_entry(path, entry.data, options, depth, estimate);
+            }
+            Ok(())
+        }
+        _ => {
+            let mut archive = open_zip_archive(bytes, "archive")?;
+            for i in 0..archive.len() {
+                let mut file = match archive.by_index(i) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        estimate.notes.push(format!("⚠ Could not read entry #{}: {}", i, e));
+                        continue;
+                    }
+                };
+                if file.is_dir() {
+                    continue;
+                }
+                let path = format!("{}{}", prefix, file.name());
+                if let Some(reason) = skip_reason(&path, file.size(), options) {
+                    estimate.notes.push(reason);
+                    continue;
+                }
+                note_zip_entry(&file);
+
+                let mut contents = Vec::with_capacity(file.size() as usize);
+                if let Err(e) = file.read_to_end(&mut contents) {
+                    let err = EstimatorError::ZipError(format!("Failed to extract entry: {}", e));
+                    estimate.entries.push(failed_entry(path, &err));
+                    continue;
+                }
+                drop(file);
+                estimate_archive_entry(path, &contents, options, depth, estimate);
+            }
+            Ok(())
         }
     }
 }
 
+/// Estimates the body and attachments of an email message.
+///
+/// The text body (preferring `text/plain` over `text/html`) becomes a
+/// `(message body)` entry; each attachment is estimated using its filename,
+/// or a name derived from its declared content type.
+fn collect_email_entries(
+    bytes: &[u8],
+    prefix: &str,
+    options: &EstimateOptions,
+    depth: usize,
+    estimate: &mut ArchiveEstimate,
+) {
+    let message = parse_mime(bytes);
+    let mut plain_body: Option<&MimePart> = None;
+    let mut html_body: Option<&MimePart> = None;
+    let mut attachments = Vec::new();
+
+    for part in message.leaves() {
+        let content_type = part.content_type();
+        if part.is_attachment() || !content_type.starts_with("text/") {
+            attachments.push(part);
+        } else if content_type == "text/html" {
+            html_body.get_or_insert(part);
+        } else {
+            plain_body.get_or_insert(part);
+        }
+    }
+
+    let body_text = match (plain_body, html_body) {
+        (Some(part), _) => Some(String::from_utf8_lossy(&part.body).into_owned()),
+        (None, Some(part)) => Some(html_to_text(&String::from_utf8_lossy(&part.body))),
+        (None, None) => None,
+    };
+    if let Some(text) = body_text {
+        let mut result = estimate_text_pages(text.as_bytes(), options);
+        apply_print_summary(&mut result, options);
+        estimate.entries.push(ArchiveEntryResult {
+            path: format!("{}(message body)", prefix),
+            result: Some(result),
+            error: None,
+            error_code: None,
+        });
+    }
+
+    for (i, part) in attachments.into_iter().enumerate() {
+        let content_type = part.content_type();
+        let name = part.filename().unwrap_or_else(|| {
+            let ext = extension_for_content_type(&content_type).unwrap_or("bin");
+            format!("attachment-{}.{}", i + 1, ext)
+        });
+        if content_type.starts_with("image/") && part.filename().is_none() {
+            estimate.notes.push(format!("Skipped inline image part ({})", content_type));
+            continue;
+        }
+        let path = format!("{}{}", prefix, name);
+        if let Some(reason) = skip_reason(&path, part.body.len() as u64, options) {
+            estimate.notes.push(reason);
+            continue;
+        }
+        estimate_archive_entry(path, &part.body, options, depth, estimate);
+    }
+}
+
+/// Collapses per-entry results into a single result for callers that expect one document.
+fn combine_entries(estimate: ArchiveEstimate) -> EstimateResult {
+    let mut combined = EstimateResult::default();
+    for entry in estimate.entries {
+        match entry.result {
+            Some(result) => {
+                combined.page_count += result.page_count;
+                combined.page_sizes.extend(result.page_sizes);
+                combined
+                    .notes
+                    .push(format!("{}: {} pages", entry.path, result.page_count));
+            }
+            None => combined.notes.push(format!(
+                "{}: {}",
+                entry.path,
+                entry.error.unwrap_or_default()
+            )),
+        }
+    }
+    combined.notes.extend(estimate.notes);
+    combined
+}
+
+/// Explains why an entry is skipped (hidden or over the size cap), if it is.
+fn skip_reason(path: &str, size: u64, options: &EstimateOptions) -> Option<String> {
+    let limit = max_entry_bytes(options);
+    let system = is_system_entry(path);
+    if system && !options.archive_include_system_entries.unwrap_or(false) {
+        Some(format!("Skipped system entry '{}'", path))
+    } else if !system && path.split('/').any(|part| part.starts_with('.')) {
+        Some(format!("Skipped hidden entry '{}'", path))
+    } else if size > limit {
+        Some(format!(
+            "Skipped '{}': {} bytes exceeds the per-entry limit of {} bytes",
+            path, size, limit
+        ))
+    } else {
+        None
+    }
+}
+
+/// Estimates a single extracted entry, recursing into nested archives when allowed.
+fn estimate_archive_entry(
+    path: String,
+    contents: &[u8],
+    options: &EstimateOptions,
+    depth: usize,
+    estimate: &mut ArchiveEstimate,
+) {
+    let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+    let detected = detect_type(Some(&name), contents);
+
+    if is_container(&detected) {
+        if options.archive_recurse_nested.unwrap_or(false) && depth == 0 {
+            let nested_prefix = format!("{}/", path);
+            if let Err(e) = collect_container_entries(
+                contents,
+                &detected,
+                &nested_prefix,
+                options,
+                depth + 1,
+                estimate,
+            ) {
+                estimate.entries.push(failed_entry(path, &e));
+            }
+        } else {
+            estimate.notes.push(format!("Skipped nested archive '{}'", path));
+        }
+        return;
+    }
+
+    let (_, result) = estimate_bytes(contents, Some(&name), options);
+    let entry = match result {
+        Ok(r) => ArchiveEntryResult { path, result: Some(r), error: None, error_code: None },
+        Err(e) => failed_entry(path, &e),
+    };
+    estimate.entries.push(entry);
+}
+
+/// Builds the archive entry for a document that could not be estimated.
+fn failed_entry(path: String, err: &EstimatorError) -> ArchiveEntryResult {
+    ArchiveEntryResult {
+        path,
+        result: None,
+        error: Some(err.to_string()),
+        error_code: Some(error_code(err).to_string()),
+    }
+}
+
 /// Estimate PDF pages using PDF.js (async)
 /// 
 /// This function uses PDF.js through JavaScript bindings for fast and reliable
-/// PDF page counting. Falls back to Rust parser if PDF.js is not available.
+/// PDF page counting. Falls back to Rust parser if PDF.js is not available, and
+/// says so in the result's notes (the console only sees it at the `debug` log level).
 #[wasm_bindgen]
 pub async fn estimate_pdf_with_pdfjs(bytes: Vec<u8>) -> JsValue {
+    let mut fallback_note = None;
     // Try PDF.js first (fast and reliable)
     match count_pdf_pages_js(&bytes).await {
         Ok(js_result) => {
@@ -244,17 +1564,17 @@ pub async fn estimate_pdf_with_pdfjs(bytes: Vec<u8>) -> JsValue {
                         let width_mm = mm_from_pt(width_pt);
                         let height_mm = mm_from_pt(height_pt);
                         
-                        let result = EstimateResult {
+                        let result = EstimateResult::uniform(
                             page_count,
-                            page_sizes: vec![PageSizeMm { width_mm, height_mm }; page_count],
-                            notes: vec![
+                            Some((width_mm, height_mm)),
+                            vec![
                                 format!("PDF has {} pages (dimensions: {:.1} × {:.1} mm)", 
                                     page_count, width_mm, height_mm),
                                 "⚡ Using PDF.js (fast and reliable)".to_string(),
                             ],
-                        };
+                        );
                         
-                        match serde_json::to_string(&result) {
+                        match to_versioned_json(&result) {
                             Ok(s) => return JsValue::from_str(&s),
                             Err(_) => {}
                         }
@@ -264,21 +1584,695 @@ pub async fn estimate_pdf_with_pdfjs(bytes: Vec<u8>) -> JsValue {
             }
         }
         Err(e) => {
-            // PDF.js failed, log and fall back to Rust parser
-            let error_msg = format!("PDF.js not available, using Rust parser: {:?}", e);
-            web_sys::console::log_1(&error_msg.into());
+            // PDF.js failed, note it and fall back to Rust parser
+            let note = format!("PDF.js not available, using Rust parser: {}", describe_js_error(&e));
+            log(LogLevel::Debug, &note);
+            fallback_note = Some(note);
         }
     }
     
     // Fallback to Rust parser
     let options = EstimateOptions::default();
-    match estimate_pdf_pages(&bytes, &options) {
-        Ok(result) => match serde_json::to_string(&result) {
-            Ok(s) => JsValue::from_str(&s),
-            Err(_) => JsValue::from_str(&json!({"error":"serialization failed"}).to_string()),
+    let estimate = estimate_pdf_pages(&bytes, &options).map(|mut result| {
+        result.notes.splice(0..0, fallback_note);
+        result
+    });
+    match estimate {
+        Ok(result) => match to_versioned_json(&result) {
+            Ok(s) | Err(s) => JsValue::from_str(&s),
         },
-        Err(err) => JsValue::from_str(
-            &json!({"error": format!("{:?}", err), "detected": "pdf"}).to_string()
-        ),
+        Err(err) => JsValue::from_str(&with_schema_version(
+            &json!({"error": err.to_string(), "error_code": error_code(&err), "detected": "pdf"}).to_string()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use proptest::prelude::*;
+    use serde_json::Value;
+
+    fn options(json: &str) -> EstimateOptions {
+        let (options, warnings) = parse_options_checked(Some(json));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        options
+    }
+
+    fn entry_paths(estimate: &ArchiveEstimate) -> Vec<&str> {
+        estimate.entries.iter().map(|entry| entry.path.as_str()).collect()
+    }
+
+    /// Values a property schema of `options_schema` accepts.
+    fn schema_values(schema: &Value) -> BoxedStrategy<Value> {
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(kind) => vec![kind.as_str()],
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let value = if let Some(values) = schema["enum"].as_array() {
+            prop::sample::select(values.clone()).boxed()
+        } else if types.contains(&"boolean") {
+            any::<bool>().prop_map(Value::from).boxed()
+        } else if types.contains(&"integer") {
+            let min = schema["minimum"].as_u64().unwrap_or(0);
+            let max = schema["maximum"].as_u64().unwrap_or(min + 100_000);
+            (min..=max).prop_map(Value::from).boxed()
+        } else if types.contains(&"number") {
+            let min = schema["minimum"].as_f64().or(schema["exclusiveMinimum"].as_f64().map(|m| m + 1e-3));
+            let min = min.unwrap_or(-1e4);
+            let max = schema["maximum"].as_f64().unwrap_or(min + 1e4);
+            (min..=max).prop_map(Value::from).boxed()
+        } else if types.contains(&"string") {
+            // the generator has no anchors; a generated string is matched whole anyway
+            let pattern = schema["pattern"].as_str().map(|p| p.trim_start_matches('^').trim_end_matches('$'));
+            let strings = proptest::string::string_regex(pattern.unwrap_or("[a-zA-Z0-9 -]{0,12}")).unwrap();
+            strings.prop_map(Value::from).boxed()
+        } else if let Some(items) = schema["prefixItems"].as_array() {
+            (schema_values(&items[0]), schema_values(&items[1])).prop_map(|(a, b)| Value::from(vec![a, b])).boxed()
+        } else if types.contains(&"array") {
+            prop::collection::vec(schema_values(&schema["items"]), 0..4).prop_map(Value::from).boxed()
+        } else if types.contains(&"object") {
+            prop::collection::btree_map("[A-Za-z ]{1,8}", schema_values(&schema["additionalProperties"]), 0..3)
+                .prop_map(|map| Value::Object(map.into_iter().collect()))
+                .boxed()
+        } else {
+            panic!("no generator for {}", schema)
+        };
+        if types.contains(&"null") { prop_oneof![1 => Just(Value::Null), 3 => value].boxed() } else { value }
+    }
+
+    /// Option objects `options_schema` accepts, each property present or not.
+    fn schema_options() -> impl Strategy<Value = serde_json::Map<String, Value>> {
+        let schema = serde_json::to_value(schema_for!(EstimateOptions)).unwrap();
+        let properties: Vec<_> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, property)| {
+                let name = name.clone();
+                proptest::option::of(schema_values(property)).prop_map(move |value| (name.clone(), value))
+            })
+            .collect();
+        properties.prop_map(|pairs| pairs.into_iter().filter_map(|(name, value)| Some((name, value?))).collect())
+    }
+
+    #[test]
+    fn schema_lists_every_option() {
+        let schema = serde_json::to_value(schema_for!(EstimateOptions)).unwrap();
+        let mut listed: Vec<&String> = schema["properties"].as_object().unwrap().keys().collect();
+        let defaults = serde_json::to_value(EstimateOptions::default()).unwrap();
+        let mut fields: Vec<&String> = defaults.as_object().unwrap().keys().collect();
+        listed.sort();
+        fields.sort();
+        assert_eq!(listed, fields);
+    }
+
+    proptest! {
+        #[test]
+        fn schema_valid_options_pass_validation(mut object in schema_options()) {
+            // first <= last is the one rule of validate_options a JSON Schema can't state
+            if let Some(Value::Array(range)) = object.get_mut("page_range") {
+                range.sort_by_key(|page| page.as_u64());
+            }
+            let json = Value::Object(object);
+            let options: EstimateOptions = serde_json::from_value(json.clone()).unwrap();
+            prop_assert!(validate_options(&options).is_ok(), "{} rejected: {:?}", json, validate_options(&options));
+            let again: EstimateOptions = serde_json::from_value(serde_json::to_value(&options).unwrap()).unwrap();
+            prop_assert_eq!(again, options);
+        }
+    }
+
+    /// One document of each estimator, with its filename.
+    fn invariant_corpus() -> Vec<(Vec<u8>, &'static str)> {
+        vec![
+            (build_pdf(4, XrefStyle::Classic, true, false), "a.pdf"),
+            (build_mixed_orientation_pdf(), "mixed.pdf"),
+            (build_blank_pages_pdf(3), "blank.pdf"),
+            (build_docx(40, 2, Some(3)), "a.docx"),
+            (build_docx(40, 2, None), "b.docx"),
+            (build_xlsx(&[(120, 6), (0, 0), (10, 2)]), "a.xlsx"),
+            (build_pptx(5, 1), "a.pptx"),
+            (build_notebook(2), "a.ipynb"),
+            ("A line of plain text.\n".repeat(300).into_bytes(), "a.txt"),
+            (b"# Title\n\nSome *text*.\n\n- one\n- two\n".repeat(40), "a.md"),
+            (b"id,name,total\n1,alpha,3.5\n2,beta,4\n".repeat(80), "a.csv"),
+            (b"fn main() {\n    println!(\"hi\");\n}\n".repeat(50), "main.rs"),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(48))]
+
+        #[test]
+        fn estimates_keep_their_invariants_under_any_options(
+            object in schema_options(),
+            document in 0..12usize,
+        ) {
+            let options: EstimateOptions = serde_json::from_value(Value::Object(object)).unwrap();
+            let (bytes, filename) = &invariant_corpus()[document];
+            // estimate_bytes asserts the invariants itself in debug builds
+            if let (_, Ok(result)) = estimate_bytes(bytes, Some(filename), &options) {
+                prop_assert_eq!(result.validate_invariants(), Ok(()), "{} with {:?}", filename, options);
+            }
+        }
+    }
+
+    /// Position of an error's variant in `EstimatorError`; the match has no
+    /// wildcard, so a new variant fails to compile until the test lists it.
+    fn variant_index(err: &EstimatorError) -> usize {
+        use EstimatorError::*;
+        match err {
+            UnsupportedFormat => 0,
+            PdfError(_) => 1,
+            XlsxError(_) => 2,
+            DocxError(_) => 3,
+            PptxError(_) => 4,
+            XpsError(_) => 5,
+            PostScriptError(_) => 6,
+            ZipError(_) => 7,
+            XmlError(_) => 8,
+            EncryptedDocument => 9,
+            WrongPassword => 10,
+            EmptyInput => 11,
+            TooLarge { .. } => 12,
+            NetworkError(_) => 13,
+            HttpError { .. } => 14,
+            InvalidOptions(_) => 15,
+            CustomEstimatorError(_) => 16,
+            InvalidInput(_) => 17,
+            NotExported(_) => 18,
+            ParseBudgetExceeded { .. } => 19,
+            DeadlineExceeded { .. } => 20,
+            InvalidResult(_) => 21,
+            General(_) => 22,
+        }
+    }
+
+    #[test]
+    fn every_error_has_a_stable_code() {
+        let detail = || "detail".to_string();
+        let codes = [
+            (EstimatorError::UnsupportedFormat, "unsupported_format"),
+            (EstimatorError::PdfError(detail()), "pdf_error"),
+            (EstimatorError::XlsxError(detail()), "xlsx_error"),
+            (EstimatorError::DocxError(detail()), "docx_error"),
+            (EstimatorError::PptxError(detail()), "pptx_error"),
+            (EstimatorError::XpsError(detail()), "xps_error"),
+            (EstimatorError::PostScriptError(detail()), "postscript_error"),
+            (EstimatorError::ZipError(detail()), "zip_error"),
+            (EstimatorError::XmlError(detail()), "xml_error"),
+            (EstimatorError::EncryptedDocument, "encrypted_document"),
+            (EstimatorError::WrongPassword, "wrong_password"),
+            (EstimatorError::EmptyInput, "empty_input"),
+            (EstimatorError::TooLarge { limit: 10, actual: 11 }, "too_large"),
+            (EstimatorError::NetworkError(detail()), "network_error"),
+            (EstimatorError::HttpError { status: 404 }, "http_error"),
+            (EstimatorError::InvalidOptions(detail()), "invalid_options"),
+            (EstimatorError::CustomEstimatorError(detail()), "custom_estimator_error"),
+            (EstimatorError::InvalidInput(detail()), "invalid_input"),
+            (EstimatorError::NotExported(detail()), "not_exported"),
+            (EstimatorError::ParseBudgetExceeded { budget: 100, pages_found: 2 }, "parse_budget_exceeded"),
+            (EstimatorError::DeadlineExceeded { deadline_ms: 50 }, "deadline_exceeded"),
+            (EstimatorError::InvalidResult(detail()), "invalid_result"),
+            (EstimatorError::General(detail()), "general"),
+        ];
+        let indices: Vec<usize> = codes.iter().map(|(err, _)| variant_index(err)).collect();
+        assert_eq!(indices, (0..codes.len()).collect::<Vec<_>>(), "every variant, once, in declaration order");
+        for (err, code) in &codes {
+            assert_eq!(error_code(err), *code);
+            let message = err.to_string();
+            assert!(!message.is_empty() && !message.contains("{"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn failures_report_the_precise_error() {
+        let no_document = build_zip(&[("[Content_Types].xml", b"<Types/>"), ("word/styles.xml", b"<w:styles/>")]);
+        let no_slides = build_zip(&[("[Content_Types].xml", b"<Types/>"), ("ppt/theme.xml", b"<a:theme/>")]);
+        let cases: [(&[u8], &str, &str); 8] = [
+            (b"", "empty.pdf", "empty_input"),
+            (b"not a zip", "report.docx", "zip_error"),
+            (b"not a zip", "deck.pptx", "zip_error"),
+            (&no_document, "report.docx", "docx_error"),
+            (&no_slides, "deck.pptx", "pptx_error"),
+            (&build_encrypted_office_package(), "report.docx", "encrypted_document"),
+            (b"%PDF-1.4\n%%EOF", "scan.pdf", "pdf_error"),
+            (b"garbage\x00\x01\x02", "blob.bin", "unsupported_format"),
+        ];
+        for (bytes, name, code) in cases {
+            let (_, result) = estimate_bytes(bytes, Some(name), &EstimateOptions::default());
+            assert_eq!(error_code(&result.unwrap_err()), code, "{}", name);
+        }
+
+        let limited = options(r#"{"archive_max_entry_bytes":10}"#);
+        let (_, result) = estimate_bytes(&gzip(&[b'a'; 1000], 1), Some("notes.txt.gz"), &limited);
+        assert!(matches!(result, Err(EstimatorError::TooLarge { limit: 10, .. })));
+
+        let zero = EstimateOptions { chars_per_page: Some(0), ..Default::default() };
+        let body = estimate_to_json(b"text", Some("a.txt"), &zero, &[]).unwrap_err();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error_code"], "invalid_options");
+    }
+
+    #[test]
+    fn outputs_start_with_the_schema_version() {
+        let prefix = format!("{{\"schema_version\":{},", SCHEMA_VERSION);
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let outputs = [
+            estimate_to_json(&pdf, Some("a.pdf"), &EstimateOptions::default(), &[]).unwrap(),
+            estimate_to_json(&pdf, None, &options(r#"{"validate_only": true}"#), &[]).unwrap(),
+            estimate_to_json(b"", Some("a.pdf"), &EstimateOptions::default(), &[]).unwrap_err(),
+            to_versioned_json(&estimate_archive_bytes(&build_zip(&[("a.pdf", &pdf)]), &Default::default()).1.unwrap())
+                .unwrap(),
+        ];
+        for output in outputs {
+            assert!(output.starts_with(&prefix), "{}", output);
+        }
+    }
+
+    #[test]
+    fn stored_results_of_every_version_are_parsed() {
+        let v1 = parse_result(V1_RESULT_JSON).unwrap();
+        assert_eq!(v1.page_count, 2);
+        assert_eq!(v1.page_sizes[1].width_mm, 215.9);
+        assert_eq!(v1.effective_options.as_ref().unwrap().pages_per_sheet, Some(1));
+
+        // the current output round-trips, and upgraded v1 results are written as current
+        let pdf = build_pdf(3, XrefStyle::Stream, false, false);
+        let current = estimate_to_json(&pdf, Some("a.pdf"), &EstimateOptions::default(), &[]).unwrap();
+        let parsed = parse_result(&current).unwrap();
+        assert_eq!(to_versioned_json(&parsed).unwrap(), current);
+        let upgraded = to_versioned_json(&v1).unwrap();
+        assert_eq!(parse_result(&upgraded).unwrap().page_count, 2);
+        assert!(upgraded.starts_with(&format!("{{\"schema_version\":{},", SCHEMA_VERSION)));
+    }
+
+    #[test]
+    fn unreadable_results_are_invalid_results() {
+        let newer = V1_RESULT_JSON.replacen('{', &format!("{{\"schema_version\":{},", SCHEMA_VERSION + 1), 1);
+        let error = estimate_to_json(b"", Some("a.pdf"), &EstimateOptions::default(), &[]).unwrap_err();
+        let cases = [
+            (newer.as_str(), "newer than this build reads"),
+            (&*V1_RESULT_JSON.replacen('{', "{\"schema_version\":0,", 1), "positive integer"),
+            (&error, "error object"),
+            ("{\"page_count\":", "EOF"),
+            ("{\"page_count\":\"two\"}", "invalid type"),
+        ];
+        for (json, message) in cases {
+            let err = parse_result(json).unwrap_err();
+            assert_eq!(error_code(&err), "invalid_result");
+            assert!(err.to_string().contains(message), "{}: {}", json, err);
+        }
+    }
+
+    #[test]
+    fn error_json_carries_message_code_and_detected_type() {
+        let body = estimate_to_json(b"", Some("empty.pdf"), &EstimateOptions::default(), &[]).unwrap_err();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"], "Input is empty");
+        assert_eq!(body["error_code"], "empty_input");
+        assert_eq!(body["detected"], "pdf");
+        assert!(body["schema_version"].is_u64());
+    }
+
+    #[test]
+    fn base64_is_decoded_in_either_alphabet() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let engines = [
+            base64::engine::general_purpose::STANDARD,
+            base64::engine::general_purpose::STANDARD_NO_PAD,
+            base64::engine::general_purpose::URL_SAFE,
+            base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        ];
+        for engine in engines {
+            assert_eq!(decode_base64(&engine.encode(&bytes)).unwrap(), bytes);
+        }
+        let err = decode_base64("not base64!").unwrap_err();
+        assert_eq!(error_code(&err), "invalid_input");
+        assert!(err.to_string().contains("base64 decode failed"), "{}", err);
+        assert!(decode_base64("ab+c-d").is_err());
+    }
+
+    /// The options set in the `effective_options` of `bytes`' estimate, and its first note.
+    fn effective_options(bytes: &[u8], filename: &str, options_json: &str) -> (Value, Value) {
+        let (options, warnings) = parse_options_checked(Some(options_json));
+        let json: Value = serde_json::from_str(&estimate_to_json(bytes, Some(filename), &options, &warnings).unwrap())
+            .unwrap();
+        let mut effective = json["effective_options"].as_object().unwrap().clone();
+        effective.retain(|_, value| !value.is_null());
+        (Value::Object(effective), json["notes"][0].clone())
+    }
+
+    /// Options every format resolves, as `effective_options` reports them by default.
+    fn resolved_defaults() -> serde_json::Map<String, Value> {
+        let Value::Object(defaults) = serde_json::json!({
+            "allow_tiny_text": false, "analyze_color": false, "archive_include_system_entries": false,
+            "archive_max_entry_bytes": 104857600, "archive_recurse_nested": false, "debug": false,
+            "detect_language": false, "duplex": false, "estimate_attachments": false,
+            "exclude_blank_pages": false, "extended_pdf_info": false, "extract_pdf_text_metrics": false,
+            "extract_text_metrics": false, "include_page_sizes": true, "include_point_sizes": false,
+            "pages_per_sheet": 1, "reading_wpm": 200, "return_boundaries": false,
+            "split_oversized_pages": false, "validate_only": false, "verbosity": 1,
+        }) else {
+            unreachable!()
+        };
+        defaults
+    }
+
+    fn with_defaults(resolved: Value) -> Value {
+        let mut options = resolved_defaults();
+        options.extend(resolved.as_object().unwrap().clone());
+        Value::Object(options)
+    }
+
+    #[test]
+    fn effective_options_snapshots() {
+        let (text, note) = effective_options(b"Some plain text.", "a.txt", "{}");
+        let expected = serde_json::json!({
+            "chars_per_page": 1800, "custom_paper_mm": [210.0, 297.0], "default_paper": "A4", "rounding": "ceil",
+        });
+        assert_eq!(text, with_defaults(expected));
+        assert_eq!(note, "chars: 16, cells: 16, chars_per_page: 1800");
+
+        // an override is reported as given, and the locale picks the paper
+        let (text, _) = effective_options(b"Some plain text.", "a.txt", r#"{"chars_per_page":1200,"locale":"en-US"}"#);
+        let expected = serde_json::json!({
+            "chars_per_page": 1200, "custom_paper_mm": [215.9, 279.4], "default_paper": "Letter",
+            "locale": "en-US", "rounding": "ceil",
+        });
+        assert_eq!(text, with_defaults(expected));
+
+        // source code is laid out by lines, not characters
+        let (code, _) = effective_options(b"fn main() {}\n", "main.rs", r#"{"default_paper":"letter","duplex":true}"#);
+        let expected = serde_json::json!({
+            "chars_per_line": 100, "custom_paper_mm": [215.9, 279.4], "default_paper": "Letter", "duplex": true,
+            "line_spacing": 1.0, "lines_per_page": 60, "paragraph_spacing_lines": 0.0, "rounding": "ceil",
+            "skip_blank_lines": false, "tab_width": 8,
+        });
+        assert_eq!(code, with_defaults(expected));
+
+        // rows per page and margins come from the sheets, so they stay unset
+        let (xlsx, _) = effective_options(&build_xlsx(&[(10, 2)]), "b.xlsx", "{}");
+        let expected = serde_json::json!({
+            "custom_paper_mm": [210.0, 297.0], "default_paper": "A4", "rounding": "ceil",
+            "xlsx_min_pages_per_visible_sheet": 0,
+        });
+        assert_eq!(xlsx, with_defaults(expected));
+
+        // a custom size leaves the paper name unset, and unknown keys lead the notes
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let (pdf, note) = effective_options(&pdf, "c.pdf", r#"{"custom_paper_mm":[100,150],"chars_per_pag":3}"#);
+        let expected = serde_json::json!({"custom_paper_mm": [100.0, 150.0], "pdf_scan_budget_factor": 8});
+        assert_eq!(pdf, with_defaults(expected));
+        assert_eq!(note, "⚠ Unknown option 'chars_per_pag' ignored");
+    }
+
+    #[test]
+    fn unparsable_options_fall_back_to_the_defaults() {
+        let (options, warnings) = parse_options_checked(Some("{\"duplex\":"));
+        assert_eq!(options, EstimateOptions::default());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("⚠ Options could not be parsed ("), "{:?}", warnings);
+        assert_eq!(parse_options_checked(None), (EstimateOptions::default(), Vec::new()));
+        let (text, note) = effective_options(b"Some plain text.", "a.txt", "[1, 2]");
+        assert_eq!(text["chars_per_page"], 1800);
+        assert!(note.as_str().unwrap().starts_with("⚠ Options could not be parsed"), "{}", note);
+    }
+
+    #[test]
+    fn summary_snapshots() {
+        let text = "The press checked the order and will ship it by Friday. ".repeat(200);
+        let tex = format!(
+            "\\documentclass{{article}}\n\\begin{{document}}\n{}\n\\end{{document}}\n",
+            "Some text here. ".repeat(300)
+        );
+        let cases: [(Vec<u8>, &str, &str, &str); 14] = [
+            (build_pdf(42, XrefStyle::Classic, false, false), "a.pdf", "{}", "pdf pages=42 size=210x297mm warnings=1"),
+            (build_receipt_pdf(&[200.0, 400.0]), "r.pdf", "{}", "pdf pages=2 size=mixed warnings=1"),
+            (build_docx(30, 0, Some(4)), "a.docx", "{}", "docx pages=4 size=210x297mm warnings=0"),
+            (build_docx(300, 2, None), "b.docx", "{}", "docx pages=3 range=3-9 size=210x297mm warnings=1"),
+            (build_xlsx(&[(90, 3)]), "a.xlsx", "{}", "xlsx pages=3 range=2-4 size=210x297mm warnings=0"),
+            (build_pptx(6, 2), "a.pptx", "{}", "pptx pages=6 size=254x191mm warnings=0"),
+            (text.clone().into_bytes(), "a.txt", "{}", "txt pages=7 range=5-9 size=210x297mm warnings=0"),
+            (
+                format!("# T\n\n{}", text).into_bytes(),
+                "a.md",
+                r#"{"locale":"en-US"}"#,
+                "markdown pages=7 range=5-9 size=216x279mm warnings=0",
+            ),
+            (
+                "fn main() {}\n".repeat(100).into_bytes(),
+                "main.rs",
+                r#"{"include_page_sizes":false,"bogus":1}"#,
+                "code pages=2 range=1-3 method=code-lines warnings=1",
+            ),
+            (tex.into_bytes(), "paper.tex", "{}", "latex pages=2 range=1-3 size=210x297mm confidence=0.30 warnings=1"),
+            (
+                build_docbook_book(2, 2),
+                "book.xml",
+                "{}",
+                "docbook pages=7 range=5-9 size=210x297mm confidence=0.40 warnings=1",
+            ),
+            (build_xps(&[(210.0, 297.0), (297.0, 210.0)], false), "a.xps", "{}", "xps pages=2 size=mixed warnings=0"),
+            (Vec::new(), "e.pdf", "{}", "pdf error=empty_input"),
+            (b"%PDF-1.7\ngarbage".to_vec(), "g.pdf", "{}", "pdf error=pdf_error"),
+        ];
+        for (bytes, filename, options, expected) in cases {
+            let line = estimate_document_summary(&bytes, Some(filename.to_string()), Some(options.to_string()));
+            assert_eq!(line, expected, "{}", filename);
+            assert!(!line.contains('\n'));
+        }
+    }
+
+    #[test]
+    fn text_page_ranges_report_their_span() {
+        let text = "Chapter text that runs on for a while. ".repeat(200);
+        let all = estimate(text.as_bytes(), None, r#"{"chars_per_page":1000,"return_boundaries":true}"#).unwrap();
+        let boundaries = all.page_boundaries.unwrap();
+        assert_eq!(all.page_count, 8);
+
+        let range = estimate(text.as_bytes(), None, r#"{"chars_per_page":1000,"page_range":[2,4]}"#).unwrap();
+        assert_eq!((range.page_count, range.range_page_count), (8, Some(3)));
+        assert_eq!((range.range_char_start, range.range_char_end), (Some(boundaries[1]), Some(boundaries[4])));
+        assert_eq!(range.page_boundaries, None);
+        assert!(range.notes.contains(&"Range: pages 2–4 of 8".to_string()), "{:?}", range.notes);
+
+        // a single page, and a range clamped to the last page
+        let single = estimate(text.as_bytes(), None, r#"{"chars_per_page":1000,"page_range":[1,1]}"#).unwrap();
+        assert_eq!(single.range_page_count, Some(1));
+        assert_eq!((single.range_char_start, single.range_char_end), (Some(0), Some(boundaries[1])));
+        assert!(single.notes.contains(&"Range: page 1 of 8".to_string()), "{:?}", single.notes);
+        let tail = estimate(text.as_bytes(), None, r#"{"chars_per_page":1000,"page_range":[8,20]}"#).unwrap();
+        assert_eq!((tail.range_page_count, tail.range_char_end), (Some(1), Some(text.len())));
+        assert!(tail.notes.contains(&"Range: page 8 of 8".to_string()), "{:?}", tail.notes);
+
+        let markdown = format!("# Manuscript\n\n{}", text);
+        let range = estimate(markdown.as_bytes(), Some("book.md"), r#"{"page_range":[2,2]}"#).unwrap();
+        let span = range.range_char_start.unwrap()..range.range_char_end.unwrap();
+        assert!(span.start > 0 && span.end <= markdown.len() && markdown.is_char_boundary(span.start));
+    }
+
+    #[test]
+    fn exact_formats_clamp_page_ranges_without_a_span() {
+        let pdf = build_pdf(5, XrefStyle::Classic, false, false);
+        let range = estimate(&pdf, None, r#"{"page_range":[4,9]}"#).unwrap();
+        assert_eq!(range.range_page_count, Some(2));
+        assert_eq!((range.range_char_start, range.range_char_end), (None, None));
+        assert_eq!(estimate(&build_pptx(6, 0), None, r#"{"page_range":[6,6]}"#).unwrap().range_page_count, Some(1));
+
+        let Err(EstimatorError::InvalidOptions(message)) = estimate(&pdf, None, r#"{"page_range":[6,7]}"#) else {
+            panic!("a range past the last page was accepted");
+        };
+        assert_eq!(message, "page_range starts at page 6, but the document has 5 pages");
+        for range in ["[0,2]", "[3,2]"] {
+            let result = estimate(&pdf, None, &format!(r#"{{"page_range":{}}}"#, range));
+            let Err(EstimatorError::InvalidOptions(message)) = result else { panic!("{} was accepted", range) };
+            assert!(message.starts_with("page_range must be 1-based"), "{}", message);
+        }
+        let merged = EstimateResult::merge(&[range.clone(), range]);
+        assert_eq!(merged.range_page_count, None);
+    }
+
+    #[test]
+    fn archive_entries_are_estimated_in_order() {
+        let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        let docx = build_docx(10, 0, Some(4));
+        let zip = build_zip(&[
+            ("batch/", b""),
+            ("batch/report.pdf", &pdf),
+            ("batch/.hidden.txt", b"left out"),
+            ("batch/letter.docx", &docx),
+            ("batch/readme.txt", b"A short note that goes with the batch of documents."),
+        ]);
+        let (detected, result) = estimate_archive_bytes(&zip, &EstimateOptions::default());
+        let estimate = result.unwrap();
+        assert_eq!(detected, "zip");
+        assert_eq!(entry_paths(&estimate), ["batch/report.pdf", "batch/letter.docx", "batch/readme.txt"]);
+        let pages: Vec<usize> = estimate.entries.iter().map(|e| e.result.as_ref().unwrap().page_count).collect();
+        assert_eq!(pages, [3, 4, 1]);
+        assert_eq!(estimate.total_page_count, 8);
+        let hidden = "Skipped hidden entry 'batch/.hidden.txt'".to_string();
+        assert!(estimate.notes.contains(&hidden), "{:?}", estimate.notes);
+    }
+
+    #[test]
+    fn archive_entries_over_the_cap_and_failures_are_reported() {
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let big = "word ".repeat(2000);
+        let zip = build_zip(&[("big.txt", big.as_bytes()), ("broken.docx", b"PK\x03\x04 broken"), ("a.pdf", &pdf)]);
+        let (_, result) = estimate_archive_bytes(&zip, &options(r#"{"archive_max_entry_bytes":5000}"#));
+        let estimate = result.unwrap();
+        assert_eq!(entry_paths(&estimate), ["broken.docx", "a.pdf"]);
+        assert!(estimate.entries[0].error.is_some() && estimate.entries[0].error_code.is_some());
+        assert_eq!(estimate.total_page_count, 2);
+        assert!(estimate.notes[0].starts_with("Skipped 'big.txt': 10000 bytes exceeds"), "{:?}", estimate.notes);
+    }
+
+    #[test]
+    fn nested_archives_are_skipped_unless_recursion_is_on() {
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let inner = build_zip(&[("inner.pdf", &pdf), ("deeper.zip", &build_zip(&[("x.pdf", &pdf)]))]);
+        let outer = build_zip(&[("outer.pdf", &pdf), ("inner.zip", &inner)]);
+
+        let (_, result) = estimate_archive_bytes(&outer, &EstimateOptions::default());
+        let estimate = result.unwrap();
+        assert_eq!(entry_paths(&estimate), ["outer.pdf"]);
+        assert!(estimate.notes.contains(&"Skipped nested archive 'inner.zip'".to_string()));
+
+        let (_, result) = estimate_archive_bytes(&outer, &options(r#"{"archive_recurse_nested":true}"#));
+        let estimate = result.unwrap();
+        assert_eq!(entry_paths(&estimate), ["outer.pdf", "inner.zip/inner.pdf"]);
+        assert_eq!(estimate.total_page_count, 4);
+        // one level only
+        assert!(estimate.notes.contains(&"Skipped nested archive 'inner.zip/deeper.zip'".to_string()));
+    }
+
+    #[test]
+    fn system_entries_are_skipped_unless_asked_for() {
+        let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        let zip = to_finder_zip(&build_zip(&[
+            ("batch/report.pdf", &pdf),
+            ("batch/Thumbs.db", b"\xD0\xCF\x11\xE0"),
+            ("batch/notes.txt", b"Plain notes that go with the report."),
+        ]));
+        let (_, result) = estimate_archive_bytes(&zip, &EstimateOptions::default());
+        let estimate = result.unwrap();
+        assert_eq!(entry_paths(&estimate), ["batch/report.pdf", "batch/notes.txt"]);
+        assert_eq!(estimate.total_page_count, 4);
+        for path in [".DS_Store", "batch/.DS_Store", "__MACOSX/batch/._report.pdf", "batch/Thumbs.db"] {
+            let note = format!("Skipped system entry '{}'", path);
+            assert!(estimate.notes.contains(&note), "{:?}", estimate.notes);
+        }
+
+        let (_, result) = estimate_archive_bytes(&zip, &options(r#"{"archive_include_system_entries":true}"#));
+        let estimate = result.unwrap();
+        assert_eq!(estimate.entries.len(), 8);
+        assert!(entry_paths(&estimate).contains(&"__MACOSX/batch/._notes.txt"));
+        assert!(!estimate.notes.iter().any(|note| note.starts_with("Skipped system entry")), "{:?}", estimate.notes);
+    }
+
+    #[test]
+    fn archive_that_cannot_be_opened_is_an_error() {
+        let (_, result) = estimate_archive_bytes(b"PK\x03\x04 not really", &EstimateOptions::default());
+        assert!(matches!(result, Err(EstimatorError::ZipError(_))));
+    }
+
+    #[test]
+    fn gzipped_tar_entries_are_estimated() {
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let tar = build_tar(&[("docs/a.pdf", &pdf), ("docs/notes.txt", b"Plain notes about the PDF.")]);
+        let (detected, result) = estimate_archive_bytes(&gzip(&tar, 1), &EstimateOptions::default());
+        let estimate = result.unwrap();
+        assert_eq!(detected, "gzip");
+        assert_eq!(entry_paths(&estimate), ["docs/a.pdf", "docs/notes.txt"]);
+        assert_eq!(estimate.total_page_count, 3);
+
+        let (_, result) = estimate_archive_bytes(&gzip(&pdf, 1), &EstimateOptions::default());
+        assert!(matches!(result, Err(EstimatorError::General(_))));
+    }
+
+    #[test]
+    fn gzipped_documents_are_estimated_as_their_content() {
+        let markdown = "# Notes\n\nA paragraph of markdown.\n".repeat(40);
+        let plain = estimate(markdown.as_bytes(), Some("notes.md"), "{}").unwrap();
+        let gzipped = gzip(markdown.as_bytes(), 1);
+        let (detected, result) = estimate_bytes(&gzipped, Some("notes.md.gz"), &EstimateOptions::default());
+        let result = result.unwrap();
+        assert_eq!(detected, "markdown");
+        assert_eq!(result.page_count, plain.page_count);
+        assert_eq!(result.notes.last().unwrap(), &format!("Decompressed gzip input ({} bytes)", markdown.len()));
+
+        let twice = estimate(&gzip(markdown.as_bytes(), 2), Some("notes.md.gz.gz"), "{}").unwrap();
+        assert_eq!(twice.page_count, plain.page_count);
+    }
+
+    #[test]
+    fn locators_are_refused_unless_tiny_text_is_allowed() {
+        let cases = [
+            ("https://example.com/file.pdf", "the input is a URL"),
+            ("C:\\Users\\ada\\report.docx", "the input is a file path"),
+        ];
+        for (text, message) in cases {
+            let (detected, result) = estimate_bytes(text.as_bytes(), None, &EstimateOptions::default());
+            assert_eq!(detected, "txt");
+            let err = result.unwrap_err();
+            assert_eq!(error_code(&err), "invalid_input");
+            assert!(err.to_string().contains(message) && err.to_string().contains("estimate_url"), "{}", err);
+        }
+
+        // a genuine one-line text file
+        let line = b"/etc/hosts.txt";
+        let allowed = estimate(line, Some("snippet.txt"), r#"{"allow_tiny_text": true}"#).unwrap();
+        assert_eq!(allowed.page_count, 1);
+        assert!(estimate(line, Some("snippet.txt"), "{}").is_err());
+        let zip = build_zip(&[("link.txt", b"https://example.com/file.pdf".as_slice())]);
+        let archive = estimate_archive_bytes(&zip, &EstimateOptions::default()).1.unwrap();
+        assert_eq!(archive.entries[0].result.as_ref().map(|result| result.page_count), Some(1));
+    }
+
+    #[test]
+    fn deeply_nested_gzip_is_refused() {
+        let pdf = build_pdf(1, XrefStyle::Classic, false, false);
+        let (_, result) = estimate_bytes(&gzip(&pdf, MAX_GZIP_LAYERS), None, &EstimateOptions::default());
+        assert_eq!(result.unwrap().page_count, 1);
+
+        // each layer used to be a stack frame
+        for layers in [MAX_GZIP_LAYERS + 1, 3000] {
+            let (detected, result) = estimate_bytes(&gzip(&pdf, layers), None, &EstimateOptions::default());
+            assert_eq!(detected, "gzip");
+            let err = result.unwrap_err();
+            assert_eq!(error_code(&err), "invalid_input");
+            assert!(err.to_string().contains("compressed more than 4 times over"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn email_attachments_are_estimated_separately() {
+        let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        let docx = build_docx(10, 0, Some(2));
+        let body = "Hello,\n\nthe signed copies are attached.\n";
+        let eml = build_email(body, &[("signed.pdf", "application/pdf", &pdf), ("", "application/pdf", &pdf)], true);
+        let (detected, result) = estimate_archive_bytes(&eml, &EstimateOptions::default());
+        assert_eq!(detected, "eml");
+        let archive = result.unwrap();
+        assert_eq!(entry_paths(&archive), ["(message body)", "signed.pdf", "attachment-2.pdf"]);
+        let pages: Vec<usize> = archive.entries.iter().map(|e| e.result.as_ref().unwrap().page_count).collect();
+        assert_eq!(pages, [1, 3, 3]);
+        assert_eq!(archive.total_page_count, 7);
+
+        let docx_type = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+        let eml = build_email(body, &[("letter.docx", docx_type, &docx)], false);
+        let combined = estimate(&eml, Some("intake.eml"), "{}").unwrap();
+        assert_eq!(combined.page_count, 3);
+        assert_eq!(combined.notes[..2], ["(message body): 1 pages", "letter.docx: 2 pages"]);
+    }
+
+    #[test]
+    fn plain_zip_is_not_estimated_as_a_document() {
+        let zip = build_zip(&[("a.txt", b"some text in an archive")]);
+        assert_eq!(detect_type(None, &zip), "zip");
+        assert!(estimate(&zip, None, "{}").is_err());
     }
 }
@@ -0,0 +1,301 @@
+//! # Estimate Cache
+//!
+//! An opt-in, in-module cache of estimate results, used by
+//! `estimate_document_cached`. Interfaces that re-estimate the same file
+//! whenever an unrelated setting changes can call it instead of
+//! `estimate_document` to skip repeated parsing.
+//!
+//! Entries are keyed by a 64-bit XXH64 hash of the document bytes together
+//! with the filename and the canonical (re-serialized) options. The full
+//! options and filename are stored and compared as well, so a hash collision
+//! or different options for the same bytes can never return a wrong result.
+//!
+//! ## Eviction
+//!
+//! The cache holds at most `max_entries` results (default 16) and at most
+//! `max_bytes` of result JSON (default 4 MB), evicting the least recently used
+//! entries first. Only successful estimates are cached, and not those cut short
+//! by `deadline_ms`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Default maximum number of cached results.
+pub const DEFAULT_CACHE_ENTRIES: usize = 16;
+
+/// Default maximum total size of the cached result JSON, in bytes.
+pub const DEFAULT_CACHE_BYTES: usize = 4 * 1024 * 1024;
+
+thread_local! {
+    static ESTIMATE_CACHE: RefCell<EstimateCache> = RefCell::new(EstimateCache::default());
+}
+
+/// Identifies a cached estimate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheKey {
+    /// XXH64 hash of the document bytes.
+    pub content_hash: u64,
+    /// Length of the document in bytes.
+    pub content_len: usize,
+    /// Filename hint passed with the document.
+    pub filename: Option<String>,
+    /// Canonical JSON of the estimation options.
+    pub options: String,
+}
+
+impl CacheKey {
+    /// Builds the key for estimating `bytes` with the given filename and canonical options JSON.
+    pub fn new(bytes: &[u8], filename: Option<&str>, options: String) -> Self {
+        Self { content_hash: xxh64(bytes, 0), content_len: bytes.len(), filename: filename.map(str::to_string), options }
+    }
+}
+
+/// Least-recently-used cache of result JSON strings.
+#[derive(Debug)]
+pub struct EstimateCache {
+    /// Entries from least to most recently used.
+    entries: VecDeque<(CacheKey, String)>,
+    max_entries: usize,
+    max_bytes: usize,
+    total_bytes: usize,
+}
+
+impl Default for EstimateCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_ENTRIES, DEFAULT_CACHE_BYTES)
+    }
+}
+
+impl EstimateCache {
+    /// Creates an empty cache with the given limits.
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self { entries: VecDeque::new(), max_entries, max_bytes, total_bytes: 0 }
+    }
+
+    /// Returns the cached JSON for `key`, marking it as most recently used.
+    pub fn get(&mut self, key: &CacheKey) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index)?;
+        let json = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(json)
+    }
+
+    /// Stores the JSON for `key`, evicting least recently used entries to stay within the limits.
+    ///
+    /// Results larger than `max_bytes` on their own are not stored.
+    pub fn insert(&mut self, key: CacheKey, json: String) {
+        if let Some(index) = self.entries.iter().position(|(k, _)| *k == key)
+            && let Some((_, old)) = self.entries.remove(index)
+        {
+            self.total_bytes -= old.len();
+        }
+        if json.len() > self.max_bytes || self.max_entries == 0 {
+            return;
+        }
+        self.total_bytes += json.len();
+        self.entries.push_back((key, json));
+        self.evict();
+    }
+
+    /// Changes the limits, evicting entries that no longer fit.
+    pub fn set_limits(&mut self, max_entries: usize, max_bytes: usize) {
+        self.max_entries = max_entries;
+        self.max_bytes = max_bytes;
+        self.evict();
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.total_bytes = 0;
+    }
+
+    /// Number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no results.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total size of the cached JSON, in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Maximum number of cached results.
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Maximum total size of the cached JSON, in bytes.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.max_entries || self.total_bytes > self.max_bytes {
+            let Some((_, json)) = self.entries.pop_front() else { break };
+            self.total_bytes -= json.len();
+        }
+    }
+}
+
+/// Runs `f` with the module's estimate cache.
+pub fn with_estimate_cache<T>(f: impl FnOnce(&mut EstimateCache) -> T) -> T {
+    ESTIMATE_CACHE.with(|cache| f(&mut cache.borrow_mut()))
+}
+
+/// Sets the limits of the cache used by `estimate_document_cached`.
+///
+/// # Parameters
+///
+/// * `max_entries` - Maximum number of cached results (default 16; 0 disables caching)
+///
+/// * `max_bytes` - Optional maximum total size of the cached result JSON in bytes
+///                 (default 4 MB)
+#[wasm_bindgen]
+pub fn configure_estimate_cache(max_entries: usize, max_bytes: Option<usize>) {
+    with_estimate_cache(|cache| cache.set_limits(max_entries, max_bytes.unwrap_or(DEFAULT_CACHE_BYTES)));
+}
+
+/// Removes every result cached by `estimate_document_cached`.
+#[wasm_bindgen]
+pub fn clear_estimate_cache() {
+    with_estimate_cache(EstimateCache::clear);
+}
+
+const PRIME64_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME64_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME64_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME64_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME64_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+/// Computes the XXH64 hash of `input`.
+///
+/// A fast, non-cryptographic hash; it identifies cache entries and is not
+/// suitable where collisions must be hard to produce.
+pub fn xxh64(input: &[u8], seed: u64) -> u64 {
+    let mut chunks = input.chunks_exact(32);
+    let mut hash = if input.len() >= 32 {
+        let mut acc = [
+            seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+            seed.wrapping_add(PRIME64_2),
+            seed,
+            seed.wrapping_sub(PRIME64_1),
+        ];
+        for chunk in &mut chunks {
+            for (lane, word) in acc.iter_mut().zip(chunk.chunks_exact(8)) {
+                *lane = xxh64_round(*lane, read_u64(word));
+            }
+        }
+        let mut hash = acc[0]
+            .rotate_left(1)
+            .wrapping_add(acc[1].rotate_left(7))
+            .wrapping_add(acc[2].rotate_left(12))
+            .wrapping_add(acc[3].rotate_left(18));
+        for lane in acc {
+            hash = (hash ^ xxh64_round(0, lane)).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+        }
+        hash
+    } else {
+        seed.wrapping_add(PRIME64_5)
+    };
+    hash = hash.wrapping_add(input.len() as u64);
+
+    let mut rest = chunks.remainder();
+    while rest.len() >= 8 {
+        hash ^= xxh64_round(0, read_u64(&rest[..8]));
+        hash = hash.rotate_left(27).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        hash ^= u64::from(u32::from_le_bytes(rest[..4].try_into().unwrap_or_default())).wrapping_mul(PRIME64_1);
+        hash = hash.rotate_left(23).wrapping_mul(PRIME64_2).wrapping_add(PRIME64_3);
+        rest = &rest[4..];
+    }
+    for &byte in rest {
+        hash ^= u64::from(byte).wrapping_mul(PRIME64_5);
+        hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ (hash >> 32)
+}
+
+fn xxh64_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME64_2)).rotate_left(31).wrapping_mul(PRIME64_1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str, options: &str) -> CacheKey {
+        CacheKey::new(b"%PDF-1.7", Some(name), options.to_string())
+    }
+
+    #[test]
+    fn xxh64_matches_the_reference_vectors() {
+        assert_eq!(xxh64(b"", 0), 0xEF46_DB37_51D8_E999);
+        assert_eq!(xxh64(b"a", 0), 0xD24E_C4F1_A98C_6E5B);
+        assert_eq!(xxh64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+        assert_eq!(xxh64(b"Nobody inspects the spammish repetition", 0), 0xFBCE_A83C_8A37_8BF1);
+        assert_ne!(xxh64(b"abc", 1), xxh64(b"abc", 0));
+    }
+
+    #[test]
+    fn keys_tell_options_and_filenames_apart() {
+        assert_eq!(key("a.pdf", "{}"), key("a.pdf", "{}"));
+        assert_ne!(key("a.pdf", "{}"), key("a.pdf", r#"{"analyze_color":true}"#));
+        assert_ne!(key("a.pdf", "{}"), key("b.pdf", "{}"));
+        assert_ne!(key("a.pdf", "{}"), CacheKey::new(b"%PDF-1.6", Some("a.pdf"), "{}".into()));
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_first() {
+        let mut cache = EstimateCache::new(2, 1024);
+        cache.insert(key("a.pdf", "{}"), "a".into());
+        cache.insert(key("b.pdf", "{}"), "b".into());
+        assert_eq!(cache.get(&key("a.pdf", "{}")).as_deref(), Some("a"));
+        cache.insert(key("c.pdf", "{}"), "c".into());
+        assert_eq!(cache.get(&key("b.pdf", "{}")), None);
+        assert_eq!((cache.len(), cache.total_bytes()), (2, 2));
+
+        // storing a key again replaces its result
+        cache.insert(key("c.pdf", "{}"), "cc".into());
+        assert_eq!((cache.len(), cache.total_bytes()), (2, 3));
+        assert_eq!(cache.get(&key("c.pdf", "{}")).as_deref(), Some("cc"));
+    }
+
+    #[test]
+    fn total_size_limits_the_cache() {
+        let mut cache = EstimateCache::new(16, 10);
+        cache.insert(key("a.pdf", "{}"), "aaaa".into());
+        cache.insert(key("b.pdf", "{}"), "bbbb".into());
+        cache.insert(key("c.pdf", "{}"), "cccc".into());
+        assert_eq!(cache.get(&key("a.pdf", "{}")), None);
+        assert_eq!((cache.len(), cache.total_bytes()), (2, 8));
+        cache.insert(key("d.pdf", "{}"), "d".repeat(11));
+        assert_eq!(cache.get(&key("d.pdf", "{}")), None);
+
+        cache.set_limits(1, 10);
+        assert_eq!(cache.get(&key("c.pdf", "{}")).as_deref(), Some("cccc"));
+        assert_eq!(cache.len(), 1);
+        cache.set_limits(0, 10);
+        cache.insert(key("e.pdf", "{}"), "e".into());
+        assert!(cache.is_empty());
+        assert_eq!(cache.total_bytes(), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! # Comic Book Archives
+//!
+//! A CBZ file is a ZIP of page images, read in natural filename order
+//! (`page2.jpg` before `page10.jpg`). An optional `ComicInfo.xml` carries a
+//! `<PageCount>` written by the tagging tool, which is treated as
+//! authoritative when present.
+//!
+//! ## Page images
+//!
+//! Entries with an image extension count as pages, except directories,
+//! hidden entries (including `__MACOSX` resource forks), and thumbnails
+//! (file names containing `thumb`).
+
+use crate::file_utils::{is_system_entry, note_zip_entry};
+use quick_xml::events::Event;
+use quick_xml::Reader as XmlReader;
+use std::cmp::Ordering;
+use std::io::{BufReader, Cursor};
+use zip::ZipArchive;
+
+/// Filename extensions of page images.
+pub const PAGE_IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+
+/// Extensions of metadata files that may accompany the images.
+const METADATA_EXTENSIONS: &[&str] = &[".xml", ".txt", ".nfo", ".sfv", ".json"];
+
+/// Share of (non-metadata) entries that must be page images for a ZIP to be detected as a CBZ.
+const COMIC_IMAGE_SHARE: f64 = 0.9;
+
+/// Checks whether an entry name is a page image.
+pub fn is_page_image(name: &str) -> bool {
+    if name.ends_with('/') || is_hidden(name) {
+        return false;
+    }
+    let file = name.rsplit('/').next().unwrap_or(name).to_lowercase();
+    let Some((stem, ext)) = file.rsplit_once('.') else { return false };
+    PAGE_IMAGE_EXTENSIONS.contains(&ext) && !stem.contains("thumb")
+}
+
+fn is_hidden(name: &str) -> bool {
+    is_system_entry(name) || name.split('/').any(|part| part.starts_with('.'))
+}
+
+/// Lists the page images of a comic archive in natural order.
+pub fn page_images(archive: &ZipArchive<Cursor<&[u8]>>) -> Vec<String> {
+    let mut pages: Vec<String> = archive.file_names().filter(|n| is_page_image(n)).map(str::to_string).collect();
+    pages.sort_by(|a, b| natural_cmp(a, b));
+    pages
+}
+
+/// Checks whether a ZIP (that is not an Office or XPS package) holds a comic.
+///
+/// At least two entries must be page images, and they must make up
+/// `COMIC_IMAGE_SHARE` of the files, not counting directories, hidden
+/// entries, thumbnails, and metadata files.
+pub fn looks_like_comic(archive: &ZipArchive<Cursor<&[u8]>>) -> bool {
+    let mut images = 0usize;
+    let mut others = 0usize;
+    for name in archive.file_names() {
+        let lower = name.to_lowercase();
+        if is_page_image(name) {
+            images += 1;
+        } else if !(name.ends_with('/')
+            || is_hidden(name)
+            || lower.contains("thumb")
+            || METADATA_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)))
+        {
+            others += 1;
+        }
+    }
+    images >= 2 && images as f64 >= (images + others) as f64 * COMIC_IMAGE_SHARE
+}
+
+/// Reads `<PageCount>` from the archive's `ComicInfo.xml`, if there is one.
+pub fn read_comic_info_page_count(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<usize> {
+    let name = archive.file_names().find(|n| n.eq_ignore_ascii_case("ComicInfo.xml"))?.to_string();
+    let file = archive.by_name(&name).ok()?;
+    note_zip_entry(&file);
+    let mut reader = XmlReader::from_reader(BufReader::new(file));
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut in_page_count = false;
+    loop {
+        match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(ref e) => in_page_count = e.local_name().as_ref() == b"PageCount",
+            Event::Text(ref e) if in_page_count => {
+                return e.unescape().ok()?.trim().parse().ok().filter(|n| *n > 0);
+            }
+            Event::End(_) => in_page_count = false,
+            Event::Eof => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Compares file names so that runs of digits sort by numeric value.
+///
+/// Letters compare case-insensitively; ties are broken by plain byte order so
+/// the ordering is total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a.as_bytes(), b.as_bytes());
+    while let (Some(&ca), Some(&cb)) = (x.first(), y.first()) {
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let (da, ra) = split_digits(x);
+            let (db, rb) = split_digits(y);
+            // compare numerically without parsing: strip leading zeros, then length, then digits
+            let (ta, tb) = (trim_zeros(da), trim_zeros(db));
+            let order = ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb));
+            if order != Ordering::Equal {
+                return order;
+            }
+            (x, y) = (ra, rb);
+        } else {
+            let order = ca.to_ascii_lowercase().cmp(&cb.to_ascii_lowercase());
+            if order != Ordering::Equal {
+                return order;
+            }
+            (x, y) = (&x[1..], &y[1..]);
+        }
+    }
+    x.len().cmp(&y.len()).then_with(|| a.cmp(b))
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let n = s.iter().take_while(|b| b.is_ascii_digit()).count();
+    s.split_at(n)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let n = digits.iter().take_while(|b| **b == b'0').count();
+    &digits[n..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_utils::detect_type;
+    use crate::fixtures::*;
+    use crate::schema::{EstimatorError, PageSizeMm};
+
+    fn archive(bytes: &[u8]) -> ZipArchive<Cursor<&[u8]>> {
+        ZipArchive::new(Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn page_images_in_natural_order() {
+        let cbz = build_cbz(12, None);
+        let pages = page_images(&archive(&cbz));
+        let expected: Vec<String> = (1..=12).map(|n| format!("Comic/page{}.png", n)).collect();
+        assert_eq!(pages, expected);
+        assert_eq!(natural_cmp("page2.png", "page10.png"), Ordering::Less);
+        assert_eq!(natural_cmp("Page002.png", "page2.png"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a"), Ordering::Equal);
+    }
+
+    #[test]
+    fn counted_from_images_with_the_first_page_size() {
+        let result = estimate(&build_cbz(12, None), Some("issue.cbz"), "{}").unwrap();
+        assert_eq!(result.page_count, 12);
+        let page = PageSizeMm { width_mm: 1200.0 / 96.0 * 25.4, height_mm: 1800.0 / 96.0 * 25.4 };
+        assert_eq!(result.page_sizes, vec![page; 12]);
+    }
+
+    #[test]
+    fn comic_info_page_count_is_authoritative() {
+        let cbz = build_cbz(12, Some(14));
+        assert_eq!(read_comic_info_page_count(&mut archive(&cbz)), Some(14));
+        let result = estimate(&cbz, Some("issue.cbz"), "{}").unwrap();
+        assert_eq!(result.page_count, 14);
+        assert!(result.notes.iter().any(|n| n.contains("differs from the 12 page images")), "{:?}", result.notes);
+
+        let agreeing = estimate(&build_cbz(12, Some(12)), Some("issue.cbz"), "{}").unwrap();
+        assert!(!agreeing.notes.iter().any(|n| n.starts_with('⚠')), "{:?}", agreeing.notes);
+    }
+
+    #[test]
+    fn detected_from_extension_or_contents() {
+        let cbz = build_cbz(3, None);
+        assert_eq!(detect_type(Some("issue.cbz"), &cbz), "cbz");
+        assert_eq!(detect_type(None, &cbz), "cbz");
+        assert!(looks_like_comic(&archive(&cbz)));
+
+        let batch = build_zip(&[("a.png", &png_header(10, 10)), ("b.png", &png_header(10, 10)), ("c.pdf", b"%PDF")]);
+        assert!(!looks_like_comic(&archive(&batch)));
+        assert_ne!(detect_type(None, &batch), "cbz");
+    }
+
+    #[test]
+    fn empty_comic_and_rar_are_rejected() {
+        let empty = build_zip(&[("ComicInfo.xml", b"<ComicInfo/>")]);
+        assert!(matches!(estimate(&empty, Some("issue.cbz"), "{}"), Err(EstimatorError::ZipError(_))));
+
+        let cbr = b"Rar!\x1a\x07\x01\x00 rest of the archive";
+        assert_eq!(detect_type(Some("issue.cbr"), cbr), "rar");
+        let err = estimate(cbr, Some("issue.cbr"), "{}").unwrap_err();
+        assert!(err.to_string().contains("RAR archives (including .cbr comics) are not supported"), "{}", err);
+    }
+}
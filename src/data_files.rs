@@ -0,0 +1,298 @@
+//! # Data Files
+//!
+//! Recognizes JSON, YAML, and XML files (configuration, API payloads) so they
+//! are paginated like source code, line by line in a monospace font, instead
+//! of with the prose heuristic, which undercounts their short lines.
+//!
+//! ## Detection
+//!
+//! Files are routed by extension (`.json`, `.yaml`, `.yml`, `.xml`). Without a
+//! filename, the first KB is sniffed:
+//!
+//! - JSON: starts with `{` or `[` and tokenizes as JSON up to the end of the sample
+//! - XML: starts with an `<?xml` declaration
+//! - YAML: a `---` document marker, or mostly `key: value` and `- item` lines
+//!
+//! ## Minified Data
+//!
+//! Minified JSON and XML is one very long line, which the line model counts
+//! by wrapping. With `pretty_print_minified`, it is laid out as if
+//! pretty-printed with 2-space indentation instead: one line per member,
+//! array item, or element.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// How much of the input is sniffed for a data format.
+pub const DATA_SNIFF_BYTES: usize = 1024;
+
+/// Share of sampled lines that must look like YAML for the content to be taken as YAML.
+const YAML_LINE_SHARE: f64 = 0.8;
+
+/// Fewest sampled lines YAML is recognized from without a `---` marker.
+const YAML_MIN_LINES: usize = 3;
+
+/// Average line length above which JSON or XML counts as minified.
+const MINIFIED_LINE_CHARS: usize = 200;
+
+/// The JSON literal names.
+const JSON_LITERALS: [&str; 3] = ["true", "false", "null"];
+
+/// Indentation of pretty-printed data.
+const INDENT: &str = "  ";
+
+/// A data file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataKind {
+    Json,
+    Yaml,
+    Xml,
+}
+
+impl DataKind {
+    /// Display name used in notes (`"JSON"`, `"YAML"`, `"XML"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            DataKind::Json => "JSON",
+            DataKind::Yaml => "YAML",
+            DataKind::Xml => "XML",
+        }
+    }
+}
+
+/// Recognizes a data file from its first KB.
+///
+/// # Returns
+///
+/// The format, or `None` when the content doesn't look like JSON, XML, or YAML.
+pub fn sniff_data(bytes: &[u8]) -> Option<DataKind> {
+    let sample = &bytes[..bytes.len().min(DATA_SNIFF_BYTES)];
+    // a multi-byte character may be cut at the end of the sample
+    let sample = match std::str::from_utf8(sample) {
+        Ok(text) => text,
+        Err(err) => std::str::from_utf8(&sample[..err.valid_up_to()]).ok()?,
+    };
+    let trimmed = sample.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with(['{', '[']) {
+        return json_prefix_is_valid(trimmed).then_some(DataKind::Json);
+    }
+    if trimmed.starts_with("<?xml") {
+        return Some(DataKind::Xml);
+    }
+    looks_like_yaml(trimmed, bytes.len() > DATA_SNIFF_BYTES).then_some(DataKind::Yaml)
+}
+
+/// Decides the format of a file already routed to the data estimator (e.g. by extension).
+///
+/// Falls back to YAML, the only one of the three without a required first character.
+pub fn data_kind(text: &str) -> DataKind {
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with(['{', '[']) {
+        DataKind::Json
+    } else if trimmed.starts_with('<') {
+        DataKind::Xml
+    } else {
+        DataKind::Yaml
+    }
+}
+
+/// Whether JSON or XML text is minified: a few very long lines.
+pub fn is_minified(kind: DataKind, text: &str) -> bool {
+    let lines = text.lines().filter(|line| !line.trim().is_empty()).count().max(1);
+    kind != DataKind::Yaml && text.len() / lines > MINIFIED_LINE_CHARS
+}
+
+/// Lays out JSON or XML as if pretty-printed with 2-space indentation.
+///
+/// # Returns
+///
+/// The pretty-printed text, or `None` for YAML (which is never minified) and
+/// for input that doesn't parse.
+pub fn pretty_print(kind: DataKind, text: &str) -> Option<String> {
+    match kind {
+        DataKind::Json => {
+            let value: serde_json::Value = serde_json::from_str(text.trim_start_matches('\u{feff}')).ok()?;
+            serde_json::to_string_pretty(&value).ok()
+        }
+        DataKind::Xml => pretty_print_xml(text),
+        DataKind::Yaml => None,
+    }
+}
+
+/// Pretty-prints XML: one line per element, with its text kept on the element's line.
+fn pretty_print_xml(text: &str) -> Option<String> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    // whether the last line is the start tag of the element still open (so text and its end tag join it)
+    let mut open_line = false;
+    loop {
+        let event = reader.read_event().ok()?;
+        let raw = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+        match event {
+            Event::Start(e) => {
+                lines.push(format!("{}<{}>", INDENT.repeat(depth), raw(&e)));
+                depth += 1;
+                open_line = true;
+            }
+            Event::End(e) => {
+                depth = depth.saturating_sub(1);
+                let end = format!("</{}>", raw(e.name().as_ref()));
+                match lines.last_mut() {
+                    Some(last) if open_line => last.push_str(&end),
+                    _ => lines.push(format!("{}{}", INDENT.repeat(depth), end)),
+                }
+                open_line = false;
+            }
+            Event::Text(e) if open_line => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(&raw(&e));
+                }
+            }
+            Event::Empty(e) => {
+                lines.push(format!("{}<{}/>", INDENT.repeat(depth), raw(&e)));
+                open_line = false;
+            }
+            Event::Text(e) => {
+                lines.push(format!("{}{}", INDENT.repeat(depth), raw(&e)));
+                open_line = false;
+            }
+            Event::CData(e) => {
+                lines.push(format!("{}<![CDATA[{}]]>", INDENT.repeat(depth), raw(&e)));
+                open_line = false;
+            }
+            Event::Comment(e) => {
+                lines.push(format!("{}<!--{}-->", INDENT.repeat(depth), raw(&e)));
+                open_line = false;
+            }
+            Event::Decl(e) => lines.push(format!("<?{}?>", raw(&e))),
+            Event::PI(e) => lines.push(format!("{}<?{}?>", INDENT.repeat(depth), raw(&e))),
+            Event::DocType(e) => lines.push(format!("<!DOCTYPE {}>", raw(&e))),
+            Event::Eof => break,
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+/// Checks that a sample tokenizes as JSON with properly nested brackets.
+///
+/// The sample may stop anywhere, so running out of input is fine; only a
+/// character JSON doesn't allow or a mismatched bracket rejects it.
+fn json_prefix_is_valid(sample: &str) -> bool {
+    let mut stack = Vec::new();
+    let mut chars = sample.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => stack.push(c),
+            '}' | ']' => {
+                let open = if c == '}' { '{' } else { '[' };
+                if stack.pop() != Some(open) {
+                    return false;
+                }
+            }
+            ',' | ':' if stack.is_empty() => return false,
+            ',' | ':' => {}
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '-' | '0'..='9' => {
+                while chars.next_if(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')).is_some() {}
+            }
+            't' | 'f' | 'n' => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                    word.push(c);
+                }
+                // a literal cut by the end of the sample is still a prefix of one
+                let valid = match chars.peek() {
+                    Some(_) => JSON_LITERALS.contains(&word.as_str()),
+                    None => JSON_LITERALS.iter().any(|literal| literal.starts_with(word.as_str())),
+                };
+                if !valid {
+                    return false;
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Whether a sample is mostly YAML mappings and sequences, with at least one mapping.
+fn looks_like_yaml(sample: &str, truncated: bool) -> bool {
+    if sample.starts_with("---") {
+        return true;
+    }
+    let mut lines: Vec<&str> = sample.lines().collect();
+    if truncated {
+        // the last line may be cut by the end of the sample
+        lines.pop();
+    }
+    let lines: Vec<&str> =
+        lines.into_iter().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+    let is_item = |line: &str| line == "-" || line.starts_with("- ");
+    let yaml_lines = lines.iter().filter(|line| is_item(line) || is_yaml_key_line(line)).count();
+    let has_key = lines.iter().any(|line| is_yaml_key_line(line.strip_prefix("- ").unwrap_or(line)));
+    has_key && lines.len() >= YAML_MIN_LINES && yaml_lines as f64 >= lines.len() as f64 * YAML_LINE_SHARE
+}
+
+/// Whether a trimmed line is a YAML `key: value` pair (or a `key:` opening a block).
+fn is_yaml_key_line(line: &str) -> bool {
+    match line.split_once(':') {
+        Some((key, value)) => {
+            // unquoted keys have no spaces, which leaves out most sentences with a colon
+            let quoted = key.len() >= 2
+                && ((key.starts_with('"') && key.ends_with('"')) || (key.starts_with('\'') && key.ends_with('\'')));
+            let word = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+            (quoted || word) && (value.is_empty() || value.starts_with(' '))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn data_is_sniffed_from_its_first_kb() {
+        assert_eq!(sniff_data(build_minified_json(400).as_bytes()), Some(DataKind::Json));
+        assert_eq!(sniff_data(b"\xef\xbb\xbf  [1, 2, {\"a\": null}]"), Some(DataKind::Json));
+        assert_eq!(sniff_data(b"<?xml version=\"1.0\"?><a/>"), Some(DataKind::Xml));
+        assert_eq!(sniff_data(b"---\ntitle: Report\n"), Some(DataKind::Yaml));
+        assert_eq!(sniff_data(b"name: build\non: push\njobs:\n  test:\n    - run\n"), Some(DataKind::Yaml));
+        // braces that don't tokenize as JSON, HTML, and prose with a colon are not data
+        assert_eq!(sniff_data(b"{ this is a LaTeX group }"), None);
+        assert_eq!(sniff_data(b"<!DOCTYPE html><html></html>"), None);
+        assert_eq!(sniff_data(b"Dear team,\nNote: the printer is down.\nThanks\n"), None);
+    }
+
+    #[test]
+    fn only_long_json_and_xml_lines_are_minified() {
+        assert!(is_minified(DataKind::Json, &build_minified_json(400)));
+        assert!(!is_minified(DataKind::Json, &build_pretty_json(400)));
+        assert!(!is_minified(DataKind::Yaml, &format!("key: {}\n", "x".repeat(500))));
+    }
+
+    #[test]
+    fn minified_data_is_pretty_printed_with_two_spaces() {
+        assert_eq!(pretty_print(DataKind::Json, &build_minified_json(3)), Some(build_pretty_json(3)));
+        assert_eq!(
+            pretty_print(DataKind::Xml, r#"<a><b x="1">t</b><c/></a>"#).as_deref(),
+            Some("<a>\n  <b x=\"1\">t</b>\n  <c/>\n</a>")
+        );
+        assert_eq!(pretty_print(DataKind::Json, "{\"unterminated\": "), None);
+        assert_eq!(pretty_print(DataKind::Yaml, "a: 1"), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! # Deadlines
+//!
+//! `deadline_ms` caps how long an estimate may take, so a pathological file
+//! stops with what was counted so far instead of running until a gateway
+//! gives up on the request. `estimate_bytes` runs the estimator inside
+//! `with_deadline`, and the estimators look at the clock with
+//! `deadline_passed` at coarse loop boundaries:
+//!
+//! - PDF: before each counting strategy after the first, every
+//!   `DEADLINE_CHECK_BYTES` the raw scans examine (`charge_deadline`), and
+//!   before each optional analysis
+//! - XLSX: before each sheet after the first one estimated
+//!
+//! The first strategy and the first sheet always start, so even a deadline
+//! that has passed by the time they do gets what they counted. Other formats
+//! are read in one pass and never stop early.
+//!
+//! ## Partial Results
+//!
+//! An estimator that stops early returns the pages it counted so far, and
+//! `estimate_bytes` flags the result with a `⚠ deadline_exceeded` warning and
+//! at most `DEADLINE_CONFIDENCE`. An estimator that stops before counting
+//! anything fails with `deadline_exceeded`. Partial results are not cached.
+//!
+//! Estimates nested in another one (archive entries) share its deadline, and
+//! an entry cut short makes the outer result partial too.
+
+use crate::diagnostics::now_ms;
+use std::cell::Cell;
+
+/// Bytes the raw scans examine between two looks at the clock.
+pub const DEADLINE_CHECK_BYTES: usize = 1024 * 1024;
+
+/// Highest confidence of a result cut short by its deadline.
+pub const DEADLINE_CONFIDENCE: f64 = 0.3;
+
+/// Start of the warning added to results cut short by their deadline.
+pub const DEADLINE_WARNING: &str = "⚠ deadline_exceeded";
+
+thread_local! {
+    /// When the current estimate must stop, in `now_ms` time; `None` outside `with_deadline`.
+    static DEADLINE: Cell<Option<f64>> = const { Cell::new(None) };
+    /// Whether a check found the deadline passed.
+    static DEADLINE_PASSED: Cell<bool> = const { Cell::new(false) };
+    /// Bytes charged with `charge_deadline` since the clock was last read.
+    static SCANNED_SINCE_CHECK: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `f` with a deadline of `deadline_ms` milliseconds from now, or the
+/// deadline of an enclosing call when that is earlier (see the module docs).
+///
+/// # Returns
+///
+/// The result of `f`, and whether a check found the deadline passed while it ran.
+pub fn with_deadline<T>(deadline_ms: Option<u32>, f: impl FnOnce() -> T) -> (T, bool) {
+    let outer = DEADLINE.get();
+    let own = deadline_ms.map(|ms| now_ms() + f64::from(ms));
+    let deadline = match (outer, own) {
+        (Some(outer), Some(own)) => Some(outer.min(own)),
+        (outer, own) => outer.or(own),
+    };
+    DEADLINE.set(deadline);
+    let outer_passed = DEADLINE_PASSED.replace(false);
+    let result = f();
+    DEADLINE.set(outer);
+    let passed = DEADLINE_PASSED.get();
+    // an entry cut short leaves the estimate it belongs to partial as well
+    DEADLINE_PASSED.set(outer.is_some() && (outer_passed || passed));
+    (result, passed)
+}
+
+/// Whether the current deadline has passed; always `false` outside `with_deadline`.
+///
+/// Reads the clock until it finds the deadline passed, so call it at coarse
+/// boundaries (a sheet, a strategy) rather than in tight loops.
+pub fn deadline_passed() -> bool {
+    let Some(deadline) = DEADLINE.get() else {
+        return false;
+    };
+    if !DEADLINE_PASSED.get() && now_ms() >= deadline {
+        DEADLINE_PASSED.set(true);
+    }
+    DEADLINE_PASSED.get()
+}
+
+/// Counts `bytes` scanned toward the next look at the clock, which happens
+/// once every `DEADLINE_CHECK_BYTES`.
+///
+/// # Returns
+///
+/// `false` once the deadline has passed; always `true` outside `with_deadline`.
+pub fn charge_deadline(bytes: usize) -> bool {
+    if DEADLINE.get().is_none() {
+        return true;
+    }
+    if DEADLINE_PASSED.get() {
+        return false;
+    }
+    let scanned = SCANNED_SINCE_CHECK.get().saturating_add(bytes);
+    if scanned < DEADLINE_CHECK_BYTES {
+        SCANNED_SINCE_CHECK.set(scanned);
+        return true;
+    }
+    SCANNED_SINCE_CHECK.set(0);
+    !deadline_passed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Sleeps long enough for a 1 ms deadline to pass.
+    fn miss_deadline() {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    #[test]
+    fn estimates_without_a_deadline_never_stop() {
+        assert!(!deadline_passed());
+        assert!(charge_deadline(usize::MAX));
+        let ((), passed) = with_deadline(None, || {
+            miss_deadline();
+            assert!(!deadline_passed() && charge_deadline(4 * DEADLINE_CHECK_BYTES));
+        });
+        assert!(!passed);
+    }
+
+    #[test]
+    fn deadlines_are_found_passed_at_the_next_check() {
+        let (checks, passed) = with_deadline(Some(1), || {
+            let before = deadline_passed();
+            miss_deadline();
+            // the clock is only read once enough bytes were scanned
+            let early = charge_deadline(DEADLINE_CHECK_BYTES / 2);
+            let late = charge_deadline(DEADLINE_CHECK_BYTES / 2);
+            (before, early, late, deadline_passed())
+        });
+        assert_eq!((checks, passed), ((false, true, false, true), true));
+        // the deadline ends with its call
+        assert!(!deadline_passed());
+        let ((), passed) = with_deadline(Some(60_000), || assert!(!deadline_passed()));
+        assert!(!passed);
+    }
+
+    #[test]
+    fn nested_estimates_share_the_earlier_deadline() {
+        let ((inner, seen_outside), outer) = with_deadline(Some(60_000), || {
+            let (_, inner) = with_deadline(Some(1), || {
+                miss_deadline();
+                deadline_passed()
+            });
+            (inner, deadline_passed())
+        });
+        // an entry cut short leaves the outer estimate partial
+        assert_eq!((inner, seen_outside, outer), (true, true, true));
+        // passing is only found by a check
+        let ((), passed) = with_deadline(Some(1), miss_deadline);
+        assert!(!passed);
+
+        let (inner, outer) = with_deadline(Some(1), || {
+            miss_deadline();
+            with_deadline(Some(60_000), deadline_passed)
+        });
+        assert_eq!((inner, outer), ((true, true), true));
+    }
+}
@@ -0,0 +1,431 @@
+//! # Debug Diagnostics
+//!
+//! With the `debug` option, the estimation entry points attach a machine-readable
+//! `diagnostics` object to the result, so wrong counts can be investigated
+//! without rebuilding the crate. Nothing here runs otherwise.
+//!
+//! ## Contents
+//!
+//! - PDF: the count and timing of every `PdfStrategy` (not only the ones the
+//!   estimator's pipeline needed), the strategy the pipeline would pick, and
+//!   the object references and byte offsets of the catalog (`/Root`) and the
+//!   page tree root (`/Pages`), with its `/Count`, and the 0-based indices of
+//!   the blank pages (see the `pdf_blank` module)
+//! - DOCX, PPTX, XLSX: which of the parts the estimators read are present,
+//!   which `docProps/app.xml` fields hold a value, and the package's ZIP
+//!   statistics (see `ZipStats`)
+//!
+//! Packages with more than `MEDIA_NOTE_BYTES` of embedded media also get a
+//! note saying so, as large media explains slow or surprising estimates.
+//!
+//! Offsets are where an object's body starts, just past its `obj` keyword.
+//! Timings are in milliseconds and only as precise as the platform clock
+//! (`Date.now()` in the browser), or come from the clock set with `set_clock`.
+
+use crate::file_utils::{note_zip_entry, open_zip_archive};
+use crate::pdf::{PdfCounter, PdfStrategy};
+use crate::pdf_blank::find_blank_pages;
+use crate::pdf_utils::{dict_ref, dict_slice, dict_value, find_bytes, ObjRef, ObjectIndex};
+use js_sys::Function;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::io::{Cursor, Read};
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+use zip::ZipArchive;
+
+/// Every PDF strategy, in the order they are reported.
+const PDF_STRATEGIES: [PdfStrategy; 3] = [PdfStrategy::TypeScan, PdfStrategy::PageTree, PdfStrategy::CountScan];
+
+/// Parts of an OOXML package the estimators read, by format.
+const OOXML_PARTS: &[(&str, &[&str])] = &[
+    ("docx", &["word/document.xml", "word/_rels/document.xml.rels", "word/settings.xml"]),
+    ("pptx", &["ppt/presentation.xml", "ppt/_rels/presentation.xml.rels"]),
+    ("xlsx", &["xl/workbook.xml", "xl/_rels/workbook.xml.rels", "xl/sharedStrings.xml", "xl/styles.xml"]),
+];
+
+/// Parts every OOXML package may have, whatever the format.
+const PACKAGE_PARTS: &[&str] =
+    &["[Content_Types].xml", "_rels/.rels", "docProps/app.xml", "docProps/core.xml", "_xmlsignatures/origin.sigs"];
+
+/// Number of entries listed by size in `ZipStats::largest`.
+pub const LARGEST_ENTRIES: usize = 5;
+
+/// Uncompressed size of embedded media above which a package gets a note (100 MB).
+pub const MEDIA_NOTE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Sizes of a ZIP package's entries, read from its central directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ZipStats {
+    /// Number of entries, directories included.
+    pub entry_count: usize,
+    /// Total compressed size in bytes.
+    pub compressed_bytes: u64,
+    /// Total uncompressed size in bytes.
+    pub uncompressed_bytes: u64,
+    /// Uncompressed size in bytes of the entries in a `media/` folder
+    /// (`word/media/`, `ppt/media/`, `xl/media/`).
+    pub media_bytes: u64,
+    /// The largest entries by uncompressed size, as (name, compressed, uncompressed).
+    pub largest: Vec<(String, u64, u64)>,
+}
+
+impl ZipStats {
+    /// Reads the sizes of every entry of an archive.
+    ///
+    /// # Notes
+    ///
+    /// Only entry headers are read; nothing is decompressed, so the cost
+    /// grows with the number of entries, not with their size.
+    pub fn read(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Self {
+        let mut stats = ZipStats { entry_count: archive.len(), ..Default::default() };
+        for i in 0..archive.len() {
+            let Ok(file) = archive.by_index_raw(i) else { continue };
+            let (compressed, uncompressed) = (file.compressed_size(), file.size());
+            stats.compressed_bytes += compressed;
+            stats.uncompressed_bytes += uncompressed;
+            if file.name().starts_with("media/") || file.name().contains("/media/") {
+                stats.media_bytes += uncompressed;
+            }
+            let at = stats.largest.partition_point(|(_, _, size)| *size >= uncompressed);
+            if at < LARGEST_ENTRIES {
+                stats.largest.insert(at, (file.name().to_string(), compressed, uncompressed));
+                stats.largest.truncate(LARGEST_ENTRIES);
+            }
+        }
+        stats
+    }
+
+    /// Uncompressed size divided by compressed size (0 for an empty package).
+    pub fn compression_ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 { 0.0 } else { self.uncompressed_bytes as f64 / self.compressed_bytes as f64 }
+    }
+
+    /// Whether embedded media makes up more than half of the uncompressed package.
+    pub fn media_dominates(&self) -> bool {
+        self.media_bytes * 2 > self.uncompressed_bytes
+    }
+
+    /// The statistics as reported in `diagnostics.zip`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "entry_count": self.entry_count,
+            "compressed_bytes": self.compressed_bytes,
+            "uncompressed_bytes": self.uncompressed_bytes,
+            "compression_ratio": self.compression_ratio(),
+            "media_bytes": self.media_bytes,
+            "media_dominates": self.media_dominates(),
+            "largest_entries": self
+                .largest
+                .iter()
+                .map(|(name, compressed, size)| json!({ "name": name, "compressed_bytes": compressed, "bytes": size }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// The note for a package with more than `MEDIA_NOTE_BYTES` of embedded media,
+/// from its diagnostics (e.g. "Document contains 320 MB of embedded media").
+pub fn media_note(diagnostics: &Value) -> Option<String> {
+    let media_bytes = diagnostics.get("zip")?.get("media_bytes")?.as_u64()?;
+    (media_bytes > MEDIA_NOTE_BYTES).then(|| {
+        format!("Document contains {} MB of embedded media", (media_bytes as f64 / 1_048_576.0).round())
+    })
+}
+
+/// Collects the diagnostics of a document for its detected format.
+///
+/// # Returns
+///
+/// The diagnostics object, or `None` for formats without diagnostics and for
+/// input that isn't the detected format as given (e.g. gzip-compressed).
+pub fn collect_diagnostics(bytes: &[u8], detected: &str) -> Option<Value> {
+    match detected {
+        "pdf" => pdf_diagnostics(bytes),
+        "docx" | "pptx" | "xlsx" => ooxml_diagnostics(bytes, detected),
+        _ => None,
+    }
+}
+
+/// Runs every PDF strategy and reads the catalog and page tree root.
+pub fn pdf_diagnostics(bytes: &[u8]) -> Option<Value> {
+    find_bytes(&bytes[..bytes.len().min(1024)], b"%PDF-")?;
+    let strategies: Vec<(PdfStrategy, usize, f64)> = PDF_STRATEGIES
+        .iter()
+        .map(|&strategy| {
+            let start = now_ms();
+            let count = PdfCounter::new(vec![strategy]).count(bytes).count;
+            (strategy, count, now_ms() - start)
+        })
+        .collect();
+    // the strategy the estimator's pipeline stops at
+    let pipeline = PdfCounter::default();
+    let chosen = pipeline
+        .strategies()
+        .iter()
+        .find(|strategy| strategies.iter().any(|(s, count, _)| s == *strategy && *count > 0));
+    let counts: Vec<usize> = strategies.iter().map(|(_, count, _)| *count).collect();
+
+    let start = now_ms();
+    let index = ObjectIndex::build(bytes);
+    let index_ms = now_ms() - start;
+    let root = index.root_ref();
+    let pages = index.catalog().and_then(|catalog| dict_ref(catalog, b"/Pages"));
+    let pages_count = pages
+        .and_then(|pages| index.get(pages))
+        .and_then(|body| dict_value(dict_slice(body), b"/Count"))
+        .and_then(|value| {
+            let end = value.iter().position(|b| !b.is_ascii_digit()).unwrap_or(value.len());
+            std::str::from_utf8(&value[..end]).ok()?.parse::<usize>().ok()
+        });
+
+    Some(json!({
+        "format": "pdf",
+        "strategies": strategies
+            .iter()
+            .map(|(strategy, count, ms)| json!({ "name": strategy.name(), "count": count, "ms": ms }))
+            .collect::<Vec<_>>(),
+        "chosen_strategy": chosen.map(|strategy| strategy.name()),
+        "strategies_agree": counts.windows(2).all(|pair| pair[0] == pair[1]),
+        "blank_pages": find_blank_pages(bytes),
+        "objects_indexed": index.len(),
+        "index_ms": index_ms,
+        "root": root.map(|id| object_json(&index, id)),
+        "pages": pages.map(|id| {
+            let mut object = object_json(&index, id);
+            object["count"] = json!(pages_count);
+            object
+        }),
+    }))
+}
+
+/// Lists the parts of an OOXML package the estimators read, the `app.xml` fields set, and its `ZipStats`.
+pub fn ooxml_diagnostics(bytes: &[u8], format: &str) -> Option<Value> {
+    let mut archive = open_zip_archive(bytes, "OOXML").ok()?;
+    let format_parts = OOXML_PARTS.iter().find(|(id, _)| *id == format).map_or(&[][..], |(_, parts)| parts);
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let (found, missing): (Vec<&str>, Vec<&str>) = PACKAGE_PARTS
+        .iter()
+        .chain(format_parts)
+        .partition(|part| names.iter().any(|name| name == *part));
+
+    let mut app_xml = String::new();
+    if let Ok(mut file) = archive.by_name("docProps/app.xml") {
+        note_zip_entry(&file);
+        file.read_to_string(&mut app_xml).ok();
+    }
+    let app_fields = app_xml_fields(&app_xml);
+    Some(json!({
+        "format": format,
+        "part_count": names.len(),
+        "parts_found": found,
+        "parts_missing": missing,
+        "app_xml_fields": app_fields,
+        "zip": ZipStats::read(&mut archive).to_json(),
+    }))
+}
+
+/// Names of the top-level elements of `app.xml` that hold text (e.g. `Pages`, `Application`).
+fn app_xml_fields(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut fields = Vec::new();
+    let mut depth = 0usize;
+    let mut current = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                depth += 1;
+                // depth 2: direct children of <Properties>
+                if depth == 2 {
+                    current = Some(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+                }
+            }
+            Ok(Event::Text(_)) if depth == 2 => {
+                if let Some(field) = current.take() {
+                    fields.push(field);
+                }
+            }
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    fields
+}
+
+/// An object reference with the offset of its body, e.g. `{"object": "1 0 R", "offset": 15}`.
+fn object_json(index: &ObjectIndex, id: ObjRef) -> Value {
+    json!({ "object": format!("{} {} R", id.0, id.1), "offset": index.offset(id) })
+}
+
+thread_local! {
+    /// Clock registered with `set_clock`, read by `now_ms` in place of the platform clock.
+    static CLOCK: RefCell<Option<Function>> = const { RefCell::new(None) };
+}
+
+/// Replaces the clock behind every timing and `deadline_ms` check, so tests
+/// and replays get the same `index_ms`, strategy timings, and deadlines on
+/// every run.
+///
+/// # Parameters
+///
+/// * `clock` - Called with no arguments, it returns the time in milliseconds
+///             (e.g. `() => fakeNow`). Pass `null` to go back to `Date.now()`.
+///             A clock that throws or returns something other than a number
+///             is ignored for that reading.
+#[wasm_bindgen]
+pub fn set_clock(clock: Option<Function>) {
+    CLOCK.with(|slot| *slot.borrow_mut() = clock);
+}
+
+/// Current time in milliseconds: from the clock registered with `set_clock`,
+/// otherwise `Date.now()` in wasm and the system clock natively.
+pub fn now_ms() -> f64 {
+    let injected = CLOCK.with(|slot| slot.borrow().as_ref().and_then(|clock| clock.call0(&JsValue::NULL).ok()));
+    if let Some(ms) = injected.and_then(|value| value.as_f64()) {
+        return ms;
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    /// A page tree of two pages whose `/Count` claims five.
+    fn miscounted_pdf() -> Vec<u8> {
+        build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 5 >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+        ])
+    }
+
+    /// Diagnostics with their timings zeroed, for comparing.
+    fn without_timings(mut diagnostics: Value) -> Value {
+        for strategy in diagnostics["strategies"].as_array_mut().into_iter().flatten() {
+            strategy["ms"] = json!(0);
+        }
+        diagnostics["index_ms"] = json!(0);
+        diagnostics
+    }
+
+    #[test]
+    fn disagreeing_pdf_strategies_are_all_reported() {
+        let pdf = miscounted_pdf();
+        let body_after = |marker: &[u8]| find_bytes(&pdf, marker).unwrap() + marker.len();
+        let result = estimate(&pdf, None, r#"{"debug":true}"#).unwrap();
+        assert_eq!(result.page_count, 2);
+        assert_eq!(
+            without_timings(result.diagnostics.unwrap()),
+            json!({
+                "format": "pdf",
+                "strategies": [
+                    { "name": "type-scan", "count": 2, "ms": 0 },
+                    { "name": "page-tree", "count": 2, "ms": 0 },
+                    { "name": "count-scan", "count": 5, "ms": 0 },
+                ],
+                "chosen_strategy": "type-scan",
+                "strategies_agree": false,
+                "blank_pages": [0, 1],
+                "objects_indexed": 4,
+                "index_ms": 0,
+                "root": { "object": "1 0 R", "offset": body_after(b"1 0 obj") },
+                "pages": { "object": "2 0 R", "offset": body_after(b"2 0 obj"), "count": 5 },
+            })
+        );
+
+        let agreeing = pdf_diagnostics(&build_pdf(3, XrefStyle::Stream, false, false)).unwrap();
+        assert_eq!(agreeing["strategies_agree"], true);
+        assert!(agreeing["strategies"].as_array().unwrap().iter().all(|strategy| strategy["count"] == 3));
+    }
+
+    #[test]
+    fn diagnostics_need_the_debug_option() {
+        assert_eq!(estimate(&miscounted_pdf(), None, "{}").unwrap().diagnostics, None);
+        assert_eq!(estimate(&build_docx(10, 1, Some(3)), None, r#"{"debug":false}"#).unwrap().diagnostics, None);
+        // formats without diagnostics and inputs that aren't what was detected get none
+        assert_eq!(collect_diagnostics(b"plain text", "text"), None);
+        assert_eq!(collect_diagnostics(b"not a PDF", "pdf"), None);
+        assert_eq!(collect_diagnostics(b"not a ZIP", "docx"), None);
+    }
+
+    #[test]
+    fn ooxml_diagnostics_list_the_parts_read() {
+        let result = estimate(&build_docx(10, 1, Some(3)), None, r#"{"debug":true}"#).unwrap();
+        let diagnostics = result.diagnostics.unwrap();
+        assert_eq!(diagnostics["format"], "docx");
+        assert_eq!(diagnostics["part_count"], 4);
+        assert_eq!(
+            diagnostics["parts_found"],
+            json!(["[Content_Types].xml", "_rels/.rels", "docProps/app.xml", "word/document.xml"])
+        );
+        assert_eq!(
+            diagnostics["parts_missing"],
+            json!([
+                "docProps/core.xml",
+                "_xmlsignatures/origin.sigs",
+                "word/_rels/document.xml.rels",
+                "word/settings.xml",
+            ])
+        );
+        assert_eq!(diagnostics["app_xml_fields"], json!(["Application", "Pages"]));
+        assert_eq!(diagnostics["zip"]["entry_count"], 4);
+
+        let pptx = ooxml_diagnostics(&build_pptx(4, 1), "pptx").unwrap();
+        let missing = pptx["parts_missing"].as_array().unwrap();
+        assert!(!missing.iter().any(|part| part == "ppt/presentation.xml"), "{}", pptx);
+        let fields = pptx["app_xml_fields"].as_array().unwrap();
+        assert!(fields.iter().any(|field| field == "HiddenSlides"), "{}", pptx);
+    }
+
+    #[test]
+    fn zip_statistics_come_from_the_central_directory() {
+        let mut docx = build_docx(10, 1, Some(3));
+        let media = [("word/media/image1.png", 40_000), ("word/media/image2.png", 90_000), ("media/logo.png", 5)];
+        for (part, size) in media {
+            docx = with_part(&docx, part, &"x".repeat(size));
+        }
+        let stats = ZipStats::read(&mut ZipArchive::new(Cursor::new(&docx[..])).unwrap());
+        assert_eq!((stats.entry_count, stats.media_bytes), (7, 130_005));
+        assert!(stats.media_dominates());
+        assert!(stats.compression_ratio() > 10.0, "{}", stats.compression_ratio());
+        assert!(stats.compressed_bytes < stats.uncompressed_bytes);
+        let largest: Vec<(&str, u64)> = stats.largest.iter().map(|(name, _, size)| (name.as_str(), *size)).collect();
+        assert_eq!(largest.len(), LARGEST_ENTRIES);
+        assert_eq!(largest[..2], [("word/media/image2.png", 90_000), ("word/media/image1.png", 40_000)]);
+        assert!(largest.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        let result = estimate(&docx, None, r#"{"debug":true}"#).unwrap();
+        let zip = &result.diagnostics.unwrap()["zip"];
+        assert_eq!((&zip["media_bytes"], &zip["media_dominates"]), (&json!(130_005), &json!(true)));
+        let biggest = json!({"name": "word/media/image2.png", "compressed_bytes": stats.largest[0].1, "bytes": 90_000});
+        assert_eq!(zip["largest_entries"][0], biggest);
+        assert_eq!(ZipStats::default().compression_ratio(), 0.0);
+    }
+
+    #[test]
+    fn large_media_gets_a_note() {
+        let diagnostics = |media_bytes: u64| json!({"format": "pptx", "zip": {"media_bytes": media_bytes}});
+        let note = media_note(&diagnostics(320 * 1024 * 1024));
+        assert_eq!(note.as_deref(), Some("Document contains 320 MB of embedded media"));
+        assert_eq!(media_note(&diagnostics(MEDIA_NOTE_BYTES)), None);
+        assert_eq!(media_note(&json!({"format": "pdf"})), None);
+        let result = estimate(&build_pptx(2, 0), None, r#"{"debug":true}"#).unwrap();
+        assert!(!result.notes.iter().any(|note| note.contains("embedded media")), "{:?}", result.notes);
+    }
+}
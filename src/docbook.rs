@@ -0,0 +1,361 @@
+//! # DocBook Documents
+//!
+//! Reads DocBook XML (`<book>`, `<article>`, ...) with a streaming XML reader
+//! and measures what each element would take up once rendered, so the
+//! estimator can apply element-aware rules instead of treating the markup as
+//! data.
+//!
+//! ## Rules
+//!
+//! - Text of paragraphs, titles, and lists is prose, measured in character
+//!   cells with its whitespace collapsed
+//! - `<programlisting>`, `<screen>`, `<literallayout>`, and `<synopsis>` are
+//!   monospace blocks, one line per source line
+//! - Figures and media objects are image blocks of `IMAGE_BLOCK_LINES` lines
+//! - Tables take one line per `<row>`; their cell text isn't counted as prose
+//! - Chapter-level elements (`<chapter>`, `<appendix>`, `<preface>`, ...) add
+//!   `CHAPTER_HEADING_LINES` lines of heading space and, in a book, start a new
+//!   page; sections add `SECTION_HEADING_LINES`
+//! - Metadata (`<info>`, `<bookinfo>`, ...), remarks, and the text of
+//!   `<indexterm>` are not printed in place
+//!
+//! ## Generated Pages
+//!
+//! An `<index>` element is filled from the document's `<indexterm>`s (or
+//! lists its own `<indexentry>`s), and a `<toc>` element, or any `<book>`,
+//! gets a table of contents with a line per chapter and section. The
+//! estimator turns these counts into pages.
+//!
+//! ## Detection
+//!
+//! `.dbk` and `.docbook` files are DocBook. `.xml` files, and XML without a
+//! filename, are taken as DocBook when their root element is a DocBook root
+//! (`DOCBOOK_ROOTS`) and they declare the DocBook 5 namespace or a DocBook
+//! DTD; other XML stays a data file.
+
+use crate::markup::IMAGE_BLOCK_LINES;
+use crate::text_layout::char_width;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Namespace of DocBook 5.
+pub const DOCBOOK_NAMESPACE: &str = "http://docbook.org/ns/docbook";
+
+/// Root elements of a DocBook document.
+pub const DOCBOOK_ROOTS: &[&str] = &["book", "article", "set", "part", "chapter", "reference", "refentry"];
+
+/// How much of the input is searched for the root element and the DocBook declaration.
+pub const DOCBOOK_SNIFF_BYTES: usize = 4096;
+
+/// Lines of heading space before a chapter-level element.
+pub const CHAPTER_HEADING_LINES: usize = 6;
+
+/// Lines of heading space before a section.
+pub const SECTION_HEADING_LINES: usize = 3;
+
+/// Elements that, in a book, start on a new page.
+const CHAPTER_ELEMENTS: &[&str] = &[
+    "chapter", "appendix", "preface", "part", "article", "glossary", "bibliography", "colophon", "dedication",
+    "reference",
+];
+
+/// Section elements, each with a heading.
+const SECTION_ELEMENTS: &[&str] = &[
+    "section", "sect1", "sect2", "sect3", "sect4", "sect5", "simplesect", "refsect1", "refsect2", "refsect3",
+    "refsection",
+];
+
+/// Elements printed verbatim in a monospace font.
+const MONOSPACE_ELEMENTS: &[&str] = &["programlisting", "screen", "literallayout", "synopsis"];
+
+/// Elements printed as an image block.
+const IMAGE_ELEMENTS: &[&str] = &["figure", "informalfigure", "mediaobject"];
+
+/// Elements printed as a table.
+const TABLE_ELEMENTS: &[&str] = &["table", "informaltable"];
+
+/// Elements whose content isn't printed in place.
+const HIDDEN_ELEMENTS: &[&str] = &[
+    "info", "bookinfo", "articleinfo", "chapterinfo", "sectioninfo", "prefaceinfo", "appendixinfo", "remark",
+    "indexterm", "annotation", "alt",
+];
+
+/// A run of content that starts on a new page.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocbookSegment {
+    /// Character cells of prose.
+    pub prose_cells: usize,
+    /// Lines of monospace blocks, table rows, images, and heading space.
+    pub lines: usize,
+}
+
+/// What a DocBook document would print, element by element.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocbookLayout {
+    /// Local name of the root element (`book`, `article`, ...).
+    pub root: String,
+    /// The content, split where chapters of a book start new pages.
+    pub segments: Vec<DocbookSegment>,
+    /// Number of chapter-level elements.
+    pub chapters: usize,
+    /// Number of sections.
+    pub sections: usize,
+    /// Number of lines in monospace blocks.
+    pub monospace_lines: usize,
+    /// Number of image blocks.
+    pub images: usize,
+    /// Number of table rows.
+    pub table_rows: usize,
+    /// Number of `<indexterm>` elements.
+    pub index_terms: usize,
+    /// Number of `<indexentry>` elements written out in an `<index>`.
+    pub index_entries: usize,
+    /// Whether the document has an `<index>` element.
+    pub has_index: bool,
+    /// Whether a table of contents is printed: a `<toc>` element, or a book.
+    pub has_toc: bool,
+}
+
+impl DocbookLayout {
+    /// Lines of the generated index: its written-out entries, or else one per `<indexterm>`.
+    pub fn index_lines(&self) -> usize {
+        if !self.has_index {
+            return 0;
+        }
+        if self.index_entries > 0 { self.index_entries } else { self.index_terms }
+    }
+
+    /// Lines of the table of contents: one per chapter and section.
+    pub fn toc_lines(&self) -> usize {
+        if self.has_toc { self.chapters + self.sections } else { 0 }
+    }
+
+    fn current(&mut self) -> &mut DocbookSegment {
+        if self.segments.is_empty() {
+            self.segments.push(DocbookSegment::default());
+        }
+        self.segments.last_mut().expect("at least one segment")
+    }
+
+    fn add_lines(&mut self, lines: usize) {
+        self.current().lines += lines;
+    }
+
+    /// Adds prose, collapsing whitespace the way it's rendered.
+    fn add_prose(&mut self, text: &str, pending_space: &mut bool) {
+        let mut cells = 0;
+        for word in text.split_inclusive(char::is_whitespace) {
+            let trimmed = word.trim_end();
+            if !trimmed.is_empty() {
+                if *pending_space {
+                    cells += 1;
+                }
+                cells += trimmed.chars().map(char_width).sum::<usize>();
+                *pending_space = false;
+            }
+            if trimmed.len() < word.len() {
+                *pending_space = true;
+            }
+        }
+        self.current().prose_cells += cells;
+    }
+}
+
+/// Checks whether XML is a DocBook document (see the module docs).
+pub fn looks_like_docbook(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(DOCBOOK_SNIFF_BYTES)];
+    let mut reader = Reader::from_reader(head);
+    let mut buf = Vec::new();
+    let mut doctype_docbook = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::DocType(ref e)) => {
+                doctype_docbook = String::from_utf8_lossy(e).to_ascii_lowercase().contains("docbook");
+            }
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                let namespaced = e.attributes().flatten().any(|attr| {
+                    attr.key.as_ref() == b"xmlns" && attr.value.as_ref() == DOCBOOK_NAMESPACE.as_bytes()
+                });
+                return DOCBOOK_ROOTS.contains(&name.as_str()) && (namespaced || doctype_docbook);
+            }
+            Ok(Event::Eof) | Err(_) => return false,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Reads a DocBook document into its layout.
+///
+/// # Arguments
+///
+/// * `bytes` - The DocBook XML
+///
+/// # Errors
+///
+/// The XML error and its position when the document isn't well-formed, or
+/// when it has no root element.
+pub fn read_docbook(bytes: &[u8]) -> Result<DocbookLayout, String> {
+    let mut reader = Reader::from_reader(bytes);
+    let mut buf = Vec::new();
+    let mut layout = DocbookLayout::default();
+    // depths inside elements with their own rules; hidden content wins over everything else
+    let mut hidden = 0usize;
+    let mut monospace = 0usize;
+    let mut image = 0usize;
+    let mut table = 0usize;
+    let mut verbatim = String::new();
+    let mut pending_space = false;
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("{} at byte {}", e, reader.error_position()))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let empty = matches!(event, Event::Empty(_));
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                let name = name.as_str();
+                if layout.root.is_empty() {
+                    layout.root = name.to_string();
+                    layout.has_toc = name == "book";
+                }
+                match name {
+                    "indexterm" => layout.index_terms += 1,
+                    "index" => layout.has_index = true,
+                    "indexentry" => layout.index_entries += 1,
+                    "toc" => layout.has_toc = true,
+                    _ => {}
+                }
+                let counted = hidden == 0 && image == 0 && table == 0 && monospace == 0;
+                if counted && CHAPTER_ELEMENTS.contains(&name) && layout.root != name {
+                    layout.chapters += 1;
+                    // a book's chapters start on a new page; the first one only when something came before it
+                    if layout.root == "book" && layout.segments.last().is_some_and(|s| s.prose_cells + s.lines > 0) {
+                        layout.segments.push(DocbookSegment::default());
+                    }
+                    layout.add_lines(CHAPTER_HEADING_LINES);
+                } else if counted && SECTION_ELEMENTS.contains(&name) {
+                    layout.sections += 1;
+                    layout.add_lines(SECTION_HEADING_LINES);
+                } else if counted && IMAGE_ELEMENTS.contains(&name) {
+                    layout.images += 1;
+                    layout.add_lines(IMAGE_BLOCK_LINES);
+                } else if hidden == 0 && table > 0 && name == "row" {
+                    layout.table_rows += 1;
+                    layout.add_lines(1);
+                }
+                if !empty {
+                    if HIDDEN_ELEMENTS.contains(&name) {
+                        hidden += 1;
+                    } else if MONOSPACE_ELEMENTS.contains(&name) {
+                        monospace += 1;
+                    } else if IMAGE_ELEMENTS.contains(&name) {
+                        image += 1;
+                    } else if TABLE_ELEMENTS.contains(&name) {
+                        table += 1;
+                    }
+                }
+            }
+            Event::End(ref e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                let name = name.as_str();
+                if HIDDEN_ELEMENTS.contains(&name) {
+                    hidden = hidden.saturating_sub(1);
+                } else if MONOSPACE_ELEMENTS.contains(&name) {
+                    monospace = monospace.saturating_sub(1);
+                    if monospace == 0 {
+                        let lines = verbatim.trim_matches('\n').lines().count().max(1);
+                        if hidden == 0 && image == 0 && table == 0 {
+                            layout.monospace_lines += lines;
+                            layout.add_lines(lines);
+                        }
+                        verbatim.clear();
+                    }
+                } else if IMAGE_ELEMENTS.contains(&name) {
+                    image = image.saturating_sub(1);
+                } else if TABLE_ELEMENTS.contains(&name) {
+                    table = table.saturating_sub(1);
+                }
+            }
+            Event::Text(ref e) => {
+                // entities declared in a DTD can't be resolved; their text is counted as written
+                let text = e.unescape().map(|text| text.into_owned()).unwrap_or_else(|_| {
+                    String::from_utf8_lossy(e.as_ref()).into_owned()
+                });
+                if monospace > 0 {
+                    verbatim.push_str(&text);
+                } else if hidden == 0 && image == 0 && table == 0 {
+                    layout.add_prose(&text, &mut pending_space);
+                }
+            }
+            Event::CData(ref e) => {
+                let text = String::from_utf8_lossy(e.as_ref()).into_owned();
+                if monospace > 0 {
+                    verbatim.push_str(&text);
+                } else if hidden == 0 && image == 0 && table == 0 {
+                    layout.add_prose(&text, &mut pending_space);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    if layout.root.is_empty() {
+        return Err("no root element".into());
+    }
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn docbook_is_told_from_other_xml_by_root_and_namespace() {
+        let cases: [(&str, bool); 5] = [
+            (r#"<?xml version="1.0"?><article xmlns="http://docbook.org/ns/docbook"><para/></article>"#, true),
+            (
+                r#"<!DOCTYPE book PUBLIC "-//OASIS//DTD DocBook XML V4.5//EN" "docbookx.dtd"><book><chapter/></book>"#,
+                true,
+            ),
+            ("<book><chapter><title>No namespace</title></chapter></book>", false),
+            (r#"<catalog xmlns="http://docbook.org/ns/docbook"><item/></catalog>"#, false),
+            ("not XML at all", false),
+        ];
+        for (xml, docbook) in cases {
+            assert_eq!(looks_like_docbook(xml.as_bytes()), docbook, "{}", xml);
+        }
+        assert!(looks_like_docbook(&build_docbook_book(1, 1)));
+    }
+
+    #[test]
+    fn elements_are_measured_by_their_rules() {
+        let layout = read_docbook(&build_docbook_book(2, 2)).unwrap();
+        assert_eq!((layout.root.as_str(), layout.chapters, layout.sections), ("book", 2, 4));
+        // a figure's media object is part of the figure's image block
+        assert_eq!((layout.images, layout.monospace_lines, layout.table_rows), (2, 48, 12));
+        assert_eq!((layout.index_lines(), layout.toc_lines()), (8, 6));
+        // each chapter is its own page run, and the metadata before the first isn't printed
+        assert_eq!(layout.segments.len(), 2);
+        assert_eq!(layout.segments[0], layout.segments[1]);
+
+        let article = concat!(
+            r#"<article xmlns="http://docbook.org/ns/docbook"><info><title>Unprinted</title></info>"#,
+            "<para>Hello   \n  world</para><remark>hidden</remark><indexterm><primary>term</primary></indexterm>",
+            "<programlisting>\nfn main() {\n}\n</programlisting><informaltable><tgroup cols=\"1\"><tbody>",
+            "<row><entry>cell text</entry></row></tbody></tgroup></informaltable></article>",
+        );
+        let layout = read_docbook(article.as_bytes()).unwrap();
+        assert_eq!(layout.segments, [DocbookSegment { prose_cells: 11, lines: 3 }]);
+        assert_eq!((layout.monospace_lines, layout.table_rows, layout.index_terms), (2, 1, 1));
+        // without an <index> the terms generate nothing, and articles have no table of contents
+        assert_eq!((layout.index_lines(), layout.toc_lines()), (0, 0));
+
+        let err = read_docbook(b"<book><chapter></book>").unwrap_err();
+        assert!(err.contains("at byte"), "{}", err);
+        assert!(read_docbook(b"").is_err());
+    }
+}
@@ -8,6 +8,9 @@
 //!
 //! - **Text files** (`.txt`) - estimated based on character count
 //! - **Markdown files** (`.md`) - treated similarly to text files
+//! - **Source code** (`.rs`, `.py`, `.js`, ...) - line-based with monospace defaults
+//! - **LaTeX sources** (`.tex`) - prose left after stripping commands, low confidence
+//! - **reStructuredText / AsciiDoc** (`.rst`, `.adoc`) - rendered lines, paginated line by line
 //! - **Excel files** (`.xlsx`) - estimated based on row count per sheet
 //! - **Word documents** (`.docx`) - exact page count from metadata or estimated from content
 //! - **PowerPoint presentations** (`.pptx`) - exact slide count from metadata
@@ -21,14 +24,63 @@
 //!
 //! The estimators respect user-provided options for paper sizes and other parameters.
 
-use crate::file_utils::{a4_mm, letter_mm};
-use crate::schema::{EstimateOptions, EstimateResult, EstimatorError, PageSizeMm};
-use calamine::{Data, Reader, Xlsx};
-use std::io::{Cursor, Read};
+use crate::file_utils::{
+    a4_mm, is_encrypted_office_package, mm_from_pt, note_zip_entry, open_zip_archive, paper_for_locale, parse_paper,
+    recover_zip_entry, RecoveredZip, is_system_entry, read_relationship_targets, resolve_part, strip_bom,
+};
+use crate::assembly::max_entry_bytes;
+use crate::data_files::{data_kind, is_minified, pretty_print};
+use crate::deadline::deadline_passed;
+use crate::external_refs::{external_references_note, find_external_references, ExternalReference};
+use crate::notebook::parse_notebook;
+use crate::records::parse_records;
+use crate::mime::{html_to_text, mht_root, mht_to_text, parse_mime, part_text, MimePart};
+use crate::language::{detect_language, DEFAULT_LANGUAGE_MIN_WORDS, LANGUAGE_SAMPLE_CHARS};
+use crate::docbook::read_docbook;
+use crate::latex::strip_latex;
+use crate::markup::{render_asciidoc, render_rst, RenderedMarkup};
+use crate::pdf::{displayed_page_sizes, displayed_page_sizes_pt, PdfCounter, PdfStrategy};
+use crate::pdf_blank::find_blank_pages;
+use crate::pdf_catalog::read_catalog_counts;
+use crate::pdf_color::analyze_page_colors;
+use crate::pdf_crypt::{unlock, PasswordKind, PdfDecryptor};
+use crate::pdf_portfolio::scan_portfolio;
+use crate::pdf_text::extract_text_stats;
+use crate::pdf_utils::{has_signature, with_scan_budget, xref_shift, ObjectIndex, DEFAULT_SCAN_BUDGET_FACTOR};
+use crate::comic::{page_images, read_comic_info_page_count};
+use crate::images::{read_image_header, IMAGE_HEADER_BYTES};
+use crate::iwork::{iwork_application, read_quicklook_preview};
+use crate::postscript::{postscript_section, scan_dsc, DOS_EPS_MAGIC};
+use crate::producer::{ooxml_producer, pdf_producer, producer_confidence, RELIABLE_PRODUCER_CONFIDENCE};
+use crate::printing::split_oversized_pages;
+use crate::pptx::{read_slide_size, read_slide_texts, SlideSize, PRESENTATION_PART};
+use crate::reading::{html_media, markdown_media, reading_minutes, DEFAULT_READING_WPM};
+use crate::xlsx::{
+    fit_to_page, paginate_rows, read_sheet_layout, workbook_sheets, FittedPages, SheetLayout, DEFAULT_SHEET_MARGIN_MM,
+    WORKBOOK_PART,
+};
+use crate::xps::{
+    find_document_sequence, mm_from_xps_units, read_document_references, read_fixed_page_size, read_page_refs,
+};
+use crate::text_layout::{
+    char_width, clean_terminal_output, count_printed_chars, layout_lines, paginate_cells, printed_chars, rtl_note,
+    LineMetrics, DEFAULT_LINES_PER_PAGE, DEFAULT_TAB_WIDTH,
+};
+use crate::notes::{verbosity, DETAIL_VERBOSITY};
+use crate::ooxml::{attribute_value, detect_conformance, local_name_in, NamespaceFamily, OoxmlNamespace};
+use crate::schema::{
+    EstimateOptions, EstimateResult, EstimatorError, PageRounding, PageSizeMm, PageSizePt, SheetEstimate, TextMetrics,
+};
+use calamine::{Data, Reader, SheetVisible, Xlsx};
+use std::collections::BTreeMap;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Cursor, Read};
 use wasm_bindgen::prelude::*;
 use zip::ZipArchive;
 use quick_xml::Reader as XmlReader;
 use quick_xml::events::Event;
+use quick_xml::name::QName;
+use quick_xml::NsReader;
 
 // Placeholder for PDF.js integration (optional feature)
 // Note: PDF.js integration can be added separately via JavaScript
@@ -37,6 +89,39 @@ pub async fn count_pdf_pages_js(_bytes: &[u8]) -> Result<JsValue, JsValue> {
     Err(JsValue::from_str("PDF.js not integrated"))
 }
 
+/// Resolves the page size to report for non-PDF documents.
+///
+/// The size comes from `EstimateOptions::paper` and is `None` when
+/// `include_page_sizes` is off. When the locale decides the paper, a note
+/// explaining the choice is returned alongside the size.
+fn paper_size(options: &EstimateOptions) -> (Option<(f64, f64)>, Option<String>) {
+    let note = match (&options.custom_paper_mm, &options.default_paper, &options.locale) {
+        (None, None, Some(locale)) => {
+            let choice = paper_for_locale(locale);
+            Some(format!("Paper: {} ({})", choice.name, choice.reason))
+        }
+        _ => None,
+    };
+    (options.wants_page_sizes().then(|| options.paper()), note)
+}
+
+/// Size of the slides of a deck, from its `<p:sldSz>`, or PowerPoint's 4:3
+/// default of 10" × 7.5" (254mm × 190.5mm) when it declares none, unless
+/// `include_page_sizes` is off.
+fn slide_size(options: &EstimateOptions, deck: Option<&SlideSize>) -> Option<(f64, f64)> {
+    let size = deck.and_then(SlideSize::mm).unwrap_or((254.0, 190.5));
+    options.wants_page_sizes().then_some(size)
+}
+
+/// Reads the slide size of an opened PPTX package (see `read_slide_size`).
+fn deck_slide_size(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<SlideSize> {
+    let file = archive.by_name(PRESENTATION_PART).ok()?;
+    note_zip_entry(&file);
+    read_slide_size(BufReader::new(file))
+}
+
+/// Characters on a full page of plain text (heuristic).
+pub const DEFAULT_CHARS_PER_PAGE: usize = 1800;
 
 /// Estimates the number of pages for a plain text file.
 ///
@@ -62,8 +147,18 @@ pub async fn count_pdf_pages_js(_bytes: &[u8]) -> Result<JsValue, JsValue> {
 /// # Notes
 ///
 /// - If the input is not valid UTF-8, returns 0 pages with an error note
-/// - The character count is based on Unicode characters, not bytes
-/// - Pages are rounded up (e.g., 1801 characters = 2 pages with default settings)
+/// - The character count is based on Unicode characters, not bytes; East Asian wide
+///   characters count as two cells and combining marks as none
+/// - When `chars_per_line` is set, lines are laid out instead: tabs expand to the next
+///   `tab_width` stop and long lines wrap onto extra display lines (`lines_per_page` per page,
+///   reduced by `line_spacing` and `paragraph_spacing_lines`)
+/// - Pages are rounded up (e.g., 1801 cells = 2 pages with default settings); `rounding`
+///   can round a partly filled last page to the nearest page or down instead
+/// - With `return_boundaries` (or `page_range`, which needs them), `page_boundaries` holds the
+///   byte offset where each page starts (omitted for terminal output, whose offsets change
+///   when it is cleaned)
+/// - ANSI escape sequences are stripped and carriage-return repaints (progress bars)
+///   collapse to their final state before counting
 ///
 /// # Example
 ///
@@ -77,50 +172,513 @@ pub async fn count_pdf_pages_js(_bytes: &[u8]) -> Result<JsValue, JsValue> {
 /// println!("Estimated {} pages", result.page_count);
 /// ```
 pub fn estimate_text_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
-    let s = match std::str::from_utf8(bytes) {
+    // the byte order mark isn't text; boundaries are shifted past it below
+    let text = strip_bom(bytes);
+    let bom_len = bytes.len() - text.len();
+    let s = match std::str::from_utf8(text) {
         Ok(v) => v,
         Err(_) => {
             return EstimateResult {
                 page_count: 0,
                 page_sizes: vec![],
-                notes: vec!["Text not valid UTF-8".into()],
+                notes: vec!["⚠ Text not valid UTF-8".into()],
+                ..Default::default()
             };
         }
     };
 
-    let chars = s.chars().count();
-    let chars_per_page = options.chars_per_page.unwrap_or(1800); // heuristic ~ 1800 chars/page
-    let pages = (chars + chars_per_page - 1) / chars_per_page;
-
     // decide paper size
-    let (w, h) = if let Some(custom) = options.custom_paper_mm {
-        custom
-    } else if let Some(ref def) = options.default_paper {
-        match def.as_str() {
-            "Letter" | "letter" => letter_mm(),
-            _ => a4_mm(),
-        }
+    let (size, paper_note) = paper_size(options);
+    let mut notes = Vec::new();
+
+    // terminal logs: drop color codes and progress-bar repaints before counting
+    let cleaned = clean_terminal_output(s);
+    if let Some(ref cleaned) = cleaned {
+        notes.push(format!(
+            "Removed {} bytes of ANSI escape sequences; collapsed {} carriage-return repainted lines",
+            cleaned.escape_bytes, cleaned.collapsed_lines
+        ));
+    }
+    let s = cleaned.as_ref().map_or(s, |c| c.text.as_str());
+
+    let (pages, boundaries) = if let Some(chars_per_line) = options.chars_per_line {
+        // line-based mode: lay out lines with wrapping and tab expansion
+        let metrics = line_metrics(options, chars_per_line, DEFAULT_LINES_PER_PAGE);
+        let boundaries = paginate_lines(s, &metrics, options.rounding(), &mut notes);
+        (boundaries.len(), boundaries)
+    } else {
+        let chars = count_printed_chars(s);
+        let chars_per_page = options.chars_per_page.unwrap_or(DEFAULT_CHARS_PER_PAGE);
+        // wide (CJK) characters count as two cells, combining marks as none
+        let (cells, mut boundaries) = paginate_cells(s, chars_per_page);
+        notes.push(format!(
+            "chars: {}, cells: {}, chars_per_page: {}",
+            chars, cells, chars_per_page
+        ));
+        let last_page_cells: usize = boundaries.last().map_or(0, |&start| s[start..].chars().map(char_width).sum());
+        let last_page_fill = last_page_cells as f64 / chars_per_page.max(1) as f64;
+        boundaries.truncate(options.rounding().round_layout(boundaries.len(), last_page_fill));
+        (boundaries.len(), boundaries)
+    };
+    notes.extend(rtl_note(s));
+    notes.extend(rounding_note(options));
+    notes.extend(paper_note);
+
+    // offsets into cleaned text don't map back onto the input
+    let wants_boundaries = options.return_boundaries.unwrap_or(false) || options.page_range.is_some();
+    let page_boundaries = (wants_boundaries && cleaned.is_none())
+        .then(|| boundaries.into_iter().map(|start| start + bom_len).collect());
+
+    let mut result = EstimateResult {
+        page_boundaries,
+        text_metrics: Some(TextMetrics {
+            line_count: s.lines().count(),
+            word_count: s.split_whitespace().count(),
+            char_count: count_printed_chars(s),
+            ..Default::default()
+        }),
+        ..EstimateResult::uniform(pages, size, notes)
+    }
+    .with_heuristic_range();
+    add_language(&mut result, s, options);
+    result
+}
+
+/// Guesses the language of a document's text into `language` when
+/// `detect_language` is set (see the `language` module).
+///
+/// The guess and its confidence are also reported under `language` in
+/// `diagnostics`, and summarized in a note.
+fn add_language(result: &mut EstimateResult, text: &str, options: &EstimateOptions) {
+    if !options.detect_language.unwrap_or(false) {
+        return;
+    }
+    let min_words = options.language_min_words.unwrap_or(DEFAULT_LANGUAGE_MIN_WORDS);
+    let guess = detect_language(text, min_words);
+    result.notes.push(match guess.code {
+        Some(code) => format!("Language: {} (confidence {:.2})", code, guess.confidence),
+        None if guess.words < min_words => format!(
+            "Language not detected: {} words, fewer than language_min_words ({})",
+            guess.words, min_words
+        ),
+        None => "Language not detected: the text matches none of the known languages".into(),
+    });
+    result.language = guess.code.map(str::to_string);
+    let diagnostics = result.diagnostics.get_or_insert_with(|| json!({}));
+    if let Some(diagnostics) = diagnostics.as_object_mut() {
+        diagnostics.insert("language".into(), guess.to_json());
+    }
+}
+
+/// Builds line layout metrics from the options, with format-specific defaults.
+fn line_metrics(
+    options: &EstimateOptions,
+    chars_per_line: usize,
+    default_lines_per_page: usize,
+) -> LineMetrics {
+    LineMetrics {
+        chars_per_line,
+        lines_per_page: options.lines_per_page.unwrap_or(default_lines_per_page),
+        tab_width: options.tab_width.unwrap_or(DEFAULT_TAB_WIDTH) as usize,
+        line_spacing: options.line_spacing.unwrap_or(1.0) as f64,
+        paragraph_spacing_lines: options.paragraph_spacing_lines.unwrap_or(0.0) as f64,
+    }
+}
+
+/// Lays out text line by line, adding layout notes, and returns the page start offsets.
+///
+/// A partly filled last page is kept or dropped according to `rounding`.
+fn paginate_lines(
+    text: &str,
+    metrics: &LineMetrics,
+    rounding: PageRounding,
+    notes: &mut Vec<String>,
+) -> Vec<usize> {
+    let layout = layout_lines(text, metrics);
+    notes.push(format!(
+        "display lines: {}, chars_per_line: {}, lines_per_page: {} ({} after {}x line spacing), tab_width: {}",
+        layout.display_lines,
+        metrics.chars_per_line,
+        metrics.lines_per_page,
+        metrics.effective_lines_per_page(),
+        metrics.line_spacing,
+        metrics.tab_width
+    ));
+    if metrics.paragraph_spacing_lines > 0.0 {
+        notes.push(format!(
+            "paragraph spacing: {} lines",
+            metrics.paragraph_spacing_lines
+        ));
+    }
+    let pages = rounding.round_layout(layout.page_boundaries.len(), layout.last_page_fill(metrics));
+    notes.extend(layout.notes);
+    let mut boundaries = layout.page_boundaries;
+    boundaries.truncate(pages);
+    boundaries
+}
+
+/// Notes the rounding mode of heuristic page counts when it isn't the default.
+fn rounding_note(options: &EstimateOptions) -> Option<String> {
+    let how = match options.rounding() {
+        PageRounding::Ceil => return None,
+        PageRounding::Nearest => "to the nearest page (rounding: nearest)",
+        PageRounding::Floor => "down (rounding: floor)",
+    };
+    Some(format!("Partly filled last pages rounded {}", how))
+}
+
+/// Default printed line width for source code.
+pub const CODE_CHARS_PER_LINE: usize = 100;
+
+/// Default lines per page for source code printed in a monospace font.
+pub const CODE_LINES_PER_PAGE: usize = 60;
+
+/// Estimates the number of pages for a source code file.
+///
+/// Code is paginated with the line-based model using monospace assumptions:
+/// long lines wrap at `chars_per_line` (default 100), tabs expand to the next
+/// `tab_width` stop, and `lines_per_page` defaults to 60. No syntax is parsed.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the source file
+/// * `options` - Estimation options; `skip_blank_lines` drops empty lines before layout
+///
+/// # Returns
+///
+/// Returns an `EstimateResult` with `method` set to `"code-lines"` and the
+/// source line count in `text_metrics`.
+pub fn estimate_code_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    let source = String::from_utf8_lossy(bytes);
+    let lines: Vec<&str> = source.lines().collect();
+    let blank_lines = lines.iter().filter(|l| l.trim().is_empty()).count();
+    let skip_blank = options.skip_blank_lines.unwrap_or(false);
+
+    let text = if skip_blank {
+        lines
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n")
     } else {
-        a4_mm()
+        source.to_string()
+    };
+
+    let mut notes = vec![format!(
+        "{} source lines ({} blank{})",
+        lines.len(),
+        blank_lines,
+        if skip_blank { ", skipped" } else { "" }
+    )];
+    let metrics = line_metrics(
+        options,
+        options.chars_per_line.unwrap_or(CODE_CHARS_PER_LINE),
+        CODE_LINES_PER_PAGE,
+    );
+    let page_count = paginate_lines(&text, &metrics, options.rounding(), &mut notes).len();
+
+    let (size, paper_note) = paper_size(options);
+    notes.extend(rounding_note(options));
+    notes.extend(paper_note);
+
+    EstimateResult {
+        method: Some("code-lines".into()),
+        text_metrics: Some(TextMetrics {
+            line_count: lines.len(),
+            word_count: source.split_whitespace().count(),
+            char_count: count_printed_chars(&source),
+            ..Default::default()
+        }),
+        ..EstimateResult::uniform(page_count, size, notes)
+    }
+    .with_heuristic_range()
+}
+
+/// Estimates the number of pages for a JSON, YAML, or XML data file.
+///
+/// Data is paginated like source code (see `estimate_code_pages`): line by
+/// line, wrapping at `chars_per_line` (default 100), 60 lines per page. The
+/// format is recognized from the content (see the `data_files` module).
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the data file
+/// * `options` - Estimation options; with `pretty_print_minified`, minified
+///   JSON or XML is laid out as if pretty-printed with 2-space indentation
+///   instead of as a few wrapped lines
+///
+/// # Returns
+///
+/// Returns an `EstimateResult` with `method` set to `"data-lines"` and the
+/// source line count in `text_metrics`.
+pub fn estimate_data_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    let source = String::from_utf8_lossy(bytes);
+    let kind = data_kind(&source);
+    let line_count = source.lines().count();
+    let mut notes = vec![format!("{} data, {} lines", kind.name(), line_count)];
+
+    let minified = is_minified(kind, &source);
+    let pretty_print_minified = options.pretty_print_minified.unwrap_or(false);
+    let pretty = if minified && pretty_print_minified { pretty_print(kind, &source) } else { None };
+    let text = match &pretty {
+        Some(pretty) => {
+            notes.push(format!(
+                "Minified {} laid out as pretty-printed with 2-space indentation ({} lines)",
+                kind.name(),
+                pretty.lines().count()
+            ));
+            pretty.as_str()
+        }
+        None if minified && pretty_print_minified => {
+            notes.push(format!("⚠ Minified {} could not be parsed; counted as written", kind.name()));
+            &source
+        }
+        None if minified => {
+            notes.push(format!(
+                "{} looks minified; set pretty_print_minified to count it as pretty-printed",
+                kind.name()
+            ));
+            &source
+        }
+        None => &source,
+    };
+
+    let metrics = line_metrics(
+        options,
+        options.chars_per_line.unwrap_or(CODE_CHARS_PER_LINE),
+        CODE_LINES_PER_PAGE,
+    );
+    let page_count = paginate_lines(text, &metrics, options.rounding(), &mut notes).len();
+
+    let (size, paper_note) = paper_size(options);
+    notes.extend(rounding_note(options));
+    notes.extend(paper_note);
+
+    EstimateResult {
+        method: Some("data-lines".into()),
+        text_metrics: Some(TextMetrics {
+            line_count,
+            word_count: source.split_whitespace().count(),
+            char_count: count_printed_chars(&source),
+            ..Default::default()
+        }),
+        ..EstimateResult::uniform(page_count, size, notes)
+    }
+    .with_heuristic_range()
+}
+
+/// Default printed height of an image output in a notebook, about a plot at page width.
+pub const DEFAULT_IMAGE_HEIGHT_MM: f64 = 90.0;
+
+/// Top and bottom page margins of printed notebooks (nbconvert's 1 inch).
+const NOTEBOOK_MARGIN_MM: f64 = 25.4;
+
+/// Estimates the number of pages for a Jupyter notebook (.ipynb).
+///
+/// Each part of the notebook is measured with its own model (see the
+/// `notebook` module), as a fraction of a page:
+///
+/// - Markdown cells by characters, like Markdown text
+/// - Code cells and text outputs line by line in a monospace font, like source code
+/// - Image outputs as blocks of `image_height_mm` within the page margins
+///
+/// The fractions are added up and rounded once, so short cells share pages.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the `.ipynb` file
+/// * `options` - Estimation options; `chars_per_page` applies to Markdown
+///   cells, and `chars_per_line` and `lines_per_page` to code and outputs
+///
+/// # Errors
+///
+/// Returns `EstimatorError::General` when the input isn't an nbformat 4 notebook.
+pub fn estimate_notebook_pages(bytes: &[u8], options: &EstimateOptions) -> Result<EstimateResult, EstimatorError> {
+    let content = parse_notebook(bytes).map_err(EstimatorError::General)?;
+    // cells are printed one after the other, with a blank line between them
+    let join_cells = |cells: &[String]| {
+        cells.iter().map(|cell| cell.trim_end_matches('\n')).collect::<Vec<_>>().join("\n\n")
     };
 
+    let markdown = join_cells(&content.markdown);
+    let chars_per_page = options.chars_per_page.unwrap_or(DEFAULT_CHARS_PER_PAGE);
+    let (markdown_cells, _) = paginate_cells(&markdown, chars_per_page);
+    let markdown_pages = markdown_cells as f64 / chars_per_page.max(1) as f64;
+
+    let metrics = line_metrics(
+        options,
+        options.chars_per_line.unwrap_or(CODE_CHARS_PER_LINE),
+        CODE_LINES_PER_PAGE,
+    );
+    let lines_per_page = metrics.effective_lines_per_page() as f64;
+    let code_lines = layout_lines(&join_cells(&content.code), &metrics).display_lines;
+    let output_lines = layout_lines(&join_cells(&content.outputs), &metrics).display_lines;
+    let code_pages = code_lines as f64 / lines_per_page;
+    let output_pages = output_lines as f64 / lines_per_page;
+
+    let (_, paper_height) = options.paper();
+    let image_height = options.image_height_mm.unwrap_or(DEFAULT_IMAGE_HEIGHT_MM);
+    let image_pages = content.images as f64 * image_height / (paper_height - 2.0 * NOTEBOOK_MARGIN_MM).max(1.0);
+
+    let total = markdown_pages + code_pages + output_pages + image_pages;
+    let page_count = options.rounding().round(total);
+    let (size, paper_note) = paper_size(options);
+    let mut notes = vec![
+        format!(
+            "Markdown: {} cells, {} chars at {} per page ({:.2} pages)",
+            content.markdown.len(),
+            markdown_cells,
+            chars_per_page,
+            markdown_pages
+        ),
+        format!(
+            "Code: {} cells, {} display lines at {} per page ({:.2} pages)",
+            content.code.len(),
+            code_lines,
+            metrics.effective_lines_per_page(),
+            code_pages
+        ),
+        format!(
+            "Outputs: {} text outputs, {} display lines ({:.2} pages); {} images at {} mm ({:.2} pages)",
+            content.outputs.len(),
+            output_lines,
+            output_pages,
+            content.images,
+            image_height,
+            image_pages
+        ),
+    ];
+    if content.raw_cells > 0 {
+        notes.push(format!("{} raw cells skipped", content.raw_cells));
+    }
+    notes.extend(rounding_note(options));
+    notes.extend(paper_note);
+
+    let all_text: String = [&content.markdown, &content.code, &content.outputs]
+        .into_iter()
+        .map(|cells| join_cells(cells))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let words = all_text.split_whitespace().count();
+    let mut media = markdown_media(&markdown);
+    media.images += content.images;
+    media.code_blocks += content.code.len();
+    let wpm = options.reading_wpm.unwrap_or(DEFAULT_READING_WPM);
+
+    Ok(EstimateResult {
+        method: Some("notebook".into()),
+        text_metrics: Some(TextMetrics {
+            line_count: all_text.lines().count(),
+            word_count: words,
+            char_count: count_printed_chars(&all_text),
+            ..Default::default()
+        }),
+        reading_time_minutes: Some(reading_minutes(words, media, wpm)),
+        ..EstimateResult::uniform(page_count, size, notes)
+    }
+    .with_heuristic_range())
+}
+
+/// Default contacts per printed page of a vCard listing.
+pub const DEFAULT_CONTACTS_PER_PAGE: usize = 8;
+
+/// Default events per printed page of an iCalendar listing.
+pub const DEFAULT_EVENTS_PER_PAGE: usize = 12;
+
+/// Estimates the number of pages for a vCard (.vcf) or iCalendar (.ics) file.
+///
+/// Contacts and events are printed as listings of a few fields each, so they
+/// are counted as records: `contacts_per_page` contacts (default 8) and
+/// `events_per_page` events (default 12) per page. The listing is also laid
+/// out line by line like source code, one `NAME: value` line per property
+/// (see the `records` module), and the line count is used instead when:
+///
+/// - the file has no contacts or events (e.g. only to-dos or time zones)
+/// - the records need more room than the per-page counts allow, such as
+///   events with long descriptions
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the file
+/// * `options` - Estimation options; `chars_per_line` and `lines_per_page`
+///   apply to the line count
+///
+/// # Returns
+///
+/// Returns an `EstimateResult` with `method` set to `"records"` or
+/// `"record-lines"`, and the number of contacts and events in
+/// `text_metrics.record_count`.
+pub fn estimate_records_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    let records = parse_records(bytes);
+    let contacts_per_page = options.contacts_per_page.unwrap_or(DEFAULT_CONTACTS_PER_PAGE);
+    let events_per_page = options.events_per_page.unwrap_or(DEFAULT_EVENTS_PER_PAGE);
+    let contact_pages = records.contacts as f64 / contacts_per_page.max(1) as f64;
+    let event_pages = records.events as f64 / events_per_page.max(1) as f64;
+    let record_pages = contact_pages + event_pages;
+
+    let text = if records.records() > 0 { &records.listing } else { &records.other_listing };
+    let metrics = line_metrics(
+        options,
+        options.chars_per_line.unwrap_or(CODE_CHARS_PER_LINE),
+        CODE_LINES_PER_PAGE,
+    );
+    let display_lines = layout_lines(text, &metrics).display_lines;
+    let line_pages = display_lines as f64 / metrics.effective_lines_per_page() as f64;
+
     let mut notes = Vec::new();
+    if records.contacts > 0 {
+        notes.push(format!(
+            "vCard: {} contacts at {} per page ({:.2} pages)",
+            records.contacts, contacts_per_page, contact_pages
+        ));
+    }
+    if records.events > 0 {
+        notes.push(format!(
+            "iCalendar: {} events at {} per page ({:.2} pages)",
+            records.events, events_per_page, event_pages
+        ));
+    }
     notes.push(format!(
-        "chars: {}, chars_per_page: {}",
-        chars, chars_per_page
+        "{} unfolded lines, {} display lines at {} per page ({:.2} pages)",
+        records.lines,
+        display_lines,
+        metrics.effective_lines_per_page(),
+        line_pages
     ));
+    if records.other_components > 0 {
+        notes.push(format!(
+            "{} other components (time zones, to-dos, alarms) {}",
+            records.other_components,
+            if records.records() > 0 { "not printed" } else { "listed" }
+        ));
+    }
+    let (total, method) = if records.records() == 0 {
+        notes.push("No contacts or events; counted line by line".into());
+        (line_pages, "record-lines")
+    } else if line_pages > record_pages {
+        notes.push("Records are longer than the per-page counts allow; counted line by line".into());
+        (line_pages, "record-lines")
+    } else {
+        (record_pages, "records")
+    };
+    let page_count = options.rounding().round(total);
+    let (size, paper_note) = paper_size(options);
+    notes.extend(rounding_note(options));
+    notes.extend(paper_note);
 
     EstimateResult {
-        page_count: pages,
-        page_sizes: vec![
-            PageSizeMm {
-                width_mm: w,
-                height_mm: h
-            };
-            pages
-        ],
-        notes,
+        method: Some(method.into()),
+        text_metrics: Some(TextMetrics {
+            line_count: records.lines,
+            word_count: text.split_whitespace().count(),
+            char_count: count_printed_chars(text),
+            record_count: Some(records.records()),
+            ..Default::default()
+        }),
+        ..EstimateResult::uniform(page_count, size, notes)
     }
+    .with_heuristic_range()
 }
 
 /// Estimates the number of pages for a Markdown file.
@@ -156,9 +714,288 @@ pub fn estimate_markdown_pages(bytes: &[u8], options: &EstimateOptions) -> Estim
     let mut res = estimate_text_pages(bytes, options);
     res.notes
         .push("Markdown parsed as text; images/embedded content not considered.".into());
+    let media = markdown_media(&String::from_utf8_lossy(bytes));
+    let wpm = options.reading_wpm.unwrap_or(DEFAULT_READING_WPM);
+    res.reading_time_minutes =
+        res.text_metrics.as_ref().map(|metrics| reading_minutes(metrics.word_count, media, wpm));
+    res
+}
+
+/// Estimates the number of pages for an MHT (MHTML) web archive.
+///
+/// The archive is parsed as a MIME message and only its root document (see
+/// `mime::mht_root`), usually quoted-printable HTML, is estimated: its visible
+/// text is paginated like plain text. Resource parts (images, stylesheets) are
+/// skipped, so their base64 payloads don't count as text.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the `.mht` or `.mhtml` file
+/// * `options` - Estimation options (same as `estimate_text_pages`)
+///
+/// # Errors
+///
+/// Returns `EstimatorError::General` when the archive has no HTML or text part.
+pub fn estimate_mht_pages(bytes: &[u8], options: &EstimateOptions) -> Result<EstimateResult, EstimatorError> {
+    let message = parse_mime(bytes);
+    let root = mht_root(&message)
+        .ok_or_else(|| EstimatorError::General("MHT archive has no HTML or text part".into()))?;
+    let text = part_text(root);
+    let resources: Vec<&MimePart> =
+        message.leaves().into_iter().filter(|part| !std::ptr::eq(*part, root)).collect();
+    let resource_bytes: usize = resources.iter().map(|part| part.body.len()).sum();
+
+    let mut res = estimate_text_pages(text.as_bytes(), options);
+    // boundaries index the extracted text, not the archive
+    res.page_boundaries = None;
+    if root.content_type().contains("html") {
+        let media = html_media(&String::from_utf8_lossy(&root.body));
+        let wpm = options.reading_wpm.unwrap_or(DEFAULT_READING_WPM);
+        res.reading_time_minutes =
+            res.text_metrics.as_ref().map(|metrics| reading_minutes(metrics.word_count, media, wpm));
+    }
+    res.notes.push(format!(
+        "MHT web archive: {} root part estimated as text; {} resource parts ({} bytes) skipped",
+        root.content_type(),
+        resources.len(),
+        resource_bytes
+    ));
+    Ok(res)
+}
+
+/// Estimates the number of pages for a reStructuredText (.rst) file.
+///
+/// The source is first reduced to its rendered lines (see the `markup` module):
+/// section underlines and directive boilerplate are dropped, images become
+/// fixed-height blocks, and code and literal blocks are kept verbatim. The
+/// rendered text is then paginated line by line.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the reStructuredText file
+/// * `options` - Estimation options; `chars_per_line` defaults to 80 for markup
+///
+/// # Returns
+///
+/// Returns an `EstimateResult` like `estimate_text_pages`, with a note summarizing
+/// the recognized structure.
+pub fn estimate_rst_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    match std::str::from_utf8(bytes) {
+        Ok(source) => estimate_markup_pages("RST", render_rst(source), options),
+        Err(_) => estimate_text_pages(bytes, options),
+    }
+}
+
+/// Estimates the number of pages for an AsciiDoc (.adoc) file.
+///
+/// Works like `estimate_rst_pages`: `=` headings, `----` listing blocks,
+/// `image::` macros, and attribute entries (`:key: value`, excluded from the
+/// count) are recognized before the rendered text is paginated line by line.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the AsciiDoc file
+/// * `options` - Estimation options; `chars_per_line` defaults to 80 for markup
+pub fn estimate_asciidoc_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    match std::str::from_utf8(bytes) {
+        Ok(source) => estimate_markup_pages("AsciiDoc", render_asciidoc(source), options),
+        Err(_) => estimate_text_pages(bytes, options),
+    }
+}
+
+/// Default printed line width for markup documents paginated line by line.
+pub const MARKUP_CHARS_PER_LINE: usize = 80;
+
+fn estimate_markup_pages(
+    kind: &str,
+    rendered: RenderedMarkup,
+    options: &EstimateOptions,
+) -> EstimateResult {
+    let mut line_options = options.clone();
+    line_options.chars_per_line = options.chars_per_line.or(Some(MARKUP_CHARS_PER_LINE));
+
+    let mut res = estimate_text_pages(rendered.text.as_bytes(), &line_options);
+    // boundaries index the rendered text, not the source
+    res.page_boundaries = None;
+    res.notes.push(format!(
+        "{} rendered: {} headings, {} images, {} code lines, {} source lines excluded",
+        kind, rendered.headings, rendered.images, rendered.code_lines, rendered.excluded_lines
+    ));
     res
 }
 
+/// Confidence assigned to LaTeX estimates, which are made without compiling.
+const LATEX_CONFIDENCE: f64 = 0.3;
+
+/// Estimates the number of pages for a LaTeX source (.tex) file.
+///
+/// The source is stripped down to its prose (see the `latex` module) and the
+/// character heuristic is applied to each run of prose between hard page breaks
+/// (`\newpage`, `\clearpage`). Figures and tables count as a third of a page each.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the LaTeX source
+/// * `options` - Estimation options; `chars_per_page` overrides the document
+///   class default (article: 3000, report: 2700, book: 2400, beamer: 400)
+///
+/// # Returns
+///
+/// Returns an `EstimateResult` with a low confidence and notes describing what
+/// was stripped from the source.
+pub fn estimate_latex_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    let source = String::from_utf8_lossy(bytes);
+    let stripped = strip_latex(&source);
+
+    let class = stripped.document_class.as_deref().unwrap_or("article");
+    let chars_per_page = options.chars_per_page.unwrap_or(match class {
+        "report" => 2700,
+        "book" | "memoir" => 2400,
+        "beamer" => 400,
+        "letter" => 2000,
+        _ => 3000,
+    });
+    let float_chars = chars_per_page / 3;
+
+    let mut page_count = 0;
+    let mut prose_chars = 0;
+    let mut floats = 0;
+    for segment in &stripped.segments {
+        let (cells, _) = paginate_cells(&segment.text, chars_per_page);
+        prose_chars += cells;
+        floats += segment.floats;
+        page_count += (cells + segment.floats * float_chars).div_ceil(chars_per_page.max(1)).max(1);
+    }
+
+    let (size, paper_note) = paper_size(options);
+    let mut notes = vec![
+        format!(
+            "LaTeX source estimated from {} prose characters, chars_per_page: {} (documentclass: {})",
+            prose_chars,
+            chars_per_page,
+            stripped.document_class.as_deref().unwrap_or("unknown")
+        ),
+        format!(
+            "Stripped {} comments and {} bytes of preamble; {} references counted as one word",
+            stripped.comments, stripped.preamble_bytes, stripped.references
+        ),
+        format!(
+            "{} figure/table floats counted as {} characters each; {} hard page breaks",
+            floats,
+            float_chars,
+            stripped.segments.len().saturating_sub(1)
+        ),
+    ];
+    if !stripped.stripped_commands.is_empty() {
+        let commands: Vec<String> = stripped
+            .stripped_commands
+            .iter()
+            .map(|(name, count)| format!("{} x{}", name, count))
+            .collect();
+        notes.push(format!("Removed: {}", commands.join(", ")));
+    }
+    notes.push("Note: LaTeX is estimated without compiling; the page count is rough".into());
+    notes.extend(paper_note);
+
+    EstimateResult {
+        confidence: Some(LATEX_CONFIDENCE),
+        ..EstimateResult::uniform(page_count, size, notes)
+    }
+    .with_heuristic_range()
+}
+
+/// Confidence assigned to DocBook estimates, which are made without rendering.
+const DOCBOOK_CONFIDENCE: f64 = 0.4;
+
+/// Columns a generated DocBook index is set in.
+pub const DOCBOOK_INDEX_COLUMNS: usize = 2;
+
+/// Estimates the number of pages for a DocBook XML document.
+///
+/// Elements are measured with the rules of the `docbook` module: prose is
+/// paginated with the character heuristic, and monospace lines, table rows,
+/// image blocks, and heading space take a line's share of the page each. A
+/// book gets a title page and a table of contents, and its chapters start on
+/// new pages. A generated index takes a line per entry in
+/// `DOCBOOK_INDEX_COLUMNS` columns.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw bytes of the DocBook document
+/// * `options` - Estimation options; `chars_per_page` overrides the default
+///   (book: 2400, other documents: 3000) and `lines_per_page` sets the share of
+///   a line
+///
+/// # Returns
+///
+/// Returns an `EstimateResult` with a low confidence and notes on the elements
+/// found. Malformed XML is estimated as a data file instead.
+pub fn estimate_docbook_pages(bytes: &[u8], options: &EstimateOptions) -> EstimateResult {
+    let layout = match read_docbook(bytes) {
+        Ok(layout) => layout,
+        Err(err) => {
+            let mut result = estimate_data_pages(bytes, options);
+            result.notes.insert(0, format!("⚠ DocBook XML could not be read ({}); estimated as a data file", err));
+            return result;
+        }
+    };
+    let book = layout.root == "book";
+    let chars_per_page = options.chars_per_page.unwrap_or(if book { 2400 } else { 3000 });
+    let lines_per_page = options.lines_per_page.unwrap_or(DEFAULT_LINES_PER_PAGE).max(1);
+    let line_cells = (chars_per_page / lines_per_page).max(1);
+
+    let mut body_pages = 0;
+    let mut prose_cells = 0;
+    let mut block_lines = 0;
+    for segment in &layout.segments {
+        prose_cells += segment.prose_cells;
+        block_lines += segment.lines;
+        let cells = segment.prose_cells.saturating_add(segment.lines.saturating_mul(line_cells));
+        body_pages += cells.div_ceil(chars_per_page.max(1)).max(1);
+    }
+    let body_pages = body_pages.max(1);
+    let title_pages = usize::from(book);
+    let toc_pages = layout.toc_lines().div_ceil(lines_per_page);
+    let index_pages = layout.index_lines().div_ceil(lines_per_page.saturating_mul(DOCBOOK_INDEX_COLUMNS));
+    let page_count = title_pages + toc_pages + body_pages + index_pages;
+
+    let (size, paper_note) = paper_size(options);
+    let mut notes = vec![
+        format!(
+            "DocBook {} estimated from {} prose characters and {} block lines, chars_per_page: {}, lines_per_page: {}",
+            layout.root, prose_cells, block_lines, chars_per_page, lines_per_page
+        ),
+        format!(
+            "{} chapters, {} sections, {} image blocks, {} monospace lines, {} table rows",
+            layout.chapters, layout.sections, layout.images, layout.monospace_lines, layout.table_rows
+        ),
+    ];
+    if book && layout.segments.len() > 1 {
+        notes.push(format!("{} parts of the book start on a new page", layout.segments.len()));
+    }
+    if title_pages + toc_pages + index_pages > 0 {
+        notes.push(format!(
+            "Generated pages: {} title, {} table of contents ({} entries), {} index ({} entries)",
+            title_pages,
+            toc_pages,
+            layout.toc_lines(),
+            index_pages,
+            layout.index_lines()
+        ));
+    }
+    notes.push("Note: DocBook is estimated without rendering; the page count is rough".into());
+    notes.extend(paper_note);
+
+    EstimateResult {
+        confidence: Some(DOCBOOK_CONFIDENCE),
+        ..EstimateResult::uniform(page_count, size, notes)
+    }
+    .with_heuristic_range()
+}
+
+/// Fit-to-page scale in percent below which a sheet gets a readability warning.
+const LOW_FIT_SCALE_PERCENT: u32 = 50;
+
 /// Estimates the number of pages for an Excel (.xlsx) file.
 ///
 /// This function parses the Excel workbook and estimates pages based on the number
@@ -170,8 +1007,13 @@ pub fn estimate_markdown_pages(bytes: &[u8], options: &EstimateOptions) -> Estim
 /// * `bytes` - The raw bytes of the Excel file
 /// * `options` - Estimation options including:
 ///   - `rows_per_page`: Number of rows per printed page (default: 40)
+///   - `columns_per_page`: Number of columns per printed page (default: sheets are one page wide)
+///   - `xlsx_margins_mm`: Top and bottom margins for row-height pagination (default: the sheet's own)
+///   - `xlsx_sheets` / `xlsx_sheet_indices`: Sheets to estimate (default: all visible sheets)
+///   - `xlsx_sheet_paper`: Paper of particular sheets (default: `default_paper` for every sheet)
 ///   - `default_paper`: Paper size ("Letter" or "A4")
 ///   - `custom_paper_mm`: Custom paper dimensions in millimeters
+///   - `deadline_ms`: Time after which the sheets after the first are left unread (see the `deadline` module)
 ///
 /// # Returns
 ///
@@ -185,8 +1027,24 @@ pub fn estimate_markdown_pages(bytes: &[u8], options: &EstimateOptions) -> Estim
 /// # Sheet Processing
 ///
 /// - Only non-empty rows are counted (rows with at least one non-empty cell)
-/// - Empty sheets contribute 0 pages to the total
+/// - Rows are paginated by their heights (see `xlsx::paginate_rows`) against the
+///   paper height less the margins; sheets without row heights, or any sheet when
+///   `rows_per_page` is given, use a fixed number of rows per page. A partly
+///   filled last page is rounded according to `rounding`
+/// - Empty sheets contribute 0 pages to the total, unless `xlsx_min_pages_per_visible_sheet`
+///   raises every estimated sheet (hidden and unselected ones excluded) to that many pages;
+///   such sheets are marked `floored`
 /// - Unreadable sheets are noted but don't cause the estimation to fail
+/// - Hidden sheets are skipped, since Excel doesn't print them
+/// - `xlsx_sheets` (names, case-insensitive) and `xlsx_sheet_indices` (0-based)
+///   select the sheets to estimate; together they select the union. A selection
+///   overrides visibility, so a selected hidden sheet is estimated. Empty lists
+///   are treated as no selection, not as selecting nothing
+/// - Sheets set to fit to page count their fitted pages (see `xlsx::fit_to_page`),
+///   with a warning when the implied scale is below 50%
+/// - Sheets named in `xlsx_sheet_paper` are paginated on their own paper, and
+///   the default 40 rows per page scale with its height. `page_sizes` then lists
+///   each sheet's pages on its paper, in sheet order
 ///
 /// # Example
 ///
@@ -205,69 +1063,306 @@ pub fn estimate_xlsx_pages(
     bytes: &[u8],
     options: &EstimateOptions,
 ) -> Result<EstimateResult, EstimatorError> {
+    if is_encrypted_office_package(bytes) {
+        return Err(EstimatorError::EncryptedDocument);
+    }
     let cursor = Cursor::new(bytes);
-    let mut xlsx = Xlsx::new(cursor).map_err(|e| EstimatorError::XlsxError(format!("{:?}", e)))?;
-    let rows_per_page = options.rows_per_page.unwrap_or(40); // heuristic
-    let (w, h) = if let Some(custom) = options.custom_paper_mm {
-        custom
-    } else if let Some(ref def) = options.default_paper {
-        match def.as_str() {
-            "Letter" | "letter" => letter_mm(),
-            _ => a4_mm(),
+    let mut xlsx = match Xlsx::new(cursor) {
+        Ok(xlsx) => xlsx,
+        Err(e) => {
+            let error = EstimatorError::XlsxError(format!("{:?}", e));
+            if open_zip_archive(bytes, "XLSX").is_ok() {
+                return Err(error);
+            }
+            return estimate_rebuilt_package(bytes, WORKBOOK_PART, estimate_xlsx_pages, options).ok_or(error);
         }
-    } else {
-        a4_mm()
     };
+    // sheet layouts are read as each sheet is estimated, so a deadline leaves the rest unread
+    let (metadata, is_signed, ooxml_conformance, producer, external, declared, mut archive) =
+        match open_zip_archive(bytes, "XLSX") {
+            Ok(mut archive) => {
+                let declared = workbook_sheets(&mut archive);
+                let conformance = detect_conformance(&mut archive, WORKBOOK_PART).map(|c| c.as_str().to_string());
+                let producer = ooxml_producer(&mut archive);
+                let external = find_external_references(&mut archive);
+                let signed = is_signed_package(&mut archive);
+                let core = read_core_properties(&mut archive);
+                (core, signed, conformance, producer, external, declared, Some(archive))
+            }
+            Err(_) => (None, false, None, None, Vec::new(), Vec::new(), None),
+        };
+    let rows_per_page = options.rows_per_page.unwrap_or(40); // heuristic
+    let min_pages = options.xlsx_min_pages_per_visible_sheet.unwrap_or(0);
+    let (size, paper_note) = paper_size(options);
+    let default_paper = options.paper();
+    let sheet_papers = options.xlsx_sheet_paper.as_ref();
 
+    let detail = verbosity(options) >= DETAIL_VERBOSITY;
     let mut total_pages = 0usize;
-    let mut notes = Vec::new();
-    let mut per_page_sizes = Vec::new();
+    let mut notes: Vec<String> = paper_note.into_iter().chain(rounding_note(options)).collect();
+    let mut sheets = Vec::new();
+    let mut height_sheets = 0usize;
+    let mut unread_sheets = Vec::new();
+
+    // an empty selection list means no filter, like an absent one
+    let selected_names = options.xlsx_sheets.as_deref().unwrap_or_default();
+    let selected_indices = options.xlsx_sheet_indices.as_deref().unwrap_or_default();
+    let filtered = !selected_names.is_empty() || !selected_indices.is_empty();
+    // tab order is the order of `xl/workbook.xml`, whatever the order of the parts in the ZIP
+    let declared_position = |name: &str| declared.iter().position(|sheet| sheet.name == name).unwrap_or(usize::MAX);
+    let mut sheet_list = xlsx.sheets_metadata().to_owned();
+    sheet_list.sort_by_key(|sheet| declared_position(&sheet.name));
+    for name in selected_names {
+        if !sheet_list.iter().any(|sheet| sheet.name.eq_ignore_ascii_case(name)) {
+            notes.push(format!("⚠ Selected sheet '{}' not found in the workbook", name));
+        }
+    }
+    for index in selected_indices.iter().filter(|i| **i >= sheet_list.len()) {
+        notes.push(format!("⚠ Selected sheet index {} is out of range ({} sheets)", index, sheet_list.len()));
+    }
+    for name in sheet_papers.into_iter().flat_map(|papers| papers.keys()) {
+        if !sheet_list.iter().any(|sheet| sheet.name.eq_ignore_ascii_case(name)) {
+            notes.push(format!("⚠ xlsx_sheet_paper names sheet '{}', which is not in the workbook", name));
+        }
+    }
 
-    for sheet_name in xlsx.sheet_names().to_owned() {
+    for (index, sheet) in sheet_list.into_iter().enumerate() {
+        let sheet_name = sheet.name;
+        let hidden = sheet.visible != SheetVisible::Visible;
+        let declared_sheet = declared.iter().find(|declared| declared.name == sheet_name);
+        let sheet_id = declared_sheet.and_then(|sheet| sheet.sheet_id);
+        // an explicit selection overrides visibility
+        let skip_reason = if filtered {
+            let selected = selected_indices.contains(&index)
+                || selected_names.iter().any(|name| name.eq_ignore_ascii_case(&sheet_name));
+            (!selected).then_some("not selected")
+        } else {
+            hidden.then_some("hidden")
+        };
+        if let Some(reason) = skip_reason {
+            if detail {
+                notes.push(format!("Sheet '{}' skipped ({})", sheet_name, reason));
+            }
+            sheets.push(SheetEstimate { hidden, skipped: true, sheet_id, ..SheetEstimate::empty(sheet_name) });
+            continue;
+        }
+        // the first sheet estimated is always read, so a late workbook still gets a partial count
+        if sheets.iter().any(|sheet| !sheet.skipped) && deadline_passed() {
+            unread_sheets.push(format!("'{}'", sheet_name));
+            sheets.push(SheetEstimate { hidden, skipped: true, sheet_id, ..SheetEstimate::empty(sheet_name) });
+            continue;
+        }
         match xlsx.worksheet_range(&sheet_name) {
             Ok(range) => {
-                // count non-empty rows
+                let layout = match (archive.as_mut(), declared_sheet.and_then(|sheet| sheet.part.as_deref())) {
+                    (Some(archive), Some(part)) => read_sheet_layout(archive, part),
+                    _ => SheetLayout::default(),
+                };
+                let paper_override = sheet_papers
+                    .and_then(|papers| papers.iter().find(|(name, _)| name.eq_ignore_ascii_case(&sheet_name)))
+                    .and_then(|(_, paper)| parse_paper(paper));
+                let paper = paper_override.unwrap_or(default_paper);
+                // the default rows per page suit the options' paper; explicit ones apply as given
+                let rows_per_page = match (options.rows_per_page, paper_override) {
+                    (None, Some((_, height))) => {
+                        ((rows_per_page as f64 * height / default_paper.1).round() as usize).max(1)
+                    }
+                    _ => rows_per_page,
+                };
+                if let Some((width, height)) = paper_override {
+                    notes.push(format!(
+                        "Sheet '{}' paginated on {} × {} mm paper (xlsx_sheet_paper)",
+                        sheet_name, width, height
+                    ));
+                }
+                // count non-empty rows and columns
                 let mut last_row_index = 0usize;
+                let mut last_column_index = 0usize;
                 for (ridx, row) in range.rows().enumerate() {
                     // treat row as non-empty if any cell non-empty
-                    if row.iter().any(|c| !matches!(c, Data::Empty)) {
+                    if let Some(cidx) = row.iter().rposition(|c| !matches!(c, Data::Empty)) {
                         last_row_index = ridx + 1;
+                        last_column_index = last_column_index.max(cidx + 1);
                     }
                 }
-                let pages_for_sheet = (last_row_index + rows_per_page - 1) / rows_per_page;
-                if pages_for_sheet > 0 {
-                    total_pages += pages_for_sheet;
-                    per_page_sizes.extend(
-                        std::iter::repeat(PageSizeMm {
-                            width_mm: w,
-                            height_mm: h,
-                        })
-                        .take(pages_for_sheet),
-                    );
+                // paginate by row heights unless rows_per_page was given explicitly
+                let first_row = range.start().map_or(1, |(row, _)| row as usize + 1);
+                let (top, bottom) = options
+                    .xlsx_margins_mm
+                    .or(layout.margins_mm)
+                    .unwrap_or((DEFAULT_SHEET_MARGIN_MM, DEFAULT_SHEET_MARGIN_MM));
+                let printable_height = paper.1 - top - bottom;
+                let by_height = match options.rows_per_page {
+                    Some(_) => None,
+                    None if last_row_index == 0 => None,
+                    None => paginate_rows(&layout, first_row..=first_row + last_row_index - 1, printable_height),
+                };
+                if options.rows_per_page.is_none() && layout.has_row_heights() && printable_height <= 0.0 {
                     notes.push(format!(
-                        "Sheet '{}' rows: {}, pages: {}",
-                        sheet_name, last_row_index, pages_for_sheet
+                        "⚠ Margins of sheet '{}' leave no room for rows; using {} rows per page",
+                        sheet_name, rows_per_page
                     ));
-                } else {
+                }
+                // extent at 100% in pages; without columns_per_page a sheet is one page wide
+                let natural_tall = by_height.map_or(last_row_index as f64 / rows_per_page as f64, |p| p.pages_tall);
+                let natural_wide = match options.columns_per_page {
+                    _ if last_row_index == 0 => 0.0,
+                    Some(columns_per_page) => last_column_index as f64 / columns_per_page as f64,
+                    None => 1.0,
+                };
+                let rounding = options.rounding();
+                let pages_tall = match by_height {
+                    Some(p) => rounding.round_layout(p.pages, p.last_page_fill),
+                    None => rounding.round(natural_tall),
+                };
+                let natural_pages = rounding.round(natural_wide) * pages_tall;
+                // scaling down never adds pages, however the natural count was rounded
+                let fitted = layout.fit_to_page.then(|| {
+                    let fit = fit_to_page(natural_wide, natural_tall, layout.fit_to_width, layout.fit_to_height);
+                    FittedPages { pages: fit.pages.min(natural_pages), ..fit }
+                });
+                let pages_for_sheet = fitted.map_or(natural_pages, |fit| fit.pages);
+                if by_height.is_some() {
+                    height_sheets += 1;
+                }
+                if pages_for_sheet > 0 {
+                    total_pages += pages_for_sheet;
+                    if detail {
+                        let row_basis = match by_height {
+                            Some(p) => format!("average row height {:.1} mm", p.average_row_height_mm),
+                            None => format!("{} rows per page", rows_per_page),
+                        };
+                        notes.push(format!(
+                            "Sheet '{}' rows: {}, pages: {} ({})",
+                            sheet_name, last_row_index, pages_for_sheet, row_basis
+                        ));
+                    }
+                } else if detail {
                     notes.push(format!("Sheet '{}' empty; 0 pages", sheet_name));
                 }
+                // the floor only raises sheets that are estimated; skipped ones were handled above
+                let floored = pages_for_sheet < min_pages;
+                if floored {
+                    total_pages += min_pages - pages_for_sheet;
+                    if detail {
+                        notes.push(format!(
+                            "Sheet '{}' raised from {} to {} pages (xlsx_min_pages_per_visible_sheet)",
+                            sheet_name, pages_for_sheet, min_pages
+                        ));
+                    }
+                }
+                if let Some(fit) = fitted
+                    && fit.pages > 0
+                {
+                    if detail {
+                        notes.push(format!(
+                            "Sheet '{}' fit to {} × {} pages (0 = as many as needed): {} natural pages, {} at {}%",
+                            sheet_name,
+                            layout.fit_to_width,
+                            layout.fit_to_height,
+                            natural_pages,
+                            fit.pages,
+                            fit.scale_percent
+                        ));
+                    }
+                    if fit.scale_percent < LOW_FIT_SCALE_PERCENT {
+                        notes.push(format!(
+                            "⚠ Sheet '{}' prints at {}% to fit to page; text may be hard to read",
+                            sheet_name, fit.scale_percent
+                        ));
+                    }
+                }
+                sheets.push(SheetEstimate {
+                    name: sheet_name,
+                    sheet_id,
+                    rows: last_row_index,
+                    columns: last_column_index,
+                    pages: pages_for_sheet.max(min_pages),
+                    natural_pages,
+                    fitted_pages: fitted.map(|fit| fit.pages),
+                    scale_percent: fitted.map(|fit| fit.scale_percent),
+                    readable: true,
+                    hidden,
+                    skipped: false,
+                    floored,
+                    paper_mm: Some(paper),
+                });
             }
             Err(_) => {
-                notes.push(format!("Could not read sheet '{}'", sheet_name));
+                notes.push(format!("⚠ Could not read sheet '{}'", sheet_name));
+                sheets.push(SheetEstimate { readable: false, hidden, sheet_id, ..SheetEstimate::empty(sheet_name) });
             }
         }
     }
+    if !unread_sheets.is_empty() {
+        notes.push(format!(
+            "⚠ {} sheets not read before the deadline and not counted: {}",
+            unread_sheets.len(),
+            unread_sheets.join(", ")
+        ));
+    }
+
+    if !detail {
+        let with_content = sheets.iter().filter(|s| s.pages > 0 && s.rows > 0).count();
+        let skipped = sheets.iter().filter(|s| s.skipped).count();
+        let floored = sheets.iter().filter(|s| s.floored).count();
+        let row_basis = if height_sheets == 0 {
+            format!("{} rows per page", rows_per_page)
+        } else if height_sheets == with_content {
+            "rows paginated by their heights".to_string()
+        } else {
+            format!("rows paginated by their heights ({} sheets) or {} per page", height_sheets, rows_per_page)
+        };
+        let skipped_note = if skipped > 0 { format!(", {} skipped", skipped) } else { String::new() };
+        let floored_note =
+            if floored > 0 { format!(", {} raised to the {}-page minimum", floored, min_pages) } else { String::new() };
+        notes.insert(
+            0,
+            format!(
+                "{} sheets ({} with content, {} empty{}{}); {}",
+                sheets.len(),
+                with_content,
+                sheets.iter().filter(|s| s.readable && !s.skipped).count() - with_content,
+                skipped_note,
+                floored_note,
+                row_basis
+            ),
+        );
+    }
 
-    if total_pages == 0 {
+    if total_pages == 0 && filtered {
+        notes.push("⚠ The selected sheets are empty or unreadable; returning 0 pages.".into());
+    } else if total_pages == 0 {
         // maybe workbook is empty
-        notes.push("Workbook appears empty or unreadable; returning 0 pages.".into());
+        notes.push("⚠ Workbook appears empty or unreadable; returning 0 pages.".into());
     }
 
-    Ok(EstimateResult {
-        page_count: total_pages,
-        page_sizes: per_page_sizes,
-        notes,
-    })
+    // sheets on their own paper list their pages in sheet order
+    let own_paper = sheets.iter().any(|sheet| sheet.paper_mm.is_some_and(|paper| paper != default_paper));
+    let per_sheet_sizes = (size.is_some() && own_paper).then(|| {
+        sheets
+            .iter()
+            .flat_map(|sheet| {
+                let (width_mm, height_mm) = sheet.paper_mm.unwrap_or(default_paper);
+                std::iter::repeat_n(PageSizeMm { width_mm, height_mm }, sheet.pages)
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut result = EstimateResult {
+        metadata,
+        sheets: Some(sheets),
+        is_signed,
+        ooxml_conformance,
+        producer,
+        ..EstimateResult::uniform(total_pages, size, notes)
+    }
+    .with_heuristic_range();
+    if let Some(page_sizes) = per_sheet_sizes {
+        result.set_orientation_counts(&page_sizes);
+        result.page_sizes = page_sizes;
+    }
+    add_external_references(&mut result, &external);
+    Ok(result)
 }
 
 /// Estimates the number of pages in a PDF file using simple regex parsing.
@@ -275,69 +1370,242 @@ pub fn estimate_xlsx_pages(
 /// This is a fallback method for synchronous PDF processing. For better accuracy
 /// and reliability, use the async `estimate_pdf_with_pdfjs` function which uses PDF.js.
 ///
-/// This function counts PDF page objects by searching for `/Type /Page` patterns in the PDF structure.
+/// This function counts pages with the default `PdfCounter` pipeline: it searches for
+/// `/Type /Page` patterns in the PDF structure and falls back to walking the page tree.
 ///
 /// # Parameters
 ///
 /// * `bytes` - The raw PDF file bytes
-/// * `_options` - Estimation options (currently unused for PDFs, as page dimensions are extracted from the PDF)
+/// * `options` - Estimation options; `analyze_color` enables per-page color detection,
+///   `exclude_blank_pages` the blank page count, `extended_pdf_info` the
+///   bookmark, named destination, and attachment counts,
+///   `extract_pdf_text_metrics` the word and character counts, and
+///   `pdf_password` opens encrypted files (see the `pdf_crypt` module).
+///   `pdf_scan_budget_factor` caps the bytes the page count may examine, and
+///   `deadline_ms` the time the estimate may take (see the `deadline` module)
+///
+/// Portfolios whose page tree holds only a cover sheet get a warning naming the
+/// number of embedded documents (see the `pdf_portfolio` module). Their pages
+/// are added by `estimate_bytes` when `estimate_attachments` is set.
 ///
 /// # Returns
 ///
 /// Returns a `Result` containing the `EstimateResult` with page count and dimensions,
 /// This is a fallback method for synchronous PDF processing.
-/// or an `EstimatorError` if the PDF cannot be parsed.
+/// or an `EstimatorError` if the PDF cannot be parsed, or `ParseBudgetExceeded`
+/// when counting its pages runs out of scan budget (see the `pdf_utils` module),
+/// or `DeadlineExceeded` when the deadline passes before a page is counted.
 pub fn estimate_pdf_pages(
     bytes: &[u8],
-    _options: &EstimateOptions,
+    options: &EstimateOptions,
 ) -> Result<EstimateResult, EstimatorError> {
-    // Convert bytes to string for pattern matching
-    let pdf_str = String::from_utf8_lossy(bytes);
-    
-    // Count occurrences of /Type /Page (but not /Type /Pages)
-    // This is a simple heuristic that works for most PDFs
-    let mut page_count = 0;
-    let mut search_pos = 0;
-    
-    while let Some(pos) = pdf_str[search_pos..].find("/Type") {
-        let abs_pos = search_pos + pos;
-        let remaining = &pdf_str[abs_pos..];
-        
-        // Check if this is "/Type /Page" or "/Type/Page"
-        if remaining.starts_with("/Type /Page") || remaining.starts_with("/Type/Page") {
-            // Make sure it's not "/Type /Pages"
-            let after_page = if remaining.starts_with("/Type /Page") {
-                &remaining[11..]
-            } else {
-                &remaining[10..]
-            };
-            
-            // Check the character after "Page" is not 's'
-            if !after_page.starts_with('s') {
-                page_count += 1;
-            }
-        }
-        
-        search_pos = abs_pos + 5; // Move past "/Type"
+    // Count occurrences of /Type /Page (but not /Type /Pages), falling back
+    // to the page tree when the scan finds nothing
+    let budget = options.pdf_scan_budget_factor.unwrap_or(DEFAULT_SCAN_BUDGET_FACTOR).saturating_mul(bytes.len());
+    let (outcome, exceeded) = with_scan_budget(budget, || PdfCounter::default().count(bytes));
+    let page_count = outcome.count;
+    if exceeded {
+        return Err(EstimatorError::ParseBudgetExceeded { budget, pages_found: page_count });
     }
-    
+    let out_of_time = deadline_passed();
+    if out_of_time && page_count == 0 {
+        return Err(EstimatorError::DeadlineExceeded { deadline_ms: options.deadline_ms.unwrap_or_default() });
+    }
+
     if page_count == 0 {
         return Err(EstimatorError::PdfError(
             "No pages found in PDF. File may be corrupted or use an unsupported format.".to_string(),
         ));
     }
-    
-    // Use A4 as default page size for PDFs
-    let (width_mm, height_mm) = a4_mm();
-    
-    Ok(EstimateResult {
-        page_count,
-        page_sizes: vec![PageSizeMm { width_mm, height_mm }; page_count],
-        notes: vec![
-            format!("PDF has {} pages (estimated using simple parsing)", page_count),
-            "⚠ For more accurate results, use the async estimate_pdf_with_pdfjs function".to_string(),
-        ],
-    })
+
+    let source = match outcome.strategy {
+        Some(PdfStrategy::PageTree) => "from the page tree",
+        _ => "estimated using simple parsing",
+    };
+    let mut notes = vec![format!("PDF has {} pages ({})", page_count, source)];
+    notes.extend(outcome.notes);
+    notes.push("⚠ For more accurate results, use the async estimate_pdf_with_pdfjs function".to_string());
+    // the object index reads the whole file again; a late count goes out on A4 as it is
+    if out_of_time {
+        return Ok(EstimateResult::uniform(page_count, options.wants_page_sizes().then(a4_mm), notes));
+    }
+
+    let index = ObjectIndex::build(bytes);
+    let encrypted = index.encryption().is_some();
+    let decryptor = match options.pdf_password.as_deref() {
+        Some(password) => unlock(bytes, &index, password)?,
+        // files that only restrict permissions open with the empty user password
+        None => unlock(bytes, &index, "").ok().flatten(),
+    };
+    let portfolio = scan_portfolio(&index);
+    if portfolio.covers_cover_sheet_only(page_count) {
+        notes.insert(
+            0,
+            format!(
+                "⚠ PDF portfolio detected; count covers the cover sheet only; {} embedded documents present",
+                portfolio.embedded_files.len()
+            ),
+        );
+        if portfolio.pages_roots > 1 {
+            notes.push(format!("{} separate page trees found; only the catalog's is counted", portfolio.pages_roots));
+        }
+    } else if !portfolio.embedded_files.is_empty() {
+        notes.push(format!("{} embedded files (not counted)", portfolio.embedded_files.len()));
+    }
+    if let Some(shift) = xref_shift(bytes) {
+        notes.push(format!(
+            "Cross-reference offsets are shifted by {} bytes (the file was altered in transit); \
+             objects were located by their headers instead",
+            shift
+        ));
+    }
+    if encrypted {
+        notes.push(match &decryptor {
+            Some(decryptor) if options.pdf_password.is_some() => {
+                let password = match decryptor.opened_with {
+                    PasswordKind::User => "user",
+                    PasswordKind::Owner => "owner",
+                };
+                format!("PDF is encrypted ({}); opened with the {} password", decryptor.describe(), password)
+            }
+            Some(decryptor) => format!("PDF is encrypted ({}) without a user password", decryptor.describe()),
+            None => "PDF is encrypted; pages are counted, but its producer and text need pdf_password".to_string(),
+        });
+    }
+
+    // page sizes as displayed, in points as written, when the page tree lists
+    // every counted page; A4 stands in otherwise
+    let sizes_pt = displayed_page_sizes_pt(&index).filter(|sizes| sizes.len() == page_count);
+    let mut result = EstimateResult::uniform(page_count, options.wants_page_sizes().then(a4_mm), notes);
+    if let Some(sizes) = sizes_pt.as_ref().filter(|_| options.wants_page_sizes()) {
+        result.page_sizes = sizes.iter().map(PageSizePt::to_mm).collect();
+    }
+    result.is_signed = has_signature(bytes);
+    result.encrypted = encrypted;
+    // encrypted strings read as noise without the key
+    if !encrypted || decryptor.is_some() {
+        result.producer = pdf_producer(&index, decryptor.as_ref());
+    }
+    // orientation needs every page's real size, whether or not `page_sizes` is reported
+    let displayed = displayed_page_sizes(&index).unwrap_or_default();
+    result.set_orientation_counts(&displayed);
+    if options.split_oversized_pages.unwrap_or(false) {
+        split_oversized_pages(&mut result, &displayed, options);
+    }
+    if options.include_point_sizes.unwrap_or(false) && options.wants_page_sizes() {
+        // when the page tree lists fewer pages than were counted, `page_sizes` is converted instead
+        result.page_sizes_pt = sizes_pt;
+    }
+    if result.portrait_pages.is_some_and(|pages| pages < page_count) {
+        result.notes.push(format!(
+            "Orientation: {} portrait, {} landscape, {} square pages",
+            result.portrait_pages.unwrap_or(0),
+            result.landscape_pages.unwrap_or(0),
+            result.square_pages.unwrap_or(0)
+        ));
+    }
+
+    if options.analyze_color.unwrap_or(false) && !deadline_passed() {
+        match analyze_page_colors(bytes) {
+            Some(flags) => {
+                let color = flags.iter().filter(|c| **c).count();
+                result.notes.push(format!(
+                    "Color analysis: {} color pages, {} grayscale pages",
+                    color,
+                    flags.len() - color
+                ));
+                result.color_pages = Some(color);
+                result.grayscale_pages = Some(flags.len() - color);
+                result.color_page_flags = Some(flags);
+            }
+            None => result
+                .notes
+                .push("⚠ Color analysis unavailable: page tree could not be resolved".into()),
+        }
+    }
+
+    if options.exclude_blank_pages.unwrap_or(false) && !deadline_passed() {
+        match find_blank_pages(bytes) {
+            Some(blank) => {
+                if !blank.is_empty() {
+                    let listed: Vec<String> = blank.iter().map(|i| (i + 1).to_string()).collect();
+                    result.notes.push(format!(
+                        "{} blank pages excluded from non_blank_pages (pages {})",
+                        blank.len(),
+                        listed.join(", ")
+                    ));
+                }
+                result.non_blank_pages = Some(page_count.saturating_sub(blank.len()));
+            }
+            None => result
+                .notes
+                .push("⚠ Blank page detection unavailable: page tree could not be resolved".into()),
+        }
+    }
+
+    if options.extended_pdf_info.unwrap_or(false) && !deadline_passed() {
+        let counts = read_catalog_counts(bytes);
+        result.bookmark_count = counts.bookmarks;
+        result.named_destination_count = counts.named_destinations;
+        result.attachment_count = counts.attachments;
+        result.notes.extend(counts.notes);
+    }
+
+    if options.extract_pdf_text_metrics.unwrap_or(false) && !deadline_passed() {
+        if encrypted && decryptor.is_none() {
+            result.notes.push("⚠ Text metrics unavailable: the PDF is encrypted (set pdf_password)".into());
+        } else {
+            add_pdf_text_metrics(bytes, decryptor.as_ref(), options, &mut result);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Counts the words and characters of a PDF's pages into `text_metrics` (see the `pdf_text` module),
+/// and guesses their language when `detect_language` is set.
+fn add_pdf_text_metrics(
+    bytes: &[u8],
+    decryptor: Option<&PdfDecryptor>,
+    options: &EstimateOptions,
+    result: &mut EstimateResult,
+) {
+    let Some(stats) = extract_text_stats(bytes, decryptor) else {
+        result.notes.push("⚠ Text metrics unavailable: page tree could not be resolved".into());
+        return;
+    };
+    result.notes.push(format!(
+        "Text: about {} words and {} characters on {} pages ({:.0} words per page, confidence {:.2})",
+        stats.words,
+        stats.chars,
+        stats.pages,
+        stats.words_per_page(),
+        stats.confidence()
+    ));
+    if stats.words == 0 {
+        result.notes.push("No text found; the PDF may be scanned or have its text as outlines".into());
+    }
+    if stats.undecoded_glyphs > 0 {
+        result.notes.push(format!(
+            "⚠ {} glyphs in fonts without a ToUnicode map could not be decoded; their words were estimated",
+            stats.undecoded_glyphs
+        ));
+    }
+    if stats.skipped_streams > 0 {
+        result.notes.push(format!(
+            "⚠ {} content streams with filters other than FlateDecode were skipped",
+            stats.skipped_streams
+        ));
+    }
+    result.text_metrics = Some(TextMetrics {
+        line_count: stats.lines,
+        word_count: stats.words,
+        char_count: stats.chars,
+        words_per_page: Some(stats.words_per_page()),
+        confidence: Some(stats.confidence()),
+        ..Default::default()
+    });
+    add_language(result, &stats.sample, options);
 }
 
 /// Estimates the number of pages in a Word document (.docx).
@@ -361,22 +1629,38 @@ pub fn estimate_pdf_pages(
 /// - The page count is extracted directly from document metadata (exact count)
 /// - If the metadata doesn't contain page count, attempts to estimate from content
 /// - Paper size is set based on options or defaults to A4
+/// - With `return_boundaries`, `page_start_paragraphs` lists the paragraph each
+///   page starts in, from the page breaks Word recorded (see `docx_page_start_paragraphs`)
 pub fn estimate_docx_pages(
     bytes: &[u8],
     options: &EstimateOptions,
 ) -> Result<EstimateResult, EstimatorError> {
-    let cursor = Cursor::new(bytes);
-    let mut archive = ZipArchive::new(cursor)
-        .map_err(|e| EstimatorError::General(format!("Failed to open DOCX as ZIP: {:?}", e)))?;
+    if is_encrypted_office_package(bytes) {
+        return Err(EstimatorError::EncryptedDocument);
+    }
+    let mut archive = match open_zip_archive(bytes, "DOCX") {
+        Ok(archive) => archive,
+        Err(e) => {
+            return estimate_rebuilt_package(bytes, "word/document.xml", estimate_docx_pages, options)
+                .or_else(|| estimate_truncated_docx(bytes, options))
+                .ok_or(e);
+        }
+    };
+    let metadata = read_core_properties(&mut archive);
+    let is_signed = is_signed_package(&mut archive);
+    let ooxml_conformance = detect_conformance(&mut archive, "word/document.xml").map(|c| c.as_str().to_string());
+    let producer = ooxml_producer(&mut archive);
+    let external = find_external_references(&mut archive);
     
     // Try to read page count from docProps/app.xml
     let page_count_result = {
         match archive.by_name("docProps/app.xml") {
             Ok(mut file) => {
+                note_zip_entry(&file);
                 let mut contents = String::new();
                 match file.read_to_string(&mut contents) {
                     Ok(_) => Some(parse_pages_from_app_xml(&contents)),
-                    Err(e) => Some(Err(EstimatorError::General(format!("Failed to read app.xml: {:?}", e)))),
+                    Err(e) => Some(Err(EstimatorError::DocxError(format!("Failed to read app.xml: {:?}", e)))),
                 }
             }
             Err(_) => None,
@@ -388,29 +1672,115 @@ pub fn estimate_docx_pages(
         Some(Err(e)) => return Err(e),
         None => {
             // If app.xml doesn't exist, try to estimate from content
-            return estimate_docx_from_content(&mut archive, options);
+            return estimate_docx_from_content(&mut archive, options).map(|result| {
+                let mut result = EstimateResult { metadata, is_signed, ooxml_conformance, producer, ..result };
+                add_external_references(&mut result, &external);
+                result
+            });
         }
     };
     
     // Determine paper size
-    let (w, h) = if let Some(custom) = options.custom_paper_mm {
-        custom
-    } else if let Some(ref def) = options.default_paper {
-        match def.as_str() {
-            "Letter" | "letter" => letter_mm(),
-            _ => a4_mm(),
-        }
-    } else {
-        a4_mm()
-    };
+    let (size, paper_note) = paper_size(options);
     
-    Ok(EstimateResult {
-        page_count,
-        page_sizes: vec![PageSizeMm { width_mm: w, height_mm: h }; page_count],
-        notes: vec![
-            format!("DOCX document has {} pages (from metadata)", page_count),
-        ],
-    })
+    let mut notes: Vec<String> = std::iter::once(format!("DOCX document has {} pages (from metadata)", page_count))
+        .chain(paper_note)
+        .collect();
+    let confidence = metadata_confidence("docx", producer.as_deref(), &mut notes);
+    let mut result = EstimateResult {
+        metadata,
+        is_signed,
+        ooxml_conformance,
+        producer,
+        confidence,
+        ..EstimateResult::uniform(page_count, size, notes)
+    };
+    add_external_references(&mut result, &external);
+    Ok(add_docx_body_details(&mut archive, options, result))
+}
+
+/// Counts the pages of a DOCX both ways `estimate_docx_pages` can, for
+/// checking a count (see the `verification` module).
+///
+/// # Returns
+///
+/// `("metadata", pages)` from `<Pages>` in `docProps/app.xml`, then
+/// `("content", pages)` estimated from the body, each when it can be read.
+pub fn docx_page_count_candidates(bytes: &[u8], options: &EstimateOptions) -> Vec<(&'static str, usize)> {
+    let Ok(mut archive) = open_zip_archive(bytes, "DOCX") else { return Vec::new() };
+    let mut candidates = Vec::new();
+    if let Ok(mut file) = archive.by_name("docProps/app.xml") {
+        note_zip_entry(&file);
+        let mut contents = String::new();
+        if file.read_to_string(&mut contents).is_ok()
+            && let Ok(pages) = parse_pages_from_app_xml(&contents)
+        {
+            candidates.push(("metadata", pages));
+        }
+    }
+    if let Ok(result) = estimate_docx_from_content(&mut archive, options) {
+        candidates.push(("content", result.page_count));
+    }
+    candidates
+}
+
+/// Counts the words and characters of the DOCX body into `text_metrics` when
+/// `extract_text_metrics` is set, guesses its language when `detect_language`
+/// is, and reads where its pages start into `page_start_paragraphs` when
+/// `return_boundaries` is, for counts read from metadata.
+fn add_docx_body_details(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    options: &EstimateOptions,
+    mut result: EstimateResult,
+) -> EstimateResult {
+    let metrics = options.extract_text_metrics.unwrap_or(false);
+    let page_starts = options.return_boundaries.unwrap_or(false);
+    if !metrics && !page_starts && !options.detect_language.unwrap_or(false) {
+        return result;
+    }
+    if let Ok(file) = archive.by_name("word/document.xml") {
+        note_zip_entry(&file);
+        let stats = scan_docx_document(BufReader::new(file));
+        if metrics {
+            result.text_metrics = Some(docx_text_metrics(&stats));
+        }
+        if page_starts {
+            result.page_start_paragraphs = docx_page_start_paragraphs(&stats);
+        }
+        add_language(&mut result, &stats.sample, options);
+    }
+    result
+}
+
+/// The paragraph each page of a DOCX starts in, from the page breaks Word
+/// recorded when it last laid out the document (`<w:lastRenderedPageBreak>`).
+///
+/// Paragraphs are numbered from 0 the way python-docx lists
+/// `Document.paragraphs`: only the `<w:p>` children of `<w:body>` count, not
+/// the paragraphs of tables, content controls (`<w:sdt>`), or text boxes. A
+/// break in a paragraph counts as that paragraph; a break anywhere else, such
+/// as in a table cell, as the next body paragraph, which is the number of
+/// body paragraphs when nothing follows.
+///
+/// # Returns
+///
+/// 0 for the first page, then one index per recorded break, or `None` when
+/// Word recorded no breaks.
+fn docx_page_start_paragraphs(stats: &DocxContentStats) -> Option<Vec<usize>> {
+    if stats.rendered_break_paragraphs.is_empty() {
+        return None;
+    }
+    Some(std::iter::once(0).chain(stats.rendered_break_paragraphs.iter().copied()).collect())
+}
+
+/// Text metrics of a DOCX body: one line per paragraph.
+fn docx_text_metrics(stats: &DocxContentStats) -> TextMetrics {
+    TextMetrics {
+        line_count: stats.paragraphs,
+        word_count: stats.words,
+        char_count: stats.text_chars,
+        ..Default::default()
+    }
 }
 
 /// Estimates the number of slides in a PowerPoint presentation (.pptx).
@@ -434,22 +1804,39 @@ pub fn estimate_docx_pages(
 /// - The slide count is extracted directly from document metadata (exact count)
 /// - Each slide is considered as one "page" for printing purposes
 /// - Uses standard presentation dimensions (10" × 7.5" / 254mm × 190.5mm)
+/// - With `extract_text_metrics`, the text of the slides and their speaker
+///   notes is counted in `text_metrics`; slides without notes add nothing
 pub fn estimate_pptx_pages(
     bytes: &[u8],
-    _options: &EstimateOptions,
+    options: &EstimateOptions,
 ) -> Result<EstimateResult, EstimatorError> {
-    let cursor = Cursor::new(bytes);
-    let mut archive = ZipArchive::new(cursor)
-        .map_err(|e| EstimatorError::General(format!("Failed to open PPTX as ZIP: {:?}", e)))?;
+    if is_encrypted_office_package(bytes) {
+        return Err(EstimatorError::EncryptedDocument);
+    }
+    let mut archive = match open_zip_archive(bytes, "PPTX") {
+        Ok(archive) => archive,
+        Err(e) => {
+            return estimate_rebuilt_package(bytes, PRESENTATION_PART, estimate_pptx_pages, options)
+                .or_else(|| estimate_truncated_pptx(bytes, options))
+                .ok_or(e);
+        }
+    };
+    let metadata = read_core_properties(&mut archive);
+    let is_signed = is_signed_package(&mut archive);
+    let ooxml_conformance = detect_conformance(&mut archive, PRESENTATION_PART).map(|c| c.as_str().to_string());
+    let producer = ooxml_producer(&mut archive);
+    let external = find_external_references(&mut archive);
+    let deck_size = deck_slide_size(&mut archive);
     
     // Try to read slide count from docProps/app.xml
     let slide_count_result = {
         match archive.by_name("docProps/app.xml") {
             Ok(mut file) => {
+                note_zip_entry(&file);
                 let mut contents = String::new();
                 match file.read_to_string(&mut contents) {
                     Ok(_) => Some(parse_slides_from_app_xml(&contents)),
-                    Err(e) => Some(Err(EstimatorError::General(format!("Failed to read app.xml: {:?}", e)))),
+                    Err(e) => Some(Err(EstimatorError::PptxError(format!("Failed to read app.xml: {:?}", e)))),
                 }
             }
             Err(_) => None,
@@ -461,25 +1848,479 @@ pub fn estimate_pptx_pages(
         Some(Err(e)) => return Err(e),
         None => {
             // If app.xml doesn't exist, try to count slide files
-            return estimate_pptx_from_content(&mut archive);
+            return estimate_pptx_from_content(&mut archive, deck_size.as_ref(), options).map(|result| {
+                let mut result = EstimateResult { metadata, is_signed, ooxml_conformance, producer, ..result };
+                add_external_references(&mut result, &external);
+                add_slide_text_metrics(&mut archive, options, result)
+            });
         }
     };
     
-    // Standard PowerPoint slide dimensions: 10" × 7.5" (254mm × 190.5mm)
-    let (w, h) = (254.0, 190.5);
-    
+    let mut notes = vec![format!("PPTX presentation has {} slides (from metadata)", slide_count)];
+    notes.extend(deck_size.as_ref().and_then(SlideSize::mismatch_note));
+    let confidence = metadata_confidence("pptx", producer.as_deref(), &mut notes);
+    let mut result = EstimateResult {
+        metadata,
+        is_signed,
+        ooxml_conformance,
+        producer,
+        confidence,
+        ..EstimateResult::uniform(slide_count, slide_size(options, deck_size.as_ref()), notes)
+    };
+    add_external_references(&mut result, &external);
+    Ok(add_slide_text_metrics(&mut archive, options, result))
+}
+
+/// Sets `has_external_references` and adds the note listing the first targets.
+fn add_external_references(result: &mut EstimateResult, references: &[ExternalReference]) {
+    result.has_external_references = !references.is_empty();
+    result.notes.extend(external_references_note(references));
+}
+
+/// Confidence of a count read from `docProps/app.xml`.
+///
+/// `None` (no judgement) unless the producer is listed in `PRODUCER_CONFIDENCE`,
+/// in which case a note is added to `notes`: a warning, unless the producer
+/// is reliable (`RELIABLE_PRODUCER_CONFIDENCE`).
+fn metadata_confidence(format: &str, producer: Option<&str>, notes: &mut Vec<String>) -> Option<f64> {
+    let producer = producer?;
+    let confidence = producer_confidence(format, producer)?;
+    if confidence >= RELIABLE_PRODUCER_CONFIDENCE {
+        notes.push(format!("Count from metadata written by {}, which lays out pages when exporting", producer));
+    } else {
+        notes.push(format!(
+            "⚠ Count from metadata written by {}, which doesn't lay out pages; it may not match the document",
+            producer
+        ));
+    }
+    Some(confidence)
+}
+
+/// Counts slide and speaker notes text into `text_metrics` when `extract_text_metrics` is set.
+fn add_slide_text_metrics(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    options: &EstimateOptions,
+    mut result: EstimateResult,
+) -> EstimateResult {
+    if !options.extract_text_metrics.unwrap_or(false) {
+        return result;
+    }
+    let slides = read_slide_texts(archive);
+    let mut slide_total = TextMetrics::default();
+    let mut notes_total = TextMetrics::default();
+    let mut with_notes = 0;
+    let mut slide_notes = Vec::new();
+    for (number, slide) in slides.iter().enumerate() {
+        slide_total.line_count += slide.slide.line_count;
+        slide_total.word_count += slide.slide.word_count;
+        slide_total.char_count += slide.slide.char_count;
+        if let Some(notes) = &slide.notes {
+            with_notes += 1;
+            notes_total.line_count += notes.line_count;
+            notes_total.word_count += notes.word_count;
+            notes_total.char_count += notes.char_count;
+        }
+        if verbosity(options) >= DETAIL_VERBOSITY {
+            let notes = slide.notes.as_ref().map_or("no notes".to_string(), |notes| {
+                format!("notes {} words, {} characters", notes.word_count, notes.char_count)
+            });
+            slide_notes.push(format!(
+                "Slide {}: {} words, {} characters; {}",
+                number + 1,
+                slide.slide.word_count,
+                slide.slide.char_count,
+                notes
+            ));
+        }
+    }
+    result.notes.push(format!(
+        "Text: {} words on {} slides, {} words in the notes of {} slides",
+        slide_total.word_count,
+        slides.len(),
+        notes_total.word_count,
+        with_notes
+    ));
+    result.notes.extend(slide_notes);
+    result.text_metrics = Some(TextMetrics {
+        line_count: slide_total.line_count + notes_total.line_count,
+        word_count: slide_total.word_count + notes_total.word_count,
+        char_count: slide_total.char_count + notes_total.char_count,
+        ..Default::default()
+    });
+    result
+}
+
+/// Number of leading page images whose headers are read to find the page size.
+const COMIC_SIZE_SAMPLE: usize = 3;
+
+/// Estimates the number of pages in a comic book archive (.cbz).
+///
+/// Every page image counts as one page, unless the archive's `ComicInfo.xml`
+/// declares a `<PageCount>`, which takes precedence.
+///
+/// # Parameters
+///
+/// * `bytes` - The raw CBZ (ZIP) file bytes
+/// * `options` - Estimation options; the paper size is used when no image header can be read
+///
+/// # Returns
+///
+/// Returns `Ok(EstimateResult)` with the page count and a uniform page size,
+/// or `Err(EstimatorError)` if the archive can't be opened or has no pages.
+///
+/// # Notes
+///
+/// - The page size is read from the header of the first of the first few
+///   images (in natural order) that can be parsed, at its declared resolution
+///   or 96 dpi; no pixel data is decoded
+pub fn estimate_cbz_pages(
+    bytes: &[u8],
+    options: &EstimateOptions,
+) -> Result<EstimateResult, EstimatorError> {
+    let mut archive = open_zip_archive(bytes, "CBZ")?;
+    let images = page_images(&archive);
+    let declared = read_comic_info_page_count(&mut archive);
+
+    let mut notes = Vec::new();
+    let page_count = match declared {
+        Some(count) => {
+            notes.push(format!("Comic has {} pages (from ComicInfo.xml)", count));
+            if count != images.len() {
+                notes.push(format!(
+                    "⚠ ComicInfo.xml PageCount {} differs from the {} page images",
+                    count,
+                    images.len()
+                ));
+            }
+            count
+        }
+        None if images.is_empty() => {
+            return Err(EstimatorError::ZipError("Comic archive contains no page images".into()));
+        }
+        None => {
+            notes.push(format!("Comic has {} pages (counted from page images)", images.len()));
+            images.len()
+        }
+    };
+
+    let size = options.wants_page_sizes().then(|| {
+        images
+            .iter()
+            .take(COMIC_SIZE_SAMPLE)
+            .find_map(|name| {
+                let file = archive.by_name(name).ok()?;
+                note_zip_entry(&file);
+                let mut head = Vec::new();
+                file.take(IMAGE_HEADER_BYTES as u64).read_to_end(&mut head).ok()?;
+                let header = read_image_header(&head)?;
+                notes.push(format!(
+                    "Page size from {} ({}x{} px {})",
+                    name, header.width_px, header.height_px, header.format
+                ));
+                Some(header.size_mm())
+            })
+            .unwrap_or_else(|| {
+                notes.push("No readable image header; using the default paper size".into());
+                options.paper()
+            })
+    });
+
+    Ok(EstimateResult::uniform(page_count, size, notes))
+}
+
+/// Estimates the number of pages in an Apple iWork document (.pages, .numbers, .key).
+///
+/// The IWA document streams aren't parsed; the pages are counted in the
+/// QuickLook preview PDF that iWork embeds in the package.
+///
+/// # Parameters
+///
+/// * `bytes` - The raw iWork (ZIP) file bytes
+/// * `options` - Estimation options; the paper size is used for preview pages without a `/MediaBox`
+///
+/// # Returns
+///
+/// Returns `Ok(EstimateResult)` with the page (or sheet, or slide) count of the
+/// preview, or `Err(EstimatorError::General)` naming the application when the
+/// document was saved without a preview.
+///
+/// # Notes
+///
+/// - Page sizes come from the preview's `/MediaBox` entries
+pub fn estimate_iwork_pages(
+    bytes: &[u8],
+    options: &EstimateOptions,
+) -> Result<EstimateResult, EstimatorError> {
+    let mut archive = open_zip_archive(bytes, "iWork")?;
+    let app = iwork_application(&mut archive).unwrap_or("iWork");
+    let unsupported = || {
+        EstimatorError::General(format!(
+            "{} document has no readable QuickLook preview to count pages from; export it to PDF instead",
+            app
+        ))
+    };
+    let preview = read_quicklook_preview(&mut archive).ok_or_else(unsupported)?;
+
+    let counter = PdfCounter::default();
+    let outcome = counter.count(&preview);
+    if outcome.count == 0 {
+        return Err(unsupported());
+    }
+    let mut notes = vec![format!("{} document has {} pages (from its QuickLook preview)", app, outcome.count)];
+    notes.extend(outcome.notes);
+
+    let page_sizes = if options.wants_page_sizes() {
+        counter
+            .page_sizes(&preview)
+            .filter(|sizes| sizes.len() == outcome.count)
+            .unwrap_or_else(|| {
+                let (width_mm, height_mm) = options.paper();
+                vec![PageSizeMm { width_mm, height_mm }; outcome.count]
+            })
+    } else {
+        Vec::new()
+    };
+    let mut result = EstimateResult {
+        page_count: outcome.count,
+        notes,
+        confidence: Some(outcome.confidence),
+        ..Default::default()
+    };
+    result.set_orientation_counts(&page_sizes);
+    Ok(EstimateResult { page_sizes, ..result })
+}
+
+/// Confidence for PostScript files without DSC page comments, counted by `showpage` operators.
+const SHOWPAGE_CONFIDENCE: f64 = 0.5;
+
+/// Estimates the number of pages in a PostScript (.ps) or Encapsulated PostScript (.eps) file.
+///
+/// The count comes from the file's DSC comments: `%%Pages:` (following
+/// `(atend)` to the trailer), else the number of `%%Page:` comments. EPS files
+/// are single illustrations and always count as one page.
+///
+/// # Parameters
+///
+/// * `bytes` - The raw PostScript file bytes, optionally with a DOS EPS binary header
+/// * `options` - Estimation options; the paper size is used when the file declares no size
+///
+/// # Returns
+///
+/// Returns `Ok(EstimateResult)` with the page count and size, or
+/// `Err(EstimatorError::PostScriptError)` if no PostScript program is found.
+///
+/// # Notes
+///
+/// - Documents take their size from `%%DocumentMedia:`, then `%%BoundingBox:`;
+///   EPS files use the bounding box first
+/// - Files without DSC page comments are counted by `showpage` operators at lower confidence
+pub fn estimate_postscript_pages(
+    bytes: &[u8],
+    options: &EstimateOptions,
+) -> Result<EstimateResult, EstimatorError> {
+    let ps = postscript_section(bytes).ok_or_else(|| {
+        EstimatorError::PostScriptError("No PostScript program found (missing %! header)".into())
+    })?;
+    let dsc = scan_dsc(ps, bytes.starts_with(&DOS_EPS_MAGIC));
+
+    let mut confidence = None;
+    let mut notes = Vec::new();
+    let page_count = if dsc.is_eps {
+        notes.push("EPS illustration counted as 1 page".to_string());
+        1
+    } else if let Some(pages) = dsc.pages {
+        notes.push(format!("PostScript document has {} pages (from %%Pages)", pages));
+        if dsc.page_comments > 0 && dsc.page_comments != pages {
+            notes.push(format!(
+                "⚠ Found {} %%Page: comments, which disagrees with %%Pages: {}",
+                dsc.page_comments, pages
+            ));
+        }
+        pages
+    } else if dsc.page_comments > 0 {
+        notes.push(format!(
+            "PostScript document has {} pages (counted from %%Page: comments)",
+            dsc.page_comments
+        ));
+        dsc.page_comments
+    } else {
+        confidence = Some(SHOWPAGE_CONFIDENCE);
+        notes.push(format!(
+            "Note: No DSC page comments; counted {} showpage operators",
+            dsc.showpages
+        ));
+        dsc.showpages.max(1)
+    };
+
+    let declared = if dsc.is_eps {
+        dsc.bounding_box.or(dsc.media)
+    } else {
+        dsc.media.or(dsc.bounding_box)
+    };
+    let size = match declared {
+        Some((w, h)) => (mm_from_pt(w), mm_from_pt(h)),
+        None => {
+            notes.push("No %%DocumentMedia or %%BoundingBox; using the default paper size".into());
+            options.paper()
+        }
+    };
+
     Ok(EstimateResult {
-        page_count: slide_count,
-        page_sizes: vec![PageSizeMm { width_mm: w, height_mm: h }; slide_count],
-        notes: vec![
-            format!("PPTX presentation has {} slides (from metadata)", slide_count),
-        ],
+        confidence,
+        ..EstimateResult::uniform(page_count, options.wants_page_sizes().then_some(size), notes)
     })
 }
 
+/// Estimates the number of pages in an XPS or OpenXPS document (.xps, .oxps).
+///
+/// The page count is the number of `<PageContent>` references across the
+/// FixedDocuments of the package's FixedDocumentSequence, which is exact for
+/// print-to-XPS output. Each page's size is read from its FixedPage part.
+///
+/// # Parameters
+///
+/// * `bytes` - The raw XPS file bytes
+/// * `options` - Estimation options; the paper size is used for pages whose size can't be read
+///
+/// # Returns
+///
+/// Returns `Ok(EstimateResult)` with the page count and per-page sizes,
+/// or `Err(EstimatorError)` if the package has no document sequence or no pages.
+///
+/// # Notes
+///
+/// - FixedPage `Width`/`Height` are in 1/96 inch; the `<PageContent>` size
+///   hints are used when a page part can't be read
+/// - Page parts are only opened when `include_page_sizes` is on
+pub fn estimate_xps_pages(
+    bytes: &[u8],
+    options: &EstimateOptions,
+) -> Result<EstimateResult, EstimatorError> {
+    let mut archive = open_zip_archive(bytes, "XPS")?;
+    let sequence = find_document_sequence(&mut archive)
+        .ok_or_else(|| EstimatorError::XpsError("No FixedDocumentSequence found in XPS package".into()))?;
+    let documents = read_document_references(&mut archive, &sequence)?;
+
+    let mut pages = Vec::new();
+    for document in &documents {
+        pages.extend(read_page_refs(&mut archive, document)?);
+    }
+    if pages.is_empty() {
+        return Err(EstimatorError::XpsError("No pages found in XPS document".into()));
+    }
+
+    let mut notes = vec![format!(
+        "XPS document has {} pages in {} fixed documents",
+        pages.len(),
+        documents.len()
+    )];
+
+    let mut page_sizes = Vec::new();
+    if options.wants_page_sizes() {
+        let mut unsized_pages = 0;
+        for page in &pages {
+            let size = read_fixed_page_size(&mut archive, &page.part)
+                .or(page.width.zip(page.height))
+                .map(|(w, h)| (mm_from_xps_units(w), mm_from_xps_units(h)));
+            let (width_mm, height_mm) = size.unwrap_or_else(|| {
+                unsized_pages += 1;
+                options.paper()
+            });
+            page_sizes.push(PageSizeMm { width_mm, height_mm });
+        }
+        if unsized_pages > 0 {
+            notes.push(format!(
+                "⚠ {} pages have no readable size; using the default paper size",
+                unsized_pages
+            ));
+        }
+    }
+
+    let mut result = EstimateResult { page_count: pages.len(), notes, ..Default::default() };
+    result.set_orientation_counts(&page_sizes);
+    Ok(EstimateResult { page_sizes, ..result })
+}
+
+/// Maps the local names of `docProps/core.xml` elements to `metadata` keys.
+const CORE_PROPERTY_KEYS: &[(&[u8], &str)] = &[
+    (b"title", "title"),
+    (b"creator", "author"),
+    (b"lastModifiedBy", "last_modified_by"),
+    (b"created", "created"),
+    (b"modified", "modified"),
+];
+
+/// Parses the core document properties shared by all OOXML formats.
+///
+/// Elements are matched on their local names because producers use different
+/// namespace prefixes (`dc:`, `cp:`, `dcterms:`, or none). Dates are passed
+/// through as the ISO 8601 strings found in the XML.
+///
+/// # Returns
+///
+/// A map with any of the keys `title`, `author`, `last_modified_by`, `created`
+/// and `modified`; empty when none are present or the XML is malformed.
+pub fn parse_core_properties(xml_content: &str) -> BTreeMap<String, String> {
+    let mut reader = XmlReader::from_str(xml_content);
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut properties = BTreeMap::new();
+    let mut current: Option<&str> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                current = CORE_PROPERTY_KEYS
+                    .iter()
+                    .find(|(local, _)| e.local_name().as_ref() == *local)
+                    .map(|(_, key)| *key);
+            }
+            Ok(Event::Text(e)) => {
+                if let Some(key) = current
+                    && let Ok(text) = e.unescape()
+                    && !text.is_empty()
+                {
+                    properties.insert(key.to_string(), text.into_owned());
+                }
+            }
+            Ok(Event::End(_)) => current = None,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    properties
+}
+
+/// Checks whether an OOXML package carries a digital signature.
+///
+/// Signed packages keep their signatures in the `_xmlsignatures/` folder, which
+/// the package relationships reference as the `digital-signature/origin` part.
+/// Only presence is checked; signatures are not validated.
+fn is_signed_package(archive: &mut ZipArchive<Cursor<&[u8]>>) -> bool {
+    if archive.file_names().any(|name| name.starts_with("_xmlsignatures/")) {
+        return true;
+    }
+    let Ok(mut rels) = archive.by_name("_rels/.rels") else { return false };
+    note_zip_entry(&rels);
+    let mut contents = String::new();
+    rels.read_to_string(&mut contents).is_ok() && contents.contains("/digital-signature/origin")
+}
+
+/// Reads `docProps/core.xml` from an OOXML archive, if present and non-empty.
+fn read_core_properties(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<BTreeMap<String, String>> {
+    let mut file = archive.by_name("docProps/core.xml").ok()?;
+    note_zip_entry(&file);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    let properties = parse_core_properties(&contents);
+    if properties.is_empty() { None } else { Some(properties) }
+}
+
 /// Helper function to parse page count from app.xml content
 fn parse_pages_from_app_xml(xml_content: &str) -> Result<usize, EstimatorError> {
-    let mut reader = XmlReader::from_str(xml_content);
+    let mut reader = NsReader::from_str(xml_content);
     reader.config_mut().trim_text(true);
     let mut buf = Vec::new();
     let mut in_pages = false;
@@ -487,37 +2328,37 @@ fn parse_pages_from_app_xml(xml_content: &str) -> Result<usize, EstimatorError>
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
-                if e.name().as_ref() == b"Pages" {
+                if is_app_property(&reader, e.name(), b"Pages") {
                     in_pages = true;
                 }
             }
             Ok(Event::Text(e)) => {
                 if in_pages {
                     let text = e.unescape()
-                        .map_err(|e| EstimatorError::General(format!("XML unescape error: {:?}", e)))?;
+                        .map_err(|e| EstimatorError::XmlError(format!("{:?}", e)))?;
                     let page_count = text.parse::<usize>()
-                        .map_err(|e| EstimatorError::General(format!("Failed to parse page count: {:?}", e)))?;
+                        .map_err(|e| EstimatorError::DocxError(format!("Failed to parse page count: {:?}", e)))?;
                     return Ok(page_count);
                 }
             }
             Ok(Event::End(ref e)) => {
-                if e.name().as_ref() == b"Pages" {
+                if is_app_property(&reader, e.name(), b"Pages") {
                     in_pages = false;
                 }
             }
             Ok(Event::Eof) => break,
-            Err(e) => return Err(EstimatorError::General(format!("XML parse error: {:?}", e))),
+            Err(e) => return Err(EstimatorError::XmlError(format!("{:?}", e))),
             _ => {}
         }
         buf.clear();
     }
     
-    Err(EstimatorError::General("No page count found in app.xml".to_string()))
+    Err(EstimatorError::DocxError("No page count found in app.xml".to_string()))
 }
 
 /// Helper function to parse slide count from app.xml content
 fn parse_slides_from_app_xml(xml_content: &str) -> Result<usize, EstimatorError> {
-    let mut reader = XmlReader::from_str(xml_content);
+    let mut reader = NsReader::from_str(xml_content);
     reader.config_mut().trim_text(true);
     let mut buf = Vec::new();
     let mut in_slides = false;
@@ -525,110 +2366,2096 @@ fn parse_slides_from_app_xml(xml_content: &str) -> Result<usize, EstimatorError>
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
-                if e.name().as_ref() == b"Slides" {
+                if is_app_property(&reader, e.name(), b"Slides") {
                     in_slides = true;
                 }
             }
             Ok(Event::Text(e)) => {
                 if in_slides {
                     let text = e.unescape()
-                        .map_err(|e| EstimatorError::General(format!("XML unescape error: {:?}", e)))?;
+                        .map_err(|e| EstimatorError::XmlError(format!("{:?}", e)))?;
                     let slide_count = text.parse::<usize>()
-                        .map_err(|e| EstimatorError::General(format!("Failed to parse slide count: {:?}", e)))?;
+                        .map_err(|e| EstimatorError::PptxError(format!("Failed to parse slide count: {:?}", e)))?;
                     return Ok(slide_count);
                 }
             }
             Ok(Event::End(ref e)) => {
-                if e.name().as_ref() == b"Slides" {
+                if is_app_property(&reader, e.name(), b"Slides") {
                     in_slides = false;
                 }
             }
             Ok(Event::Eof) => break,
-            Err(e) => return Err(EstimatorError::General(format!("XML parse error: {:?}", e))),
+            Err(e) => return Err(EstimatorError::XmlError(format!("{:?}", e))),
             _ => {}
         }
         buf.clear();
     }
     
-    Err(EstimatorError::General("No slide count found in app.xml".to_string()))
+    Err(EstimatorError::PptxError("No slide count found in app.xml".to_string()))
 }
 
-/// Fallback: estimate DOCX pages by analyzing content structure
-fn estimate_docx_from_content(
-    archive: &mut ZipArchive<Cursor<&[u8]>>,
-    options: &EstimateOptions,
-) -> Result<EstimateResult, EstimatorError> {
-    // Try to read document.xml and count paragraph/page break elements
-    match archive.by_name("word/document.xml") {
-        Ok(mut file) => {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)
-                .map_err(|e| EstimatorError::General(format!("Failed to read document.xml: {:?}", e)))?;
-            
-            // Count explicit page breaks
-            let page_breaks = contents.matches("<w:br w:type=\"page\"").count();
-            
-            // If there are page breaks, use that count + 1 (for the first page)
-            let estimated_pages = if page_breaks > 0 {
-                page_breaks + 1
-            } else {
-                // Fallback: estimate based on paragraph count
-                let paragraphs = contents.matches("<w:p ").count() + contents.matches("<w:p>").count();
-                let paragraphs_per_page = 25; // rough heuristic
-                ((paragraphs + paragraphs_per_page - 1) / paragraphs_per_page).max(1)
-            };
-            
-            let (w, h) = if let Some(custom) = options.custom_paper_mm {
-                custom
-            } else if let Some(ref def) = options.default_paper {
-                match def.as_str() {
-                    "Letter" | "letter" => letter_mm(),
-                    _ => a4_mm(),
-                }
-            } else {
-                a4_mm()
-            };
-            
-            Ok(EstimateResult {
-                page_count: estimated_pages,
-                page_sizes: vec![PageSizeMm { width_mm: w, height_mm: h }; estimated_pages],
-                notes: vec![
-                    format!("DOCX document estimated at {} pages (from content analysis)", estimated_pages),
-                    "Note: Page count estimated from content structure; may not be exact".to_string(),
-                ],
-            })
+/// Checks whether an element of `docProps/app.xml` is the extended property `local`.
+fn is_app_property<R>(reader: &NsReader<R>, name: QName, local: &[u8]) -> bool {
+    local_name_in(reader, name, OoxmlNamespace::ExtendedProperties, NamespaceFamily::Any) == Some(local)
+}
+
+/// Counts the slides listed in `ppt/presentation.xml` (its `p:sldId` elements).
+fn count_slide_ids(xml: &str) -> usize {
+    let mut reader = NsReader::from_str(xml);
+    let mut count = 0;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if local_name_in(&reader, e.name(), OoxmlNamespace::PresentationMl, NamespaceFamily::Any)
+                    == Some(b"sldId") =>
+            {
+                count += 1
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
         }
-        Err(e) => Err(EstimatorError::General(format!("Failed to read DOCX content: {:?}", e))),
+    }
+    count
+}
+
+/// Element counts gathered while streaming `word/document.xml`.
+#[derive(Default)]
+struct DocxContentStats {
+    /// Explicit page breaks (`<w:br w:type="page"/>`).
+    page_breaks: usize,
+    /// Page breaks Word recorded the last time it laid out the document.
+    rendered_page_breaks: usize,
+    /// The body paragraph each of those page breaks falls in (see `docx_page_start_paragraphs`).
+    rendered_break_paragraphs: Vec<usize>,
+    paragraphs: usize,
+    /// Characters inside `<w:t>` runs.
+    text_chars: usize,
+    /// Whitespace-separated words of the `<w:t>` runs, joined within each paragraph.
+    words: usize,
+    /// The start of the text, up to `LANGUAGE_SAMPLE_CHARS` bytes, one line per paragraph.
+    sample: String,
+    /// Set when the XML could not be parsed to the end; the counts cover what was read.
+    malformed: bool,
+    /// Relationship ids of `<w:altChunk>` elements, in document order.
+    alt_chunks: Vec<String>,
+    /// Embedded objects (`<w:object>`).
+    objects: usize,
+    /// Relationship ids of the parts behind embedded objects (`<o:OLEObject>`,
+    /// `<w:objectEmbed>`, `<w:objectLink>`), in document order.
+    object_parts: Vec<String>,
+    /// Equations (`<m:oMath>`) set on their own line, inside `<m:oMathPara>`.
+    display_equations: usize,
+    /// Equations (`<m:oMath>`) within the text of a paragraph.
+    inline_equations: usize,
+    /// `<mc:Fallback>` branches skipped, so content with an alternate isn't counted twice.
+    fallbacks_skipped: usize,
+}
+
+/// Streams `word/document.xml` and counts its elements.
+///
+/// The part is read through a small buffer rather than loaded into a `String`,
+/// so long documents (tens of MB of XML) don't double peak memory on top of
+/// the ZIP inflation buffer.
+fn scan_docx_document<R: BufRead>(source: R) -> DocxContentStats {
+    const WORD: OoxmlNamespace = OoxmlNamespace::WordprocessingMl;
+    const MATH: OoxmlNamespace = OoxmlNamespace::Math;
+    let mut reader = NsReader::from_reader(source);
+    let mut buf = Vec::new();
+    let mut skip_buf = Vec::new();
+    let mut stats = DocxContentStats::default();
+    let mut in_text = false;
+    // whether the last character read was part of a word; runs split words, paragraphs don't
+    let mut in_word = false;
+    // open <m:oMathPara> elements: equations inside them are display equations
+    let mut math_paragraphs = 0usize;
+    // element depth, the depth of <w:body>, and the body paragraphs started so far
+    // and whether the last one is still open
+    let mut depth = 0usize;
+    let mut body_depth = None;
+    let mut body_paragraphs = 0usize;
+    let mut in_body_paragraph = false;
+
+    loop {
+        let event = reader.read_event_into(&mut buf);
+        match event {
+            Ok(Event::Start(ref e)) => {
+                depth += 1;
+                match local_name_in(&reader, e.name(), WORD, NamespaceFamily::Any) {
+                    Some(b"body") if body_depth.is_none() => body_depth = Some(depth),
+                    Some(b"p") if body_depth.is_some_and(|body| depth == body + 1) => {
+                        body_paragraphs += 1;
+                        in_body_paragraph = true;
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Empty(ref e))
+                if body_depth.is_some_and(|body| depth == body)
+                    && local_name_in(&reader, e.name(), WORD, NamespaceFamily::Any) == Some(b"p") =>
+            {
+                body_paragraphs += 1
+            }
+            Ok(Event::End(ref e)) => {
+                if body_depth.is_some_and(|body| depth == body + 1)
+                    && local_name_in(&reader, e.name(), WORD, NamespaceFamily::Any) == Some(b"p")
+                {
+                    in_body_paragraph = false;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        match event {
+            // only the <mc:Choice> branch of <mc:AlternateContent> is counted
+            Ok(Event::Start(ref e))
+                if local_name_in(&reader, e.name(), OoxmlNamespace::MarkupCompatibility, NamespaceFamily::Any)
+                    == Some(b"Fallback") =>
+            {
+                let end = e.name().as_ref().to_vec();
+                skip_buf.clear();
+                if reader.read_to_end_into(QName(&end), &mut skip_buf).is_err() {
+                    stats.malformed = true;
+                    break;
+                }
+                depth = depth.saturating_sub(1);
+                stats.fallbacks_skipped += 1;
+            }
+            Ok(Event::Start(ref e))
+                if local_name_in(&reader, e.name(), MATH, NamespaceFamily::Any) == Some(b"oMathPara") =>
+            {
+                math_paragraphs += 1
+            }
+            Ok(Event::End(ref e))
+                if local_name_in(&reader, e.name(), MATH, NamespaceFamily::Any) == Some(b"oMathPara") =>
+            {
+                math_paragraphs = math_paragraphs.saturating_sub(1)
+            }
+            Ok(Event::Start(ref e)) if local_name_in(&reader, e.name(), WORD, NamespaceFamily::Any) == Some(b"t") => {
+                in_text = true
+            }
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                match local_name_in(&reader, e.name(), WORD, NamespaceFamily::Any) {
+                    Some(b"p") => {
+                        stats.paragraphs += 1;
+                        in_word = false;
+                        if !stats.sample.is_empty() && stats.sample.len() < LANGUAGE_SAMPLE_CHARS {
+                            stats.sample.push('\n');
+                        }
+                    }
+                    Some(b"br") if attribute_value(&reader, e, WORD, b"type").is_some_and(|value| value == "page") => {
+                        stats.page_breaks += 1
+                    }
+                    Some(b"lastRenderedPageBreak") => {
+                        stats.rendered_page_breaks += 1;
+                        // outside a body paragraph (in a table), the page starts before the next one
+                        let paragraph = if in_body_paragraph { body_paragraphs - 1 } else { body_paragraphs };
+                        stats.rendered_break_paragraphs.push(paragraph);
+                    }
+                    Some(b"altChunk") => {
+                        stats.alt_chunks.extend(attribute_value(&reader, e, OoxmlNamespace::Relationships, b"id"))
+                    }
+                    Some(b"object") => stats.objects += 1,
+                    Some(b"objectEmbed" | b"objectLink") => {
+                        stats.object_parts.extend(attribute_value(&reader, e, OoxmlNamespace::Relationships, b"id"))
+                    }
+                    _ if local_name_in(&reader, e.name(), OoxmlNamespace::VmlOffice, NamespaceFamily::Any)
+                        == Some(b"OLEObject") =>
+                    {
+                        stats.object_parts.extend(attribute_value(&reader, e, OoxmlNamespace::Relationships, b"id"))
+                    }
+                    _ if local_name_in(&reader, e.name(), MATH, NamespaceFamily::Any) == Some(b"oMath") => {
+                        if math_paragraphs > 0 {
+                            stats.display_equations += 1
+                        } else {
+                            stats.inline_equations += 1
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Text(ref e)) if in_text => {
+                let text = e.unescape().unwrap_or_default();
+                if stats.sample.len() < LANGUAGE_SAMPLE_CHARS {
+                    stats.sample.push_str(&text);
+                }
+                for c in text.chars() {
+                    stats.text_chars += printed_chars(c);
+                    if c.is_whitespace() {
+                        in_word = false;
+                    } else if !in_word {
+                        stats.words += 1;
+                        in_word = true;
+                    }
+                }
+            }
+            Ok(Event::End(ref e)) if local_name_in(&reader, e.name(), WORD, NamespaceFamily::Any) == Some(b"t") => {
+                in_text = false
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => {
+                stats.malformed = true;
+                break;
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+    stats
+}
+
+/// Fallback: estimate DOCX pages by analyzing content structure
+fn estimate_docx_from_content(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    options: &EstimateOptions,
+) -> Result<EstimateResult, EstimatorError> {
+    // Stream document.xml and count paragraph/page break elements
+    let file = archive
+        .by_name("word/document.xml")
+        .map_err(|e| EstimatorError::DocxError(format!("Failed to read DOCX content: {:?}", e)))?;
+    note_zip_entry(&file);
+    let stats = scan_docx_document(BufReader::new(file));
+
+    // equations hold little text but take vertical space: display equations
+    // more than inline ones, which mostly make their line taller
+    let equations = stats.display_equations + stats.inline_equations;
+    let equation_lines = stats.display_equations as f64
+        * options.docx_display_equation_lines.unwrap_or(DEFAULT_DISPLAY_EQUATION_LINES)
+        + stats.inline_equations as f64
+            * options.docx_inline_equation_lines.unwrap_or(DEFAULT_INLINE_EQUATION_LINES);
+    let equation_pages = equation_lines / DEFAULT_LINES_PER_PAGE as f64;
+
+    // If there are page breaks, use that count + 1 (for the first page)
+    let body_pages = if stats.page_breaks > 0 {
+        stats.page_breaks + 1
+    } else {
+        // Fallback: estimate based on paragraph count
+        let paragraphs_per_page = 25; // rough heuristic
+        options.rounding().round(stats.paragraphs as f64 / paragraphs_per_page as f64 + equation_pages)
+    };
+
+    // altChunk parts (HTML, MHT, or text merged in by conversion tools) are
+    // estimated separately and added to the body
+    let mut chunk_notes = Vec::new();
+    let mut chunk_pages = 0;
+    if !stats.alt_chunks.is_empty() {
+        let targets = read_relationship_targets(archive, "word/_rels/document.xml.rels");
+        for id in &stats.alt_chunks {
+            let Some(target) = targets.get(id) else {
+                chunk_notes.push(format!("⚠ altChunk {} has no relationship target; skipped", id));
+                continue;
+            };
+            let part = resolve_part("word/document.xml", target);
+            match read_alt_chunk_text(archive, &part) {
+                Ok(text) => {
+                    let pages = estimate_text_pages(text.as_bytes(), options).page_count;
+                    chunk_pages += pages;
+                    chunk_notes.push(format!("altChunk {}: {} pages", part, pages));
+                }
+                Err(reason) => chunk_notes.push(format!("⚠ altChunk {} skipped: {}", part, reason)),
+            }
+        }
+    }
+    let objects = estimate_docx_objects(archive, &stats, options);
+    let object_pages = objects.estimated_pages + options.rounding().round(objects.page_equivalents);
+    let estimated_pages = body_pages.saturating_add(chunk_pages).saturating_add(object_pages).max(1);
+
+    let (size, paper_note) = paper_size(options);
+
+    let mut notes = vec![
+        format!("DOCX document estimated at {} pages (from content analysis)", estimated_pages),
+        "Note: Page count estimated from content structure; may not be exact".to_string(),
+        format!(
+            "Content: {} paragraphs, {} page breaks, {} rendered page breaks, {} characters of text",
+            stats.paragraphs, stats.page_breaks, stats.rendered_page_breaks, stats.text_chars
+        ),
+    ];
+    if stats.malformed {
+        notes.push("⚠ document.xml is malformed; counts cover the readable part only".to_string());
+    }
+    notes.extend(rtl_note(&stats.sample));
+    if equations > 0 {
+        let contribution = if stats.page_breaks > 0 {
+            "not added, as page breaks set the page count"
+        } else {
+            "added to the paragraph estimate"
+        };
+        notes.push(format!(
+            "{} equations ({} display, {} inline) take about {:.1} lines ({:.2} pages), {}",
+            equations, stats.display_equations, stats.inline_equations, equation_lines, equation_pages, contribution
+        ));
+    }
+    if stats.fallbacks_skipped > 0 {
+        notes.push(format!(
+            "{} mc:Fallback branches skipped; only their mc:Choice content is counted",
+            stats.fallbacks_skipped
+        ));
+    }
+    if !stats.alt_chunks.is_empty() {
+        notes.push(format!(
+            "{} altChunk parts add {} pages to the {} pages of the body",
+            stats.alt_chunks.len(),
+            chunk_pages,
+            body_pages
+        ));
+        notes.extend(chunk_notes);
+    }
+    if stats.objects > 0 || !objects.notes.is_empty() {
+        notes.push(format!(
+            "{} embedded objects add {} pages ({} estimated, {} page equivalents)",
+            stats.objects,
+            object_pages,
+            objects.estimated_pages,
+            objects.page_equivalents
+        ));
+        notes.extend(objects.notes);
+    }
+    if stats.page_breaks == 0 {
+        notes.extend(rounding_note(options));
+    }
+    notes.extend(paper_note);
+
+    // hard page breaks force at least that many pages; at most, every break
+    // wastes a page's worth of space on top of the pages the text fills
+    let text_pages = stats.text_chars.div_ceil(DEFAULT_CHARS_PER_PAGE);
+    let min = stats.page_breaks + 1 + chunk_pages + objects.estimated_pages;
+    let max = text_pages.max(body_pages) + stats.page_breaks + chunk_pages + objects.estimated_pages
+        + objects.page_equivalents.ceil() as usize
+        + equation_pages.ceil() as usize;
+    let text_metrics = options.extract_text_metrics.unwrap_or(false).then(|| docx_text_metrics(&stats));
+    let page_start_paragraphs =
+        options.return_boundaries.unwrap_or(false).then(|| docx_page_start_paragraphs(&stats)).flatten();
+    let mut result = EstimateResult {
+        text_metrics,
+        page_start_paragraphs,
+        ..EstimateResult::uniform(estimated_pages, size, notes)
+    }
+    .with_range(min, max);
+    add_language(&mut result, &stats.sample, options);
+    Ok(result)
+}
+
+/// Pages counted for an embedded DOCX object that isn't estimated itself.
+pub const DEFAULT_OBJECT_PAGE_EQUIVALENT: f64 = 0.5;
+
+/// Largest `docx_object_page_equivalent` accepted; no embedded object prints as more.
+pub const MAX_OBJECT_PAGE_EQUIVALENT: f64 = 100.0;
+
+/// Lines added for a display equation (`<m:oMathPara>`) in a DOCX body.
+pub const DEFAULT_DISPLAY_EQUATION_LINES: f64 = 2.0;
+
+/// Lines added for an inline equation (`<m:oMath>`) in a DOCX body.
+pub const DEFAULT_INLINE_EQUATION_LINES: f64 = 0.5;
+
+/// Most lines `docx_display_equation_lines` or `docx_inline_equation_lines`
+/// may add per equation: a full page.
+pub const MAX_EQUATION_LINES: f64 = DEFAULT_LINES_PER_PAGE as f64;
+
+/// Pages contributed by the objects embedded in a DOCX body.
+#[derive(Default)]
+struct DocxObjectPages {
+    /// Pages of the embedded documents that were estimated.
+    estimated_pages: usize,
+    /// Sum of the page equivalents of the other objects (fractional).
+    page_equivalents: f64,
+    /// One note per embedding, saying how it was treated.
+    notes: Vec<String>,
+}
+
+/// Accounts for the objects embedded in a DOCX body.
+///
+/// Each object part is counted as `docx_object_page_equivalent` pages, unless
+/// `estimate_attachments` is set and it is a PDF, DOCX, XLSX, or PPTX package,
+/// in which case it is estimated (with `estimate_attachments` turned off) and
+/// its pages are added. Objects without a part of their own count as page
+/// equivalents too. `word/embeddings` parts the body doesn't reference (such as
+/// chart data) are only noted.
+fn estimate_docx_objects(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    stats: &DocxContentStats,
+    options: &EstimateOptions,
+) -> DocxObjectPages {
+    let equivalent = options.docx_object_page_equivalent.unwrap_or(DEFAULT_OBJECT_PAGE_EQUIVALENT);
+    let recurse = options.estimate_attachments.unwrap_or(false);
+    let inner_options = EstimateOptions { estimate_attachments: Some(false), ..options.clone() };
+    let mut objects = DocxObjectPages::default();
+
+    let targets = read_relationship_targets(archive, "word/_rels/document.xml.rels");
+    let mut parts: Vec<String> = Vec::new();
+    for id in &stats.object_parts {
+        let part = targets.get(id).map(|target| resolve_part("word/document.xml", target));
+        match part {
+            Some(part) if !parts.contains(&part) => parts.push(part),
+            Some(_) => {}
+            None => {
+                objects.page_equivalents += equivalent;
+                objects.notes.push(format!("Embedded object {}: no part found; counted as {} pages", id, equivalent));
+            }
+        }
+    }
+    let unreferenced = stats.objects.saturating_sub(stats.object_parts.len());
+    if unreferenced > 0 {
+        objects.page_equivalents += unreferenced as f64 * equivalent;
+        objects.notes.push(format!(
+            "{} embedded objects without a part counted as {} pages each",
+            unreferenced, equivalent
+        ));
+    }
+
+    for part in &parts {
+        let extension = part.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+        let parseable = matches!(extension.as_str(), "pdf" | "docx" | "docm" | "xlsx" | "xlsm" | "pptx" | "pptm");
+        if recurse && parseable {
+            match read_embedded_part(archive, part, max_entry_bytes(options)).and_then(|bytes| {
+                match extension.as_str() {
+                    "pdf" => estimate_pdf_pages(&bytes, &inner_options),
+                    "docx" | "docm" => estimate_docx_pages(&bytes, &inner_options),
+                    "xlsx" | "xlsm" => estimate_xlsx_pages(&bytes, &inner_options),
+                    _ => estimate_pptx_pages(&bytes, &inner_options),
+                }
+                .map_err(|err| err.to_string())
+            }) {
+                Ok(result) => {
+                    objects.estimated_pages += result.page_count;
+                    objects.notes.push(format!("Embedded object {}: {} pages (estimated)", part, result.page_count));
+                    continue;
+                }
+                Err(reason) => objects.notes.push(format!(
+                    "⚠ Embedded object {} could not be estimated ({}); counted as {} pages",
+                    part, reason, equivalent
+                )),
+            }
+        } else {
+            objects.notes.push(format!("Embedded object {}: counted as {} pages", part, equivalent));
+        }
+        objects.page_equivalents += equivalent;
+    }
+
+    let ignored = archive
+        .file_names()
+        .filter(|name| {
+            name.starts_with("word/embeddings/") && !is_system_entry(name) && !parts.iter().any(|part| part == name)
+        })
+        .count();
+    if ignored > 0 {
+        objects.notes.push(format!(
+            "{} parts in word/embeddings aren't referenced by the body (e.g. chart data); not counted",
+            ignored
+        ));
+    }
+    objects
+}
+
+/// Reads an embedded part for estimation, refusing parts over `limit` bytes.
+fn read_embedded_part(archive: &mut ZipArchive<Cursor<&[u8]>>, part: &str, limit: u64) -> Result<Vec<u8>, String> {
+    let file = archive.by_name(part).map_err(|_| "part is missing".to_string())?;
+    note_zip_entry(&file);
+    let mut bytes = Vec::new();
+    file.take(limit + 1).read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("part exceeds {} bytes", limit));
+    }
+    Ok(bytes)
+}
+
+/// Maximum size of an altChunk part that is read for estimation.
+const MAX_ALT_CHUNK_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Reads the text of an altChunk part, decoding it according to its extension.
+///
+/// # Returns
+///
+/// The visible text of HTML, MHT, and plain-text parts, or the reason the part
+/// can't be estimated (missing, too large, or an unsupported type such as an
+/// embedded DOCX or RTF).
+fn read_alt_chunk_text(archive: &mut ZipArchive<Cursor<&[u8]>>, part: &str) -> Result<String, String> {
+    let extension = part.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    if !matches!(extension.as_str(), "htm" | "html" | "xhtml" | "mht" | "mhtml" | "txt") {
+        return Err(format!("unsupported chunk type '.{}'", extension));
+    }
+    let file = archive.by_name(part).map_err(|_| "part is missing".to_string())?;
+    note_zip_entry(&file);
+    let mut bytes = Vec::new();
+    file.take(MAX_ALT_CHUNK_BYTES + 1).read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > MAX_ALT_CHUNK_BYTES {
+        return Err(format!("part exceeds {} bytes", MAX_ALT_CHUNK_BYTES));
+    }
+    match extension.as_str() {
+        "mht" | "mhtml" => mht_to_text(&bytes).ok_or_else(|| "MHT archive has no text part".to_string()),
+        "txt" => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        _ => Ok(html_to_text(&String::from_utf8_lossy(&bytes))),
     }
 }
 
 /// Fallback: estimate PPTX slides by counting slide files
 fn estimate_pptx_from_content(
     archive: &mut ZipArchive<Cursor<&[u8]>>,
+    deck_size: Option<&SlideSize>,
+    options: &EstimateOptions,
 ) -> Result<EstimateResult, EstimatorError> {
     // Count slide files in ppt/slides/ directory
     let mut slide_count = 0;
     for i in 0..archive.len() {
         if let Ok(file) = archive.by_index(i) {
             let name = file.name();
-            if name.starts_with("ppt/slides/slide") && name.ends_with(".xml") {
+            if !is_system_entry(name) && name.starts_with("ppt/slides/slide") && name.ends_with(".xml") {
                 slide_count += 1;
             }
         }
     }
     
     if slide_count == 0 {
-        return Err(EstimatorError::General("No slides found in PPTX".to_string()));
+        return Err(EstimatorError::PptxError("No slides found in PPTX".to_string()));
     }
     
-    let (w, h) = (254.0, 190.5); // Standard PowerPoint dimensions
-    
-    Ok(EstimateResult {
-        page_count: slide_count,
-        page_sizes: vec![PageSizeMm { width_mm: w, height_mm: h }; slide_count],
-        notes: vec![
-            format!("PPTX presentation has {} slides (counted from files)", slide_count),
-        ],
+    let notes = std::iter::once(format!("PPTX presentation has {} slides (counted from files)", slide_count))
+        .chain(deck_size.and_then(SlideSize::mismatch_note))
+        .collect();
+    Ok(EstimateResult::uniform(slide_count, slide_size(options, deck_size), notes))
+}
+
+/// Confidence assigned to estimates recovered from truncated or damaged archives.
+const TRUNCATED_ARCHIVE_CONFIDENCE: f64 = 0.5;
+
+/// Confidence of estimates of packages rebuilt from their local file headers.
+///
+/// Every recovered entry passed its CRC check, but an entry the scan couldn't
+/// read would be missing without a trace, so the estimate isn't fully trusted.
+const REBUILT_ARCHIVE_CONFIDENCE: f64 = 0.9;
+
+/// Estimates an OOXML package whose central directory is damaged but whose
+/// entries are intact, by rebuilding it from its local file headers (see
+/// `RecoveredZip`) and estimating the rebuilt package as usual.
+///
+/// # Arguments
+///
+/// * `bytes` - The package that failed to open
+/// * `main_part` - Part that must be recovered (e.g. `word/document.xml`)
+/// * `estimate` - The format's estimator, run on the rebuilt package
+/// * `options` - Estimation options
+///
+/// # Returns
+///
+/// The estimate with a warning and at most `REBUILT_ARCHIVE_CONFIDENCE`, or
+/// `None` when `main_part` can't be recovered or the rebuilt package can't be
+/// estimated either.
+fn estimate_rebuilt_package(
+    bytes: &[u8],
+    main_part: &str,
+    estimate: fn(&[u8], &EstimateOptions) -> Result<EstimateResult, EstimatorError>,
+    options: &EstimateOptions,
+) -> Option<EstimateResult> {
+    let recovered = RecoveredZip::scan(bytes);
+    if !recovered.contains(main_part) {
+        return None;
+    }
+    let rebuilt = recovered.to_zip().ok()?;
+    // the rebuilt package must open, or the estimator would come back here
+    open_zip_archive(&rebuilt, "ZIP").ok()?;
+    let mut result = estimate(&rebuilt, options).ok()?;
+    result.notes.push(format!(
+        "⚠ ZIP central directory is damaged; package rebuilt from {} intact entries",
+        recovered.len()
+    ));
+    let confidence = result.confidence.map_or(REBUILT_ARCHIVE_CONFIDENCE, |c| c.min(REBUILT_ARCHIVE_CONFIDENCE));
+    result.confidence = Some(confidence);
+    Some(result)
+}
+
+/// Fallback: recover a DOCX page count from local headers when the archive can't be opened
+fn estimate_truncated_docx(bytes: &[u8], options: &EstimateOptions) -> Option<EstimateResult> {
+    let app_xml = recover_zip_entry(bytes, "docProps/app.xml")?;
+    let page_count = parse_pages_from_app_xml(&app_xml).ok()?;
+
+    let (size, paper_note) = paper_size(options);
+
+    let notes = [
+        format!("DOCX document has {} pages (from metadata)", page_count),
+        "⚠ Document appears truncated; metadata recovered from local ZIP headers".to_string(),
+    ]
+    .into_iter()
+    .chain(paper_note)
+    .collect();
+
+    Some(EstimateResult {
+        confidence: Some(TRUNCATED_ARCHIVE_CONFIDENCE),
+        metadata: recover_zip_entry(bytes, "docProps/core.xml")
+            .map(|xml| parse_core_properties(&xml))
+            .filter(|props| !props.is_empty()),
+        ..EstimateResult::uniform(page_count, size, notes)
+    })
+}
+
+/// Fallback: recover a PPTX slide count from local headers when the archive can't be opened
+fn estimate_truncated_pptx(bytes: &[u8], options: &EstimateOptions) -> Option<EstimateResult> {
+    let presentation = recover_zip_entry(bytes, PRESENTATION_PART);
+    let deck_size = presentation.as_deref().and_then(|xml| read_slide_size(xml.as_bytes()));
+    let (slide_count, source) = match recover_zip_entry(bytes, "docProps/app.xml")
+        .and_then(|xml| parse_slides_from_app_xml(&xml).ok())
+    {
+        Some(count) => (count, "metadata"),
+        None => {
+            let count = count_slide_ids(presentation.as_deref()?);
+            if count == 0 {
+                return None;
+            }
+            (count, "presentation.xml")
+        }
+    };
+
+    let mut notes = vec![
+        format!("PPTX presentation has {} slides (from {})", slide_count, source),
+        "⚠ Document appears truncated; slide count recovered from local ZIP headers".to_string(),
+    ];
+    notes.extend(deck_size.as_ref().and_then(SlideSize::mismatch_note));
+
+    Some(EstimateResult {
+        confidence: Some(TRUNCATED_ARCHIVE_CONFIDENCE),
+        metadata: recover_zip_entry(bytes, "docProps/core.xml")
+            .map(|xml| parse_core_properties(&xml))
+            .filter(|props| !props.is_empty()),
+        ..EstimateResult::uniform(slide_count, slide_size(options, deck_size.as_ref()), notes)
     })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::{error_code, estimate_bytes, estimate_to_json};
+    use crate::deadline::{with_deadline, DEADLINE_CONFIDENCE, DEADLINE_WARNING};
+    use crate::file_utils::detect_type;
+    use crate::fixtures::*;
+    use crate::formats::format_for_filename;
+    use crate::pdf_text::TEXT_METRICS_CONFIDENCE;
+    use crate::schema::HEURISTIC_RANGE;
+    use proptest::collection::vec as vec_of;
+    use proptest::option::of as option_of;
+    use proptest::prelude::*;
+
+    const CORE_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties"
+    xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/"
+    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <dc:title>Quarterly report &amp; outlook</dc:title>
+  <dc:creator>A. Author</dc:creator>
+  <cp:lastModifiedBy>B. Editor</cp:lastModifiedBy>
+  <dcterms:created xsi:type="dcterms:W3CDTF">2026-01-05T09:30:00Z</dcterms:created>
+  <dcterms:modified xsi:type="dcterms:W3CDTF">2026-02-11T17:04:12Z</dcterms:modified>
+</cp:coreProperties>"#;
+
+    #[test]
+    fn source_code_is_paginated_by_line() {
+        let function = "fn add(a: i32, b: i32) -> i32 {\n\ta + b\n}\n\n";
+        let source = function.repeat(30);
+        for name in ["lib.rs", "main.py", "app.js", "Main.java", "run.sh"] {
+            assert_eq!(detect_type(Some(name), source.as_bytes()), "code", "{}", name);
+            assert_eq!(format_for_filename(name).map(|format| format.id), Some("code"), "{}", name);
+        }
+
+        let result = estimate(source.as_bytes(), Some("lib.rs"), "{}").unwrap();
+        assert_eq!(result.method.as_deref(), Some("code-lines"));
+        assert_eq!(result.text_metrics.as_ref().unwrap().line_count, 120);
+        // 120 lines at 60 per page
+        assert_eq!(result.page_count, 2);
+        assert_eq!(result.notes[0], "120 source lines (30 blank)");
+        let layout = "display lines: 120, chars_per_line: 100, lines_per_page: 60 (60 after 1x line spacing)";
+        assert_eq!(result.notes[1], format!("{}, tab_width: 8", layout));
+
+        let skipped = estimate(source.as_bytes(), Some("lib.rs"), r#"{"skip_blank_lines":true}"#).unwrap();
+        assert_eq!(skipped.notes[0], "120 source lines (30 blank, skipped)");
+        assert!(skipped.notes[1].starts_with("display lines: 90,"));
+        assert_eq!(skipped.text_metrics.unwrap().line_count, 120);
+    }
+
+    #[test]
+    fn long_and_indented_code_lines_wrap() {
+        let long_line = format!("let names = [{}];\n", "\"name\", ".repeat(30));
+        let indented = "\t\t\t\t\t\t\t\t\t\t\t\tdeeply_nested();\n";
+        let source = format!("{}{}", long_line, indented);
+        let result = estimate(source.as_bytes(), Some("names.ts"), "{}").unwrap();
+        // 255 columns wrap onto three lines; twelve tabs reach column 96 and the call wraps
+        assert!(result.notes[1].starts_with("display lines: 5,"), "{:?}", result.notes);
+        let narrow = estimate(source.as_bytes(), Some("names.ts"), r#"{"tab_width":2}"#).unwrap();
+        assert!(narrow.notes[1].starts_with("display lines: 4,"), "{:?}", narrow.notes);
+    }
+
+    /// Slices `text` at `boundaries`, checking they start the text, rise, and fall on characters.
+    fn pages_at<'a>(text: &'a str, boundaries: &[usize], bom_len: usize) -> Vec<&'a str> {
+        assert_eq!(boundaries.first(), Some(&bom_len));
+        assert!(boundaries.windows(2).all(|w| w[0] < w[1]), "{:?}", boundaries);
+        let mut ends = boundaries[1..].to_vec();
+        ends.push(text.len());
+        boundaries.iter().zip(ends).map(|(&start, end)| &text[start..end]).collect()
+    }
+
+    #[test]
+    fn character_boundaries_slice_the_text_into_its_pages() {
+        let text = "Añadir café, crème brûlée et 東京の地図 à la liste. ".repeat(120);
+        let options =
+            EstimateOptions { return_boundaries: Some(true), chars_per_page: Some(700), ..Default::default() };
+        let result = estimate_text_pages(text.as_bytes(), &options);
+        let boundaries = result.page_boundaries.unwrap();
+        assert!(result.page_count > 5);
+        assert_eq!(boundaries.len(), result.page_count);
+        let pages = pages_at(&text, &boundaries, 0);
+        for page in &pages {
+            assert_eq!(estimate_text_pages(page.as_bytes(), &options).page_count, 1);
+        }
+        assert_eq!(pages.concat(), text);
+    }
+
+    #[test]
+    fn line_boundaries_slice_the_text_into_its_pages() {
+        let line = "fn main() {\n\tprintln!(\"a line long enough to wrap at forty columns wide\");\n}\n";
+        let text = format!("\u{FEFF}{}", line.repeat(60));
+        let options = EstimateOptions {
+            return_boundaries: Some(true),
+            chars_per_line: Some(40),
+            lines_per_page: Some(25),
+            ..Default::default()
+        };
+        let text_result = estimate_text_pages(text.as_bytes(), &options);
+        for result in [text_result, estimate_markdown_pages(text.as_bytes(), &options)] {
+            let boundaries = result.page_boundaries.unwrap();
+            assert_eq!(boundaries.len(), result.page_count);
+            let pages = pages_at(&text, &boundaries, 3);
+            for page in &pages {
+                assert_eq!(estimate_text_pages(page.as_bytes(), &options).page_count, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn boundaries_are_only_reported_when_asked_for_text() {
+        let text = "Plain words. ".repeat(500);
+        assert_eq!(estimate(text.as_bytes(), Some("a.txt"), "{}").unwrap().page_boundaries, None);
+        let asked = r#"{"return_boundaries":true}"#;
+        assert!(estimate(text.as_bytes(), Some("a.txt"), asked).unwrap().page_boundaries.is_some());
+        // offsets into cleaned terminal output wouldn't match the input
+        let log = format!("\x1b[32m{}\x1b[0m", text);
+        assert_eq!(estimate(log.as_bytes(), Some("build.log"), asked).unwrap().page_boundaries, None);
+
+        let others = [
+            build_pdf(2, XrefStyle::Classic, false, false),
+            build_docx(30, 2, None),
+            build_pptx(2, 0),
+            build_xlsx(&[(80, 4)]),
+        ];
+        for document in others {
+            assert_eq!(estimate(&document, None, asked).unwrap().page_boundaries, None);
+        }
+    }
+
+    #[test]
+    fn core_properties_are_read_by_local_name() {
+        let properties = parse_core_properties(CORE_XML);
+        let expected = [
+            ("author", "A. Author"),
+            ("created", "2026-01-05T09:30:00Z"),
+            ("last_modified_by", "B. Editor"),
+            ("modified", "2026-02-11T17:04:12Z"),
+            ("title", "Quarterly report & outlook"),
+        ];
+        let found: Vec<(&str, &str)> = properties.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(found, expected);
+
+        // other producers use other prefixes, or none
+        let unprefixed = parse_core_properties(
+            "<coreProperties xmlns:x=\"urn:x\"><x:title>Memo</x:title><creator>C</creator><keywords>k</keywords>\
+             <modified>2026-03-01</modified></coreProperties>",
+        );
+        let found: Vec<(&str, &str)> = unprefixed.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(found, [("author", "C"), ("modified", "2026-03-01"), ("title", "Memo")]);
+
+        assert!(parse_core_properties("<coreProperties><title></title></coreProperties>").is_empty());
+        assert!(parse_core_properties("not xml").is_empty());
+    }
+
+    #[test]
+    fn core_properties_are_reported_for_every_ooxml_format() {
+        let packages = [
+            ("docx", build_docx(5, 0, Some(1))),
+            ("xlsx", build_xlsx(&[(3, 3)])),
+            ("pptx", build_pptx(2, 0)),
+        ];
+        for (format, package) in packages {
+            assert_eq!(estimate(&package, None, "{}").unwrap().metadata, None, "{}", format);
+            let with_core = with_part(&package, "docProps/core.xml", CORE_XML);
+            let metadata = estimate(&with_core, None, "{}").unwrap().metadata.unwrap();
+            assert_eq!(metadata["title"], "Quarterly report & outlook", "{}", format);
+            assert_eq!(metadata["created"], "2026-01-05T09:30:00Z", "{}", format);
+            assert_eq!(metadata.len(), 5, "{}", format);
+        }
+    }
+
+    #[test]
+    fn truncated_docx_keeps_its_metadata_count() {
+        let docx = truncate_in_part(&build_docx(40, 5, Some(12)), "word/document.xml");
+        assert!(open_zip_archive(&docx, "DOCX").is_err());
+        let result = estimate(&docx, Some("report.docx"), "{}").unwrap();
+        assert_eq!(result.page_count, 12);
+        assert_eq!(result.confidence, Some(TRUNCATED_ARCHIVE_CONFIDENCE));
+        assert!(result.notes.iter().any(|n| n.contains("appears truncated")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn truncated_pptx_keeps_its_slide_count() {
+        let pptx = truncate_in_part(&build_pptx(9, 0), PRESENTATION_PART);
+        let result = estimate(&pptx, Some("deck.pptx"), "{}").unwrap();
+        assert_eq!(result.page_count, 9);
+        assert_eq!(result.confidence, Some(TRUNCATED_ARCHIVE_CONFIDENCE));
+        assert!(result.notes.iter().any(|n| n.contains("appears truncated")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn package_truncated_before_its_metadata_fails_as_corrupt() {
+        let docx = truncate_in_part(&build_docx(40, 5, Some(12)), "docProps/app.xml");
+        assert!(matches!(estimate(&docx, Some("report.docx"), "{}"), Err(EstimatorError::ZipError(_))));
+        let pptx = truncate_in_part(&build_pptx(9, 0), "docProps/app.xml");
+        assert!(matches!(estimate(&pptx, Some("deck.pptx"), "{}"), Err(EstimatorError::ZipError(_))));
+    }
+
+    #[test]
+    fn page_sizes_can_be_left_out() {
+        let documents = [
+            (build_pdf(4, XrefStyle::Classic, false, false), "a.pdf"),
+            (build_docx(40, 5, Some(7)), "a.docx"),
+            (build_docx(60, 0, None), "b.docx"),
+            (build_pptx(5, 0), "a.pptx"),
+            (build_xlsx(&[(90, 3), (10, 2)]), "a.xlsx"),
+            ("line\n".repeat(200).into_bytes(), "a.txt"),
+        ];
+        for (bytes, name) in &documents {
+            let full = estimate(bytes, Some(name), "{}").unwrap();
+            assert_eq!(full.page_sizes.len(), full.page_count, "{}", name);
+
+            let counted = estimate(bytes, Some(name), r#"{"include_page_sizes":false}"#).unwrap();
+            assert_eq!(counted.page_count, full.page_count, "{}", name);
+            assert!(counted.page_sizes.is_empty(), "{}", name);
+            let json: serde_json::Value = serde_json::to_value(&counted).unwrap();
+            assert_eq!(json["page_sizes"], serde_json::json!([]), "{}", name);
+        }
+    }
+
+    /// Page breaks and paragraphs as the content fallback counted them before
+    /// streaming: substring matches over the whole part.
+    fn substring_counts(xml: &str) -> (usize, usize) {
+        let page_breaks = xml.matches("<w:br w:type=\"page\"").count();
+        let paragraphs = xml.matches("<w:p ").count() + xml.matches("<w:p>").count();
+        (page_breaks, paragraphs)
+    }
+
+    #[test]
+    fn streamed_document_counts_match_substring_counts() {
+        let fixtures = [
+            build_docx(1, 0, None),
+            build_docx(60, 0, None),
+            build_docx(40, 5, None),
+            build_docx(900, 30, None),
+        ];
+        for docx in fixtures {
+            let xml = read_part(&docx, "word/document.xml");
+            // a small buffer splits tags across reads
+            let stats = scan_docx_document(BufReader::with_capacity(7, xml.as_slice()));
+            let text = String::from_utf8(xml).unwrap();
+            assert_eq!((stats.page_breaks, stats.paragraphs), substring_counts(&text));
+            assert!(!stats.malformed);
+        }
+    }
+
+    #[test]
+    fn content_fallback_keeps_its_page_estimate() {
+        for (paragraphs, breaks, pages) in [(60, 0, 3), (40, 5, 6), (1, 0, 1)] {
+            let result = estimate(&build_docx(paragraphs, breaks, None), Some("report.docx"), "{}").unwrap();
+            assert_eq!(result.page_count, pages, "{} paragraphs, {} breaks", paragraphs, breaks);
+            assert!(result.notes.iter().any(|n| n.contains("from content analysis")), "{:?}", result.notes);
+        }
+    }
+
+    #[test]
+    fn malformed_document_is_counted_up_to_the_error() {
+        let docx = build_docx(60, 0, None);
+        let xml = String::from_utf8(read_part(&docx, "word/document.xml")).unwrap();
+        let cut = xml.match_indices("<w:p>").nth(30).unwrap().0;
+        let broken = format!("{}<w:p></w:x>{}", &xml[..cut], &xml[cut..]);
+        let stats = scan_docx_document(broken.as_bytes());
+        assert!(stats.malformed);
+        assert_eq!(stats.paragraphs, 31);
+
+        let docx = replace_part(&docx, "word/document.xml", broken.as_bytes());
+        let result = estimate(&docx, Some("report.docx"), "{}").unwrap();
+        assert!(result.notes.iter().any(|n| n.contains("malformed")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn signed_packages_are_flagged() {
+        let packages = [
+            (build_docx(6, 2, Some(3)), "a.docx"),
+            (build_xlsx(&[(8, 3)]), "a.xlsx"),
+            (build_pptx(3, 0), "a.pptx"),
+        ];
+        let origin = concat!(
+            r#"<Relationship Id="rIdSig" Target="_xmlsignatures/origin.sigs" "#,
+            r#"Type="http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/origin"/>"#,
+        );
+        for (package, name) in &packages {
+            let unsigned = estimate(package, Some(name), "{}").unwrap();
+            assert!(!unsigned.is_signed, "{}", name);
+            assert_eq!(serde_json::to_value(&unsigned).unwrap()["is_signed"], false, "{}", name);
+
+            let signature = with_part(package, "_xmlsignatures/sig1.xml", "<Signature/>");
+            assert!(estimate(&signature, Some(name), "{}").unwrap().is_signed, "{}", name);
+
+            let rels = String::from_utf8(read_part(package, "_rels/.rels")).unwrap();
+            let rels = rels.replace("</Relationships>", &format!("{}</Relationships>", origin));
+            let related = replace_part(package, "_rels/.rels", rels.as_bytes());
+            assert!(estimate(&related, Some(name), "{}").unwrap().is_signed, "{}", name);
+        }
+    }
+
+    #[test]
+    fn page_count_ranges_enclose_the_estimate() {
+        let text = "The press checked the order and will ship it by Friday. ".repeat(2000);
+        let markdown = format!("# Order\n\n{}\n\n## Delivery\n\n- by Friday\n", text);
+        let corpus: [(&str, Vec<u8>, bool); 10] = [
+            ("report.pdf", build_pdf(3, XrefStyle::Classic, false, false), false),
+            ("stream.pdf", build_pdf(4, XrefStyle::Stream, true, false), false),
+            ("deck.pptx", build_pptx(5, 1), false),
+            ("stated.docx", build_docx(40, 0, Some(4)), false),
+            ("fallback.docx", build_docx(400, 3, None), true),
+            ("notes.txt", text.clone().into_bytes(), true),
+            ("readme.md", markdown.into_bytes(), true),
+            ("book.xlsx", build_xlsx(&[(400, 6), (30, 2)]), true),
+            ("build.log", CI_LOG.as_bytes().to_vec(), true),
+            ("main.rs", "fn main() {\n    println!(\"hello\");\n}\n".repeat(300).into_bytes(), true),
+        ];
+        for (filename, bytes, heuristic) in corpus {
+            let result = estimate(&bytes, Some(filename), "{}").unwrap();
+            let (min, max) = result.page_count_range();
+            assert!(min <= result.page_count && result.page_count <= max, "{}: {:?}", filename, (min, max));
+            assert_eq!(result.page_count_min.is_some(), heuristic, "{}", filename);
+            assert_eq!(result.page_count_max.is_some(), heuristic, "{}", filename);
+            if heuristic && result.page_count > 0 {
+                assert!(min >= 1, "{}: a non-empty estimate has a page", filename);
+            }
+        }
+    }
+
+    #[test]
+    fn heuristic_ranges_are_bands_around_the_estimate() {
+        let text = "The press checked the order and will ship it by Friday. ".repeat(2000);
+        let result = estimate(text.as_bytes(), Some("notes.txt"), "{}").unwrap();
+        let pages = result.page_count as f64;
+        assert!(result.page_count >= 10, "{}", result.page_count);
+        let expected = (
+            (pages * (1.0 - HEURISTIC_RANGE)).floor() as usize,
+            (pages * (1.0 + HEURISTIC_RANGE)).ceil() as usize,
+        );
+        assert_eq!(result.page_count_range(), expected);
+
+        // a single page keeps its floor of one
+        let note = estimate(b"A short note.", Some("note.txt"), "{}").unwrap();
+        assert_eq!((note.page_count, note.page_count_range()), (1, (1, 2)));
+
+        // the DOCX fallback is bounded by its hard breaks and its characters
+        let docx = estimate(&build_docx(400, 3, None), Some("fallback.docx"), "{}").unwrap();
+        let (min, max) = docx.page_count_range();
+        assert!(min >= 4, "three hard breaks make four pages at least: {:?}", (min, max));
+        assert!(max >= docx.page_count + 3, "every break may waste a page: {:?}", (min, max));
+
+        // the range is reported in JSON without changing the page count
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["page_count"], result.page_count);
+        assert_eq!(json["page_count_min"], expected.0);
+        assert_eq!(json["page_count_max"], expected.1);
+        let exact = serde_json::to_value(estimate(&build_pptx(5, 0), None, "{}").unwrap()).unwrap();
+        assert!(exact.get("page_count_min").is_none() && exact.get("page_count_max").is_none(), "{}", exact);
+    }
+
+    #[test]
+    fn portfolios_warn_and_optionally_count_their_documents() {
+        let report = build_pdf(3, XrefStyle::Classic, false, false);
+        let appendix = build_pdf(5, XrefStyle::Stream, false, false);
+        let pdf = build_portfolio_pdf(&[("report.pdf", &report), ("appendix.pdf", &appendix)]);
+        let result = estimate(&pdf, Some("portfolio.pdf"), "{}").unwrap();
+        assert_eq!(result.page_count, 1);
+        let warning = "⚠ PDF portfolio detected; count covers the cover sheet only; 2 embedded documents present";
+        assert_eq!(result.notes.first().map(String::as_str), Some(warning), "{:?}", result.notes);
+
+        let result = estimate(&pdf, Some("portfolio.pdf"), r#"{"estimate_attachments":true}"#).unwrap();
+        assert_eq!(result.page_count, 9);
+        assert_eq!(result.page_sizes.len(), 9);
+        let notes = [
+            "Embedded 'report.pdf': 3 pages",
+            "Embedded 'appendix.pdf': 5 pages",
+            "Added 8 pages from embedded documents",
+        ];
+        for note in notes {
+            assert!(result.notes.iter().any(|n| n == note), "{}: {:?}", note, result.notes);
+        }
+
+        // a portfolio nested in one is counted by its cover sheet
+        let nested = build_portfolio_pdf(&[("inner.pdf", &pdf)]);
+        let result = estimate(&nested, None, r#"{"estimate_attachments":true}"#).unwrap();
+        assert_eq!(result.page_count, 2);
+
+        // embedded files over the entry limit are skipped
+        let options = r#"{"estimate_attachments":true,"archive_max_entry_bytes":100}"#;
+        let result = estimate(&pdf, None, options).unwrap();
+        assert_eq!(result.page_count, 1);
+        assert!(result.notes.iter().any(|n| n.contains("report.pdf")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn selected_sheets_are_the_only_ones_estimated() {
+        // 2, 1, and 3 pages of 40 rows; the third sheet is hidden
+        let xlsx = build_hidden_sheets_xlsx(&[(80, 2), (40, 2), (120, 2)], &[2]);
+        let estimate_sheets = |options: &str| {
+            let result = estimate(&xlsx, Some("model.xlsx"), options).unwrap();
+            let skipped: Vec<bool> = result.sheets.as_ref().unwrap().iter().map(|sheet| sheet.skipped).collect();
+            (result.page_count, skipped, result.notes)
+        };
+
+        // without a selection, hidden sheets aren't printed
+        let (pages, skipped, _) = estimate_sheets("{}");
+        assert_eq!((pages, skipped), (3, vec![false, false, true]));
+        let result = estimate(&xlsx, None, "{}").unwrap();
+        let hidden: Vec<bool> = result.sheets.unwrap().iter().map(|sheet| sheet.hidden).collect();
+        assert_eq!(hidden, [false, false, true]);
+
+        // an empty selection is no filter, not an empty one
+        let (pages, skipped, _) = estimate_sheets(r#"{"xlsx_sheets":[],"xlsx_sheet_indices":[]}"#);
+        assert_eq!((pages, skipped), (3, vec![false, false, true]));
+
+        // names match case-insensitively; a selection overrides visibility
+        let (pages, skipped, notes) = estimate_sheets(r#"{"xlsx_sheets":["sheet2"],"verbosity":2}"#);
+        assert_eq!((pages, skipped), (1, vec![true, false, true]));
+        assert!(notes.iter().any(|n| n == "Sheet 'Sheet1' skipped (not selected)"), "{:?}", notes);
+        let (pages, skipped, _) = estimate_sheets(r#"{"xlsx_sheets":["SHEET3"]}"#);
+        assert_eq!((pages, skipped), (3, vec![true, true, false]));
+
+        // names and indices select together
+        let (pages, skipped, _) = estimate_sheets(r#"{"xlsx_sheet_indices":[0]}"#);
+        assert_eq!((pages, skipped), (2, vec![false, true, true]));
+        let (pages, skipped, _) = estimate_sheets(r#"{"xlsx_sheets":["Sheet2"],"xlsx_sheet_indices":[0,2]}"#);
+        assert_eq!((pages, skipped), (6, vec![false, false, false]));
+
+        // selections that match nothing are reported
+        let (pages, _, notes) = estimate_sheets(r#"{"xlsx_sheets":["Sheet1","Summary"],"xlsx_sheet_indices":[7]}"#);
+        assert_eq!(pages, 2);
+        for note in [
+            "⚠ Selected sheet 'Summary' not found in the workbook",
+            "⚠ Selected sheet index 7 is out of range (3 sheets)",
+        ] {
+            assert!(notes.iter().any(|n| n == note), "{}: {:?}", note, notes);
+        }
+    }
+
+    #[test]
+    fn empty_sheets_are_floored_only_when_included() {
+        let xlsx = build_formatting_only_xlsx(100);
+        let sheets = |result: &EstimateResult| -> Vec<(usize, bool)> {
+            result.sheets.as_ref().unwrap().iter().map(|sheet| (sheet.pages, sheet.floored)).collect()
+        };
+        let plain = estimate(&xlsx, None, "{}").unwrap();
+        assert_eq!((plain.page_count, sheets(&plain)), (3, vec![(3, false), (0, false)]));
+        let zero = estimate(&xlsx, None, r#"{"xlsx_min_pages_per_visible_sheet":0}"#).unwrap();
+        assert_eq!(zero.page_count, 3);
+
+        let floored = estimate(&xlsx, None, r#"{"xlsx_min_pages_per_visible_sheet":1}"#).unwrap();
+        assert_eq!((floored.page_count, sheets(&floored)), (4, vec![(3, false), (1, true)]));
+        assert!(floored.notes[0].contains("1 raised to the 1-page minimum"), "{:?}", floored.notes);
+        let detail = estimate(&xlsx, None, r#"{"xlsx_min_pages_per_visible_sheet":2,"verbosity":2}"#).unwrap();
+        assert_eq!(sheets(&detail), [(3, false), (2, true)]);
+        let note = "Sheet 'Sheet2' raised from 0 to 2 pages (xlsx_min_pages_per_visible_sheet)".to_string();
+        assert!(detail.notes.contains(&note), "{:?}", detail.notes);
+
+        // hidden and unselected sheets aren't printed, so they aren't floored
+        let hidden = build_hidden_sheets_xlsx(&[(80, 2), (0, 0), (0, 0)], &[2]);
+        let result = estimate(&hidden, None, r#"{"xlsx_min_pages_per_visible_sheet":1}"#).unwrap();
+        assert_eq!((result.page_count, sheets(&result)), (3, vec![(2, false), (1, true), (0, false)]));
+        let options = r#"{"xlsx_min_pages_per_visible_sheet":1,"xlsx_sheet_indices":[0]}"#;
+        let result = estimate(&hidden, None, options).unwrap();
+        assert_eq!((result.page_count, sheets(&result)), (2, vec![(2, false), (0, false), (0, false)]));
+        // a hidden sheet selected by name is printed, and floored
+        let options = r#"{"xlsx_min_pages_per_visible_sheet":1,"xlsx_sheets":["Sheet3"]}"#;
+        assert_eq!(sheets(&estimate(&hidden, None, options).unwrap()), [(0, false), (0, false), (1, true)]);
+    }
+
+    #[test]
+    fn pptx_text_metrics_include_the_speaker_notes() {
+        let pptx = build_notes_pptx(&[Some("Welcome everyone.\nToday: three results."), None, Some("Questions?")]);
+        let result = estimate(&pptx, Some("talk.pptx"), "{}").unwrap();
+        assert_eq!(result.page_count, 3);
+        assert_eq!(result.text_metrics, None);
+
+        let result = estimate(&pptx, Some("talk.pptx"), r#"{"extract_text_metrics":true,"verbosity":2}"#).unwrap();
+        let metrics = result.text_metrics.unwrap();
+        // three slides of "Slide N", then the notes of two of them
+        assert_eq!((metrics.line_count, metrics.word_count, metrics.char_count), (6, 12, 69));
+        for note in [
+            "Text: 6 words on 3 slides, 6 words in the notes of 2 slides",
+            "Slide 1: 2 words, 7 characters; notes 5 words, 38 characters",
+            "Slide 2: 2 words, 7 characters; no notes",
+        ] {
+            assert!(result.notes.iter().any(|n| n == note), "{}: {:?}", note, result.notes);
+        }
+    }
+
+    #[test]
+    fn navigation_counts_need_extended_pdf_info() {
+        let pdf = build_pdf(6, XrefStyle::Classic, true, false);
+        let result = estimate(&pdf, None, "{}").unwrap();
+        let counts = (result.bookmark_count, result.named_destination_count, result.attachment_count);
+        assert_eq!(counts, (None, None, None));
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json.get("bookmark_count").is_none(), "{}", json);
+
+        let result = estimate(&pdf, None, r#"{"extended_pdf_info":true}"#).unwrap();
+        let counts = (result.bookmark_count, result.named_destination_count, result.attachment_count);
+        assert_eq!(counts, (Some(6), Some(0), Some(0)));
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!((&json["bookmark_count"], &json["attachment_count"]), (&json!(6), &json!(0)));
+    }
+
+    #[test]
+    fn strict_packages_count_like_their_transitional_twins() {
+        let twins: [(&str, Vec<u8>, &str); 7] = [
+            ("stated.docx", build_docx(30, 0, Some(4)), "{}"),
+            ("fallback.docx", build_docx(300, 2, None), r#"{"extract_text_metrics":true}"#),
+            ("chunks.docx", build_alt_chunk_docx(&[("txt", "Attached notes.\n")]), "{}"),
+            ("rendered.docx", build_rendered_breaks_docx(), r#"{"return_boundaries":true}"#),
+            ("deck.pptx", build_pptx(6, 2), "{}"),
+            ("talk.pptx", build_notes_pptx(&[Some("Hello."), None]), r#"{"extract_text_metrics":true}"#),
+            ("book.xlsx", build_xlsx(&[(90, 3), (10, 2)]), "{}"),
+        ];
+        for (filename, transitional, options) in twins {
+            let strict = estimate(&to_strict(&transitional), Some(filename), options).unwrap();
+            let transitional = estimate(&transitional, Some(filename), options).unwrap();
+            assert_eq!(strict.page_count, transitional.page_count, "{}", filename);
+            assert_eq!(strict.page_count_range(), transitional.page_count_range(), "{}", filename);
+            assert_eq!(strict.text_metrics, transitional.text_metrics, "{}", filename);
+            assert_eq!(strict.page_start_paragraphs, transitional.page_start_paragraphs, "{}", filename);
+            assert_eq!(strict.notes, transitional.notes, "{}", filename);
+            assert_eq!(transitional.ooxml_conformance.as_deref(), Some("transitional"), "{}", filename);
+            assert_eq!(strict.ooxml_conformance.as_deref(), Some("strict"), "{}", filename);
+        }
+    }
+
+    #[test]
+    fn metadata_counts_are_trusted_by_producer() {
+        let result = estimate(&build_docx_written_by("python-docx", 40, 1), None, "{}").unwrap();
+        assert_eq!(result.page_count, 1);
+        assert_eq!(result.producer.as_deref(), Some("python-docx"));
+        assert_eq!(result.confidence, Some(0.4));
+        let warning = "⚠ Count from metadata written by python-docx, which doesn't lay out pages; \
+                       it may not match the document";
+        assert!(result.notes.iter().any(|n| n == warning), "{:?}", result.notes);
+
+        let result = estimate(&build_google_docx(40, 0, 2), None, "{}").unwrap();
+        assert_eq!((result.producer.as_deref(), result.confidence), (Some("Google Docs"), Some(0.95)));
+        assert!(!result.notes.iter().any(|n| n.starts_with('⚠')), "{:?}", result.notes);
+
+        // producers not in the table make no judgement
+        let result = estimate(&build_docx_written_by("Microsoft Office Word", 40, 2), None, "{}").unwrap();
+        assert_eq!((result.producer.as_deref(), result.confidence), (Some("Microsoft Office Word"), None));
+    }
+
+    #[test]
+    fn pdf_orientation_counts_split_the_pages() {
+        let result = estimate(&build_mixed_orientation_pdf(), None, "{}").unwrap();
+        assert_eq!(result.page_count, 6);
+        let counts = (result.portrait_pages, result.landscape_pages, result.square_pages);
+        assert_eq!(counts, (Some(3), Some(2), Some(1)));
+        let note = "Orientation: 3 portrait, 2 landscape, 1 square pages";
+        assert!(result.notes.iter().any(|n| n == note), "{:?}", result.notes);
+
+        // all-portrait documents get the counters but no note
+        let result = estimate(&build_pdf(4, XrefStyle::Classic, false, false), None, "{}").unwrap();
+        assert_eq!((result.portrait_pages, result.landscape_pages, result.square_pages), (Some(4), Some(0), Some(0)));
+        assert!(!result.notes.iter().any(|n| n.starts_with("Orientation")), "{:?}", result.notes);
+
+        // other formats take them from their page size
+        let deck = estimate(&build_pptx(3, 0), None, "{}").unwrap();
+        assert_eq!((deck.portrait_pages, deck.landscape_pages, deck.square_pages), (Some(0), Some(3), Some(0)));
+    }
+
+    #[test]
+    fn rounding_modes_at_page_boundaries() {
+        let pages = |bytes: &[u8], filename: &str, options: &str, mode: &str| {
+            let options = format!(r#"{{{},"rounding":"{}"}}"#, options, mode);
+            estimate(bytes, Some(filename), &options).unwrap().page_count
+        };
+        // exactly 3 pages, 3 pages and one more unit, just under, and exactly
+        // 3.5 pages, and a single unit: in ceil, nearest, and floor
+        let expected: [(&str, [usize; 5]); 3] =
+            [("ceil", [3, 4, 4, 4, 1]), ("nearest", [3, 3, 3, 4, 1]), ("floor", [3, 3, 3, 3, 1])];
+        for (mode, counts) in expected {
+            let chars = [300, 301, 349, 350, 1].map(|n| {
+                let text = "x".repeat(n);
+                pages(text.as_bytes(), "a.txt", r#""chars_per_page":100"#, mode)
+            });
+            assert_eq!(chars, counts, "characters, {}", mode);
+            let lines = [30, 31, 34, 35, 1].map(|n| {
+                let text = "line\n".repeat(n);
+                pages(text.as_bytes(), "a.txt", r#""chars_per_line":40,"lines_per_page":10"#, mode)
+            });
+            assert_eq!(lines, counts, "lines, {}", mode);
+            let rows =
+                [30, 31, 34, 35, 1].map(|n| pages(&build_xlsx(&[(n, 2)]), "a.xlsx", r#""rows_per_page":10"#, mode));
+            assert_eq!(rows, counts, "rows, {}", mode);
+        }
+
+        // the mode is noted unless it is the default
+        let notes = |mode: &str| estimate(b"x", Some("a.txt"), &format!(r#"{{"rounding":"{}"}}"#, mode)).unwrap().notes;
+        assert!(!notes("ceil").iter().any(|n| n.contains("rounding")));
+        let nearest = "Partly filled last pages rounded to the nearest page (rounding: nearest)";
+        assert!(notes("nearest").iter().any(|n| n == nearest));
+        assert!(notes("floor").iter().any(|n| n == "Partly filled last pages rounded down (rounding: floor)"));
+        assert!(matches!(estimate(b"x", None, r#"{"rounding":"up"}"#), Err(EstimatorError::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn embedded_objects_add_pages_to_docx_estimates() {
+        let xlsx = build_xlsx(&[(200, 5)]);
+        let pdf = build_pdf(5, XrefStyle::Classic, false, false);
+        let docx = build_embedded_objects_docx(&[
+            ("Microsoft_Excel_Worksheet.xlsx", &xlsx),
+            ("spec.pdf", &pdf),
+            ("oleObject1.bin", b"\xd0\xcf\x11\xe0opaque"),
+        ]);
+        let notes_for = |result: &EstimateResult| -> Vec<String> {
+            result.notes.iter().filter(|note| note.contains("mbedded object")).cloned().collect()
+        };
+
+        // by default every object is half a page: one page of text plus 1.5 rounded up
+        let counted = estimate(&docx, Some("spec.docx"), "{}").unwrap();
+        assert_eq!(counted.page_count, 3);
+        assert_eq!(
+            notes_for(&counted),
+            [
+                "3 embedded objects add 2 pages (0 estimated, 1.5 page equivalents)",
+                "Embedded object word/embeddings/Microsoft_Excel_Worksheet.xlsx: counted as 0.5 pages",
+                "Embedded object word/embeddings/spec.pdf: counted as 0.5 pages",
+                "Embedded object word/embeddings/oleObject1.bin: counted as 0.5 pages",
+            ]
+        );
+        let heavier = estimate(&docx, Some("spec.docx"), r#"{"docx_object_page_equivalent":2}"#).unwrap();
+        assert_eq!(heavier.page_count, 1 + 6);
+
+        // the spreadsheet and the PDF are estimated; the opaque blob still counts as half a page
+        let xlsx_pages = estimate(&xlsx, Some("sheet.xlsx"), "{}").unwrap().page_count;
+        let recursed = estimate(&docx, Some("spec.docx"), r#"{"estimate_attachments":true}"#).unwrap();
+        assert_eq!(recursed.page_count, 1 + xlsx_pages + 5 + 1);
+        assert_eq!(
+            notes_for(&recursed)[1..],
+            [
+                format!(
+                    "Embedded object word/embeddings/Microsoft_Excel_Worksheet.xlsx: {} pages (estimated)",
+                    xlsx_pages
+                ),
+                "Embedded object word/embeddings/spec.pdf: 5 pages (estimated)".to_string(),
+                "Embedded object word/embeddings/oleObject1.bin: counted as 0.5 pages".to_string(),
+            ]
+        );
+        assert!(recursed.page_count_min.unwrap() >= 1 + xlsx_pages + 5);
+
+        // parts over the entry limit fall back to the page equivalent
+        let capped =
+            estimate(&docx, Some("spec.docx"), r#"{"estimate_attachments":true,"archive_max_entry_bytes":1000}"#)
+                .unwrap();
+        assert_eq!(capped.page_count, 3);
+        assert!(notes_for(&capped)[2].contains("could not be estimated (part exceeds 1000 bytes)"), "{:?}", capped);
+
+        // parts the body doesn't reference, like chart data, are only noted
+        let charted = with_part(&docx, "word/embeddings/Microsoft_Excel_Chart1.xlsx", "chart data");
+        let charted = estimate(&charted, Some("spec.docx"), "{}").unwrap();
+        assert_eq!(charted.page_count, 3);
+        assert!(charted.notes.last().unwrap().starts_with("1 parts in word/embeddings aren't referenced"));
+
+        for out_of_range in ["-1", "100.5", "1e300"] {
+            let options = format!(r#"{{"docx_object_page_equivalent":{}}}"#, out_of_range);
+            let err = estimate(&docx, Some("spec.docx"), &options).unwrap_err();
+            assert!(err.to_string().contains("must be from 0 to 100"), "{}", err);
+        }
+        // the largest accepted equivalent counts every object in full
+        let largest = estimate(&docx, Some("spec.docx"), r#"{"docx_object_page_equivalent":100}"#).unwrap();
+        assert!(largest.page_count > 100 && largest.page_count < 1000, "{}", largest.page_count);
+    }
+
+    #[test]
+    fn data_files_are_estimated_line_by_line() {
+        let minified = build_minified_json(400);
+        let pretty = build_pretty_json(400);
+        let pretty_pages = estimate(pretty.as_bytes(), Some("orders.json"), "{}").unwrap().page_count;
+        assert_eq!(pretty_pages, pretty.lines().count().div_ceil(CODE_LINES_PER_PAGE));
+        for filename in [Some("orders.json"), None] {
+            let (detected, _) = estimate_bytes(minified.as_bytes(), filename, &EstimateOptions::default());
+            assert_eq!(detected, "data", "{:?}", filename);
+
+            // as one long line, the minified records wrap onto far fewer pages
+            let literal = estimate(minified.as_bytes(), filename, "{}").unwrap();
+            assert_eq!(literal.method.as_deref(), Some("data-lines"));
+            assert!(literal.page_count * 4 < pretty_pages, "{} pages", literal.page_count);
+            assert!(literal.notes[1].starts_with("JSON looks minified; set pretty_print_minified"));
+
+            let laid_out = estimate(minified.as_bytes(), filename, r#"{"pretty_print_minified":true}"#).unwrap();
+            assert_eq!(laid_out.page_count, pretty_pages, "{:?}", filename);
+        }
+
+        let elements: String = (0..300)
+            .map(|i| format!(r#"<order id="{}"><sku>SKU-{}</sku><qty>{}</qty></order>"#, i, i, i % 9))
+            .collect();
+        let xml = format!(r#"<?xml version="1.0"?><orders>{}</orders>"#, elements);
+        let laid_out = estimate(xml.as_bytes(), None, r#"{"pretty_print_minified":true}"#).unwrap();
+        // the declaration, the root's two tags, and a line per order and per field
+        assert!(laid_out.notes[1].ends_with("(1203 lines)"), "{:?}", laid_out.notes);
+        assert_eq!(laid_out.page_count, 1203usize.div_ceil(CODE_LINES_PER_PAGE));
+
+        let yaml = "server:\n  host: example.com\n  port: 8080\nfeatures:\n  - print\n  - scan\n";
+        let result = estimate(yaml.as_bytes(), None, r#"{"pretty_print_minified":true}"#).unwrap();
+        assert_eq!((result.page_count, result.method.as_deref()), (1, Some("data-lines")));
+        assert_eq!(result.notes[0], "YAML data, 6 lines");
+    }
+
+    #[test]
+    fn word_web_archives_count_like_their_text() {
+        let paragraph = "The committee reviewed the annual budget and approved the proposed changes to the \
+                         printing schedule for the coming quarter, noting that demand had risen in every region.";
+        let paragraphs = [paragraph; 60];
+        let mht = build_word_mht(&paragraphs, 100_000);
+        let original = estimate(paragraphs.join("\n\n").as_bytes(), Some("report.txt"), "{}").unwrap();
+
+        for filename in [Some("report.mht"), Some("report.mhtml"), None] {
+            let (detected, _) = estimate_bytes(&mht, filename, &EstimateOptions::default());
+            assert_eq!(detected, "mht", "{:?}", filename);
+            let result = estimate(&mht, filename, "{}").unwrap();
+            assert_eq!(result.page_count, original.page_count, "{:?}", filename);
+            assert_eq!(
+                result.notes.last().unwrap(),
+                "MHT web archive: text/html root part estimated as text; 2 resource parts (100095 bytes) skipped"
+            );
+        }
+        // read as text, the base64 image alone fills dozens of pages
+        let as_text = estimate(&mht, Some("report.txt"), "{}").unwrap();
+        assert!(as_text.page_count > 10 * original.page_count, "{} pages", as_text.page_count);
+    }
+
+    #[test]
+    fn docx_equations_add_their_lines() {
+        let paper = build_equations_docx(25, 75, 20);
+        let without_lines = r#"{"docx_display_equation_lines":0,"docx_inline_equation_lines":0}"#;
+        let text_only = estimate(&paper, None, without_lines).unwrap().page_count;
+        let result = estimate(&paper, None, "{}").unwrap();
+        // 101 paragraphs (the text box's included) make 4.04 pages, the equations' 160 lines 3.2 more
+        assert_eq!((text_only, result.page_count), (5, 8));
+        let nearest = |options: &str| estimate(&paper, None, options).unwrap().page_count;
+        assert_eq!(nearest(r#"{"rounding":"nearest"}"#), EQUATIONS_DOCX_PAGES);
+        assert_eq!(
+            nearest(r#"{"rounding":"nearest","docx_display_equation_lines":0,"docx_inline_equation_lines":0}"#),
+            EQUATIONS_DOCX_PAGES - 3
+        );
+        assert!(result.notes.contains(
+            &"95 equations (75 display, 20 inline) take about 160.0 lines (3.20 pages), added to the paragraph estimate"
+                .to_string()
+        ));
+        assert!(result.page_count_max.unwrap() >= result.page_count);
+
+        let options = r#"{"docx_display_equation_lines":4,"docx_inline_equation_lines":0}"#;
+        assert_eq!(estimate(&paper, None, options).unwrap().page_count, 4 + 6 + 1);
+        for out_of_range in [
+            r#"{"docx_inline_equation_lines":-0.5}"#,
+            r#"{"docx_display_equation_lines":1e9}"#,
+            r#"{"docx_inline_equation_lines":50.5}"#,
+        ] {
+            let err = estimate(&paper, None, out_of_range).unwrap_err();
+            assert!(matches!(err, EstimatorError::InvalidOptions(_)), "{:?}", err);
+            assert!(err.to_string().contains("must be from 0 to 50"), "{}", err);
+        }
+        // a page per equation is the most accepted
+        let options = r#"{"docx_display_equation_lines":50,"docx_inline_equation_lines":50}"#;
+        assert!(estimate(&paper, None, options).unwrap().page_count > 95);
+
+        // with page breaks, the breaks set the count
+        let broken = build_docx(30, 3, None);
+        assert_eq!(estimate(&broken, None, "{}").unwrap().page_count, 4);
+    }
+
+    #[test]
+    fn docx_fallback_content_is_counted_once() {
+        let result = estimate(&build_equations_docx(2, 0, 0), None, r#"{"extract_text_metrics":true}"#).unwrap();
+        // two paragraphs of 55 characters and the caption of the Choice branch only
+        assert_eq!(result.text_metrics.unwrap().char_count, 2 * 55 + "Figure 1".len());
+        assert!(result.notes.contains(
+            &"1 mc:Fallback branches skipped; only their mc:Choice content is counted".to_string()
+        ));
+    }
+
+    #[test]
+    fn pdf_page_sizes_are_read_from_the_media_box() {
+        let letter = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R 5 0 R] /Count 3 /MediaBox [0 0 612 792] >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 792 612] >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+        ]);
+        let result = estimate(&letter, None, r#"{"include_point_sizes":true}"#).unwrap();
+        let in_points: Vec<(f64, f64)> =
+            result.page_sizes_pt.as_ref().unwrap().iter().map(|size| (size.width_pt, size.height_pt)).collect();
+        assert_eq!(in_points, [(612.0, 792.0), (792.0, 612.0), (612.0, 792.0)]);
+        let in_mm: Vec<(f64, f64)> =
+            result.page_sizes.iter().map(|size| (size.width_mm.round(), size.height_mm.round())).collect();
+        assert_eq!(in_mm, [(216.0, 279.0), (279.0, 216.0), (216.0, 279.0)]);
+        assert_eq!((result.portrait_pages, result.landscape_pages), (Some(2), Some(1)));
+
+        // the two lists agree through mm_from_pt, quarter-turned pages included
+        for pdf in [letter, build_mixed_orientation_pdf(), build_receipt_pdf(&[200.0, 400.0])] {
+            let result = estimate(&pdf, None, r#"{"include_point_sizes":true}"#).unwrap();
+            let in_points = result.page_sizes_pt.unwrap();
+            assert_eq!(in_points.len(), result.page_sizes.len());
+            for (pt, mm) in in_points.iter().zip(&result.page_sizes) {
+                assert!((mm_from_pt(pt.width_pt) - mm.width_mm).abs() < 1e-9, "{:?} {:?}", pt, mm);
+                assert!((mm_from_pt(pt.height_pt) - mm.height_mm).abs() < 1e-9, "{:?} {:?}", pt, mm);
+            }
+        }
+        let receipt = estimate(&build_receipt_pdf(&[200.0, 400.0]), None, "{}").unwrap();
+        let heights: Vec<f64> = receipt.page_sizes.iter().map(|size| size.height_mm.round()).collect();
+        assert_eq!(heights, [200.0, 400.0]);
+        assert!(receipt.page_sizes.iter().all(|size| (size.width_mm - RECEIPT_WIDTH_MM).abs() < 0.5));
+
+        // a page tree listing fewer pages than were counted leaves A4 in place
+        let orphaned = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Type /Page /MediaBox [0 0 612 792] >>",
+        ]);
+        let result = estimate(&orphaned, None, "{}").unwrap();
+        assert_eq!(result.page_count, 2);
+        assert!(result.page_sizes.iter().all(|size| (size.width_mm, size.height_mm) == a4_mm()));
+    }
+
+    #[test]
+    fn shifted_pdfs_keep_the_page_tree_count() {
+        for style in [XrefStyle::Classic, XrefStyle::Stream] {
+            let pdf = build_shifted_pdf(9, style, 37);
+            let result = estimate(&pdf, Some("scanned.pdf"), r#"{"include_point_sizes":true}"#).unwrap();
+            let unshifted = estimate(&build_pdf(9, style, false, false), None, "{}").unwrap();
+            assert_eq!((result.page_count, result.confidence), (9, unshifted.confidence), "{:?}", style);
+            assert_eq!(result.page_sizes_pt.unwrap().len(), 9);
+            assert!(
+                result.notes.contains(
+                    &"Cross-reference offsets are shifted by 37 bytes (the file was altered in transit); \
+                      objects were located by their headers instead"
+                        .to_string()
+                ),
+                "{:?}",
+                result.notes
+            );
+            assert!(!unshifted.notes.iter().any(|note| note.contains("shifted")));
+        }
+    }
+
+    #[test]
+    fn byte_order_marks_are_not_counted_as_text() {
+        let text = "Quarterly report, section one. ".repeat(200);
+        let with_bom = format!("\u{FEFF}{}", text);
+        let plain = estimate(text.as_bytes(), None, "{}").unwrap();
+        let marked = estimate(with_bom.as_bytes(), None, "{}").unwrap();
+        assert_eq!(detect_type(None, with_bom.as_bytes()), "txt");
+        assert_eq!((marked.page_count, &marked.notes), (plain.page_count, &plain.notes));
+        assert!(plain.notes[0].starts_with(&format!("chars: {},", text.chars().count())), "{:?}", plain.notes);
+
+        let json = format!("\u{FEFF}{}", build_pretty_json(40));
+        assert_eq!(detect_type(None, json.as_bytes()), "data");
+        assert!(estimate(json.as_bytes(), None, "{}").unwrap().page_count > 0);
+    }
+
+    #[test]
+    fn finder_zipped_pptx_counts_each_slide_once() {
+        // without docProps/app.xml the slides are counted from their files
+        let pptx = to_finder_zip(&without_part(&build_pptx(4, 0), "docProps/app.xml"));
+        let archive = ZipArchive::new(Cursor::new(&pptx[..])).unwrap();
+        let names: Vec<String> = archive.file_names().map(Into::into).collect();
+        assert!(names.iter().any(|name| name == "__MACOSX/ppt/slides/._slide1.xml"), "{:?}", names);
+        for options in ["{}", r#"{"extract_text_metrics":true}"#] {
+            let result = estimate(&pptx, Some("deck.pptx"), options).unwrap();
+            assert_eq!(result.page_count, 4);
+            assert_eq!(result.notes[0], "PPTX presentation has 4 slides (counted from files)");
+        }
+        let words = estimate(&pptx, None, r#"{"extract_text_metrics":true}"#).unwrap().text_metrics.unwrap();
+        let plain = without_part(&build_pptx(4, 0), "docProps/app.xml");
+        assert_eq!(words, estimate(&plain, None, r#"{"extract_text_metrics":true}"#).unwrap().text_metrics.unwrap());
+    }
+
+    #[test]
+    fn pptx_slides_take_the_deck_size() {
+        let poster = estimate(&build_sized_pptx(2, 43_891_200, 32_918_400, Some("custom")), Some("poster.pptx"), "{}");
+        let poster = poster.unwrap();
+        assert_eq!(poster.page_count, 2);
+        assert!(poster.page_sizes.iter().all(|size| (size.width_mm, size.height_mm) == (1219.2, 914.4)));
+        assert_eq!(poster.landscape_pages, Some(2));
+
+        let a4 = estimate(&build_sized_pptx(1, 6_858_000, 9_906_000, Some("A4")), Some("a4.pptx"), "{}").unwrap();
+        assert_eq!((a4.page_sizes[0].width_mm, a4.portrait_pages), (190.5, Some(1)));
+        assert!(!a4.notes.iter().any(|note| note.contains("doesn't match")), "{:?}", a4.notes);
+
+        let mislabelled = build_sized_pptx(1, 9_144_000, 5_143_500, Some("screen4x3"));
+        let wide = estimate(&mislabelled, Some("wide.pptx"), "{}").unwrap();
+        assert_eq!(wide.page_sizes[0].height_mm, 142.875);
+        assert!(wide.notes.iter().any(|note| note.contains("doesn't match its type")), "{:?}", wide.notes);
+        let plain = estimate(&build_pptx(1, 0), Some("plain.pptx"), "{}").unwrap();
+        assert_eq!((plain.page_sizes[0].width_mm, plain.page_sizes[0].height_mm), (254.0, 190.5));
+    }
+
+    #[test]
+    fn packages_with_a_damaged_central_directory_are_rebuilt() {
+        let packages = [
+            (build_docx(30, 2, Some(5)), "a.docx"),
+            (build_pptx(7, 0), "a.pptx"),
+            (build_xlsx(&[(120, 4), (10, 2)]), "a.xlsx"),
+        ];
+        for (package, filename) in &packages {
+            let intact = estimate(package, Some(filename), "{}").unwrap();
+            let damaged = &package[..package.len() - 8];
+            let rebuilt = estimate(damaged, Some(filename), "{}").unwrap();
+            assert_eq!(rebuilt.page_count, intact.page_count, "{}", filename);
+            assert_eq!(rebuilt.page_sizes, intact.page_sizes, "{}", filename);
+            assert!(rebuilt.confidence.unwrap() <= 0.9, "{}", filename);
+            assert!(rebuilt.notes.iter().any(|note| note.contains("central directory is damaged")), "{}", filename);
+            // the damaged package is still recognized without its name
+            assert_eq!(estimate(damaged, None, "{}").unwrap().page_count, intact.page_count, "{}", filename);
+        }
+    }
+
+    #[test]
+    fn packages_without_their_main_part_fall_back() {
+        // DOCX falls back to the metadata of the truncated-archive recovery
+        let docx = corrupt_part(&build_docx(30, 2, Some(5)), "word/document.xml");
+        let result = estimate(&docx[..docx.len() - 8], Some("a.docx"), "{}").unwrap();
+        assert_eq!(result.page_count, 5);
+        assert!(result.notes.iter().any(|note| note.contains("appears truncated")), "{:?}", result.notes);
+
+        let xlsx = corrupt_part(&build_xlsx(&[(120, 4)]), "xl/workbook.xml");
+        let err = estimate(&xlsx[..xlsx.len() - 8], Some("a.xlsx"), "{}").unwrap_err();
+        assert_eq!(error_code(&err), "xlsx_error");
+    }
+
+    #[test]
+    fn contacts_and_events_are_counted_per_page() {
+        let contacts = estimate(&build_vcards(20), Some("contacts.vcf"), "{}").unwrap();
+        assert_eq!((contacts.page_count, contacts.method.as_deref()), (3, Some("records")));
+        assert_eq!(contacts.text_metrics.unwrap().record_count, Some(20));
+        assert_eq!(contacts.notes[0], "vCard: 20 contacts at 8 per page (2.50 pages)");
+        // ten contacts of seven lines each don't fit on a page, so the lines count instead
+        let denser = estimate(&build_vcards(20), None, r#"{"contacts_per_page": 10}"#).unwrap();
+        assert_eq!((denser.page_count, denser.method.as_deref()), (3, Some("record-lines")));
+        let sparser = estimate(&build_vcards(20), None, r#"{"contacts_per_page": 4}"#).unwrap();
+        assert_eq!((sparser.page_count, sparser.method.as_deref()), (5, Some("records")));
+
+        let events = estimate(&build_calendar(24), Some("team.ics"), r#"{"events_per_page": 6}"#).unwrap();
+        assert_eq!((events.page_count, events.method.as_deref()), (4, Some("records")));
+        assert!(events.notes.iter().any(|note| note.contains("1 other components")), "{:?}", events.notes);
+        // twelve of these events take more lines than a page holds
+        let crowded = estimate(&build_calendar(24), Some("team.ics"), "{}").unwrap();
+        assert_eq!(crowded.method.as_deref(), Some("record-lines"));
+        assert!(crowded.page_count > 2);
+
+        let zones = b"BEGIN:VCALENDAR\r\nBEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nEND:VTIMEZONE\r\nEND:VCALENDAR\r\n";
+        let listed = estimate(zones, Some("zones.ics"), "{}").unwrap();
+        assert_eq!((listed.page_count, listed.method.as_deref()), (1, Some("record-lines")));
+        assert_eq!(listed.text_metrics.unwrap().record_count, Some(0));
+    }
+
+    #[test]
+    fn pdf_text_metrics_are_extracted_on_request() {
+        let pdf = build_text_pdf(4, 300);
+        let plain = estimate(&pdf, Some("a.pdf"), "{}").unwrap();
+        assert_eq!(plain.text_metrics, None);
+        let result = estimate(&pdf, Some("a.pdf"), r#"{"extract_pdf_text_metrics": true}"#).unwrap();
+        let metrics = result.text_metrics.as_ref().unwrap();
+        // within 10% of the words on the pages, with the caveats in the metrics' confidence only
+        assert!(metrics.word_count.abs_diff(1200) <= 120, "{}", metrics.word_count);
+        assert_eq!(metrics.words_per_page, Some(300.0));
+        assert_eq!(metrics.confidence, Some(TEXT_METRICS_CONFIDENCE));
+        assert_eq!((result.page_count, result.confidence), (plain.page_count, plain.confidence));
+        assert!(result.notes.iter().any(|note| note.starts_with("Text: about 1200 words")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn protected_pdfs_are_counted_and_opened_with_their_password() {
+        for cipher in [PdfCipher::Rc4, PdfCipher::Aes128] {
+            let pdf = build_protected_pdf(3, "user", "owner", cipher);
+            let locked = estimate(&pdf, Some("a.pdf"), "{}").unwrap();
+            assert_eq!((locked.page_count, locked.encrypted, locked.producer.as_deref()), (3, true, None));
+            assert!(locked.notes.iter().any(|note| note.contains("need pdf_password")), "{:?}", locked.notes);
+
+            let options = r#"{"pdf_password": "owner", "extract_pdf_text_metrics": true}"#;
+            let opened = estimate(&pdf, Some("a.pdf"), options).unwrap();
+            assert_eq!((opened.page_count, opened.encrypted), (3, true));
+            assert_eq!(opened.producer.as_deref(), Some(PROTECTED_PDF_PRODUCER));
+            assert_eq!(opened.text_metrics.unwrap().word_count, 18);
+            assert!(opened.notes.iter().any(|note| note.contains("opened with the owner password")));
+
+            let err = estimate(&pdf, Some("a.pdf"), r#"{"pdf_password": "guess"}"#).unwrap_err();
+            assert_eq!(error_code(&err), "wrong_password");
+        }
+        // the password is never echoed
+        let pdf = build_protected_pdf(1, "", "owner", PdfCipher::Aes128);
+        let options = EstimateOptions { pdf_password: Some("owner".into()), ..Default::default() };
+        let json = estimate_to_json(&pdf, None, &options, &[]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(json["effective_options"]["pdf_password"].is_null(), "{}", json["effective_options"]);
+    }
+
+    #[test]
+    fn languages_are_detected_on_request() {
+        let german = LANGUAGE_SAMPLES[1].1;
+        assert_eq!(estimate(german.as_bytes(), Some("a.txt"), "{}").unwrap().language, None);
+        let options = r#"{"detect_language": true}"#;
+        let documents = [
+            (german.as_bytes().to_vec(), "a.txt", Some("de")),
+            (format!("# Titre\n\n{}\n", LANGUAGE_SAMPLES[2].1).into_bytes(), "a.md", Some("fr")),
+            (build_docx(30, 0, None), "a.docx", Some("en")),
+            (b"Kurzer Text.".to_vec(), "b.txt", None),
+        ];
+        for (bytes, filename, code) in documents {
+            let result = estimate(&bytes, Some(filename), options).unwrap();
+            assert_eq!(result.language.as_deref(), code, "{}", filename);
+            let guess = &result.diagnostics.unwrap()["language"];
+            assert_eq!(guess["code"].as_str(), code, "{}", filename);
+        }
+        let pdf = build_text_pdf(2, 200);
+        let options = r#"{"detect_language": true, "extract_pdf_text_metrics": true}"#;
+        assert_eq!(estimate(&pdf, Some("a.pdf"), options).unwrap().language.as_deref(), Some("en"));
+        let short = estimate(b"Kurzer Text.", Some("b.txt"), r#"{"detect_language": true}"#).unwrap();
+        let note = "Language not detected: 2 words, fewer than language_min_words (20)";
+        assert!(short.notes.iter().any(|n| n == note), "{:?}", short.notes);
+    }
+
+    #[test]
+    fn sheets_can_have_their_own_paper() {
+        let xlsx = build_xlsx(&[(200, 3), (200, 3), (200, 3)]);
+        let options = r#"{"xlsx_sheet_paper": {"sheet2": "297x420", "SHEET3": "A3 landscape", "Totals": "A4"}}"#;
+        let result = estimate(&xlsx, Some("pack.xlsx"), options).unwrap();
+        let sheets: Vec<(usize, Option<(f64, f64)>)> =
+            result.sheets.as_ref().unwrap().iter().map(|sheet| (sheet.pages, sheet.paper_mm)).collect();
+        // 40 rows per A4 page become 57 on A3 portrait, and stay 40 on A3 landscape
+        assert_eq!(sheets, [(5, Some(a4_mm())), (4, Some((297.0, 420.0))), (5, Some((420.0, 297.0)))]);
+        assert_eq!(result.page_count, 14);
+        let sizes: Vec<(f64, f64)> = result.page_sizes.iter().map(|size| (size.width_mm, size.height_mm)).collect();
+        assert_eq!(sizes[4..6], [a4_mm(), (297.0, 420.0)]);
+        assert_eq!(sizes[9..], [(420.0, 297.0); 5]);
+        let unknown = "⚠ xlsx_sheet_paper names sheet 'Totals', which is not in the workbook";
+        assert!(result.notes.iter().any(|note| note == unknown), "{:?}", result.notes);
+
+        // explicit rows per page apply on any paper
+        let options = r#"{"rows_per_page": 50, "xlsx_sheet_paper": {"Sheet2": "A3"}}"#;
+        let sheets = estimate(&xlsx, Some("pack.xlsx"), options).unwrap().sheets.unwrap();
+        assert!(sheets.iter().all(|sheet| sheet.pages == 4));
+        let err = estimate(&xlsx, Some("pack.xlsx"), r#"{"xlsx_sheet_paper": {"Sheet2": "huge"}}"#).unwrap_err();
+        assert_eq!(error_code(&err), "invalid_options");
+    }
+
+    #[test]
+    fn sheets_are_estimated_in_tab_order() {
+        let sheets = [(10, 2), (50, 2), (90, 2)];
+        let reordered = estimate(&build_reordered_xlsx(&sheets), Some("moved.xlsx"), "{}").unwrap();
+        let listed: Vec<(String, Option<u32>, usize)> = reordered
+            .sheets
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|sheet| (sheet.name.clone(), sheet.sheet_id, sheet.pages))
+            .collect();
+        assert_eq!(
+            listed,
+            [("Sheet1".into(), Some(3), 1), ("Sheet2".into(), Some(2), 2), ("Sheet3".into(), Some(1), 3)]
+        );
+        let in_order = estimate(&build_xlsx(&sheets), Some("moved.xlsx"), "{}").unwrap();
+        assert_eq!(reordered.page_count, in_order.page_count);
+
+        // indices count tabs, not parts
+        let first = estimate(&build_reordered_xlsx(&sheets), None, r#"{"xlsx_sheet_indices":[0]}"#).unwrap();
+        assert_eq!(first.page_count, 1);
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["sheets"][2]["sheet_id"], 1);
+    }
+
+    #[test]
+    fn google_drive_stubs_fail_with_not_exported() {
+        for (stub, filename) in [
+            (build_drive_stub("spreadsheet"), Some("budget.xlsx")),
+            (build_drive_stub("document"), None),
+            (build_drive_sign_in_page(), Some("letter.docx")),
+        ] {
+            let body = estimate_to_json(&stub, filename, &EstimateOptions::default(), &[]).unwrap_err();
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["error_code"], "not_exported", "{}", body);
+            assert!(body["error"].as_str().unwrap().contains("Export it as"), "{}", body);
+        }
+        let err = estimate(&build_drive_stub("presentation"), Some("deck.pptx"), "{}").unwrap_err();
+        assert!(err.to_string().contains("for a Google Slides file"), "{}", err);
+        assert!(err.to_string().contains(".pptx or .pdf"), "{}", err);
+    }
+
+    #[test]
+    fn docbook_pages_add_up_from_the_layout() {
+        let book = estimate(&build_docbook_book(2, 2), Some("guide.xml"), "{}").unwrap();
+        // title, contents, two pages per chapter (1970 prose cells and 57 lines each), and the index
+        assert_eq!(book.page_count, 1 + 1 + 2 * 2 + 1);
+        assert_eq!((book.page_count_min, book.page_count_max), (Some(5), Some(9)));
+        assert_eq!(book.confidence, Some(0.4));
+        let generated = "Generated pages: 1 title, 1 table of contents (6 entries), 1 index (8 entries)";
+        assert!(book.notes.iter().any(|n| n == generated), "{:?}", book.notes);
+
+        // smaller pages split each chapter further
+        let dense = estimate(&build_docbook_book(2, 2), Some("guide.xml"), r#"{"chars_per_page":1200}"#).unwrap();
+        assert_eq!(dense.page_count, 3 + 2 * 3);
+        // chapters start on a new page, so the body grows with them
+        let longer = estimate(&build_docbook_book(8, 2), Some("guide.xml"), "{}").unwrap();
+        assert_eq!(longer.page_count, 1 + 1 + 8 * 2 + 1);
+
+        let article = br#"<article xmlns="http://docbook.org/ns/docbook"><para>Short.</para></article>"#;
+        let result = estimate(article, None, "{}").unwrap();
+        assert_eq!(result.page_count, 1);
+        assert!(result.notes[0].starts_with("DocBook article estimated from 6 prose characters"), "{:?}", result.notes);
+
+        let broken = estimate(b"<book xmlns=\"http://docbook.org/ns/docbook\"><chapter></book>", Some("b.dbk"), "{}");
+        let notes = broken.unwrap().notes;
+        assert!(notes[0].starts_with("⚠ DocBook XML could not be read"), "{:?}", notes);
+        assert_eq!(notes[1], "XML data, 1 lines");
+    }
+
+    #[test]
+    fn docbook_survives_extreme_page_metrics() {
+        let book = build_docbook_book(2, 2);
+        for options in [
+            r#"{"lines_per_page": 18446744073709551615}"#,
+            r#"{"lines_per_page": 2147483648}"#,
+            r#"{"chars_per_page": 18446744073709551615}"#,
+            r#"{"chars_per_page": 18446744073709551615, "lines_per_page": 18446744073709551615}"#,
+        ] {
+            let result = estimate(&book, Some("guide.xml"), options).unwrap();
+            // title, contents, a page per chapter, and the index
+            assert_eq!(result.page_count, 1 + 1 + 2 + 1, "{}", options);
+        }
+    }
+
+    #[test]
+    fn rescanning_pdfs_fail_fast_with_parse_budget_exceeded() {
+        let pdf = build_rescanning_pdf(8000);
+        let start = std::time::Instant::now();
+        let body = estimate_to_json(&pdf, None, &EstimateOptions::default(), &[]).unwrap_err();
+        // unbudgeted, this file took about 40 s
+        assert!(start.elapsed() < std::time::Duration::from_secs(5), "took {:?}", start.elapsed());
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error_code"], "parse_budget_exceeded");
+        let budget = DEFAULT_SCAN_BUDGET_FACTOR * pdf.len();
+        assert_eq!(body["diagnostics"]["scan_budget_bytes"], budget);
+        let found = body["diagnostics"]["pages_found"].as_u64().unwrap();
+        assert!(found > 0 && found <= 8000, "{}", body);
+
+        // a larger factor buys more scanning, and 0 is refused
+        let err = estimate(&pdf, None, r#"{"pdf_scan_budget_factor":16}"#).unwrap_err();
+        assert!(matches!(err, EstimatorError::ParseBudgetExceeded { budget, .. } if budget == 16 * pdf.len()));
+        let err = estimate(&pdf, None, r#"{"pdf_scan_budget_factor":0}"#).unwrap_err();
+        assert!(matches!(err, EstimatorError::InvalidOptions(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn well_formed_pdfs_fit_a_small_scan_budget() {
+        let pdfs = [
+            (build_pdf(5, XrefStyle::Classic, true, false), 5),
+            (build_pdf(300, XrefStyle::Stream, false, false), 300),
+            (build_incrementally_saved_pdf(4, 3), 4),
+            (build_linearized_pdf(6), 6),
+            (build_mixed_orientation_pdf(), 6),
+        ];
+        for (pdf, pages) in pdfs {
+            let result = estimate(&pdf, None, r#"{"pdf_scan_budget_factor":2}"#).unwrap();
+            assert_eq!(result.page_count, pages);
+        }
+    }
+
+    #[test]
+    fn bidi_marks_leave_text_estimates_unchanged() {
+        for text in [ARABIC_TEXT, HEBREW_TEXT] {
+            let plain = format!("{}\n\n", text).repeat(40);
+            let marked = format!("{}\n", with_bidi_marks(text)).repeat(40);
+            let plain = estimate(plain.as_bytes(), Some("letter.txt"), "{}").unwrap();
+            let marked = estimate(marked.as_bytes(), Some("letter.txt"), "{}").unwrap();
+            assert_eq!(marked.page_count, plain.page_count);
+            assert_eq!(marked.text_metrics.unwrap().char_count, plain.text_metrics.unwrap().char_count);
+            assert_eq!(marked.notes, plain.notes);
+            assert!(plain.notes.iter().any(|n| n.contains("assumes left-to-right line metrics")), "{:?}", plain.notes);
+        }
+        let english = estimate("A few words. ".repeat(400).as_bytes(), None, "{}").unwrap();
+        assert!(!english.notes.iter().any(|n| n.contains("right-to-left")), "{:?}", english.notes);
+    }
+
+    #[test]
+    fn late_workbooks_count_the_sheets_read_in_time() {
+        let xlsx = build_xlsx(&[(200, 4), (300, 4), (100, 4)]);
+        let options = EstimateOptions { deadline_ms: Some(1), ..Default::default() };
+        let (result, passed) = with_deadline(Some(1), || {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            estimate_xlsx_pages(&xlsx, &options).unwrap()
+        });
+        assert!(passed);
+        assert_eq!(result.page_count, 5);
+        let unread = "⚠ 2 sheets not read before the deadline and not counted: 'Sheet2', 'Sheet3'";
+        assert_eq!(result.notes.last().unwrap(), unread);
+
+        // a workbook slower than its deadline comes back partial and flagged
+        let xlsx = build_xlsx(&[(5000, 4), (300, 4), (100, 4)]);
+        let full = estimate(&xlsx, None, "{}").unwrap();
+        let partial = estimate(&xlsx, None, r#"{"deadline_ms": 1}"#).unwrap();
+        assert_eq!((full.page_count, partial.page_count), (136, 125));
+        assert!(partial.notes[0].starts_with(DEADLINE_WARNING), "{:?}", partial.notes);
+        assert_eq!(partial.confidence, Some(DEADLINE_CONFIDENCE));
+        assert!(!full.notes.iter().any(|n| n.contains("deadline")), "{:?}", full.notes);
+        let generous = estimate(&xlsx, None, r#"{"deadline_ms": 600000}"#).unwrap();
+        assert_eq!((generous.page_count, generous.confidence), (136, full.confidence));
+    }
+
+    #[test]
+    fn late_pdfs_fail_when_nothing_was_counted() {
+        // pages without /Type are only found by the page tree, the second strategy
+        let untyped = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>",
+            "<< /Parent 2 0 R >>",
+            "<< /Parent 2 0 R >>",
+        ]);
+        let options = EstimateOptions { deadline_ms: Some(1), ..Default::default() };
+        let late = |pdf: &[u8]| {
+            with_deadline(Some(1), || {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                estimate_pdf_pages(pdf, &options)
+            })
+        };
+        let (result, passed) = late(&untyped);
+        assert!(passed);
+        let err = result.unwrap_err();
+        assert_eq!(error_code(&err), "deadline_exceeded");
+        assert_eq!(estimate(&untyped, None, r#"{"deadline_ms": 600000}"#).unwrap().page_count, 2);
+
+        // the first strategy always runs
+        let (result, passed) = late(&build_pdf(4, XrefStyle::Classic, false, false));
+        assert!(passed);
+        assert_eq!(result.unwrap().page_count, 4);
+        let err = estimate(&untyped, None, r#"{"deadline_ms": 0}"#).unwrap_err();
+        assert_eq!(error_code(&err), "invalid_options");
+    }
+
+    /// Where the pages of a DOCX start, read the way python-docx numbers paragraphs:
+    /// only the `<w:p>` children of `<w:body>` count, and a page break outside
+    /// one starts the next.
+    fn python_docx_page_starts(docx: &[u8]) -> Vec<usize> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(docx)).unwrap();
+        let mut xml = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("word/document.xml").unwrap(), &mut xml).unwrap();
+        let mut reader = XmlReader::from_str(&xml);
+        let (mut path, mut paragraphs, mut starts) = (Vec::new(), 0, vec![0]);
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) => {
+                    path.push(e.name().as_ref().to_vec());
+                    if path.len() == 3 && path[1] == b"w:body" && path[2] == b"w:p" {
+                        paragraphs += 1;
+                    }
+                }
+                Event::End(_) => {
+                    path.pop();
+                }
+                Event::Empty(e) if e.name().as_ref() == b"w:lastRenderedPageBreak" => {
+                    let in_body_paragraph = path.len() >= 3 && path[1] == b"w:body" && path[2] == b"w:p";
+                    starts.push(if in_body_paragraph { paragraphs - 1 } else { paragraphs });
+                }
+                Event::Eof => return starts,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn docx_pages_start_in_the_paragraphs_python_docx_lists() {
+        let docx = build_rendered_breaks_docx();
+        assert_eq!(python_docx_page_starts(&docx), RENDERED_BREAKS_PAGE_STARTS);
+        let boundaries = r#"{"return_boundaries": true}"#;
+        // from the page count Word saved, or estimated from the body
+        for (docx, pages) in [(docx.clone(), 4), (without_part(&docx, "docProps/app.xml"), 1)] {
+            let result = estimate(&docx, None, boundaries).unwrap();
+            assert_eq!(result.page_count, pages);
+            assert_eq!(result.page_start_paragraphs.as_deref(), Some(&RENDERED_BREAKS_PAGE_STARTS[..]));
+        }
+        let options = EstimateOptions { return_boundaries: Some(true), ..Default::default() };
+        let json = estimate_to_json(&docx, None, &options, &[]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["page_start_paragraphs"], json!(RENDERED_BREAKS_PAGE_STARTS));
+
+        // only asked for, and only from recorded breaks
+        let plain = estimate(&docx, None, "{}").unwrap();
+        assert_eq!(plain.page_start_paragraphs, None);
+        assert!(!serde_json::to_string(&plain).unwrap().contains("page_start_paragraphs"));
+        let unmarked = build_docx(30, 2, Some(4));
+        assert_eq!(python_docx_page_starts(&unmarked), [0]);
+        assert_eq!(estimate(&unmarked, None, boundaries).unwrap().page_start_paragraphs, None);
+    }
+
+    #[test]
+    fn signed_pdfs_are_flagged() {
+        let page = "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>";
+        let signed = |catalog: &str, field: &str| {
+            let pdf = build_raw_pdf(&[catalog, "<< /Type /Pages /Kids [3 0 R] /Count 1 >>", page, field]);
+            estimate(&pdf, Some("contract.pdf"), "{}").unwrap().is_signed
+        };
+        let form = "<< /Type /Catalog /Pages 2 0 R /AcroForm << /Fields [4 0 R] /SigFlags 3 >> >>";
+        let plain = "<< /Type /Catalog /Pages 2 0 R >>";
+        let signature = "<< /FT /Sig /T (Signature1) /V << /Type /Sig /Filter /Adobe.PPKLite /Contents <00> >> >>";
+        assert!(signed(form, signature));
+        // /Type is optional in a signature dictionary
+        assert!(signed(form, "<< /FT /Sig /V << /Filter /Adobe.PPKLite /ByteRange [0 10 20 30] >> >>"));
+        assert!(signed("<< /Type /Catalog /Pages 2 0 R /Perms << /DocMDP 4 0 R >> >>", "<< /Type /Annot >>"));
+        assert!(!signed(plain, "<< /FT /Tx /T (Name) /V (Ada) >>"));
+        let unsigned = estimate(&build_pdf(2, XrefStyle::Classic, false, false), Some("a.pdf"), "{}").unwrap();
+        assert_eq!(serde_json::to_value(&unsigned).unwrap()["is_signed"], false);
+    }
+
+    #[test]
+    fn alt_chunks_add_their_pages_to_the_body() {
+        let html: String = (0..200)
+            .map(|i| format!("<p>Dear customer {}, thank you for your order of the fixture products.</p>\n", i))
+            .collect();
+        let html = format!("<html><body>{}</body></html>", html);
+        // the same page as an MHT archive, quoted-printable with soft line breaks
+        let mht = format!(
+            "MIME-Version: 1.0\r\nContent-Type: multipart/related; boundary=\"b\"\r\n\r\n--b\r\n\
+             Content-Type: text/html; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n\
+             {}\r\n--b--\r\n",
+            html.replace('=', "=3D").replace('\n', "=\r\n")
+        );
+        let chunks = [("afchunk.htm", html.as_str()), ("afchunk2.mht", &mht), ("notes.txt", "one\ntwo\n")];
+        let result = estimate(&build_alt_chunk_docx(&chunks), Some("merge.docx"), "{}").unwrap();
+
+        let html_pages = estimate_text_pages(html_to_text(&html).as_bytes(), &EstimateOptions::default()).page_count;
+        assert!(html_pages > 1);
+        assert_eq!(result.page_count, 1 + html_pages * 2 + 1);
+        let listed = [
+            format!("3 altChunk parts add {} pages to the 1 pages of the body", html_pages * 2 + 1),
+            format!("altChunk word/afchunk.htm: {} pages", html_pages),
+            format!("altChunk word/afchunk2.mht: {} pages", html_pages),
+            "altChunk word/notes.txt: 1 pages".to_string(),
+        ];
+        assert!(result.notes.ends_with(&listed), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn unreadable_alt_chunks_are_skipped() {
+        let result = estimate(&build_alt_chunk_docx(&[("old.rtf", r"{\rtf1 Dear customer}")]), None, "{}").unwrap();
+        assert_eq!(result.page_count, 1);
+        assert!(result.notes.contains(&"⚠ altChunk word/old.rtf skipped: unsupported chunk type '.rtf'".to_string()));
+
+        let docx = build_alt_chunk_docx(&[("afchunk.htm", "<p>Dear customer</p>")]);
+        let rels = String::from_utf8(read_part(&docx, "word/_rels/document.xml.rels")).unwrap();
+        let rels = rels.replace("rIdChunk1", "rId9");
+        let dangling = replace_part(&docx, "word/_rels/document.xml.rels", rels.as_bytes());
+        let result = estimate(&dangling, None, "{}").unwrap();
+        assert!(result.notes.contains(&"⚠ altChunk rIdChunk1 has no relationship target; skipped".to_string()));
+    }
+
+    /// Markup spliced into a part by [`mutated_ooxml_parts_never_panic`].
+    const XML_TOKENS: &[&str] = &[
+        "<", ">", "/>", "</", "\"", "&amp;", "&#", "<w:p>", "</w:p>", "<w:br w:type=\"page\"/>",
+        "<w:sectPr>", "<p:sldId id=\"1\" r:id=\"rId9\"/>", "<row r=\"1048577\">", "<c r=\"XFD1\">",
+        "<dimension ref=\"A1:XFD1048576\"/>", "<Pages>", "</Pages>", "<![CDATA[", "<!--",
+    ];
+
+    /// Rebuilds `package` with `part` (which must exist) holding `contents`.
+    fn replace_part(package: &[u8], part: &str, contents: &[u8]) -> Vec<u8> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut entries = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            let data = if file.name() == part { contents.to_vec() } else { data };
+            entries.push((file.name().to_string(), data));
+        }
+        let entries: Vec<(&str, &[u8])> = entries.iter().map(|(n, d)| (n.as_str(), d.as_slice())).collect();
+        build_zip(&entries)
+    }
+
+    fn read_part(package: &[u8], part: &str) -> Vec<u8> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut data = Vec::new();
+        archive.by_name(part).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    /// Runs `package` through the OOXML estimators.
+    fn check_ooxml(package: &[u8]) {
+        let options = EstimateOptions::default();
+        let _ = estimate_docx_pages(package, &options);
+        let _ = estimate_pptx_pages(package, &options);
+        let _ = estimate_xlsx_pages(package, &options);
+    }
+
+    /// The fixture packages and the part of each that the mutations target.
+    fn ooxml_fixtures() -> Vec<(Vec<u8>, &'static str)> {
+        vec![
+            (build_docx(6, 2, Some(3)), "word/document.xml"),
+            (build_docx(6, 2, Some(3)), "docProps/app.xml"),
+            (build_pptx(3, 1), PRESENTATION_PART),
+            (build_pptx(3, 1), "ppt/slides/slide1.xml"),
+            (build_xlsx(&[(8, 3), (2, 2)]), "xl/worksheets/sheet1.xml"),
+            (build_xlsx(&[(8, 3)]), "xl/workbook.xml"),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(96))]
+
+        #[test]
+        fn arbitrary_bytes_never_panic_the_ooxml_estimators(
+            data in vec_of(any::<u8>(), 0..2048),
+        ) {
+            check_ooxml(&data);
+            let mut zip = b"PK\x03\x04".to_vec();
+            zip.extend_from_slice(&data);
+            check_ooxml(&zip);
+        }
+
+        #[test]
+        fn mutated_ooxml_parts_never_panic(
+            fixture in 0..6usize,
+            edits in vec_of((any::<usize>(), 0..XML_TOKENS.len()), 1..8),
+            cut in option_of(any::<usize>()),
+        ) {
+            let (package, part) = &ooxml_fixtures()[fixture];
+            let mut xml = read_part(package, part);
+            for (at, token) in edits {
+                let at = at % (xml.len() + 1);
+                xml.splice(at..at, XML_TOKENS[token].bytes());
+            }
+            if let Some(cut) = cut {
+                xml.truncate(cut % (xml.len() + 1));
+            }
+            check_ooxml(&replace_part(package, part, &xml));
+        }
+
+        #[test]
+        fn mutated_ooxml_packages_never_panic(
+            fixture in 0..6usize,
+            edits in vec_of((any::<usize>(), any::<u8>()), 1..8),
+        ) {
+            let mut package = ooxml_fixtures()[fixture].0.clone();
+            for (at, byte) in edits {
+                let at = at % package.len();
+                package[at] = byte;
+            }
+            check_ooxml(&package);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! # External References
+//!
+//! Flags OOXML documents whose content isn't all inside the package: pictures
+//! inserted with "Link to File", linked (not embedded) OLE objects, and
+//! formulas that read other workbooks (`xl/externalLinks/`). Such documents
+//! often print differently from what the author saw (missing images, stale
+//! values), so the estimate carries a warning.
+//!
+//! Only presence is detected. Targets are never resolved or fetched.
+//!
+//! ## Detection
+//!
+//! Every relationships part (`*.rels`) of the package is read, and
+//! relationships with `TargetMode="External"` are reported, except hyperlinks,
+//! which don't change what is printed.
+
+use crate::file_utils::note_zip_entry;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::{BufReader, Cursor};
+use zip::ZipArchive;
+
+/// How many external targets are listed in the note.
+pub const EXTERNAL_TARGETS_IN_NOTES: usize = 3;
+
+/// A relationship to content outside the package.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalReference {
+    /// The relationships part that declares it (e.g. `word/_rels/document.xml.rels`).
+    pub rels_part: String,
+    /// Last segment of the relationship type (e.g. `image`, `oleObject`, `externalLinkPath`).
+    pub kind: String,
+    /// The target as written, usually a path or URL.
+    pub target: String,
+}
+
+/// Lists the external relationships of an OOXML package, hyperlinks excluded.
+///
+/// # Returns
+///
+/// The references in archive order, then document order within each part.
+pub fn find_external_references(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Vec<ExternalReference> {
+    let rels_parts: Vec<String> =
+        archive.file_names().filter(|name| name.ends_with(".rels")).map(str::to_string).collect();
+    let mut references = Vec::new();
+    for part in rels_parts {
+        let Ok(file) = archive.by_name(&part) else { continue };
+        note_zip_entry(&file);
+        let mut reader = Reader::from_reader(BufReader::new(file));
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"Relationship" => {
+                    let value = |name: &[u8]| {
+                        e.attributes()
+                            .flatten()
+                            .find(|attr| attr.key.as_ref() == name)
+                            .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+                    };
+                    let external = value(b"TargetMode").is_some_and(|mode| mode.eq_ignore_ascii_case("External"));
+                    let kind = value(b"Type").and_then(|kind| kind.rsplit('/').next().map(str::to_string));
+                    if let (true, Some(kind), Some(target)) = (external, kind, value(b"Target"))
+                        && kind != "hyperlink"
+                    {
+                        references.push(ExternalReference { rels_part: part.clone(), kind, target });
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+    references
+}
+
+/// Summarizes external references in a note, listing the first `EXTERNAL_TARGETS_IN_NOTES` targets.
+///
+/// # Returns
+///
+/// A warning such as `⚠ 2 external references (linked content may print
+/// differently): image ../logo.png, externalLinkPath budget.xlsx`, or `None`
+/// when there are none.
+pub fn external_references_note(references: &[ExternalReference]) -> Option<String> {
+    if references.is_empty() {
+        return None;
+    }
+    let mut listed: Vec<String> = references
+        .iter()
+        .take(EXTERNAL_TARGETS_IN_NOTES)
+        .map(|reference| format!("{} {}", reference.kind, reference.target))
+        .collect();
+    if references.len() > EXTERNAL_TARGETS_IN_NOTES {
+        listed.push(format!("and {} more", references.len() - EXTERNAL_TARGETS_IN_NOTES));
+    }
+    Some(format!(
+        "⚠ {} external references (linked content may print differently): {}",
+        references.len(),
+        listed.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::schema::EstimateResult;
+
+    fn references(package: &[u8]) -> Vec<ExternalReference> {
+        find_external_references(&mut ZipArchive::new(Cursor::new(package)).unwrap())
+    }
+
+    #[test]
+    fn linked_pictures_and_workbooks_are_found() {
+        let docx = build_linked_images_docx(&["file:///C:/Plans/site.png"]);
+        assert_eq!(
+            references(&docx),
+            [ExternalReference {
+                rels_part: "word/_rels/document.xml.rels".into(),
+                kind: "image".into(),
+                target: "file:///C:/Plans/site.png".into(),
+            }]
+        );
+        let xlsx = build_external_link_xlsx(&[(10, 3)], "budget%202024.xlsx");
+        let found = references(&xlsx);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].kind.as_str(), found[0].target.as_str()), ("externalLinkPath", "budget%202024.xlsx"));
+        assert_eq!(found[0].rels_part, "xl/externalLinks/_rels/externalLink1.xml.rels");
+
+        // the hyperlink is external too, but it doesn't change what's printed
+        assert!(references(&build_linked_images_docx(&[])).is_empty());
+        for package in [build_docx(10, 1, Some(2)), build_xlsx(&[(5, 5)]), build_pptx(3, 0)] {
+            assert!(references(&package).is_empty());
+        }
+    }
+
+    #[test]
+    fn estimates_flag_external_references() {
+        let linked = estimate(&build_linked_images_docx(&["logo.png", "map.png"]), None, "{}").unwrap();
+        assert!(linked.has_external_references);
+        let note = "⚠ 2 external references (linked content may print differently): image logo.png, image map.png";
+        assert!(linked.notes.contains(&note.to_string()), "{:?}", linked.notes);
+
+        let workbook = estimate(&build_external_link_xlsx(&[(10, 3)], "budget.xlsx"), None, "{}").unwrap();
+        assert!(workbook.has_external_references);
+        for (package, name) in [(build_docx(10, 0, None), "a.docx"), (build_xlsx(&[(5, 5)]), "a.xlsx")] {
+            let result = estimate(&package, Some(name), "{}").unwrap();
+            assert!(!result.has_external_references, "{}", name);
+            assert!(!result.notes.iter().any(|note| note.contains("external references")), "{:?}", result.notes);
+        }
+        assert!(EstimateResult::merge(&[workbook, estimate(&build_pptx(2, 0), None, "{}").unwrap()])
+            .has_external_references);
+    }
+
+    #[test]
+    fn notes_list_the_first_targets() {
+        let reference = |target: &str| ExternalReference {
+            rels_part: "word/_rels/document.xml.rels".into(),
+            kind: "oleObject".into(),
+            target: target.into(),
+        };
+        assert_eq!(external_references_note(&[]), None);
+        let five: Vec<_> = ["a.xlsx", "b.xlsx", "c.xlsx", "d.xlsx", "e.xlsx"].map(reference).into();
+        assert_eq!(
+            external_references_note(&five).unwrap(),
+            "⚠ 5 external references (linked content may print differently): \
+             oleObject a.xlsx, oleObject b.xlsx, oleObject c.xlsx, and 2 more"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! # Fallback Estimator
+//!
+//! Lets JavaScript callers plug in an estimator for formats this crate doesn't
+//! support (e.g. an in-house document format), so that `estimate_document`
+//! stays the single entry point for every upload.
+//!
+//! The registered callback is only consulted when the built-in detection
+//! finds no estimator for the input (`UnsupportedFormat`). Its result is
+//! validated before it is passed on, and exceptions it throws are reported as
+//! `CustomEstimatorError` instead of escaping into the caller.
+
+use crate::cache::clear_estimate_cache;
+use crate::schema::{EstimateResult, EstimatorError};
+use js_sys::{Function, Uint8Array, JSON};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+
+thread_local! {
+    static FALLBACK_ESTIMATOR: RefCell<Option<Function>> = const { RefCell::new(None) };
+}
+
+/// Note added to every result produced by the fallback estimator.
+pub const FALLBACK_NOTE: &str = "Estimated by the registered fallback estimator";
+
+/// Registers a JavaScript function that estimates documents no built-in estimator supports.
+///
+/// # Parameters
+///
+/// * `callback` - Called as `callback(bytes, detected, filename)` with the document
+///                bytes as a `Uint8Array`, the detected format (`"unknown"` when
+///                nothing matched), and the filename or `undefined`. It returns
+///                `null`/`undefined` to decline, or an object (or JSON string)
+///                shaped like an estimate result, with at least `page_count`,
+///                `page_sizes`, and `notes`. Pass `null` to remove the callback.
+///
+/// Registering or removing a callback clears the `estimate_document_cached` cache,
+/// since cached results may have come from the previous callback.
+///
+/// # Example
+///
+/// ```javascript
+/// register_fallback_estimator((bytes, detected, filename) =>
+///     filename?.endsWith(".acme")
+///         ? { page_count: readAcmePages(bytes), page_sizes: [], notes: [] }
+///         : null);
+/// ```
+#[wasm_bindgen]
+pub fn register_fallback_estimator(callback: Option<Function>) {
+    FALLBACK_ESTIMATOR.with(|slot| *slot.borrow_mut() = callback);
+    clear_estimate_cache();
+}
+
+/// Runs the registered fallback estimator, if any.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw document bytes
+/// * `detected` - The format reported by `detect_type`
+/// * `filename` - Optional filename passed to the estimate
+///
+/// # Returns
+///
+/// `None` when no callback is registered or it declined the document;
+/// otherwise the validated result (with `FALLBACK_NOTE` appended), or a
+/// `CustomEstimatorError` if the callback threw or returned an invalid result.
+pub fn run_fallback_estimator(
+    bytes: &[u8],
+    detected: &str,
+    filename: Option<&str>,
+) -> Option<Result<EstimateResult, EstimatorError>> {
+    let callback = FALLBACK_ESTIMATOR.with(|slot| slot.borrow().clone())?;
+    let filename = filename.map_or(JsValue::UNDEFINED, JsValue::from_str);
+    let returned = match callback.call3(
+        &JsValue::NULL,
+        &Uint8Array::from(bytes).into(),
+        &JsValue::from_str(detected),
+        &filename,
+    ) {
+        Ok(value) => value,
+        Err(thrown) => {
+            return Some(Err(EstimatorError::CustomEstimatorError(format!(
+                "fallback estimator threw: {}",
+                describe_js_error(&thrown)
+            ))));
+        }
+    };
+    if returned.is_null() || returned.is_undefined() {
+        return None;
+    }
+    Some(parse_result(&returned).and_then(|mut result| {
+        validate_custom_result(&result)?;
+        result.notes.push(FALLBACK_NOTE.to_string());
+        Ok(result)
+    }))
+}
+
+/// Converts the callback's return value (an object or a JSON string) into a result.
+fn parse_result(value: &JsValue) -> Result<EstimateResult, EstimatorError> {
+    let json = match value.as_string() {
+        Some(s) => s,
+        None => JSON::stringify(value)
+            .ok()
+            .and_then(|s| s.as_string())
+            .ok_or_else(|| EstimatorError::CustomEstimatorError("fallback result is not serializable".into()))?,
+    };
+    serde_json::from_str(&json).map_err(|e| {
+        EstimatorError::CustomEstimatorError(format!("fallback result does not match EstimateResult: {}", e))
+    })
+}
+
+/// Checks that a result from the fallback estimator is internally consistent.
+///
+/// # Notes
+///
+/// - `page_sizes` must be empty or list one size per page
+/// - Page dimensions must be finite and positive
+/// - `confidence`, when present, must lie between 0 and 1
+pub fn validate_custom_result(result: &EstimateResult) -> Result<(), EstimatorError> {
+    if !result.page_sizes.is_empty() && result.page_sizes.len() != result.page_count {
+        return Err(EstimatorError::CustomEstimatorError(format!(
+            "fallback result lists {} page sizes for {} pages",
+            result.page_sizes.len(),
+            result.page_count
+        )));
+    }
+    if let Some(size) = result.page_sizes.iter().find(|s| {
+        !(s.width_mm.is_finite() && s.height_mm.is_finite() && s.width_mm > 0.0 && s.height_mm > 0.0)
+    }) {
+        return Err(EstimatorError::CustomEstimatorError(format!(
+            "fallback result has an invalid page size {} × {} mm",
+            size.width_mm, size.height_mm
+        )));
+    }
+    if let Some(confidence) = result.confidence
+        && !(0.0..=1.0).contains(&confidence)
+    {
+        return Err(EstimatorError::CustomEstimatorError(format!(
+            "fallback result confidence must be between 0 and 1, got {}",
+            confidence
+        )));
+    }
+    Ok(())
+}
+
+/// Describes a thrown JavaScript value (an `Error`'s message, a string, or its JSON form).
+pub fn describe_js_error(thrown: &JsValue) -> String {
+    if let Some(error) = thrown.dyn_ref::<js_sys::Error>() {
+        return String::from(error.message());
+    }
+    thrown
+        .as_string()
+        .or_else(|| JSON::stringify(thrown).ok().and_then(|s| s.as_string()))
+        .unwrap_or_else(|| format!("{:?}", thrown))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_utils::a4_mm;
+    use crate::schema::PageSizeMm;
+
+    fn rejection(result: &EstimateResult) -> String {
+        validate_custom_result(result).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn consistent_results_pass() {
+        assert!(validate_custom_result(&EstimateResult::uniform(2, Some(a4_mm()), Vec::new())).is_ok());
+        let mut counted = EstimateResult::uniform(2, None, Vec::new());
+        counted.confidence = Some(1.0);
+        assert!(validate_custom_result(&counted).is_ok());
+    }
+
+    #[test]
+    fn inconsistent_results_are_rejected() {
+        let mut result = EstimateResult::uniform(2, Some(a4_mm()), Vec::new());
+        result.page_count = 3;
+        assert!(rejection(&result).contains("lists 2 page sizes for 3 pages"), "{}", rejection(&result));
+
+        for (width_mm, height_mm) in [(f64::NAN, 297.0), (210.0, f64::INFINITY), (0.0, 297.0), (210.0, -1.0)] {
+            let mut result = EstimateResult::uniform(1, Some(a4_mm()), Vec::new());
+            result.page_sizes[0] = PageSizeMm { width_mm, height_mm };
+            assert!(rejection(&result).contains("invalid page size"), "{}", rejection(&result));
+        }
+
+        let mut result = EstimateResult::uniform(1, None, Vec::new());
+        result.confidence = Some(1.5);
+        assert!(rejection(&result).contains("between 0 and 1"), "{}", rejection(&result));
+    }
+}
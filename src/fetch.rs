@@ -0,0 +1,230 @@
+//! # Estimating Documents by URL
+//!
+//! `estimate_url` downloads a document with the `fetch` API of the browser (or
+//! web worker) and runs the normal estimator on it, so files that already live
+//! on a server don't have to be passed through JavaScript first. Only built
+//! with the `fetch` feature.
+//!
+//! ## PDF fast path
+//!
+//! When the URL looks like a PDF (or its type can't be told from the name),
+//! only the first `LINEARIZATION_HEADER_BYTES` are requested with a `Range`
+//! header. Linearized PDFs state their page count there, so the download stops
+//! at 2 KB. Otherwise (or when the server ignores ranges) the whole file is
+//! downloaded.
+//!
+//! ## Errors
+//!
+//! - Network failures, including requests blocked by CORS (browsers don't
+//!   distinguish the two), map to `network_error`
+//! - Unsuccessful HTTP statuses map to `http_error`
+//! - A `Content-Length` (or range total) above `max_input_bytes` maps to
+//!   `too_large` before the body is downloaded
+
+use crate::assembly::{error_code, estimate_bytes, parse_options, to_versioned_json, with_schema_version};
+use crate::fallback::describe_js_error;
+use crate::file_utils::a4_mm;
+use crate::formats::format_for_filename;
+use crate::notes::apply_verbosity;
+use crate::pdf::{linearized_page_count, LINEARIZATION_HEADER_BYTES};
+use crate::printing::apply_print_summary;
+use crate::schema::{EstimateOptions, EstimateResult, EstimatorError};
+use js_sys::{Promise, Uint8Array};
+use serde_json::json;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, RequestMode, Response, Window, WorkerGlobalScope};
+
+/// Downloads a document and estimates its page count.
+///
+/// # Parameters
+///
+/// * `url` - URL of the document; cross-origin servers must allow it with CORS
+///
+/// * `filename_hint` - Optional filename used as a type hint. Defaults to the
+///                     last segment of the URL path.
+///
+/// * `options_json` - Optional JSON string containing estimation options.
+///                    `max_input_bytes` limits the size of the download.
+///
+/// # Returns
+///
+/// A `Promise` resolving to the same JSON string as `estimate_document`. Errors
+/// carry an `error_code`; besides the estimator codes these are `network_error`,
+/// `http_error` (with the `status`), and `too_large`.
+#[wasm_bindgen]
+pub async fn estimate_url(url: String, filename_hint: Option<String>, options_json: Option<String>) -> JsValue {
+    let options = parse_options(options_json);
+    let filename = filename_hint.or_else(|| filename_from_url(&url));
+    let (detected, result) = match fetch_and_estimate(&url, filename.as_deref(), &options).await {
+        Ok(outcome) => outcome,
+        Err(err) => (hinted_type(filename.as_deref()).to_string(), Err(err)),
+    };
+
+    match result {
+        Ok(est) => match to_versioned_json(&est) {
+            Ok(s) | Err(s) => JsValue::from_str(&s),
+        },
+        Err(err) => {
+            let mut body = json!({"error": err.to_string(), "error_code": error_code(&err), "detected": detected});
+            if let EstimatorError::HttpError { status } = err {
+                body["status"] = status.into();
+            }
+            JsValue::from_str(&with_schema_version(&body.to_string()))
+        }
+    }
+}
+
+/// Downloads the document (trying the PDF fast path first) and estimates it.
+async fn fetch_and_estimate(
+    url: &str,
+    filename: Option<&str>,
+    options: &EstimateOptions,
+) -> Result<(String, Result<EstimateResult, EstimatorError>), EstimatorError> {
+    let mut response = None;
+    // options that read more than the page count need the whole file
+    let full_pdf = options.analyze_color.unwrap_or(false)
+        || options.exclude_blank_pages.unwrap_or(false)
+        || options.estimate_attachments.unwrap_or(false)
+        || options.extended_pdf_info.unwrap_or(false);
+    if matches!(hinted_type(filename), "pdf" | "unknown") && !full_pdf {
+        let range = format!("bytes=0-{}", LINEARIZATION_HEADER_BYTES - 1);
+        let partial = fetch(url, Some(&range)).await?;
+        if partial.status() == 206 {
+            if let Some(total) = content_range_total(&partial) {
+                check_limit(total, options)?;
+                let head = read_body(&partial).await?;
+                if let Some(page_count) = linearized_page_count(&head, total) {
+                    return Ok(("pdf".into(), Ok(linearized_result(page_count, options))));
+                }
+            }
+        } else {
+            // the server ignored the range and sent the whole file (or an error)
+            response = Some(partial);
+        }
+    }
+
+    let response = match response {
+        Some(response) => response,
+        None => fetch(url, None).await?,
+    };
+    if !response.ok() {
+        return Err(EstimatorError::HttpError { status: response.status() });
+    }
+    if let Some(length) = header_number(&response, "Content-Length") {
+        check_limit(length, options)?;
+    }
+    let bytes = read_body(&response).await?;
+    Ok(estimate_bytes(&bytes, filename, options))
+}
+
+/// Builds the result for a linearized PDF counted from its first bytes.
+fn linearized_result(page_count: usize, options: &EstimateOptions) -> EstimateResult {
+    let notes = vec![format!(
+        "PDF has {} pages (from the linearization dictionary; only the first {} bytes were downloaded)",
+        page_count, LINEARIZATION_HEADER_BYTES
+    )];
+    let mut result = EstimateResult::uniform(page_count, options.wants_page_sizes().then(a4_mm), notes);
+    // the A4 sizes are placeholders; the real ones aren't downloaded
+    result.set_orientation_counts(&[]);
+    apply_print_summary(&mut result, options);
+    apply_verbosity(&mut result, options);
+    result
+}
+
+/// Starts a GET request with the global `fetch` of a window or worker.
+async fn fetch(url: &str, range: Option<&str>) -> Result<Response, EstimatorError> {
+    let init = RequestInit::new();
+    init.set_method("GET");
+    init.set_mode(RequestMode::Cors);
+    let request = Request::new_with_str_and_init(url, &init)
+        .map_err(|e| EstimatorError::NetworkError(describe_js_error(&e)))?;
+    if let Some(range) = range {
+        request
+            .headers()
+            .set("Range", range)
+            .map_err(|e| EstimatorError::NetworkError(describe_js_error(&e)))?;
+    }
+
+    let global = js_sys::global();
+    let promise: Promise = if let Some(window) = global.dyn_ref::<Window>() {
+        window.fetch_with_request(&request)
+    } else if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+        worker.fetch_with_request(&request)
+    } else {
+        return Err(EstimatorError::NetworkError("fetch is not available in this environment".into()));
+    };
+    let response = JsFuture::from(promise)
+        .await
+        .map_err(|e| EstimatorError::NetworkError(describe_js_error(&e)))?;
+    response
+        .dyn_into::<Response>()
+        .map_err(|_| EstimatorError::NetworkError("fetch did not return a Response".into()))
+}
+
+/// Reads a response body into memory.
+async fn read_body(response: &Response) -> Result<Vec<u8>, EstimatorError> {
+    let buffer = response
+        .array_buffer()
+        .map_err(|e| EstimatorError::NetworkError(describe_js_error(&e)))?;
+    let buffer = JsFuture::from(buffer)
+        .await
+        .map_err(|e| EstimatorError::NetworkError(describe_js_error(&e)))?;
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+/// Fails with `TooLarge` when `length` exceeds `max_input_bytes`.
+fn check_limit(length: usize, options: &EstimateOptions) -> Result<(), EstimatorError> {
+    match options.max_input_bytes {
+        Some(limit) if length > limit => Err(EstimatorError::TooLarge { limit, actual: length }),
+        _ => Ok(()),
+    }
+}
+
+fn header_number(response: &Response, name: &str) -> Option<usize> {
+    response.headers().get(name).ok()??.trim().parse().ok()
+}
+
+/// Reads the total length from a `Content-Range: bytes 0-2047/12345` header.
+fn content_range_total(response: &Response) -> Option<usize> {
+    let value = response.headers().get("Content-Range").ok()??;
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// The format implied by the filename's extension, or `"unknown"`.
+fn hinted_type(filename: Option<&str>) -> &'static str {
+    filename.and_then(format_for_filename).map_or("unknown", |format| format.id)
+}
+
+/// Takes the last path segment of a URL (without query or fragment) as its filename.
+fn filename_from_url(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest.split_once('/').map_or("", |(_, p)| p));
+    let name = path.rsplit('/').next()?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames_come_from_the_last_path_segment() {
+        let name = |url: &str| filename_from_url(url);
+        assert_eq!(name("https://cdn.example.com/docs/report.pdf?sig=abc#page=2").as_deref(), Some("report.pdf"));
+        assert_eq!(name("/files/plan.docx").as_deref(), Some("plan.docx"));
+        assert_eq!(name("https://cdn.example.com/docs/"), None);
+        assert_eq!(name("https://cdn.example.com"), None);
+        assert_eq!(hinted_type(Some("plan.docx")), "docx");
+        assert_eq!(hinted_type(None), "unknown");
+    }
+
+    #[test]
+    fn downloads_over_the_limit_are_too_large() {
+        let options = EstimateOptions { max_input_bytes: Some(100), ..Default::default() };
+        assert!(check_limit(100, &options).is_ok());
+        assert!(matches!(check_limit(101, &options), Err(EstimatorError::TooLarge { limit: 100, actual: 101 })));
+        assert!(check_limit(usize::MAX, &EstimateOptions::default()).is_ok());
+    }
+}
@@ -1,3 +1,26 @@
+use crate::comic::looks_like_comic;
+use crate::data_files::sniff_data;
+use crate::docbook::looks_like_docbook;
+use crate::notebook::looks_like_notebook;
+use crate::formats::{format_for_filename, format_for_mime_type, is_generic_mime_type};
+use crate::iwork::is_iwork_package;
+use crate::latex::looks_like_latex;
+use crate::mime::{looks_like_email, looks_like_mht};
+use crate::postscript::DOS_EPS_MAGIC;
+use crate::records::looks_like_records;
+use crate::schema::EstimatorError;
+use crate::stats::{note_buffer, note_bytes_examined};
+use crate::xps::is_xps_package;
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader as XmlReader;
+use std::collections::BTreeMap;
+use std::io::{BufReader, Cursor, Read, Write};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::read::ZipFile;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
 /// Converts points to millimeters.
 ///
 /// # Arguments
@@ -20,6 +43,123 @@ pub fn mm_from_pt(pt: f64) -> f64 {
     pt / 72.0 * 25.4
 }
 
+/// The UTF-8 byte order mark, which some editors write at the start of text files.
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Returns `bytes` without a leading UTF-8 byte order mark.
+pub fn strip_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)
+}
+
+/// Inputs of this many bytes or more are never taken for a URL or a path.
+pub const LOCATOR_MAX_BYTES: usize = 2048;
+
+/// Recognizes an input that is a URL or a file path rather than a document,
+/// as when a caller passes the encoded string instead of the file's bytes.
+///
+/// # Returns
+///
+/// `"URL"` or `"file path"` when the input is shorter than `LOCATOR_MAX_BYTES`,
+/// a single line of printable UTF-8, and either a URL (`scheme://…`, without
+/// spaces) or an absolute or relative path (`/`, `~/`, `./`, `../`, `C:\`,
+/// `C:/`, or `\\server`) whose file name has an extension; `None` otherwise.
+pub fn looks_like_locator(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.len() >= LOCATOR_MAX_BYTES {
+        return None;
+    }
+    let text = std::str::from_utf8(strip_bom(bytes)).ok()?.trim();
+    if text.is_empty() || text.chars().any(char::is_control) {
+        return None;
+    }
+    if let Some((scheme, rest)) = text.split_once("://")
+        && scheme.len() >= 2
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !rest.is_empty()
+        && !text.contains(char::is_whitespace)
+    {
+        return Some("URL");
+    }
+    let drive = text.as_bytes();
+    let rooted = ["/", "~/", "./", "../", "\\\\"].iter().any(|root| text.starts_with(root))
+        || (drive.len() > 3 && drive[0].is_ascii_alphabetic() && drive[1] == b':' && matches!(drive[2], b'\\' | b'/'));
+    let file = text.rsplit(['/', '\\']).next().unwrap_or(text);
+    let has_extension = file.rsplit_once('.').is_some_and(|(stem, extension)| {
+        !stem.is_empty() && (1..=5).contains(&extension.len()) && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+    (rooted && has_extension).then_some("file path")
+}
+
+/// Inputs of this many bytes or more are never taken for a Google Drive stub.
+pub const GOOGLE_STUB_MAX_BYTES: usize = 64 * 1024;
+
+/// Google Workspace file types: `(mimeType suffix, application, formats to export as)`.
+///
+/// The suffix follows `application/vnd.google-apps.` in a `mimeType`, and
+/// `docs.google.com/` in a URL (where spreadsheets are plural).
+pub const GOOGLE_APPS: [(&str, &str, &str); 3] = [
+    ("document", "Google Docs", ".docx or .pdf"),
+    ("spreadsheet", "Google Sheets", ".xlsx or .pdf"),
+    ("presentation", "Google Slides", ".pptx or .pdf"),
+];
+
+/// A placeholder for a Google Workspace file, uploaded instead of an export of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GoogleStub {
+    /// What the input is: `"Google Drive file stub"` or `"Google sign-in page"`.
+    pub kind: &'static str,
+    /// The application the file belongs to (`"Google Docs"`, ...), or
+    /// `"Google Drive"` when the stub doesn't say.
+    pub application: &'static str,
+    /// Formats to export the file as, for the error message.
+    pub formats: &'static str,
+}
+
+/// Recognizes what Google Drive hands out for a Google Docs, Sheets, or Slides
+/// file that hasn't been exported: the file's JSON metadata, a `.gdoc`-style
+/// shortcut, or the HTML page redirecting to the Google sign-in.
+///
+/// # Returns
+///
+/// The stub when the input is shorter than `GOOGLE_STUB_MAX_BYTES` and either
+/// JSON with `"kind": "drive#file"` (or a `doc_id` and a `google.com` URL), or
+/// HTML redirecting to a sign-in page on `accounts.google.com`; `None` otherwise.
+pub fn looks_like_google_stub(bytes: &[u8]) -> Option<GoogleStub> {
+    if bytes.len() >= GOOGLE_STUB_MAX_BYTES {
+        return None;
+    }
+    let text = String::from_utf8_lossy(strip_bom(bytes)).to_lowercase();
+    let text = text.trim_start();
+    let kind = if text.starts_with('{') {
+        let drive_file = text.contains("\"kind\"") && text.contains("\"drive#file\"");
+        let shortcut = text.contains("\"doc_id\"") && text.contains("google.com/");
+        (drive_file || shortcut).then_some("Google Drive file stub")?
+    } else {
+        let html = text.starts_with("<!doctype html") || text.contains("<html");
+        let sign_in = ["/servicelogin", "/v3/signin", "/signin"]
+            .iter()
+            .any(|path| text.contains(&format!("accounts.google.com{}", path)));
+        (html && sign_in).then_some("Google sign-in page")?
+    };
+    let (application, formats) = GOOGLE_APPS
+        .iter()
+        .find(|(suffix, _, _)| {
+            // sign-in pages carry the document URL percent-encoded in their `continue` parameter
+            [format!("google-apps.{}", suffix), format!("docs.google.com/{}", suffix)]
+                .iter()
+                .any(|marker| text.contains(marker.as_str()) || text.contains(&marker.replace('/', "%2f")))
+        })
+        .map_or(("Google Drive", ".docx, .xlsx, .pptx, or .pdf"), |(_, application, formats)| {
+            (*application, *formats)
+        });
+    Some(GoogleStub { kind, application, formats })
+}
+
+/// Converts millimeters to points, the inverse of `mm_from_pt`.
+pub fn pt_from_mm(mm: f64) -> f64 {
+    mm / 25.4 * 72.0
+}
+
 /// Returns the standard A4 paper dimensions in millimeters.
 ///
 /// # Returns
@@ -42,10 +182,121 @@ pub fn letter_mm() -> (f64, f64) {
     (215.9, 279.4)
 }
 
-/// Detects the file type from filename extension or magic bytes.
+/// Paper sizes known by name to `parse_paper`, as portrait `(width, height)` in millimeters.
+pub const PAPER_PRESETS: [(&str, (f64, f64)); 9] = [
+    ("A3", (297.0, 420.0)),
+    ("A4", (210.0, 297.0)),
+    ("A5", (148.0, 210.0)),
+    ("B4", (250.0, 353.0)),
+    ("B5", (176.0, 250.0)),
+    ("Letter", (215.9, 279.4)),
+    ("Legal", (215.9, 355.6)),
+    ("Tabloid", (279.4, 431.8)),
+    ("Executive", (184.15, 266.7)),
+];
+
+/// Parses a paper size given by name or by its dimensions.
+///
+/// # Arguments
 ///
-/// This function attempts to identify the file type by first checking the filename
-/// extension (if provided), and then falling back to examining the file's magic bytes.
+/// * `spec` - A name of `PAPER_PRESETS` (case-insensitive), or a width and
+///   height in millimeters separated by `x` (`"297x420"`, `"215.9 x 279.4"`),
+///   optionally followed by `portrait` or `landscape` (`"A3 landscape"`)
+///
+/// # Returns
+///
+/// `(width, height)` in millimeters, or `None` when `spec` names no preset and
+/// isn't a pair of positive numbers. With `landscape` the longer side is the
+/// width; with `portrait` the height.
+pub fn parse_paper(spec: &str) -> Option<(f64, f64)> {
+    let spec = spec.trim();
+    let (size, orientation) = match spec.rsplit_once(char::is_whitespace) {
+        Some((size, word)) if word.eq_ignore_ascii_case("landscape") || word.eq_ignore_ascii_case("portrait") => {
+            (size.trim_end(), Some(word.eq_ignore_ascii_case("landscape")))
+        }
+        _ => (spec, None),
+    };
+    let (width, height) = match PAPER_PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(size)) {
+        Some(&(_, preset)) => preset,
+        None => {
+            let (width, height) = size.split_once(['x', 'X', '×'])?;
+            let (width, height): (f64, f64) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+            if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+                return None;
+            }
+            (width, height)
+        }
+    };
+    let (short, long) = (width.min(height), width.max(height));
+    Some(match orientation {
+        Some(true) => (long, short),
+        Some(false) => (short, long),
+        None => (width, height),
+    })
+}
+
+/// Regions whose default paper size is Letter; every other region uses A4.
+const LETTER_REGIONS: &[&str] = &["US", "CA", "MX", "PH"];
+
+/// A paper size chosen from a locale, with the reason for the choice.
+pub struct LocalePaper {
+    /// Paper name (`"Letter"` or `"A4"`).
+    pub name: &'static str,
+    /// Paper dimensions `(width, height)` in millimeters.
+    pub size_mm: (f64, f64),
+    /// Human-readable explanation of how the paper was chosen.
+    pub reason: String,
+}
+
+/// Chooses the default paper size for a BCP-47 locale tag.
+///
+/// # Arguments
+///
+/// * `locale` - A locale tag such as `"en-US"`, `"de-DE"`, `"zh-Hant-TW"` or `"fr"`.
+///   Underscores are accepted as separators (`"en_US"`).
+///
+/// # Returns
+///
+/// Letter for the Letter-default regions (US, CA, MX, PH) and A4 otherwise.
+///
+/// # Fallback Rules
+///
+/// - Script subtags (four letters, e.g. `Hant`) are skipped when looking for the region
+/// - Language-only tags (e.g. `"en"`) have no region and fall back to A4
+/// - Unknown or malformed regions fall back to A4
+pub fn paper_for_locale(locale: &str) -> LocalePaper {
+    let region = locale
+        .split(['-', '_'])
+        .skip(1)
+        .find(|subtag| {
+            (subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+                || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|r| r.to_ascii_uppercase());
+
+    match region {
+        Some(r) if LETTER_REGIONS.contains(&r.as_str()) => LocalePaper {
+            name: "Letter",
+            size_mm: letter_mm(),
+            reason: format!("locale '{}' region {} uses Letter", locale, r),
+        },
+        Some(r) => LocalePaper {
+            name: "A4",
+            size_mm: a4_mm(),
+            reason: format!("locale '{}' region {} uses A4", locale, r),
+        },
+        None => LocalePaper {
+            name: "A4",
+            size_mm: a4_mm(),
+            reason: format!("locale '{}' has no region; defaulting to A4", locale),
+        },
+    }
+}
+
+/// Detects the file type from its content and filename extension.
+///
+/// The same as `detect_format` without a MIME hint; see there for the order
+/// in which the content and the filename are consulted.
 ///
 /// # Arguments
 ///
@@ -59,79 +310,907 @@ pub fn letter_mm() -> (f64, f64) {
 /// - `"xlsx"` - Excel spreadsheets (detected by .xlsx/.xlsm extension)
 /// - `"docx"` - Word documents (detected by .docx extension)
 /// - `"pptx"` - PowerPoint presentations (detected by .pptx extension)
+/// - `"postscript"` - PostScript/EPS files (detected by .ps/.eps extension, `%!PS` header, or DOS EPS magic bytes)
+/// - `"xps"` - XPS/OpenXPS documents (detected by .xps/.oxps extension or a FixedDocumentSequence in a ZIP)
 /// - `"markdown"` - Markdown files (detected by .md/.markdown extension)
+/// - `"latex"` - LaTeX sources (detected by .tex extension or `\documentclass`/`\begin{document}`)
+/// - `"rst"` - reStructuredText files (detected by .rst extension)
+/// - `"asciidoc"` - AsciiDoc files (detected by .adoc/.asciidoc extension)
+/// - `"docbook"` - DocBook XML (detected by .dbk/.docbook extension, or XML with a DocBook root element
+///   and namespace or DTD)
 /// - `"txt"` - Plain text files (detected by .txt extension or printable ASCII content)
+/// - `"cbz"` - Comic book archives (detected by .cbz extension or a ZIP made up of page images)
+/// - `"rar"` - RAR archives, including .cbr comics (detected by the `Rar!` signature); not supported
+/// - `"zip"` - ZIP archives that are not Office documents (detected by .zip extension or PK signature)
+/// - `"gzip"` - gzip-compressed data (detected by .gz/.tgz extension or 1F 8B magic bytes)
+/// - `"tar"` - TAR archives (detected by .tar extension or the "ustar" marker at offset 257)
+/// - `"mht"` - MHT web archives (detected by .mht/.mhtml extension or `MIME-Version` with a
+///   `multipart/related` content type)
+/// - `"eml"` - RFC 822 email messages (detected by .eml extension or mail headers like "From:"/"Received:")
+/// - `"code"` - Source code files (detected by extension, e.g. .rs/.py/.js/.java)
+/// - `"notebook"` - Jupyter notebooks (detected by .ipynb extension or JSON with `nbformat` and `cells` keys)
+/// - `"records"` - vCard and iCalendar files (detected by .vcf/.ics extension or a first
+///   `BEGIN:VCARD`/`BEGIN:VCALENDAR` line)
+/// - `"data"` - JSON, YAML, and XML data files (detected by .json/.yaml/.yml/.xml extension or content)
 /// - `"unknown"` - Unable to determine file type
+pub fn detect_type(filename: Option<&str>, bytes: &[u8]) -> String {
+    detect_format(filename, None, bytes).format
+}
+
+/// The format `detect_format` settled on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detection {
+    /// The format, as listed for `detect_type`.
+    pub format: String,
+    /// A warning when the content contradicts the MIME hint or the filename.
+    pub warning: Option<String>,
+}
+
+/// Detects the file type from its content, a MIME type hint, and its filename extension.
+///
+/// # Arguments
+///
+/// * `filename` - Optional filename to check for extension-based detection
+/// * `mime_hint` - Optional MIME type, such as a browser's `File.type`
+/// * `bytes` - The file contents as a byte slice for magic byte detection
 ///
 /// # Detection Strategy
 ///
-/// 1. Filename extension check (case-insensitive)
-/// 2. Magic bytes check for PDF (%PDF header)
-/// 3. Magic bytes check for ZIP-based formats (PK signature) with content verification
-/// 4. Text detection based on printable ASCII characters (tabs, newlines, spaces, and chars 32-127)
-pub fn detect_type(filename: Option<&str>, bytes: &[u8]) -> String {
-    if let Some(name) = filename {
-        let lower = name.to_lowercase();
-        if lower.ends_with(".pdf") {
-            return "pdf".into();
-        }
-        if lower.ends_with(".xlsx") || lower.ends_with(".xlsm") {
-            return "xlsx".into();
-        }
-        if lower.ends_with(".docx") {
-            return "docx".into();
-        }
-        if lower.ends_with(".pptx") {
-            return "pptx".into();
-        }
-        if lower.ends_with(".md") || lower.ends_with(".markdown") {
-            return "markdown".into();
-        }
-        if lower.ends_with(".txt") {
-            return "txt".into();
-        }
+/// 1. Conclusive magic bytes: a `%PDF` or PostScript header, or a readable ZIP
+///    holding the main part of an Office, XPS, or iWork package. A MIME hint or
+///    extension naming another format is overruled with a warning
+/// 2. The MIME hint, looked up in the `formats` registry; unknown and generic
+///    types (see `GENERIC_MIME_TYPES`) are skipped here
+/// 3. Filename extension check (case-insensitive, via the `formats` registry)
+/// 4. Other magic bytes: any other ZIP (checked for comic pages), RAR, gzip, and TAR
+/// 5. MHT and email headers, then LaTeX commands
+/// 6. Jupyter notebooks: a JSON object with `nbformat` and `cells` keys anywhere in the input
+/// 7. vCard and iCalendar files: a first line of `BEGIN:VCARD` or `BEGIN:VCALENDAR`
+/// 8. DocBook: a DocBook root element declaring the DocBook namespace or DTD, also for
+///    `.xml` files and XML MIME types
+/// 9. Data file sniffing (JSON tokens, `<?xml`, or mostly YAML `key: value` lines) in the first KB
+/// 10. Text detection based on printable ASCII characters (tabs, newlines, spaces, and chars 32-127),
+///     also accepting ANSI CSI/OSC escape sequences as found in terminal logs
+/// 11. A generic MIME hint (`text/plain`, `application/zip`, ...), when nothing above applied
+///
+/// A leading UTF-8 byte order mark is skipped before the content checks, and
+/// data sniffing tolerates whitespace before `<?xml`, `{`, or `[`. The `%PDF`
+/// and other magic bytes must still start the content.
+pub fn detect_format(filename: Option<&str>, mime_hint: Option<&str>, bytes: &[u8]) -> Detection {
+    let content = strip_bom(bytes);
+    let hinted = mime_hint.and_then(format_for_mime_type);
+    let specific_hint = hinted.filter(|_| !mime_hint.is_some_and(is_generic_mime_type));
+    let named = filename.and_then(format_for_filename);
+    let magic = magic_format(content);
+    if let Some((format, true)) = magic {
+        let contradicted = [specific_hint.map(|f| ("MIME type", f)), named.map(|f| ("filename", f))];
+        let warning = contradicted.into_iter().flatten().find(|(_, f)| f.id != format).map(|(source, f)| {
+            format!("⚠ The {} says {}, but the content is {}; estimated as {}", source, f.id, format, format)
+        });
+        return Detection { format: format.into(), warning };
+    }
+    if let Some(format) = specific_hint.or(named) {
+        // DocBook is usually saved as .xml
+        let format = if format.id == "data" && looks_like_docbook(content) { "docbook" } else { format.id };
+        return Detection { format: format.into(), warning: None };
     }
-    // fallback: magic
+    let sniffed = match magic {
+        Some((format, _)) => format,
+        None => sniff_content(content),
+    };
+    let format = match (sniffed, hinted) {
+        ("unknown", Some(format)) => format.id,
+        _ => sniffed,
+    };
+    Detection { format: format.into(), warning: None }
+}
+
+/// Identifies a format by the signature its content starts with.
+///
+/// # Returns
+///
+/// The format and whether the signature is conclusive (see `detect_format`),
+/// or `None` when the content starts with no known signature.
+fn magic_format(bytes: &[u8]) -> Option<(&'static str, bool)> {
     if bytes.len() >= 4 && &bytes[0..4] == b"%PDF" {
-        return "pdf".into();
+        return Some(("pdf", true));
+    }
+    if bytes.starts_with(b"%!PS") || bytes.starts_with(&DOS_EPS_MAGIC) {
+        return Some(("postscript", true));
     }
     // Office files (docx, pptx, xlsx) are all ZIP archives with PK signature
     // Try to differentiate them by checking internal structure
     if bytes.len() >= 4 && &bytes[0..2] == b"PK" {
         // Try to detect Office document type by checking for specific files
-        return detect_office_type(bytes);
+        return Some(detect_office_type(bytes));
     }
-    // crude text detection: printable
-    if bytes
-        .iter()
-        .all(|b| *b == 9 || *b == 10 || *b == 13 || (32..=127).contains(b))
-    {
-        return "txt".into();
+    if bytes.starts_with(b"Rar!\x1A\x07") {
+        return Some(("rar", false));
+    }
+    if bytes.len() >= 2 && bytes[0..2] == [0x1F, 0x8B] {
+        return Some(("gzip", false));
+    }
+    if bytes.len() >= 262 && &bytes[257..262] == b"ustar" {
+        return Some(("tar", false));
+    }
+    None
+}
+
+/// Identifies a text format by its content, or `"unknown"`.
+fn sniff_content(bytes: &[u8]) -> &'static str {
+    // browser web archives carry mail headers too, so they are checked first
+    if looks_like_mht(bytes) {
+        return "mht";
+    }
+    if looks_like_email(bytes) {
+        return "eml";
     }
-    "unknown".into()
+    if looks_like_latex(bytes) {
+        return "latex";
+    }
+    if looks_like_notebook(bytes) {
+        return "notebook";
+    }
+    if looks_like_records(bytes) {
+        return "records";
+    }
+    if looks_like_docbook(bytes) {
+        return "docbook";
+    }
+    if sniff_data(bytes).is_some() {
+        return "data";
+    }
+    // crude text detection: printable, allowing ANSI escape sequences from terminal logs
+    if bytes.iter().enumerate().all(|(i, b)| {
+        *b == 9
+            || *b == 10
+            || *b == 13
+            || (32..=127).contains(b)
+            || (*b == 0x1B && matches!(bytes.get(i + 1), Some(b'[' | b']' | b'\\')))
+            || *b == 0x07
+    }) {
+        return "txt";
+    }
+    "unknown"
 }
 
 /// Helper function to detect specific Office document type from ZIP content
-fn detect_office_type(bytes: &[u8]) -> String {
-    use std::io::Cursor;
-    use zip::ZipArchive;
-    
-    let cursor = Cursor::new(bytes);
-    if let Ok(mut archive) = ZipArchive::new(cursor) {
+///
+/// The type is conclusive when the archive could be read and holds the main
+/// part of an Office, XPS, or iWork package.
+fn detect_office_type(bytes: &[u8]) -> (&'static str, bool) {
+    if let Ok(mut archive) = open_zip_archive(bytes, "Office document") {
         // Check for Word document markers
         if archive.by_name("word/document.xml").is_ok() {
-            return "docx".into();
+            return ("docx", true);
         }
         // Check for PowerPoint presentation markers
         if archive.by_name("ppt/presentation.xml").is_ok() {
-            return "pptx".into();
+            return ("pptx", true);
         }
         // Check for Excel workbook markers
         if archive.by_name("xl/workbook.xml").is_ok() {
-            return "xlsx".into();
+            return ("xlsx", true);
+        }
+        // XPS packages point at a FixedDocumentSequence instead of an Office main part
+        if is_xps_package(&mut archive) {
+            return ("xps", true);
+        }
+        if is_iwork_package(&archive) {
+            return ("iwork", true);
+        }
+        if looks_like_comic(&archive) {
+            return ("cbz", false);
+        }
+    } else {
+        // with a damaged central directory, the local headers still name the main part
+        let main_parts = [("word/document.xml", "docx"), ("ppt/presentation.xml", "pptx"), ("xl/workbook.xml", "xlsx")];
+        if let Some((_, format)) = main_parts.iter().find(|(part, _)| find_bytes(bytes, part.as_bytes()).is_some()) {
+            return (format, false);
+        }
+    }
+    // A ZIP without Office markers is a plain archive, not a document
+    ("zip", false)
+}
+
+
+/// Opens an in-memory OOXML package (DOCX, PPTX, XLSX) as a ZIP archive.
+///
+/// Every ZIP-based reader in the crate goes through this function so that
+/// archive handling is consistent. ZIP64 archives (packages over 4 GB or with
+/// more than 65,535 entries) are read natively by the `zip` crate; this
+/// function only makes sure that a failure to open one is reported as such
+/// rather than as a generic corrupt-archive error.
+///
+/// # Arguments
+///
+/// * `bytes` - The complete archive contents
+/// * `kind` - Human-readable format name used in error messages (e.g. `"DOCX"`)
+///
+/// # Returns
+///
+/// The opened archive, or `EstimatorError::ZipError` with a message that
+/// distinguishes unsupported ZIP64/ZIP features from a corrupt archive.
+pub fn open_zip_archive<'a>(
+    bytes: &'a [u8],
+    kind: &str,
+) -> Result<ZipArchive<Cursor<&'a [u8]>>, EstimatorError> {
+    let archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| EstimatorError::ZipError(describe_zip_error(bytes, kind, &e)))?;
+    // the central directory and the end records after it
+    note_bytes_examined(bytes.len().saturating_sub(archive.central_directory_start() as usize));
+    Ok(archive)
+}
+
+/// Records the bytes of an entry about to be read from the input, its local
+/// header and compressed data, in `bytes_examined` (see the `stats` module).
+pub fn note_zip_entry(file: &ZipFile) {
+    let header = file.data_start().saturating_sub(file.header_start());
+    note_bytes_examined(header.saturating_add(file.compressed_size()) as usize);
+}
+
+/// Builds a descriptive error message for a ZIP archive that failed to open.
+fn describe_zip_error(bytes: &[u8], kind: &str, err: &ZipError) -> String {
+    let zip64 = has_zip64_records(bytes);
+    match err {
+        ZipError::UnsupportedArchive(detail) if zip64 => format!(
+            "Failed to open {} as ZIP: ZIP64 structures not supported ({})",
+            kind, detail
+        ),
+        ZipError::UnsupportedArchive(detail) => format!(
+            "Failed to open {} as ZIP: unsupported archive feature ({})",
+            kind, detail
+        ),
+        _ if zip64 => format!(
+            "Failed to open {} as ZIP: corrupt ZIP64 archive ({})",
+            kind, err
+        ),
+        _ => format!("Failed to open {} as ZIP: corrupt archive ({})", kind, err),
+    }
+}
+
+/// Checks the archive tail for ZIP64 end-of-central-directory records.
+///
+/// The ZIP64 locator (`PK\x06\x07`) sits immediately before the classic
+/// end-of-central-directory record, which itself may be followed by a
+/// comment of up to 65,535 bytes, so only the tail of the file is searched.
+fn has_zip64_records(bytes: &[u8]) -> bool {
+    const TAIL_WINDOW: usize = 65_535 + 22 + 20;
+    let start = bytes.len().saturating_sub(TAIL_WINDOW);
+    bytes[start..]
+        .windows(4)
+        .any(|w| w == b"PK\x06\x07" || w == b"PK\x06\x06")
+}
+
+/// Resolves a package part reference relative to the part that contains it.
+///
+/// Absolute references (`/Documents/1/FixedDoc.fdoc`) are taken from the
+/// package root; relative ones are joined to the referencing part's folder,
+/// with `.` and `..` segments applied. The result has no leading `/`, as in
+/// ZIP entry names.
+pub fn resolve_part(base: &str, target: &str) -> String {
+    let mut segments: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        base.rsplit_once('/').map_or_else(Vec::new, |(dir, _)| dir.split('/').collect())
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Reads a relationships part into a map from relationship id to target.
+pub fn read_relationship_targets(archive: &mut ZipArchive<Cursor<&[u8]>>, rels: &str) -> BTreeMap<String, String> {
+    let mut targets = BTreeMap::new();
+    let Ok(file) = archive.by_name(rels) else { return targets };
+    note_zip_entry(&file);
+    let mut reader = XmlReader::from_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"Relationship" => {
+                let value = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|attr| attr.key.as_ref() == name)
+                        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+                };
+                if let (Some(id), Some(target)) = (value(b"Id"), value(b"Target")) {
+                    targets.insert(id, target);
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    targets
+}
+
+/// Files that operating systems add to the folders they zip: Finder's folder
+/// settings and the Windows Explorer thumbnail cache.
+const SYSTEM_FILE_NAMES: [&str; 3] = [".DS_Store", "Thumbs.db", "desktop.ini"];
+
+/// Whether an archive entry was added by the operating system rather than
+/// being part of the content.
+///
+/// Matches `__MACOSX/` resource forks, AppleDouble `._` files, the files of
+/// `SYSTEM_FILE_NAMES` (case-insensitively), and directory placeholders
+/// (names ending with `/`). Every ZIP iteration in the crate skips them, so
+/// that archives zipped on macOS or Windows aren't counted twice.
+pub fn is_system_entry(name: &str) -> bool {
+    let file = name.rsplit('/').next().unwrap_or(name);
+    name.ends_with('/')
+        || name.split('/').any(|part| part == "__MACOSX")
+        || file.starts_with("._")
+        || SYSTEM_FILE_NAMES.iter().any(|system| file.eq_ignore_ascii_case(system))
+}
+
+/// Signature of an OLE compound file, used by legacy and password-protected Office documents.
+pub const OLE_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Checks whether bytes are a password-protected Office Open XML document.
+///
+/// Encrypted DOCX/PPTX/XLSX files are not ZIP archives but OLE compound files
+/// holding an `EncryptedPackage` stream.
+pub fn is_encrypted_office_package(bytes: &[u8]) -> bool {
+    // "EncryptedPackage" stream name, stored as UTF-16LE in the OLE directory
+    let marker: Vec<u8> = "EncryptedPackage".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    bytes.starts_with(OLE_MAGIC) && find_bytes(bytes, &marker).is_some()
+}
+
+/// Reads a single entry from a ZIP archive by scanning its local file headers.
+///
+/// This is a tolerant fallback for archives whose central directory is missing
+/// or unreadable (e.g. files cut off during upload). Entries near the start of
+/// the file can often still be recovered from their local headers even though
+/// `ZipArchive::new` refuses to open the archive.
+///
+/// # Arguments
+///
+/// * `bytes` - The (possibly truncated) archive contents
+/// * `name` - The entry path to look for (e.g. `"docProps/app.xml"`)
+///
+/// # Returns
+///
+/// The entry contents as a UTF-8 string, or `None` if no intact local entry
+/// with that name could be found. Entries that rely on a trailing data
+/// descriptor for their sizes cannot be recovered this way.
+pub fn recover_zip_entry(bytes: &[u8], name: &str) -> Option<String> {
+    let mut pos = 0;
+    while let Some(offset) = find_bytes(&bytes[pos..], b"PK\x03\x04") {
+        let start = pos + offset;
+        let mut cursor = Cursor::new(&bytes[start..]);
+        if let Ok(Some(mut file)) = zip::read::read_zipfile_from_stream(&mut cursor)
+            && file.name() == name
+        {
+            let mut contents = String::new();
+            return file.read_to_string(&mut contents).ok().map(|_| contents);
+        }
+        pos = start + 4;
+    }
+    None
+}
+
+/// Largest entry `RecoveredZip::scan` inflates, in bytes; larger entries are left out.
+pub const MAX_RECOVERED_ENTRY_BYTES: u64 = 100 * 1024 * 1024;
+
+/// The entries of a ZIP archive read from their local file headers, for
+/// archives whose central directory is damaged (e.g. a few bytes lost at the
+/// end of an upload) while the entries themselves are intact.
+///
+/// Unlike `recover_zip_entry`, the archive is scanned once and every entry is
+/// kept, so a whole OOXML package can be rebuilt with `to_zip` and estimated
+/// as usual.
+#[derive(Clone, Debug, Default)]
+pub struct RecoveredZip {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl RecoveredZip {
+    /// Reads every intact entry from its local file header.
+    ///
+    /// Entries are inflated by the sizes their headers declare and kept only
+    /// when their CRC matches. Entries that rely on a trailing data descriptor
+    /// for their sizes, or inflate to more than `MAX_RECOVERED_ENTRY_BYTES`,
+    /// are skipped. When a name occurs twice, the later entry wins, as it does
+    /// for archives that were appended to.
+    pub fn scan(bytes: &[u8]) -> Self {
+        let mut entries = BTreeMap::new();
+        let mut pos = 0;
+        while let Some(offset) = find_bytes(&bytes[pos..], b"PK\x03\x04") {
+            let start = pos + offset;
+            let mut cursor = Cursor::new(&bytes[start..]);
+            pos = start + 4;
+            let Ok(Some(file)) = zip::read::read_zipfile_from_stream(&mut cursor) else { continue };
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.name().to_string();
+            let mut contents = Vec::new();
+            let read = file.take(MAX_RECOVERED_ENTRY_BYTES + 1).read_to_end(&mut contents);
+            if read.is_ok() && contents.len() as u64 <= MAX_RECOVERED_ENTRY_BYTES {
+                entries.insert(name, contents);
+                // skip the entry's data, which may itself hold a header signature
+                pos = pos.max(start + cursor.position() as usize);
+            }
+        }
+        Self { entries }
+    }
+
+    /// Contents of an entry by name.
+    pub fn by_name(&self, name: &str) -> Option<&[u8]> {
+        self.entries.get(name).map(Vec::as_slice)
+    }
+
+    /// Whether an entry with that name was recovered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Number of recovered entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no entry could be recovered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the recovered entries to a new, intact ZIP archive (stored, uncompressed).
+    pub fn to_zip(&self) -> Result<Vec<u8>, EstimatorError> {
+        let zip_error = |e: ZipError| EstimatorError::ZipError(format!("Failed to rebuild archive: {}", e));
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, contents) in &self.entries {
+            writer.start_file(name.as_str(), options).map_err(zip_error)?;
+            writer.write_all(contents).map_err(|e| zip_error(e.into()))?;
+        }
+        Ok(writer.finish().map_err(zip_error)?.into_inner())
+    }
+}
+
+/// Returns the offset of the first occurrence of `needle` in `haystack`.
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Decompresses gzip data, refusing to produce more than `limit` bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The gzip-compressed input
+/// * `limit` - Maximum decompressed size in bytes; guards against decompression bombs
+///
+/// # Returns
+///
+/// The decompressed bytes, `EstimatorError::General` if the data is not valid
+/// gzip, or `EstimatorError::TooLarge` if it decompresses to more than `limit` bytes.
+pub fn decompress_gzip(bytes: &[u8], limit: u64) -> Result<Vec<u8>, EstimatorError> {
+    let mut out = Vec::new();
+    GzDecoder::new(bytes)
+        .take(limit + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| EstimatorError::General(format!("gzip decompression failed: {}", e)))?;
+    if out.len() as u64 > limit {
+        return Err(EstimatorError::TooLarge { limit: limit as usize, actual: out.len() });
+    }
+    note_buffer(out.len());
+    Ok(out)
+}
+
+/// Strips a compression suffix from a filename (`report.md.gz` → `report.md`,
+/// `bundle.tgz` → `bundle.tar`).
+pub fn strip_gzip_extension(name: &str) -> String {
+    let lower = name.to_lowercase();
+    if lower.ends_with(".tgz") {
+        format!("{}.tar", &name[..name.len() - 4])
+    } else if lower.ends_with(".gz") {
+        name[..name.len() - 3].to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// A regular file stored in a TAR archive.
+pub struct TarEntry<'a> {
+    /// Full path of the entry, including PAX or GNU long-name overrides.
+    pub path: String,
+    /// The file contents.
+    pub data: &'a [u8],
+}
+
+/// Lists the regular files of a TAR archive.
+///
+/// Supports ustar headers with name prefixes, PAX extended headers (`path`
+/// records), and GNU long names (`././@LongLink`). Directories, links, and
+/// other special entries are skipped; GNU sparse files are rejected with a note.
+///
+/// # Returns
+///
+/// The regular file entries in archive order plus notes about skipped entries,
+/// or `EstimatorError::General` if a header is malformed.
+pub fn read_tar_entries(bytes: &[u8]) -> Result<(Vec<TarEntry<'_>>, Vec<String>), EstimatorError> {
+    const BLOCK: usize = 512;
+    let mut entries = Vec::new();
+    let mut notes = Vec::new();
+    let mut pos = 0;
+    let mut long_name: Option<String> = None;
+
+    while pos + BLOCK <= bytes.len() {
+        let header = &bytes[pos..pos + BLOCK];
+        if header.iter().all(|b| *b == 0) {
+            break; // end-of-archive marker
+        }
+        let size = parse_tar_size(&header[124..136]).ok_or_else(|| {
+            EstimatorError::General(format!("Malformed TAR header at offset {}", pos))
+        })?;
+        let data_start = pos + BLOCK;
+        let data_end = data_start
+            .checked_add(size)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| EstimatorError::General("TAR archive is truncated".into()))?;
+        let data = &bytes[data_start..data_end];
+        pos = data_start + size.div_ceil(BLOCK) * BLOCK;
+
+        let header_name = {
+            let name = tar_string(&header[0..100]);
+            let prefix = tar_string(&header[345..500]);
+            if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                format!("{}/{}", prefix, name)
+            } else {
+                name
+            }
+        };
+
+        match header[156] {
+            b'x' => long_name = pax_path(data).or(long_name),
+            b'L' => long_name = Some(tar_string(data)),
+            b'g' => {}
+            b'S' => {
+                let name = long_name.take().unwrap_or(header_name);
+                notes.push(format!("Skipped sparse file '{}' (not supported)", name));
+            }
+            b'0' | 0 | b'7' => {
+                let path = long_name.take().unwrap_or(header_name);
+                entries.push(TarEntry { path, data });
+            }
+            _ => {
+                long_name = None;
+            }
+        }
+    }
+
+    Ok((entries, notes))
+}
+
+/// Parses a TAR size field (octal, or base-256 when the high bit is set).
+fn parse_tar_size(field: &[u8]) -> Option<usize> {
+    if field[0] & 0x80 != 0 {
+        let mut value: usize = (field[0] & 0x7F) as usize;
+        for b in &field[1..] {
+            value = value.checked_mul(256)?.checked_add(*b as usize)?;
         }
+        return Some(value);
+    }
+    let text = tar_string(field);
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
     }
-    // Default to xlsx for backward compatibility
-    "xlsx".into()
+    usize::from_str_radix(text, 8).ok()
 }
 
+/// Reads a NUL-terminated string field.
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Extracts the `path` record from PAX extended header data (`"<len> path=<value>\n"`).
+fn pax_path(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    text.lines().find_map(|line| {
+        let (_, record) = line.split_once(' ')?;
+        record.strip_prefix("path=").map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{
+        build_docx, build_drive_sign_in_page, build_drive_stub, build_pdf, build_xlsx, corrupt_part, estimate, gzip,
+        to_zip64, XrefStyle,
+    };
+
+    #[test]
+    fn locales_choose_letter_or_a4_by_region() {
+        let cases = [
+            ("en-US", "Letter"),
+            ("en_us", "Letter"),
+            ("fr-CA", "Letter"),
+            ("es-MX", "Letter"),
+            ("fil-PH", "Letter"),
+            ("en-GB", "A4"),
+            ("de-DE", "A4"),
+            ("zh-Hant-TW", "A4"),
+            ("es-419", "A4"),
+        ];
+        for (locale, paper) in cases {
+            assert_eq!(paper_for_locale(locale).name, paper, "{}", locale);
+        }
+        assert_eq!(paper_for_locale("en-US").size_mm, letter_mm());
+        assert_eq!(paper_for_locale("en-US").reason, "locale 'en-US' region US uses Letter");
+        assert_eq!(paper_for_locale("de-DE").size_mm, a4_mm());
+    }
+
+    #[test]
+    fn locales_without_a_known_region_fall_back_to_a4() {
+        // a language alone says nothing about paper, even for English
+        for locale in ["en", "", "en-", "en-USA", "not a locale", "sr-Latn"] {
+            let choice = paper_for_locale(locale);
+            assert_eq!(choice.name, "A4", "{}", locale);
+            assert_eq!(choice.reason, format!("locale '{}' has no region; defaulting to A4", locale));
+        }
+        assert_eq!(paper_for_locale("en-ZZ").reason, "locale 'en-ZZ' region ZZ uses A4");
+    }
+
+    #[test]
+    fn locale_applies_only_without_an_explicit_paper() {
+        let docx = build_docx(20, 0, None);
+        let us = estimate(&docx, None, r#"{"locale":"en-US"}"#).unwrap();
+        assert_eq!(us.page_sizes[0].width_mm, letter_mm().0);
+        let note = "Paper: Letter (locale 'en-US' region US uses Letter)".to_string();
+        assert!(us.notes.contains(&note), "{:?}", us.notes);
+
+        let chosen = estimate(&docx, None, r#"{"locale":"en-US","default_paper":"A4"}"#).unwrap();
+        assert_eq!(chosen.page_sizes[0].width_mm, a4_mm().0);
+        assert!(!chosen.notes.iter().any(|note| note.starts_with("Paper:")));
+    }
+
+    #[test]
+    fn zip64_workbook_lists_its_sheets() {
+        let workbook = to_zip64(&build_xlsx(&[(12, 2), (3, 4)]));
+        assert!(has_zip64_records(&workbook));
+        let archive = open_zip_archive(&workbook, "XLSX").unwrap();
+        assert!(archive.file_names().any(|name| name == "xl/workbook.xml"));
+
+        let result = estimate(&workbook, Some("book.xlsx"), "{}").unwrap();
+        let sheets = result.sheets.unwrap();
+        let names: Vec<&str> = sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Sheet1", "Sheet2"]);
+        assert_eq!((sheets[0].rows, sheets[1].columns), (12, 4));
+    }
+
+    #[test]
+    fn zip64_document_estimates_like_the_original() {
+        let docx = build_docx(12, 3, Some(5));
+        let plain = estimate(&docx, None, "{}").unwrap();
+        let zip64 = estimate(&to_zip64(&docx), None, "{}").unwrap();
+        assert_eq!(zip64.page_count, plain.page_count);
+        assert_eq!(zip64.confidence, plain.confidence);
+    }
+
+    #[test]
+    fn broken_zip64_records_are_named_in_the_error() {
+        let mut workbook = to_zip64(&build_xlsx(&[(2, 2)]));
+        let record = workbook.windows(4).rposition(|w| w == b"PK\x06\x06").unwrap();
+        // point the ZIP64 record at a central directory past the end of the file
+        workbook[record + 48..record + 56].copy_from_slice(&u64::MAX.to_le_bytes());
+        let Err(EstimatorError::ZipError(message)) = open_zip_archive(&workbook, "XLSX") else {
+            panic!("a ZIP64 archive with a bad central directory offset opened");
+        };
+        assert!(message.contains("ZIP64"), "{}", message);
+
+        let Err(EstimatorError::ZipError(message)) = open_zip_archive(b"PK\x03\x04 not a zip", "DOCX") else {
+            panic!("garbage opened as a ZIP archive");
+        };
+        assert!(message.starts_with("Failed to open DOCX as ZIP: corrupt archive"), "{}", message);
+    }
+
+    #[test]
+    fn byte_order_marks_dont_defeat_sniffing() {
+        let cases: [(&[u8], &str); 6] = [
+            (b"<?xml version=\"1.0\"?>\n<urlset><url><loc>https://example.com/</loc></url></urlset>\n", "data"),
+            (b"Meeting notes\n\nAgenda items for Tuesday.\n", "txt"),
+            (b"{\"report\": [{\"id\": 1, \"total\": 12.5}]}\n", "data"),
+            (b"\n  \t<?xml version=\"1.0\"?>\n<report/>\n", "data"),
+            (b"\r\n  {\"id\": 1}\n", "data"),
+            (b"%PDF-1.7\n", "pdf"),
+        ];
+        for (content, format) in cases {
+            let with_bom = [UTF8_BOM, content].concat();
+            assert_eq!(detect_type(None, content), format, "{:?}", String::from_utf8_lossy(content));
+            assert_eq!(detect_type(None, &with_bom), format, "{:?}", String::from_utf8_lossy(content));
+        }
+        // the extension wins, BOM or not
+        assert_eq!(detect_type(Some("sitemap.xml"), &[UTF8_BOM, b"<urlset/>"].concat()), "data");
+        // only a BOM is skipped ahead of the magic bytes
+        assert_ne!(detect_type(None, b"  %PDF-1.7\n"), "pdf");
+        assert_eq!(strip_bom(b"\xEF\xBBtext"), b"\xEF\xBBtext");
+        assert_eq!(strip_bom(&[UTF8_BOM, UTF8_BOM].concat()), UTF8_BOM);
+    }
+
+    #[test]
+    fn system_entries_are_recognized() {
+        let system = [
+            "__MACOSX/ppt/slides/._slide1.xml",
+            "__MACOSX/ppt/slides/slide1.xml",
+            "ppt/slides/._slide1.xml",
+            ".DS_Store",
+            "scans/.ds_store",
+            "scans/Thumbs.db",
+            "scans/desktop.ini",
+            "ppt/slides/",
+        ];
+        for name in system {
+            assert!(is_system_entry(name), "{}", name);
+        }
+        for name in ["ppt/slides/slide1.xml", "MACOSX/notes.txt", "scans/page._1.png", "Thumbs.db.pdf", ".hidden.txt"] {
+            assert!(!is_system_entry(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn entries_are_recovered_without_the_central_directory() {
+        let docx = build_docx(30, 2, Some(5));
+        let damaged = &docx[..docx.len() - 8];
+        assert!(ZipArchive::new(Cursor::new(damaged)).is_err());
+
+        let recovered = RecoveredZip::scan(damaged);
+        let mut original = ZipArchive::new(Cursor::new(&docx[..])).unwrap();
+        assert_eq!(recovered.len(), original.len());
+        for i in 0..original.len() {
+            let mut file = original.by_index(i).unwrap();
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(recovered.by_name(file.name()), Some(&contents[..]), "{}", file.name());
+        }
+        let rebuilt = recovered.to_zip().unwrap();
+        assert_eq!(ZipArchive::new(Cursor::new(&rebuilt[..])).unwrap().len(), original.len());
+        assert!(RecoveredZip::scan(b"not a zip").is_empty());
+    }
+
+    #[test]
+    fn corrupted_entries_are_left_out() {
+        let docx = build_docx(30, 2, Some(5));
+        let corrupted = corrupt_part(&docx, "word/document.xml");
+        let recovered = RecoveredZip::scan(&corrupted[..corrupted.len() - 8]);
+        assert!(!recovered.contains("word/document.xml"));
+        assert!(recovered.contains("docProps/app.xml"));
+        assert_eq!(recovered.len(), ZipArchive::new(Cursor::new(&docx[..])).unwrap().len() - 1);
+    }
+
+    #[test]
+    fn urls_and_paths_are_locators() {
+        let cases: [(&str, Option<&str>); 12] = [
+            ("https://example.com/file.pdf", Some("URL")),
+            ("  s3://bucket/report.docx\n", Some("URL")),
+            ("C:\\Users\\ada\\Documents\\report.docx", Some("file path")),
+            ("D:/scans/page.tif", Some("file path")),
+            ("\\\\server\\share\\invoice.pdf", Some("file path")),
+            ("/home/ada/thesis.pdf", Some("file path")),
+            ("../drafts/notes.md", Some("file path")),
+            ("The meeting moved to 3pm.", None),
+            ("/home/ada/projects", None),
+            ("see https://example.com for details", None),
+            ("https://example.com/a.pdf\nhttps://example.com/b.pdf", None),
+            ("x://", None),
+        ];
+        for (text, kind) in cases {
+            assert_eq!(looks_like_locator(text.as_bytes()), kind, "{:?}", text);
+        }
+        let long = format!("/{}.pdf", "a".repeat(LOCATOR_MAX_BYTES));
+        assert_eq!(looks_like_locator(long.as_bytes()), None);
+    }
+
+    #[test]
+    fn google_drive_stubs_are_recognized() {
+        let applications: Vec<Option<&str>> = ["document", "spreadsheet", "presentation", "folder"]
+            .iter()
+            .map(|app| looks_like_google_stub(&build_drive_stub(app)).map(|stub| stub.application))
+            .collect();
+        let expected = [Some("Google Docs"), Some("Google Sheets"), Some("Google Slides"), Some("Google Drive")];
+        assert_eq!(applications, expected);
+
+        let shortcut = br#"{"url": "https://docs.google.com/spreadsheets/d/1Fixture/edit", "doc_id": "1Fixture"}"#;
+        let stub = looks_like_google_stub(shortcut).unwrap();
+        assert_eq!((stub.kind, stub.application), ("Google Drive file stub", "Google Sheets"));
+        let stub = looks_like_google_stub(&build_drive_sign_in_page()).unwrap();
+        let described = (stub.kind, stub.application, stub.formats);
+        assert_eq!(described, ("Google sign-in page", "Google Docs", ".docx or .pdf"));
+
+        // JSON and HTML that merely mention Google aren't stubs
+        for text in [
+            r#"{"kind": "book", "url": "https://docs.google.com/document/d/1"}"#,
+            "<html><body>Sign in at accounts.google.com to continue.</body></html>",
+            "Notes on drive#file metadata from accounts.google.com/signin",
+        ] {
+            assert_eq!(looks_like_google_stub(text.as_bytes()), None, "{}", text);
+        }
+        let mut large = build_drive_stub("document");
+        large.extend(std::iter::repeat_n(b' ', GOOGLE_STUB_MAX_BYTES));
+        assert_eq!(looks_like_google_stub(&large), None);
+    }
+
+    #[test]
+    fn paper_is_parsed_from_names_and_sizes() {
+        let cases = [
+            ("A4", Some((210.0, 297.0))),
+            ("  letter ", Some((215.9, 279.4))),
+            ("a3 Landscape", Some((420.0, 297.0))),
+            ("297x420", Some((297.0, 420.0))),
+            ("215.9 X 279.4", Some((215.9, 279.4))),
+            ("420×297 portrait", Some((297.0, 420.0))),
+            ("A5 sideways", None),
+            ("0x297", None),
+            ("inf x 297", None),
+            ("297x", None),
+            ("", None),
+        ];
+        for (spec, paper) in cases {
+            assert_eq!(parse_paper(spec), paper, "{:?}", spec);
+        }
+    }
+
+    const DOCX_MIME: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+    #[test]
+    fn mime_hints_rank_between_magic_and_the_filename() {
+        let docx = build_docx(30, 2, Some(4));
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let notes = b"# Notes\n\nSome *text*.\n";
+        let detect = |filename, mime_hint, bytes: &[u8]| {
+            let detection = detect_format(filename, mime_hint, bytes);
+            (detection.format, detection.warning)
+        };
+        // a correct hint without a filename, and a hint outranking the extension
+        assert_eq!(detect(None, Some(DOCX_MIME), &docx), ("docx".into(), None));
+        assert_eq!(detect(Some("notes.txt"), Some("text/markdown"), notes), ("markdown".into(), None));
+        assert_eq!(detect(None, Some("text/markdown"), notes), ("markdown".into(), None));
+
+        // conclusive magic wins over a contradicting hint or name, with a warning
+        let warning = "⚠ The MIME type says docx, but the content is pdf; estimated as pdf";
+        assert_eq!(detect(None, Some(DOCX_MIME), &pdf), ("pdf".into(), Some(warning.into())));
+        assert_eq!(detect(Some("a.pdf"), Some(DOCX_MIME), &pdf).1.as_deref(), Some(warning));
+        let named = detect(Some("report.docx"), None, &pdf);
+        assert_eq!(named.1.as_deref(), Some("⚠ The filename says docx, but the content is pdf; estimated as pdf"));
+
+        // unknown and generic types leave the decision to the name and the content
+        for mime_hint in ["application/x-foo", "not a mime type", "", "text/plain", "application/octet-stream"] {
+            assert_eq!(detect(Some("notes.md"), Some(mime_hint), notes), ("markdown".into(), None), "{}", mime_hint);
+            assert_eq!(detect(None, Some(mime_hint), &pdf), ("pdf".into(), None), "{}", mime_hint);
+        }
+        assert_eq!(detect(None, Some("application/zip"), &docx), ("docx".into(), None));
+        assert_eq!(detect(None, Some("text/plain"), b"hello world"), ("txt".into(), None));
+    }
+
+    #[test]
+    fn mime_hints_reach_the_estimate_but_not_what_it_unpacks() {
+        let docx = build_docx(30, 2, Some(4));
+        let result = estimate(&docx, None, &format!(r#"{{"mime_hint": "{}"}}"#, DOCX_MIME)).unwrap();
+        assert_eq!(result.page_count, 4);
+        let pdf = build_pdf(2, XrefStyle::Classic, false, false);
+        let result = estimate(&pdf, None, &format!(r#"{{"mime_hint": "{}"}}"#, DOCX_MIME)).unwrap();
+        assert_eq!(result.page_count, 2);
+        assert!(result.notes[0].starts_with("⚠ The MIME type says docx"), "{:?}", result.notes);
+        assert_eq!(estimate(&pdf, None, r#"{"mime_hint": "application/x-foo"}"#).unwrap().page_count, 2);
+
+        // the gzip type describes the stream, not the document inside it
+        let text = "A few words. ".repeat(400);
+        let gzip_hint = r#"{"mime_hint": "application/gzip"}"#;
+        assert_eq!(estimate(&gzip(text.as_bytes(), 1), None, gzip_hint).unwrap().page_count, 3);
+        let result = estimate(&gzip(&docx, 1), None, gzip_hint).unwrap();
+        assert_eq!(result.page_count, 4);
+        assert!(!result.notes.iter().any(|n| n.contains("MIME type")), "{:?}", result.notes);
+    }
+}
@@ -0,0 +1,2653 @@
+//! # Document Fixtures
+//!
+//! Builds minimal, structurally valid documents in memory, so checks of the
+//! estimators don't need binary files committed for every case. Only compiled
+//! for the crate's own tests and with the `test-fixtures` feature (the fuzz
+//! targets use it).
+//!
+//! ## Builders
+//!
+//! - `build_pdf`: a page tree of A4 pages, with a classic `xref` table or an
+//!   uncompressed cross-reference stream, optionally an outline and an
+//!   `/Encrypt` dictionary
+//! - `build_raw_pdf`: a PDF of the given object bodies, for structures no
+//!   other builder makes; `raw_stream` writes the body of a stream object
+//! - `build_mixed_orientation_pdf`: portrait, landscape, rotated, and
+//!   near-square pages; `MIXED_ORIENTATIONS` lists how they display
+//! - `build_blank_pages_pdf`: text pages followed by a truly blank page and a
+//!   page holding only a stamp annotation
+//! - `build_decoy_pages_pdf`: pages plus `/Type /Page` tokens that aren't
+//!   pages, in a JavaScript string, an uncompressed object stream, and a comment
+//! - `build_incrementally_saved_pdf`: a PDF saved again several times, each
+//!   incremental update replacing its first page and its page tree root
+//! - `build_shifted_pdf`: a PDF behind a banner line, its offsets all short
+//!   by the banner's length
+//! - `build_linearized_pdf`: a PDF opening with a linearization dictionary
+//!   that states its page count and file length
+//! - `build_portfolio_pdf`: a PDF portfolio of a one-page cover sheet and
+//!   compressed embedded files
+//! - `build_rescanning_pdf`: page objects that never end, each opening a
+//!   string that is never closed, behind a `startxref` far past the end of the file
+//! - `build_text_pdf`: pages of words drawn with `Tj`, kerned `TJ` arrays,
+//!   hex strings, and a composite font with a `/ToUnicode` map, in
+//!   `FlateDecode` content streams
+//! - `build_protected_pdf`: a PDF encrypted with RC4 or AES-128 under a user
+//!   and an owner password
+//! - `build_receipt_pdf`: pages as wide as a till roll and as long as given,
+//!   such as a single page several meters tall
+//! - `build_docx`: paragraphs with page breaks, optionally `<Pages>` in
+//!   `docProps/app.xml`
+//! - `build_google_docx`: a DOCX as exported by Google Docs, its producer
+//!   named in `docProps/app.xml`
+//! - `build_docx_written_by`: a DOCX whose `docProps/app.xml` names any producer
+//! - `build_alt_chunk_docx`: a DOCX whose body is one paragraph followed by
+//!   `<w:altChunk>` references to HTML, MHT, text, or other parts
+//! - `build_embedded_objects_docx`: a DOCX whose body is one paragraph
+//!   followed by OLE objects embedding spreadsheets, PDFs, or opaque blobs
+//! - `build_linked_images_docx`: a DOCX with a hyperlink and pictures linked
+//!   to files outside the package
+//! - `build_equations_docx`: a LaTeX paper converted to DOCX, with inline and
+//!   display Office Math and a text box with a fallback; it fills
+//!   `EQUATIONS_DOCX_PAGES` pages
+//! - `build_rendered_breaks_docx`: a DOCX as saved by Word, with the page
+//!   breaks of its last layout recorded in body paragraphs, a table, and after
+//!   a content control; `RENDERED_BREAKS_PAGE_STARTS` lists where its pages start
+//! - `build_drive_stub`, `build_drive_sign_in_page`: what Google Drive serves
+//!   for a Google Docs, Sheets, or Slides file that wasn't exported
+//! - `build_xlsx`: one worksheet of numbers per `(rows, cols)` entry
+//! - `build_hidden_sheets_xlsx`: the same, some of its sheets hidden
+//! - `build_external_link_xlsx`: the same, with a link to another workbook
+//! - `build_formatting_only_xlsx`: a sheet of numbers and a sheet of styled,
+//!   empty cells
+//! - `build_print_layout_xlsx`: a worksheet of given rows with print settings
+//!   (fit to page, row heights, margins) around its cell data; `number_rows`
+//!   writes rows of numbers
+//! - `build_reordered_xlsx`: worksheets whose tabs were moved, so the tab order
+//!   of `xl/workbook.xml` runs against the ZIP entries and the `sheetId`s
+//! - `build_pptx`: slides, some of them hidden
+//! - `build_notes_pptx`: slides with or without speaker notes
+//! - `build_encrypted_office_package`: the OLE compound file Office writes for
+//!   a password-protected DOCX, XLSX, or PPTX, reduced to its header and the
+//!   directory entry of its `EncryptedPackage` stream
+//! - `build_cbz`: a comic of PNG pages stored out of order, with a cover
+//!   thumbnail, a macOS resource fork, and optionally a `ComicInfo.xml`;
+//!   `png_header` is the start of one PNG page
+//! - `build_iwork`: a Pages, Numbers, or Keynote package of IWA streams and
+//!   metadata, optionally with a QuickLook preview PDF
+//! - `build_postscript`: a multi-page PostScript document with DSC comments,
+//!   its page total in the header or the trailer and an embedded EPS
+//! - `build_dos_eps`: an EPS illustration behind a DOS EPS binary header, with
+//!   a TIFF preview
+//! - `build_xps`: an XPS or OpenXPS print of pages of given sizes, with an
+//!   unreferenced page part left in the package
+//! - `build_zip`: a plain ZIP archive of the given entries, such as a batch
+//!   of documents uploaded together
+//! - `build_tar`: a ustar archive of the given entries; `gzip` compresses
+//!   any input, once per layer asked for
+//! - `build_notebook`: a Jupyter notebook of analysis sections, each a
+//!   Markdown cell and a code cell with a printed table and a plot; it fills
+//!   `NOTEBOOK_PAGES` pages
+//! - `build_vcards` and `build_calendar`: an address book export and a
+//!   calendar with CRLF line breaks and folded long lines
+//! - `build_email`: an RFC 822 message with a quoted-printable plain text
+//!   body and base64 attachments, optionally missing its closing boundary
+//! - `build_word_mht`: a web archive as Word saves it, quoted-printable HTML
+//!   paragraphs plus a base64 image and a file list
+//! - `with_part`: any of the ZIP-based documents above with one more part,
+//!   such as a `docProps/core.xml`
+//! - `without_part`: any of the ZIP-based documents above with one part left out
+//! - `to_finder_zip`: any ZIP archive as macOS Finder compresses it, with
+//!   directory entries, `.DS_Store` files, and `__MACOSX/` resource forks
+//! - `truncate_in_part`: any of the ZIP-based documents above, cut off in
+//!   the middle of one of its parts as an interrupted upload leaves it
+//! - `corrupt_part`: any of the ZIP-based documents above with one byte of
+//!   a part flipped, so that the part fails its CRC check
+//! - `to_zip64`: any of the ZIP-based documents above, its end records
+//!   rewritten in ZIP64 form as written for packages over 4 GB
+//! - `to_strict`: any of the OOXML documents above in the strict conformance
+//!   class, with `purl.oclc.org` namespaces
+//! - `build_sized_pptx`: slides of a given size, such as 16:9, A4 portrait, or
+//!   poster decks
+//! - `build_docbook_book`: a DocBook 5 book of chapters and sections with
+//!   program listings, figures, tables, index terms, and a generated index
+//!
+//! Output is deterministic: the same arguments always give the same bytes.
+//!
+//! `estimate` runs a built document through `estimate_bytes` the way
+//! `estimate_document` would, with the options given as JSON.
+//!
+//! ## Stored results
+//!
+//! `V1_RESULT_JSON` is a result as written before `schema_version` existed,
+//! frozen to check that `parse_result` keeps reading it.
+//!
+//! ## Languages
+//!
+//! `LANGUAGE_SAMPLES` pairs a paragraph of ordinary prose with its ISO 639-1
+//! code, one per language, for checking `detect_language`. Each is long enough
+//! for the default `language_min_words`.
+//!
+//! ## Right-to-left text
+//!
+//! `ARABIC_TEXT` and `HEBREW_TEXT` are plain paragraphs; `with_bidi_marks`
+//! adds the directional controls and tatweels word processors export, which
+//! take no print space, so both versions should count the same.
+//!
+//! ## Plain text
+//!
+//! `build_minified_json` writes a single line as long as its records make it,
+//! `build_pretty_json` the same records pretty-printed with 2-space
+//! indentation, and `build_tsv_log` a log whose fields are separated by tabs. `CI_LOG` is a
+//! CI job's output as saved from the terminal, with color codes, a window
+//! title, and a download progress bar repainted with carriage returns.
+//!
+//! ## Markup
+//!
+//! `SPHINX_PAGE` and `ANTORA_PAGE` are documentation pages as the two
+//! generators take them: headings, a table of contents or attribute entries,
+//! an image, code blocks with their options, an admonition, and comments.
+
+use crate::assembly::{estimate_bytes, parse_options_checked};
+use crate::pdf_utils::find_bytes;
+use crate::schema::{EstimateResult, EstimatorError, PageOrientation};
+use std::io::{Cursor, Read, Write};
+use zip::write::SimpleFileOptions;
+use zip::{DateTime, ZipWriter};
+
+/// Estimates `bytes` with the options in `options_json`, as `estimate_document` would.
+///
+/// # Panics
+///
+/// When `options_json` has a key `EstimateOptions` doesn't know or can't be
+/// parsed, so a misspelled option can't leave a check passing by accident.
+pub fn estimate(bytes: &[u8], filename: Option<&str>, options_json: &str) -> Result<EstimateResult, EstimatorError> {
+    let (options, warnings) = parse_options_checked(Some(options_json));
+    assert!(warnings.is_empty(), "options {} were not all used: {:?}", options_json, warnings);
+    estimate_bytes(bytes, filename, &options).1
+}
+
+/// A two-page PDF result of schema version 1 (no `schema_version` key). Never edit it.
+pub const V1_RESULT_JSON: &str = concat!(
+    r#"{"page_count":2,"page_sizes":[{"width_mm":210.0,"height_mm":297.0},{"width_mm":215.9,"height_mm":279.4}],"#,
+    r#""notes":["PDF has 2 pages"],"confidence":1.0,"portrait_pages":2,"landscape_pages":0,"square_pages":0,"#,
+    r#""effective_options":{"default_paper":"A4","custom_paper_mm":[210.0,297.0],"rounding":"ceil","duplex":false,"#,
+    r#""pages_per_sheet":1,"verbosity":1}}"#
+);
+
+/// Paragraphs of prose with the ISO 639-1 code of their language.
+pub const LANGUAGE_SAMPLES: [(&str, &str); 16] = [
+    (
+        "en",
+        "The printing company has reviewed the order and will send the documents to the customer by the end of \
+         the week. If there are any questions about the invoice, they should contact the office, which is open \
+         from nine to five.",
+    ),
+    (
+        "de",
+        "Die Druckerei hat den Auftrag geprüft und wird die Unterlagen bis zum Ende der Woche an den Kunden \
+         schicken. Wenn es noch Fragen zur Rechnung gibt, kann man sich an das Büro wenden, das von neun bis \
+         fünf Uhr geöffnet ist.",
+    ),
+    (
+        "fr",
+        "L'imprimerie a vérifié la commande et enverra les documents au client avant la fin de la semaine. Si \
+         vous avez des questions sur la facture, vous pouvez contacter le bureau, qui est ouvert de neuf heures \
+         à dix-sept heures.",
+    ),
+    (
+        "es",
+        "La imprenta ha revisado el pedido y enviará los documentos al cliente antes del final de la semana. Si \
+         hay alguna pregunta sobre la factura, se puede contactar con la oficina, que está abierta de nueve a \
+         cinco de la tarde.",
+    ),
+    (
+        "it",
+        "La tipografia ha controllato l'ordine e invierà i documenti al cliente entro la fine della settimana. \
+         Se ci sono domande sulla fattura, è possibile contattare l'ufficio, che è aperto dalle nove alle \
+         cinque del pomeriggio.",
+    ),
+    (
+        "pt",
+        "A gráfica já verificou o pedido e vai enviar os documentos ao cliente até o final da semana. Se houver \
+         alguma dúvida sobre a fatura, o cliente pode entrar em contato com o escritório, que fica aberto das \
+         nove às cinco.",
+    ),
+    (
+        "nl",
+        "De drukkerij heeft de bestelling gecontroleerd en zal de documenten voor het einde van de week naar de \
+         klant sturen. Als er nog vragen zijn over de factuur, kan men contact opnemen met het kantoor, dat van \
+         negen tot vijf open is.",
+    ),
+    (
+        "sv",
+        "Tryckeriet har granskat beställningen och kommer att skicka dokumenten till kunden före slutet av \
+         veckan. Om det finns frågor om fakturan kan man kontakta kontoret, som är öppet från nio till fem \
+         och som också svarar på e-post.",
+    ),
+    (
+        "da",
+        "Trykkeriet har gennemgået bestillingen og vil sende dokumenterne til kunden inden udgangen af ugen. \
+         Hvis der er spørgsmål om fakturaen, kan man kontakte kontoret, som har åbent fra ni til fem, og det \
+         er meget let at finde.",
+    ),
+    (
+        "no",
+        "Trykkeriet har gått gjennom bestillingen og vil sende dokumentene til kunden innen slutten av uken. \
+         Hvis det er spørsmål om fakturaen, kan man kontakte kontoret, som er åpent fra ni til fem, og det ble \
+         flyttet i fjor.",
+    ),
+    (
+        "pl",
+        "Drukarnia sprawdziła zamówienie i wyśle dokumenty do klienta do końca tygodnia. Jeśli są pytania \
+         dotyczące faktury, można skontaktować się z biurem, które jest czynne od dziewiątej do siedemnastej, \
+         ale tylko w dni robocze.",
+    ),
+    (
+        "ru",
+        "Типография проверила заказ и отправит документы клиенту до конца недели. Если у вас есть вопросы по \
+         счёту, вы можете связаться с офисом, который открыт с девяти до пяти, и мы также ответим на все \
+         письма.",
+    ),
+    (
+        "uk",
+        "Друкарня перевірила замовлення і надішле документи клієнту до кінця тижня. Якщо у вас є питання щодо \
+         рахунку, ви можете звернутися до офісу, який працює з дев'ятої до п'ятої, але також відповідає на \
+         листи.",
+    ),
+    (
+        "el",
+        "Το τυπογραφείο έλεγξε την παραγγελία και θα στείλει τα έγγραφα στον πελάτη μέχρι το τέλος της \
+         εβδομάδας. Αν υπάρχουν ερωτήσεις για το τιμολόγιο, μπορείτε να επικοινωνήσετε με το γραφείο.",
+    ),
+    (
+        "ja",
+        "印刷会社は注文を確認し、週末までにお客様へ書類を送ります。請求書についてご質問がある場合は、\
+         九時から五時まで営業している事務所にお問い合わせください。",
+    ),
+    (
+        "zh",
+        "印刷厂已经审核了订单，并将在本周末之前把文件寄给客户。如果对发票有任何疑问，请联系办公室，\
+         办公室的工作时间是上午九点到下午五点。",
+    ),
+];
+
+/// A paragraph of Arabic prose, without directional controls.
+pub const ARABIC_TEXT: &str = "راجعت المطبعة الطلب وسترسل المستندات إلى العميل قبل نهاية الأسبوع. \
+     إذا كانت لديك أسئلة حول الفاتورة، يرجى الاتصال بالمكتب من التاسعة إلى الخامسة.";
+
+/// A paragraph of Hebrew prose, without directional controls.
+pub const HEBREW_TEXT: &str = "בית הדפוס בדק את ההזמנה וישלח את המסמכים ללקוח עד סוף השבוע. \
+     אם יש לך שאלות לגבי החשבונית, אנא פנה למשרד בין תשע לחמש.";
+
+/// Adds directional controls to right-to-left text, as word processors export it.
+///
+/// Each line is embedded right-to-left (U+202B ... U+202C), each word is
+/// followed by a right-to-left mark (U+200F), and Arabic words of four
+/// letters or more are stretched with a tatweel (U+0640) after their first letter.
+pub fn with_bidi_marks(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        out.push('\u{202B}');
+        for (i, word) in line.split(' ').enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let mut letters = word.chars();
+            let first = letters.next();
+            let arabic = first.is_some_and(|c| ('\u{0620}'..='\u{064A}').contains(&c));
+            if arabic && word.chars().count() >= 4 {
+                out.extend(first);
+                out.push('\u{0640}');
+                out.extend(letters);
+            } else {
+                out.push_str(word);
+            }
+            out.push('\u{200F}');
+        }
+        out.push_str("\u{202C}\n");
+    }
+    out
+}
+
+/// A Sphinx page in reStructuredText.
+pub const SPHINX_PAGE: &str = r#".. _installation:
+
+============
+Installation
+============
+
+.. toctree::
+   :maxdepth: 2
+
+   quickstart
+   configuration
+
+The estimator ships as a WebAssembly package. Install it with npm and import
+the functions you need; nothing else has to be set up.
+
+Requirements
+------------
+
+.. note::
+   Node 18 or newer is required for the examples below.
+
+.. code-block:: javascript
+   :linenos:
+   :caption: index.js
+
+   import init, { estimate_document } from "page-counter-wasm";
+
+   await init();
+   const result = estimate_document(bytes, "report.pdf", "{}");
+
+.. image:: /_static/architecture.png
+   :alt: Architecture overview
+   :width: 600px
+
+The shell command is::
+
+   npm install page-counter-wasm
+
+.. This comment is not rendered.
+"#;
+
+/// The same page as an Antora page in AsciiDoc.
+pub const ANTORA_PAGE: &str = r#"= Installation
+:navtitle: Install
+:page-aliases: setup.adoc
+:source-language: javascript
+:experimental:
+
+The estimator ships as a WebAssembly package. Install it with npm and import
+the functions you need; nothing else has to be set up.
+
+== Requirements
+
+NOTE: Node 18 or newer is required for the examples below.
+
+[source,javascript]
+.index.js
+----
+import init, { estimate_document } from "page-counter-wasm";
+
+await init();
+const result = estimate_document(bytes, "report.pdf", "{}");
+----
+
+image::architecture.png[Architecture overview,600]
+
+The shell command is:
+
+....
+npm install page-counter-wasm
+....
+
+// This comment is not rendered.
+"#;
+
+/// A CI job log with ANSI colors and a carriage-return progress bar (see the module docs).
+pub const CI_LOG: &str = "\x1b]0;build #1432\x07\x1b[36m==>\x1b[0m Checking out repository\n\
+     \x1b[36m==>\x1b[0m Restoring cache\n\
+     Downloading toolchain   0%\rDownloading toolchain  25%\rDownloading toolchain  50%\r\
+     Downloading toolchain  75%\rDownloading toolchain 100%\n\
+     \x1b[1m\x1b[32m   Compiling\x1b[0m page-counter-wasm v0.1.0\n\
+     \x1b[1m\x1b[33mwarning\x1b[0m\x1b[1m: unused variable: `x`\x1b[0m\n\
+     \x1b[1m\x1b[32m    Finished\x1b[0m `test` profile in 41.2s\n\
+     test result: \x1b[32mok\x1b[0m. 67 passed; 0 failed\n";
+
+/// Builds `records` order records as minified JSON: one line, no whitespace,
+/// as bundlers and APIs write it.
+pub fn build_minified_json(records: usize) -> String {
+    let items: Vec<String> = (0..records)
+        .map(|i| {
+            format!(
+                r#"{{"id":{},"sku":"SKU-{:05}","qty":{},"price":{}.{:02},"tags":["print","a4"],"ok":true}}"#,
+                i,
+                i * 7,
+                i % 9 + 1,
+                i % 50 + 3,
+                i % 100
+            )
+        })
+        .collect();
+    format!(r#"{{"orders":[{}]}}"#, items.join(","))
+}
+
+/// Builds the records of `build_minified_json` pretty-printed with 2-space
+/// indentation: one line per member and array item.
+pub fn build_pretty_json(records: usize) -> String {
+    serde_json::from_str::<serde_json::Value>(&build_minified_json(records))
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_default()
+}
+
+/// Builds a tab-separated access log of `lines` lines: a header row, then
+/// timestamp, level, request path, status, and duration fields.
+pub fn build_tsv_log(lines: usize) -> String {
+    let mut log = String::from("time\tlevel\tpath\tstatus\tms\n");
+    for i in 1..lines {
+        let level = if i % 10 == 0 { "WARN" } else { "INFO" };
+        log.push_str(&format!(
+            "2026-03-0{}T12:{:02}:{:02}Z\t{}\t/api/v1/print/{}\t{}\t{}\n",
+            i % 9 + 1,
+            i % 60,
+            (i * 7) % 60,
+            level,
+            i,
+            if i % 10 == 0 { 503 } else { 200 },
+            i * 3 % 400
+        ));
+    }
+    log
+}
+
+/// How a built PDF stores its cross-reference data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrefStyle {
+    /// A classic `xref` table and `trailer`.
+    Classic,
+    /// An uncompressed cross-reference stream (PDF 1.5).
+    Stream,
+}
+
+/// Builds a PDF of `pages` A4 pages.
+///
+/// # Arguments
+///
+/// * `pages` - Number of pages in the page tree
+/// * `xref_style` - How the cross-reference data is stored
+/// * `with_bookmarks` - Whether to add an outline with one item per page
+/// * `encrypted` - Whether the trailer points at a standard security handler
+///   dictionary (the content itself isn't encrypted)
+pub fn build_pdf(pages: usize, xref_style: XrefStyle, with_bookmarks: bool, encrypted: bool) -> Vec<u8> {
+    let mut writer = PdfWriter::new();
+    let outlines = 3 + pages * 2;
+    let catalog_extra = if with_bookmarks { format!(" /Outlines {} 0 R", outlines) } else { String::new() };
+    writer.object(format!("<< /Type /Catalog /Pages 2 0 R{} >>", catalog_extra).as_bytes());
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    for i in 0..pages {
+        writer.object(
+            format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents {} 0 R >>", 4 + i * 2)
+                .as_bytes(),
+        );
+        let content = format!("BT /F1 12 Tf 72 720 Td (Page {}) Tj ET", i + 1);
+        writer.object(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content).as_bytes());
+    }
+    if with_bookmarks {
+        let (first, last) = (outlines + 1, outlines + pages);
+        writer.object(
+            format!("<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>", first, last, pages).as_bytes(),
+        );
+        for i in 0..pages {
+            let mut item =
+                format!("<< /Title (Page {}) /Parent {} 0 R /Dest [{} 0 R /Fit]", i + 1, outlines, 3 + i * 2);
+            if i > 0 {
+                item.push_str(&format!(" /Prev {} 0 R", first + i - 1));
+            }
+            if i + 1 < pages {
+                item.push_str(&format!(" /Next {} 0 R", first + i + 1));
+            }
+            item.push_str(" >>");
+            writer.object(item.as_bytes());
+        }
+    }
+    let encrypt = encrypted.then(|| {
+        writer.object(
+            format!("<< /Filter /Standard /V 1 /R 2 /P -4 /O <{0}> /U <{0}> >>", "00".repeat(32)).as_bytes(),
+        )
+    });
+    match xref_style {
+        XrefStyle::Classic => writer.finish_classic(encrypt),
+        XrefStyle::Stream => writer.finish_xref_stream(encrypt),
+    }
+}
+
+/// Builds a PDF of `objects`, numbered from 1, the first being the catalog.
+///
+/// Each entry is the body of an object, such as `<< /Type /Catalog /Pages 2 0 R >>`
+/// or a dictionary followed by `stream`...`endstream`. The cross-reference
+/// data is a classic table.
+pub fn build_raw_pdf(objects: &[&str]) -> Vec<u8> {
+    let mut writer = PdfWriter::new();
+    for object in objects {
+        writer.object(object.as_bytes());
+    }
+    writer.finish_classic(None)
+}
+
+/// The body of a stream object holding `data`, its dictionary having `entries` and `/Length`.
+pub fn raw_stream(entries: &str, data: &str) -> String {
+    format!("<< {} /Length {} >>\nstream\n{}\nendstream", entries, data.len(), data)
+}
+
+/// Orientations of the pages of `build_mixed_orientation_pdf` as displayed, in page order.
+pub const MIXED_ORIENTATIONS: [PageOrientation; 6] = [
+    PageOrientation::Portrait,
+    PageOrientation::Landscape,
+    PageOrientation::Landscape,
+    PageOrientation::Square,
+    PageOrientation::Portrait,
+    PageOrientation::Portrait,
+];
+
+/// Builds a six-page PDF of pages in every orientation (`MIXED_ORIENTATIONS`).
+///
+/// In page order: an A4 page with the `/MediaBox` of its page tree root; an
+/// A4 page turned by `/Rotate 90` inherited from an intermediate `/Pages`
+/// node; a landscape A4 `/MediaBox`; a 595 × 600 pt page (square within
+/// tolerance); a landscape `/MediaBox` turned by `/Rotate 270`; and an A4
+/// page turned upside down by `/Rotate 180`.
+pub fn build_mixed_orientation_pdf() -> Vec<u8> {
+    build_raw_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R 4 0 R 6 0 R 7 0 R 8 0 R 9 0 R] /Count 6 /MediaBox [0 0 595 842] >>",
+        "<< /Type /Page /Parent 2 0 R >>",
+        "<< /Type /Pages /Parent 2 0 R /Kids [5 0 R] /Count 1 /Rotate 90 >>",
+        "<< /Type /Page /Parent 4 0 R >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 842 595] >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 600] >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 842 595] /Rotate 270 >>",
+        "<< /Type /Page /Parent 2 0 R /Rotate 180 >>",
+    ])
+}
+
+/// Builds a PDF of `text_pages` pages of text, then a blank page and a stamp-only page.
+///
+/// The blank page has an empty content stream. The last page has one too,
+/// and a `/Stamp` annotation with an appearance stream, so it prints a stamp
+/// but counts as blank (see the `pdf_blank` module). Pages are A4 and the
+/// cross-reference data a classic table.
+pub fn build_blank_pages_pdf(text_pages: usize) -> Vec<u8> {
+    let mut writer = PdfWriter::new();
+    let pages = text_pages + 2;
+    writer.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    let stamp = 3 + pages * 2;
+    for i in 0..pages {
+        let annots = if i + 1 == pages { format!(" /Annots [{} 0 R]", stamp) } else { String::new() };
+        writer.object(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents {} 0 R{} >>",
+                4 + i * 2,
+                annots
+            )
+            .as_bytes(),
+        );
+        let content =
+            if i < text_pages { format!("BT /F1 12 Tf 72 720 Td (Page {}) Tj ET", i + 1) } else { String::new() };
+        writer.object(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content).as_bytes());
+    }
+    writer.object(
+        format!(
+            "<< /Type /Annot /Subtype /Stamp /Rect [200 400 400 480] /Name /Approved /AP << /N {} 0 R >> >>",
+            stamp + 1
+        )
+        .as_bytes(),
+    );
+    let appearance = "1 0 0 RG 4 w 2 2 196 76 re S";
+    writer.object(
+        format!(
+            "<< /Type /XObject /Subtype /Form /BBox [0 0 200 80] /Length {} >>\nstream\n{}\nendstream",
+            appearance.len(),
+            appearance
+        )
+        .as_bytes(),
+    );
+    writer.finish_classic(None)
+}
+
+/// Builds a PDF of `pages` A4 pages with `/Type /Page` tokens that aren't pages.
+///
+/// The catalog's `/OpenAction` runs JavaScript whose string literal mentions
+/// `/Type /Page` (with an escaped parenthesis), an uncompressed object stream
+/// holds a stale page dictionary, and a comment names one too. A correct count
+/// is `pages`. The cross-reference data is a classic table.
+pub fn build_decoy_pages_pdf(pages: usize) -> Vec<u8> {
+    let mut writer = PdfWriter::new();
+    let script = 3 + pages * 2;
+    writer.object(format!("<< /Type /Catalog /Pages 2 0 R /OpenAction {} 0 R >>", script).as_bytes());
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    for i in 0..pages {
+        writer.object(
+            format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents {} 0 R >>", 4 + i * 2)
+                .as_bytes(),
+        );
+        let content = format!("BT /F1 12 Tf 72 720 Td (Page {}) Tj ET", i + 1);
+        writer.object(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content).as_bytes());
+    }
+    writer.object(b"<< /S /JavaScript /JS (var kind = \"/Type /Page\"; app.alert\\(kind\\);) >>\n% /Type /Page");
+    let stale = "99 0 << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>";
+    writer.object(
+        format!("<< /Type /ObjStm /N 1 /First 5 /Length {} >>\nstream\n{}\nendstream", stale.len(), stale).as_bytes(),
+    );
+    writer.finish_classic(None)
+}
+
+/// Builds a PDF of `pages` A4 pages followed by `saves` incremental updates.
+///
+/// Each update appends new copies of the page tree root and of the first page,
+/// which becomes Letter sized, with a cross-reference section chaining to the
+/// previous one through `/Prev`. The file holds `saves + 1` copies of both
+/// objects; a correct count is `pages`.
+pub fn build_incrementally_saved_pdf(pages: usize, saves: usize) -> Vec<u8> {
+    let mut out = build_pdf(pages, XrefStyle::Classic, false, false);
+    let size = 3 + pages * 2;
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    for save in 1..=saves {
+        let objects = [
+            (2, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages)),
+            (3, format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Rev {} >>", save)),
+        ];
+        let prev = crate::pdf_utils::startxref_offset(&out).unwrap_or(0);
+        let mut entries = Vec::new();
+        for (id, body) in objects {
+            entries.push(format!("{} 1\n{:010} 00000 n \n", id, out.len()));
+            out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes());
+        }
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n{}", entries.concat()).as_bytes());
+        out.extend_from_slice(
+            format!("trailer\n<< /Size {} /Root 1 0 R /Prev {} >>\nstartxref\n{}\n%%EOF\n", size, prev, xref)
+                .as_bytes(),
+        );
+    }
+    out
+}
+
+/// Builds `build_pdf(pages, xref_style, false, false)` with `shift` bytes
+/// prepended and its offsets left as they were, as a mail gateway leaves a
+/// PDF it stamped with a banner line. Every offset, `startxref` included,
+/// then points `shift` bytes short of its target.
+pub fn build_shifted_pdf(pages: usize, xref_style: XrefStyle, shift: usize) -> Vec<u8> {
+    let mut banner = format!("{:<width$}", "X-Scanned: clean", width = shift.saturating_sub(1)).into_bytes();
+    banner.truncate(shift.saturating_sub(1));
+    if shift > 0 {
+        banner.push(b'\n');
+    }
+    banner.extend_from_slice(&build_pdf(pages, xref_style, false, false));
+    banner
+}
+
+/// Builds a linearized ("fast web view") PDF of `pages` A4 pages.
+///
+/// The file opens with the linearization dictionary, giving `/N` (the page
+/// count) and `/L` (the file length), as `pdf::linearized_page_count` reads
+/// it. Only that dictionary is laid out as in a linearized file: it is written
+/// before object 1 under the next free object number and left out of the
+/// classic cross-reference table, which nothing reading it needs.
+pub fn build_linearized_pdf(pages: usize) -> Vec<u8> {
+    let mut writer = PdfWriter::new();
+    let dict = format!("{} 0 obj\n<< /Linearized 1 /L 0000000000 /N {} /T 0 >>\nendobj\n", 3 + pages * 2, pages);
+    writer.out.extend_from_slice(dict.as_bytes());
+    writer.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    for i in 0..pages {
+        writer.object(
+            format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents {} 0 R >>", 4 + i * 2)
+                .as_bytes(),
+        );
+        writer.object(raw_stream("", &format!("BT /F1 12 Tf 72 720 Td (Page {}) Tj ET", i + 1)).as_bytes());
+    }
+    let mut out = writer.finish_classic(None);
+    let length = format!("/L {:010}", out.len());
+    let at = find_bytes(&out, b"/L 0000000000").unwrap();
+    out[at..at + length.len()].copy_from_slice(length.as_bytes());
+    out
+}
+
+/// Builds a PDF portfolio as Acrobat writes one: a one-page Letter cover
+/// sheet, a `/Collection` in the catalog, and one embedded file per
+/// `(name, contents)` entry.
+///
+/// The files are listed in the catalog's `/EmbeddedFiles` name tree. Each
+/// file specification names its file in `/F` and, as UTF-16BE, in `/UF`; the
+/// embedded file streams are `FlateDecode`-compressed, so the page trees of
+/// embedded PDFs aren't visible to a scan of the portfolio's objects.
+pub fn build_portfolio_pdf(files: &[(&str, &[u8])]) -> Vec<u8> {
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    let mut writer = PdfWriter::new();
+    writer.object(
+        b"<< /Type /Catalog /Pages 2 0 R /Names << /EmbeddedFiles 5 0 R >> \
+          /Collection << /Type /Collection /View /D >> /PageMode /UseAttachments >>",
+    );
+    writer.object(b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    writer.object(b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R >>");
+    writer.object(raw_stream("", "BT /F1 24 Tf 72 700 Td (Portfolio) Tj ET").as_bytes());
+    let names: Vec<String> =
+        files.iter().enumerate().map(|(i, (name, _))| format!("({}) {} 0 R", name, 6 + i * 2)).collect();
+    writer.object(format!("<< /Names [{}] >>", names.join(" ")).as_bytes());
+    for (i, (name, contents)) in files.iter().enumerate() {
+        let utf16: String = name.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+        writer.object(
+            format!("<< /Type /Filespec /F ({}) /UF <FEFF{}> /EF << /F {} 0 R >> >>", name, utf16, 7 + i * 2)
+                .as_bytes(),
+        );
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents).ok();
+        let data = encoder.finish().unwrap_or_default();
+        let mut body = format!(
+            "<< /Type /EmbeddedFile /Filter /FlateDecode /Params << /Size {} >> /Length {} >>\nstream\n",
+            contents.len(),
+            data.len()
+        )
+        .into_bytes();
+        body.extend_from_slice(&data);
+        body.extend_from_slice(b"\nendstream");
+        writer.object(&body);
+    }
+    writer.finish_classic(None)
+}
+
+/// Builds a damaged PDF crafted to make the raw scans examine the same bytes again and again.
+///
+/// Each of the `objects` page objects has no `endobj` and opens a string
+/// literal that is never closed, so every string runs to the end of the file,
+/// and the `startxref` offset lies far past the end. Without a scan budget the
+/// work grows with the square of the size; a few hundred kilobytes took
+/// seconds. The `objects` pages are a correct count.
+pub fn build_rescanning_pdf(objects: usize) -> Vec<u8> {
+    let mut out = b"%PDF-1.7\n".to_vec();
+    for i in 0..objects {
+        out.extend_from_slice(format!("{} 0 obj\n<< /Type /Page /Parent 1 0 R /Contents ({} \n", i + 2, i).as_bytes());
+    }
+    out.extend_from_slice(b"trailer\n<< /Size 2 /Root 1 0 R >>\nstartxref\n99999999999999\n%%EOF\n");
+    out
+}
+
+/// Width of the pages built by `build_receipt_pdf`, in millimeters (an 80 mm till roll).
+pub const RECEIPT_WIDTH_MM: f64 = 80.0;
+
+/// Builds a PDF with one page per entry of `heights_mm`, `RECEIPT_WIDTH_MM` wide and that tall.
+///
+/// Each page shows a line of text per 10 mm of its height, as a printed
+/// receipt would. The `/MediaBox` is in whole points, so sizes read back may
+/// differ from `heights_mm` by a fraction of a millimeter. Heights past
+/// 5080 mm (200") exceed what PDF viewers must support. The cross-reference
+/// data is a classic table.
+pub fn build_receipt_pdf(heights_mm: &[f64]) -> Vec<u8> {
+    let points = |mm: f64| (mm * 72.0 / 25.4).round() as u64;
+    let mut writer = PdfWriter::new();
+    let pages = heights_mm.len();
+    writer.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    for (i, &height_mm) in heights_mm.iter().enumerate() {
+        let (width, height) = (points(RECEIPT_WIDTH_MM), points(height_mm));
+        writer.object(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R >>",
+                width,
+                height,
+                4 + i * 2
+            )
+            .as_bytes(),
+        );
+        let lines = (height_mm / 10.0) as usize;
+        let content: String = (0..lines)
+            .map(|line| format!("BT /F1 8 Tf 8 {} Td (Item {} 1.00) Tj ET\n", height - 20 - line as u64 * 28, line + 1))
+            .collect();
+        writer.object(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content).as_bytes());
+    }
+    writer.finish_classic(None)
+}
+
+/// Words drawn by `build_text_pdf`, in turn.
+const TEXT_PDF_WORDS: [&str; 12] =
+    ["estimate", "the", "pages", "of", "a", "document", "(sic)", "before", "printing", "it", "twice", "quickly"];
+
+/// Builds a text-based PDF of `pages` pages holding `words_per_page` words each.
+///
+/// Lines of eight words take turns at four ways of showing text: a literal
+/// string with `Tj` (escaping the parentheses of `(sic)`), a `TJ` array that
+/// kerns inside words and leaves gaps between them, a hex string, and a hex
+/// string in a `Type0` font whose `/ToUnicode` map decodes it. Content streams
+/// are `FlateDecode`d. Every word is found by a correct extraction, so the
+/// word count is `pages * words_per_page`.
+pub fn build_text_pdf(pages: usize, words_per_page: usize) -> Vec<u8> {
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    let mut writer = PdfWriter::new();
+    writer.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 6 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    writer.object(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>");
+    writer.object(b"<< /Type /Font /Subtype /Type0 /BaseFont /Sans /Encoding /Identity-H /ToUnicode 5 0 R >>");
+    let cmap = concat!(
+        "/CIDInit /ProcSet findresource begin 12 dict begin begincmap\n",
+        "1 begincodespacerange <0000> <FFFF> endcodespacerange\n",
+        "1 beginbfrange <0020> <007E> <0020> endbfrange\n",
+        "endcmap CMapName currentdict /CMap defineresource pop end end"
+    );
+    writer.object(format!("<< /Length {} >>\nstream\n{}\nendstream", cmap.len(), cmap).as_bytes());
+
+    let hex = |text: &str, width: usize| -> String {
+        text.bytes().map(|b| format!("{:0width$X}", b, width = width)).collect()
+    };
+    for page in 0..pages {
+        let words: Vec<&str> =
+            (0..words_per_page).map(|i| TEXT_PDF_WORDS[(page * words_per_page + i) % TEXT_PDF_WORDS.len()]).collect();
+        let mut content = String::from("BT /F1 11 Tf 14 TL 72 770 Td\n");
+        for (line, chunk) in words.chunks(8).enumerate() {
+            let text = chunk.join(" ");
+            match line % 4 {
+                0 => content.push_str(&format!("({}) Tj T*\n", text.replace('(', "\\(").replace(')', "\\)"))),
+                1 => {
+                    let items: Vec<String> = chunk
+                        .iter()
+                        .map(|word| {
+                            let (head, tail) = word.split_at(word.len() / 2);
+                            let escape = |part: &str| part.replace('(', "\\(").replace(')', "\\)");
+                            format!("({}) 20 ({})", escape(head), escape(tail))
+                        })
+                        .collect();
+                    content.push_str(&format!("[{}] TJ T*\n", items.join(" -300 ")));
+                }
+                2 => content.push_str(&format!("<{}> Tj T*\n", hex(&text, 2))),
+                _ => content.push_str(&format!("/F2 11 Tf <{}> Tj T* /F1 11 Tf\n", hex(&text, 4))),
+            }
+        }
+        content.push_str("ET");
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes()).ok();
+        let data = encoder.finish().unwrap_or_default();
+
+        writer.object(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> \
+                 /Contents {} 0 R >>",
+                7 + page * 2
+            )
+            .as_bytes(),
+        );
+        let mut stream = format!("<< /Length {} /Filter /FlateDecode >>\nstream\n", data.len()).into_bytes();
+        stream.extend_from_slice(&data);
+        stream.extend_from_slice(b"\nendstream");
+        writer.object(&stream);
+    }
+    writer.finish_classic(None)
+}
+
+/// How `build_protected_pdf` encrypts its strings and streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PdfCipher {
+    /// RC4 with a 128-bit key (security handler revision 3).
+    Rc4,
+    /// AES-128 crypt filters (security handler revision 4).
+    Aes128,
+}
+
+/// The `/Producer` of `build_protected_pdf`, readable only once decrypted.
+pub const PROTECTED_PDF_PRODUCER: &str = "Fixture Writer 1.0";
+
+/// Builds a password-protected PDF of `pages` A4 pages.
+///
+/// # Arguments
+///
+/// * `pages` - Number of pages, each showing `Page N of the protected document`
+/// * `user_password` - Password that opens the document (may be empty)
+/// * `owner_password` - Password that also lifts the permission restrictions
+/// * `cipher` - How strings and streams are encrypted
+///
+/// The content streams and the `/Producer` string (`PROTECTED_PDF_PRODUCER`)
+/// are encrypted with the standard security handler; the page tree is in the
+/// clear, as in every encrypted PDF. Each page has 6 words.
+pub fn build_protected_pdf(pages: usize, user_password: &str, owner_password: &str, cipher: PdfCipher) -> Vec<u8> {
+    use crate::pdf_crypt::{pad_password, rc4, PASSWORD_PADDING};
+    use aes::cipher::{BlockEncrypt, KeyInit};
+    use md5::{Digest, Md5};
+
+    let xor = |key: &[u8], round: u8| key.iter().map(|byte| byte ^ round).collect::<Vec<u8>>();
+    let id = [0x01u8; 16];
+    let permissions: i32 = -3904;
+    // `/O`: the padded user password, encrypted with a key from the owner password
+    let mut owner_hash = Md5::digest(pad_password(owner_password)).to_vec();
+    for _ in 0..50 {
+        owner_hash = Md5::digest(&owner_hash).to_vec();
+    }
+    let owner_key = &owner_hash[..16];
+    let owner = (0..=19u8).fold(pad_password(user_password).to_vec(), |data, round| rc4(&xor(owner_key, round), &data));
+    // the file key, from the user password
+    let mut md5 = Md5::new();
+    md5.update(pad_password(user_password));
+    md5.update(&owner);
+    md5.update(permissions.to_le_bytes());
+    md5.update(id);
+    let mut key = md5.finalize().to_vec();
+    for _ in 0..50 {
+        key = Md5::digest(&key).to_vec();
+    }
+    // `/U`: the hash of the padding and the ID, encrypted with the file key
+    let mut md5 = Md5::new();
+    md5.update(PASSWORD_PADDING);
+    md5.update(id);
+    let mut user = (0..=19u8).fold(md5.finalize().to_vec(), |data, round| rc4(&xor(&key, round), &data));
+    user.extend_from_slice(&[0; 16]);
+
+    let encrypt = |object: usize, data: &[u8]| {
+        let mut md5 = Md5::new();
+        md5.update(&key);
+        md5.update(&(object as u32).to_le_bytes()[..3]);
+        md5.update([0, 0]);
+        if cipher == PdfCipher::Aes128 {
+            md5.update(b"sAlT");
+        }
+        let object_key = md5.finalize();
+        match cipher {
+            PdfCipher::Rc4 => rc4(&object_key, data),
+            PdfCipher::Aes128 => {
+                let aes = aes::Aes128::new(&object_key);
+                let padding = 16 - data.len() % 16;
+                let mut padded = data.to_vec();
+                padded.resize(data.len() + padding, padding as u8);
+                // a fixed IV keeps the output deterministic
+                let mut out = vec![object as u8; 16];
+                for block in padded.chunks_exact(16) {
+                    let previous = &out[out.len() - 16..];
+                    let mut chained = *aes::Block::from_slice(block);
+                    chained.iter_mut().zip(previous).for_each(|(byte, chain)| *byte ^= chain);
+                    aes.encrypt_block(&mut chained);
+                    out.extend_from_slice(&chained);
+                }
+                out
+            }
+        }
+    };
+    let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>();
+
+    let mut writer = PdfWriter::new();
+    writer.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages).as_bytes());
+    for i in 0..pages {
+        writer.object(
+            format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents {} 0 R >>", 4 + i * 2)
+                .as_bytes(),
+        );
+        let content = format!("BT /F1 12 Tf 72 720 Td (Page {} of the protected document) Tj ET", i + 1);
+        let data = encrypt(4 + i * 2, content.as_bytes());
+        let mut body = format!("<< /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(&data);
+        body.extend_from_slice(b"\nendstream");
+        writer.object(&body);
+    }
+    let info = 3 + pages * 2;
+    let producer = encrypt(info, PROTECTED_PDF_PRODUCER.as_bytes());
+    writer.info = Some(writer.object(format!("<< /Producer <{}> >>", hex(&producer)).as_bytes()));
+    let dict = match cipher {
+        PdfCipher::Rc4 => "/V 2 /R 3 /Length 128".to_string(),
+        PdfCipher::Aes128 => {
+            "/V 4 /R 4 /Length 128 /CF << /StdCF << /CFM /AESV2 /Length 16 >> >> /StmF /StdCF /StrF /StdCF".to_string()
+        }
+    };
+    let encryption = writer.object(
+        format!("<< /Filter /Standard {} /P {} /O <{}> /U <{}> >>", dict, permissions, hex(&owner), hex(&user))
+            .as_bytes(),
+    );
+    writer.finish_classic(Some(encryption))
+}
+
+/// Writes numbered PDF objects while recording their offsets.
+struct PdfWriter {
+    out: Vec<u8>,
+    offsets: Vec<usize>,
+    /// The document information dictionary named in the trailer, if any.
+    info: Option<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self { out: b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec(), offsets: Vec::new(), info: None }
+    }
+
+    /// Appends the next object (ids start at 1) and returns its id.
+    fn object(&mut self, body: &[u8]) -> usize {
+        self.offsets.push(self.out.len());
+        let id = self.offsets.len();
+        self.out.extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+        self.out.extend_from_slice(body);
+        self.out.extend_from_slice(b"\nendobj\n");
+        id
+    }
+
+    /// Trailer entries shared by both cross-reference styles.
+    fn trailer_entries(&self, size: usize, encrypt: Option<usize>) -> String {
+        let mut entries = format!("/Size {} /Root 1 0 R /ID [<{1}> <{1}>]", size, "01".repeat(16));
+        if let Some(info) = self.info {
+            entries.push_str(&format!(" /Info {} 0 R", info));
+        }
+        if let Some(encrypt) = encrypt {
+            entries.push_str(&format!(" /Encrypt {} 0 R", encrypt));
+        }
+        entries
+    }
+
+    /// Finishes the file with a classic `xref` table and trailer.
+    fn finish_classic(mut self, encrypt: Option<usize>) -> Vec<u8> {
+        let xref = self.out.len();
+        let size = self.offsets.len() + 1;
+        self.out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", size).as_bytes());
+        for offset in &self.offsets {
+            self.out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        let trailer = self.trailer_entries(size, encrypt);
+        self.out.extend_from_slice(format!("trailer\n<< {} >>\nstartxref\n{}\n%%EOF\n", trailer, xref).as_bytes());
+        self.out
+    }
+
+    /// Finishes the file with an uncompressed cross-reference stream.
+    fn finish_xref_stream(mut self, encrypt: Option<usize>) -> Vec<u8> {
+        let xref = self.out.len();
+        self.offsets.push(xref);
+        let size = self.offsets.len() + 1;
+        // W [1 4 2]: type, offset, generation
+        let mut data = vec![0u8, 0, 0, 0, 0, 0xFF, 0xFF];
+        for offset in &self.offsets {
+            data.push(1);
+            data.extend_from_slice(&(*offset as u32).to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+        }
+        let trailer = self.trailer_entries(size, encrypt);
+        self.out.extend_from_slice(
+            format!("{} 0 obj\n<< /Type /XRef {} /W [1 4 2] /Length {} >>\nstream\n", size - 1, trailer, data.len())
+                .as_bytes(),
+        );
+        self.out.extend_from_slice(&data);
+        self.out.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref).as_bytes());
+        self.out
+    }
+}
+
+/// Builds a DOCX of `paragraphs` paragraphs.
+///
+/// # Arguments
+///
+/// * `paragraphs` - Number of body paragraphs, each one sentence long
+/// * `page_breaks` - Number of paragraphs (from the second on) that start with
+///   a page break; capped at `paragraphs - 1`
+/// * `metadata_pages` - `<Pages>` written to `docProps/app.xml`, which is
+///   stored before `word/document.xml`; `None` leaves out `app.xml`, so the
+///   estimate comes from the content
+pub fn build_docx(paragraphs: usize, page_breaks: usize, metadata_pages: Option<usize>) -> Vec<u8> {
+    write_docx(paragraphs, page_breaks, metadata_pages, FIXTURE_APPLICATION)
+}
+
+/// Builds a DOCX like `build_docx`, with `<Pages>` set and `Google Docs` as
+/// the `Application` of `docProps/app.xml`.
+pub fn build_google_docx(paragraphs: usize, page_breaks: usize, metadata_pages: usize) -> Vec<u8> {
+    write_docx(paragraphs, page_breaks, Some(metadata_pages), "Google Docs")
+}
+
+/// Builds a DOCX like `build_docx`, with `<Pages>` set and `application` as
+/// the `Application` of `docProps/app.xml` (e.g. `python-docx`).
+pub fn build_docx_written_by(application: &str, paragraphs: usize, metadata_pages: usize) -> Vec<u8> {
+    write_docx(paragraphs, 0, Some(metadata_pages), application)
+}
+
+/// Builds a DOCX as written by mail-merge and conversion tools: a cover
+/// paragraph, then one `<w:altChunk>` per entry of `chunks`.
+///
+/// Each entry is a part name in `word/` (its extension sets the content
+/// type) and the part's contents; the chunks are referenced as `rIdChunk1` …
+/// in `word/_rels/document.xml.rels`. There is no `docProps/app.xml`, so
+/// the estimate comes from the content.
+pub fn build_alt_chunk_docx(chunks: &[(&str, &str)]) -> Vec<u8> {
+    let references: String =
+        (1..=chunks.len()).map(|n| format!(r#"<w:altChunk r:id="rIdChunk{}"/>"#, n)).collect();
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><w:body>"#,
+            r#"<w:p><w:r><w:t>Cover letter</w:t></w:r></w:p>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:body></w:document>"#,
+        ),
+        references
+    );
+    let rels: String = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, (part, _))| {
+            format!(r#"<Relationship Id="rIdChunk{}" Type="{}/aFChunk" Target="{}"/>"#, i + 1, RELATIONSHIP_TYPES, part)
+        })
+        .collect();
+    let part_names: Vec<String> = chunks.iter().map(|(part, _)| format!("/word/{}", part)).collect();
+    let overrides: Vec<(&str, &str)> = part_names
+        .iter()
+        .map(|name| {
+            let kind = match name.rsplit_once('.').map_or("", |(_, ext)| ext) {
+                "htm" | "html" => "text/html",
+                "mht" | "mhtml" => "message/rfc822",
+                "txt" => "text/plain",
+                "rtf" => "application/rtf",
+                _ => "application/octet-stream",
+            };
+            (name.as_str(), kind)
+        })
+        .collect();
+    let mut parts = vec![
+        ("[Content_Types].xml", content_types(MAIN_DOCUMENT_TYPE, "/word/document.xml", &overrides)),
+        ("_rels/.rels", package_rels("word/document.xml")),
+        ("word/document.xml", document),
+        ("word/_rels/document.xml.rels", relationships(&rels)),
+    ];
+    parts.extend(part_names.iter().zip(chunks).map(|(name, (_, contents))| (&name[1..], contents.to_string())));
+    write_package(&parts)
+}
+
+/// Builds a DOCX whose body is one paragraph followed by an OLE object
+/// (`<w:object>` holding an `<o:OLEObject>`) per entry.
+///
+/// Each entry is a part name in `word/embeddings/` and the part's contents;
+/// the objects are referenced as `rIdObject1` … in
+/// `word/_rels/document.xml.rels`. There is no `docProps/app.xml`, so the
+/// estimate comes from the content.
+pub fn build_embedded_objects_docx(objects: &[(&str, &[u8])]) -> Vec<u8> {
+    let references: String = (1..=objects.len())
+        .map(|n| {
+            format!(
+                concat!(
+                    r#"<w:p><w:r><w:object><v:shape id="_x0000_i{0}" style="width:400pt;height:300pt"/>"#,
+                    r#"<o:OLEObject Type="Embed" ShapeID="_x0000_i{0}" DrawAspect="Content" r:id="rIdObject{0}"/>"#,
+                    r#"</w:object></w:r></w:p>"#,
+                ),
+                n
+            )
+        })
+        .collect();
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" "#,
+            r#"xmlns:v="urn:schemas-microsoft-com:vml" xmlns:o="urn:schemas-microsoft-com:office:office"><w:body>"#,
+            r#"<w:p><w:r><w:t>Engineering specification</w:t></w:r></w:p>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:body></w:document>"#,
+        ),
+        references
+    );
+    let rels: String = objects
+        .iter()
+        .enumerate()
+        .map(|(i, (part, _))| {
+            format!(
+                r#"<Relationship Id="rIdObject{}" Type="{}/oleObject" Target="embeddings/{}"/>"#,
+                i + 1,
+                RELATIONSHIP_TYPES,
+                part
+            )
+        })
+        .collect();
+    let part_names: Vec<String> = objects.iter().map(|(part, _)| format!("word/embeddings/{}", part)).collect();
+    let package = [
+        ("[Content_Types].xml".to_string(), content_types(MAIN_DOCUMENT_TYPE, "/word/document.xml", &[])),
+        ("_rels/.rels".to_string(), package_rels("word/document.xml")),
+        ("word/document.xml".to_string(), document),
+        ("word/_rels/document.xml.rels".to_string(), relationships(&rels)),
+    ];
+    let entries: Vec<(&str, &[u8])> = package
+        .iter()
+        .map(|(part, contents)| (part.as_str(), contents.as_bytes()))
+        .chain(part_names.iter().zip(objects).map(|(name, (_, contents))| (name.as_str(), *contents)))
+        .collect();
+    build_zip(&entries)
+}
+
+/// Builds a DOCX whose body is one paragraph with a hyperlink, then one
+/// paragraph per entry of `images` holding a picture inserted with "Link to
+/// File" (`<a:blip r:link>`), the entry being the picture's path.
+///
+/// The hyperlink and the pictures are `TargetMode="External"` relationships
+/// in `word/_rels/document.xml.rels`. There is no `docProps/app.xml`.
+pub fn build_linked_images_docx(images: &[&str]) -> Vec<u8> {
+    let pictures: String = (1..=images.len())
+        .map(|n| {
+            format!(
+                concat!(
+                    r#"<w:p><w:r><w:drawing><wp:inline><a:graphic><a:graphicData><pic:pic><pic:blipFill>"#,
+                    r#"<a:blip r:link="rIdImage{}"/></pic:blipFill></pic:pic></a:graphicData></a:graphic>"#,
+                    r#"</wp:inline></w:drawing></w:r></w:p>"#,
+                ),
+                n
+            )
+        })
+        .collect();
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" "#,
+            r#"xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing" "#,
+            r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" "#,
+            r#"xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture"><w:body>"#,
+            r#"<w:p><w:hyperlink r:id="rIdLink"><w:r><w:t>Site plan</w:t></w:r></w:hyperlink></w:p>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:body></w:document>"#,
+        ),
+        pictures
+    );
+    let mut rels = format!(
+        r#"<Relationship Id="rIdLink" Type="{}/hyperlink" Target="https://example.com/plan" TargetMode="External"/>"#,
+        RELATIONSHIP_TYPES
+    );
+    for (i, image) in images.iter().enumerate() {
+        rels.push_str(&format!(
+            r#"<Relationship Id="rIdImage{}" Type="{}/image" Target="{}" TargetMode="External"/>"#,
+            i + 1,
+            RELATIONSHIP_TYPES,
+            image
+        ));
+    }
+    write_package(&[
+        ("[Content_Types].xml", content_types(MAIN_DOCUMENT_TYPE, "/word/document.xml", &[])),
+        ("_rels/.rels", package_rels("word/document.xml")),
+        ("word/document.xml", document),
+        ("word/_rels/document.xml.rels", relationships(&rels)),
+    ])
+}
+
+/// Page count of `build_equations_docx(25, 75, 20)` when each paragraph of
+/// text takes two lines and each display equation four (a fraction and the
+/// space around it), 50 lines to the page.
+pub const EQUATIONS_DOCX_PAGES: usize = 7;
+
+/// Builds a DOCX as pandoc converts a LaTeX paper: `text_paragraphs`
+/// paragraphs of prose, the first `inline_equations` of them ending in an
+/// inline `<m:oMath>`, then `display_equations` paragraphs each holding a
+/// fraction in an `<m:oMathPara>`.
+///
+/// The first paragraph also holds a text box as `<mc:AlternateContent>`: the
+/// `<mc:Choice>` branch (`wps`) and the VML `<mc:Fallback>` both contain the
+/// caption "Figure 1". There is no `docProps/app.xml`, so the estimate comes
+/// from the content.
+pub fn build_equations_docx(text_paragraphs: usize, display_equations: usize, inline_equations: usize) -> Vec<u8> {
+    const MATH_RUN: &str = "<m:r><m:t>x</m:t></m:r>";
+    let text_box = |branch: &str| {
+        format!(
+            "<w:txbxContent><w:p><w:r><w:t>Figure 1</w:t></w:r></w:p></w:txbxContent>{}",
+            if branch == "Choice" { "" } else { "<v:textbox/>" }
+        )
+    };
+    let alternate = format!(
+        concat!(
+            r#"<w:r><mc:AlternateContent><mc:Choice Requires="wps"><w:drawing>{}</w:drawing></mc:Choice>"#,
+            r#"<mc:Fallback><w:pict>{}</w:pict></mc:Fallback></mc:AlternateContent></w:r>"#,
+        ),
+        text_box("Choice"),
+        text_box("Fallback")
+    );
+    let mut body = String::new();
+    for i in 0..text_paragraphs {
+        body.push_str("<w:p>");
+        if i == 0 {
+            body.push_str(&alternate);
+        }
+        body.push_str("<w:r><w:t>The derivation below follows from the conservation law.</w:t></w:r>");
+        if i < inline_equations {
+            body.push_str(&format!("<m:oMath>{}</m:oMath>", MATH_RUN));
+        }
+        body.push_str("</w:p>");
+    }
+    for _ in 0..display_equations {
+        body.push_str(&format!(
+            concat!(
+                r#"<w:p><m:oMathPara><m:oMathParaPr><m:jc m:val="center"/></m:oMathParaPr>"#,
+                "<m:oMath><m:f><m:num>{0}</m:num><m:den>{0}</m:den></m:f></m:oMath></m:oMathPara></w:p>",
+            ),
+            MATH_RUN
+        ));
+    }
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+            r#"xmlns:m="http://schemas.openxmlformats.org/officeDocument/2006/math" "#,
+            r#"xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" "#,
+            r#"xmlns:v="urn:schemas-microsoft-com:vml" mc:Ignorable="w14"><w:body>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:body></w:document>"#,
+        ),
+        body
+    );
+    write_package(&[
+        ("[Content_Types].xml", content_types(MAIN_DOCUMENT_TYPE, "/word/document.xml", &[])),
+        ("_rels/.rels", package_rels("word/document.xml")),
+        ("word/document.xml", document),
+    ])
+}
+
+/// The paragraph each page of `build_rendered_breaks_docx` starts in, as
+/// numbered by python-docx's `Document.paragraphs`.
+pub const RENDERED_BREAKS_PAGE_STARTS: [usize; 4] = [0, 2, 3, 4];
+
+/// Builds a DOCX whose body paragraphs (numbered from 0, as python-docx does)
+/// hold Word's `<w:lastRenderedPageBreak>` markers:
+///
+/// - paragraphs 0 and 1, then a table whose second row starts page 2
+/// - paragraph 2, then paragraph 3, which runs onto page 3
+/// - a content control (`<w:sdt>`) holding a paragraph python-docx doesn't list
+/// - paragraph 4, which starts page 4
+///
+/// `<Pages>` in `docProps/app.xml` is 4.
+pub fn build_rendered_breaks_docx() -> Vec<u8> {
+    const BREAK: &str = "<w:lastRenderedPageBreak/>";
+    let paragraph = |runs: &str| format!("<w:p>{}</w:p>", runs);
+    let run = |text: &str| format!("<w:r><w:t>{}</w:t></w:r>", text);
+    let cell = |runs: &str| format!("<w:tc>{}</w:tc>", paragraph(runs));
+    let body = [
+        paragraph(&run("Quarterly report")),
+        paragraph(&run("The figures below cover the first quarter.")),
+        format!(
+            "<w:tbl><w:tr>{}{}</w:tr><w:tr>{}{}</w:tr></w:tbl>",
+            cell(&run("Region")),
+            cell(&run("Sales")),
+            cell(&format!("<w:r>{}<w:t>North</w:t></w:r>", BREAK)),
+            cell(&run("1200")),
+        ),
+        paragraph(&run("Sales rose in every region.")),
+        paragraph(&format!(
+            "{}<w:r>{}<w:t>and the second half of this paragraph is printed on the next page.</w:t></w:r>",
+            run("The first half of this long paragraph fits on page 2,"),
+            BREAK
+        )),
+        format!("<w:sdt><w:sdtContent>{}</w:sdtContent></w:sdt>", paragraph(&run("Prepared by the finance team"))),
+        paragraph(&format!("<w:r>{}<w:t>Appendix</w:t></w:r>", BREAK)),
+    ]
+    .concat();
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:body></w:document>"#,
+        ),
+        body
+    );
+    write_package(&[
+        ("[Content_Types].xml", content_types(MAIN_DOCUMENT_TYPE, "/word/document.xml", &[])),
+        ("_rels/.rels", package_rels("word/document.xml")),
+        ("word/document.xml", document),
+        ("docProps/app.xml", app_properties(FIXTURE_APPLICATION, "<Pages>4</Pages>")),
+    ])
+}
+
+/// Builds the Drive API metadata of a Google Workspace file, as downloaded in
+/// place of its content.
+///
+/// # Arguments
+///
+/// * `app` - The `mimeType` suffix: `"document"`, `"spreadsheet"`, or `"presentation"`
+pub fn build_drive_stub(app: &str) -> Vec<u8> {
+    format!(
+        concat!(
+            r#"{{"kind": "drive#file", "id": "1FixtureFileId", "name": "Fixture", "#,
+            r#""mimeType": "application/vnd.google-apps.{}"}}"#,
+        ),
+        app
+    )
+    .into_bytes()
+}
+
+/// Builds the interstitial page Google Drive serves for a Google Docs
+/// document to a client that isn't signed in, redirecting to the sign-in.
+pub fn build_drive_sign_in_page() -> Vec<u8> {
+    concat!(
+        "<!DOCTYPE html><html><head><title>Google Docs</title>",
+        r#"<meta http-equiv="refresh" content="0; url=https://accounts.google.com/ServiceLogin?service=wise"#,
+        r#"&amp;continue=https%3A%2F%2Fdocs.google.com%2Fdocument%2Fd%2F1FixtureFileId%2Fedit">"#,
+        "</head><body>Redirecting to sign in...</body></html>",
+    )
+    .as_bytes()
+    .to_vec()
+}
+
+/// Writes a DOCX package for `build_docx`, `application` being the producer in `docProps/app.xml`.
+fn write_docx(paragraphs: usize, page_breaks: usize, metadata_pages: Option<usize>, application: &str) -> Vec<u8> {
+    let mut body = String::new();
+    for i in 0..paragraphs {
+        body.push_str("<w:p><w:r>");
+        if i > 0 && i <= page_breaks {
+            body.push_str(r#"<w:br w:type="page"/>"#);
+        }
+        body.push_str(&format!("<w:t>Paragraph {} of the fixture document.</w:t></w:r></w:p>", i + 1));
+    }
+    let document = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{}"#,
+            r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:body></w:document>"#,
+        ),
+        body
+    );
+    let mut parts = vec![
+        ("[Content_Types].xml", content_types(MAIN_DOCUMENT_TYPE, "/word/document.xml", &[])),
+        ("_rels/.rels", package_rels("word/document.xml")),
+    ];
+    if let Some(pages) = metadata_pages {
+        parts.push(("docProps/app.xml", app_properties(application, &format!("<Pages>{}</Pages>", pages))));
+    }
+    parts.push(("word/document.xml", document));
+    write_package(&parts)
+}
+
+/// Builds an XLSX with one worksheet per `(rows, cols)` entry, filled with numbers.
+pub fn build_xlsx(sheets: &[(usize, usize)]) -> Vec<u8> {
+    let worksheets: Vec<String> = sheets.iter().map(|(rows, cols)| sheet_data(&number_rows(*rows, *cols))).collect();
+    write_xlsx(&worksheets, false, &[])
+}
+
+/// Builds an XLSX like `build_xlsx` whose sheets at the (0-based) indices in
+/// `hidden` are hidden (`state="hidden"` in `xl/workbook.xml`).
+pub fn build_hidden_sheets_xlsx(sheets: &[(usize, usize)], hidden: &[usize]) -> Vec<u8> {
+    let worksheets: Vec<String> = sheets.iter().map(|(rows, cols)| sheet_data(&number_rows(*rows, *cols))).collect();
+    write_xlsx(&worksheets, false, hidden)
+}
+
+/// Builds an XLSX like `build_xlsx`, as left after its tabs were moved into reverse order.
+///
+/// Tab `k` of `n` is still named `Sheet{k}` and holds the `k`-th entry of
+/// `sheets`, but is stored in `xl/worksheets/sheet{n-k+1}.xml` with `sheetId`
+/// `n-k+1`. The worksheet entries of the ZIP follow the part numbers, so the
+/// last tab comes first.
+pub fn build_reordered_xlsx(sheets: &[(usize, usize)]) -> Vec<u8> {
+    let worksheets: Vec<String> = sheets.iter().map(|(rows, cols)| sheet_data(&number_rows(*rows, *cols))).collect();
+    write_xlsx(&worksheets, true, &[])
+}
+
+/// Builds an XLSX like `build_xlsx` whose formulas read another workbook:
+/// `xl/externalLinks/externalLink1.xml` caches values from `workbook`, which
+/// its relationships part names as an external `externalLinkPath` target.
+pub fn build_external_link_xlsx(sheets: &[(usize, usize)], workbook: &str) -> Vec<u8> {
+    let link = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<externalLink xmlns="{}" xmlns:r="{}"><externalBook r:id="rId1"><sheetNames>"#,
+            r#"<sheetName val="Totals"/></sheetNames></externalBook></externalLink>"#,
+        ),
+        SPREADSHEET_NAMESPACE, RELATIONSHIP_TYPES
+    );
+    let rels = relationships(&format!(
+        r#"<Relationship Id="rId1" Type="{}/externalLinkPath" Target="{}" TargetMode="External"/>"#,
+        RELATIONSHIP_TYPES, workbook
+    ));
+    let package = with_part(&build_xlsx(sheets), "xl/externalLinks/externalLink1.xml", &link);
+    with_part(&package, "xl/externalLinks/_rels/externalLink1.xml.rels", &rels)
+}
+
+/// Builds an XLSX whose first sheet holds `rows` × 2 numbers and whose second
+/// sheet is formatting only: `rows` rows of cells with a style and no value,
+/// as left behind when cells are formatted before their content is entered.
+pub fn build_formatting_only_xlsx(rows: usize) -> Vec<u8> {
+    let formatted: String = (1..=rows)
+        .map(|row| format!(r#"<row r="{0}"><c r="A{0}" s="1"/><c r="B{0}" s="1"/></row>"#, row))
+        .collect();
+    write_xlsx(&[sheet_data(&number_rows(rows, 2)), sheet_data(&formatted)], false, &[])
+}
+
+/// Builds an XLSX with one worksheet whose `<sheetData>` holds `rows` (`<row>`
+/// elements, such as `number_rows` writes), with print settings around it:
+/// `before` the cell data (`<sheetPr>`, `<sheetFormatPr>`) and `after` it
+/// (`<pageMargins>`, `<pageSetup>`).
+pub fn build_print_layout_xlsx(before: &str, rows: &str, after: &str) -> Vec<u8> {
+    write_xlsx(&[format!("{}{}{}", before, sheet_data(rows), after)], false, &[])
+}
+
+/// The `<sheetData>` content of `rows` × `cols` numbers.
+pub fn number_rows(rows: usize, cols: usize) -> String {
+    let mut data = String::new();
+    for row in 1..=rows {
+        data.push_str(&format!(r#"<row r="{}">"#, row));
+        for col in 0..cols {
+            data.push_str(&format!(r#"<c r="{}{}"><v>{}</v></c>"#, column_name(col), row, row * (col + 1)));
+        }
+        data.push_str("</row>");
+    }
+    data
+}
+
+fn sheet_data(rows: &str) -> String {
+    format!("<sheetData>{}</sheetData>", rows)
+}
+
+/// Writes an XLSX package with one worksheet (`Sheet1`, `Sheet2`, ...) per entry of `worksheets`,
+/// the content of its `<worksheet>` element, stored in reverse order when `reversed` (see
+/// `build_reordered_xlsx`). The sheets at the indices in `hidden` are hidden.
+fn write_xlsx(worksheets: &[String], reversed: bool, hidden: &[usize]) -> Vec<u8> {
+    let mut sheet_list = String::new();
+    let mut rels = String::new();
+    let mut overrides = Vec::new();
+    let mut sheet_parts = Vec::new();
+    for (i, content) in worksheets.iter().enumerate() {
+        // `n` numbers the part, its relationship, and its sheetId
+        let n = if reversed { worksheets.len() - i } else { i + 1 };
+        let state = if hidden.contains(&i) { r#" state="hidden""# } else { "" };
+        sheet_list.push_str(&format!(r#"<sheet name="Sheet{}" sheetId="{1}"{2} r:id="rId{1}"/>"#, i + 1, n, state));
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{0}" Type="{1}/worksheet" Target="worksheets/sheet{0}.xml"/>"#,
+            n, RELATIONSHIP_TYPES
+        ));
+        overrides.push((format!("/xl/worksheets/sheet{}.xml", n), WORKSHEET_TYPE));
+        sheet_parts.push((
+            format!("xl/worksheets/sheet{}.xml", n),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="{}">{}</worksheet>"#,
+                SPREADSHEET_NAMESPACE, content
+            ),
+        ));
+    }
+    let workbook = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="{}" xmlns:r="{}"><sheets>{}</sheets></workbook>"#,
+        SPREADSHEET_NAMESPACE, RELATIONSHIP_TYPES, sheet_list
+    );
+    if reversed {
+        sheet_parts.reverse();
+    }
+    let overrides: Vec<(&str, &str)> = overrides.iter().map(|(part, kind)| (part.as_str(), *kind)).collect();
+    let mut parts = vec![
+        ("[Content_Types].xml", content_types(WORKBOOK_TYPE, "/xl/workbook.xml", &overrides)),
+        ("_rels/.rels", package_rels("xl/workbook.xml")),
+        ("xl/workbook.xml", workbook),
+        ("xl/_rels/workbook.xml.rels", relationships(&rels)),
+    ];
+    parts.extend(sheet_parts.iter().map(|(part, xml)| (part.as_str(), xml.clone())));
+    write_package(&parts)
+}
+
+/// Builds a PPTX of `slides` slides, the last `hidden` of them hidden (`show="0"`).
+///
+/// `docProps/app.xml` lists every slide in `<Slides>` and the hidden ones in
+/// `<HiddenSlides>`, as PowerPoint does. Slides are 4:3 (`<p:sldSz>` without a `type`).
+pub fn build_pptx(slides: usize, hidden: usize) -> Vec<u8> {
+    write_pptx(slides, hidden, r#"<p:sldSz cx="9144000" cy="6858000"/>"#)
+}
+
+/// Builds a PPTX of `slides` slides of the given `<p:sldSz>` size, in EMUs, and `type`.
+///
+/// For example:
+///
+/// - 16:9: `(9144000, 5143500, Some("screen16x9"))`
+/// - A4 portrait: `(6858000, 9906000, Some("A4"))`
+/// - a 48" × 36" poster: `(43891200, 32918400, Some("custom"))`
+pub fn build_sized_pptx(slides: usize, cx: u64, cy: u64, size_type: Option<&str>) -> Vec<u8> {
+    let size_type = size_type.map(|t| format!(r#" type="{}""#, t)).unwrap_or_default();
+    write_pptx(slides, 0, &format!(r#"<p:sldSz cx="{}" cy="{}"{}/>"#, cx, cy, size_type))
+}
+
+/// Writes a PPTX package with `slide_size` as its `<p:sldSz>` element.
+/// Builds a PPTX with one slide per entry of `notes`, each holding the text
+/// `Slide N`, with a notes slide of the given speaker notes where there are some.
+///
+/// Paragraphs of a note are separated by newlines. As PowerPoint writes them,
+/// notes slides also hold a slide image placeholder and a slide number
+/// placeholder (`N`); their DrawingML elements use the prefix `d:` rather
+/// than `a:`, as other writers do.
+pub fn build_notes_pptx(notes: &[Option<&str>]) -> Vec<u8> {
+    let mut pptx = write_pptx(notes.len(), 0, r#"<p:sldSz cx="9144000" cy="5143500" type="screen16x9"/>"#);
+    for (i, note) in notes.iter().enumerate() {
+        let Some(note) = note else { continue };
+        let n = i + 1;
+        let rel = format!(
+            r#"<Relationship Id="rId1" Type="{}/notesSlide" Target="../notesSlides/notesSlide{}.xml"/>"#,
+            RELATIONSHIP_TYPES, n
+        );
+        pptx = with_part(&pptx, &format!("ppt/slides/_rels/slide{}.xml.rels", n), &relationships(&rel));
+        let paragraphs: String =
+            note.lines().map(|line| format!("<d:p><d:r><d:t>{}</d:t></d:r></d:p>", line)).collect();
+        let shape = |placeholder: &str, text: &str| {
+            format!(
+                r#"<p:sp><p:nvSpPr><p:nvPr><p:ph {}/></p:nvPr></p:nvSpPr><p:txBody>{}</p:txBody></p:sp>"#,
+                placeholder, text
+            )
+        };
+        let notes_slide = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<p:notes xmlns:d="{}" xmlns:p="{}"><p:cSld><p:spTree>{}{}{}</p:spTree></p:cSld></p:notes>"#,
+            ),
+            DRAWING_NAMESPACE,
+            PRESENTATION_NAMESPACE,
+            r#"<p:sp><p:nvSpPr><p:nvPr><p:ph type="sldImg"/></p:nvPr></p:nvSpPr></p:sp>"#,
+            shape(r#"type="body" idx="1""#, &paragraphs),
+            shape(r#"type="sldNum" idx="5""#, &format!("<d:p><d:r><d:t>{}</d:t></d:r></d:p>", n)),
+        );
+        pptx = with_part(&pptx, &format!("ppt/notesSlides/notesSlide{}.xml", n), &notes_slide);
+    }
+    pptx
+}
+
+fn write_pptx(slides: usize, hidden: usize, slide_size: &str) -> Vec<u8> {
+    let hidden = hidden.min(slides);
+    let mut slide_ids = String::new();
+    let mut rels = String::new();
+    let mut overrides = Vec::new();
+    let mut slide_parts = Vec::new();
+    for i in 0..slides {
+        let n = i + 1;
+        slide_ids.push_str(&format!(r#"<p:sldId id="{}" r:id="rId{}"/>"#, 255 + n, n));
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{0}" Type="{1}/slide" Target="slides/slide{0}.xml"/>"#,
+            n, RELATIONSHIP_TYPES
+        ));
+        overrides.push((format!("/ppt/slides/slide{}.xml", n), SLIDE_TYPE));
+        let show = if i >= slides - hidden { r#" show="0""# } else { "" };
+        slide_parts.push((
+            format!("ppt/slides/slide{}.xml", n),
+            format!(
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                    r#"<p:sld xmlns:a="{}" xmlns:p="{}"{}><p:cSld><p:spTree><p:sp><p:txBody>"#,
+                    r#"<a:p><a:r><a:t>Slide {}</a:t></a:r></a:p></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#,
+                ),
+                DRAWING_NAMESPACE, PRESENTATION_NAMESPACE, show, n
+            ),
+        ));
+    }
+    let presentation = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<p:presentation xmlns:p="{}" xmlns:r="{}"><p:sldIdLst>{}</p:sldIdLst>"#,
+            r#"{}</p:presentation>"#,
+        ),
+        PRESENTATION_NAMESPACE, RELATIONSHIP_TYPES, slide_ids, slide_size
+    );
+    let overrides: Vec<(&str, &str)> = overrides.iter().map(|(part, kind)| (part.as_str(), *kind)).collect();
+    let counts = format!("<Slides>{}</Slides><HiddenSlides>{}</HiddenSlides>", slides, hidden);
+    let app = app_properties(FIXTURE_APPLICATION, &counts);
+    let mut parts = vec![
+        ("[Content_Types].xml", content_types(PRESENTATION_TYPE, "/ppt/presentation.xml", &overrides)),
+        ("_rels/.rels", package_rels("ppt/presentation.xml")),
+        ("docProps/app.xml", app),
+        ("ppt/presentation.xml", presentation),
+        ("ppt/_rels/presentation.xml.rels", relationships(&rels)),
+    ];
+    parts.extend(slide_parts.iter().map(|(part, xml)| (part.as_str(), xml.clone())));
+    write_package(&parts)
+}
+
+/// Builds the outline of a password-protected Office document: an OLE header
+/// followed by a directory sector naming the `EncryptedPackage` and
+/// `EncryptionInfo` streams, whose contents are left empty.
+pub fn build_encrypted_office_package() -> Vec<u8> {
+    let mut ole = vec![0u8; 1024];
+    ole[..8].copy_from_slice(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]);
+    for (i, name) in ["EncryptionInfo", "EncryptedPackage"].iter().enumerate() {
+        let entry = 512 + 128 * (i + 1);
+        let utf16: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        ole[entry..entry + utf16.len()].copy_from_slice(&utf16);
+        ole[entry + 64] = (utf16.len() + 2) as u8;
+        ole[entry + 66] = 2; // stream
+    }
+    ole
+}
+
+/// Builds the start of a PNG image of `width` × `height` pixels: its
+/// signature, `IHDR` chunk, and `IEND`, with no image data or resolution.
+pub fn png_header(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+    png.extend_from_slice(&width.to_be_bytes());
+    png.extend_from_slice(&height.to_be_bytes());
+    png.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png.extend_from_slice(&[0; 4]); // CRC, not checked
+    png.extend_from_slice(b"\x00\x00\x00\x00IEND\xae\x42\x60\x82");
+    png
+}
+
+/// Builds a CBZ comic of `pages` 1200 × 1800 px PNG pages, optionally with a
+/// `ComicInfo.xml` declaring `declared` pages.
+///
+/// The pages (`page1.png` … in a `Comic/` folder) are stored last to first,
+/// and the archive also holds a cover thumbnail and a `__MACOSX` resource
+/// fork, neither of which is a page.
+pub fn build_cbz(pages: usize, declared: Option<usize>) -> Vec<u8> {
+    let page = png_header(1200, 1800);
+    let thumb = png_header(120, 180);
+    let names: Vec<String> = (1..=pages).rev().map(|n| format!("Comic/page{}.png", n)).collect();
+    let info = declared.map(|n| format!("<ComicInfo><Series>Fixture</Series><PageCount>{}</PageCount></ComicInfo>", n));
+
+    let mut entries: Vec<(&str, &[u8])> = vec![("Comic/", b"")];
+    entries.extend(names.iter().map(|name| (name.as_str(), page.as_slice())));
+    entries.push(("Comic/cover-thumb.png", &thumb));
+    entries.push(("__MACOSX/Comic/._page1.png", b"\x00\x05\x16\x07"));
+    if let Some(info) = &info {
+        entries.push(("ComicInfo.xml", info.as_bytes()));
+    }
+    build_zip(&entries)
+}
+
+/// Builds a document package as saved by the iWork app `app` (`"Pages"`, `"Numbers"`, or `"Keynote"`).
+///
+/// The package holds placeholder IWA streams, the build version history
+/// naming `app`, `Properties.plist`, and a `preview.jpg` thumbnail, plus
+/// `QuickLook/Preview.pdf` holding `preview` when given.
+pub fn build_iwork(app: &str, preview: Option<&[u8]>) -> Vec<u8> {
+    let history = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\"><array>\
+         <string>{} 14.1 (7040.0.73)</string></array></plist>",
+        app
+    );
+    let properties = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\"><dict>\
+                      <key>isMultiPage</key><false/><key>revision</key><string>0::5D1B</string></dict></plist>";
+    let mut entries: Vec<(&str, &[u8])> = vec![
+        ("Index/Document.iwa", b"\x00\x1b\x00\x00\x19\x08\x01\x12\x15"),
+        ("Index/Metadata.iwa", b"\x00\x0c\x00\x00\x0a\x08\x02"),
+        ("Metadata/BuildVersionHistory.plist", history.as_bytes()),
+        ("Metadata/Properties.plist", properties.as_bytes()),
+        ("preview.jpg", b"\xFF\xD8\xFF\xE0\x00\x10JFIF\x00"),
+    ];
+    if let Some(preview) = preview {
+        entries.push(("QuickLook/Preview.pdf", preview));
+    }
+    build_zip(&entries)
+}
+
+/// Builds a PostScript document of `pages` A4 pages with DSC comments.
+///
+/// `%%Pages:` is in the header, or with `atend` deferred to the trailer. The
+/// second page (if any) embeds an EPS logo between `%%BeginDocument` and
+/// `%%EndDocument`, whose own page comments and `showpage` aren't the
+/// document's.
+pub fn build_postscript(pages: usize, atend: bool) -> Vec<u8> {
+    let declared = if atend { "(atend)".to_string() } else { pages.to_string() };
+    let mut ps = format!(
+        "%!PS-Adobe-3.0\n%%Creator: fixture\n%%Pages: {}\n%%DocumentMedia: A4 595 842 0 () ()\n\
+         %%BoundingBox: 0 0 595 842\n%%EndComments\n%%BeginProlog\n/F {{ findfont 12 scalefont setfont }} def\n\
+         %%EndProlog\n",
+        declared
+    );
+    for page in 1..=pages {
+        ps.push_str(&format!("%%Page: {} {}\n/Helvetica F 72 720 moveto (Page {}) show\n", page, page, page));
+        if page == 2 {
+            ps.push_str(
+                "%%BeginDocument: logo.eps\n%!PS-Adobe-3.0 EPSF-3.0\n%%Pages: 1\n%%BoundingBox: 0 0 50 50\n\
+                 %%Page: 1 1\n0 0 moveto 50 50 lineto stroke showpage\n%%EndDocument\n",
+            );
+        }
+        ps.push_str("showpage\n");
+    }
+    ps.push_str("%%Trailer\n");
+    if atend {
+        ps.push_str(&format!("%%Pages: {}\n", pages));
+    }
+    ps.push_str("%%EOF\n");
+    ps.into_bytes()
+}
+
+/// Builds a DOS EPS file: the binary header, an EPS illustration of
+/// `width` × `height` points, and a TIFF preview after it.
+///
+/// The preview holds a stray `%%Pages: 9`, which only the PostScript section's
+/// reader must not see.
+pub fn build_dos_eps(width: u32, height: u32) -> Vec<u8> {
+    let ps = format!(
+        "%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 {} {}\n%%EndComments\n\
+         newpath 0 0 moveto {} {} lineto stroke\n%%EOF\n",
+        width, height, width, height
+    );
+    let preview = b"II*\x00\x08\x00\x00\x00%%Pages: 9\n";
+    let mut eps = vec![0xC5, 0xD0, 0xD3, 0xC6];
+    let ps_start = 30u32;
+    let preview_start = ps_start + ps.len() as u32;
+    for field in [ps_start, ps.len() as u32, 0, 0, preview_start, preview.len() as u32] {
+        eps.extend_from_slice(&field.to_le_bytes());
+    }
+    eps.extend_from_slice(&[0xFF, 0xFF]);
+    eps.extend_from_slice(ps.as_bytes());
+    eps.extend_from_slice(preview);
+    eps
+}
+
+/// Builds an XPS (or, with `oxps`, OpenXPS) document of one page per
+/// `(width, height)` entry, in 1/96 inch.
+///
+/// The pages are listed by a single FixedDocument. One more FixedPage part,
+/// which no document references, is left in the package as printer drivers
+/// sometimes do.
+pub fn build_xps(page_sizes: &[(f64, f64)], oxps: bool) -> Vec<u8> {
+    let ns = if oxps { "http://schemas.openxps.org/oxps/v1.0" } else { "http://schemas.microsoft.com/xps/2005/06" };
+    let rels = format!(
+        "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+         <Relationship Id=\"R0\" Type=\"{}/fixedrepresentation\" Target=\"/FixedDocSeq.fdseq\"/></Relationships>",
+        ns
+    );
+    let sequence = format!(
+        "<FixedDocumentSequence xmlns=\"{}\"><DocumentReference Source=\"Documents/1/FixedDoc.fdoc\"/>\
+         </FixedDocumentSequence>",
+        ns
+    );
+    let contents: String =
+        (1..=page_sizes.len()).map(|n| format!("<PageContent Source=\"Pages/{}.fpage\"/>", n)).collect();
+    let document = format!("<FixedDocument xmlns=\"{}\">{}</FixedDocument>", ns, contents);
+    let pages: Vec<(String, String)> = page_sizes
+        .iter()
+        .chain([&(816.0, 1056.0)])
+        .enumerate()
+        .map(|(i, (width, height))| {
+            let page = format!(
+                "<FixedPage xmlns=\"{}\" Width=\"{}\" Height=\"{}\" xml:lang=\"en-US\">\
+                 <Path Data=\"M 96,96 L 480,96\" Stroke=\"#000000\"/></FixedPage>",
+                ns, width, height
+            );
+            (format!("Documents/1/Pages/{}.fpage", i + 1), page)
+        })
+        .collect();
+
+    let mut entries: Vec<(&str, &[u8])> = vec![
+        ("[Content_Types].xml", b"<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\"/>"),
+        ("_rels/.rels", rels.as_bytes()),
+        ("FixedDocSeq.fdseq", sequence.as_bytes()),
+        ("Documents/1/FixedDoc.fdoc", document.as_bytes()),
+    ];
+    entries.extend(pages.iter().map(|(name, page)| (name.as_str(), page.as_bytes())));
+    build_zip(&entries)
+}
+
+/// Builds a ZIP archive of `(path, contents)` entries, deflated, in the given order.
+///
+/// A path ending in `/` adds a directory entry; its contents are ignored.
+pub fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().last_modified_time(DateTime::default());
+    for (path, contents) in entries {
+        if path.ends_with('/') {
+            zip.add_directory(*path, options).ok();
+        } else if zip.start_file(*path, options).is_ok() {
+            zip.write_all(contents).ok();
+        }
+    }
+    zip.finish().map(Cursor::into_inner).unwrap_or_default()
+}
+
+/// Builds a ustar archive of `(path, contents)` regular files, in the given order.
+pub fn build_tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut tar = Vec::new();
+    for (path, contents) in entries {
+        let mut header = [0u8; 512];
+        header[..path.len()].copy_from_slice(path.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+        header[136..147].copy_from_slice(b"00000000000");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].copy_from_slice(b"        ");
+        let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(contents);
+        tar.resize(tar.len().div_ceil(512) * 512, 0);
+    }
+    tar.resize(tar.len() + 1024, 0);
+    tar
+}
+
+/// Compresses `bytes` with gzip `layers` times over, as `file.gz.gz` would be.
+pub fn gzip(bytes: &[u8], layers: usize) -> Vec<u8> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut out = bytes.to_vec();
+    for layer in 0..layers {
+        // compressed data doesn't shrink again; storing it keeps deep nesting small
+        let level = if layer == 0 { Compression::fast() } else { Compression::none() };
+        let mut encoder = GzEncoder::new(Vec::new(), level);
+        encoder.write_all(&out).ok();
+        out = encoder.finish().unwrap_or_default();
+    }
+    out
+}
+
+/// Pages `build_notebook(10)` fills when printed: each section is a heading,
+/// a paragraph, twelve lines of code, a table of eight lines, and a
+/// 6.4 × 4.8 in plot, close to a page of A4 between one-inch margins.
+pub const NOTEBOOK_PAGES: usize = 10;
+
+/// Builds a Jupyter notebook (nbformat 4.5) as saved after running an analysis.
+///
+/// Each of the `sections` is a Markdown cell (a heading and a paragraph) and
+/// a code cell of twelve lines whose outputs are a printed table (`stream`)
+/// and a PNG plot (`display_data` with the usual `<Figure ...>` text repr).
+/// The notebook ends with a raw cell and a code cell that raised an error
+/// with an ANSI-colored traceback. Cells carry execution counts and metadata.
+pub fn build_notebook(sections: usize) -> Vec<u8> {
+    use base64::Engine;
+    use serde_json::json;
+
+    let mut plot = png_header(640, 480);
+    plot.resize(6000, 0);
+    let plot = base64::engine::general_purpose::STANDARD.encode(&plot);
+    let mut cells = Vec::new();
+    for n in 1..=sections {
+        cells.push(json!({
+            "cell_type": "markdown",
+            "id": format!("md-{}", n),
+            "metadata": {},
+            "source": [
+                format!("## {}. Sales by region\n", n),
+                "\n",
+                "Monthly totals are grouped by region and compared with the previous year. \
+                 The plot below shows the trend, and the table lists the first rows of the summary.",
+            ],
+        }));
+        let code: Vec<String> = [
+            "import pandas as pd",
+            "import matplotlib.pyplot as plt",
+            "",
+            "sales = pd.read_csv(\"sales.csv\", parse_dates=[\"month\"])",
+            "summary = sales.groupby([\"region\", \"month\"])[\"total\"].sum().unstack(0)",
+            "print(summary.head(7))",
+            "",
+            "fig, ax = plt.subplots()",
+            "summary.plot(ax=ax)",
+            "ax.set_ylabel(\"Total (EUR)\")",
+            "ax.set_title(\"Sales by region\")",
+            "plt.show()",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, line)| if i < 11 { format!("{}\n", line) } else { line.to_string() })
+        .collect();
+        let table: Vec<String> = std::iter::once("region        north    south     west\n".to_string())
+            .chain((1..=7).map(|m| format!("2024-{:02}-01  {:>7}  {:>7}  {:>7}\n", m, 1200 + m * n, 980 + m, 1430 - m)))
+            .collect();
+        cells.push(json!({
+            "cell_type": "code",
+            "execution_count": n,
+            "id": format!("code-{}", n),
+            "metadata": {"scrolled": false},
+            "outputs": [
+                {"name": "stdout", "output_type": "stream", "text": table},
+                {
+                    "data": {"image/png": plot, "text/plain": ["<Figure size 640x480 with 1 Axes>"]},
+                    "metadata": {"needs_background": "light"},
+                    "output_type": "display_data",
+                },
+            ],
+            "source": code,
+        }));
+    }
+    cells.push(json!({"cell_type": "raw", "id": "raw-1", "metadata": {}, "source": ["\\newpage"]}));
+    cells.push(json!({
+        "cell_type": "code",
+        "execution_count": sections + 1,
+        "id": "error-1",
+        "metadata": {},
+        "outputs": [{
+            "ename": "KeyError",
+            "evalue": "'east'",
+            "output_type": "error",
+            "traceback": [
+                "\u{1b}[0;31m---------------------------------------------------------------------------\u{1b}[0m",
+                "\u{1b}[0;31mKeyError\u{1b}[0m                                  Traceback (most recent call last)",
+                "\u{1b}[0;31mKeyError\u{1b}[0m: 'east'",
+            ],
+        }],
+        "source": ["summary[\"east\"]"],
+    }));
+    let notebook = json!({
+        "cells": cells,
+        "metadata": {
+            "kernelspec": {"display_name": "Python 3", "language": "python", "name": "python3"},
+            "language_info": {"name": "python", "version": "3.12.2"},
+        },
+        "nbformat": 4,
+        "nbformat_minor": 5,
+    });
+    serde_json::to_vec_pretty(&notebook).unwrap_or_default()
+}
+
+/// Builds a vCard 3.0 file of `contacts` contacts, each with a name, a
+/// phone number, an email address, and a note folded over three lines.
+pub fn build_vcards(contacts: usize) -> Vec<u8> {
+    let mut vcf = String::new();
+    for i in 1..=contacts {
+        vcf.push_str(&format!(
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nUID:contact-{0}\r\nFN:Contact {0}\r\nN:{0};Contact;;;\r\n\
+             TEL;TYPE=WORK:+1 555 01{0:02}\r\nEMAIL:contact{0}@example.com\r\n\
+             NOTE:Met at the spring trade fair\\, interested in the\r\n  annual maintenance plan\r\n  \
+             and a quote for next year.\r\nEND:VCARD\r\n",
+            i
+        ));
+    }
+    vcf.into_bytes()
+}
+
+/// Builds an iCalendar file of `events` events, each with a summary, a
+/// start and end, a location, and a folded description, after a time zone.
+pub fn build_calendar(events: usize) -> Vec<u8> {
+    let mut ics = String::from(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Fixtures//Calendar//EN\r\n\
+         BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nEND:VTIMEZONE\r\n",
+    );
+    for i in 1..=events {
+        ics.push_str(&format!(
+            "BEGIN:VEVENT\r\nUID:event-{0}@example.com\r\nSUMMARY:Weekly review {0}\r\n\
+             DTSTART;TZID=Europe/Berlin:20261005T{1:02}0000\r\nDTEND;TZID=Europe/Berlin:20261005T{1:02}3000\r\n\
+             LOCATION:Room 4\r\nDESCRIPTION:Agenda: open items\\nbudget\r\n\t and hiring\r\nEND:VEVENT\r\n",
+            i,
+            8 + i % 10
+        ));
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics.into_bytes()
+}
+
+/// Builds an `.eml` message: `body` as quoted-printable `text/plain`, then one
+/// base64 part per `(filename, content_type, contents)` attachment.
+///
+/// An empty filename leaves the part unnamed. Without `closed` the message
+/// ends after the last attachment, with no closing boundary.
+pub fn build_email(body: &str, attachments: &[(&str, &str, &[u8])], closed: bool) -> Vec<u8> {
+    let mut eml = String::from(
+        "From: Intake <intake@example.com>\r\nTo: legal@example.com\r\nSubject: Documents\r\n\
+         Date: Mon, 5 Oct 2026 09:00:00 +0000\r\nMIME-Version: 1.0\r\n\
+         Content-Type: multipart/mixed; boundary=\"fixture-boundary\"\r\n\r\n\
+         This is a multi-part message in MIME format.\r\n",
+    );
+    eml.push_str("--fixture-boundary\r\nContent-Type: text/plain; charset=utf-8\r\n");
+    eml.push_str("Content-Transfer-Encoding: quoted-printable\r\n\r\n");
+    eml.push_str(&quoted_printable(body));
+    eml.push_str("\r\n");
+    for (filename, content_type, contents) in attachments {
+        eml.push_str(&format!("--fixture-boundary\r\nContent-Type: {}\r\n", content_type));
+        if !filename.is_empty() {
+            eml.push_str(&format!("Content-Disposition: attachment; filename=\"{}\"\r\n", filename));
+        }
+        eml.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        eml.push_str(&base64_lines(contents));
+    }
+    if closed {
+        eml.push_str("--fixture-boundary--\r\n");
+    }
+    eml.into_bytes()
+}
+
+/// Builds an `.mht` web archive as Word's "Save as Single File Web Page" writes it.
+///
+/// The root part is quoted-printable HTML with Word's markup (`MsoNormal`
+/// paragraphs in a `WordSection1` division, a style sheet, conditional
+/// comments) holding one paragraph per entry of `paragraphs`. It is followed
+/// by a base64 PNG of `image_bytes` bytes and the archive's file list. The
+/// root is named by the `start` parameter, as Word does.
+pub fn build_word_mht(paragraphs: &[&str], image_bytes: usize) -> Vec<u8> {
+    let body: String = paragraphs.iter().map(|text| format!("<p class=MsoNormal>{}</p>\n", text)).collect();
+    let html = format!(
+        concat!(
+            "<html xmlns:v=\"urn:schemas-microsoft-com:vml\" ",
+            "xmlns:o=\"urn:schemas-microsoft-com:office:office\">\n",
+            "<head>\n<meta http-equiv=Content-Type content=\"text/html; charset=utf-8\">\n",
+            "<meta name=Generator content=\"Microsoft Word 15\">\n",
+            "<!--[if gte mso 9]><xml><o:DocumentProperties><o:Pages>9</o:Pages>",
+            "</o:DocumentProperties></xml><![endif]-->\n",
+            "<style>\np.MsoNormal {{ margin: 0cm; font-size: 11.0pt; ",
+            "font-family: \"Calibri\", sans-serif; }}\n</style>\n",
+            "</head>\n<body lang=EN-US style='tab-interval:36.0pt'>\n<div class=WordSection1>\n{}",
+            "<p class=MsoNormal><img width=602 height=339 src=\"report_files/image001.png\"></p>\n",
+            "</div>\n</body>\n</html>\n",
+        ),
+        body
+    );
+    let mut image = png_header(1200, 675);
+    image.resize(image_bytes.max(image.len()), 0x5a);
+    let mut mht = String::from(concat!(
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/related; boundary=\"----=_NextPart_01DA0000.00000000\"; ",
+        "type=\"text/html\"; start=\"<root@fixture>\"\r\n\r\n",
+        "This document is a Single File Web Page, also known as a Web Archive file.\r\n\r\n",
+    ));
+    let boundary = "------=_NextPart_01DA0000.00000000";
+    let file_list =
+        "<xml xmlns:o=\"urn:schemas-microsoft-com:office:office\">\n <o:File HRef=\"image001.png\"/>\n</xml>";
+    mht.push_str(&format!(
+        "{}\r\nContent-Location: file:///C:/report_files/filelist.xml\r\n\
+         Content-Transfer-Encoding: quoted-printable\r\nContent-Type: text/xml; charset=\"utf-8\"\r\n\r\n{}\r\n",
+        boundary,
+        quoted_printable(file_list)
+    ));
+    mht.push_str(&format!(
+        "{}\r\nContent-ID: <root@fixture>\r\nContent-Location: file:///C:/report.htm\r\n\
+         Content-Transfer-Encoding: quoted-printable\r\nContent-Type: text/html; charset=\"utf-8\"\r\n\r\n{}\r\n",
+        boundary,
+        quoted_printable(&html)
+    ));
+    mht.push_str(&format!(
+        "{}\r\nContent-Location: file:///C:/report_files/image001.png\r\nContent-Transfer-Encoding: base64\r\n\
+         Content-Type: image/png\r\n\r\n{}",
+        boundary,
+        base64_lines(&image)
+    ));
+    mht.push_str(&format!("{}--\r\n", boundary));
+    mht.into_bytes()
+}
+
+/// Encodes text as quoted-printable, in lines of at most 76 characters with
+/// `\n` written as CRLF.
+fn quoted_printable(text: &str) -> String {
+    let mut out = String::new();
+    let mut line_len = 0;
+    for byte in text.bytes() {
+        let encoded = match byte {
+            b'\n' => {
+                out.push_str("\r\n");
+                line_len = 0;
+                continue;
+            }
+            b'=' | 0x80.. => format!("={:02X}", byte),
+            _ => (byte as char).to_string(),
+        };
+        if line_len + encoded.len() > 75 {
+            out.push_str("=\r\n");
+            line_len = 0;
+        }
+        line_len += encoded.len();
+        out.push_str(&encoded);
+    }
+    out
+}
+
+/// Encodes bytes as base64 in lines of 76 characters, each ending in CRLF.
+fn base64_lines(bytes: &[u8]) -> String {
+    use base64::Engine;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    encoded.as_bytes().chunks(76).map(|chunk| format!("{}\r\n", String::from_utf8_lossy(chunk))).collect()
+}
+
+/// Copies a ZIP package and appends `part` holding `contents`.
+///
+/// Returns the package unchanged when it can't be opened.
+pub fn with_part(package: &[u8], part: &str, contents: &str) -> Vec<u8> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(package)) else { return package.to_vec() };
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..archive.len() {
+        if let Ok(file) = archive.by_index_raw(i) {
+            zip.raw_copy_file(file).ok();
+        }
+    }
+    let options = SimpleFileOptions::default().last_modified_time(DateTime::default());
+    if zip.start_file(part, options).is_ok() {
+        zip.write_all(contents.as_bytes()).ok();
+    }
+    zip.finish().map(Cursor::into_inner).unwrap_or_default()
+}
+
+/// Copies a ZIP package without `part`, such as a `docProps/app.xml`.
+///
+/// Returns the package unchanged when it can't be opened.
+pub fn without_part(package: &[u8], part: &str) -> Vec<u8> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(package)) else { return package.to_vec() };
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..archive.len() {
+        if let Ok(file) = archive.by_index_raw(i)
+            && file.name() != part
+        {
+            zip.raw_copy_file(file).ok();
+        }
+    }
+    zip.finish().map(Cursor::into_inner).unwrap_or_default()
+}
+
+/// First bytes of an AppleDouble file: its magic number, version 2, and filler.
+const APPLE_DOUBLE_HEADER: &[u8] = b"\x00\x05\x16\x07\x00\x02\x00\x00Mac OS X        \x00\x00";
+
+/// Re-zips a ZIP archive as macOS Finder's "Compress" writes a folder.
+///
+/// Each folder gets a directory entry before its first file and a
+/// `.DS_Store`, and each file is followed by its AppleDouble resource fork,
+/// `__MACOSX/<folder>/._<name>`. Returns the archive unchanged when it can't be opened.
+pub fn to_finder_zip(package: &[u8]) -> Vec<u8> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(package)) else { return package.to_vec() };
+    let mut entries: Vec<(String, Vec<u8>)> = vec![(".DS_Store".into(), b"\x00\x00\x00\x01Bud1".to_vec())];
+    for i in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(i) else { continue };
+        let name = file.name().to_string();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).ok();
+        let (folder, file_name) = name.rsplit_once('/').map_or(("", name.as_str()), |(dir, file)| (dir, file));
+        let placeholder = format!("{}/", folder);
+        if !folder.is_empty() && !entries.iter().any(|(entry, _)| *entry == placeholder) {
+            entries.push((placeholder, Vec::new()));
+            entries.push((format!("{}/.DS_Store", folder), b"\x00\x00\x00\x01Bud1".to_vec()));
+        }
+        let fork = if folder.is_empty() {
+            format!("__MACOSX/._{}", file_name)
+        } else {
+            format!("__MACOSX/{}/._{}", folder, file_name)
+        };
+        entries.push((name, contents));
+        entries.push((fork, APPLE_DOUBLE_HEADER.to_vec()));
+    }
+    let entries: Vec<(&str, &[u8])> =
+        entries.iter().map(|(name, contents)| (name.as_str(), contents.as_slice())).collect();
+    build_zip(&entries)
+}
+
+/// Cuts a ZIP package off halfway through the data of `part`.
+///
+/// The entries before `part` stay intact, and the central directory is lost.
+/// Returns the package unchanged when it has no such part.
+pub fn truncate_in_part(package: &[u8], part: &str) -> Vec<u8> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(package)) else { return package.to_vec() };
+    let Ok(file) = archive.by_name(part) else { return package.to_vec() };
+    let cut = file.data_start() + file.compressed_size() / 2;
+    package[..cut as usize].to_vec()
+}
+
+/// Flips a byte halfway through the data of `part`, so that it fails its CRC check.
+///
+/// The central directory stays intact. Returns the package unchanged when it
+/// has no such part.
+pub fn corrupt_part(package: &[u8], part: &str) -> Vec<u8> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(package)) else { return package.to_vec() };
+    let Ok(file) = archive.by_name(part) else { return package.to_vec() };
+    let mut corrupted = package.to_vec();
+    corrupted[(file.data_start() + file.compressed_size() / 2) as usize] ^= 0xff;
+    corrupted
+}
+
+/// Rewrites the end records of a ZIP archive in ZIP64 form.
+///
+/// The entry counts and central directory size and offset move to a ZIP64
+/// end-of-central-directory record and its locator, and the classic record
+/// that follows them holds `0xFFFF`/`0xFFFFFFFF` placeholders, as ZIP writers
+/// leave it when the values don't fit. Entries are unchanged. Input without
+/// an end-of-central-directory record is returned as is.
+pub fn to_zip64(zip: &[u8]) -> Vec<u8> {
+    let Some(eocd) = zip.windows(4).rposition(|w| w == b"PK\x05\x06") else { return zip.to_vec() };
+    let Some(record) = zip.get(eocd..eocd + 22) else { return zip.to_vec() };
+    let u16_at = |at: usize| u16::from_le_bytes([record[at], record[at + 1]]) as u64;
+    let u32_at = |at: usize| u32::from_le_bytes([record[at], record[at + 1], record[at + 2], record[at + 3]]) as u64;
+    let (entries, size, offset) = (u16_at(10), u32_at(12), u32_at(16));
+
+    let mut out = zip[..eocd].to_vec();
+    let zip64_eocd = out.len() as u64;
+    out.extend_from_slice(b"PK\x06\x06");
+    out.extend_from_slice(&44u64.to_le_bytes());
+    out.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    for value in [entries, entries, size, offset] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(b"PK\x06\x07");
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&zip64_eocd.to_le_bytes());
+    out.extend_from_slice(&1u32.to_le_bytes());
+    out.extend_from_slice(b"PK\x05\x06\0\0\0\0\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\0\0");
+    out
+}
+
+/// Transitional namespace URIs and the strict URIs Office's "Strict Open XML" save mode writes instead.
+///
+/// Relationship types are built on the relationships namespace, so they are
+/// rewritten with it; content types are the same in both classes.
+const STRICT_NAMESPACES: [(&str, &str); 7] = [
+    (
+        "http://schemas.openxmlformats.org/wordprocessingml/2006/main",
+        "http://purl.oclc.org/ooxml/wordprocessingml/main",
+    ),
+    ("http://schemas.openxmlformats.org/spreadsheetml/2006/main", "http://purl.oclc.org/ooxml/spreadsheetml/main"),
+    ("http://schemas.openxmlformats.org/presentationml/2006/main", "http://purl.oclc.org/ooxml/presentationml/main"),
+    ("http://schemas.openxmlformats.org/drawingml/2006/main", "http://purl.oclc.org/ooxml/drawingml/main"),
+    (
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+        "http://purl.oclc.org/ooxml/officeDocument/relationships",
+    ),
+    (
+        "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties",
+        "http://purl.oclc.org/ooxml/officeDocument/extendedProperties",
+    ),
+    ("http://schemas.openxmlformats.org/officeDocument/2006/math", "http://purl.oclc.org/ooxml/officeDocument/math"),
+];
+
+/// Rewrites an OOXML package built by the builders above in the strict
+/// conformance class: every transitional namespace URI in its XML parts and
+/// relationships becomes its strict twin, as Office's strict save mode writes them.
+///
+/// Returns the package unchanged when it can't be opened.
+pub fn to_strict(package: &[u8]) -> Vec<u8> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(package)) else { return package.to_vec() };
+    let mut parts = Vec::new();
+    for i in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(i) else { continue };
+        let mut contents = String::new();
+        if std::io::Read::read_to_string(&mut file, &mut contents).is_err() {
+            continue;
+        }
+        for (transitional, strict) in STRICT_NAMESPACES {
+            contents = contents.replace(transitional, strict);
+        }
+        parts.push((file.name().to_string(), contents));
+    }
+    let parts: Vec<(&str, String)> = parts.iter().map(|(part, contents)| (part.as_str(), contents.clone())).collect();
+    write_package(&parts)
+}
+
+/// A paragraph of the DocBook fixture, about 60 words long.
+const DOCBOOK_PARAGRAPH: &str = concat!(
+    "The estimator reads the document once and measures each element as it would be rendered, ",
+    "so that <emphasis>paragraphs</emphasis>, listings, and tables take the space they need on the page. ",
+    "Headings add space of their own, and every chapter of a book starts on a new page, ",
+    "which is why short chapters still cost a full sheet when printed.",
+);
+
+/// Builds a DocBook 5 book of `chapters` chapters of `sections` sections each.
+///
+/// Each section holds three paragraphs, a 12-line program listing, and two
+/// `<indexterm>`s; each chapter also has a figure and a table of 6 rows. The
+/// book has an `<info>` block and ends with an empty `<index/>`, which
+/// DocBook stylesheets fill from the index terms.
+pub fn build_docbook_book(chapters: usize, sections: usize) -> Vec<u8> {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        "\n",
+        r#"<book xmlns="http://docbook.org/ns/docbook" version="5.0">"#,
+        "<info><title>Fixture Book</title><author><personname>A. Writer</personname></author></info>\n",
+    ));
+    for chapter in 1..=chapters {
+        xml.push_str(&format!("<chapter><title>Chapter {}</title>\n", chapter));
+        for section in 1..=sections {
+            xml.push_str(&format!("  <section><title>Section {}.{}</title>\n", chapter, section));
+            for _ in 0..3 {
+                xml.push_str(&format!("    <para>{}</para>\n", DOCBOOK_PARAGRAPH));
+            }
+            xml.push_str(&format!(
+                "    <para><indexterm><primary>term {0}.{1}</primary></indexterm>See the listing.\
+                 <indexterm><primary>listing {0}.{1}</primary></indexterm></para>\n",
+                chapter, section
+            ));
+            xml.push_str("    <programlisting>");
+            for line in 1..=12 {
+                xml.push_str(&format!("let value_{} = estimate(&amp;bytes, {});\n", line, line));
+            }
+            xml.push_str("</programlisting>\n  </section>\n");
+        }
+        xml.push_str(concat!(
+            "  <figure><title>Pages per chapter</title><mediaobject><imageobject>",
+            r#"<imagedata fileref="chart.png"/></imageobject></mediaobject></figure>"#,
+            "\n  <table><title>Results</title><tgroup cols=\"2\"><tbody>",
+        ));
+        for row in 1..=6 {
+            xml.push_str(&format!("<row><entry>Case {}</entry><entry>{} pages</entry></row>", row, row * 3));
+        }
+        xml.push_str("</tbody></tgroup></table>\n</chapter>\n");
+    }
+    xml.push_str("<index/>\n</book>\n");
+    xml.into_bytes()
+}
+
+const RELATIONSHIP_TYPES: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const SPREADSHEET_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const PRESENTATION_NAMESPACE: &str = "http://schemas.openxmlformats.org/presentationml/2006/main";
+const DRAWING_NAMESPACE: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const MAIN_DOCUMENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml";
+const WORKBOOK_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml";
+const WORKSHEET_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml";
+const PRESENTATION_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml";
+const SLIDE_TYPE: &str = "application/vnd.openxmlformats-officedocument.presentationml.slide+xml";
+/// `Application` written to `docProps/app.xml`, unless a builder names a real producer.
+const FIXTURE_APPLICATION: &str = "page-counter-wasm fixtures";
+
+/// `[Content_Types].xml` with the main part and `overrides` as `(part, content type)` pairs.
+fn content_types(main_type: &str, main_part: &str, overrides: &[(&str, &str)]) -> String {
+    let mut xml = String::from(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+            r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+            r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+        ),
+    );
+    xml.push_str(&format!(r#"<Override PartName="{}" ContentType="{}"/>"#, main_part, main_type));
+    for (part, kind) in overrides {
+        xml.push_str(&format!(r#"<Override PartName="{}" ContentType="{}"/>"#, part, kind));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+/// `_rels/.rels` pointing at the main part.
+fn package_rels(main_part: &str) -> String {
+    relationships(&format!(
+        r#"<Relationship Id="rId1" Type="{}/officeDocument" Target="{}"/>"#,
+        RELATIONSHIP_TYPES, main_part
+    ))
+}
+
+/// A relationships part holding the `<Relationship>` elements in `entries`.
+fn relationships(entries: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}</Relationships>"#,
+        entries
+    )
+}
+
+/// `docProps/app.xml` naming `application` and holding the extended properties in `properties`.
+fn app_properties(application: &str, properties: &str) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties">"#,
+            r#"<Application>{}</Application>{}</Properties>"#,
+        ),
+        application, properties
+    )
+}
+
+/// Spreadsheet column name of a 0-based column index (`0` → `A`, `26` → `AA`).
+fn column_name(mut col: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Zips `(part, contents)` pairs, with a fixed timestamp so the output is deterministic.
+fn write_package(parts: &[(&str, String)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().last_modified_time(DateTime::default());
+    for (part, contents) in parts {
+        // writing to memory only fails on malformed part names, which the builders don't produce
+        if zip.start_file(*part, options).is_ok() {
+            zip.write_all(contents.as_bytes()).ok();
+        }
+    }
+    zip.finish().map(Cursor::into_inner).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdf_round_trips_in_both_xref_styles() {
+        for style in [XrefStyle::Classic, XrefStyle::Stream] {
+            let result = estimate(&build_pdf(7, style, false, false), None, "{}").unwrap();
+            assert_eq!(result.page_count, 7, "{:?}", style);
+            assert!(result.notes[0].starts_with("PDF has 7 pages"), "{:?}", result.notes);
+            assert!(!result.encrypted);
+        }
+    }
+
+    #[test]
+    fn pdf_bookmarks_and_encryption_are_read_back() {
+        let bookmarked = build_pdf(4, XrefStyle::Classic, true, false);
+        let result = estimate(&bookmarked, None, r#"{"extended_pdf_info":true}"#).unwrap();
+        assert_eq!((result.page_count, result.bookmark_count), (4, Some(4)));
+
+        let result = estimate(&build_pdf(3, XrefStyle::Stream, false, true), None, "{}").unwrap();
+        assert_eq!(result.page_count, 3);
+        assert!(result.encrypted);
+    }
+
+    #[test]
+    fn docx_round_trips_through_metadata_and_content() {
+        let result = estimate(&build_docx(30, 2, Some(9)), None, "{}").unwrap();
+        assert_eq!(result.page_count, 9);
+        let result = estimate(&build_docx(30, 2, None), None, "{}").unwrap();
+        assert_eq!(result.page_count, 3);
+    }
+
+    #[test]
+    fn xlsx_round_trips_one_sheet_per_entry() {
+        let result = estimate(&build_xlsx(&[(10, 3), (100, 2), (1, 1)]), None, "{}").unwrap();
+        let sheets = result.sheets.unwrap();
+        let names: Vec<&str> = sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Sheet1", "Sheet2", "Sheet3"]);
+        assert_eq!(result.page_count, sheets.iter().map(|s| s.pages).sum::<usize>());
+        assert!(sheets[1].pages > sheets[0].pages);
+    }
+
+    #[test]
+    fn pptx_round_trips_counting_hidden_slides() {
+        let result = estimate(&build_pptx(6, 2), None, "{}").unwrap();
+        assert_eq!(result.page_count, 6);
+        assert!((result.page_sizes[0].width_mm - 254.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn raw_pdf_holds_the_given_objects() {
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+        ]);
+        let result = estimate(&pdf, None, "{}").unwrap();
+        assert_eq!(result.page_count, 1);
+    }
+
+    #[test]
+    fn blank_pages_pdf_ends_in_two_blank_pages() {
+        let result = estimate(&build_blank_pages_pdf(3), None, r#"{"exclude_blank_pages":true}"#).unwrap();
+        assert_eq!((result.page_count, result.non_blank_pages), (5, Some(3)));
+        assert!(result.notes.iter().any(|note| note.ends_with("(pages 4, 5)")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn decoy_pages_pdf_counts_only_its_pages() {
+        let pdf = build_decoy_pages_pdf(3);
+        let tokens = pdf.windows(b"/Type /Page".len()).filter(|w| *w == b"/Type /Page").count();
+        // the page tree root, the pages, and the decoys all carry the token
+        assert!(tokens > 3 + 1, "{} tokens", tokens);
+        assert_eq!(estimate(&pdf, None, "{}").unwrap().page_count, 3);
+    }
+
+    #[test]
+    fn incrementally_saved_pdf_reads_its_latest_revision() {
+        let pdf = build_incrementally_saved_pdf(4, 3);
+        assert_eq!(pdf.windows(b"/Prev".len()).filter(|w| *w == b"/Prev").count(), 3);
+        let result = estimate(&pdf, None, r#"{"include_point_sizes":true}"#).unwrap();
+        assert_eq!(result.page_count, 4);
+        let sizes = result.page_sizes_pt.unwrap();
+        assert_eq!((sizes[0].width_pt, sizes[0].height_pt), (612.0, 792.0));
+        assert!(sizes[1..].iter().all(|size| (size.width_pt, size.height_pt) == (595.0, 842.0)));
+    }
+
+    #[test]
+    fn rescanning_pdf_runs_out_of_scan_budget() {
+        let result = estimate(&build_rescanning_pdf(50), None, "{}");
+        assert!(matches!(result, Err(EstimatorError::ParseBudgetExceeded { .. })), "{:?}", result);
+    }
+
+    #[test]
+    fn protected_pdf_opens_with_its_password() {
+        for cipher in [PdfCipher::Rc4, PdfCipher::Aes128] {
+            let pdf = build_protected_pdf(3, "user", "owner", cipher);
+            let locked = estimate(&pdf, None, "{}").unwrap();
+            assert_eq!((locked.page_count, locked.encrypted, locked.producer), (3, true, None), "{:?}", cipher);
+            for password in ["user", "owner"] {
+                let options = format!(r#"{{"pdf_password":"{}","extract_pdf_text_metrics":true}}"#, password);
+                let opened = estimate(&pdf, None, &options).unwrap();
+                assert_eq!(opened.producer.as_deref(), Some(PROTECTED_PDF_PRODUCER), "{:?} {}", cipher, password);
+                assert_eq!(opened.text_metrics.unwrap().word_count, 3 * 6, "{:?} {}", cipher, password);
+            }
+        }
+    }
+
+    #[test]
+    fn reordered_xlsx_keeps_the_tab_order() {
+        let sheets = [(10, 2), (200, 3), (1, 1)];
+        let summary = |bytes: &[u8]| -> Vec<(String, Option<u32>, usize)> {
+            let result = estimate(bytes, None, "{}").unwrap();
+            result.sheets.unwrap().into_iter().map(|sheet| (sheet.name, sheet.sheet_id, sheet.pages)).collect()
+        };
+        let reordered = summary(&build_reordered_xlsx(&sheets));
+        let plain = summary(&build_xlsx(&sheets));
+        let ids: Vec<Option<u32>> = reordered.iter().map(|(_, id, _)| *id).collect();
+        assert_eq!(ids, [Some(3), Some(2), Some(1)]);
+        let without_ids = |sheets: &[(String, Option<u32>, usize)]| -> Vec<(String, usize)> {
+            sheets.iter().map(|(name, _, pages)| (name.clone(), *pages)).collect()
+        };
+        assert_eq!(without_ids(&reordered), without_ids(&plain));
+    }
+
+    #[test]
+    fn formatting_only_xlsx_has_an_empty_second_sheet() {
+        let sheets = estimate(&build_formatting_only_xlsx(100), None, "{}").unwrap().sheets.unwrap();
+        let counts: Vec<(usize, usize)> = sheets.iter().map(|sheet| (sheet.rows, sheet.pages)).collect();
+        assert_eq!(counts, [(100, 3), (0, 0)]);
+    }
+
+    #[test]
+    fn sized_pptx_reports_its_slide_size() {
+        for (cx, cy, size_type, mm) in [
+            (9144000, 5143500, Some("screen16x9"), (254.0, 142.875)),
+            (6858000, 9906000, Some("A4"), (190.5, 275.17)),
+            (43891200, 32918400, Some("custom"), (1219.2, 914.4)),
+        ] {
+            let result = estimate(&build_sized_pptx(2, cx, cy, size_type), None, "{}").unwrap();
+            assert_eq!(result.page_count, 2);
+            let size = &result.page_sizes[0];
+            assert!((size.width_mm - mm.0).abs() < 0.01 && (size.height_mm - mm.1).abs() < 0.01, "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn rendered_breaks_docx_matches_its_page_starts() {
+        let result = estimate(&build_rendered_breaks_docx(), None, r#"{"return_boundaries":true}"#).unwrap();
+        assert_eq!(result.page_count, RENDERED_BREAKS_PAGE_STARTS.len());
+        assert_eq!(result.page_start_paragraphs.as_deref(), Some(&RENDERED_BREAKS_PAGE_STARTS[..]));
+    }
+
+    #[test]
+    fn drive_downloads_are_not_exported() {
+        for (bytes, expected) in [
+            (build_drive_stub("document"), "Google Drive file stub for a Google Docs file"),
+            (build_drive_stub("spreadsheet"), "Google Drive file stub for a Google Sheets file"),
+            (build_drive_sign_in_page(), "Google sign-in page for a Google Docs file"),
+        ] {
+            match estimate(&bytes, None, "{}") {
+                Err(EstimatorError::NotExported(message)) => assert!(message.contains(expected), "{}", message),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn builders_are_deterministic() {
+        assert_eq!(build_pdf(3, XrefStyle::Stream, true, true), build_pdf(3, XrefStyle::Stream, true, true));
+        assert_eq!(build_docx(5, 1, Some(2)), build_docx(5, 1, Some(2)));
+        assert_eq!(build_xlsx(&[(3, 3)]), build_xlsx(&[(3, 3)]));
+        assert_eq!(build_pptx(3, 1), build_pptx(3, 1));
+        let protected = || build_protected_pdf(2, "u", "o", PdfCipher::Aes128);
+        assert_eq!(protected(), protected());
+        assert_eq!(build_reordered_xlsx(&[(3, 3), (1, 1)]), build_reordered_xlsx(&[(3, 3), (1, 1)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "were not all used")]
+    fn estimate_rejects_unknown_options() {
+        let _ = estimate(&build_pdf(1, XrefStyle::Classic, false, false), None, r#"{"no_such_option":1}"#);
+    }
+}
@@ -0,0 +1,242 @@
+//! # Format Registry
+//!
+//! The list of document formats the estimator understands, with the filename
+//! extensions and MIME types that select them. Detection in `detect_format`
+//! and the `supported_formats` export both read from this table, so the file
+//! picker can never advertise a format the estimator doesn't route, and a
+//! MIME hint can never select one it doesn't know.
+//!
+//! ## Generic MIME Types
+//!
+//! Some MIME types are shared by many formats: browsers report `text/plain`
+//! for source code and logs, and `application/zip` for comics and other
+//! packages. They are listed under the format they name (`txt`, `zip`), but
+//! `GENERIC_MIME_TYPES` marks them, so detection only falls back on them when
+//! neither the filename nor the content says more.
+
+use serde::Serialize;
+
+/// A supported document format.
+#[derive(Serialize)]
+pub struct FormatInfo {
+    /// Format identifier, as reported by `detect_type` (e.g. `"pdf"`, `"code"`).
+    pub id: &'static str,
+    /// Human-readable name for display.
+    pub name: &'static str,
+    /// Lowercase filename extensions without the leading dot.
+    pub extensions: &'static [&'static str],
+    /// Lowercase MIME types, such as a browser reports in `File.type`.
+    pub mime_types: &'static [&'static str],
+}
+
+/// MIME types shared by many formats (see the module docs).
+pub const GENERIC_MIME_TYPES: &[&str] = &[
+    "text/plain",
+    "application/zip",
+    "application/x-zip-compressed",
+    "application/json",
+    "application/xml",
+    "text/xml",
+];
+
+/// Every supported format, in the order extensions are matched.
+pub const FORMATS: &[FormatInfo] = &[
+    FormatInfo {
+        id: "pdf",
+        name: "PDF document",
+        extensions: &["pdf"],
+        mime_types: &["application/pdf", "application/x-pdf"],
+    },
+    FormatInfo {
+        id: "xlsx",
+        name: "Excel workbook",
+        extensions: &["xlsx", "xlsm"],
+        mime_types: &[
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/vnd.ms-excel.sheet.macroenabled.12",
+        ],
+    },
+    FormatInfo {
+        id: "docx",
+        name: "Word document",
+        extensions: &["docx"],
+        mime_types: &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"],
+    },
+    FormatInfo {
+        id: "pptx",
+        name: "PowerPoint presentation",
+        extensions: &["pptx"],
+        mime_types: &["application/vnd.openxmlformats-officedocument.presentationml.presentation"],
+    },
+    FormatInfo {
+        id: "xps",
+        name: "XPS document",
+        extensions: &["xps", "oxps"],
+        mime_types: &["application/vnd.ms-xpsdocument", "application/oxps"],
+    },
+    FormatInfo {
+        id: "postscript",
+        name: "PostScript document",
+        extensions: &["ps", "eps"],
+        mime_types: &["application/postscript"],
+    },
+    FormatInfo {
+        id: "cbz",
+        name: "Comic book archive",
+        extensions: &["cbz"],
+        mime_types: &["application/vnd.comicbook+zip", "application/x-cbz"],
+    },
+    FormatInfo {
+        id: "iwork",
+        name: "Apple iWork document",
+        extensions: &["pages", "numbers", "key"],
+        mime_types: &[
+            "application/vnd.apple.pages",
+            "application/vnd.apple.numbers",
+            "application/vnd.apple.keynote",
+            "application/x-iwork-pages-sffpages",
+            "application/x-iwork-numbers-sffnumbers",
+            "application/x-iwork-keynote-sffkey",
+        ],
+    },
+    FormatInfo {
+        id: "markdown",
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        mime_types: &["text/markdown", "text/x-markdown"],
+    },
+    FormatInfo {
+        id: "latex",
+        name: "LaTeX source",
+        extensions: &["tex"],
+        mime_types: &["application/x-tex", "application/x-latex", "text/x-tex"],
+    },
+    FormatInfo { id: "rst", name: "reStructuredText", extensions: &["rst"], mime_types: &["text/x-rst"] },
+    FormatInfo {
+        id: "asciidoc",
+        name: "AsciiDoc",
+        extensions: &["adoc", "asciidoc"],
+        mime_types: &["text/asciidoc", "text/x-asciidoc"],
+    },
+    FormatInfo {
+        id: "docbook",
+        name: "DocBook XML",
+        extensions: &["dbk", "docbook"],
+        mime_types: &["application/docbook+xml"],
+    },
+    FormatInfo { id: "txt", name: "Plain text", extensions: &["txt", "log"], mime_types: &["text/plain"] },
+    FormatInfo {
+        id: "code",
+        name: "Source code",
+        extensions: &[
+            "rs", "py", "js", "mjs", "cjs", "ts", "jsx", "tsx", "java", "kt", "kts", "scala",
+            "c", "h", "cc", "cpp", "cxx", "hpp", "hh", "cs", "go", "rb", "php", "swift", "m",
+            "mm", "sh", "bash", "zsh", "ps1", "sql", "lua", "pl", "r", "dart", "ex", "exs",
+            "erl", "hs", "clj", "css", "scss", "vue", "svelte",
+        ],
+        mime_types: &[
+            "text/javascript", "application/javascript", "application/x-javascript", "text/x-python",
+            "text/x-java-source", "text/x-c", "text/x-csrc", "text/x-chdr", "text/x-c++src", "text/x-csharp",
+            "text/x-go", "text/x-rust", "text/x-ruby", "application/x-httpd-php", "text/x-php", "text/x-swift",
+            "application/x-sh", "text/x-shellscript", "application/sql", "text/x-sql", "text/x-lua", "text/x-perl",
+            "text/css", "text/x-scss",
+        ],
+    },
+    FormatInfo {
+        id: "notebook",
+        name: "Jupyter notebook",
+        extensions: &["ipynb"],
+        mime_types: &["application/x-ipynb+json"],
+    },
+    FormatInfo {
+        id: "records",
+        name: "Contacts or calendar (vCard, iCalendar)",
+        extensions: &["vcf", "vcard", "ics", "ical"],
+        mime_types: &["text/vcard", "text/x-vcard", "text/directory", "text/calendar"],
+    },
+    FormatInfo {
+        id: "data",
+        name: "Data file (JSON, YAML, XML)",
+        extensions: &["json", "yaml", "yml", "xml"],
+        mime_types: &[
+            "application/json", "application/yaml", "application/x-yaml", "text/yaml", "text/x-yaml",
+            "application/xml", "text/xml",
+        ],
+    },
+    FormatInfo {
+        id: "zip",
+        name: "ZIP archive",
+        extensions: &["zip"],
+        mime_types: &["application/zip", "application/x-zip-compressed"],
+    },
+    FormatInfo {
+        id: "gzip",
+        name: "gzip-compressed file",
+        extensions: &["gz", "tgz"],
+        mime_types: &["application/gzip", "application/x-gzip"],
+    },
+    FormatInfo { id: "tar", name: "TAR archive", extensions: &["tar"], mime_types: &["application/x-tar"] },
+    FormatInfo { id: "eml", name: "Email message", extensions: &["eml"], mime_types: &["message/rfc822"] },
+    FormatInfo {
+        id: "mht",
+        name: "Web archive (MHT)",
+        extensions: &["mht", "mhtml"],
+        mime_types: &["multipart/related", "application/x-mimearchive"],
+    },
+];
+
+/// Looks up the format registered for a filename's extension (case-insensitive).
+pub fn format_for_filename(filename: &str) -> Option<&'static FormatInfo> {
+    let (_, ext) = filename.rsplit_once('.')?;
+    let ext = ext.to_lowercase();
+    FORMATS.iter().find(|f| f.extensions.contains(&ext.as_str()))
+}
+
+/// Looks up the format registered for a MIME type, ignoring case and
+/// parameters (`text/markdown; charset=utf-8`). Unknown types give `None`.
+pub fn format_for_mime_type(mime_type: &str) -> Option<&'static FormatInfo> {
+    let essence = mime_essence(mime_type);
+    FORMATS.iter().find(|f| f.mime_types.contains(&essence.as_str()))
+}
+
+/// Whether a MIME type is shared by many formats (see the module docs).
+pub fn is_generic_mime_type(mime_type: &str) -> bool {
+    GENERIC_MIME_TYPES.contains(&mime_essence(mime_type).as_str())
+}
+
+/// The type and subtype of a MIME type, lowercase and without parameters.
+fn mime_essence(mime_type: &str) -> String {
+    mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mime::extension_for_content_type;
+
+    #[test]
+    fn every_mime_type_selects_the_format_listing_it() {
+        for format in FORMATS {
+            for mime_type in format.mime_types {
+                assert_eq!(mime_essence(mime_type), *mime_type, "{} should be listed as its essence", mime_type);
+                let selected = format_for_mime_type(mime_type).map(|f| f.id);
+                assert_eq!(selected, Some(format.id), "{} is listed by more than one format", mime_type);
+                assert_eq!(extension_for_content_type(mime_type), format.extensions.first().copied());
+            }
+        }
+        for generic in GENERIC_MIME_TYPES {
+            assert!(format_for_mime_type(generic).is_some(), "{} is marked generic but no format lists it", generic);
+        }
+    }
+
+    #[test]
+    fn mime_types_are_matched_by_their_essence() {
+        let pdf = Some("pdf");
+        assert_eq!(format_for_mime_type("application/pdf").map(|f| f.id), pdf);
+        assert_eq!(format_for_mime_type(" Application/PDF; charset=binary").map(|f| f.id), pdf);
+        assert!(is_generic_mime_type("text/plain; charset=UTF-8") && !is_generic_mime_type("application/pdf"));
+        for unknown in ["application/x-foo", "text/csv", "", ";;;", "application/"] {
+            assert!(format_for_mime_type(unknown).is_none(), "{:?}", unknown);
+        }
+    }
+}
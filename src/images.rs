@@ -0,0 +1,171 @@
+//! # Raster Image Headers
+//!
+//! Reads the pixel dimensions and resolution of PNG, JPEG, GIF, and WebP
+//! images from their headers, without decoding any pixel data. Used wherever
+//! an image stands for a printed page (e.g. comic book archives).
+//!
+//! ## Notes
+//!
+//! - Resolution is taken from PNG `pHYs` chunks and JPEG JFIF density; other
+//!   images are assumed to be `DEFAULT_IMAGE_DPI`
+//! - Only the first `IMAGE_HEADER_BYTES` of an image are needed; JPEG SOF
+//!   markers after a large EXIF block may fall outside and yield `None`
+
+/// Bytes of an image needed to read its header (covers a maximum-size EXIF segment).
+pub const IMAGE_HEADER_BYTES: usize = 128 * 1024;
+
+/// Resolution assumed for images that don't declare one.
+pub const DEFAULT_IMAGE_DPI: f64 = 96.0;
+
+/// Dimensions read from an image header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageHeader {
+    /// Image format: `"png"`, `"jpeg"`, `"gif"`, or `"webp"`.
+    pub format: &'static str,
+    /// Width in pixels.
+    pub width_px: u32,
+    /// Height in pixels.
+    pub height_px: u32,
+    /// Horizontal and vertical resolution in dots per inch, when declared.
+    pub dpi: Option<(f64, f64)>,
+}
+
+impl ImageHeader {
+    /// Printed size in millimeters at the declared (or default) resolution.
+    pub fn size_mm(&self) -> (f64, f64) {
+        let (dpi_x, dpi_y) = self.dpi.unwrap_or((DEFAULT_IMAGE_DPI, DEFAULT_IMAGE_DPI));
+        (self.width_px as f64 / dpi_x * 25.4, self.height_px as f64 / dpi_y * 25.4)
+    }
+}
+
+/// Reads the header of a PNG, JPEG, GIF, or WebP image.
+///
+/// # Arguments
+///
+/// * `bytes` - The start of the image file (at least the header)
+///
+/// # Returns
+///
+/// The image's dimensions, or `None` if the format isn't recognized or the
+/// header is truncated or has a zero dimension.
+pub fn read_image_header(bytes: &[u8]) -> Option<ImageHeader> {
+    let header = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        read_png(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        read_jpeg(bytes)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(ImageHeader {
+            format: "gif",
+            width_px: u16_le(bytes, 6)? as u32,
+            height_px: u16_le(bytes, 8)? as u32,
+            dpi: None,
+        })
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        read_webp(bytes)
+    } else {
+        None
+    }?;
+    (header.width_px > 0 && header.height_px > 0).then_some(header)
+}
+
+fn read_png(bytes: &[u8]) -> Option<ImageHeader> {
+    if bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let mut header =
+        ImageHeader { format: "png", width_px: u32_be(bytes, 16)?, height_px: u32_be(bytes, 20)?, dpi: None };
+
+    // pHYs must precede the image data
+    let mut pos = 8;
+    while let (Some(length), Some(kind)) = (u32_be(bytes, pos), bytes.get(pos + 4..pos + 8)) {
+        match kind {
+            b"pHYs" => {
+                let (x, y) = (u32_be(bytes, pos + 8)?, u32_be(bytes, pos + 12)?);
+                // unit 1 is pixels per meter; unit 0 is an aspect ratio only
+                if *bytes.get(pos + 16)? == 1 && x > 0 && y > 0 {
+                    header.dpi = Some((x as f64 * 0.0254, y as f64 * 0.0254));
+                }
+                break;
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => pos = pos.checked_add(12 + length as usize)?,
+        }
+    }
+    Some(header)
+}
+
+fn read_jpeg(bytes: &[u8]) -> Option<ImageHeader> {
+    let mut dpi = None;
+    let mut pos = 2;
+    loop {
+        // skip fill bytes before the marker code
+        while *bytes.get(pos)? == 0xFF && *bytes.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        if marker == 0xD8 || (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            pos += 2;
+            continue;
+        }
+        let length = u16_be(bytes, pos + 2)? as usize;
+        let segment = pos + 4;
+        match marker {
+            // SOF0..SOF15, except DHT (C4), JPG (C8), and DAC (CC)
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some(ImageHeader {
+                    format: "jpeg",
+                    width_px: u16_be(bytes, segment + 3)? as u32,
+                    height_px: u16_be(bytes, segment + 1)? as u32,
+                    dpi,
+                });
+            }
+            0xE0 if bytes.get(segment..segment + 5) == Some(b"JFIF\0") => {
+                let units = *bytes.get(segment + 7)?;
+                let (x, y) = (u16_be(bytes, segment + 8)? as f64, u16_be(bytes, segment + 10)? as f64);
+                if x > 0.0 && y > 0.0 {
+                    dpi = match units {
+                        1 => Some((x, y)),
+                        2 => Some((x * 2.54, y * 2.54)),
+                        _ => None,
+                    };
+                }
+            }
+            0xD9 | 0xDA => return None,
+            _ => {}
+        }
+        pos = segment.checked_add(length)?.checked_sub(2)?;
+    }
+}
+
+fn read_webp(bytes: &[u8]) -> Option<ImageHeader> {
+    let (width_px, height_px) = match bytes.get(12..16)? {
+        b"VP8X" => (u24_le(bytes, 24)? + 1, u24_le(bytes, 27)? + 1),
+        b"VP8 " => ((u16_le(bytes, 26)? & 0x3FFF) as u32, (u16_le(bytes, 28)? & 0x3FFF) as u32),
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1)
+        }
+        _ => return None,
+    };
+    Some(ImageHeader { format: "webp", width_px, height_px, dpi: None })
+}
+
+fn u16_be(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u24_le(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
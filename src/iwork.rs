@@ -0,0 +1,104 @@
+//! # Apple iWork Documents
+//!
+//! Pages, Numbers, and Keynote files are ZIP packages. Their content lives in
+//! IWA streams (`Index/*.iwa`, or an inner `Index.zip`), a snappy-compressed
+//! protobuf format that isn't parsed here. Instead, the page count is taken
+//! from the `QuickLook/Preview.pdf` that iWork embeds when a document is saved
+//! with a preview, using the regular PDF counter.
+//!
+//! ## Notes
+//!
+//! - iWork '09 packages (with a root `buildVersionHistory.plist`) are detected too
+//! - The `preview*.jpg` thumbnails show the first page only, so they aren't counted
+//! - The application is identified from the build version history, which
+//!   names the app that saved the document
+
+use crate::file_utils::note_zip_entry;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// Part holding the PDF preview of an iWork document.
+pub const QUICKLOOK_PREVIEW: &str = "QuickLook/Preview.pdf";
+
+/// Build version history parts: current iWork, then iWork '09.
+const VERSION_HISTORY_PARTS: &[&str] = &["Metadata/BuildVersionHistory.plist", "buildVersionHistory.plist"];
+
+/// Checks whether a ZIP package is an iWork document.
+///
+/// Matches IWA document streams, the inner `Index.zip` of package bundles,
+/// a build version history, or a QuickLook preview PDF.
+pub fn is_iwork_package(archive: &ZipArchive<Cursor<&[u8]>>) -> bool {
+    archive.file_names().any(|name| {
+        name == "Index.zip"
+            || name == QUICKLOOK_PREVIEW
+            || VERSION_HISTORY_PARTS.contains(&name)
+            || (name.starts_with("Index/") && name.ends_with(".iwa"))
+    })
+}
+
+/// Names the application (`"Pages"`, `"Numbers"`, or `"Keynote"`) that saved an iWork document.
+///
+/// # Returns
+///
+/// The application named in the build version history, or `None` when the
+/// history is missing or names none of them.
+pub fn iwork_application(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<&'static str> {
+    let history = VERSION_HISTORY_PARTS.iter().find_map(|part| {
+        let mut file = archive.by_name(part).ok()?;
+        note_zip_entry(&file);
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).ok()?;
+        Some(contents)
+    })?;
+    let history = String::from_utf8_lossy(&history);
+    ["Pages", "Numbers", "Keynote"].into_iter().find(|app| history.contains(app))
+}
+
+/// Reads the embedded QuickLook preview PDF, if the document was saved with one.
+pub fn read_quicklook_preview(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<Vec<u8>> {
+    let mut file = archive.by_name(QUICKLOOK_PREVIEW).ok()?;
+    note_zip_entry(&file);
+    let mut preview = Vec::new();
+    file.read_to_end(&mut preview).ok()?;
+    preview.starts_with(b"%PDF").then_some(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_utils::detect_type;
+    use crate::fixtures::*;
+    use crate::pdf::PdfCounter;
+
+    #[test]
+    fn packages_are_detected_by_their_structure() {
+        let keynote = build_iwork("Keynote", None);
+        assert_eq!(detect_type(None, &keynote), "iwork");
+        assert_eq!(detect_type(Some("talk.key"), &keynote), "iwork");
+        let iwork09 = build_zip(&[("buildVersionHistory.plist", b"<string>Pages 4.3</string>"), ("index.xml.gz", b"")]);
+        assert_eq!(detect_type(None, &iwork09), "iwork");
+        assert_eq!(detect_type(None, &build_zip(&[("notes.txt", b"hi")])), "zip");
+    }
+
+    #[test]
+    fn quicklook_preview_gives_the_pages() {
+        let preview = build_receipt_pdf(&[297.0, 297.0, 150.0]);
+        let pages = estimate(&build_iwork("Pages", Some(&preview)), Some("letter.pages"), "{}").unwrap();
+        assert_eq!(pages.page_count, 3);
+        assert_eq!(pages.notes[0], "Pages document has 3 pages (from its QuickLook preview)");
+        assert_eq!(pages.page_sizes, PdfCounter::default().page_sizes(&preview).unwrap());
+
+        let slides = build_pdf(12, XrefStyle::Stream, false, false);
+        let keynote = estimate(&build_iwork("Keynote", Some(&slides)), None, "{}").unwrap();
+        assert_eq!(keynote.page_count, 12);
+        assert!(keynote.notes[0].starts_with("Keynote document"), "{:?}", keynote.notes);
+    }
+
+    #[test]
+    fn documents_without_a_preview_name_their_app() {
+        let err = estimate(&build_iwork("Numbers", None), Some("budget.numbers"), "{}").unwrap_err();
+        assert!(err.to_string().contains("Numbers document has no readable QuickLook preview"), "{}", err);
+        let not_pdf = build_iwork("Pages", Some(b"\xFF\xD8\xFF\xE0"));
+        let err = estimate(&not_pdf, Some("letter.pages"), "{}").unwrap_err();
+        assert!(err.to_string().contains("export it to PDF"), "{}", err);
+    }
+}
@@ -0,0 +1,409 @@
+//! # Language Detection
+//!
+//! Guesses the language of a document's text, for routing rules such as
+//! sending German documents to another print center. It runs on the text the
+//! estimators already extract (plain text, Markdown, HTML, DOCX, and PDFs read
+//! for text metrics), only when the `detect_language` option is set.
+//!
+//! ## Method
+//!
+//! The classifier is small on purpose: no model is downloaded, and the tables
+//! below are the whole of it.
+//!
+//! - The letters of the first `LANGUAGE_SAMPLE_CHARS` characters are sorted
+//!   by script. Scripts used by a single language of the tables decide alone:
+//!   Greek, Hebrew, Arabic, Thai, Devanagari (Hindi), Hangul (Korean), and
+//!   Han, which is Japanese when kana make up a tenth of it and Chinese otherwise
+//! - Latin and Cyrillic text is matched against the `STOPWORDS` of each of
+//!   their languages. A word shared by several lists counts for each of them
+//!   in proportion, so the words unique to a language decide between close ones
+//!   (Danish and Norwegian, Spanish and Portuguese)
+//!
+//! `confidence` is the share of the script (for the first group) or of the
+//! stopword matches (for the second) that points to the language.
+//!
+//! ## Undetected text
+//!
+//! No language is reported when the sample has fewer words than
+//! `language_min_words` (`DEFAULT_LANGUAGE_MIN_WORDS`), or when fewer than
+//! `MIN_STOPWORD_SHARE` of its words are stopwords, as in code, tables, or
+//! lists of names. Scripts written without spaces count
+//! `SPACELESS_CHARS_PER_WORD` letters as a word.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Characters of a text read to guess its language.
+pub const LANGUAGE_SAMPLE_CHARS: usize = 20_000;
+
+/// Words a text needs for its language to be guessed, when `language_min_words` isn't set.
+pub const DEFAULT_LANGUAGE_MIN_WORDS: usize = 20;
+
+/// Share of the words of a Latin or Cyrillic text that must be stopwords of some language.
+pub const MIN_STOPWORD_SHARE: f64 = 0.2;
+
+/// Letters counted as a word in scripts written without spaces (Chinese, Japanese, Thai).
+pub const SPACELESS_CHARS_PER_WORD: usize = 2;
+
+/// Share of kana among the Han and kana letters from which text is Japanese.
+const KANA_SHARE: f64 = 0.1;
+
+/// Frequent short words of each language written in the Latin or Cyrillic script, by ISO 639-1 code.
+pub const STOPWORDS: [(&str, &[&str]); 21] = [
+    (
+        "en",
+        &[
+            "the", "and", "of", "to", "is", "in", "that", "it", "was", "for", "with", "as", "are", "this", "be",
+            "on", "have", "not", "by", "from", "at", "which", "you", "they", "were", "has", "or", "an", "but",
+            "will", "would", "there", "their", "been", "we", "he", "she", "his",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "und", "das", "ist", "nicht", "ein", "eine", "zu", "den", "von", "mit", "sich", "des",
+            "auf", "für", "im", "dem", "auch", "es", "werden", "aus", "er", "hat", "dass", "sie", "nach", "wird",
+            "bei", "einer", "um", "noch", "wie", "über", "zum", "war", "haben", "nur", "oder", "aber", "sind",
+            "ich", "wir", "kann",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "de", "des", "et", "est", "un", "une", "du", "en", "que", "qui", "dans", "pour",
+            "pas", "sur", "au", "avec", "ce", "il", "elle", "sont", "par", "plus", "ne", "se", "aux", "ou",
+            "mais", "nous", "vous", "cette", "été", "être", "leur", "très", "aussi", "comme",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "de", "que", "y", "en", "los", "se", "del", "las", "un", "por", "con", "no", "una", "su",
+            "para", "es", "al", "lo", "como", "más", "pero", "sus", "le", "ya", "este", "ha", "muy", "también",
+            "fue", "hay", "entre", "cuando", "está", "son", "sin", "sobre",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "di", "che", "e", "la", "per", "un", "in", "del", "della", "non", "una", "sono", "è", "le",
+            "con", "si", "da", "al", "dei", "nel", "gli", "alla", "anche", "come", "più", "ma", "questo", "delle",
+            "ha", "lo", "ci", "loro", "essere", "molto", "questa",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "de", "a", "o", "que", "e", "do", "da", "em", "um", "para", "é", "com", "não", "uma", "os", "no",
+            "se", "na", "por", "mais", "as", "dos", "como", "mas", "foi", "ao", "ele", "das", "tem", "à", "seu",
+            "sua", "ou", "ser", "quando", "muito", "nos", "já", "está", "também", "são", "pelo", "pela",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "van", "is", "dat", "op", "te", "in", "zijn", "niet", "met", "voor", "die",
+            "er", "aan", "ook", "als", "maar", "om", "bij", "door", "naar", "wordt", "dan", "nog", "werd", "uit",
+            "kan", "zo", "deze", "wel", "geen", "hij", "ze", "wij", "heeft", "worden", "hun",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "i", "att", "det", "som", "en", "på", "är", "av", "för", "med", "till", "den", "har", "de",
+            "inte", "om", "ett", "han", "men", "var", "jag", "sig", "från", "vi", "så", "kan", "när", "ska",
+            "eller", "efter", "också", "vid", "under", "bara", "detta", "sina", "mycket",
+        ],
+    ),
+    (
+        "da",
+        &[
+            "og", "i", "at", "det", "en", "af", "er", "til", "på", "som", "de", "med", "han", "for", "ikke",
+            "der", "var", "den", "har", "jeg", "et", "men", "om", "sig", "fra", "vi", "så", "kan", "efter",
+            "skal", "eller", "også", "meget", "blev", "være", "hvor", "hvis", "nu", "når", "hvad",
+        ],
+    ),
+    (
+        "no",
+        &[
+            "og", "i", "det", "at", "en", "på", "er", "til", "som", "av", "for", "med", "har", "de", "ikke",
+            "den", "han", "jeg", "et", "om", "men", "var", "seg", "fra", "vi", "så", "kan", "etter", "skal",
+            "eller", "også", "være", "blir", "hun", "ble", "hva", "mye", "hvis", "når", "denne", "dette",
+        ],
+    ),
+    (
+        "fi",
+        &[
+            "ja", "on", "ei", "se", "että", "oli", "hän", "ovat", "mutta", "kun", "myös", "sen", "joka", "tai",
+            "niin", "ole", "kuin", "jos", "vain", "voi", "ne", "mitä", "tämä", "jo", "sekä", "hänen", "olla",
+            "nyt", "sitä", "mukaan", "jotka", "siitä", "sitten", "vielä",
+        ],
+    ),
+    (
+        "pl",
+        &[
+            "i", "w", "nie", "na", "się", "z", "do", "to", "że", "jest", "o", "jak", "a", "po", "co", "tak",
+            "za", "od", "ale", "jego", "przez", "dla", "może", "czy", "już", "są", "był", "tylko", "oraz", "jej",
+            "ich", "być", "przy", "bardzo", "które", "który", "która", "jednak",
+        ],
+    ),
+    (
+        "cs",
+        &[
+            "a", "se", "na", "je", "že", "v", "s", "z", "do", "to", "by", "jako", "ale", "o", "pro", "jsou",
+            "který", "jak", "jeho", "jen", "po", "však", "také", "bylo", "byl", "které", "této", "při", "nebo",
+            "tak", "už", "když", "mezi", "podle", "jejich", "ještě", "být",
+        ],
+    ),
+    (
+        "ro",
+        &[
+            "și", "şi", "în", "de", "la", "a", "cu", "pe", "nu", "din", "că", "o", "un", "se", "mai", "este", "sunt",
+            "pentru", "care", "sau", "ca", "ce", "lui", "fost", "prin", "dar", "au", "această", "acest", "el",
+            "ei", "foarte", "după", "când", "al", "ale",
+        ],
+    ),
+    (
+        "hu",
+        &[
+            "a", "az", "és", "hogy", "nem", "is", "egy", "meg", "van", "de", "csak", "el", "már", "ki", "mint",
+            "még", "volt", "ez", "azt", "vagy", "kell", "lesz", "ha", "fel", "mert", "nagyon", "lehet", "után",
+            "pedig", "ezt", "most", "minden", "amely", "amit", "között", "szerint",
+        ],
+    ),
+    (
+        "tr",
+        &[
+            "ve", "bir", "bu", "da", "de", "için", "ile", "olarak", "çok", "daha", "gibi", "ne", "o", "en", "ama",
+            "kadar", "var", "ise", "sonra", "olan", "her", "değil", "veya", "mi", "göre", "ancak", "ya", "yok",
+            "şu", "diye", "çünkü", "oldu", "olduğu", "bunu",
+        ],
+    ),
+    (
+        "id",
+        &[
+            "yang", "dan", "di", "ini", "itu", "dengan", "untuk", "tidak", "dari", "dalam", "akan", "pada", "ke",
+            "juga", "adalah", "saya", "ada", "oleh", "bisa", "mereka", "karena", "sudah", "atau", "kami", "kita",
+            "telah", "seperti", "lebih", "banyak", "hanya", "bahwa",
+        ],
+    ),
+    (
+        "ru",
+        &[
+            "и", "в", "не", "на", "что", "я", "с", "он", "как", "а", "то", "это", "по", "к", "но", "из", "у",
+            "за", "о", "так", "же", "от", "его", "для", "все", "она", "был", "было", "мы", "вы", "бы", "только",
+            "еще", "ещё", "или", "при", "когда", "уже", "если", "их", "также", "этот",
+        ],
+    ),
+    (
+        "uk",
+        &[
+            "і", "в", "у", "на", "що", "не", "з", "та", "до", "як", "він", "це", "а", "за", "по", "від", "але",
+            "для", "його", "я", "ми", "ви", "так", "вже", "чи", "ще", "був", "було", "вона", "також", "коли",
+            "які", "який", "яка", "тому", "щоб", "її", "їх", "або", "при", "цей",
+        ],
+    ),
+    (
+        "bg",
+        &[
+            "и", "в", "на", "е", "да", "се", "с", "не", "за", "от", "по", "са", "че", "като", "това", "той",
+            "тя", "но", "си", "ще", "му", "или", "при", "беше", "към", "след", "още", "има", "във",
+            "което", "които", "който", "така", "много",
+        ],
+    ),
+    (
+        "hr",
+        &[
+            "i", "je", "u", "na", "se", "da", "za", "su", "od", "s", "a", "koji", "ne", "što", "kao", "iz", "o",
+            "te", "biti", "će", "ili", "bi", "ali", "sa", "koja", "koje", "bio", "kako", "već", "samo", "nije",
+            "do", "jer", "ima", "još", "prema",
+        ],
+    ),
+];
+
+/// Writing systems told apart by the classifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Hebrew,
+    Arabic,
+    Thai,
+    Devanagari,
+    Hangul,
+    Kana,
+    Han,
+}
+
+impl Script {
+    /// The script of a character, or `None` for punctuation, digits, and other scripts.
+    fn of(c: char) -> Option<Script> {
+        let script = match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+            0x400..=0x52F => Script::Cyrillic,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x590..=0x5FF => Script::Hebrew,
+            0x600..=0x6FF | 0x750..=0x77F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+            0xE00..=0xE7F => Script::Thai,
+            0x900..=0x97F => Script::Devanagari,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            0x3040..=0x30FF => Script::Kana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+            _ => return None,
+        };
+        // the Latin ranges hold a few symbols (×, ÷)
+        (script != Script::Latin || c.is_alphabetic()).then_some(script)
+    }
+
+    /// Whether the script is written without spaces between words.
+    fn is_spaceless(self) -> bool {
+        matches!(self, Script::Thai | Script::Kana | Script::Han)
+    }
+}
+
+/// The language guessed for a text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LanguageGuess {
+    /// ISO 639-1 code of the language, or `None` when the text is too short
+    /// or matches none of the languages.
+    pub code: Option<&'static str>,
+    /// Share of the evidence pointing to `code`, from 0 to 1; 0 without a code.
+    pub confidence: f64,
+    /// Words of the sample read.
+    pub words: usize,
+}
+
+impl LanguageGuess {
+    /// The guess as it appears under `language` in `diagnostics`.
+    pub fn to_json(&self) -> Value {
+        json!({ "code": self.code, "confidence": self.confidence, "words": self.words })
+    }
+}
+
+/// Guesses the language of a text (see the module docs).
+///
+/// # Arguments
+///
+/// * `text` - The extracted text; only its first `LANGUAGE_SAMPLE_CHARS` characters are read
+/// * `min_words` - Words the sample needs for a language to be guessed
+///
+/// # Returns
+///
+/// The guess, with no `code` when the sample is shorter than `min_words` or
+/// isn't recognizably in one of the languages.
+pub fn detect_language(text: &str, min_words: usize) -> LanguageGuess {
+    let sample = match text.char_indices().nth(LANGUAGE_SAMPLE_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+    let mut scripts: HashMap<Script, usize> = HashMap::new();
+    for script in sample.chars().filter_map(Script::of) {
+        *scripts.entry(script).or_insert(0) += 1;
+    }
+    let letters: usize = scripts.values().sum();
+    let Some((&script, &count)) = scripts.iter().max_by_key(|&(_, count)| *count) else {
+        return LanguageGuess::default();
+    };
+
+    let words = if script.is_spaceless() {
+        scripts.iter().filter(|(script, _)| script.is_spaceless()).map(|(_, count)| count).sum::<usize>()
+            / SPACELESS_CHARS_PER_WORD
+    } else {
+        sample.split(|c: char| !c.is_alphabetic()).filter(|word| !word.is_empty()).count()
+    };
+    let mut guess = LanguageGuess { words, ..Default::default() };
+    if words < min_words {
+        return guess;
+    }
+
+    let share = count as f64 / letters as f64;
+    let (code, confidence) = match script {
+        Script::Latin | Script::Cyrillic => match stopword_language(sample) {
+            Some(found) => found,
+            None => return guess,
+        },
+        Script::Greek => ("el", share),
+        Script::Hebrew => ("he", share),
+        Script::Arabic => ("ar", share),
+        Script::Thai => ("th", share),
+        Script::Devanagari => ("hi", share),
+        Script::Hangul => ("ko", share),
+        Script::Kana | Script::Han => {
+            // Japanese mixes kana and Han, so both count toward its script
+            let kana = scripts.get(&Script::Kana).copied().unwrap_or(0);
+            let han = scripts.get(&Script::Han).copied().unwrap_or(0);
+            let code = if kana as f64 >= KANA_SHARE * (kana + han) as f64 { "ja" } else { "zh" };
+            (code, (kana + han) as f64 / letters as f64)
+        }
+    };
+    guess.code = Some(code);
+    guess.confidence = (confidence * 100.0).round() / 100.0;
+    guess
+}
+
+/// Matches the words of a Latin or Cyrillic text against `STOPWORDS`.
+///
+/// # Returns
+///
+/// The best language and its share of the matches, or `None` when fewer than
+/// `MIN_STOPWORD_SHARE` of the words are stopwords.
+fn stopword_language(sample: &str) -> Option<(&'static str, f64)> {
+    let mut lists: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, (_, words)) in STOPWORDS.iter().enumerate() {
+        for word in words.iter() {
+            let languages = lists.entry(word).or_default();
+            if !languages.contains(&i) {
+                languages.push(i);
+            }
+        }
+    }
+
+    let mut scores = [0.0; STOPWORDS.len()];
+    let mut words = 0usize;
+    let mut matched = 0usize;
+    for word in sample.split(|c: char| !c.is_alphabetic()).filter(|word| !word.is_empty()) {
+        words += 1;
+        if let Some(languages) = lists.get(word.to_lowercase().as_str()) {
+            matched += 1;
+            for &i in languages {
+                scores[i] += 1.0 / languages.len() as f64;
+            }
+        }
+    }
+    if (matched as f64) < MIN_STOPWORD_SHARE * words as f64 || matched == 0 {
+        return None;
+    }
+    let (best, score) = scores.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+    Some((STOPWORDS[best].0, score / matched as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn every_sample_language_is_recognized() {
+        for (code, text) in LANGUAGE_SAMPLES {
+            let guess = detect_language(text, DEFAULT_LANGUAGE_MIN_WORDS);
+            assert_eq!(guess.code, Some(code), "{}", text);
+            assert!(guess.confidence > 0.3 && guess.confidence <= 1.0, "{}: {}", code, guess.confidence);
+        }
+        assert_eq!(detect_language(&ARABIC_TEXT.repeat(3), DEFAULT_LANGUAGE_MIN_WORDS).code, Some("ar"));
+        assert_eq!(detect_language(&HEBREW_TEXT.repeat(3), DEFAULT_LANGUAGE_MIN_WORDS).code, Some("he"));
+    }
+
+    #[test]
+    fn short_or_unrecognizable_text_gets_no_language() {
+        let short = detect_language("Die Druckerei hat den Auftrag geprüft.", DEFAULT_LANGUAGE_MIN_WORDS);
+        assert_eq!((short.code, short.confidence, short.words), (None, 0.0, 6));
+        assert_eq!(detect_language("Die Druckerei hat den Auftrag geprüft.", 5).code, Some("de"));
+
+        let names = "Ada Lovelace, Grace Hopper, Alan Turing, Edsger Dijkstra, Barbara Liskov, ".repeat(5);
+        assert_eq!(detect_language(&names, DEFAULT_LANGUAGE_MIN_WORDS).code, None);
+        let code = "fn main() { let total = items.iter().map(|item| item.price).sum::<u64>(); }\n".repeat(5);
+        assert_eq!(detect_language(&code, DEFAULT_LANGUAGE_MIN_WORDS).code, None);
+        assert_eq!(detect_language("", 0), LanguageGuess::default());
+    }
+}
@@ -0,0 +1,376 @@
+//! # LaTeX Source Stripping
+//!
+//! Reduces a `.tex` source to the prose it would typeset, so that a character
+//! heuristic can give a rough page estimate without compiling. This is
+//! heuristic by nature: macros are not expanded and math is counted as text.
+//!
+//! ## Rules
+//!
+//! - Comments (`%` to end of line, except `\%`) and the preamble are removed
+//! - Non-content commands (`\usepackage`, `\label`, `\vspace`, ...) are removed
+//!   together with their arguments
+//! - References and citations (`\cite`, `\ref`, ...) count as a single word
+//! - Other commands (`\section`, `\textbf`, ...) are removed but the text of
+//!   their arguments is kept
+//! - `\newpage`, `\clearpage`, `\pagebreak`, and `\cleardoublepage` are hard page breaks
+//! - `figure` and `table` environments are counted as fixed-height float blocks
+
+use std::collections::BTreeMap;
+
+/// Commands that produce no text; their arguments are dropped too.
+const NON_CONTENT_COMMANDS: &[&str] = &[
+    "usepackage", "documentclass", "label", "bibliographystyle", "bibliography",
+    "maketitle", "tableofcontents", "listoffigures", "listoftables", "newcommand",
+    "renewcommand", "providecommand", "newenvironment", "renewenvironment", "setlength",
+    "addtolength", "setcounter", "vspace", "hspace", "pagestyle", "thispagestyle",
+    "includegraphics", "input", "include", "centering", "noindent", "indent", "small",
+    "large", "Large", "footnotesize", "normalsize", "hline", "midrule", "toprule",
+    "bottomrule", "graphicspath", "hypersetup", "geometry", "linespread",
+];
+
+/// Commands that render as a short reference (about one word).
+const REFERENCE_COMMANDS: &[&str] =
+    &["cite", "citep", "citet", "ref", "eqref", "pageref", "autoref", "cref", "Cref"];
+
+/// Commands that force a page break.
+const BREAK_COMMANDS: &[&str] = &["newpage", "clearpage", "pagebreak", "cleardoublepage"];
+
+/// Environments typeset as floats with a fixed height.
+const FLOAT_ENVIRONMENTS: &[&str] = &["figure", "figure*", "table", "table*"];
+
+/// Environments whose content is never typeset.
+const HIDDEN_ENVIRONMENTS: &[&str] = &["comment"];
+
+/// A run of prose between two hard page breaks.
+#[derive(Default)]
+pub struct LatexSegment {
+    /// The typeset prose of the segment.
+    pub text: String,
+    /// Number of float blocks (figures and tables) in the segment.
+    pub floats: usize,
+}
+
+/// The prose of a LaTeX document and what was removed to get it.
+pub struct StrippedLatex {
+    /// The document class (`article`, `report`, `book`, ...), if declared.
+    pub document_class: Option<String>,
+    /// Prose split at hard page breaks.
+    pub segments: Vec<LatexSegment>,
+    /// Number of comments removed.
+    pub comments: usize,
+    /// Bytes of preamble removed before `\begin{document}`.
+    pub preamble_bytes: usize,
+    /// Number of removed commands by name.
+    pub stripped_commands: BTreeMap<String, usize>,
+    /// Number of references and citations counted as one word.
+    pub references: usize,
+}
+
+/// Checks whether text looks like a LaTeX source.
+pub fn looks_like_latex(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(64 * 1024)];
+    let contains = |needle: &[u8]| head.windows(needle.len()).any(|w| w == needle);
+    contains(b"\\documentclass") || contains(b"\\begin{document}")
+}
+
+/// Strips a LaTeX source down to its prose.
+///
+/// # Arguments
+///
+/// * `source` - The `.tex` source text
+///
+/// # Returns
+///
+/// The prose split at hard page breaks, the document class, and counts of
+/// what was removed (for the estimate's notes).
+pub fn strip_latex(source: &str) -> StrippedLatex {
+    let (source, comments) = strip_comments(source);
+
+    let document_class = find_command_argument(&source, "documentclass");
+    let (body, preamble_bytes) = match source.find("\\begin{document}") {
+        Some(at) => (&source[at + "\\begin{document}".len()..], at),
+        None => (source.as_str(), 0),
+    };
+    let body = body.find("\\end{document}").map_or(body, |at| &body[..at]);
+
+    let mut stripped = StrippedLatex {
+        document_class,
+        segments: vec![LatexSegment::default()],
+        comments,
+        preamble_bytes,
+        stripped_commands: BTreeMap::new(),
+        references: 0,
+    };
+    stripped.scan(body);
+    stripped.segments.retain(|s| !s.text.trim().is_empty() || s.floats > 0);
+    stripped
+}
+
+impl StrippedLatex {
+    fn current(&mut self) -> &mut LatexSegment {
+        self.segments.last_mut().expect("at least one segment")
+    }
+
+    fn scan(&mut self, body: &str) {
+        let bytes = body.as_bytes();
+        let mut i = 0;
+        while i < body.len() {
+            match bytes[i] {
+                b'\\' => i = self.command(body, i),
+                b'{' | b'}' | b'$' => i += 1,
+                b'~' => {
+                    self.current().text.push(' ');
+                    i += 1;
+                }
+                _ => {
+                    let ch = body[i..].chars().next().unwrap_or(' ');
+                    self.current().text.push(ch);
+                    i += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Handles the command starting at `start` and returns the position after it.
+    fn command(&mut self, body: &str, start: usize) -> usize {
+        let bytes = body.as_bytes();
+        let name_start = start + 1;
+        let Some(first) = body[name_start..].chars().next() else { return body.len() };
+        if !first.is_ascii_alphabetic() {
+            // control symbols: `\\` is a line break, `\%` etc. are literal characters
+            self.current().text.push(if first == '\\' { '\n' } else { first });
+            return name_start + first.len_utf8();
+        }
+
+        let name_end = name_start
+            + bytes[name_start..].iter().take_while(|b| b.is_ascii_alphabetic()).count();
+        let name = &body[name_start..name_end];
+        let mut pos = name_end;
+        if bytes.get(pos) == Some(&b'*') {
+            pos += 1;
+        }
+
+        if name == "begin" || name == "end" {
+            let (env, after) = match braced(body, skip_spaces(body, pos)) {
+                Some((env, after)) => (env, after),
+                None => return pos,
+            };
+            if name == "begin" && (FLOAT_ENVIRONMENTS.contains(&env) || HIDDEN_ENVIRONMENTS.contains(&env)) {
+                let end_tag = format!("\\end{{{}}}", env);
+                let end = body[after..].find(&end_tag).map_or(body.len(), |at| after + at + end_tag.len());
+                if FLOAT_ENVIRONMENTS.contains(&env) {
+                    self.current().floats += 1;
+                }
+                self.count(env);
+                return end;
+            }
+            return after;
+        }
+
+        if BREAK_COMMANDS.contains(&name) {
+            self.count(name);
+            self.segments.push(LatexSegment::default());
+            return pos;
+        }
+        if REFERENCE_COMMANDS.contains(&name) {
+            self.references += 1;
+            self.current().text.push_str("[1]");
+            return skip_arguments(body, pos);
+        }
+        if NON_CONTENT_COMMANDS.contains(&name) {
+            self.count(name);
+            return skip_arguments(body, pos);
+        }
+
+        // formatting and sectioning commands: drop the name and optional
+        // arguments, keep the text of the braced arguments
+        let mut pos = skip_spaces(body, pos);
+        while bytes.get(pos) == Some(&b'[') {
+            pos = matching(body, pos, b'[', b']');
+        }
+        pos
+    }
+
+    fn count(&mut self, name: &str) {
+        *self.stripped_commands.entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// Removes `%` comments (respecting `\%`) and returns the number removed.
+fn strip_comments(source: &str) -> (String, usize) {
+    let mut out = String::with_capacity(source.len());
+    let mut comments = 0;
+    for line in source.split_inclusive('\n') {
+        let bytes = line.as_bytes();
+        let mut cut = None;
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'%' => {
+                    cut = Some(i);
+                    break;
+                }
+                _ => i += 1,
+            }
+        }
+        match cut {
+            Some(at) => {
+                comments += 1;
+                out.push_str(&line[..at]);
+                // a comment also swallows the line break
+            }
+            None => out.push_str(line),
+        }
+    }
+    (out, comments)
+}
+
+/// Reads the braced argument of the first occurrence of `\name`.
+fn find_command_argument(source: &str, name: &str) -> Option<String> {
+    let at = source.find(&format!("\\{}", name))?;
+    let mut pos = at + name.len() + 1;
+    pos = skip_spaces(source, pos);
+    while source.as_bytes().get(pos) == Some(&b'[') {
+        pos = skip_spaces(source, matching(source, pos, b'[', b']'));
+    }
+    braced(source, pos).map(|(arg, _)| arg.trim().to_string())
+}
+
+/// Skips all `[...]` and `{...}` argument groups following a command.
+fn skip_arguments(body: &str, mut pos: usize) -> usize {
+    loop {
+        let next = skip_spaces(body, pos);
+        match body.as_bytes().get(next) {
+            Some(b'[') => pos = matching(body, next, b'[', b']'),
+            Some(b'{') => pos = matching(body, next, b'{', b'}'),
+            _ => return pos,
+        }
+    }
+}
+
+/// Returns the content of the `{...}` group at `pos` and the position after it.
+fn braced(body: &str, pos: usize) -> Option<(&str, usize)> {
+    if body.as_bytes().get(pos) != Some(&b'{') {
+        return None;
+    }
+    let end = matching(body, pos, b'{', b'}');
+    let inner_end = if body.as_bytes().get(end - 1) == Some(&b'}') { end - 1 } else { end };
+    Some((&body[pos + 1..inner_end], end))
+}
+
+/// Returns the position after the delimiter matching the one at `pos` (nesting-aware).
+fn matching(body: &str, pos: usize, open: u8, close: u8) -> usize {
+    let bytes = body.as_bytes();
+    let mut depth = 0usize;
+    let mut i = pos;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b if b == open => depth += 1,
+            b if b == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Skips spaces and tabs (not line breaks, which end paragraphs).
+fn skip_spaces(body: &str, pos: usize) -> usize {
+    pos + body.as_bytes()[pos.min(body.len())..]
+        .iter()
+        .take_while(|b| **b == b' ' || **b == b'\t')
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::estimate;
+
+    const PAPER: &str = r"\documentclass[11pt]{article}
+\usepackage{graphicx} % figures
+\title{Counting pages}
+\begin{document}
+\maketitle
+\section{Introduction}\label{sec:intro}
+Printing costs 5\% less, as \cite{smith2020, doe2021} found in \textbf{two} studies.
+% a full-line comment
+\begin{figure}[t]
+  \centering
+  \includegraphics[width=\linewidth]{plot.pdf}
+  \caption{A plot that takes space}
+\end{figure}
+See Section~\ref{sec:intro}.
+\newpage
+\section*{Appendix}
+Tables follow.
+\begin{table}\begin{tabular}{ll} a & b \\ \end{tabular}\end{table}
+\end{document}
+";
+
+    #[test]
+    fn prose_is_kept_and_markup_removed() {
+        let stripped = strip_latex(PAPER);
+        assert_eq!(stripped.document_class.as_deref(), Some("article"));
+        assert_eq!(stripped.comments, 2);
+        let uncommented = strip_comments(PAPER).0;
+        assert_eq!(stripped.preamble_bytes, uncommented.find(r"\begin{document}").unwrap());
+        assert_eq!(stripped.references, 2);
+        assert_eq!(stripped.segments.len(), 2);
+
+        let first = &stripped.segments[0];
+        let words: Vec<&str> = first.text.split_whitespace().collect();
+        assert_eq!(
+            words,
+            [
+                "Introduction", "Printing", "costs", "5%", "less,", "as", "[1]", "found", "in", "two",
+                "studies.", "See", "Section", "[1].",
+            ]
+        );
+        assert_eq!((first.floats, stripped.segments[1].floats), (1, 1));
+        assert_eq!(stripped.segments[1].text.split_whitespace().collect::<Vec<_>>(), ["Appendix", "Tables", "follow."]);
+
+        let removed: Vec<(&str, usize)> = stripped.stripped_commands.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(removed, [("figure", 1), ("label", 1), ("maketitle", 1), ("newpage", 1), ("table", 1)]);
+    }
+
+    #[test]
+    fn comments_respect_escaped_percent_signs() {
+        let (text, comments) = strip_comments("50\\% off % not this\n% nor this\nkept");
+        assert_eq!(comments, 2);
+        // like TeX, a comment also takes the line break
+        assert_eq!(text, "50\\% off kept");
+    }
+
+    #[test]
+    fn page_breaks_and_document_classes_set_the_estimate() {
+        let result = estimate(PAPER.as_bytes(), Some("paper.tex"), "{}").unwrap();
+        // each side of the \newpage is a page of its own
+        assert_eq!(result.page_count, 2);
+        assert_eq!(result.confidence, Some(0.3));
+        assert!(result.notes[0].ends_with("chars_per_page: 3000 (documentclass: article)"), "{:?}", result.notes);
+        let floats = "2 figure/table floats counted as 1000 characters each; 1 hard page breaks".to_string();
+        assert!(result.notes.contains(&floats));
+
+        let prose = "Words of a sentence that is set on the page. ".repeat(190);
+        let pages = |class: &str| {
+            let source = format!("\\documentclass{{{}}}\n\\begin{{document}}\n{}\n\\end{{document}}", class, prose);
+            estimate(source.as_bytes(), Some("doc.tex"), "{}").unwrap().page_count
+        };
+        assert_eq!([pages("article"), pages("book"), pages("beamer")], [3, 4, 22]);
+    }
+
+    #[test]
+    fn sources_are_recognized_by_their_commands() {
+        assert!(looks_like_latex(PAPER.as_bytes()));
+        assert!(looks_like_latex(b"% fragment\n\\begin{document}\nHi\n\\end{document}"));
+        assert!(!looks_like_latex(b"Use \\section for headings."));
+    }
+}
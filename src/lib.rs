@@ -1,4 +1,50 @@
 pub mod assembly;
+pub mod cache;
+pub mod comic;
+pub mod data_files;
+pub mod deadline;
+pub mod diagnostics;
+pub mod docbook;
 pub mod estimators;
+pub mod external_refs;
+pub mod fallback;
+#[cfg(feature = "fetch")]
+pub mod fetch;
 pub mod file_utils;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+pub mod formats;
+pub mod images;
+pub mod iwork;
+pub mod language;
+pub mod latex;
+pub mod logging;
+pub mod markup;
+pub mod mime;
+pub mod notebook;
+pub mod notes;
+pub mod ooxml;
+pub mod pdf;
+pub mod pdf_blank;
+pub mod pdf_catalog;
+pub mod pdf_color;
+pub mod pdf_crypt;
+pub mod pdf_page_iterator;
+pub mod pdf_portfolio;
+pub mod pdf_text;
+pub mod pdf_utils;
+pub mod postscript;
+pub mod pptx;
+pub mod printing;
+pub mod reading;
+pub mod records;
+pub mod producer;
 pub mod schema;
+pub mod stats;
+pub mod text_layout;
+pub mod validation;
+pub mod verification;
+pub mod worker;
+pub mod xlsx;
+pub mod xlsx_strings;
+pub mod xps;
@@ -0,0 +1,118 @@
+//! # Console Logging
+//!
+//! Every console line the module writes goes through `log`, which drops
+//! messages above the level set with `set_log_level`. The default is `error`,
+//! so production pages only see failures; information worth keeping about an
+//! estimate belongs in its notes rather than on the console.
+//!
+//! ## Levels
+//!
+//! - `silent`: nothing is written
+//! - `error`: failures only (`console.error`)
+//! - `info`: also progress and fallbacks (`console.log`)
+//! - `debug`: also diagnostic detail (`console.debug`)
+
+use crate::assembly::error_code;
+use crate::schema::EstimatorError;
+use serde_json::json;
+use std::cell::Cell;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+/// How much the module writes to the console, from least to most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Silent,
+    Error,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    /// Parses a level name (`"silent"`, `"error"`, `"info"`, or `"debug"`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "silent" => Some(LogLevel::Silent),
+            "error" => Some(LogLevel::Error),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// Level used until `set_log_level` is called.
+pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Error;
+
+thread_local! {
+    static LOG_LEVEL: Cell<LogLevel> = const { Cell::new(DEFAULT_LOG_LEVEL) };
+}
+
+/// Sets how much the module writes to the browser console.
+///
+/// # Parameters
+///
+/// * `level` - `"silent"`, `"error"` (the default), `"info"`, or `"debug"`
+///
+/// # Errors
+///
+/// Throws a JSON string `{error, error_code}` (`invalid_options`) for an
+/// unknown level, leaving the current level unchanged.
+///
+/// # Example
+///
+/// ```javascript
+/// import init, { set_log_level } from './pkg/page_counter_wasm.js';
+/// await init();
+/// set_log_level('silent');
+/// ```
+#[wasm_bindgen]
+pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+    let Some(parsed) = LogLevel::from_name(level) else {
+        let err = EstimatorError::InvalidOptions(format!(
+            "log level must be \"silent\", \"error\", \"info\", or \"debug\", got \"{}\"",
+            level
+        ));
+        return Err(JsValue::from_str(
+            &json!({"error": err.to_string(), "error_code": error_code(&err)}).to_string(),
+        ));
+    };
+    LOG_LEVEL.with(|current| current.set(parsed));
+    Ok(())
+}
+
+/// Returns the current log level.
+pub fn log_level() -> LogLevel {
+    LOG_LEVEL.with(Cell::get)
+}
+
+/// Writes a message to the console if `level` is enabled.
+///
+/// `LogLevel::Silent` messages are never written.
+pub fn log(level: LogLevel, message: &str) {
+    if level == LogLevel::Silent || level > log_level() {
+        return;
+    }
+    let message = JsValue::from_str(message);
+    match level {
+        LogLevel::Error => web_sys::console::error_1(&message),
+        LogLevel::Info => web_sys::console::log_1(&message),
+        LogLevel::Debug | LogLevel::Silent => web_sys::console::debug_1(&message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_are_ordered_by_verbosity() {
+        let names = ["silent", "error", "info", "debug"];
+        let levels: Vec<LogLevel> = names.iter().filter_map(|name| LogLevel::from_name(name)).collect();
+        assert_eq!(levels, [LogLevel::Silent, LogLevel::Error, LogLevel::Info, LogLevel::Debug]);
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(LogLevel::from_name("Debug"), None);
+        assert_eq!(LogLevel::from_name("warn"), None);
+        assert_eq!(log_level(), DEFAULT_LOG_LEVEL);
+    }
+}
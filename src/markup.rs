@@ -0,0 +1,345 @@
+//! # Lightweight Markup Rendering
+//!
+//! Reduces reStructuredText and AsciiDoc sources to the lines they would occupy
+//! when rendered, so that directive boilerplate and attribute entries are not
+//! paginated as content. The result feeds the line-based text pagination.
+//!
+//! ## Rendering Rules
+//!
+//! - Headings keep their text and are followed by a blank line; RST section
+//!   underlines/overlines and AsciiDoc `=` markers are dropped
+//! - Images (`.. image::`, `.. figure::`, `image::`) become a block of
+//!   `IMAGE_BLOCK_LINES` blank lines
+//! - Code and literal blocks (`.. code-block::`, `::` literal blocks, `----`
+//!   and `....` delimited blocks) are kept verbatim, one line per source line
+//! - Comments, targets, block attributes (`[source,java]`), and AsciiDoc
+//!   attribute entries (`:key: value`) are excluded
+
+/// Number of display lines an embedded image is assumed to occupy.
+pub const IMAGE_BLOCK_LINES: usize = 15;
+
+/// RST directives whose content is not rendered inline.
+const RST_HIDDEN_DIRECTIVES: &[&str] = &["toctree", "meta", "index", "contents", "only", "raw"];
+
+/// The rendered lines of a markup document and what was recognized while rendering.
+pub struct RenderedMarkup {
+    /// Text as it would be laid out on the page, one rendered line per line.
+    pub text: String,
+    /// Number of headings found.
+    pub headings: usize,
+    /// Number of image blocks found.
+    pub images: usize,
+    /// Number of lines inside code or literal blocks.
+    pub code_lines: usize,
+    /// Number of source lines that were excluded (directives, attributes, comments).
+    pub excluded_lines: usize,
+}
+
+impl RenderedMarkup {
+    fn new() -> Self {
+        Self { text: String::new(), headings: 0, images: 0, code_lines: 0, excluded_lines: 0 }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    fn push_heading(&mut self, title: &str) {
+        self.headings += 1;
+        self.push_line(title.trim());
+        self.push_line("");
+    }
+
+    fn push_image(&mut self) {
+        self.images += 1;
+        for _ in 0..IMAGE_BLOCK_LINES {
+            self.push_line("");
+        }
+    }
+}
+
+/// Renders reStructuredText into the lines it would occupy on the page.
+pub fn render_rst(source: &str) -> RenderedMarkup {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut out = RenderedMarkup::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        // overline + title + underline
+        if is_rst_adornment(trimmed)
+            && let (Some(title), Some(under)) = (lines.get(i + 1), lines.get(i + 2))
+            && !title.trim().is_empty()
+            && is_rst_adornment(under.trim())
+        {
+            out.push_heading(title);
+            out.excluded_lines += 2;
+            i += 3;
+            continue;
+        }
+        // title + underline
+        if !trimmed.is_empty()
+            && !line.starts_with(char::is_whitespace)
+            && let Some(under) = lines.get(i + 1)
+            && is_rst_adornment(under.trim())
+            && under.trim().len() >= trimmed.chars().count().min(3)
+        {
+            out.push_heading(trimmed);
+            out.excluded_lines += 1;
+            i += 2;
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix(".. ") {
+            let indent = indent_of(line);
+            let directive = rest.split_once("::").map(|(name, arg)| (name.trim(), arg.trim()));
+            let block_end = indented_block_end(&lines, i + 1, indent);
+            match directive {
+                Some(("image" | "figure", _)) => {
+                    out.push_image();
+                    // a figure's caption is the non-option text in its body
+                    for body in &lines[i + 1..block_end] {
+                        let body = body.trim();
+                        if body.is_empty() || body.starts_with(':') {
+                            out.excluded_lines += 1;
+                        } else {
+                            out.push_line(body);
+                        }
+                    }
+                }
+                Some(("code-block" | "code" | "sourcecode" | "literalinclude", _)) => {
+                    out.excluded_lines += 1;
+                    push_literal_block(&mut out, &lines[i + 1..block_end]);
+                }
+                Some((name, _)) if RST_HIDDEN_DIRECTIVES.contains(&name) => {
+                    out.excluded_lines += block_end - i;
+                }
+                Some((_, argument)) => {
+                    // admonitions and other containers render their content
+                    out.excluded_lines += 1;
+                    if !argument.is_empty() {
+                        out.push_line(argument);
+                    }
+                    for body in &lines[i + 1..block_end] {
+                        if !body.trim_start().starts_with(':') {
+                            out.push_line(body.trim());
+                        }
+                    }
+                }
+                None => {
+                    // comments and hyperlink targets
+                    out.excluded_lines += block_end - i;
+                }
+            }
+            i = block_end;
+            continue;
+        }
+
+        // a paragraph ending in `::` introduces a literal block
+        if trimmed.ends_with("::") {
+            let text = trimmed.trim_end_matches("::");
+            if !text.is_empty() {
+                out.push_line(&format!("{}:", text));
+            }
+            let start = skip_blank(&lines, i + 1);
+            let end = indented_block_end(&lines, start, indent_of(line));
+            push_literal_block(&mut out, &lines[start..end]);
+            i = end;
+            continue;
+        }
+
+        out.push_line(line);
+        i += 1;
+    }
+
+    out
+}
+
+/// Renders AsciiDoc into the lines it would occupy on the page.
+pub fn render_asciidoc(source: &str) -> RenderedMarkup {
+    let mut out = RenderedMarkup::new();
+    let mut delimiter: Option<&str> = None;
+
+    for line in source.lines() {
+        let trimmed = line.trim_end();
+
+        if let Some(open) = delimiter {
+            if trimmed == open {
+                delimiter = None;
+                out.excluded_lines += 1;
+            } else if open == "////" {
+                out.excluded_lines += 1;
+            } else {
+                out.code_lines += 1;
+                out.push_line(trimmed);
+            }
+            continue;
+        }
+
+        if is_asciidoc_delimiter(trimmed) {
+            delimiter = Some(trimmed);
+            out.excluded_lines += 1;
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix('=')
+            && let Some(title) = rest.trim_start_matches('=').strip_prefix(' ')
+        {
+            out.push_heading(title);
+            continue;
+        }
+        if trimmed.starts_with("image::") {
+            out.push_image();
+            continue;
+        }
+        if is_asciidoc_attribute_entry(trimmed)
+            || (trimmed.starts_with("//") && !trimmed.starts_with("///"))
+            || (trimmed.starts_with('[') && trimmed.ends_with(']'))
+            || trimmed.starts_with("include::")
+        {
+            out.excluded_lines += 1;
+            continue;
+        }
+
+        out.push_line(trimmed);
+    }
+
+    out
+}
+
+/// Checks whether a line is an RST section adornment (a repeated punctuation character).
+fn is_rst_adornment(line: &str) -> bool {
+    let mut chars = line.chars();
+    let Some(first) = chars.next() else { return false };
+    "=-`:'\"~^_*+#<>.".contains(first) && line.len() >= 2 && chars.all(|c| c == first)
+}
+
+/// Checks whether a line opens or closes a delimited AsciiDoc listing, literal, or comment block.
+fn is_asciidoc_delimiter(line: &str) -> bool {
+    line.len() >= 4 && (line.chars().all(|c| c == '-') || line.chars().all(|c| c == '.'))
+        || line == "////"
+}
+
+/// Checks for an AsciiDoc attribute entry (`:key: value`, `:key!:`).
+fn is_asciidoc_attribute_entry(line: &str) -> bool {
+    let Some(rest) = line.strip_prefix(':') else { return false };
+    rest.split_once(':').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && name
+                .trim_end_matches('!')
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn skip_blank(lines: &[&str], mut i: usize) -> usize {
+    while i < lines.len() && lines[i].trim().is_empty() {
+        i += 1;
+    }
+    i
+}
+
+/// Returns the end (exclusive) of the block indented deeper than `indent` starting at `start`.
+///
+/// Blank lines inside the block are included; trailing blank lines are not.
+fn indented_block_end(lines: &[&str], start: usize, indent: usize) -> usize {
+    let mut end = start;
+    let mut i = start;
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        if indent_of(lines[i]) <= indent {
+            break;
+        }
+        i += 1;
+        end = i;
+    }
+    end
+}
+
+/// Emits a literal/code block verbatim (dedented), dropping directive options.
+fn push_literal_block(out: &mut RenderedMarkup, body: &[&str]) {
+    let dedent = body
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_of(l))
+        .min()
+        .unwrap_or(0);
+    let mut in_options = true;
+    for line in body {
+        if in_options && (line.trim_start().starts_with(':') || line.trim().is_empty()) {
+            out.excluded_lines += 1;
+            continue;
+        }
+        in_options = false;
+        out.code_lines += 1;
+        out.push_line(line.get(dedent..).unwrap_or("").trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{estimate, ANTORA_PAGE, SPHINX_PAGE};
+
+    fn rendered_lines(markup: &RenderedMarkup) -> Vec<&str> {
+        markup.text.lines().filter(|line| !line.is_empty()).collect()
+    }
+
+    #[test]
+    fn sphinx_page_renders_its_content_only() {
+        let markup = render_rst(SPHINX_PAGE);
+        assert_eq!((markup.headings, markup.images, markup.code_lines), (2, 1, 5));
+        assert_eq!(
+            rendered_lines(&markup),
+            [
+                "Installation",
+                "The estimator ships as a WebAssembly package. Install it with npm and import",
+                "the functions you need; nothing else has to be set up.",
+                "Requirements",
+                "Node 18 or newer is required for the examples below.",
+                "import init, { estimate_document } from \"page-counter-wasm\";",
+                "await init();",
+                "const result = estimate_document(bytes, \"report.pdf\", \"{}\");",
+                "The shell command is:",
+                "npm install page-counter-wasm",
+            ]
+        );
+        assert!(markup.text.contains(&"\n".repeat(IMAGE_BLOCK_LINES)));
+    }
+
+    #[test]
+    fn antora_page_renders_its_content_only() {
+        let markup = render_asciidoc(ANTORA_PAGE);
+        assert_eq!((markup.headings, markup.images, markup.code_lines), (2, 1, 5));
+        let lines = rendered_lines(&markup);
+        assert_eq!(lines[0], "Installation");
+        assert!(!lines.iter().any(|line| line.starts_with(':') || line.starts_with("//") || line.starts_with('[')));
+        assert!(lines.contains(&"npm install page-counter-wasm"));
+        // attribute entries, block attributes, delimiters, and the comment
+        assert_eq!(markup.excluded_lines, 4 + 1 + 4 + 1);
+    }
+
+    #[test]
+    fn both_markups_of_a_page_estimate_alike() {
+        // the same page, rendered by either toolchain, takes the same space
+        let rst = render_rst(SPHINX_PAGE).text.lines().count();
+        let adoc = render_asciidoc(ANTORA_PAGE).text.lines().count();
+        assert!(rst.abs_diff(adoc) * 5 <= rst, "{} vs {} rendered lines", rst, adoc);
+
+        let options = r#"{"lines_per_page":20}"#;
+        let sphinx = estimate(SPHINX_PAGE.as_bytes(), Some("install.rst"), options).unwrap();
+        let antora = estimate(ANTORA_PAGE.as_bytes(), Some("install.adoc"), options).unwrap();
+        assert_eq!(sphinx.page_count, antora.page_count);
+        let note = "RST rendered: 2 headings, 1 images, 5 code lines, 17 source lines excluded".to_string();
+        assert!(sphinx.notes.contains(&note), "{:?}", sphinx.notes);
+    }
+}
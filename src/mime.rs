@@ -0,0 +1,452 @@
+//! # MIME Message Parsing
+//!
+//! A small, tolerant MIME decoder for RFC 822 messages (`.eml` exports) and
+//! other MIME-wrapped documents. It splits multipart bodies into their parts,
+//! decodes `base64` and `quoted-printable` transfer encodings, and exposes the
+//! headers needed to classify each part.
+//!
+//! Malformed input degrades gracefully: a missing closing boundary ends the
+//! last part at the end of the input, and undecodable bodies are returned raw.
+
+use crate::formats::format_for_mime_type;
+use base64::Engine;
+
+/// Maximum nesting depth of multipart bodies that will be expanded.
+const MAX_MULTIPART_DEPTH: usize = 8;
+
+/// A single MIME part with its headers and decoded body.
+pub struct MimePart {
+    /// Header fields in order of appearance, with folded lines unfolded.
+    pub headers: Vec<(String, String)>,
+    /// The body after transfer decoding (empty for multipart containers).
+    pub body: Vec<u8>,
+    /// Child parts of a multipart body.
+    pub children: Vec<MimePart>,
+}
+
+impl MimePart {
+    /// Returns the first header value with the given name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the lowercased media type (e.g. `"text/plain"`), defaulting to `text/plain`.
+    pub fn content_type(&self) -> String {
+        self.header("Content-Type")
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "text/plain".into())
+    }
+
+    /// Returns the attachment filename from `Content-Disposition` or the `name` content-type parameter.
+    pub fn filename(&self) -> Option<String> {
+        self.header("Content-Disposition")
+            .and_then(|v| header_param(v, "filename"))
+            .or_else(|| self.header("Content-Type").and_then(|v| header_param(v, "name")))
+    }
+
+    /// Whether the part is explicitly marked as an attachment or carries a filename.
+    pub fn is_attachment(&self) -> bool {
+        let disposition = self
+            .header("Content-Disposition")
+            .map(|v| v.trim_start().to_lowercase().starts_with("attachment"))
+            .unwrap_or(false);
+        disposition || self.filename().is_some()
+    }
+
+    /// Returns the leaf parts of this part in document order.
+    pub fn leaves(&self) -> Vec<&MimePart> {
+        if self.children.is_empty() {
+            vec![self]
+        } else {
+            self.children.iter().flat_map(|c| c.leaves()).collect()
+        }
+    }
+}
+
+/// Parses a MIME message or part.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw message, starting with its header block
+///
+/// # Returns
+///
+/// The root part. Multipart bodies are expanded into `children`; leaf bodies
+/// are decoded according to their `Content-Transfer-Encoding`.
+pub fn parse_mime(bytes: &[u8]) -> MimePart {
+    parse_part(bytes, 0)
+}
+
+fn parse_part(bytes: &[u8], depth: usize) -> MimePart {
+    let (headers, body) = split_headers(bytes);
+    let mut part = MimePart { headers, body: Vec::new(), children: Vec::new() };
+
+    let content_type = part.header("Content-Type").unwrap_or("").to_string();
+    if content_type.trim_start().to_lowercase().starts_with("multipart/")
+        && depth < MAX_MULTIPART_DEPTH
+        && let Some(boundary) = header_param(&content_type, "boundary")
+    {
+        part.children = split_multipart(body, &boundary)
+            .into_iter()
+            .map(|child| parse_part(child, depth + 1))
+            .collect();
+        if !part.children.is_empty() {
+            return part;
+        }
+    }
+
+    let encoding = part
+        .header("Content-Transfer-Encoding")
+        .map(|v| v.trim().to_lowercase())
+        .unwrap_or_default();
+    part.body = match encoding.as_str() {
+        "base64" => decode_base64_lenient(body),
+        "quoted-printable" => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    };
+    part
+}
+
+/// Splits the header block from the body, unfolding continuation lines.
+fn split_headers(bytes: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let (head, body) = match find_blank_line(bytes) {
+        Some((head_end, body_start)) => (&bytes[..head_end], &bytes[body_start..]),
+        None => (bytes, &bytes[bytes.len()..]),
+    };
+
+    let text = String::from_utf8_lossy(head);
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+/// Finds the end of the header block: returns (end of headers, start of body).
+fn find_blank_line(bytes: &[u8]) -> Option<(usize, usize)> {
+    // a part may have no headers at all
+    if bytes.starts_with(b"\r\n") {
+        return Some((0, 2));
+    }
+    if bytes.starts_with(b"\n") {
+        return Some((0, 1));
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"\r\n\r\n") {
+            return Some((i, i + 4));
+        }
+        if bytes[i..].starts_with(b"\n\n") {
+            return Some((i, i + 2));
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Splits a multipart body on its boundary, ignoring preamble and epilogue.
+///
+/// A missing closing boundary ends the last part at the end of the input.
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    let mut parts = Vec::new();
+    let mut current: Option<usize> = None;
+    let mut pos = 0;
+
+    while pos < body.len() {
+        let line_end = body[pos..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(body.len(), |p| pos + p + 1);
+        let line = trim_line_end(&body[pos..line_end]);
+
+        if line.starts_with(delimiter) {
+            if let Some(start) = current.take() {
+                // the line break before the delimiter belongs to the delimiter
+                parts.push(trim_line_end(&body[start..pos]));
+            }
+            if line[delimiter.len()..].starts_with(b"--") {
+                return parts;
+            }
+            current = Some(line_end);
+        }
+        pos = line_end;
+    }
+
+    if let Some(start) = current {
+        parts.push(&body[start.min(body.len())..]);
+    }
+    parts
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let mut end = line.len();
+    while end > 0 && (line[end - 1] == b'\n' || line[end - 1] == b'\r') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Extracts a parameter from a structured header value (e.g. `boundary="abc"`).
+pub fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, val) = param.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case(name) {
+            return None;
+        }
+        let val = val.trim().trim_matches('"');
+        if val.is_empty() { None } else { Some(val.to_string()) }
+    })
+}
+
+/// Decodes base64 while ignoring line breaks and other non-alphabet characters.
+pub fn decode_base64_lenient(input: &[u8]) -> Vec<u8> {
+    let cleaned: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|b| b.is_ascii_alphanumeric() || *b == b'+' || *b == b'/' || *b == b'=')
+        .collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(&cleaned)
+        .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(cleaned.trim_ascii_end()))
+        .unwrap_or_else(|_| input.to_vec())
+}
+
+/// Decodes quoted-printable data (`=XX` escapes and `=` soft line breaks).
+pub fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'=' {
+            if input[i + 1..].starts_with(b"\r\n") {
+                i += 3;
+                continue;
+            }
+            if input[i + 1..].starts_with(b"\n") {
+                i += 2;
+                continue;
+            }
+            if let (Some(h), Some(l)) = (
+                input.get(i + 1).and_then(|b| (*b as char).to_digit(16)),
+                input.get(i + 2).and_then(|b| (*b as char).to_digit(16)),
+            ) {
+                out.push((h * 16 + l) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(input[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Reduces HTML to its visible text: tags, comments, scripts, and styles are
+/// removed, common entities decoded, and whitespace collapsed.
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() / 2);
+    // ASCII lowercasing keeps byte offsets aligned with the original
+    let lower = html.to_ascii_lowercase();
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &lower[i..];
+        if rest.starts_with("<!--") {
+            i += rest.find("-->").map_or(rest.len(), |p| p + 3);
+        } else if rest.starts_with("<script") || rest.starts_with("<style") {
+            let close = if rest.starts_with("<script") { "</script>" } else { "</style>" };
+            i += rest.find(close).map_or(rest.len(), |p| p + close.len());
+        } else if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |p| p + 1);
+            let tag = &rest[..end];
+            let block = ["<br", "<p", "</p", "<div", "</div", "<li", "<tr", "<h"];
+            if block.iter().any(|b| tag.starts_with(b)) {
+                out.push('\n');
+            }
+            i += end;
+        } else {
+            let ch = html[i..].chars().next().unwrap_or(' ');
+            out.push(ch);
+            i += ch.len_utf8();
+        }
+    }
+
+    let decoded = out
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    decoded
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extracts the visible text of an MHT (MHTML) web archive.
+///
+/// The archive is parsed as a MIME message and its root part (see `mht_root`)
+/// is reduced with `html_to_text`, or taken as is when it isn't HTML.
+pub fn mht_to_text(bytes: &[u8]) -> Option<String> {
+    let message = parse_mime(bytes);
+    mht_root(&message).map(part_text)
+}
+
+/// Finds the root document of an MHT web archive.
+///
+/// # Returns
+///
+/// The part named by the `start` parameter of the `multipart/related` type
+/// (matched against `Content-ID`), else the first `text/html` part, else the
+/// first other `text/` part. The other parts are resources (images,
+/// stylesheets) the root refers to.
+pub fn mht_root(message: &MimePart) -> Option<&MimePart> {
+    let leaves = message.leaves();
+    let start = message.header("Content-Type").and_then(|value| header_param(value, "start"));
+    let content_id =
+        |part: &MimePart| part.header("Content-ID").map(|id| id.trim().trim_matches(['<', '>']).to_string());
+    start
+        .and_then(|start| {
+            let start = start.trim_matches(['<', '>']).to_string();
+            leaves.iter().find(|part| content_id(part).as_ref() == Some(&start))
+        })
+        .or_else(|| leaves.iter().find(|part| part.content_type() == "text/html"))
+        .or_else(|| leaves.iter().find(|part| part.content_type().starts_with("text/")))
+        .copied()
+}
+
+/// The visible text of a part: HTML reduced with `html_to_text`, other text as is.
+pub fn part_text(part: &MimePart) -> String {
+    let body = String::from_utf8_lossy(&part.body);
+    if part.content_type() == "text/html" { html_to_text(&body) } else { body.into_owned() }
+}
+
+/// Checks whether the input looks like an MHT web archive.
+///
+/// The header block must declare `MIME-Version` and a `multipart/related`
+/// content type, as browsers ("Save as Web Archive") and Word write them.
+/// Browser archives also carry mail headers (`From: <Saved by Blink>`), so
+/// this is checked before `looks_like_email`.
+pub fn looks_like_mht(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(8192)];
+    let (headers, _) = split_headers(head);
+    let header = |name: &str| headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+    let related = |value: &str| value.trim_start().to_lowercase().starts_with("multipart/related");
+    header("MIME-Version").is_some() && header("Content-Type").is_some_and(related)
+}
+
+/// Checks whether the input looks like an RFC 822 message.
+///
+/// The header block at the start of the input must contain a `From:` field and
+/// at least one other typical mail header (`Received:`, `Date:`, `Subject:`,
+/// `Message-ID:`, `Return-Path:`).
+pub fn looks_like_email(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(8192)];
+    let (headers, _) = split_headers(head);
+    let Some((first, _)) = headers.first() else { return false };
+    if first.contains(' ') {
+        return false;
+    }
+    let has = |name: &str| headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(name));
+    has("From")
+        && ["Received", "Date", "Subject", "Message-ID", "Return-Path"]
+            .iter()
+            .any(|h| has(h))
+}
+
+/// Suggests a filename extension for an attachment's media type: the first
+/// extension of the format registered for it (see the `formats` module).
+pub fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    format_for_mime_type(content_type).and_then(|format| format.extensions.first().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{build_email, build_word_mht};
+
+    #[test]
+    fn quoted_printable_escapes_and_soft_breaks_are_decoded() {
+        assert_eq!(decode_quoted_printable(b"caf=C3=A9 cr=\r\n=C3=A8me =3D 1"), "café crème = 1".as_bytes());
+        // a stray `=` is kept
+        assert_eq!(decode_quoted_printable(b"a =zz b="), b"a =zz b=");
+    }
+
+    #[test]
+    fn base64_bodies_are_decoded_across_lines() {
+        assert_eq!(decode_base64_lenient(b"aGVsbG8g\r\nd29ybGQ=\r\n"), b"hello world");
+        assert_eq!(decode_base64_lenient(b"aGVsbG8"), b"hello");
+        assert_eq!(decode_base64_lenient(b"abcde"), b"abcde");
+    }
+
+    #[test]
+    fn multipart_message_is_split_into_parts() {
+        let body = "Please find the contract attached. The fee is 1 000 € = net.\n".repeat(3);
+        let eml = build_email(&body, &[("contract.pdf", "application/pdf", b"%PDF-1.4 stub")], true);
+        assert!(looks_like_email(&eml));
+        let message = parse_mime(&eml);
+        let leaves = message.leaves();
+        assert_eq!(leaves.len(), 2);
+        assert_eq!(String::from_utf8_lossy(&leaves[0].body), body.trim_end().replace('\n', "\r\n"));
+        assert!(!leaves[0].is_attachment());
+        assert_eq!(leaves[1].content_type(), "application/pdf");
+        assert_eq!(leaves[1].filename().as_deref(), Some("contract.pdf"));
+        assert_eq!(leaves[1].body, b"%PDF-1.4 stub");
+    }
+
+    #[test]
+    fn missing_closing_boundary_ends_the_last_part_at_the_end() {
+        let attachments: [(&str, &str, &[u8]); 2] =
+            [("a.txt", "text/plain", b"first"), ("b.txt", "text/plain", b"second")];
+        let closed = parse_mime(&build_email("Body.", &attachments, true));
+        let open = parse_mime(&build_email("Body.", &attachments, false));
+        let bodies = |part: &MimePart| part.leaves().iter().map(|p| p.body.clone()).collect::<Vec<_>>();
+        assert_eq!(bodies(&open), bodies(&closed));
+        assert_eq!(bodies(&open)[2], b"second");
+    }
+
+    #[test]
+    fn headers_alone_are_not_an_email() {
+        assert!(!looks_like_email(b"From: someone\r\n\r\nno other header"));
+        assert!(!looks_like_email(b"Dear team, From: here\r\nSubject: x\r\n\r\n"));
+    }
+
+    #[test]
+    fn web_archive_root_is_the_start_part() {
+        let mht = build_word_mht(&["Budget = approved", "Café opens at 9"], 500);
+        assert!(looks_like_mht(&mht));
+        // Word lists the file list before the root, which the start parameter names
+        let text = mht_to_text(&mht).unwrap();
+        assert!(text.contains("Budget = approved") && text.contains("Café opens at 9"), "{}", text);
+        assert!(!text.contains("MsoNormal") && !text.contains("o:File"), "{}", text);
+
+        // without a start parameter, the first HTML part is the root
+        let browser = concat!(
+            "From: <Saved by Blink>\r\nSubject: Report\r\nMIME-Version: 1.0\r\n",
+            "Content-Type: multipart/related; type=\"text/html\"; boundary=\"b\"\r\n\r\n",
+            "--b\r\nContent-Type: text/css\r\n\r\np { margin: 0 }\r\n",
+            "--b\r\nContent-Type: text/html\r\n\r\n<p>Saved page</p>\r\n",
+            "--b--\r\n",
+        );
+        assert!(looks_like_mht(browser.as_bytes()));
+        assert_eq!(mht_to_text(browser.as_bytes()).unwrap().trim(), "Saved page");
+        // an email is multipart/mixed, not a web archive
+        assert!(!looks_like_mht(&build_email("Body.", &[], true)));
+    }
+}
@@ -0,0 +1,194 @@
+//! # Jupyter Notebooks
+//!
+//! Reads `.ipynb` files (nbformat 4), which are JSON documents holding a list
+//! of cells. Printed (e.g. with "Download as PDF"), each cell type looks
+//! different, so the estimator paginates them separately:
+//!
+//! - Markdown cells are prose, counted like Markdown text
+//! - Code cells are monospace source, counted line by line like source code
+//! - Stream and text outputs (`print`, `repr`, tracebacks) are monospace too
+//! - Image outputs (`image/png`, `image/jpeg`, `image/svg+xml`) are fixed-height
+//!   blocks of `image_height_mm`, whatever their pixel size
+//!
+//! Raw cells are skipped, as nbconvert leaves them out of most formats.
+//! Execution counts and cell metadata don't print and are ignored.
+//!
+//! ## Detection
+//!
+//! Files are routed by the `.ipynb` extension. Without a filename, JSON whose
+//! top-level object has `nbformat` and `cells` keys is taken as a notebook.
+//! Notebooks usually end with the `nbformat` key, so the whole input is searched.
+
+use crate::file_utils::strip_bom;
+use crate::pdf_utils::find_bytes;
+use crate::text_layout::clean_terminal_output;
+use serde_json::Value;
+
+/// Output data types counted as images.
+const IMAGE_MIME_TYPES: [&str; 3] = ["image/png", "image/jpeg", "image/svg+xml"];
+
+/// The printed content of a notebook, by cell type.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NotebookContent {
+    /// Source of each Markdown cell.
+    pub markdown: Vec<String>,
+    /// Source of each code cell.
+    pub code: Vec<String>,
+    /// Text of each stream, `text/plain`, and error output.
+    pub outputs: Vec<String>,
+    /// Number of image outputs.
+    pub images: usize,
+    /// Number of raw cells, which are skipped.
+    pub raw_cells: usize,
+}
+
+/// Whether the input looks like a Jupyter notebook: a JSON object with `nbformat` and `cells` keys.
+pub fn looks_like_notebook(bytes: &[u8]) -> bool {
+    let bytes = strip_bom(bytes);
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace());
+    start.is_some_and(|start| bytes[start] == b'{')
+        && find_bytes(bytes, b"\"nbformat\"").is_some()
+        && find_bytes(bytes, b"\"cells\"").is_some()
+}
+
+/// Parses a notebook into its printed content.
+///
+/// # Returns
+///
+/// The content, or an error message when the input isn't JSON or isn't an
+/// nbformat 4 notebook (nbformat 3 keeps its cells in `worksheets`).
+pub fn parse_notebook(bytes: &[u8]) -> Result<NotebookContent, String> {
+    let notebook: Value =
+        serde_json::from_slice(strip_bom(bytes)).map_err(|e| format!("Notebook is not valid JSON: {}", e))?;
+    let version = notebook.get("nbformat").and_then(Value::as_u64);
+    let cells = match (version, notebook.get("cells").and_then(Value::as_array)) {
+        (Some(4..), Some(cells)) => cells,
+        (Some(4..), None) => return Err("Notebook has no cells list".into()),
+        (Some(version), _) => return Err(format!("Unsupported notebook format version {}", version)),
+        (None, _) => return Err("Notebook has no nbformat version".into()),
+    };
+
+    let mut content = NotebookContent::default();
+    for cell in cells {
+        let source = multiline_text(cell.get("source"));
+        match cell.get("cell_type").and_then(Value::as_str) {
+            Some("markdown") => content.markdown.push(source),
+            Some("code") => {
+                content.code.push(source);
+                let outputs = cell.get("outputs").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+                for output in outputs {
+                    add_output(output, &mut content);
+                }
+            }
+            _ => content.raw_cells += 1,
+        }
+    }
+    Ok(content)
+}
+
+/// Adds a code cell output: its image, or else its text.
+fn add_output(output: &Value, content: &mut NotebookContent) {
+    match output.get("output_type").and_then(Value::as_str) {
+        Some("stream") => content.outputs.push(multiline_text(output.get("text"))),
+        Some("execute_result") | Some("display_data") => {
+            let data = output.get("data");
+            // a figure also carries a text/plain repr (`<Figure size ...>`), which isn't printed
+            if IMAGE_MIME_TYPES.iter().any(|mime| data.and_then(|data| data.get(mime)).is_some()) {
+                content.images += 1;
+            } else if let Some(text) = data.and_then(|data| data.get("text/plain")) {
+                content.outputs.push(multiline_text(Some(text)));
+            }
+        }
+        Some("error") => {
+            let traceback = multiline_text(output.get("traceback"));
+            // tracebacks are colored with ANSI escape sequences
+            let text = clean_terminal_output(&traceback).map_or(traceback, |cleaned| cleaned.text);
+            content.outputs.push(text);
+        }
+        _ => {}
+    }
+}
+
+/// Reads nbformat multiline text, stored as a string or as a list of lines.
+///
+/// The lines of a list keep their own `\n`, except tracebacks, whose lines are joined with one.
+fn multiline_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => {
+            let lines: Vec<&str> = lines.iter().filter_map(Value::as_str).collect();
+            let rest = lines.len().saturating_sub(1);
+            if lines[..rest].iter().all(|line| line.ends_with('\n')) {
+                lines.concat()
+            } else {
+                lines.join("\n")
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::validation::validate_bytes;
+
+    #[test]
+    fn cells_are_read_by_type() {
+        let content = parse_notebook(&build_notebook(3)).unwrap();
+        assert_eq!((content.markdown.len(), content.code.len(), content.images, content.raw_cells), (3, 4, 3, 1));
+        assert!(content.markdown[0].starts_with("## 1. Sales by region\n\nMonthly totals"), "{}", content.markdown[0]);
+        assert_eq!(content.code[0].lines().count(), 12);
+        // the table of each section, then the error; the figures' text reprs aren't printed
+        assert_eq!(content.outputs.len(), 4);
+        assert!(!content.outputs.iter().any(|output| output.contains("<Figure")));
+        assert_eq!(content.outputs[0].lines().count(), 8);
+        let traceback = format!("{}\nKeyError{}Traceback (most recent call last)", "-".repeat(75), " ".repeat(34));
+        assert_eq!(content.outputs[3], format!("{}\nKeyError: 'east'", traceback));
+    }
+
+    #[test]
+    fn notebooks_land_near_their_printed_length() {
+        let notebook = build_notebook(10);
+        assert!(looks_like_notebook(&notebook));
+        let result = estimate(&notebook, Some("analysis.ipynb"), "{}").unwrap();
+        assert_eq!(result.method.as_deref(), Some("notebook"));
+        assert!(result.page_count.abs_diff(NOTEBOOK_PAGES) <= 1, "{} pages: {:?}", result.page_count, result.notes);
+        assert_eq!(
+            result.notes[..4],
+            [
+                "Markdown: 10 cells, 1809 chars at 1800 per page (1.00 pages)",
+                "Code: 11 cells, 131 display lines at 60 per page (2.18 pages)",
+                "Outputs: 11 text outputs, 93 display lines (1.55 pages); 10 images at 90 mm (3.66 pages)",
+                "1 raw cells skipped",
+            ]
+        );
+        // sniffed without a filename, the same
+        assert_eq!(estimate(&notebook, None, "{}").unwrap().page_count, result.page_count);
+        // read as JSON data, the base64 plots count as text
+        assert!(estimate(&notebook, Some("analysis.json"), "{}").unwrap().page_count > 2 * NOTEBOOK_PAGES);
+
+        let small_plots = estimate(&notebook, Some("analysis.ipynb"), r#"{"image_height_mm":45}"#).unwrap();
+        assert!(small_plots.notes[2].ends_with("10 images at 45 mm (1.83 pages)"), "{:?}", small_plots.notes);
+        assert_eq!(small_plots.page_count, result.page_count - 2);
+    }
+
+    #[test]
+    fn unreadable_notebooks_are_reported() {
+        let cases: [(&[u8], &str); 4] = [
+            (b"{\"nbformat\": 4, \"cells\": [", "Notebook is not valid JSON"),
+            (b"{\"nbformat\": 3, \"cells\": [], \"worksheets\": []}", "Unsupported notebook format version 3"),
+            (b"{\"nbformat\": 4, \"cells\": {}}", "Notebook has no cells list"),
+            (b"{\"cells\": []}", "Notebook has no nbformat version"),
+        ];
+        for (bytes, message) in cases {
+            let error = parse_notebook(bytes).unwrap_err();
+            assert!(error.starts_with(message), "{}", error);
+            let report = validate_bytes(bytes, Some("broken.ipynb"));
+            assert!(!report.valid && report.issues[0].starts_with(message), "{:?}", report.issues);
+        }
+        assert!(validate_bytes(&build_notebook(2), None).valid);
+        assert!(!looks_like_notebook(b"[{\"nbformat\": 4, \"cells\": []}]"));
+    }
+}
@@ -0,0 +1,153 @@
+//! # Estimate Notes
+//!
+//! Trims the notes of an estimate to the requested `verbosity`, so that large
+//! documents (e.g. workbooks with hundreds of sheets) don't bloat the JSON
+//! while debugging can still ask for every detail.
+//!
+//! ## Levels
+//!
+//! - `0`: warnings only (notes starting with `⚠` or `Note:`)
+//! - `1` (default): summary notes; per-item detail is left to structured fields
+//!   such as `sheets`
+//! - `2`: full per-sheet and per-strategy detail
+//!
+//! Each level has a hard cap on the number of notes. Notes over the cap are
+//! collapsed into a final `… and N more` note, keeping warnings in preference
+//! to other notes. Otherwise the estimator's order is preserved, so the notes
+//! are deterministic for a given input.
+
+use crate::schema::{EstimateOptions, EstimateResult};
+
+/// Verbosity used when the option is not set.
+pub const DEFAULT_VERBOSITY: u8 = 1;
+
+/// Verbosity at which estimators add per-item detail notes.
+pub const DETAIL_VERBOSITY: u8 = 2;
+
+/// Maximum number of notes (including the overflow note) at each verbosity level.
+const NOTE_CAPS: [usize; 3] = [5, 12, 200];
+
+/// Returns the requested verbosity, clamped to the supported levels.
+pub fn verbosity(options: &EstimateOptions) -> u8 {
+    options.verbosity.unwrap_or(DEFAULT_VERBOSITY).min(DETAIL_VERBOSITY)
+}
+
+/// Checks whether a note is a warning, which every verbosity level keeps.
+pub fn is_warning(note: &str) -> bool {
+    note.starts_with('⚠') || note.starts_with("Note:")
+}
+
+/// Trims notes to a verbosity level.
+///
+/// # Arguments
+///
+/// * `notes` - The notes to trim, in the order the estimator produced them
+/// * `verbosity` - Level from 0 (warnings only) to 2 (full detail)
+///
+/// # Notes
+///
+/// - At level 0 every note that isn't a warning is removed
+/// - When more notes remain than the level's cap, warnings are kept first, then
+///   other notes in order, and the rest are counted in a final `… and N more` note
+pub fn limit_notes(notes: &mut Vec<String>, verbosity: u8) {
+    let verbosity = verbosity.min(DETAIL_VERBOSITY);
+    if verbosity == 0 {
+        notes.retain(|note| is_warning(note));
+    }
+
+    let cap = NOTE_CAPS[verbosity as usize];
+    if notes.len() <= cap {
+        return;
+    }
+
+    let total = notes.len();
+    let keep = cap - 1;
+    let warnings = notes.iter().filter(|note| is_warning(note)).count();
+    let mut warnings_left = warnings.min(keep);
+    let mut others_left = keep - warnings_left;
+    notes.retain(|note| {
+        let left = if is_warning(note) { &mut warnings_left } else { &mut others_left };
+        if *left == 0 {
+            return false;
+        }
+        *left -= 1;
+        true
+    });
+    notes.push(format!("… and {} more", total - notes.len()));
+}
+
+/// Trims the notes of an estimate according to the `verbosity` option.
+pub fn apply_verbosity(result: &mut EstimateResult, options: &EstimateOptions) {
+    limit_notes(&mut result.notes, verbosity(options));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn notes(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn warnings_only_at_level_zero() {
+        let mut kept = notes(&["Paper: A4", "⚠ Could not read sheet 'x'", "Note: estimate", "3 sheets"]);
+        limit_notes(&mut kept, 0);
+        assert_eq!(kept, notes(&["⚠ Could not read sheet 'x'", "Note: estimate"]));
+    }
+
+    #[test]
+    fn notes_under_the_cap_are_unchanged() {
+        let original: Vec<String> = (0..12).map(|i| format!("detail {}", i)).collect();
+        let mut kept = original.clone();
+        limit_notes(&mut kept, 1);
+        assert_eq!(kept, original);
+    }
+
+    #[test]
+    fn overflow_collapses_into_a_final_note_keeping_warnings_first() {
+        let mut kept: Vec<String> = (0..30).map(|i| format!("detail {}", i)).collect();
+        kept.push("⚠ late warning".into());
+        limit_notes(&mut kept, 1);
+        assert_eq!(kept.len(), 12);
+        assert_eq!(kept[0], "detail 0");
+        assert!(kept.contains(&"⚠ late warning".to_string()));
+        assert_eq!(kept[9], "detail 9");
+        assert_eq!(kept[11], "… and 20 more");
+
+        // more warnings than fit: only the first warnings are kept, in order
+        let mut warnings: Vec<String> = (0..8).map(|i| format!("⚠ warning {}", i)).collect();
+        limit_notes(&mut warnings, 0);
+        assert_eq!(warnings, notes(&["⚠ warning 0", "⚠ warning 1", "⚠ warning 2", "⚠ warning 3", "… and 4 more"]));
+    }
+
+    #[test]
+    fn levels_above_the_last_act_as_full_detail() {
+        let options = EstimateOptions { verbosity: Some(9), ..Default::default() };
+        assert_eq!(verbosity(&options), DETAIL_VERBOSITY);
+        assert_eq!(verbosity(&EstimateOptions::default()), DEFAULT_VERBOSITY);
+        let mut kept: Vec<String> = (0..250).map(|i| format!("detail {}", i)).collect();
+        limit_notes(&mut kept, 9);
+        assert_eq!(kept.len(), 200);
+        assert_eq!(kept[199], "… and 51 more");
+    }
+
+    #[test]
+    fn large_workbook_notes_are_capped_and_sheets_carry_the_detail() {
+        let workbook = build_xlsx(&vec![(3, 2); 200]);
+        let summary = estimate(&workbook, Some("book.xlsx"), "{}").unwrap();
+        assert!(summary.notes.len() <= 12, "{:?}", summary.notes);
+        assert!(summary.notes.iter().any(|n| n.starts_with("200 sheets (200 with content, 0 empty)")));
+        let sheets = summary.sheets.as_ref().unwrap();
+        assert_eq!(sheets.len(), 200);
+        assert!(sheets.iter().all(|s| s.readable && s.pages == 1));
+
+        let detail = estimate(&workbook, Some("book.xlsx"), r#"{"verbosity":2}"#).unwrap();
+        assert_eq!(detail.notes.iter().filter(|n| n.starts_with("Sheet '")).count(), 200);
+        assert_eq!(detail.notes.len(), 200);
+
+        // the same input gives the same notes
+        assert_eq!(estimate(&workbook, Some("book.xlsx"), "{}").unwrap().notes, summary.notes);
+    }
+}
@@ -0,0 +1,269 @@
+//! # OOXML Namespaces
+//!
+//! Office Open XML comes in two conformance classes with different namespace
+//! URIs for the same vocabulary: *transitional* (`schemas.openxmlformats.org`,
+//! what Office writes by default) and *strict* (`purl.oclc.org/ooxml`, Office's
+//! "Strict Open XML" save mode). Prefixes are only conventions, and some
+//! third-party producers pick their own.
+//!
+//! Elements and attributes are therefore matched here by local name plus the
+//! namespace they resolve to, checked against a namespace *family*:
+//! transitional, strict, or either. Names without a namespace (producers that
+//! leave out `xmlns`) only match the `Any` family.
+
+use crate::file_utils::note_zip_entry;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::{Namespace, QName, ResolveResult};
+use quick_xml::NsReader;
+use std::io::{BufReader, Cursor};
+use zip::ZipArchive;
+
+/// Conformance class of an OOXML document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conformance {
+    Transitional,
+    Strict,
+}
+
+impl Conformance {
+    /// Name reported in `ooxml_conformance` (`"transitional"` or `"strict"`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Conformance::Transitional => "transitional",
+            Conformance::Strict => "strict",
+        }
+    }
+}
+
+/// Which conformance classes a namespace match accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamespaceFamily {
+    Transitional,
+    Strict,
+    /// Either class, and names without a namespace.
+    Any,
+}
+
+/// The OOXML vocabularies the estimators read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OoxmlNamespace {
+    /// WordprocessingML (`w:`), in `word/document.xml`.
+    WordprocessingMl,
+    /// SpreadsheetML, in `xl/workbook.xml` and the worksheets.
+    SpreadsheetMl,
+    /// PresentationML (`p:`), in `ppt/presentation.xml` and the slides.
+    PresentationMl,
+    /// DrawingML (`a:`), the text and shapes inside slides.
+    DrawingMl,
+    /// Relationship references (`r:id`).
+    Relationships,
+    /// Extended properties, in `docProps/app.xml`.
+    ExtendedProperties,
+    /// VML Office (`o:`), which holds `o:OLEObject` in embedded object markup.
+    /// Both conformance classes use the same URI.
+    VmlOffice,
+    /// Office Math (`m:`), the equations in `word/document.xml`.
+    Math,
+    /// Markup Compatibility (`mc:`), which wraps content with a fallback in
+    /// `mc:AlternateContent`. Both conformance classes use the same URI.
+    MarkupCompatibility,
+}
+
+/// Transitional and strict namespace URIs of each vocabulary, in declaration order.
+const NAMESPACE_URIS: [(OoxmlNamespace, &str, &str); 9] = [
+    (
+        OoxmlNamespace::WordprocessingMl,
+        "http://schemas.openxmlformats.org/wordprocessingml/2006/main",
+        "http://purl.oclc.org/ooxml/wordprocessingml/main",
+    ),
+    (
+        OoxmlNamespace::SpreadsheetMl,
+        "http://schemas.openxmlformats.org/spreadsheetml/2006/main",
+        "http://purl.oclc.org/ooxml/spreadsheetml/main",
+    ),
+    (
+        OoxmlNamespace::PresentationMl,
+        "http://schemas.openxmlformats.org/presentationml/2006/main",
+        "http://purl.oclc.org/ooxml/presentationml/main",
+    ),
+    (
+        OoxmlNamespace::DrawingMl,
+        "http://schemas.openxmlformats.org/drawingml/2006/main",
+        "http://purl.oclc.org/ooxml/drawingml/main",
+    ),
+    (
+        OoxmlNamespace::Relationships,
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+        "http://purl.oclc.org/ooxml/officeDocument/relationships",
+    ),
+    (
+        OoxmlNamespace::ExtendedProperties,
+        "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties",
+        "http://purl.oclc.org/ooxml/officeDocument/extendedProperties",
+    ),
+    (
+        OoxmlNamespace::VmlOffice,
+        "urn:schemas-microsoft-com:office:office",
+        "urn:schemas-microsoft-com:office:office",
+    ),
+    (
+        OoxmlNamespace::Math,
+        "http://schemas.openxmlformats.org/officeDocument/2006/math",
+        "http://purl.oclc.org/ooxml/officeDocument/math",
+    ),
+    (
+        OoxmlNamespace::MarkupCompatibility,
+        "http://schemas.openxmlformats.org/markup-compatibility/2006",
+        "http://schemas.openxmlformats.org/markup-compatibility/2006",
+    ),
+];
+
+impl OoxmlNamespace {
+    /// Namespace URI of the vocabulary in a conformance class.
+    pub fn uri(self, conformance: Conformance) -> &'static str {
+        let (_, transitional, strict) = NAMESPACE_URIS[self as usize];
+        match conformance {
+            Conformance::Transitional => transitional,
+            Conformance::Strict => strict,
+        }
+    }
+
+    /// The conformance class whose URI for this vocabulary is `uri`, if any.
+    pub fn conformance_of(self, uri: &[u8]) -> Option<Conformance> {
+        [Conformance::Transitional, Conformance::Strict]
+            .into_iter()
+            .find(|conformance| self.uri(*conformance).as_bytes() == uri)
+    }
+}
+
+impl NamespaceFamily {
+    /// Whether a resolved namespace belongs to `namespace` in this family.
+    pub fn accepts(self, namespace: OoxmlNamespace, resolved: &ResolveResult) -> bool {
+        match resolved {
+            ResolveResult::Bound(Namespace(uri)) => match (self, namespace.conformance_of(uri)) {
+                (_, None) => false,
+                (NamespaceFamily::Any, Some(_)) => true,
+                (NamespaceFamily::Transitional, Some(conformance)) => conformance == Conformance::Transitional,
+                (NamespaceFamily::Strict, Some(conformance)) => conformance == Conformance::Strict,
+            },
+            ResolveResult::Unbound | ResolveResult::Unknown(_) => self == NamespaceFamily::Any,
+        }
+    }
+}
+
+/// Returns the local name of an element when it belongs to `namespace` in `family`.
+///
+/// # Arguments
+///
+/// * `reader` - The reader the element was read with, which knows the namespaces in scope
+/// * `name` - The element's qualified name (e.g. `w:p`)
+/// * `namespace` - The vocabulary the element must belong to
+/// * `family` - The conformance classes to accept
+///
+/// # Returns
+///
+/// The name without its prefix (e.g. `b"p"`), or `None` for elements of other namespaces.
+pub fn local_name_in<'n, R>(
+    reader: &NsReader<R>,
+    name: QName<'n>,
+    namespace: OoxmlNamespace,
+    family: NamespaceFamily,
+) -> Option<&'n [u8]> {
+    let (resolved, local) = reader.resolve_element(name);
+    family.accepts(namespace, &resolved).then(|| local.into_inner())
+}
+
+/// Reads the value of the attribute `local` in `namespace` (e.g. `w:type`, `r:id`).
+pub fn attribute_value<R>(
+    reader: &NsReader<R>,
+    e: &BytesStart,
+    namespace: OoxmlNamespace,
+    local: &[u8],
+) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|attr| {
+            let (resolved, name) = reader.resolve_attribute(attr.key);
+            name.as_ref() == local && NamespaceFamily::Any.accepts(namespace, &resolved)
+        })
+        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// Detects the conformance class of a package from the root element of its main part.
+///
+/// # Arguments
+///
+/// * `archive` - The opened OOXML package
+/// * `part` - The main part (`word/document.xml`, `ppt/presentation.xml`, or `xl/workbook.xml`)
+///
+/// # Returns
+///
+/// The class of the root element's namespace, or `None` when the part is
+/// missing or its root isn't in an OOXML namespace.
+pub fn detect_conformance(archive: &mut ZipArchive<Cursor<&[u8]>>, part: &str) -> Option<Conformance> {
+    let file = archive.by_name(part).ok()?;
+    note_zip_entry(&file);
+    let mut reader = NsReader::from_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_resolved_event_into(&mut buf) {
+            Ok((ResolveResult::Bound(Namespace(uri)), Event::Start(_)))
+            | Ok((ResolveResult::Bound(Namespace(uri)), Event::Empty(_))) => {
+                return NAMESPACE_URIS.iter().find_map(|(namespace, _, _)| namespace.conformance_of(uri));
+            }
+            Ok((_, Event::Start(_)))
+            | Ok((_, Event::Empty(_)))
+            | Ok((_, Event::Eof))
+            | Err(_) => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn conformance(package: &[u8], part: &str) -> Option<Conformance> {
+        detect_conformance(&mut ZipArchive::new(Cursor::new(package)).unwrap(), part)
+    }
+
+    #[test]
+    fn conformance_comes_from_the_main_part() {
+        let docx = build_docx(3, 0, None);
+        assert_eq!(conformance(&docx, "word/document.xml"), Some(Conformance::Transitional));
+        assert_eq!(conformance(&to_strict(&docx), "word/document.xml"), Some(Conformance::Strict));
+        let pptx = to_strict(&build_pptx(2, 0));
+        assert_eq!(conformance(&pptx, "ppt/presentation.xml"), Some(Conformance::Strict));
+        assert_eq!(conformance(&pptx, "ppt/missing.xml"), None);
+        // the root of the content types isn't in an OOXML namespace
+        assert_eq!(conformance(&pptx, "[Content_Types].xml"), None);
+    }
+
+    #[test]
+    fn families_accept_their_conformance_classes() {
+        let xml = format!(
+            r#"<x:root xmlns:x="{}" xmlns:y="{}" xmlns:z="urn:other"><x:p/><y:p/><z:p/><p/></x:root>"#,
+            OoxmlNamespace::WordprocessingMl.uri(Conformance::Transitional),
+            OoxmlNamespace::WordprocessingMl.uri(Conformance::Strict),
+        );
+        let mut reader = NsReader::from_str(&xml);
+        let mut matches = Vec::new();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Empty(e)) => {
+                    let families = [NamespaceFamily::Transitional, NamespaceFamily::Strict, NamespaceFamily::Any];
+                    let namespace = OoxmlNamespace::WordprocessingMl;
+                    let accepted = families.map(|family| local_name_in(&reader, e.name(), namespace, family).is_some());
+                    matches.push(accepted);
+                }
+                Ok(Event::Eof) => break,
+                _ => {}
+            }
+        }
+        // x:p, y:p, z:p, and the unprefixed p
+        assert_eq!(matches, [[true, false, true], [false, true, true], [false, false, false], [false, false, true]]);
+    }
+}
@@ -0,0 +1,632 @@
+//! # PDF Page Counting
+//!
+//! `PdfCounter` runs the PDF page counting strategies as an ordered pipeline:
+//! the first strategy that finds at least one page decides the count. Every
+//! caller (the estimator, benchmarks, fuzz targets) goes through it, so a fix
+//! to a strategy lands in one place.
+//!
+//! ## Strategies
+//!
+//! - `TypeScan`: counts `/Type /Page` entries in the raw bytes, outside string
+//!   literals, streams, and comments. Fast; copies of a page saved again by
+//!   incremental updates count once, but pages an update removed still count
+//! - `PageTree`: resolves the catalog's page tree through the object index.
+//!   Exact when the page tree is stored in uncompressed objects
+//! - `CountScan`: reads the largest `/Count` of the `/Type /Pages` nodes in the
+//!   raw bytes, whichever order their keys are in. Nearly as cheap as
+//!   `TypeScan` and unaffected by pages inside object streams. Only the
+//!   newest copy of each node is read, but an update that moved the page tree
+//!   root to another object leaves the old root to overcount
+//!
+//! The default pipeline runs `TypeScan` and then `PageTree`, so the counts the
+//! estimator has always reported are unchanged and the page tree only rescues
+//! files the scan finds no pages in.
+
+use crate::deadline::deadline_passed;
+use crate::file_utils::{a4_mm, mm_from_pt};
+use crate::pdf_utils::{
+    count_page_objects, dict_ref, dict_ref_array, dict_slice, dict_value, find_bytes, has_name, max_pages_count,
+    parse_ref, rfind_bytes, ObjRef, ObjectIndex,
+};
+use crate::schema::{PageSizeMm, PageSizePt};
+use std::collections::{HashMap, HashSet};
+
+/// Maximum number of `/Parent` links followed when resolving inherited attributes.
+const MAX_INHERIT_DEPTH: usize = 64;
+
+/// Inheritable attributes resolved from `/Pages` nodes, by node and key
+/// (`None` when neither the node nor its ancestors set it).
+///
+/// Shared across the pages of a walk, so each node is read once rather than
+/// once per page under it: a flat tree's root lists every page in `/Kids`.
+type InheritedValues = HashMap<(ObjRef, &'static [u8]), Option<Vec<u8>>>;
+
+/// Bytes at the start of a PDF that hold the linearization dictionary, if any.
+pub const LINEARIZATION_HEADER_BYTES: usize = 2048;
+
+/// A page counting strategy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PdfStrategy {
+    /// Count `/Type /Page` entries in the raw bytes.
+    TypeScan,
+    /// Walk the page tree from the catalog.
+    PageTree,
+    /// Read the largest `/Count` of the `/Pages` nodes in the raw bytes.
+    CountScan,
+}
+
+impl PdfStrategy {
+    /// Stable name of the strategy, as used in notes.
+    pub fn name(self) -> &'static str {
+        match self {
+            PdfStrategy::TypeScan => "type-scan",
+            PdfStrategy::PageTree => "page-tree",
+            PdfStrategy::CountScan => "count-scan",
+        }
+    }
+
+    /// How far a count from this strategy can be trusted, from 0 to 1.
+    pub fn confidence(self) -> f64 {
+        match self {
+            PdfStrategy::TypeScan => 0.8,
+            PdfStrategy::PageTree => 0.95,
+            PdfStrategy::CountScan => 0.85,
+        }
+    }
+
+    fn count(self, bytes: &[u8]) -> usize {
+        match self {
+            PdfStrategy::TypeScan => count_page_objects(bytes),
+            PdfStrategy::PageTree => ObjectIndex::build(bytes).page_refs().map_or(0, |pages| pages.len()),
+            PdfStrategy::CountScan => max_pages_count(bytes),
+        }
+    }
+}
+
+/// Result of running a `PdfCounter` pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdfCountOutcome {
+    /// The strategy that produced the count, or `None` when none found a page.
+    pub strategy: Option<PdfStrategy>,
+    /// The page count (0 when no strategy found a page).
+    pub count: usize,
+    /// Confidence of the deciding strategy (0 when no strategy found a page).
+    pub confidence: f64,
+    /// One note per strategy that ran without finding a page, or was skipped at the deadline.
+    pub notes: Vec<String>,
+}
+
+/// Counts PDF pages by running strategies in order.
+///
+/// # Example
+///
+/// ```ignore
+/// let counter = PdfCounter::new(vec![PdfStrategy::PageTree]);
+/// let outcome = counter.count(&bytes);
+/// println!("{} pages via {:?}", outcome.count, outcome.strategy);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdfCounter {
+    strategies: Vec<PdfStrategy>,
+}
+
+impl Default for PdfCounter {
+    fn default() -> Self {
+        Self::new(vec![PdfStrategy::TypeScan, PdfStrategy::PageTree])
+    }
+}
+
+impl PdfCounter {
+    /// Creates a counter that tries `strategies` in the given order.
+    pub fn new(strategies: Vec<PdfStrategy>) -> Self {
+        Self { strategies }
+    }
+
+    /// The strategies this counter tries, in order.
+    pub fn strategies(&self) -> &[PdfStrategy] {
+        &self.strategies
+    }
+
+    /// Counts the pages of a PDF.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw PDF file bytes
+    ///
+    /// # Returns
+    ///
+    /// The outcome of the first strategy that finds at least one page; later
+    /// strategies don't run, nor do any after the first once the deadline has
+    /// passed (see the `deadline` module).
+    pub fn count(&self, bytes: &[u8]) -> PdfCountOutcome {
+        let mut notes = Vec::new();
+        for (tried, &strategy) in self.strategies.iter().enumerate() {
+            if tried > 0 && deadline_passed() {
+                notes.push(format!("PDF strategy {} skipped: the deadline passed", strategy.name()));
+                continue;
+            }
+            let count = strategy.count(bytes);
+            if count > 0 {
+                return PdfCountOutcome { strategy: Some(strategy), count, confidence: strategy.confidence(), notes };
+            }
+            notes.push(format!("PDF strategy {} found no pages", strategy.name()));
+        }
+        PdfCountOutcome { strategy: None, count: 0, confidence: 0.0, notes }
+    }
+
+    /// Reads the size of every page from its `/MediaBox`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw PDF file bytes
+    ///
+    /// # Returns
+    ///
+    /// One size per page in page order, or `None` if the page tree could not be
+    /// resolved.
+    ///
+    /// # Notes
+    ///
+    /// - `/MediaBox` is inherited from ancestor `/Pages` nodes when the page doesn't set it
+    /// - Pages without a readable `/MediaBox` are reported as A4
+    /// - `/Rotate` is not applied; see `displayed_page_sizes`
+    pub fn page_sizes(&self, bytes: &[u8]) -> Option<Vec<PageSizeMm>> {
+        let index = ObjectIndex::build(bytes);
+        let pages = index.page_refs()?;
+        let mut inherited = InheritedValues::new();
+        Some(
+            pages
+                .into_iter()
+                .map(|page| {
+                    let (width_mm, height_mm) = media_box(&index, page, &mut inherited)
+                        .map_or_else(a4_mm, |(w, h)| (mm_from_pt(w), mm_from_pt(h)));
+                    PageSizeMm { width_mm, height_mm }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Reads the size of every page as displayed, with `/Rotate` applied.
+///
+/// # Arguments
+///
+/// * `index` - The index of the PDF's objects
+///
+/// # Returns
+///
+/// One size per page in page order, or `None` if the page tree could not be
+/// resolved or a page has no readable `/MediaBox`. Pages turned by a quarter
+/// turn (`/Rotate 90` or `270`, inherited like `/MediaBox`) have their width
+/// and height swapped.
+pub fn displayed_page_sizes(index: &ObjectIndex) -> Option<Vec<PageSizeMm>> {
+    let pages = index.page_refs()?;
+    let mut inherited = InheritedValues::new();
+    pages
+        .into_iter()
+        .map(|page| {
+            let (width, height) = media_box(index, page, &mut inherited)?;
+            let turned = quarter_turned(index, page, &mut inherited);
+            let (width, height) = if turned { (height, width) } else { (width, height) };
+            Some(PageSizeMm { width_mm: mm_from_pt(width), height_mm: mm_from_pt(height) })
+        })
+        .collect()
+}
+
+/// Reads the size of every page in points, as displayed.
+///
+/// # Returns
+///
+/// One size per page in page order, or `None` if the page tree could not be
+/// resolved. The values are the `/MediaBox` as written, with the sides swapped
+/// for pages turned by a quarter turn (see `displayed_page_sizes`); pages
+/// without a readable `/MediaBox` are reported as A4.
+pub fn displayed_page_sizes_pt(index: &ObjectIndex) -> Option<Vec<PageSizePt>> {
+    let pages = index.page_refs()?;
+    let mut inherited = InheritedValues::new();
+    Some(
+        pages
+            .into_iter()
+            .map(|page| {
+                let size = match media_box(index, page, &mut inherited) {
+                    Some((width_pt, height_pt)) => PageSizePt { width_pt, height_pt },
+                    None => {
+                        let (width_mm, height_mm) = a4_mm();
+                        PageSizePt::from_mm(&PageSizeMm { width_mm, height_mm })
+                    }
+                };
+                if quarter_turned(index, page, &mut inherited) {
+                    PageSizePt { width_pt: size.height_pt, height_pt: size.width_pt }
+                } else {
+                    size
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Walks a PDF page tree one page at a time, for callers that want page sizes lazily.
+///
+/// Creating the walker only resolves the catalog and the page tree root; each
+/// `next_page` call reads the `/Kids` it needs to reach the next page and that
+/// page's size. The walker doesn't borrow the index, so it can be stored next
+/// to owned bytes and handed the index on every call.
+///
+/// # Example
+///
+/// ```ignore
+/// let index = ObjectIndex::build(&bytes);
+/// let mut walker = PageWalker::new(&index).expect("page tree");
+/// while let Some((page, size)) = walker.next_page(&index) {
+///     println!("page {}: {} × {} mm", page, size.width_mm, size.height_mm);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PageWalker {
+    /// Tree nodes still to visit, with their depth; the next one is on top.
+    pending: Vec<(ObjRef, usize)>,
+    /// `/Pages` nodes already expanded, which stops cycles.
+    visited: HashSet<ObjRef>,
+    /// 0-based index of the next page.
+    next_index: usize,
+    /// Objects read from the index so far.
+    objects_read: usize,
+    /// Attributes the pages visited so far inherited.
+    inherited: InheritedValues,
+}
+
+impl PageWalker {
+    /// Starts a walk at the catalog's page tree root.
+    ///
+    /// # Returns
+    ///
+    /// `None` when the trailer, catalog, or `/Pages` entry can't be found
+    /// among the uncompressed objects.
+    pub fn new(index: &ObjectIndex) -> Option<Self> {
+        let root = dict_ref(index.catalog()?, b"/Pages")?;
+        Some(Self { pending: vec![(root, 0)], objects_read: 1, ..Default::default() })
+    }
+
+    /// Advances to the next page in page order.
+    ///
+    /// # Returns
+    ///
+    /// The 0-based page index and its size as displayed (`/MediaBox` and
+    /// `/Rotate` inherited from ancestor nodes, quarter turns swapping width
+    /// and height; A4 when no `/MediaBox` is readable), or `None` once every
+    /// page has been visited. Nodes missing from the index are skipped, as in
+    /// `ObjectIndex::page_refs`.
+    pub fn next_page(&mut self, index: &ObjectIndex) -> Option<(usize, PageSizeMm)> {
+        while let Some((node, depth)) = self.pending.pop() {
+            // same depth cap as `ObjectIndex::page_refs`, for malformed trees
+            if depth > MAX_INHERIT_DEPTH {
+                continue;
+            }
+            self.objects_read += 1;
+            let Some(obj) = index.get(node) else { continue };
+            let dict = dict_slice(obj);
+            if has_name(dict, b"/Type", b"/Pages") {
+                if self.visited.insert(node) {
+                    self.pending.extend(dict_ref_array(dict, b"/Kids").into_iter().rev().map(|kid| (kid, depth + 1)));
+                }
+                continue;
+            }
+            let inherited = &mut self.inherited;
+            let (width_mm, height_mm) = media_box(index, node, inherited)
+                .map(|(w, h)| if quarter_turned(index, node, inherited) { (h, w) } else { (w, h) })
+                .map_or_else(a4_mm, |(w, h)| (mm_from_pt(w), mm_from_pt(h)));
+            let page = self.next_index;
+            self.next_index += 1;
+            return Some((page, PageSizeMm { width_mm, height_mm }));
+        }
+        None
+    }
+
+    /// Number of objects read so far (the catalog, then page tree nodes and
+    /// pages), which grows with the pages visited rather than with the document.
+    pub fn objects_read(&self) -> usize {
+        self.objects_read
+    }
+}
+
+/// Reads the page count of a linearized PDF from the start of the file.
+///
+/// Linearized ("fast web view") files begin with a dictionary holding the
+/// page count (`/N`) and the file length (`/L`), so the count is known without
+/// the rest of the file.
+///
+/// # Arguments
+///
+/// * `head` - The first bytes of the file (`LINEARIZATION_HEADER_BYTES` suffice)
+/// * `file_len` - Total length of the file in bytes
+///
+/// # Returns
+///
+/// The page count, or `None` if the file isn't linearized or `/L` doesn't
+/// match `file_len` (the file was updated after linearization and `/N` may be stale).
+pub fn linearized_page_count(head: &[u8], file_len: usize) -> Option<usize> {
+    if !head.starts_with(b"%PDF-") {
+        return None;
+    }
+    let marker = find_bytes(head, b"/Linearized")?;
+    let dict = dict_slice(&head[rfind_bytes(&head[..marker], b"<<")?..]);
+    let number = |key: &[u8]| -> Option<usize> {
+        let value = dict_value(dict, key)?;
+        let end = value.iter().position(|b| !b.is_ascii_digit()).unwrap_or(value.len());
+        std::str::from_utf8(&value[..end]).ok()?.parse().ok()
+    };
+    if number(b"/L")? != file_len {
+        return None;
+    }
+    number(b"/N").filter(|n| *n > 0)
+}
+
+/// Resolves a page's `/MediaBox` (walking up `/Parent` links) as width and height in points.
+fn media_box(index: &ObjectIndex, page: ObjRef, inherited: &mut InheritedValues) -> Option<(f64, f64)> {
+    parse_rectangle(index, &inherited_value(index, page, b"/MediaBox", inherited)?)
+}
+
+/// Whether a page's `/Rotate` (inherited like `/MediaBox`) turns it by an odd
+/// number of quarter turns, swapping its width and height.
+fn quarter_turned(index: &ObjectIndex, page: ObjRef, inherited: &mut InheritedValues) -> bool {
+    let Some(value) = inherited_value(index, page, b"/Rotate", inherited) else { return false };
+    let end = value
+        .iter()
+        .enumerate()
+        .position(|(i, b)| !(b.is_ascii_digit() || (i == 0 && *b == b'-')))
+        .unwrap_or(value.len());
+    let degrees: i64 = std::str::from_utf8(&value[..end]).ok().and_then(|s| s.parse().ok()).unwrap_or(0);
+    degrees.rem_euclid(180) == 90
+}
+
+/// Looks up an inheritable page attribute on the page or, walking up `/Parent`
+/// links, the nearest `/Pages` node that sets it.
+///
+/// What the ancestors resolve to is kept in `inherited`, so later pages under
+/// the same nodes stop at the first node already walked.
+fn inherited_value(
+    index: &ObjectIndex,
+    page: ObjRef,
+    key: &'static [u8],
+    inherited: &mut InheritedValues,
+) -> Option<Vec<u8>> {
+    let dict = dict_slice(index.get(page)?);
+    if let Some(value) = dict_value(dict, key) {
+        return Some(value.to_vec());
+    }
+    let mut node = dict_ref(dict, b"/Parent")?;
+    let mut walked = Vec::new();
+    let mut value = None;
+    for _ in 1..MAX_INHERIT_DEPTH {
+        if let Some(known) = inherited.get(&(node, key)) {
+            value = known.clone();
+            break;
+        }
+        walked.push(node);
+        let Some(obj) = index.get(node) else { break };
+        let dict = dict_slice(obj);
+        if let Some(found) = dict_value(dict, key) {
+            value = Some(found.to_vec());
+            break;
+        }
+        let Some(parent) = dict_ref(dict, b"/Parent") else { break };
+        node = parent;
+    }
+    for node in walked {
+        inherited.insert((node, key), value.clone());
+    }
+    value
+}
+
+/// Parses a rectangle (`[llx lly urx ury]`, inline or indirect) into its width and height.
+fn parse_rectangle(index: &ObjectIndex, value: &[u8]) -> Option<(f64, f64)> {
+    let value = if value.starts_with(b"[") { value } else { index.get(parse_ref(value)?)?.trim_ascii_start() };
+    let value = value.strip_prefix(b"[")?;
+    let end = value.iter().position(|b| *b == b']')?;
+    let numbers: Vec<f64> = value[..end]
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(|t| std::str::from_utf8(t).ok()?.parse().ok())
+        .collect::<Option<_>>()?;
+    let [llx, lly, urx, ury] = numbers[..] else { return None };
+    let (width, height) = ((urx - llx).abs(), (ury - lly).abs());
+    (width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0).then_some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::schema::PageOrientation;
+
+    /// Well-formed PDFs and their page counts.
+    fn corpus() -> Vec<(Vec<u8>, usize)> {
+        vec![
+            (build_pdf(1, XrefStyle::Classic, false, false), 1),
+            (build_pdf(9, XrefStyle::Classic, true, false), 9),
+            (build_pdf(6, XrefStyle::Stream, false, false), 6),
+            (build_pdf(4, XrefStyle::Classic, false, true), 4),
+            (build_blank_pages_pdf(3), 5),
+            (build_text_pdf(3, 50), 3),
+            (build_receipt_pdf(&[300.0, 1200.0]), 2),
+            (build_incrementally_saved_pdf(4, 3), 4),
+        ]
+    }
+
+    #[test]
+    fn default_pipeline_matches_the_type_scan() {
+        for (pdf, pages) in corpus() {
+            let outcome = PdfCounter::default().count(&pdf);
+            assert_eq!(outcome.count, count_page_objects(&pdf));
+            assert_eq!(outcome.count, pages);
+            assert_eq!(outcome.strategy, Some(PdfStrategy::TypeScan));
+            assert_eq!(outcome.confidence, PdfStrategy::TypeScan.confidence());
+            assert!(outcome.notes.is_empty());
+        }
+    }
+
+    #[test]
+    fn every_strategy_agrees_on_well_formed_files() {
+        for (pdf, pages) in corpus() {
+            for strategy in [PdfStrategy::TypeScan, PdfStrategy::PageTree, PdfStrategy::CountScan] {
+                let outcome = PdfCounter::new(vec![strategy]).count(&pdf);
+                assert_eq!((outcome.strategy, outcome.count), (Some(strategy), pages), "{}", strategy.name());
+            }
+        }
+    }
+
+    #[test]
+    fn page_tree_counts_pages_the_scan_misses() {
+        // the page dictionaries leave out their optional /Type
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>",
+            "<< /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+        ]);
+        let outcome = PdfCounter::default().count(&pdf);
+        assert_eq!((outcome.strategy, outcome.count), (Some(PdfStrategy::PageTree), 2));
+        assert_eq!(outcome.notes, ["PDF strategy type-scan found no pages"]);
+    }
+
+    #[test]
+    fn no_pages_from_any_strategy() {
+        let counter = PdfCounter::new(vec![PdfStrategy::PageTree, PdfStrategy::CountScan, PdfStrategy::TypeScan]);
+        let outcome = counter.count(b"%PDF-1.7\n%%EOF");
+        assert_eq!(outcome.strategy, None);
+        assert_eq!((outcome.count, outcome.confidence), (0, 0.0));
+        assert_eq!(outcome.notes.len(), 3);
+        assert_eq!(counter.strategies()[0], PdfStrategy::PageTree);
+    }
+
+    #[test]
+    fn page_sizes_inherit_the_media_box() {
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R 5 0 R] /Count 3 /MediaBox [0 0 612 792] >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 842 595] >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 bad] >>",
+        ]);
+        let sizes = PdfCounter::default().page_sizes(&pdf).unwrap();
+        let letter = PageSizeMm { width_mm: mm_from_pt(612.0), height_mm: mm_from_pt(792.0) };
+        assert_eq!(sizes[0], letter);
+        assert!(sizes[1].approx_eq(&PageSizeMm { width_mm: 297.0, height_mm: 210.0 }));
+        // a page's own unreadable /MediaBox isn't replaced by its parent's
+        assert_eq!(sizes[2], PageSizeMm { width_mm: 210.0, height_mm: 297.0 });
+        assert_eq!(PdfCounter::default().page_sizes(b"%PDF-1.7\n%%EOF"), None);
+    }
+
+    #[test]
+    fn page_attributes_are_inherited_from_the_nearest_node() {
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 6 0 R] /Count 3 /MediaBox [0 0 612 792] >>",
+            "<< /Type /Pages /Parent 2 0 R /Kids [4 0 R 5 0 R] /Count 2 /Rotate 90 >>",
+            "<< /Type /Page /Parent 3 0 R >>",
+            "<< /Type /Page /Parent 3 0 R /Rotate 0 >>",
+            "<< /Type /Pages /Parent 2 0 R /Kids [7 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 6 0 R /MediaBox [0 0 842 595] >>",
+        ]);
+        let sizes = displayed_page_sizes_pt(&ObjectIndex::build(&pdf)).unwrap();
+        let sides: Vec<(f64, f64)> = sizes.iter().map(|size| (size.width_pt, size.height_pt)).collect();
+        assert_eq!(sides, [(792.0, 612.0), (612.0, 792.0), (842.0, 595.0)]);
+    }
+
+    #[test]
+    fn flat_page_trees_are_read_once_for_page_sizes() {
+        // the root lists every page, so reading it again for each page would be quadratic
+        let pdf = build_pdf(2000, XrefStyle::Classic, false, false);
+        let before = crate::stats::usage_counters().bytes_examined;
+        let index = ObjectIndex::build(&pdf);
+        assert_eq!(displayed_page_sizes(&index).unwrap().len(), 2000);
+        let mut walker = PageWalker::new(&index).unwrap();
+        while walker.next_page(&index).is_some() {}
+        let examined = crate::stats::usage_counters().bytes_examined - before;
+        assert!(examined < 8 * pdf.len(), "{} bytes examined of {}", examined, pdf.len());
+    }
+
+    #[test]
+    fn linearized_pdfs_state_their_page_count() {
+        let pdf = build_linearized_pdf(7);
+        let head = &pdf[..LINEARIZATION_HEADER_BYTES.min(pdf.len())];
+        assert_eq!(linearized_page_count(head, pdf.len()), Some(7));
+        assert_eq!(PdfCounter::default().count(&pdf).count, 7);
+        // a length other than /L means the file was changed after linearization
+        assert_eq!(linearized_page_count(head, pdf.len() + 1), None);
+        let plain = build_pdf(7, XrefStyle::Classic, false, false);
+        assert_eq!(linearized_page_count(&plain, plain.len()), None);
+        assert_eq!(linearized_page_count(&head[9..], pdf.len()), None);
+    }
+
+    #[test]
+    fn count_scan_reads_either_key_order() {
+        let page = "<< /Type /Page /Parent 2 0 R >>";
+        let counter = PdfCounter::new(vec![PdfStrategy::CountScan, PdfStrategy::TypeScan]);
+        for root in [
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>",
+            "<< /Count 2 /Kids [3 0 R 4 0 R] /Type /Pages >>",
+        ] {
+            let outcome = counter.count(&build_raw_pdf(&["<< /Type /Catalog /Pages 2 0 R >>", root, page, page]));
+            assert_eq!((outcome.count, outcome.strategy), (2, Some(PdfStrategy::CountScan)), "{}", root);
+            assert_eq!(outcome.confidence, PdfStrategy::CountScan.confidence());
+        }
+    }
+
+    #[test]
+    fn displayed_sizes_apply_inherited_rotation() {
+        let pdf = build_mixed_orientation_pdf();
+        let sizes = displayed_page_sizes(&ObjectIndex::build(&pdf)).unwrap();
+        let orientations: Vec<PageOrientation> = sizes.iter().map(PageSizeMm::orientation).collect();
+        assert_eq!(orientations, MIXED_ORIENTATIONS);
+        // the turned page swaps its sides; /MediaBox as written doesn't
+        assert_eq!((sizes[1].width_mm.round(), sizes[1].height_mm.round()), (297.0, 210.0));
+        let written = PdfCounter::default().page_sizes(&pdf).unwrap();
+        assert_eq!((written[1].width_mm.round(), written[1].height_mm.round()), (210.0, 297.0));
+        let in_points = displayed_page_sizes_pt(&ObjectIndex::build(&pdf)).unwrap();
+        assert_eq!((in_points[1].width_pt, in_points[1].height_pt), (842.0, 595.0));
+        assert_eq!(in_points.iter().map(PageSizePt::to_mm).collect::<Vec<_>>(), sizes);
+
+        // a page without a readable /MediaBox leaves the sizes unknown
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+        ]);
+        assert_eq!(displayed_page_sizes(&ObjectIndex::build(&pdf)), None);
+        let in_points = displayed_page_sizes_pt(&ObjectIndex::build(&pdf)).unwrap();
+        let a4 = in_points[0].to_mm();
+        assert_eq!((a4.width_mm.round(), a4.height_mm.round()), (210.0, 297.0));
+    }
+
+    #[test]
+    fn page_walker_reads_only_the_pages_consumed() {
+        let walk = |pdf: &[u8], pages: usize| {
+            let index = ObjectIndex::build(pdf);
+            let mut walker = PageWalker::new(&index).unwrap();
+            let sizes: Vec<PageSizeMm> =
+                std::iter::from_fn(|| walker.next_page(&index)).take(pages).map(|(_, size)| size).collect();
+            (sizes, walker.objects_read())
+        };
+        let small = build_pdf(10, XrefStyle::Classic, false, false);
+        let large = build_pdf(1000, XrefStyle::Classic, false, false);
+        // the catalog, the root, then one object per page, however long the document
+        for pages in [1, 3, 10] {
+            assert_eq!(walk(&small, pages).1, 2 + pages);
+            assert_eq!(walk(&large, pages).1, 2 + pages);
+        }
+        assert_eq!(walk(&large, usize::MAX).0.len(), 1000);
+
+        // sizes match the eager reading, inherited rotation and all
+        let pdf = build_mixed_orientation_pdf();
+        let (sizes, _) = walk(&pdf, usize::MAX);
+        assert_eq!(Some(sizes), displayed_page_sizes(&ObjectIndex::build(&pdf)));
+        let index = ObjectIndex::build(&pdf);
+        let mut walker = PageWalker::new(&index).unwrap();
+        let indices: Vec<usize> = std::iter::from_fn(|| walker.next_page(&index)).map(|(page, _)| page).collect();
+        assert_eq!(indices, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(walker.next_page(&index), None);
+
+        assert!(PageWalker::new(&ObjectIndex::build(b"%PDF-1.7\nnot a pdf")).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+//! # PDF Blank Page Detection
+//!
+//! Finds the effectively blank pages of a PDF, such as the empty backsides of
+//! duplex batches, so they can be left out of what is billed. Like color
+//! detection, it decodes every page's content stream, so it only runs when the
+//! `exclude_blank_pages` option is set.
+//!
+//! ## Rules
+//!
+//! A page is blank when both:
+//!
+//! - its content streams, with those of the form XObjects it uses, hold fewer
+//!   than `BLANK_CONTENT_BYTES` non-whitespace bytes once decoded (enough for
+//!   a `q`/`Q` pair around a transform, not for selecting a font and showing text)
+//! - its resources reference no image XObject, directly or through a form
+//!
+//! ## Limitations
+//!
+//! - Annotations aren't content, so a page holding only a stamp or a sticky
+//!   note counts as blank
+//! - Scanned blank pages are images, so they are never found blank
+//! - Content in streams with filters other than `FlateDecode` is measured undecoded
+
+use crate::pdf_color::{page_resources, xobject_refs};
+use crate::pdf_utils::{dict_ref_array, dict_slice, has_name, stream_data, ObjRef, ObjectIndex};
+use std::collections::HashSet;
+
+/// Non-whitespace content bytes below which a page without images is blank.
+pub const BLANK_CONTENT_BYTES: usize = 16;
+
+/// Maximum nesting depth for form XObjects.
+const MAX_FORM_DEPTH: usize = 8;
+
+/// Finds the blank pages of a PDF.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw PDF file bytes
+///
+/// # Returns
+///
+/// The 0-based indices of the blank pages in page order, or `None` if the page
+/// tree could not be resolved (e.g. it is stored in a compressed object stream).
+pub fn find_blank_pages(bytes: &[u8]) -> Option<Vec<usize>> {
+    let index = ObjectIndex::build(bytes);
+    let pages = index.page_refs()?;
+    Some(
+        pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page_is_blank(&index, **page))
+            .map(|(i, _)| i)
+            .collect(),
+    )
+}
+
+fn page_is_blank(index: &ObjectIndex, page: ObjRef) -> bool {
+    let Some(obj) = index.get(page) else { return false };
+    let dict = dict_slice(obj);
+    let mut content_bytes: usize = dict_ref_array(dict, b"/Contents")
+        .into_iter()
+        .filter_map(|id| index.get(id).and_then(stream_data))
+        .map(|data| visible_bytes(&data))
+        .sum();
+    if let Some(resources) = page_resources(index, dict) {
+        match xobject_content(index, resources, &mut HashSet::new(), 0) {
+            Some(bytes) => content_bytes += bytes,
+            None => return false,
+        }
+    }
+    content_bytes < BLANK_CONTENT_BYTES
+}
+
+/// Adds up the content bytes of the form XObjects in a resource dictionary.
+///
+/// # Returns
+///
+/// The total, or `None` as soon as an image XObject is found.
+fn xobject_content(
+    index: &ObjectIndex,
+    resources: &[u8],
+    visited: &mut HashSet<ObjRef>,
+    depth: usize,
+) -> Option<usize> {
+    if depth > MAX_FORM_DEPTH {
+        return Some(0);
+    }
+    let Some(xobjects) = index.resolve_dict(resources, b"/XObject") else { return Some(0) };
+    let mut total = 0;
+    for id in xobject_refs(xobjects) {
+        if !visited.insert(id) {
+            continue;
+        }
+        let Some(obj) = index.get(id) else { continue };
+        let dict = dict_slice(obj);
+        if has_name(dict, b"/Subtype", b"/Image") {
+            return None;
+        }
+        if has_name(dict, b"/Subtype", b"/Form") {
+            total += stream_data(obj).map_or(0, |data| visible_bytes(&data));
+            if let Some(nested) = index.resolve_dict(dict, b"/Resources") {
+                total += xobject_content(index, nested, visited, depth + 1)?;
+            }
+        }
+    }
+    Some(total)
+}
+
+/// Counts the bytes of a content stream that aren't whitespace.
+fn visible_bytes(data: &[u8]) -> usize {
+    data.iter().filter(|b| !b.is_ascii_whitespace()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::schema::EstimateResult;
+
+    /// A one-page PDF whose page draws `content` with `resources`, followed by `objects` (numbered from 5).
+    fn page_pdf(content: &str, resources: &str, objects: &[String]) -> Vec<u8> {
+        let page = format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents 4 0 R {} >>", resources);
+        let mut bodies = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            page,
+            raw_stream("", content),
+        ];
+        bodies.extend_from_slice(objects);
+        build_raw_pdf(&bodies.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn blank_and_stamp_only_pages_are_found() {
+        assert_eq!(find_blank_pages(&build_blank_pages_pdf(3)), Some(vec![3, 4]));
+        assert_eq!(find_blank_pages(&build_pdf(4, XrefStyle::Classic, false, false)), Some(vec![]));
+        // a transform without drawing is still blank
+        assert_eq!(find_blank_pages(&page_pdf("q 1 0 0 1 0 0 cm Q", "", &[])), Some(vec![0]));
+    }
+
+    #[test]
+    fn images_and_forms_make_a_page_non_blank() {
+        let image = raw_stream("/Type /XObject /Subtype /Image /Width 1 /Height 1 /BitsPerComponent 8", "\u{ff}");
+        let drawn = page_pdf("/Im1 Do", "/Resources << /XObject << /Im1 5 0 R >> >>", std::slice::from_ref(&image));
+        assert_eq!(find_blank_pages(&drawn), Some(vec![]));
+
+        // the page only calls a form, which draws text or holds the image
+        let text_form = raw_stream("/Type /XObject /Subtype /Form /BBox [0 0 100 100]", "BT /F1 12 Tf (Scanned) Tj ET");
+        let form = page_pdf("/Fm1 Do", "/Resources << /XObject << /Fm1 5 0 R >> >>", &[text_form]);
+        assert_eq!(find_blank_pages(&form), Some(vec![]));
+        let image_form = raw_stream(
+            "/Type /XObject /Subtype /Form /BBox [0 0 100 100] /Resources << /XObject << /Im1 6 0 R >> >>",
+            "/Im1 Do",
+        );
+        let nested = page_pdf("/Fm1 Do", "/Resources << /XObject << /Fm1 5 0 R >> >>", &[image_form, image]);
+        assert_eq!(find_blank_pages(&nested), Some(vec![]));
+        assert_eq!(find_blank_pages(b"%PDF-1.7\nnot a page tree"), None);
+    }
+
+    #[test]
+    fn non_blank_pages_are_only_counted_when_asked() {
+        let pdf = build_blank_pages_pdf(2);
+        let result = estimate(&pdf, None, r#"{"exclude_blank_pages":true}"#).unwrap();
+        assert_eq!((result.page_count, result.non_blank_pages), (4, Some(2)));
+        assert!(result.notes.contains(&"2 blank pages excluded from non_blank_pages (pages 3, 4)".to_string()));
+        let plain = estimate(&pdf, None, "{}").unwrap();
+        assert_eq!(plain.non_blank_pages, None);
+        assert!(!plain.notes.iter().any(|note| note.contains("blank")), "{:?}", plain.notes);
+
+        let other = estimate(&build_blank_pages_pdf(5), None, r#"{"exclude_blank_pages":true}"#).unwrap();
+        assert_eq!(EstimateResult::merge(&[result.clone(), other]).non_blank_pages, Some(7));
+        assert_eq!(EstimateResult::merge(&[result, plain]).non_blank_pages, None);
+    }
+}
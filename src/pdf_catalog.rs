@@ -0,0 +1,335 @@
+//! # PDF Navigation Counts
+//!
+//! Counts the bookmarks, named destinations, and attachments of a PDF for
+//! document dashboards. Everything is reached from the catalog through the
+//! object index, so only the objects involved are read.
+//!
+//! ## Sources
+//!
+//! - Bookmarks: the items of the `/Outlines` tree, walked through `/First` and
+//!   `/Next`. When the items can't be walked, the root's `/Count` is used; it
+//!   counts the items that are open (visible), and is negative on a closed
+//!   item, so its absolute value is taken and may undercount
+//! - Named destinations: the leaves of the `/Names` → `/Dests` name tree, plus
+//!   the keys of a PDF 1.1 catalog `/Dests` dictionary
+//! - Attachments: the leaves of the `/Names` → `/EmbeddedFiles` name tree
+//!
+//! Like the rest of the PDF helpers, only uncompressed objects are seen. A
+//! count whose objects sit in an object stream is left out and noted.
+
+use crate::pdf_utils::{dict_ref, dict_ref_array, dict_slice, dict_value, ObjRef, ObjectIndex};
+use std::collections::HashSet;
+
+/// Limit on the depth of outline and name trees, which bounds recursion on malformed files.
+const MAX_TREE_DEPTH: usize = 64;
+
+/// Navigation counts read from a PDF catalog.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CatalogCounts {
+    /// Outline items (bookmarks); `None` when the outline couldn't be read.
+    pub bookmarks: Option<usize>,
+    /// Named destinations; `None` when the name tree couldn't be read.
+    pub named_destinations: Option<usize>,
+    /// Embedded files listed in the name tree; `None` when it couldn't be read.
+    pub attachments: Option<usize>,
+    /// Why counts were left out.
+    pub notes: Vec<String>,
+}
+
+/// Reads the bookmark, named destination, and attachment counts of a PDF.
+///
+/// # Returns
+///
+/// The counts, 0 for those the document doesn't have. When the catalog itself
+/// can't be found (e.g. it sits in an object stream), every count is `None`.
+pub fn read_catalog_counts(bytes: &[u8]) -> CatalogCounts {
+    let index = ObjectIndex::build(bytes);
+    let mut counts = CatalogCounts::default();
+    let Some(catalog) = index.catalog() else {
+        counts.notes.push("⚠ Bookmarks, named destinations, and attachments not counted: catalog not found".into());
+        return counts;
+    };
+
+    counts.bookmarks = match dict_value(catalog, b"/Outlines") {
+        None => Some(0),
+        Some(_) => count_outline(&index, catalog),
+    };
+    if counts.bookmarks.is_none() {
+        counts.notes.push("⚠ Bookmarks not counted: the outline is in a compressed object stream".into());
+    }
+
+    let names = dict_value(catalog, b"/Names").map(|_| index.resolve_dict(catalog, b"/Names"));
+    let tree = |key: &[u8]| match names {
+        None => Some(0),
+        Some(None) => None,
+        Some(Some(names)) => match dict_value(names, key) {
+            None => Some(0),
+            Some(_) => count_name_tree(&index, index.resolve_dict(names, key)?, &mut HashSet::new(), 0),
+        },
+    };
+    // PDF 1.1 documents list destinations in a catalog dictionary instead of a name tree
+    let legacy_dests = match dict_value(catalog, b"/Dests") {
+        None => Some(0),
+        Some(_) => index.resolve_dict(catalog, b"/Dests").map(count_dict_keys),
+    };
+    counts.named_destinations = tree(b"/Dests").zip(legacy_dests).map(|(tree, legacy)| tree + legacy);
+    counts.attachments = tree(b"/EmbeddedFiles");
+    if counts.named_destinations.is_none() {
+        counts.notes.push("⚠ Named destinations not counted: the name tree is in a compressed object stream".into());
+    }
+    if counts.attachments.is_none() {
+        counts.notes.push("⚠ Attachments not counted: the name tree is in a compressed object stream".into());
+    }
+    counts
+}
+
+/// Counts the items of the outline, or falls back to the root's `/Count`.
+fn count_outline(index: &ObjectIndex, catalog: &[u8]) -> Option<usize> {
+    let root = index.resolve_dict(catalog, b"/Outlines")?;
+    let mut visited = HashSet::new();
+    let walked = dict_ref(root, b"/First").and_then(|first| count_outline_items(index, first, &mut visited, 0));
+    walked.or_else(|| {
+        if dict_value(root, b"/First").is_some() {
+            let count = dict_value(root, b"/Count").and_then(leading_integer)?;
+            Some(count.unsigned_abs() as usize)
+        } else {
+            Some(0)
+        }
+    })
+}
+
+/// Counts an outline item, its siblings after it, and their descendants.
+///
+/// Returns `None` when an item isn't among the indexed objects.
+fn count_outline_items(
+    index: &ObjectIndex,
+    first: ObjRef,
+    visited: &mut HashSet<ObjRef>,
+    depth: usize,
+) -> Option<usize> {
+    if depth > MAX_TREE_DEPTH {
+        return Some(0);
+    }
+    let mut count = 0;
+    let mut next = Some(first);
+    // `visited` stops sibling chains that loop back on themselves
+    while let Some(item) = next
+        && visited.insert(item)
+    {
+        let dict = dict_slice(index.get(item)?);
+        count += 1;
+        if let Some(child) = dict_ref(dict, b"/First") {
+            count += count_outline_items(index, child, visited, depth + 1)?;
+        }
+        next = dict_ref(dict, b"/Next");
+    }
+    Some(count)
+}
+
+/// Counts the leaves (key–value pairs) of a name tree.
+///
+/// Returns `None` when a node isn't among the indexed objects.
+fn count_name_tree(index: &ObjectIndex, node: &[u8], visited: &mut HashSet<ObjRef>, depth: usize) -> Option<usize> {
+    if depth > MAX_TREE_DEPTH {
+        return Some(0);
+    }
+    let mut count = dict_value(node, b"/Names").map_or(0, count_array_strings);
+    for kid in dict_ref_array(node, b"/Kids") {
+        if visited.insert(kid) {
+            count += count_name_tree(index, dict_slice(index.get(kid)?), visited, depth + 1)?;
+        }
+    }
+    Some(count)
+}
+
+/// Counts the strings at the top level of the array `value` starts with.
+///
+/// In a name tree's `/Names` array keys are strings and values are
+/// references, arrays, or dictionaries, so this is the number of leaves.
+fn count_array_strings(value: &[u8]) -> usize {
+    if !value.starts_with(b"[") {
+        return 0;
+    }
+    let mut count = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < value.len() {
+        match value[i] {
+            b'(' => {
+                if depth == 1 {
+                    count += 1;
+                }
+                i = skip_literal_string(value, i);
+                continue;
+            }
+            b'<' if value.get(i + 1) == Some(&b'<') => {
+                depth += 1;
+                i += 1;
+            }
+            b'<' => {
+                if depth == 1 {
+                    count += 1;
+                }
+                i += value[i..].iter().position(|b| *b == b'>').unwrap_or(value.len() - i);
+            }
+            b'>' if value.get(i + 1) == Some(&b'>') => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            b'[' => depth += 1,
+            b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    count
+}
+
+/// Returns the index just past the literal string starting at `start`,
+/// honoring escapes and balanced parentheses.
+fn skip_literal_string(value: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < value.len() {
+        match value[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    value.len()
+}
+
+/// Counts the keys of a dictionary whose values are all arrays, dictionaries,
+/// or references (as in a PDF 1.1 `/Dests` dictionary).
+fn count_dict_keys(dict: &[u8]) -> usize {
+    let mut count = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < dict.len() {
+        if dict[i..].starts_with(b"<<") || dict[i..].starts_with(b">>") {
+            depth = if dict[i] == b'<' { depth + 1 } else { depth.saturating_sub(1) };
+            i += 2;
+            continue;
+        }
+        match dict[i] {
+            b'[' => depth += 1,
+            b']' => depth = depth.saturating_sub(1),
+            b'(' => {
+                i = skip_literal_string(dict, i);
+                continue;
+            }
+            // names only appear as keys at the top level; values are nested or references
+            b'/' if depth == 1 => count += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    count
+}
+
+/// Parses the integer at the start of `value` (e.g. a `/Count` entry).
+fn leading_integer(value: &[u8]) -> Option<i64> {
+    let end = value
+        .iter()
+        .enumerate()
+        .position(|(i, b)| !(b.is_ascii_digit() || (i == 0 && (*b == b'-' || *b == b'+'))))
+        .unwrap_or(value.len());
+    std::str::from_utf8(&value[..end]).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    const PAGE: &str = "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>";
+
+    #[test]
+    fn bookmarks_destinations_and_attachments_are_counted() {
+        let counts = read_catalog_counts(&build_pdf(4, XrefStyle::Classic, true, false));
+        let expected =
+            CatalogCounts { bookmarks: Some(4), named_destinations: Some(0), attachments: Some(0), notes: vec![] };
+        assert_eq!(counts, expected);
+
+        let report = build_pdf(1, XrefStyle::Classic, false, false);
+        let portfolio = build_portfolio_pdf(&[("a.pdf", &report), ("b.pdf", &report)]);
+        assert_eq!(read_catalog_counts(&portfolio).attachments, Some(2));
+
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R /Outlines 4 0 R /Names << /Dests 8 0 R >> /Dests 11 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            PAGE,
+            "<< /Type /Outlines /First 5 0 R /Last 6 0 R /Count 2 >>",
+            // a closed item: its /Count is negative and its child isn't open
+            "<< /Title (Chapter 1) /Parent 4 0 R /Next 6 0 R /First 7 0 R /Last 7 0 R /Count -1 >>",
+            "<< /Title (Chapter 2) /Parent 4 0 R /Prev 5 0 R >>",
+            r"<< /Title (Section \(1.1\)) /Parent 5 0 R >>",
+            "<< /Kids [9 0 R 10 0 R] >>",
+            r"<< /Limits [(a\)b) (intro)] /Names [(a\)b) 3 0 R (intro) [3 0 R /Fit]] >>",
+            "<< /Limits [<FEFF0078> <FEFF0078>] /Names [<FEFF0078> << /D [3 0 R /XYZ 0 0 0] >>] >>",
+            "<< /old1 [3 0 R /Fit] /old2 [3 0 R /Fit] >>",
+        ]);
+        let counts = read_catalog_counts(&pdf);
+        // every item is counted, open or closed; the legacy dictionary adds to the tree
+        assert_eq!((counts.bookmarks, counts.named_destinations, counts.attachments), (Some(3), Some(5), Some(0)));
+        assert!(counts.notes.is_empty(), "{:?}", counts.notes);
+    }
+
+    #[test]
+    fn unwalkable_outlines_fall_back_to_the_absolute_count() {
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R /Outlines 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            PAGE,
+            "<< /Type /Outlines /First 9 0 R /Last 9 0 R /Count -7 >>",
+        ]);
+        assert_eq!(read_catalog_counts(&pdf).bookmarks, Some(7));
+
+        // sibling chains that loop back end
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R /Outlines 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            PAGE,
+            "<< /Type /Outlines /First 5 0 R /Count 2 >>",
+            "<< /Title (A) /Parent 4 0 R /Next 6 0 R >>",
+            "<< /Title (B) /Parent 4 0 R /Next 5 0 R >>",
+        ]);
+        assert_eq!(read_catalog_counts(&pdf).bookmarks, Some(2));
+    }
+
+    #[test]
+    fn trees_out_of_reach_are_noted() {
+        // objects 7 and 8 aren't in the file, as if in an object stream
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R /Outlines 7 0 R /Names 8 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            PAGE,
+        ]);
+        let counts = read_catalog_counts(&pdf);
+        assert_eq!((counts.bookmarks, counts.named_destinations, counts.attachments), (None, None, None));
+        assert_eq!(
+            counts.notes,
+            [
+                "⚠ Bookmarks not counted: the outline is in a compressed object stream",
+                "⚠ Named destinations not counted: the name tree is in a compressed object stream",
+                "⚠ Attachments not counted: the name tree is in a compressed object stream",
+            ]
+        );
+        let counts = read_catalog_counts(b"%PDF-1.7\nno objects\n%%EOF\n");
+        assert_eq!(counts.bookmarks, None);
+        assert_eq!(counts.notes.len(), 1);
+    }
+}
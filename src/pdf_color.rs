@@ -0,0 +1,439 @@
+//! # PDF Color Detection
+//!
+//! Classifies PDF pages as color or grayscale for print costing. Each page's
+//! content stream is tokenized and its color-setting operators are inspected,
+//! and the image XObjects it references are checked for color spaces.
+//!
+//! ## Rules
+//!
+//! - `rg`/`RG` are color only when the three components differ (so `0 0 0 rg` is black)
+//! - `k`/`K` are color when any of the cyan, magenta, or yellow components is non-zero
+//! - `sc`/`scn`/`SC`/`SCN` follow the same rules based on their operand count;
+//!   a pattern name operand counts as color
+//! - Images are color when their color space is RGB, CMYK, Lab, or an
+//!   ICC profile with three or more components
+//! - Form XObjects are analyzed recursively
+//!
+//! Analysis is expensive (every content stream is inflated), so it only runs
+//! when the `analyze_color` option is set.
+
+use crate::pdf_utils::{
+    dict_ref_array, dict_slice, dict_value, find_bytes, has_name, parse_ref, stream_data, ObjRef,
+    ObjectIndex,
+};
+use std::collections::HashSet;
+
+/// Tolerance used when comparing color components.
+const COMPONENT_EPSILON: f64 = 0.01;
+
+/// Maximum nesting depth for form XObjects.
+const MAX_FORM_DEPTH: usize = 8;
+
+/// Maximum nesting depth for color spaces built on a base space (`/Indexed`),
+/// so one that names itself as its base ends the lookup.
+const MAX_COLOR_SPACE_DEPTH: usize = 4;
+
+/// Classifies every page of a PDF as color (`true`) or grayscale (`false`).
+///
+/// # Arguments
+///
+/// * `bytes` - The raw PDF file bytes
+///
+/// # Returns
+///
+/// One flag per page in page order, or `None` if the page tree could not be
+/// resolved (e.g. it is stored in a compressed object stream).
+pub fn analyze_page_colors(bytes: &[u8]) -> Option<Vec<bool>> {
+    let index = ObjectIndex::build(bytes);
+    let pages = index.page_refs()?;
+    Some(pages.iter().map(|page| page_uses_color(&index, *page)).collect())
+}
+
+fn page_uses_color(index: &ObjectIndex, page: ObjRef) -> bool {
+    let Some(obj) = index.get(page) else { return false };
+    let dict = dict_slice(obj);
+
+    if let Some(resources) = page_resources(index, dict)
+        && resources_use_color(index, resources, &mut HashSet::new(), 0)
+    {
+        return true;
+    }
+
+    let content: Vec<u8> = dict_ref_array(dict, b"/Contents")
+        .into_iter()
+        .filter_map(|id| index.get(id).and_then(stream_data))
+        .flat_map(|mut data| {
+            data.push(b'\n');
+            data
+        })
+        .collect();
+    content_uses_color(&content)
+}
+
+/// Looks up the page's resources, following `/Parent` links for inherited resources.
+pub fn page_resources<'a>(index: &ObjectIndex<'a>, mut dict: &'a [u8]) -> Option<&'a [u8]> {
+    for _ in 0..32 {
+        if let Some(resources) = index.resolve_dict(dict, b"/Resources") {
+            return Some(resources);
+        }
+        let parent = parse_ref(dict_value(dict, b"/Parent")?)?;
+        dict = dict_slice(index.get(parent)?);
+    }
+    None
+}
+
+/// Checks the image and form XObjects listed in a resource dictionary.
+///
+/// `visited` holds the XObjects already checked for the current page, so a
+/// form that (directly or indirectly) lists itself is only analyzed once.
+fn resources_use_color(
+    index: &ObjectIndex,
+    resources: &[u8],
+    visited: &mut HashSet<ObjRef>,
+    depth: usize,
+) -> bool {
+    if depth > MAX_FORM_DEPTH {
+        return false;
+    }
+    let Some(xobjects) = index.resolve_dict(resources, b"/XObject") else { return false };
+
+    xobject_refs(xobjects).into_iter().any(|id| {
+        if !visited.insert(id) {
+            return false;
+        }
+        let Some(obj) = index.get(id) else { return false };
+        let dict = dict_slice(obj);
+        if has_name(dict, b"/Subtype", b"/Image") {
+            dict_value(dict, b"/ColorSpace").is_some_and(|cs| color_space_is_color(index, cs, 0))
+        } else if has_name(dict, b"/Subtype", b"/Form") {
+            let nested = index
+                .resolve_dict(dict, b"/Resources")
+                .is_some_and(|r| resources_use_color(index, r, visited, depth + 1));
+            nested || stream_data(obj).is_some_and(|data| content_uses_color(&data))
+        } else {
+            false
+        }
+    })
+}
+
+/// Collects the `/Name N G R` references of an XObject dictionary.
+pub fn xobject_refs(dict: &[u8]) -> Vec<ObjRef> {
+    let inner = dict.strip_prefix(b"<<").unwrap_or(dict);
+    let mut refs = Vec::new();
+    let mut pos = 0;
+    while let Some(rel) = find_bytes(&inner[pos..], b"/") {
+        let start = pos + rel + 1;
+        let name_len = inner[start..]
+            .iter()
+            .take_while(|b| !b.is_ascii_whitespace() && !b"/<>[]()".contains(b))
+            .count();
+        if let Some(id) = parse_ref(&inner[start + name_len..]) {
+            refs.push(id);
+        }
+        pos = start + name_len;
+    }
+    refs
+}
+
+/// Decides whether an image color space value describes color.
+///
+/// `depth` counts the `/Indexed` bases followed to get here; past
+/// `MAX_COLOR_SPACE_DEPTH` the space counts as grayscale.
+fn color_space_is_color(index: &ObjectIndex, value: &[u8], depth: usize) -> bool {
+    if depth > MAX_COLOR_SPACE_DEPTH {
+        return false;
+    }
+    let value = if let Some(id) = parse_ref(value) {
+        match index.get(id) {
+            Some(obj) => trim(obj),
+            None => return false,
+        }
+    } else {
+        value
+    };
+
+    if value.starts_with(b"/DeviceGray") || value.starts_with(b"/CalGray") {
+        return false;
+    }
+    if value.starts_with(b"/DeviceRGB")
+        || value.starts_with(b"/DeviceCMYK")
+        || value.starts_with(b"/CalRGB")
+        || value.starts_with(b"/Lab")
+    {
+        return true;
+    }
+    if value.starts_with(b"[") {
+        let array = &value[1..];
+        let array = trim(array);
+        if array.starts_with(b"/ICCBased") {
+            // ICC profiles are color when they have 3 (RGB/Lab) or 4 (CMYK) components
+            return parse_ref(&array[9..])
+                .and_then(|id| index.get(id))
+                .and_then(|obj| dict_value(dict_slice(obj), b"/N"))
+                .is_some_and(|n| n.starts_with(b"3") || n.starts_with(b"4"));
+        }
+        if array.starts_with(b"/Indexed") {
+            return color_space_is_color(index, trim(&array[8..]), depth + 1);
+        }
+        if array.starts_with(b"/CalRGB") || array.starts_with(b"/Lab") {
+            return true;
+        }
+    }
+    false
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let skip = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    &bytes[skip..]
+}
+
+/// An operand collected while tokenizing a content stream.
+enum Operand {
+    Number(f64),
+    Name,
+    Other,
+}
+
+/// Tokenizes a content stream and reports whether any color-setting operator uses color.
+fn content_uses_color(content: &[u8]) -> bool {
+    let mut operands: Vec<Operand> = Vec::new();
+    let mut i = 0;
+    let len = content.len();
+
+    while i < len {
+        let b = content[i];
+        match b {
+            _ if b.is_ascii_whitespace() => i += 1,
+            b'%' => {
+                while i < len && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'(' => {
+                i = skip_literal_string(content, i);
+                operands.push(Operand::Other);
+            }
+            b'<' if content.get(i + 1) == Some(&b'<') => i += 2,
+            b'>' if content.get(i + 1) == Some(&b'>') => i += 2,
+            b'<' => {
+                while i < len && content[i] != b'>' {
+                    i += 1;
+                }
+                i += 1;
+                operands.push(Operand::Other);
+            }
+            b'[' | b']' | b'{' | b'}' => i += 1,
+            b'/' => {
+                i += 1;
+                while i < len && is_regular(content[i]) {
+                    i += 1;
+                }
+                operands.push(Operand::Name);
+            }
+            _ => {
+                let start = i;
+                while i < len && is_regular(content[i]) {
+                    i += 1;
+                }
+                if i == start {
+                    i += 1;
+                    continue;
+                }
+                let token = &content[start..i];
+                if let Some(n) = parse_number(token) {
+                    operands.push(Operand::Number(n));
+                    continue;
+                }
+                if token == b"BI" {
+                    let (is_color, next) = inline_image(content, i);
+                    if is_color {
+                        return true;
+                    }
+                    i = next;
+                } else if operator_uses_color(token, &operands) {
+                    return true;
+                }
+                operands.clear();
+            }
+        }
+    }
+    false
+}
+
+fn operator_uses_color(op: &[u8], operands: &[Operand]) -> bool {
+    let numbers: Vec<f64> = operands
+        .iter()
+        .rev()
+        .map_while(|o| match o {
+            Operand::Number(n) => Some(*n),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+
+    match op {
+        b"rg" | b"RG" => numbers.len() >= 3 && rgb_is_color(&numbers[numbers.len() - 3..]),
+        b"k" | b"K" => numbers.len() >= 4 && cmyk_is_color(&numbers[numbers.len() - 4..]),
+        b"sc" | b"SC" | b"scn" | b"SCN" => {
+            if matches!(operands.last(), Some(Operand::Name)) {
+                // pattern fills are treated as color
+                return true;
+            }
+            match numbers.len() {
+                3 => rgb_is_color(&numbers),
+                4 => cmyk_is_color(&numbers),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn rgb_is_color(c: &[f64]) -> bool {
+    (c[0] - c[1]).abs() > COMPONENT_EPSILON || (c[1] - c[2]).abs() > COMPONENT_EPSILON
+}
+
+fn cmyk_is_color(c: &[f64]) -> bool {
+    c[..3].iter().any(|v| v.abs() > COMPONENT_EPSILON)
+}
+
+/// Inspects an inline image (`BI ... ID <data> EI`) and returns whether it is
+/// color along with the position after `EI`.
+fn inline_image(content: &[u8], start: usize) -> (bool, usize) {
+    let Some(id_rel) = find_bytes(&content[start..], b"ID") else { return (false, content.len()) };
+    let header = &content[start..start + id_rel];
+    let is_color = [&b"/RGB"[..], b"/CMYK", b"/DeviceRGB", b"/DeviceCMYK"]
+        .iter()
+        .any(|cs| find_bytes(header, cs).is_some());
+
+    let data_start = start + id_rel + 2;
+    let mut pos = data_start;
+    while let Some(rel) = find_bytes(&content[pos..], b"EI") {
+        let at = pos + rel;
+        let before_ok = at > 0 && content[at - 1].is_ascii_whitespace();
+        let after_ok = content.get(at + 2).is_none_or(|b| b.is_ascii_whitespace());
+        if before_ok && after_ok {
+            return (is_color, at + 2);
+        }
+        pos = at + 2;
+    }
+    (is_color, content.len())
+}
+
+fn skip_literal_string(content: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < content.len() {
+        match content[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    content.len()
+}
+
+fn is_regular(b: u8) -> bool {
+    !b.is_ascii_whitespace() && !b"()<>[]{}/%".contains(&b)
+}
+
+fn parse_number(token: &[u8]) -> Option<f64> {
+    let first = *token.first()?;
+    if !(first.is_ascii_digit() || first == b'-' || first == b'+' || first == b'.') {
+        return None;
+    }
+    std::str::from_utf8(token).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{build_raw_pdf, estimate, raw_stream};
+
+    /// A PDF of one page per content stream, the pages sharing an image `/Im1` of `color_space`.
+    fn pdf_with_pages(contents: &[&str], color_space: &str) -> Vec<u8> {
+        let pages = contents.len();
+        let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+        let image = 3 + pages * 2;
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages),
+        ];
+        for (i, content) in contents.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /XObject << /Im1 {} 0 R >> >> \
+                 /Contents {} 0 R >>",
+                image,
+                4 + i * 2
+            ));
+            objects.push(raw_stream("", content));
+        }
+        let image_dict = "/Type /XObject /Subtype /Image /Width 1 /Height 1 /BitsPerComponent 8";
+        objects.push(raw_stream(&format!("{} /ColorSpace {}", image_dict, color_space), "x"));
+        let objects: Vec<&str> = objects.iter().map(String::as_str).collect();
+        build_raw_pdf(&objects)
+    }
+
+    #[test]
+    fn color_operators_are_told_from_gray_ones() {
+        for gray in ["0 0 0 rg", "0.5 0.5 0.5 RG", "0 0 0 1 k", "0.2 g", "0 0 0 sc", "0 0 0 0.4 SCN", "(1 0 0 rg) Tj"] {
+            assert!(!content_uses_color(gray.as_bytes()), "{}", gray);
+        }
+        for color in ["1 0 0 rg", "0 0.5 1 RG", "0 1 0 0 k", "0.1 0 0 0 K", "1 0 0 sc", "/P1 scn"] {
+            assert!(content_uses_color(color.as_bytes()), "{}", color);
+        }
+    }
+
+    #[test]
+    fn pages_are_classified_by_operators_and_images() {
+        let pdf = pdf_with_pages(
+            &["0 0 0 rg 10 10 50 50 re f", "1 0 0 rg 10 10 50 50 re f", "q 50 0 0 50 0 0 cm /Im1 Do Q"],
+            "/DeviceRGB",
+        );
+        // every page lists the image, so only a gray image leaves the first page gray
+        assert_eq!(analyze_page_colors(&pdf), Some(vec![true, true, true]));
+        let pdf = pdf_with_pages(&["0 0 0 rg 10 10 50 50 re f", "1 0 0 rg 10 10 50 50 re f"], "/DeviceGray");
+        assert_eq!(analyze_page_colors(&pdf), Some(vec![false, true]));
+
+        let result = estimate(&pdf, None, r#"{"analyze_color":true}"#).unwrap();
+        assert_eq!((result.color_pages, result.grayscale_pages), (Some(1), Some(1)));
+        assert_eq!(result.color_page_flags, Some(vec![false, true]));
+        let result = estimate(&pdf, None, "{}").unwrap();
+        assert_eq!((result.color_pages, result.color_page_flags), (None, None));
+    }
+
+    #[test]
+    fn indexed_color_space_uses_its_base() {
+        let pdf = pdf_with_pages(&["0 g"], "[/Indexed /DeviceRGB 1 <000000FF0000>]");
+        assert_eq!(analyze_page_colors(&pdf), Some(vec![true]));
+        let pdf = pdf_with_pages(&["0 g"], "[/Indexed /DeviceGray 1 <00FF>]");
+        assert_eq!(analyze_page_colors(&pdf), Some(vec![false]));
+    }
+
+    #[test]
+    fn indexed_color_space_naming_itself_as_its_base_ends() {
+        // object 6, the color space of the image, is an /Indexed space based on itself
+        let looped = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /Resources << /XObject << /Im1 5 0 R >> >> /Contents 4 0 R >>",
+            &raw_stream("", "q 50 0 0 50 0 0 cm /Im1 Do Q"),
+            &raw_stream("/Type /XObject /Subtype /Image /Width 1 /Height 1 /BitsPerComponent 8 /ColorSpace 6 0 R", "x"),
+            "[/Indexed 6 0 R 0 <00>]",
+        ]);
+        let result = estimate(&looped, None, r#"{"analyze_color":true}"#).unwrap();
+        assert_eq!(result.color_page_flags, Some(vec![false]));
+
+        let nested = "[/Indexed [/Indexed [/Indexed [/Indexed [/Indexed [/Indexed /DeviceRGB]]]]]]";
+        assert_eq!(analyze_page_colors(&pdf_with_pages(&["0 g"], nested)), Some(vec![false]));
+    }
+}
@@ -0,0 +1,437 @@
+//! # PDF Decryption
+//!
+//! Opens PDFs protected by the standard security handler (`/Filter /Standard`),
+//! with the password given in the `pdf_password` option.
+//!
+//! Encryption only covers strings and streams: dictionaries, the page tree
+//! included, are stored in the clear, so pages are counted whether or not the
+//! password is known. The key is needed for what strings and streams hold,
+//! such as the document information (`/Producer`) and the text of the pages
+//! (`extract_pdf_text_metrics`).
+//!
+//! ## Handlers
+//!
+//! - Revisions 2 and 3 (RC4 with 40 to 128-bit keys) and 4 (RC4 or AES-128
+//!   crypt filters), with the key derived as in ISO 32000-1, 7.6.3
+//! - The password may be the user or the owner password
+//! - Revisions 5 and 6 (AES-256) and other handlers (e.g. certificates) fail
+//!   with a `PdfError` naming them
+//!
+//! Without `pdf_password`, the empty user password is tried, which opens the
+//! many files that only restrict permissions (printing, copying) with an
+//! owner password.
+
+use crate::pdf_utils::{
+    decode_stream, dict_slice, dict_value, has_name, parse_ref, pdf_string_bytes, rfind_bytes, stream_parts,
+    text_string, ObjRef, ObjectIndex,
+};
+use crate::schema::EstimatorError;
+use aes::cipher::{BlockDecrypt, KeyInit};
+use aes::Aes128;
+use md5::{Digest, Md5};
+
+/// Padding appended to passwords shorter than 32 bytes (Algorithm 2).
+pub const PASSWORD_PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00,
+    0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// How the strings or the streams of a document are encrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CryptMethod {
+    /// Not encrypted (`/Identity` or `/None`).
+    Identity,
+    /// RC4 (`/V2`).
+    Rc4,
+    /// AES-128 in CBC mode, with the IV leading the data (`/AESV2`).
+    Aes128,
+}
+
+/// Which password opened a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordKind {
+    User,
+    Owner,
+}
+
+/// The key of an opened document, and how its strings and streams are encrypted.
+#[derive(Clone, Debug)]
+pub struct PdfDecryptor {
+    key: Vec<u8>,
+    /// Method of the streams (`/StmF`).
+    pub streams: CryptMethod,
+    /// Method of the strings (`/StrF`).
+    pub strings: CryptMethod,
+    /// Whether XMP metadata streams are encrypted (`/EncryptMetadata`).
+    pub encrypt_metadata: bool,
+    /// The password that opened the document.
+    pub opened_with: PasswordKind,
+}
+
+/// The values of a standard security handler dictionary.
+struct SecurityHandler {
+    revision: i64,
+    /// Key length in bytes.
+    key_length: usize,
+    owner: Vec<u8>,
+    user: Vec<u8>,
+    permissions: i32,
+    /// First element of the trailer's `/ID`.
+    id: Vec<u8>,
+    encrypt_metadata: bool,
+    streams: CryptMethod,
+    strings: CryptMethod,
+}
+
+/// Opens an encrypted PDF with a password.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw PDF file bytes
+/// * `index` - The object index of `bytes`
+/// * `password` - The user or the owner password (empty for none)
+///
+/// # Returns
+///
+/// The decryptor, or `None` when the document isn't encrypted.
+///
+/// # Errors
+///
+/// `EstimatorError::WrongPassword` when `password` is neither password, and
+/// `EstimatorError::PdfError` for unsupported handlers (see the module docs).
+pub fn unlock(bytes: &[u8], index: &ObjectIndex, password: &str) -> Result<Option<PdfDecryptor>, EstimatorError> {
+    let Some(encryption) = index.encryption() else { return Ok(None) };
+    let handler = SecurityHandler::read(bytes, encryption)?;
+    let padded = pad_password(password);
+    let (key, opened_with) = match handler.user_key(&padded) {
+        Some(key) => (key, PasswordKind::User),
+        None => (handler.owner_key(&padded).ok_or(EstimatorError::WrongPassword)?, PasswordKind::Owner),
+    };
+    Ok(Some(PdfDecryptor {
+        key,
+        streams: handler.streams,
+        strings: handler.strings,
+        encrypt_metadata: handler.encrypt_metadata,
+        opened_with,
+    }))
+}
+
+impl PdfDecryptor {
+    /// Short description of the encryption, e.g. `"RC4 128-bit"` or `"AES-128"`.
+    pub fn describe(&self) -> String {
+        match (self.streams, self.strings) {
+            (CryptMethod::Aes128, _) | (_, CryptMethod::Aes128) => "AES-128".to_string(),
+            _ => format!("RC4 {}-bit", self.key.len() * 8),
+        }
+    }
+
+    /// Decrypts a string of object `id`.
+    pub fn decrypt_string(&self, id: ObjRef, data: &[u8]) -> Vec<u8> {
+        self.decrypt(id, data, self.strings)
+    }
+
+    /// Reads a string entry of the dictionary of object `id`, decrypted (see
+    /// `ObjectIndex::resolve_string`).
+    pub fn resolve_string(&self, index: &ObjectIndex, id: ObjRef, key: &[u8]) -> Option<String> {
+        let value = dict_value(dict_slice(index.get(id)?), key)?;
+        // an indirect string is encrypted with the key of its own object
+        let (id, value) = match parse_ref(value) {
+            Some(string_id) => (string_id, index.get(string_id)?),
+            None => (id, value),
+        };
+        Some(text_string(self.decrypt_string(id, &pdf_string_bytes(value)?)))
+    }
+
+    /// Decrypts and decodes the data of stream object `id` (see `stream_data`).
+    ///
+    /// Cross-reference streams, and metadata streams without
+    /// `/EncryptMetadata`, are stored in the clear and only decoded.
+    pub fn stream_data(&self, id: ObjRef, obj: &[u8]) -> Option<Vec<u8>> {
+        let (dict, raw) = stream_parts(obj)?;
+        let metadata = has_name(dict, b"/Type", b"/Metadata");
+        if has_name(dict, b"/Type", b"/XRef") || (metadata && !self.encrypt_metadata) {
+            return Some(decode_stream(dict, raw));
+        }
+        Some(decode_stream(dict, &self.decrypt(id, raw, self.streams)))
+    }
+
+    fn decrypt(&self, id: ObjRef, data: &[u8], method: CryptMethod) -> Vec<u8> {
+        match method {
+            CryptMethod::Identity => data.to_vec(),
+            CryptMethod::Rc4 => rc4(&self.object_key(id, method), data),
+            CryptMethod::Aes128 => aes128_cbc_decrypt(&self.object_key(id, method), data),
+        }
+    }
+
+    /// The key of one object (Algorithm 1).
+    fn object_key(&self, id: ObjRef, method: CryptMethod) -> Vec<u8> {
+        let mut md5 = Md5::new();
+        md5.update(&self.key);
+        md5.update(&id.0.to_le_bytes()[..3]);
+        md5.update(id.1.to_le_bytes());
+        if method == CryptMethod::Aes128 {
+            md5.update(b"sAlT");
+        }
+        md5.finalize()[..(self.key.len() + 5).min(16)].to_vec()
+    }
+}
+
+impl SecurityHandler {
+    fn read(bytes: &[u8], dict: &[u8]) -> Result<Self, EstimatorError> {
+        if !has_name(dict, b"/Filter", b"/Standard") {
+            let filter = dict_value(dict, b"/Filter").and_then(|value| value.split(u8::is_ascii_whitespace).next());
+            return Err(EstimatorError::PdfError(format!(
+                "PDF is encrypted with the {} security handler; only password encryption (/Standard) can be opened",
+                String::from_utf8_lossy(filter.unwrap_or(b"(unnamed)"))
+            )));
+        }
+        let version = dict_integer(dict, b"/V").unwrap_or(0);
+        let revision = dict_integer(dict, b"/R").unwrap_or(2);
+        if version >= 5 || revision >= 5 {
+            return Err(EstimatorError::PdfError(format!(
+                "PDF uses AES-256 encryption (security handler revision {}), which can't be opened yet",
+                revision
+            )));
+        }
+        let string = |key: &[u8]| {
+            dict_value(dict, key).and_then(pdf_string_bytes).filter(|value| value.len() >= 32).ok_or_else(|| {
+                EstimatorError::PdfError(format!(
+                    "the encryption dictionary's {} entry is missing or too short",
+                    String::from_utf8_lossy(key)
+                ))
+            })
+        };
+        let (streams, strings) = if version == 4 {
+            (crypt_filter(dict, b"/StmF"), crypt_filter(dict, b"/StrF"))
+        } else {
+            (CryptMethod::Rc4, CryptMethod::Rc4)
+        };
+        let key_bits = match version {
+            4 => 128,
+            2 | 3 => dict_integer(dict, b"/Length").unwrap_or(40).clamp(40, 128),
+            _ => 40,
+        };
+        Ok(SecurityHandler {
+            revision,
+            key_length: if revision == 2 { 5 } else { key_bits as usize / 8 },
+            owner: string(b"/O")?,
+            user: string(b"/U")?,
+            // `/P` is a 32-bit field, which some writers store unsigned
+            permissions: dict_integer(dict, b"/P").unwrap_or(-1) as i32,
+            id: trailer_id(bytes).unwrap_or_default(),
+            encrypt_metadata: !dict_value(dict, b"/EncryptMetadata").is_some_and(|value| value.starts_with(b"false")),
+            streams,
+            strings,
+        })
+    }
+
+    /// The file key for a padded password (Algorithm 2).
+    fn file_key(&self, padded: &[u8; 32]) -> Vec<u8> {
+        let mut md5 = Md5::new();
+        md5.update(padded);
+        md5.update(&self.owner[..32]);
+        md5.update(self.permissions.to_le_bytes());
+        md5.update(&self.id);
+        if self.revision >= 4 && !self.encrypt_metadata {
+            md5.update([0xFF; 4]);
+        }
+        let mut hash = md5.finalize().to_vec();
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                hash = Md5::digest(&hash[..self.key_length]).to_vec();
+            }
+        }
+        hash.truncate(self.key_length);
+        hash
+    }
+
+    /// The file key when `padded` is the user password (Algorithms 4, 5, and 6).
+    fn user_key(&self, padded: &[u8; 32]) -> Option<Vec<u8>> {
+        let key = self.file_key(padded);
+        let matches = if self.revision == 2 {
+            rc4(&key, &PASSWORD_PADDING) == self.user[..32]
+        } else {
+            let mut md5 = Md5::new();
+            md5.update(PASSWORD_PADDING);
+            md5.update(&self.id);
+            let mut hash = rc4(&key, &md5.finalize());
+            for round in 1..=19u8 {
+                hash = rc4(&xor_key(&key, round), &hash);
+            }
+            // only the first 16 bytes are defined; the rest is arbitrary padding
+            hash[..16] == self.user[..16]
+        };
+        matches.then_some(key)
+    }
+
+    /// The file key when `padded` is the owner password (Algorithm 7).
+    fn owner_key(&self, padded: &[u8; 32]) -> Option<Vec<u8>> {
+        let mut hash = Md5::digest(padded).to_vec();
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                hash = Md5::digest(&hash).to_vec();
+            }
+        }
+        let key = &hash[..self.key_length];
+        // `/O` is the padded user password, encrypted with the owner key
+        let user = if self.revision == 2 {
+            rc4(key, &self.owner[..32])
+        } else {
+            (0..=19u8).rev().fold(self.owner[..32].to_vec(), |user, round| rc4(&xor_key(key, round), &user))
+        };
+        self.user_key(&user.try_into().ok()?)
+    }
+}
+
+/// Pads or truncates a password to 32 bytes (Algorithm 2, step a).
+///
+/// Passwords are encoded in PDFDocEncoding, which matches Latin-1 for the
+/// characters people type; other passwords are taken as UTF-8.
+pub fn pad_password(password: &str) -> [u8; 32] {
+    let bytes: Vec<u8> = if password.chars().all(|c| (c as u32) < 0x100) {
+        password.chars().map(|c| c as u8).collect()
+    } else {
+        password.as_bytes().to_vec()
+    };
+    let mut padded = PASSWORD_PADDING;
+    let len = bytes.len().min(32);
+    padded[..len].copy_from_slice(&bytes[..len]);
+    padded[len..].copy_from_slice(&PASSWORD_PADDING[..32 - len]);
+    padded
+}
+
+/// Encrypts or decrypts data with RC4, which is its own inverse.
+pub fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// Decrypts AES-128-CBC data whose first 16 bytes are the IV, removing the PKCS #7 padding.
+///
+/// Data shorter than two blocks decrypts to nothing, and a trailing partial
+/// block is dropped.
+fn aes128_cbc_decrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let Ok(cipher) = Aes128::new_from_slice(key) else { return Vec::new() };
+    if data.len() < 32 {
+        return Vec::new();
+    }
+    let mut plain = Vec::with_capacity(data.len() - 16);
+    let mut previous = &data[..16];
+    for block in data[16..].chunks_exact(16) {
+        let mut decrypted = *aes::Block::from_slice(block);
+        cipher.decrypt_block(&mut decrypted);
+        plain.extend(decrypted.iter().zip(previous).map(|(byte, chain)| byte ^ chain));
+        previous = block;
+    }
+    let padding = plain.last().copied().unwrap_or(0) as usize;
+    if (1..=16).contains(&padding) && plain[plain.len() - padding..].iter().all(|byte| *byte as usize == padding) {
+        plain.truncate(plain.len() - padding);
+    }
+    plain
+}
+
+fn xor_key(key: &[u8], round: u8) -> Vec<u8> {
+    key.iter().map(|byte| byte ^ round).collect()
+}
+
+/// The method of a version 4 crypt filter named by `/StmF` or `/StrF`.
+fn crypt_filter(dict: &[u8], key: &[u8]) -> CryptMethod {
+    let Some(name) = dict_value(dict, key).and_then(|value| value.split(|b| b.is_ascii_whitespace()).next()) else {
+        return CryptMethod::Identity;
+    };
+    if name == b"/Identity" {
+        return CryptMethod::Identity;
+    }
+    let filter = dict_value(dict, b"/CF").map(dict_slice).and_then(|filters| dict_value(filters, name)).map(dict_slice);
+    match filter {
+        Some(filter) if has_name(filter, b"/CFM", b"/AESV2") => CryptMethod::Aes128,
+        Some(filter) if has_name(filter, b"/CFM", b"/None") => CryptMethod::Identity,
+        _ => CryptMethod::Rc4,
+    }
+}
+
+/// Reads an integer entry of a dictionary.
+fn dict_integer(dict: &[u8], key: &[u8]) -> Option<i64> {
+    let value = dict_value(dict, key)?;
+    let end = value.iter().skip(1).position(|b| !b.is_ascii_digit()).map_or(value.len(), |end| end + 1);
+    std::str::from_utf8(&value[..end]).ok()?.parse().ok()
+}
+
+/// The first element of the last trailer's `/ID` array.
+fn trailer_id(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = bytes.len();
+    while let Some(at) = rfind_bytes(&bytes[..pos], b"/ID") {
+        let value = bytes[at + 3..].trim_ascii_start();
+        if let Some(array) = value.strip_prefix(b"[") {
+            return pdf_string_bytes(array);
+        }
+        pos = at;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn open(pdf: &[u8], password: &str) -> Result<Option<PdfDecryptor>, EstimatorError> {
+        unlock(pdf, &ObjectIndex::build(pdf), password)
+    }
+
+    #[test]
+    fn rc4_matches_its_test_vectors() {
+        assert_eq!(rc4(b"Key", b"Plaintext"), [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+        assert_eq!(rc4(b"Wiki", b"pedia"), [0x10, 0x21, 0xBF, 0x04, 0x20]);
+        assert_eq!(rc4(b"Key", &rc4(b"Key", b"round trip")), b"round trip");
+    }
+
+    #[test]
+    fn passwords_are_padded_to_32_bytes() {
+        assert_eq!(pad_password(""), PASSWORD_PADDING);
+        let padded = pad_password("pässwort");
+        assert_eq!(padded[..8], [b'p', 0xE4, b's', b's', b'w', b'o', b'r', b't']);
+        assert_eq!(padded[8..], PASSWORD_PADDING[..24]);
+        assert_eq!(pad_password(&"x".repeat(40)), [b'x'; 32]);
+    }
+
+    #[test]
+    fn either_password_opens_both_ciphers() {
+        for cipher in [PdfCipher::Rc4, PdfCipher::Aes128] {
+            let pdf = build_protected_pdf(2, "user", "owner", cipher);
+            let user = open(&pdf, "user").unwrap().unwrap();
+            assert_eq!(user.opened_with, PasswordKind::User);
+            assert_eq!(open(&pdf, "owner").unwrap().unwrap().opened_with, PasswordKind::Owner);
+            assert!(matches!(open(&pdf, "guess"), Err(EstimatorError::WrongPassword)));
+            assert!(matches!(open(&pdf, ""), Err(EstimatorError::WrongPassword)));
+            let method = if cipher == PdfCipher::Rc4 { CryptMethod::Rc4 } else { CryptMethod::Aes128 };
+            assert_eq!((user.streams, user.strings), (method, method));
+        }
+        let permissions_only = build_protected_pdf(1, "", "owner", PdfCipher::Rc4);
+        assert_eq!(open(&permissions_only, "").unwrap().unwrap().describe(), "RC4 128-bit");
+        assert!(open(&build_pdf(1, XrefStyle::Classic, false, false), "any").unwrap().is_none());
+    }
+
+    #[test]
+    fn aes_256_is_reported_as_unsupported() {
+        let pdf = build_protected_pdf(1, "user", "owner", PdfCipher::Aes128);
+        let pdf = String::from_utf8_lossy(&pdf).replacen("/V 4 /R 4", "/V 5 /R 6", 1).into_bytes();
+        match open(&pdf, "user") {
+            Err(EstimatorError::PdfError(message)) => assert!(message.contains("AES-256"), "{}", message),
+            other => panic!("{:?}", other.map(|d| d.is_some())),
+        }
+    }
+}
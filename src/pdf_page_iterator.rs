@@ -0,0 +1,101 @@
+//! # Lazy PDF Page Sizes
+//!
+//! `PdfPageSizeIterator` hands out page sizes one at a time, for callers such
+//! as thumbnail strips that only need the pages in view. Building it indexes
+//! the objects and resolves the page tree root; every `next()` call walks only
+//! as far as the next page (see `pdf::PageWalker`).
+//!
+//! `next()` follows the JavaScript iterator protocol, returning
+//! `{ done, value }`. wasm-bindgen can't define `[Symbol.iterator]` on a class,
+//! so `for…of` loops go through the `pdfPageSizes` generator in
+//! `js_bridges/page_size_iterator.js`, which also frees the iterator.
+
+use crate::assembly::error_code;
+use crate::pdf::PageWalker;
+use crate::pdf_utils::{ObjRef, ObjectIndex};
+use crate::schema::EstimatorError;
+use js_sys::{Object, Reflect};
+use serde_json::json;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Iterator over the page sizes of a PDF, resolved on demand.
+///
+/// # Example
+///
+/// ```javascript
+/// const pages = new PdfPageSizeIterator(bytes);
+/// for (let step = pages.next(); !step.done; step = pages.next()) {
+///     const { index, width_mm, height_mm } = step.value;
+///     drawThumbnail(index, width_mm, height_mm);
+/// }
+/// pages.free();
+/// ```
+#[wasm_bindgen]
+pub struct PdfPageSizeIterator {
+    bytes: Vec<u8>,
+    /// Object offsets of the index over `bytes` (see `ObjectIndex::into_offsets`).
+    offsets: HashMap<ObjRef, usize>,
+    walker: PageWalker,
+}
+
+#[wasm_bindgen]
+impl PdfPageSizeIterator {
+    /// Indexes a PDF and resolves its page tree root.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The raw PDF file bytes (copied into the iterator)
+    ///
+    /// # Errors
+    ///
+    /// Throws a JSON string `{error, error_code}` (`pdf_error`) when the page
+    /// tree can't be found among the uncompressed objects.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<PdfPageSizeIterator, JsValue> {
+        let bytes = bytes.to_vec();
+        let index = ObjectIndex::build(&bytes);
+        let Some(walker) = PageWalker::new(&index) else {
+            let err = EstimatorError::PdfError("page tree not found".into());
+            return Err(JsValue::from_str(
+                &json!({"error": err.to_string(), "error_code": error_code(&err)}).to_string(),
+            ));
+        };
+        let offsets = index.into_offsets();
+        Ok(Self { bytes, offsets, walker })
+    }
+
+    /// Resolves the next page (`next()` in JavaScript).
+    ///
+    /// # Returns
+    ///
+    /// `{ done: false, value: { index, width_mm, height_mm } }` with the 0-based
+    /// page index and its size as displayed (rotation applied), or
+    /// `{ done: true, value: undefined }` after the last page.
+    #[wasm_bindgen(js_name = next)]
+    pub fn next_step(&mut self) -> Object {
+        let index = ObjectIndex::from_offsets(&self.bytes, std::mem::take(&mut self.offsets));
+        let page = self.walker.next_page(&index);
+        self.offsets = index.into_offsets();
+
+        let step = Object::new();
+        let set = |target: &Object, key: &str, value: JsValue| {
+            let _ = Reflect::set(target, &JsValue::from_str(key), &value);
+        };
+        set(&step, "done", JsValue::from_bool(page.is_none()));
+        if let Some((page, size)) = page {
+            let value = Object::new();
+            set(&value, "index", JsValue::from_f64(page as f64));
+            set(&value, "width_mm", JsValue::from_f64(size.width_mm));
+            set(&value, "height_mm", JsValue::from_f64(size.height_mm));
+            set(&step, "value", value.into());
+        }
+        step
+    }
+
+    /// Number of PDF objects read so far; grows with the pages consumed, not
+    /// with the document.
+    pub fn objects_read(&self) -> usize {
+        self.walker.objects_read()
+    }
+}
@@ -0,0 +1,145 @@
+//! # PDF Portfolios and Embedded Files
+//!
+//! A PDF portfolio (a catalog with a `/Collection`) is a container: its own
+//! page tree usually holds a single cover sheet, while the documents it was
+//! made from are embedded files. Counting the page tree is correct but
+//! misleading there, so the PDF estimator looks for the evidence too.
+//!
+//! ## Evidence
+//!
+//! - Embedded files: file specifications (`/Type /Filespec`) with an `/EF`
+//!   stream, from the document's attachments or a portfolio
+//! - Page tree roots: `/Type /Pages` nodes with `/Kids` but no `/Parent`.
+//!   Documents merged by some tools keep a secondary tree the catalog doesn't
+//!   point at
+//!
+//! Like the rest of the PDF helpers, only uncompressed objects are seen.
+
+use crate::pdf_utils::{dict_ref, dict_slice, dict_value, has_name, pdf_string, stream_data, ObjRef, ObjectIndex};
+use std::collections::HashSet;
+
+/// Largest page count that is taken for a portfolio cover sheet.
+pub const PORTFOLIO_COVER_PAGES: usize = 2;
+
+/// A file embedded in a PDF.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmbeddedFile {
+    /// File name from the file specification (`/UF`, else `/F`), if it has one.
+    pub name: Option<String>,
+    /// The embedded file stream.
+    pub stream: ObjRef,
+}
+
+/// What a PDF holds besides the page tree the catalog points at.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PortfolioScan {
+    /// Whether the catalog has a `/Collection` (the document is a portfolio).
+    pub collection: bool,
+    /// Number of page tree roots (`/Pages` nodes with `/Kids` and no `/Parent`).
+    pub pages_roots: usize,
+    /// Embedded files, one per distinct file stream.
+    pub embedded_files: Vec<EmbeddedFile>,
+}
+
+impl PortfolioScan {
+    /// Whether a structured count of `page_count` most likely covers only a cover sheet.
+    ///
+    /// True for documents of at most `PORTFOLIO_COVER_PAGES` pages that are
+    /// portfolios with embedded files, or that hold more than one page tree.
+    pub fn covers_cover_sheet_only(&self, page_count: usize) -> bool {
+        page_count <= PORTFOLIO_COVER_PAGES
+            && ((self.collection && !self.embedded_files.is_empty()) || self.pages_roots > 1)
+    }
+}
+
+/// Looks for portfolio evidence in a PDF, given the index of its objects.
+pub fn scan_portfolio(index: &ObjectIndex) -> PortfolioScan {
+    let mut scan = PortfolioScan {
+        collection: index.catalog().is_some_and(|catalog| dict_value(catalog, b"/Collection").is_some()),
+        ..Default::default()
+    };
+    let mut streams = HashSet::new();
+    let mut files: Vec<(ObjRef, EmbeddedFile)> = Vec::new();
+    for (id, body) in index.objects() {
+        let dict = dict_slice(body);
+        if has_name(dict, b"/Type", b"/Pages") {
+            if dict_value(dict, b"/Kids").is_some() && dict_value(dict, b"/Parent").is_none() {
+                scan.pages_roots += 1;
+            }
+        } else if let Some(ef) = index.resolve_dict(dict, b"/EF")
+            && let Some(stream) = dict_ref(ef, b"/F").or_else(|| dict_ref(ef, b"/UF"))
+            && streams.insert(stream)
+        {
+            let name = dict_value(dict, b"/UF").or_else(|| dict_value(dict, b"/F")).and_then(pdf_string);
+            files.push((id, EmbeddedFile { name, stream }));
+        }
+    }
+    // the index is unordered; object numbers roughly follow the order files were added
+    files.sort_by_key(|(id, _)| *id);
+    scan.embedded_files = files.into_iter().map(|(_, file)| file).collect();
+    scan
+}
+
+/// Reads (and inflates) the contents of an embedded file.
+pub fn read_embedded_file(index: &ObjectIndex, file: &EmbeddedFile) -> Option<Vec<u8>> {
+    stream_data(index.get(file.stream)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn portfolios_list_their_embedded_files() {
+        let report = build_pdf(3, XrefStyle::Classic, false, false);
+        let appendix = build_pdf(5, XrefStyle::Classic, false, false);
+        let pdf = build_portfolio_pdf(&[("report.pdf", &report), ("Anhang ü.pdf", &appendix)]);
+        let index = ObjectIndex::build(&pdf);
+        let scan = scan_portfolio(&index);
+        assert!(scan.collection);
+        // the embedded page trees are compressed away
+        assert_eq!(scan.pages_roots, 1);
+        let names: Vec<_> = scan.embedded_files.iter().map(|file| file.name.as_deref()).collect();
+        assert_eq!(names, [Some("report.pdf"), Some("Anhang ü.pdf")]);
+        let contents: Vec<_> = scan.embedded_files.iter().map(|file| read_embedded_file(&index, file)).collect();
+        assert_eq!(contents, [Some(report), Some(appendix)]);
+        assert!(scan.covers_cover_sheet_only(1));
+        assert!(!scan.covers_cover_sheet_only(PORTFOLIO_COVER_PAGES + 1));
+    }
+
+    #[test]
+    fn plain_pdfs_are_not_portfolios() {
+        let pdf = build_pdf(1, XrefStyle::Classic, false, false);
+        let scan = scan_portfolio(&ObjectIndex::build(&pdf));
+        assert_eq!(scan, PortfolioScan { pages_roots: 1, ..Default::default() });
+        assert!(!scan.covers_cover_sheet_only(1));
+
+        // attachments without a collection don't make a portfolio
+        let attached = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>",
+            "<< /Type /Filespec /F (notes.txt) /EF << /F 5 0 R >> >>",
+            &raw_stream("/Type /EmbeddedFile", "attached notes"),
+        ]);
+        let scan = scan_portfolio(&ObjectIndex::build(&attached));
+        assert!(!scan.collection && scan.embedded_files.len() == 1);
+        assert!(!scan.covers_cover_sheet_only(1));
+    }
+
+    #[test]
+    fn secondary_page_trees_are_counted() {
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>",
+            "<< /Type /Pages /Kids [5 0 R 6 0 R] /Count 2 >>",
+            "<< /Type /Page /Parent 4 0 R /MediaBox [0 0 595 842] >>",
+            "<< /Type /Page /Parent 4 0 R /MediaBox [0 0 595 842] >>",
+        ]);
+        let scan = scan_portfolio(&ObjectIndex::build(&pdf));
+        assert_eq!((scan.collection, scan.pages_roots), (false, 2));
+        assert!(scan.covers_cover_sheet_only(1));
+    }
+}
@@ -0,0 +1,659 @@
+//! # PDF Text Statistics
+//!
+//! Counts the words and characters of text-based PDFs, for quotes priced by
+//! word count (e.g. translation). It decodes every page's content streams, so
+//! it only runs when the `extract_pdf_text_metrics` option is set.
+//!
+//! ## Extraction
+//!
+//! - Content streams are inflated when they are `FlateDecode`d or unfiltered;
+//!   streams with other filters are skipped and counted in `skipped_streams`
+//! - Text is taken from the string operands of the show-text operators (`Tj`,
+//!   `TJ`, `'`, `"`), literal strings with their escapes and hex strings alike.
+//!   Text in form XObjects (headers, footers, stamps) is included
+//! - Numbers in `TJ` arrays are positioning, not text; only gaps of at least
+//!   `TJ_WORD_GAP` thousandths of an em separate words
+//! - Line moves (`T*`, `Tm`, `Td`/`TD` with a vertical offset) and the end of
+//!   a text object separate words and lines
+//! - Fonts with a `/ToUnicode` map are decoded through it; other simple fonts
+//!   are read byte by byte as Latin-1
+//! - Streams of encrypted PDFs are decrypted first, when the document could
+//!   be opened (see the `pdf_crypt` module)
+//!
+//! ## Accuracy
+//!
+//! The counts are approximate, and `text_metrics.confidence` says how far:
+//!
+//! - Composite (`Type0`, CID) fonts without a `/ToUnicode` map can't be
+//!   decoded; their glyphs are counted as characters and words are estimated
+//!   at `UNDECODED_CHARS_PER_WORD` glyphs each, lowering the confidence
+//! - Skipped content streams lower the confidence in proportion
+//! - Ligatures (`fi`, `fl`) in simple fonts without `/ToUnicode` count as one
+//!   character, and custom encodings are read as Latin-1
+//! - Text drawn as outlines or in scanned images isn't found at all
+//!
+//! Characters are counted without whitespace, as PDFs rarely store spaces
+//! reliably.
+
+use crate::language::LANGUAGE_SAMPLE_CHARS;
+use crate::pdf_color::page_resources;
+use crate::pdf_crypt::PdfDecryptor;
+use crate::pdf_utils::{
+    dict_ref_array, dict_slice, dict_value, has_name, hex_string_bytes, literal_string_bytes, literal_string_end,
+    parse_ref, stream_data, ObjRef, ObjectIndex,
+};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Confidence of text metrics when every glyph was decoded and every stream read.
+pub const TEXT_METRICS_CONFIDENCE: f64 = 0.9;
+
+/// Gap in a `TJ` array, in thousandths of an em, from which it separates words.
+pub const TJ_WORD_GAP: f64 = 250.0;
+
+/// Glyphs per word assumed for text in fonts that can't be decoded, spaces included.
+pub const UNDECODED_CHARS_PER_WORD: f64 = 6.0;
+
+/// Maximum nesting depth for form XObjects.
+const MAX_FORM_DEPTH: usize = 8;
+
+/// Maximum nesting depth for arrays in a content stream; deeper brackets are skipped.
+const MAX_ARRAY_DEPTH: usize = 16;
+
+/// Largest `bfrange` of a `/ToUnicode` map that is expanded.
+const MAX_CMAP_RANGE: u32 = 0xFFFF;
+
+/// Word and character counts extracted from the pages of a PDF.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PdfTextStats {
+    /// Number of pages read.
+    pub pages: usize,
+    /// Number of words, including those estimated for undecoded glyphs.
+    pub words: usize,
+    /// Number of characters other than whitespace, including undecoded glyphs.
+    pub chars: usize,
+    /// Number of text lines.
+    pub lines: usize,
+    /// Characters decoded to Unicode.
+    pub decoded_chars: usize,
+    /// Glyphs shown in composite fonts without a `/ToUnicode` map.
+    pub undecoded_glyphs: usize,
+    /// Content streams read (pages and form XObjects).
+    pub streams: usize,
+    /// Content streams skipped for their filter.
+    pub skipped_streams: usize,
+    /// The start of the decoded text, up to `LANGUAGE_SAMPLE_CHARS` bytes, one
+    /// line per text line, for `detect_language`.
+    pub sample: String,
+}
+
+impl PdfTextStats {
+    /// Average words per page.
+    pub fn words_per_page(&self) -> f64 {
+        self.words as f64 / self.pages.max(1) as f64
+    }
+
+    /// How far the counts can be trusted, from 0 to 1 (see the module docs).
+    pub fn confidence(&self) -> f64 {
+        let glyphs = self.decoded_chars + self.undecoded_glyphs;
+        let decoded = if glyphs == 0 { 1.0 } else { self.decoded_chars as f64 / glyphs as f64 };
+        let all_streams = self.streams + self.skipped_streams;
+        let read = if all_streams == 0 { 1.0 } else { self.streams as f64 / all_streams as f64 };
+        TEXT_METRICS_CONFIDENCE * decoded * read
+    }
+}
+
+/// Counts the words and characters of every page of a PDF.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw PDF file bytes
+/// * `decryptor` - The key of an encrypted PDF (see `pdf_crypt::unlock`); without it,
+///   encrypted streams read as noise
+///
+/// # Returns
+///
+/// The counts, or `None` if the page tree could not be resolved (e.g. it is
+/// stored in a compressed object stream).
+pub fn extract_text_stats(bytes: &[u8], decryptor: Option<&PdfDecryptor>) -> Option<PdfTextStats> {
+    let index = ObjectIndex::build(bytes);
+    let pages = index.page_refs()?;
+    let mut reader = TextReader {
+        index: &index,
+        decryptor,
+        stats: PdfTextStats { pages: pages.len(), ..Default::default() },
+        fonts: HashMap::new(),
+        visited: HashSet::new(),
+    };
+    for page in pages {
+        let Some(obj) = index.get(page) else { continue };
+        let dict = dict_slice(obj);
+        let mut text = PageText::default();
+        reader.visited.clear();
+        let resources = page_resources(&index, dict);
+        for id in dict_ref_array(dict, b"/Contents") {
+            let Some(stream) = index.get(id) else { continue };
+            if let Some(content) = reader.readable_stream(id, stream) {
+                reader.read_content(&content, resources, &mut text, 0);
+            }
+        }
+        text.add_to(&mut reader.stats);
+    }
+    Some(reader.stats)
+}
+
+/// Decrypts (when `decryptor` is set) and decodes the data of stream object `id`.
+fn decoded_stream(decryptor: Option<&PdfDecryptor>, id: ObjRef, obj: &[u8]) -> Option<Vec<u8>> {
+    match decryptor {
+        Some(decryptor) => decryptor.stream_data(id, obj),
+        None => stream_data(obj),
+    }
+}
+
+/// The text of a page as it is read, one line at a time.
+#[derive(Default)]
+struct PageText {
+    line: String,
+    words: usize,
+    chars: usize,
+    lines: usize,
+    sample: String,
+}
+
+impl PageText {
+    fn push(&mut self, text: &str) {
+        self.line.push_str(text);
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.line.push(c);
+    }
+
+    fn break_word(&mut self) {
+        self.line.push(' ');
+    }
+
+    fn break_line(&mut self) {
+        let words = self.line.split_whitespace().count();
+        if words > 0 {
+            self.words += words;
+            self.chars += self.line.chars().filter(|c| !c.is_whitespace()).count();
+            self.lines += 1;
+            if self.sample.len() < LANGUAGE_SAMPLE_CHARS {
+                self.sample.push_str(self.line.trim());
+                self.sample.push('\n');
+            }
+        }
+        self.line.clear();
+    }
+
+    fn add_to(mut self, stats: &mut PdfTextStats) {
+        self.break_line();
+        if stats.sample.len() < LANGUAGE_SAMPLE_CHARS {
+            stats.sample.push_str(&self.sample);
+        }
+        stats.words += self.words;
+        stats.chars += self.chars;
+        stats.decoded_chars += self.chars;
+        stats.lines += self.lines;
+    }
+}
+
+/// A font of a resource dictionary, as far as decoding its strings goes.
+#[derive(Clone, Default)]
+struct Font {
+    /// Composite (`Type0`) fonts use multi-byte codes.
+    composite: bool,
+    to_unicode: Option<ToUnicode>,
+}
+
+impl Font {
+    /// Decodes a shown string, counting the glyphs that can't be decoded.
+    fn decode(&self, bytes: &[u8], stats: &mut PdfTextStats, text: &mut PageText) {
+        match (&self.to_unicode, self.composite) {
+            (Some(map), _) => {
+                let code_len = match map.code_len {
+                    0 if self.composite => 2,
+                    0 => 1,
+                    len => len,
+                };
+                for code in bytes.chunks(code_len) {
+                    match map.codes.get(&code_value(code)) {
+                        Some(unicode) => text.push(unicode),
+                        // unmapped codes in a simple font are still read as Latin-1
+                        None if !self.composite => text.push_char(char::from(code[0])),
+                        None => {
+                            stats.undecoded_glyphs += 1;
+                            stats.chars += 1;
+                        }
+                    }
+                }
+            }
+            (None, true) => {
+                let glyphs = bytes.len() / 2;
+                stats.undecoded_glyphs += glyphs;
+                stats.chars += glyphs;
+                stats.words += (glyphs as f64 / UNDECODED_CHARS_PER_WORD).round() as usize;
+                text.break_word();
+            }
+            (None, false) => bytes.iter().for_each(|&b| text.push_char(char::from(b))),
+        }
+    }
+}
+
+/// A `/ToUnicode` map: the Unicode text of each character code.
+#[derive(Clone, Default)]
+struct ToUnicode {
+    /// Bytes per character code.
+    code_len: usize,
+    codes: HashMap<u32, String>,
+}
+
+/// Parses the `bfchar` and `bfrange` sections of a `/ToUnicode` CMap.
+fn parse_to_unicode(data: &[u8]) -> ToUnicode {
+    let tokens = cmap_tokens(data);
+    let mut map = ToUnicode::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            CmapToken::Keyword(b"beginbfchar") => {
+                i += 1;
+                while let (Some(CmapToken::Hex(src)), Some(CmapToken::Hex(dst))) = (tokens.get(i), tokens.get(i + 1)) {
+                    map.code_len = map.code_len.max(src.len());
+                    map.codes.insert(code_value(src), utf16_text(dst));
+                    i += 2;
+                }
+            }
+            CmapToken::Keyword(b"beginbfrange") => {
+                i += 1;
+                while let (Some(CmapToken::Hex(lo)), Some(CmapToken::Hex(hi))) = (tokens.get(i), tokens.get(i + 1)) {
+                    map.code_len = map.code_len.max(lo.len());
+                    let (lo_code, hi_code) = (code_value(lo), code_value(hi));
+                    let span = hi_code.saturating_sub(lo_code).min(MAX_CMAP_RANGE);
+                    match tokens.get(i + 2) {
+                        Some(CmapToken::Hex(dst)) => {
+                            for offset in 0..=span {
+                                map.codes.insert(lo_code + offset, utf16_text(&offset_utf16(dst, offset)));
+                            }
+                        }
+                        Some(CmapToken::Array(dsts)) => {
+                            for (offset, dst) in dsts.iter().take(span as usize + 1).enumerate() {
+                                map.codes.insert(lo_code + offset as u32, utf16_text(dst));
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 3;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    map
+}
+
+/// A token of a CMap: a hex string, an array of hex strings, or anything else.
+enum CmapToken<'a> {
+    Hex(Vec<u8>),
+    Array(Vec<Vec<u8>>),
+    Keyword(&'a [u8]),
+}
+
+fn cmap_tokens(data: &[u8]) -> Vec<CmapToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut array: Option<Vec<Vec<u8>>> = None;
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'<' if data.get(i + 1) != Some(&b'<') => {
+                let end = data[i..].iter().position(|b| *b == b'>').map_or(data.len(), |end| i + end);
+                let bytes = hex_string_bytes(&data[i + 1..end]);
+                match array.as_mut() {
+                    Some(items) => items.push(bytes),
+                    None => tokens.push(CmapToken::Hex(bytes)),
+                }
+                i = end + 1;
+            }
+            b'[' => {
+                array = Some(Vec::new());
+                i += 1;
+            }
+            b']' => {
+                tokens.extend(array.take().map(CmapToken::Array));
+                i += 1;
+            }
+            b if is_regular(b) => {
+                let start = i;
+                while i < data.len() && is_regular(data[i]) {
+                    i += 1;
+                }
+                tokens.push(CmapToken::Keyword(&data[start..i]));
+            }
+            _ => i += 1,
+        }
+    }
+    tokens
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).fold(0u32, |code, b| code << 8 | u32::from(*b))
+}
+
+/// Adds `offset` to the last UTF-16 code unit of a `bfrange` destination.
+fn offset_utf16(dst: &[u8], offset: u32) -> Vec<u8> {
+    let mut dst = dst.to_vec();
+    if dst.len() >= 2 {
+        let last = dst.len() - 2;
+        let unit = u16::from_be_bytes([dst[last], dst[last + 1]]).wrapping_add(offset as u16);
+        dst[last..].copy_from_slice(&unit.to_be_bytes());
+    }
+    dst
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Reads a font dictionary.
+fn read_font(index: &ObjectIndex, decryptor: Option<&PdfDecryptor>, id: ObjRef) -> Font {
+    let Some(font) = index.get(id) else { return Font::default() };
+    let dict = dict_slice(font);
+    let to_unicode = dict_value(dict, b"/ToUnicode")
+        .and_then(parse_ref)
+        .and_then(|id| decoded_stream(decryptor, id, index.get(id)?))
+        .map(|data| parse_to_unicode(&data));
+    Font { composite: has_name(dict, b"/Subtype", b"/Type0"), to_unicode }
+}
+
+/// An operand collected while tokenizing a content stream.
+enum Operand {
+    Number(f64),
+    Name(Vec<u8>),
+    String(Vec<u8>),
+    Array(Vec<Operand>),
+}
+
+/// Reads the text of content streams into the statistics of a document.
+struct TextReader<'a, 'i> {
+    index: &'i ObjectIndex<'a>,
+    decryptor: Option<&'i PdfDecryptor>,
+    stats: PdfTextStats,
+    /// Fonts read so far, shared by the pages and forms that use them.
+    fonts: HashMap<ObjRef, Rc<Font>>,
+    /// Forms read on the current page.
+    visited: HashSet<ObjRef>,
+}
+
+impl TextReader<'_, '_> {
+    /// Decodes content stream `id`, or counts it as skipped when its filter isn't supported.
+    fn readable_stream(&mut self, id: ObjRef, obj: &[u8]) -> Option<Vec<u8>> {
+        let filters = dict_value(dict_slice(obj), b"/Filter").unwrap_or_default();
+        let supported = filters
+            .split(|b| *b == b'/')
+            .skip(1)
+            .map(|name| name.split(|b| !b.is_ascii_alphanumeric()).next().unwrap_or_default())
+            .all(|name| name == b"FlateDecode" || name == b"Fl");
+        if !supported {
+            self.stats.skipped_streams += 1;
+            return None;
+        }
+        self.stats.streams += 1;
+        decoded_stream(self.decryptor, id, obj)
+    }
+
+    /// Maps the font resource names of a resource dictionary to fonts.
+    fn resource_fonts(&mut self, resources: Option<&[u8]>) -> HashMap<Vec<u8>, Rc<Font>> {
+        let mut fonts = HashMap::new();
+        let Some(font_dict) = resources.and_then(|resources| self.index.resolve_dict(resources, b"/Font")) else {
+            return fonts;
+        };
+        let inner = font_dict.strip_prefix(b"<<").unwrap_or(font_dict);
+        let mut pos = 0;
+        while let Some(rel) = inner[pos..].iter().position(|b| *b == b'/') {
+            let start = pos + rel + 1;
+            let name_len = inner[start..].iter().take_while(|b| is_regular(**b)).count();
+            pos = start + name_len;
+            let Some(id) = parse_ref(&inner[pos..]) else { continue };
+            let (index, decryptor) = (self.index, self.decryptor);
+            let font = self.fonts.entry(id).or_insert_with(|| Rc::new(read_font(index, decryptor, id)));
+            fonts.insert(inner[start..pos].to_vec(), Rc::clone(font));
+        }
+        fonts
+    }
+
+    /// Reads the text of a content stream, following the form XObjects it draws.
+    fn read_content(&mut self, content: &[u8], resources: Option<&[u8]>, text: &mut PageText, depth: usize) {
+        let fonts = self.resource_fonts(resources);
+        let mut font = Rc::new(Font::default());
+        let mut operands: Vec<Operand> = Vec::new();
+        let mut i = 0;
+        while i < content.len() {
+            let (token, next) = read_token(content, i, 0);
+            i = next;
+            let op = match token {
+                Some(Token::Operand(operand)) => {
+                    operands.push(operand);
+                    continue;
+                }
+                Some(Token::Operator(op)) => op,
+                None => continue,
+            };
+            match op {
+                b"Tf" => {
+                    let name = operands.iter().rev().find_map(|operand| match operand {
+                        Operand::Name(name) => Some(name),
+                        _ => None,
+                    });
+                    font = name.and_then(|name| fonts.get(name)).cloned().unwrap_or_default();
+                }
+                b"Tj" | b"'" | b"\"" => {
+                    if op != b"Tj" {
+                        text.break_line();
+                    }
+                    if let Some(Operand::String(bytes)) = operands.last() {
+                        font.decode(bytes, &mut self.stats, text);
+                    }
+                }
+                b"TJ" => {
+                    let items = match operands.last() {
+                        Some(Operand::Array(items)) => items.as_slice(),
+                        _ => &[],
+                    };
+                    for item in items {
+                        match item {
+                            Operand::String(bytes) => font.decode(bytes, &mut self.stats, text),
+                            // negative numbers move the next glyph right
+                            Operand::Number(gap) if -gap >= TJ_WORD_GAP => text.break_word(),
+                            _ => {}
+                        }
+                    }
+                }
+                b"Td" | b"TD" => match operands.last() {
+                    Some(Operand::Number(ty)) if *ty != 0.0 => text.break_line(),
+                    _ => text.break_word(),
+                },
+                b"T*" | b"Tm" | b"ET" => text.break_line(),
+                b"BI" => i = skip_inline_image(content, i),
+                b"Do" if depth < MAX_FORM_DEPTH => {
+                    if let Some(Operand::Name(name)) = operands.last() {
+                        self.read_form(resources, name, text, depth);
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+
+    /// Reads the text of the form XObject drawn with `Do` under `name`.
+    fn read_form(&mut self, resources: Option<&[u8]>, name: &[u8], text: &mut PageText, depth: usize) {
+        let index = self.index;
+        let Some(xobjects) = resources.and_then(|resources| index.resolve_dict(resources, b"/XObject")) else {
+            return;
+        };
+        let mut key = b"/".to_vec();
+        key.extend_from_slice(name);
+        let Some(id) = dict_value(xobjects, &key).and_then(parse_ref) else { return };
+        // a form drawing itself is read once per page
+        if !self.visited.insert(id) {
+            return;
+        }
+        let Some(obj) = index.get(id) else { return };
+        let dict = dict_slice(obj);
+        if !has_name(dict, b"/Subtype", b"/Form") {
+            return;
+        }
+        if let Some(content) = self.readable_stream(id, obj) {
+            let form_resources = index.resolve_dict(dict, b"/Resources").or(resources);
+            self.read_content(&content, form_resources, text, depth + 1);
+        }
+    }
+}
+
+/// A token of a content stream.
+enum Token<'a> {
+    Operand(Operand),
+    Operator(&'a [u8]),
+}
+
+/// Reads the token starting at or after `i`.
+///
+/// # Returns
+///
+/// The token (`None` for whitespace, comments, and dictionary delimiters) and
+/// the position after it.
+fn read_token(content: &[u8], i: usize, depth: usize) -> (Option<Token<'_>>, usize) {
+    let len = content.len();
+    match content[i] {
+        b if b.is_ascii_whitespace() => (None, i + 1),
+        b'%' => {
+            let end = content[i..].iter().position(|b| matches!(b, b'\n' | b'\r')).map_or(len, |end| i + end);
+            (None, end)
+        }
+        b'(' => {
+            let end = literal_string_end(content, i).unwrap_or(len);
+            let bytes = literal_string_bytes(&content[i + 1..end]);
+            (Some(Token::Operand(Operand::String(bytes))), end)
+        }
+        b'<' | b'>' if content.get(i + 1) == Some(&content[i]) => (None, i + 2),
+        b'<' => {
+            let end = content[i..].iter().position(|b| *b == b'>').map_or(len, |end| i + end);
+            let bytes = hex_string_bytes(&content[i + 1..end]);
+            (Some(Token::Operand(Operand::String(bytes))), end + 1)
+        }
+        b'[' if depth < MAX_ARRAY_DEPTH => {
+            let mut items = Vec::new();
+            let mut pos = i + 1;
+            while pos < len && content[pos] != b']' {
+                let (token, next) = read_token(content, pos, depth + 1);
+                if let Some(Token::Operand(item)) = token {
+                    items.push(item);
+                }
+                pos = next;
+            }
+            (Some(Token::Operand(Operand::Array(items))), pos + 1)
+        }
+        b'/' => {
+            let start = i + 1;
+            let end = start + content[start..].iter().take_while(|b| is_regular(**b)).count();
+            (Some(Token::Operand(Operand::Name(content[start..end].to_vec()))), end)
+        }
+        b if is_regular(b) => {
+            let end = i + content[i..].iter().take_while(|b| is_regular(**b)).count();
+            let token = &content[i..end];
+            match parse_number(token) {
+                Some(number) => (Some(Token::Operand(Operand::Number(number))), end),
+                None => (Some(Token::Operator(token)), end),
+            }
+        }
+        _ => (None, i + 1),
+    }
+}
+
+/// Skips an inline image's data, returning the position after its `EI`.
+fn skip_inline_image(content: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    while let Some(rel) = content[pos..].windows(2).position(|pair| pair == b"EI") {
+        let at = pos + rel;
+        let before_ok = at > 0 && content[at - 1].is_ascii_whitespace();
+        let after_ok = content.get(at + 2).is_none_or(|b| b.is_ascii_whitespace());
+        if before_ok && after_ok {
+            return at + 2;
+        }
+        pos = at + 2;
+    }
+    content.len()
+}
+
+fn is_regular(b: u8) -> bool {
+    !b.is_ascii_whitespace() && !b"()<>[]{}/%".contains(&b)
+}
+
+fn parse_number(token: &[u8]) -> Option<f64> {
+    let first = *token.first()?;
+    if !(first.is_ascii_digit() || first == b'-' || first == b'+' || first == b'.') {
+        return None;
+    }
+    std::str::from_utf8(token).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    /// A one-page PDF showing `content` in font `/F1`, a Type0 font when `composite`.
+    fn page_pdf(content: &str, composite: bool, filter: &str) -> Vec<u8> {
+        let font = if composite {
+            "<< /Type /Font /Subtype /Type0 /BaseFont /Noto /Encoding /Identity-H >>"
+        } else {
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>"
+        };
+        build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>",
+            &raw_stream(filter, content),
+            font,
+        ])
+    }
+
+    #[test]
+    fn words_of_the_text_fixture_are_counted_exactly() {
+        let stats = extract_text_stats(&build_text_pdf(5, 250), None).unwrap();
+        assert_eq!((stats.pages, stats.words, stats.undecoded_glyphs, stats.skipped_streams), (5, 1250, 0, 0));
+        assert_eq!(stats.words_per_page(), 250.0);
+        assert_eq!(stats.confidence(), TEXT_METRICS_CONFIDENCE);
+        assert!(stats.sample.starts_with("estimate the pages"), "{}", stats.sample);
+    }
+
+    #[test]
+    fn show_text_operands_are_tokenized() {
+        let content = concat!(
+            "BT /F1 12 Tf 72 700 Td (Escaped \\(paren\\) text ) Tj ",
+            "[(Ke) -20 (rned) -300 (word)] TJ 0 -14 Td <48656c6c6f> Tj ",
+            "T* (next line) ' 2 0 (quoted) \" ET",
+        );
+        let stats = extract_text_stats(&page_pdf(content, false, ""), None).unwrap();
+        let lines: Vec<&str> = stats.sample.lines().collect();
+        assert_eq!(lines, ["Escaped (paren) text Kerned word", "Hello", "next line", "quoted"]);
+        assert_eq!((stats.words, stats.lines), (9, 4));
+        // whitespace isn't counted
+        assert_eq!(stats.chars, "Escaped(paren)textKernedwordHellonextlinequoted".len());
+    }
+
+    #[test]
+    fn undecoded_text_lowers_the_confidence() {
+        // two-byte glyph codes in a composite font without /ToUnicode
+        let stats = extract_text_stats(&page_pdf("BT /F1 12 Tf <0011002200330044004500460047> Tj ET", true, ""), None);
+        let stats = stats.unwrap();
+        assert_eq!((stats.undecoded_glyphs, stats.decoded_chars, stats.chars), (7, 0, 7));
+        assert_eq!(stats.words, 1);
+        assert_eq!(stats.confidence(), 0.0);
+
+        let skipped = extract_text_stats(&page_pdf("BT (hidden) Tj ET", false, "/Filter /LZWDecode"), None).unwrap();
+        assert_eq!((skipped.streams, skipped.skipped_streams, skipped.words), (0, 1, 0));
+        assert_eq!(skipped.confidence(), 0.0);
+    }
+}
@@ -0,0 +1,1198 @@
+//! # PDF Object Utilities
+//!
+//! Lightweight helpers for reading the object structure of a PDF without a
+//! full parser. They are used by the PDF analysis features that need to look
+//! at individual pages (e.g. color detection), where the plain `/Type /Page`
+//! scan in the estimators is not enough.
+//!
+//! ## Scope
+//!
+//! - Only uncompressed (top-level) objects are indexed; objects stored inside
+//!   object streams are not visible to these helpers
+//! - Stream data is decoded for `FlateDecode` only; other filters are returned as-is
+//! - Strings and streams of encrypted files are returned as stored; the
+//!   `pdf_crypt` module decrypts them
+//! - When an object number appears several times (incremental updates), the
+//!   occurrence latest in the file wins, both in the index and in the raw
+//!   `/Type` scans (`count_page_objects`, `max_pages_count`), which count each
+//!   object (number and generation) once
+//!
+//! ## Scan Budget
+//!
+//! A crafted file can make the raw scans revisit the same bytes over and over
+//! (an unclosed string opened at every other byte, objects without `endobj`).
+//! `with_scan_budget` caps the bytes the scans examine: the scanning helpers
+//! charge what they read with `charge_scan`, and once the budget is spent the
+//! page counting loops stop with what they found so far. Outside
+//! `with_scan_budget` nothing is counted against a budget. What they charge
+//! also counts toward the `deadline_ms` clock checks (see the `deadline`
+//! module), and the loops stop the same way once the deadline has passed.
+//!
+//! Reads of the input itself (the raw scans, object lookups, and the tail
+//! window), as opposed to parsing an object already resolved, are also
+//! recorded in the estimate's `bytes_examined` (see the `stats` module).
+
+use crate::deadline::charge_deadline;
+use crate::stats::note_bytes_examined;
+use flate2::read::ZlibDecoder;
+use memchr::memmem;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+/// Default scan budget, in bytes examined per byte of input.
+pub const DEFAULT_SCAN_BUDGET_FACTOR: usize = 8;
+
+thread_local! {
+    /// Bytes the scans may still examine; `None` outside `with_scan_budget`.
+    static SCAN_BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+    /// Whether a scan asked for more than the budget had left.
+    static SCAN_BUDGET_EXCEEDED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with a budget of `limit` bytes examined by the scanning helpers
+/// (see the module docs).
+///
+/// # Returns
+///
+/// The result of `f`, and whether the budget ran out while it ran.
+pub fn with_scan_budget<T>(limit: usize, f: impl FnOnce() -> T) -> (T, bool) {
+    let outer = SCAN_BUDGET.replace(Some(limit));
+    let outer_exceeded = SCAN_BUDGET_EXCEEDED.replace(false);
+    let result = f();
+    SCAN_BUDGET.set(outer);
+    let exceeded = SCAN_BUDGET_EXCEEDED.replace(outer_exceeded);
+    (result, exceeded)
+}
+
+/// Charges `bytes` examined to the current scan budget.
+///
+/// # Returns
+///
+/// `false` once the budget is spent or the deadline has passed; always `true`
+/// outside `with_scan_budget` and `with_deadline`.
+pub fn charge_scan(bytes: usize) -> bool {
+    if !charge_deadline(bytes) {
+        return false;
+    }
+    match SCAN_BUDGET.get() {
+        Some(remaining) if remaining >= bytes => {
+            SCAN_BUDGET.set(Some(remaining - bytes));
+            true
+        }
+        Some(_) => {
+            SCAN_BUDGET.set(Some(0));
+            SCAN_BUDGET_EXCEEDED.set(true);
+            false
+        }
+        None => true,
+    }
+}
+
+/// Charges `bytes` read from the input itself, rather than from an object
+/// already resolved, and records them in `bytes_examined` (see the `stats` module).
+fn charge_input(bytes: usize) -> bool {
+    note_bytes_examined(bytes);
+    charge_scan(bytes)
+}
+
+/// Whether the current scan budget has run out.
+pub fn scan_budget_exceeded() -> bool {
+    SCAN_BUDGET_EXCEEDED.get()
+}
+
+/// Whether the scanning loops should stop: the scan budget is spent or the deadline has passed.
+fn scan_stopped() -> bool {
+    scan_budget_exceeded() || !charge_deadline(0)
+}
+
+/// An indirect object reference (`N G R`).
+pub type ObjRef = (u32, u16);
+
+/// Index of the uncompressed objects in a PDF file.
+///
+/// Built once per file by scanning for `N G obj` headers, so that subsequent
+/// lookups don't need to rescan the whole document.
+pub struct ObjectIndex<'a> {
+    bytes: &'a [u8],
+    offsets: HashMap<ObjRef, usize>,
+}
+
+impl<'a> ObjectIndex<'a> {
+    /// Scans `bytes` for object headers and records where each object body starts.
+    ///
+    /// Copies of an object saved again by incremental updates are indexed at
+    /// their last occurrence in the file, the newest revision, so page tree
+    /// walks only see the current `/Kids` and pages.
+    pub fn build(bytes: &'a [u8]) -> Self {
+        let mut offsets = HashMap::new();
+        let mut pos = 0;
+        while let Some(rel) = find_bytes(&bytes[pos..], b" obj") {
+            let at = pos + rel;
+            if !charge_input(rel + 4) {
+                break;
+            }
+            if let Some(id) = parse_object_header(bytes, at) {
+                offsets.insert(id, at + 4);
+            }
+            pos = at + 4;
+        }
+        Self { bytes, offsets }
+    }
+
+    /// Rebuilds an index from the offsets of `into_offsets`, without rescanning.
+    ///
+    /// `bytes` must be the bytes the offsets were recorded in. Lets callers that
+    /// own the bytes (e.g. a wasm-bindgen class) keep the index between calls.
+    pub fn from_offsets(bytes: &'a [u8], offsets: HashMap<ObjRef, usize>) -> Self {
+        Self { bytes, offsets }
+    }
+
+    /// Gives up the borrow of the bytes, keeping the offsets for `from_offsets`.
+    pub fn into_offsets(self) -> HashMap<ObjRef, usize> {
+        self.offsets
+    }
+
+    /// Returns the body of an object (between `obj` and `endobj`).
+    pub fn get(&self, id: ObjRef) -> Option<&'a [u8]> {
+        let start = *self.offsets.get(&id)?;
+        let rest = &self.bytes[start..];
+        let end = find_bytes(rest, b"endobj").unwrap_or(rest.len());
+        if !charge_input(end) {
+            return None;
+        }
+        Some(&rest[..end])
+    }
+
+    /// Where the body of an object starts (just past its `obj` keyword).
+    pub fn offset(&self, id: ObjRef) -> Option<usize> {
+        self.offsets.get(&id).copied()
+    }
+
+    /// Number of indexed objects.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether no object was found.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Iterates over the indexed objects and their bodies, in file order.
+    pub fn objects(&self) -> impl Iterator<Item = (ObjRef, &'a [u8])> + '_ {
+        let mut ids: Vec<(usize, ObjRef)> = self.offsets.iter().map(|(&id, &offset)| (offset, id)).collect();
+        ids.sort_unstable();
+        ids.into_iter().filter_map(|(_, id)| Some((id, self.get(id)?)))
+    }
+
+    /// Returns the reference of the catalog, the trailer's `/Root`.
+    pub fn root_ref(&self) -> Option<ObjRef> {
+        find_trailer_ref(self.bytes, b"/Root")
+    }
+
+    /// Returns the catalog dictionary named by the trailer's `/Root`.
+    pub fn catalog(&self) -> Option<&'a [u8]> {
+        Some(dict_slice(self.get(self.root_ref()?)?))
+    }
+
+    /// Returns the document information dictionary named by the trailer's `/Info`.
+    pub fn info(&self) -> Option<&'a [u8]> {
+        Some(dict_slice(self.get(self.info_ref()?)?))
+    }
+
+    /// Returns the reference of the document information dictionary (the trailer's `/Info`).
+    pub fn info_ref(&self) -> Option<ObjRef> {
+        find_trailer_ref(self.bytes, b"/Info")
+    }
+
+    /// Returns the encryption dictionary named by the trailer's `/Encrypt`.
+    ///
+    /// Only an indirect `/Encrypt` is found; writers don't put the dictionary
+    /// in the trailer itself in practice.
+    pub fn encryption(&self) -> Option<&'a [u8]> {
+        Some(dict_slice(self.get(find_trailer_ref(self.bytes, b"/Encrypt")?)?))
+    }
+
+    /// Reads a string entry of a dictionary, following an indirect reference to the string.
+    pub fn resolve_string(&self, dict: &[u8], key: &[u8]) -> Option<String> {
+        let value = dict_value(dict, key)?;
+        match parse_ref(value) {
+            Some(id) => pdf_string(self.get(id)?),
+            None => pdf_string(value),
+        }
+    }
+
+    /// Resolves the catalog's page tree and returns page references in page order.
+    ///
+    /// Returns `None` when the trailer, catalog, or page tree root cannot be
+    /// found among the uncompressed objects.
+    pub fn page_refs(&self) -> Option<Vec<ObjRef>> {
+        let pages_root = dict_ref(self.catalog()?, b"/Pages")?;
+        let mut pages = Vec::new();
+        let mut visited = HashSet::new();
+        self.collect_pages(pages_root, &mut pages, &mut visited, 0);
+        if pages.is_empty() { None } else { Some(pages) }
+    }
+
+    fn collect_pages(
+        &self,
+        node: ObjRef,
+        out: &mut Vec<ObjRef>,
+        visited: &mut HashSet<ObjRef>,
+        depth: usize,
+    ) {
+        // page trees are shallow in practice; the cap bounds recursion on
+        // malformed files, and `visited` stops a node listed in its own
+        // subtree (or several times in one /Kids array) from being expanded
+        // again, which would otherwise blow up exponentially
+        if depth > 64 || scan_stopped() {
+            return;
+        }
+        let Some(obj) = self.get(node) else { return };
+        let dict = dict_slice(obj);
+        if has_name(dict, b"/Type", b"/Pages") {
+            if !visited.insert(node) {
+                return;
+            }
+            for kid in dict_ref_array(dict, b"/Kids") {
+                self.collect_pages(kid, out, visited, depth + 1);
+            }
+        } else {
+            out.push(node);
+        }
+    }
+
+    /// Resolves a dictionary entry that may be either inline (`<< ... >>`) or an
+    /// indirect reference, returning the dictionary bytes.
+    pub fn resolve_dict(&self, dict: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+        let value = dict_value(dict, key)?;
+        if value.starts_with(b"<<") {
+            Some(dict_slice(value))
+        } else {
+            let id = parse_ref(value)?;
+            Some(dict_slice(self.get(id)?))
+        }
+    }
+}
+
+/// Parses the `N G` in front of an ` obj` keyword located at `at`.
+fn parse_object_header(bytes: &[u8], at: usize) -> Option<ObjRef> {
+    let mut i = at;
+    let gen_end = i;
+    while i > 0 && bytes[i - 1].is_ascii_digit() {
+        i -= 1;
+    }
+    let generation = parse_ascii_number(&bytes[i..gen_end])?;
+    if i == 0 || bytes[i - 1] != b' ' {
+        return None;
+    }
+    i -= 1;
+    let num_end = i;
+    while i > 0 && bytes[i - 1].is_ascii_digit() {
+        i -= 1;
+    }
+    let number = parse_ascii_number(&bytes[i..num_end])?;
+    // the object number must start a token
+    if i > 0 && !bytes[i - 1].is_ascii_whitespace() {
+        return None;
+    }
+    Some((u32::try_from(number).ok()?, u16::try_from(generation).ok()?))
+}
+
+fn parse_ascii_number(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() || digits.len() > 10 {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// How far around a claimed `startxref` offset the cross-reference data is searched for.
+pub const XREF_SHIFT_WINDOW: usize = 4096;
+
+/// Cross-reference table entries that must agree with a shift before it is reported.
+const XREF_SHIFT_SAMPLES: usize = 3;
+
+/// Reads the offset after the last `startxref` keyword.
+///
+/// Offsets are only bounded by `usize`, so files of any size keep their
+/// cross-reference offset; callers compare it to the input length. Offsets
+/// that overflow `usize` are rejected.
+pub fn startxref_offset(bytes: &[u8]) -> Option<usize> {
+    let keyword = rfind_bytes(bytes, b"startxref");
+    charge_input(bytes.len() - keyword.unwrap_or(0));
+    let after = &bytes[keyword? + 9..];
+    let mut offset = None;
+    for digit in after.iter().skip_while(|b| b.is_ascii_whitespace()).take_while(|b| b.is_ascii_digit()) {
+        offset = Some(offset.unwrap_or(0usize).checked_mul(10)?.checked_add(usize::from(digit - b'0'))?);
+    }
+    offset
+}
+
+/// Checks that `offset` starts a classic `xref` table or an `N G obj` cross-reference stream.
+pub fn xref_at(bytes: &[u8], offset: usize) -> bool {
+    let Some(rest) = bytes.get(offset..) else { return false };
+    trim_start(rest).starts_with(b"xref") || object_header_at(bytes, offset).is_some()
+}
+
+/// Parses an `N G obj` header at `offset`, after any whitespace.
+fn object_header_at(bytes: &[u8], offset: usize) -> Option<ObjRef> {
+    let rest = trim_start(bytes.get(offset..)?);
+    let line = &rest[..rest.len().min(32)];
+    let mut tokens = line.split(|b| b.is_ascii_whitespace()).filter(|t| !t.is_empty());
+    let number = parse_ascii_number(tokens.next()?)?;
+    let generation = parse_ascii_number(tokens.next()?)?;
+    tokens.next()?.starts_with(b"obj").then_some((u32::try_from(number).ok()?, u16::try_from(generation).ok()?))
+}
+
+/// Detects cross-reference offsets that are all off by the same number of bytes.
+///
+/// Files that had bytes prepended or removed in transit (mail gateways,
+/// transfers that rewrite line endings before the header) keep offsets that
+/// no longer point at their objects. When `startxref` misses the
+/// cross-reference data, it is searched for within `XREF_SHIFT_WINDOW` bytes,
+/// and the shift found is confirmed against the first entries of a classic
+/// table (or, for a cross-reference stream, its `/Type /XRef`).
+///
+/// # Returns
+///
+/// The shift in bytes to add to every offset (negative when bytes were
+/// removed), or `None` when `startxref` is correct or no consistent shift
+/// explains it.
+///
+/// # Notes
+///
+/// `ObjectIndex` finds objects by scanning for their headers, so page counts
+/// don't depend on the offsets; the shift explains why other readers may
+/// reject or repair the file.
+pub fn xref_shift(bytes: &[u8]) -> Option<i64> {
+    let offset = startxref_offset(bytes)?;
+    if xref_at(bytes, offset) {
+        return None;
+    }
+    let start = offset.saturating_sub(XREF_SHIFT_WINDOW).min(bytes.len());
+    let end = offset.saturating_add(XREF_SHIFT_WINDOW).min(bytes.len());
+    let window = &bytes[start..end];
+    charge_input(window.len());
+    let mut candidates: Vec<usize> = memmem::find_iter(window, b"xref")
+        .map(|at| start + at)
+        .filter(|at| !bytes[..*at].ends_with(b"start"))
+        .collect();
+    candidates.extend(memmem::find_iter(window, b" obj").filter_map(|at| {
+        let at = start + at;
+        let id = parse_object_header(bytes, at)?;
+        let header = id.0.to_string().len() + id.1.to_string().len() + 1;
+        let body = dict_slice(&bytes[at + 4..]);
+        has_name(body, b"/Type", b"/XRef").then(|| at - header)
+    }));
+    candidates.sort_by_key(|at| at.abs_diff(offset));
+    candidates.into_iter().find_map(|at| {
+        let shift = at as i64 - offset as i64;
+        let confirmed = !bytes[at..].starts_with(b"xref")
+            || xref_table_samples(&bytes[at + 4..]).is_some_and(|samples| {
+                !samples.is_empty()
+                    && samples.iter().all(|(id, entry)| {
+                        usize::try_from(*entry as i64 + shift)
+                            .ok()
+                            .and_then(|pos| object_header_at(bytes, pos))
+                            .is_some_and(|(number, _)| number == *id)
+                    })
+            });
+        confirmed.then_some(shift)
+    })
+}
+
+/// Reads up to `XREF_SHIFT_SAMPLES` in-use entries of a classic `xref` table
+/// (the bytes after the keyword) as object numbers and offsets.
+fn xref_table_samples(table: &[u8]) -> Option<Vec<(u32, usize)>> {
+    let mut samples = Vec::new();
+    let mut next_id = 0u32;
+    let lines = table.split(|b| *b == b'\n' || *b == b'\r').filter(|line| !trim_start(line).is_empty());
+    for line in lines {
+        let tokens: Vec<&[u8]> = line.split(|b| b.is_ascii_whitespace()).filter(|t| !t.is_empty()).collect();
+        match tokens.as_slice() {
+            // a subsection header: first object number and entry count
+            [first, _] => next_id = u32::try_from(parse_ascii_number(first)?).ok()?,
+            [entry, _, kind] => {
+                let entry = parse_ascii_number(entry)? as usize;
+                if *kind == b"n" && entry > 0 {
+                    samples.push((next_id, entry));
+                }
+                next_id = next_id.checked_add(1)?;
+            }
+            // `trailer` ends the table
+            _ => break,
+        }
+        if samples.len() == XREF_SHIFT_SAMPLES {
+            break;
+        }
+    }
+    Some(samples)
+}
+
+/// Finds a reference (`/Root`, `/Info`, `/Encrypt`) in the last trailer (or cross-reference stream dictionary).
+fn find_trailer_ref(bytes: &[u8], key: &[u8]) -> Option<ObjRef> {
+    let mut pos = bytes.len();
+    while let Some(at) = rfind_bytes(&bytes[..pos], key) {
+        if let Some(id) = parse_ref(&bytes[at + key.len()..]) {
+            return Some(id);
+        }
+        pos = at;
+    }
+    None
+}
+
+/// Returns the dictionary at the start of an object body (`<< ... >>`, nesting-aware).
+///
+/// If the body doesn't start with a dictionary, the whole body is returned.
+pub fn dict_slice(obj: &[u8]) -> &[u8] {
+    let Some(start) = find_bytes(obj, b"<<") else { return obj };
+    let mut depth = 0usize;
+    let mut i = start;
+    while i + 1 < obj.len() {
+        match &obj[i..i + 2] {
+            b"<<" => {
+                depth += 1;
+                i += 2;
+            }
+            b">>" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    charge_scan(i - start);
+                    return &obj[start..i];
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    charge_scan(obj.len() - start);
+    &obj[start..]
+}
+
+/// Returns the bytes following `key` in a dictionary, with leading whitespace removed.
+///
+/// Only keys at the top nesting level of `dict` are matched.
+pub fn dict_value<'a>(dict: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < dict.len() {
+        if dict[i..].starts_with(b"<<") {
+            depth += 1;
+            i += 2;
+            continue;
+        }
+        if dict[i..].starts_with(b">>") {
+            depth = depth.saturating_sub(1);
+            i += 2;
+            continue;
+        }
+        if depth == 1 && dict[i..].starts_with(key) {
+            let after = &dict[i + key.len()..];
+            // make sure we matched the whole name, not a prefix of a longer one
+            if after.first().is_none_or(|b| !b.is_ascii_alphanumeric()) {
+                charge_scan(i);
+                return Some(trim_start(after));
+            }
+        }
+        i += 1;
+    }
+    charge_scan(dict.len());
+    None
+}
+
+/// Reads an indirect reference value (`N G R`) for `key`.
+pub fn dict_ref(dict: &[u8], key: &[u8]) -> Option<ObjRef> {
+    parse_ref(dict_value(dict, key)?)
+}
+
+/// Reads an array of indirect references (`[N G R N G R ...]`) for `key`.
+pub fn dict_ref_array(dict: &[u8], key: &[u8]) -> Vec<ObjRef> {
+    let Some(value) = dict_value(dict, key) else { return Vec::new() };
+    if !value.starts_with(b"[") {
+        return parse_ref(value).into_iter().collect();
+    }
+    let end = value.iter().position(|b| *b == b']').unwrap_or(value.len());
+    let tokens: Vec<&[u8]> = value[1..end]
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tokens
+        .windows(3)
+        .filter(|w| w[2] == b"R")
+        .filter_map(|w| {
+            let num = parse_ascii_number(w[0])?;
+            let generation = parse_ascii_number(w[1])?;
+            Some((u32::try_from(num).ok()?, u16::try_from(generation).ok()?))
+        })
+        .collect()
+}
+
+/// Checks whether `key` holds the name `name` (e.g. `/Type /Pages`).
+pub fn has_name(dict: &[u8], key: &[u8], name: &[u8]) -> bool {
+    dict_value(dict, key).is_some_and(|v| {
+        v.starts_with(name) && v.get(name.len()).is_none_or(|b| !b.is_ascii_alphanumeric())
+    })
+}
+
+/// Parses `N G R` at the start of `value`.
+pub fn parse_ref(value: &[u8]) -> Option<ObjRef> {
+    let mut tokens = trim_start(value)
+        .split(|b| b.is_ascii_whitespace() || *b == b'/' || *b == b'>' || *b == b']')
+        .filter(|t| !t.is_empty());
+    let num = parse_ascii_number(tokens.next()?)?;
+    let generation = parse_ascii_number(tokens.next()?)?;
+    if tokens.next()? != b"R" {
+        return None;
+    }
+    Some((u32::try_from(num).ok()?, u16::try_from(generation).ok()?))
+}
+
+/// Extracts and decodes the stream data of an object.
+///
+/// `FlateDecode` streams are inflated (tolerating truncated data); streams
+/// with other filters are returned undecoded.
+pub fn stream_data(obj: &[u8]) -> Option<Vec<u8>> {
+    let (dict, raw) = stream_parts(obj)?;
+    Some(decode_stream(dict, raw))
+}
+
+/// Splits a stream object into its dictionary and its raw (undecoded) data.
+///
+/// The data ends at a direct `/Length`, which leaves out the line break
+/// before `endstream`, or else at `endstream`.
+pub fn stream_parts(obj: &[u8]) -> Option<(&[u8], &[u8])> {
+    let dict = dict_slice(obj);
+    // `dict` borrows from `obj`, so its end offset follows from the pointer difference
+    let dict_end = dict.as_ptr() as usize - obj.as_ptr() as usize + dict.len();
+    let rest = &obj[dict_end..];
+    let kw = find_bytes(rest, b"stream")?;
+    let mut start = kw + 6;
+    if rest[start..].starts_with(b"\r\n") {
+        start += 2;
+    } else if rest[start..].starts_with(b"\n") {
+        start += 1;
+    }
+    let end_keyword = find_bytes(&rest[start..], b"endstream").map_or(rest.len(), |e| start + e);
+    let length = dict_value(dict, b"/Length").filter(|value| parse_ref(value).is_none()).and_then(|value| {
+        let digits = value.iter().position(|b| !b.is_ascii_digit()).unwrap_or(value.len());
+        parse_ascii_number(&value[..digits])
+    });
+    let end = length.map(|length| start.saturating_add(length as usize)).filter(|end| *end <= end_keyword);
+    Some((dict, &rest[start..end.unwrap_or(end_keyword)]))
+}
+
+/// Decodes the raw data of a stream with the filters of its dictionary (see `stream_data`).
+pub fn decode_stream(dict: &[u8], raw: &[u8]) -> Vec<u8> {
+    if find_bytes(dict, b"/FlateDecode").is_some() {
+        let mut decoded = Vec::new();
+        // truncated or slightly corrupt streams still yield their decodable prefix
+        let _ = ZlibDecoder::new(raw).read_to_end(&mut decoded);
+        decoded
+    } else {
+        raw.to_vec()
+    }
+}
+
+/// Decodes a PDF string value: a literal `(...)` with its escapes, or a hex `<...>`.
+///
+/// Strings starting with a UTF-16BE byte order mark are decoded as UTF-16;
+/// others are taken as UTF-8, falling back to Latin-1 (close enough to
+/// PDFDocEncoding for file names).
+pub fn pdf_string(value: &[u8]) -> Option<String> {
+    Some(text_string(pdf_string_bytes(value)?))
+}
+
+/// Reads the bytes of a PDF string value: a literal `(...)` with its escapes, or a hex `<...>`.
+pub fn pdf_string_bytes(value: &[u8]) -> Option<Vec<u8>> {
+    let value = trim_start(value);
+    if value.starts_with(b"(") {
+        Some(literal_string_bytes(&value[1..]))
+    } else if value.starts_with(b"<") && !value.starts_with(b"<<") {
+        let end = value.iter().position(|b| *b == b'>')?;
+        Some(hex_string_bytes(&value[1..end]))
+    } else {
+        None
+    }
+}
+
+/// Decodes the bytes of a text string, as `pdf_string` does.
+pub fn text_string(raw: Vec<u8>) -> String {
+    if let Some(utf16) = raw.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        return String::from_utf16_lossy(&units);
+    }
+    match String::from_utf8(raw) {
+        Ok(s) => s,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Reads the bytes of a hex string body (between `<` and `>`).
+///
+/// Whitespace and other non-hex characters are ignored, and a trailing odd
+/// digit is followed by an implied 0.
+pub fn hex_string_bytes(body: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = body.iter().copied().filter(u8::is_ascii_hexdigit).collect();
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let hex = |digit: u8| (digit as char).to_digit(16).unwrap_or(0) as u8;
+            hex(pair[0]) << 4 | pair.get(1).map_or(0, |&digit| hex(digit))
+        })
+        .collect()
+}
+
+/// Reads the bytes of a literal string body (after the opening parenthesis),
+/// decoding its escapes, up to the closing parenthesis.
+pub fn literal_string_bytes(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            b'\\' if i + 1 < body.len() => {
+                i += 1;
+                match body[i] {
+                    b'n' => out.push(b'\n'),
+                    b'r' => out.push(b'\r'),
+                    b't' => out.push(b'\t'),
+                    b'b' => out.push(0x08),
+                    b'f' => out.push(0x0C),
+                    b'0'..=b'7' => {
+                        let digits = body[i..].iter().take(3).take_while(|b| (b'0'..=b'7').contains(b)).count();
+                        let octal = std::str::from_utf8(&body[i..i + digits]).unwrap_or("0");
+                        out.push(u16::from_str_radix(octal, 8).unwrap_or(0) as u8);
+                        i += digits - 1;
+                    }
+                    // an escaped line break continues the string
+                    b'\r' | b'\n' => {}
+                    other => out.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                out.push(b'(');
+            }
+            b')' if depth == 0 => break,
+            b')' => {
+                depth -= 1;
+                out.push(b')');
+            }
+            other => out.push(other),
+        }
+        i += 1;
+    }
+    out
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let skip = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    &bytes[skip..]
+}
+
+/// Returns the offset of the first occurrence of `needle` in `haystack`.
+pub fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    memmem::find(haystack, needle)
+}
+
+/// Returns the offset of the last occurrence of `needle` in `haystack`.
+pub fn rfind_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    memmem::rfind(haystack, needle)
+}
+
+/// How far back from a key `enclosing_dict` looks for the dictionary's opening `<<`.
+const MAX_ENCLOSING_DICT_BYTES: usize = 64 * 1024;
+
+/// Returns the innermost dictionary (`<< ... >>`) containing the byte at `at`.
+///
+/// The opening `<<` is found by bracket matching backwards, so keys before
+/// `at` are included whatever order the writer emitted them in. The search
+/// gives up after `MAX_ENCLOSING_DICT_BYTES`.
+pub fn enclosing_dict(bytes: &[u8], at: usize) -> Option<&[u8]> {
+    let floor = at.saturating_sub(MAX_ENCLOSING_DICT_BYTES);
+    let mut depth = 0usize;
+    let mut i = at.min(bytes.len());
+    while i >= floor + 2 {
+        match &bytes[i - 2..i] {
+            b">>" => {
+                depth += 1;
+                i -= 2;
+            }
+            b"<<" if depth == 0 => {
+                if !charge_input(at - i) {
+                    return None;
+                }
+                return Some(dict_slice(&bytes[i - 2..]));
+            }
+            b"<<" => {
+                depth -= 1;
+                i -= 2;
+            }
+            _ => i -= 1,
+        }
+    }
+    charge_input(at - i);
+    None
+}
+
+/// Reads the largest `/Count` of the `/Type /Pages` dictionaries in the raw bytes.
+///
+/// The page tree root holds the document's page count, and no other `/Pages`
+/// node can count more. Each node's `/Count` is read from its enclosing
+/// dictionary, so it is found both before and after the `/Type` key.
+///
+/// A node saved again by an incremental update is only read at its last
+/// occurrence, so the root of a document that lost pages doesn't report its
+/// count from before the update.
+pub fn max_pages_count(bytes: &[u8]) -> usize {
+    let mut newest: HashMap<ObjRef, usize> = HashMap::new();
+    let mut unowned = Vec::new();
+    let nodes = memmem::find_iter(bytes, b"/Type").filter(|&at| {
+        let rest = trim_start(&bytes[at + 5..]);
+        rest.starts_with(b"/Pages") && rest.get(6).is_none_or(|b| !b.is_ascii_alphanumeric())
+    });
+    for at in nodes {
+        if scan_stopped() {
+            break;
+        }
+        let Some(count) = enclosing_dict(bytes, at).and_then(|dict| dict_value(dict, b"/Count")).and_then(|value| {
+            let end = value.iter().position(|b| !b.is_ascii_digit()).unwrap_or(value.len());
+            usize::try_from(parse_ascii_number(&value[..end])?).ok()
+        }) else {
+            continue;
+        };
+        // later occurrences replace earlier ones
+        match enclosing_object(bytes, at) {
+            Some(id) => {
+                newest.insert(id, count);
+            }
+            None => unowned.push(count),
+        }
+    }
+    newest.into_values().chain(unowned).max().unwrap_or(0)
+}
+
+/// Bytes before an entry searched for the header of the object holding it.
+const OBJECT_HEADER_WINDOW: usize = 16 * 1024;
+
+/// Finds the object holding the entry at `at`, from the nearest `N G obj`
+/// header before it with no `endobj` in between.
+///
+/// # Returns
+///
+/// The object's reference, or `None` when no header is found within
+/// `OBJECT_HEADER_WINDOW` bytes (e.g. in a damaged file).
+pub fn enclosing_object(bytes: &[u8], at: usize) -> Option<ObjRef> {
+    let window_start = at.saturating_sub(OBJECT_HEADER_WINDOW);
+    let Some(rel) = rfind_bytes(&bytes[window_start..at], b" obj") else {
+        charge_input(at - window_start);
+        return None;
+    };
+    let header = window_start + rel;
+    if !charge_input(2 * (at - header)) {
+        return None;
+    }
+    if find_bytes(&bytes[header..at], b"endobj").is_some() {
+        return None;
+    }
+    parse_object_header(bytes, header)
+}
+
+/// Checks whether a PDF carries a digital signature.
+///
+/// A signature is assumed when a signature dictionary is present (`/Type /Sig`,
+/// or a `/ByteRange`, since `/Type` is optional there) or when the catalog has a
+/// `/Perms` entry (certification and usage-rights signatures). Only presence
+/// is checked; signatures are not validated.
+pub fn has_signature(bytes: &[u8]) -> bool {
+    let sig_type = memmem::find_iter(bytes, b"/Type").any(|at| {
+        let rest = trim_start(&bytes[at + 5..]);
+        rest.starts_with(b"/Sig") && rest.get(4).is_none_or(|b| !b.is_ascii_alphanumeric())
+    });
+    sig_type
+        || find_bytes(bytes, b"/ByteRange").is_some()
+        || ObjectIndex::build(bytes).catalog().is_some_and(|catalog| dict_value(catalog, b"/Perms").is_some())
+}
+
+/// Counts `/Type /Page` (or `/Type/Page`) entries, excluding `/Type /Pages`.
+///
+/// This is the page count used when the page tree isn't consulted. It makes a
+/// single pass over the raw bytes, so binary streams don't need to be decoded
+/// or copied first.
+///
+/// Entries that aren't page dictionaries are skipped:
+///
+/// - inside string literals, such as embedded JavaScript that mentions `/Type /Page`
+/// - inside streams (between `stream` and `endstream`), such as uncompressed
+///   object streams holding stale revisions
+/// - inside comments
+///
+/// A string literal left open up to the end of the file is scanned as if it
+/// weren't one, so a stray `(` in a damaged file doesn't hide later pages.
+///
+/// Incremental updates append a new copy of every page they change, so a page
+/// object (number and generation, see `enclosing_object`) is counted once
+/// however many copies the file holds. Entries outside any object are each counted.
+pub fn count_page_objects(bytes: &[u8]) -> usize {
+    let stream_finder = memmem::Finder::new(b"stream");
+    let end_finder = memmem::Finder::new(b"endstream");
+    let mut pages: HashSet<ObjRef> = HashSet::new();
+    let mut unowned = 0;
+    let mut pos = 0;
+    // the next `stream` keyword at or after `pos`, found lazily
+    let mut next_stream = None;
+    while pos < bytes.len() && !scan_stopped() {
+        let stream_at = match next_stream {
+            Some(at) if at >= pos => at,
+            _ => {
+                let at = stream_finder.find(&bytes[pos..]).map_or(bytes.len(), |rel| pos + rel);
+                next_stream = Some(at);
+                at
+            }
+        };
+        let special = memchr::memchr3(b'/', b'(', b'%', &bytes[pos..stream_at]).map(|rel| pos + rel);
+        charge_input(special.unwrap_or(stream_at) - pos);
+        let Some(at) = special else {
+            if stream_at >= bytes.len() {
+                break;
+            }
+            pos = match stream_data_start(bytes, stream_at) {
+                Some(start) => end_finder.find(&bytes[start..]).map_or(bytes.len(), |rel| start + rel + 9),
+                None => stream_at + 6,
+            };
+            charge_input(pos - stream_at);
+            continue;
+        };
+        pos = match bytes[at] {
+            b'/' => {
+                if is_page_type_entry(bytes, at) {
+                    match enclosing_object(bytes, at) {
+                        Some(id) => {
+                            pages.insert(id);
+                        }
+                        None => unowned += 1,
+                    }
+                }
+                at + 1
+            }
+            b'(' => {
+                let end = literal_string_end(bytes, at);
+                note_bytes_examined(end.unwrap_or(bytes.len()) - at);
+                end.unwrap_or(at + 1)
+            }
+            _ => memchr::memchr2(b'\n', b'\r', &bytes[at..]).map_or(bytes.len(), |rel| at + rel),
+        };
+    }
+    pages.len() + unowned
+}
+
+/// Whether `/Type /Page` (or `/Type/Page`, but not `/Pages`) starts at `at`.
+fn is_page_type_entry(bytes: &[u8], at: usize) -> bool {
+    let Some(rest) = bytes[at..].strip_prefix(b"/Type") else { return false };
+    let rest = rest.strip_prefix(b" ").unwrap_or(rest);
+    rest.starts_with(b"/Page") && rest.get(5) != Some(&b's')
+}
+
+/// Where the data of a stream starts, when `at` is a `stream` keyword (not
+/// part of `endstream` or of a name such as `/Upstream`).
+fn stream_data_start(bytes: &[u8], at: usize) -> Option<usize> {
+    let delimited = at == 0 || bytes[at - 1].is_ascii_whitespace() || bytes[at - 1] == b'>';
+    if !delimited {
+        return None;
+    }
+    match &bytes[at + 6..] {
+        [b'\r', b'\n', ..] => Some(at + 8),
+        [b'\n' | b'\r', ..] => Some(at + 7),
+        _ => None,
+    }
+}
+
+/// Finds the end of the string literal opening at `start`, handling nested
+/// parentheses and backslash escapes.
+///
+/// # Returns
+///
+/// The position just past the closing `)`, or `None` when the string isn't
+/// closed before the end of the input (or the scan budget runs out first).
+pub fn literal_string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return charge_scan(i + 1 - start).then_some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    charge_scan(i - start);
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use proptest::prelude::*;
+
+    /// The `/Type /Page` scan as it was before `count_page_objects`: a search
+    /// over a lossy UTF-8 copy of the file.
+    #[allow(clippy::manual_strip)]
+    fn lossy_type_scan(bytes: &[u8]) -> usize {
+        let pdf_str = String::from_utf8_lossy(bytes);
+        let mut page_count = 0;
+        let mut search_pos = 0;
+        while let Some(pos) = pdf_str[search_pos..].find("/Type") {
+            let abs_pos = search_pos + pos;
+            let remaining = &pdf_str[abs_pos..];
+            if remaining.starts_with("/Type /Page") || remaining.starts_with("/Type/Page") {
+                let after_page = if remaining.starts_with("/Type /Page") { &remaining[11..] } else { &remaining[10..] };
+                if !after_page.starts_with('s') {
+                    page_count += 1;
+                }
+            }
+            search_pos = abs_pos + 5;
+        }
+        page_count
+    }
+
+    /// Fragments the token soups in [`page_object_count_matches_the_lossy_scan`] are made of.
+    const SOUP: &[&[u8]] = &[
+        b"/Type", b"/Type ", b"/Type /Page", b"/Type/Page", b"/Type /Pages", b"/Pages", b"/Page", b"s",
+        b" ", b"\n", b"<<", b">>", b"\xff", b"\xe2\x82", b"\xc3\xa9", b"\x00",
+    ];
+
+    // the decoy and incrementally saved fixtures are left out: skipping
+    // strings, streams, and repeated objects changed their counts on purpose
+    #[test]
+    fn page_object_count_matches_the_lossy_scan_on_fixtures() {
+        let fixtures = [
+            build_pdf(1, XrefStyle::Classic, false, false),
+            build_pdf(12, XrefStyle::Classic, true, false),
+            build_pdf(7, XrefStyle::Stream, false, false),
+            build_pdf(5, XrefStyle::Classic, false, true),
+            build_blank_pages_pdf(3),
+            build_text_pdf(2, 40),
+            build_raw_pdf(&[
+                "<< /Type/Catalog /Pages 2 0 R >>",
+                "<< /Type/Pages /Kids [3 0 R 4 0 R] /Count 2 >>",
+                "<< /Type/Page /Parent 2 0 R >>",
+                "<< /Type /Page/Parent 2 0 R >>",
+            ]),
+        ];
+        for pdf in &fixtures {
+            assert!(lossy_type_scan(pdf) > 0);
+            assert_eq!(count_page_objects(pdf), lossy_type_scan(pdf));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn page_object_count_matches_the_lossy_scan(soup in prop::collection::vec(0..SOUP.len(), 0..64)) {
+            let bytes: Vec<u8> = soup.iter().flat_map(|&i| SOUP[i].iter().copied()).collect();
+            prop_assert_eq!(count_page_objects(&bytes), lossy_type_scan(&bytes));
+        }
+    }
+
+    #[test]
+    fn page_tokens_outside_dictionaries_are_skipped() {
+        let page = "<< /Type /Page /Parent 2 0 R >>";
+        let tree = ["<< /Type /Catalog /Pages 2 0 R >>", "<< /Type /Pages /Kids [3 0 R] /Count 1 >>", page];
+        let pdf = |decoy: &str| build_raw_pdf(&[&tree[..], &[decoy]].concat());
+        let decoys = [
+            // nested parentheses and escapes keep the string open
+            "<< /JS (if \\(a\\) { x = \"(/Type /Page)\" } \\\\) >>",
+            "<< /T (outer (inner /Type /Page) still /Type/Page) >>",
+            "<< /T (a \\) /Type /Page) >>",
+            &raw_stream("/Type /ObjStm /N 1 /First 5", "9 0 << /Type /Page >>"),
+            "<< /Author (x) >>\n% /Type /Page in a comment",
+        ];
+        for decoy in decoys {
+            let bytes = pdf(decoy);
+            assert_eq!(count_page_objects(&bytes), 1, "{}", decoy);
+        }
+        // a string closed before the token, or never closed, hides nothing
+        assert_eq!(count_page_objects(&pdf("<< /T (closed) /Type /Page >>")), 2);
+        let unclosed = b"1 0 obj << /T (open /Type /Page >> endobj\n2 0 obj << /Type /Page >> endobj";
+        assert_eq!(count_page_objects(unclosed), 2);
+        assert_eq!(count_page_objects(&build_decoy_pages_pdf(4)), 4);
+    }
+
+    #[test]
+    fn enclosing_dict_includes_keys_before_the_match() {
+        let bytes = b"1 0 obj\n<< /Count 12 /Resources << /Font << /F1 5 0 R >> >> /Type /Pages /Kids [] >>\nendobj";
+        let at = find_bytes(bytes, b"/Type").unwrap();
+        let dict = enclosing_dict(bytes, at).unwrap();
+        assert!(dict.starts_with(b"<< /Count 12"), "{}", String::from_utf8_lossy(dict));
+        assert!(dict.ends_with(b"/Kids [] >>"), "{}", String::from_utf8_lossy(dict));
+        let font = find_bytes(bytes, b"/F1").unwrap();
+        assert_eq!(enclosing_dict(bytes, font), Some(&b"<< /F1 5 0 R >>"[..]));
+        assert_eq!(enclosing_dict(b"/Type /Pages >>", 0), None);
+    }
+
+    #[test]
+    fn pages_count_is_read_in_either_key_order() {
+        let page = "<< /Type /Page /Parent 2 0 R >>";
+        for root in [
+            "<< /Type /Pages /Kids [3 0 R 4 0 R 5 0 R] /Count 3 >>",
+            "<< /Count 3 /Kids [3 0 R 4 0 R 5 0 R] /Type /Pages >>",
+            "<< /Count 3 /Resources << /ProcSet [/PDF] >> /Type /Pages /Kids [3 0 R 4 0 R 5 0 R] >>",
+        ] {
+            let catalog = "<< /Type /Catalog /Pages 2 0 R /Outlines 6 0 R >>";
+            let pdf = build_raw_pdf(&[catalog, root, page, page, page, "<< /Type /Outlines /Count 40 >>"]);
+            assert_eq!(max_pages_count(&pdf), 3, "{}", root);
+        }
+        assert_eq!(max_pages_count(&build_pdf(6, XrefStyle::Stream, true, false)), 6);
+    }
+
+    #[test]
+    fn constant_xref_shifts_are_detected() {
+        for style in [XrefStyle::Classic, XrefStyle::Stream] {
+            assert_eq!(xref_shift(&build_pdf(5, style, false, false)), None, "{:?}", style);
+            for shift in [20, 37, 500, XREF_SHIFT_WINDOW - 100] {
+                let pdf = build_shifted_pdf(5, style, shift);
+                assert_eq!(pdf.len(), build_pdf(5, style, false, false).len() + shift);
+                assert_eq!(xref_shift(&pdf), Some(shift as i64), "{:?} {}", style, shift);
+                assert!(!xref_at(&pdf, startxref_offset(&pdf).unwrap()));
+            }
+            // beyond the window, the cross-reference data isn't found
+            assert_eq!(xref_shift(&build_shifted_pdf(5, style, XREF_SHIFT_WINDOW + 500)), None, "{:?}", style);
+        }
+
+        // bytes removed ahead of the objects shift the offsets the other way
+        let pdf = build_pdf(5, XrefStyle::Classic, false, false);
+        let second_line = pdf.iter().position(|b| *b == b'\n').unwrap() + 1;
+        let binary_comment = pdf[second_line..].iter().position(|b| *b == b'\n').unwrap() + 1;
+        let mut shortened = pdf.clone();
+        shortened.drain(second_line..second_line + binary_comment);
+        assert_eq!(xref_shift(&shortened), Some(-(binary_comment as i64)));
+    }
+
+    #[test]
+    fn inconsistent_offsets_are_not_a_shift() {
+        // startxref is off, but the table's entries don't follow it
+        let mut pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        let at = rfind_bytes(&pdf, b"startxref").unwrap() + b"startxref\n".len();
+        let end = at + pdf[at..].iter().position(|b| !b.is_ascii_digit()).unwrap();
+        let offset = startxref_offset(&pdf).unwrap();
+        pdf.splice(at..end, (offset - 10).to_string().into_bytes());
+        let table = find_bytes(&pdf, b"\nxref").unwrap() + 1;
+        let first_entry = table + find_bytes(&pdf[table..], b" 00000 n").unwrap() - 10;
+        pdf.splice(first_entry..first_entry + 10, *b"0000000003");
+        assert_eq!(xref_shift(&pdf), None);
+        // an offset that overflows isn't read
+        let mut huge = build_pdf(1, XrefStyle::Classic, false, false);
+        huge.extend_from_slice(b"startxref\n99999999999999999999999\n%%EOF\n");
+        assert_eq!(startxref_offset(&huge), None);
+    }
+
+    #[test]
+    fn offsets_in_a_150_mb_pdf_are_read() {
+        // the pages and cross-reference table come after 150 MB of stream data
+        let padding = raw_stream("", &" ".repeat(150 << 20));
+        let page = "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>";
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [4 0 R 5 0 R] /Count 2 >>",
+            &padding,
+            page,
+            page,
+        ]);
+        let offset = startxref_offset(&pdf).unwrap();
+        assert!(offset > 150 << 20, "{}", offset);
+        assert!(xref_at(&pdf, offset));
+        assert_eq!(xref_shift(&pdf), None);
+        assert_eq!(ObjectIndex::build(&pdf).page_refs(), Some(vec![(4, 0), (5, 0)]));
+
+        // page sizes come from the page tree, which the scan budget covered
+        let result = estimate(&pdf, Some("large.pdf"), r#"{"include_point_sizes":true}"#).unwrap();
+        assert_eq!(result.page_count, 2);
+        let sizes = result.page_sizes_pt.unwrap();
+        assert!(sizes.iter().all(|size| (size.width_pt, size.height_pt) == (612.0, 792.0)), "{:?}", sizes);
+    }
+
+    #[test]
+    fn objects_are_found_around_their_entries() {
+        let pdf = b"%PDF-1.7\n3 0 obj\n<< /Type /Page >>\nendobj\n12 2 obj << /Type /Pages >> endobj\n/Type /Page";
+        let at = |needle: &[u8], nth: usize| memmem::find_iter(pdf, needle).nth(nth).unwrap();
+        assert_eq!(enclosing_object(pdf, at(b"/Type", 0)), Some((3, 0)));
+        assert_eq!(enclosing_object(pdf, at(b"/Type", 1)), Some((12, 2)));
+        // after an `endobj`, an entry belongs to no object
+        assert_eq!(enclosing_object(pdf, at(b"/Type", 2)), None);
+    }
+
+    #[test]
+    fn scans_are_charged_to_the_innermost_budget() {
+        assert!(charge_scan(usize::MAX));
+        assert!(!scan_budget_exceeded());
+
+        let ((), exceeded) = with_scan_budget(10, || {
+            assert!(charge_scan(6) && charge_scan(4));
+            assert!(!scan_budget_exceeded());
+            let (inner, inner_exceeded) = with_scan_budget(2, || charge_scan(3));
+            assert!(!inner && inner_exceeded);
+            // the inner budget running out leaves the outer one as it was
+            assert!(!scan_budget_exceeded());
+            assert!(!charge_scan(1));
+            assert!(scan_stopped());
+        });
+        assert!(exceeded);
+        assert!(!scan_budget_exceeded() && charge_scan(1));
+    }
+
+    #[test]
+    fn rescanned_files_run_out_of_budget_with_what_they_found() {
+        let pdf = build_rescanning_pdf(2000);
+        let ((), exceeded) = with_scan_budget(8 * pdf.len(), || {
+            let index = ObjectIndex::build(&pdf);
+            assert!(!index.is_empty());
+            for object in 2..2002 {
+                let _ = index.get((object, 0));
+            }
+        });
+        assert!(exceeded);
+
+        // a well-formed file is read well within the default budget
+        let good = build_pdf(50, XrefStyle::Stream, true, false);
+        let (refs, exceeded) = with_scan_budget(DEFAULT_SCAN_BUDGET_FACTOR * good.len(), || {
+            ObjectIndex::build(&good).page_refs().map(|refs| refs.len())
+        });
+        assert_eq!((refs, exceeded), (Some(50), false));
+    }
+
+    #[test]
+    fn saved_copies_of_an_object_count_once() {
+        let saved = build_incrementally_saved_pdf(4, 3);
+        assert_eq!(lossy_type_scan(&saved), 7);
+        assert_eq!(count_page_objects(&saved), 4);
+        assert_eq!(max_pages_count(&saved), 4);
+        // walks resolve each kid to its newest copy, which is Letter sized
+        let index = ObjectIndex::build(&saved);
+        assert_eq!(index.page_refs().map(|pages| pages.len()), Some(4));
+        let first = index.get((3, 0)).unwrap();
+        assert!(memmem::find(first, b"/Rev 3").is_some());
+
+        // an update that removes a page lowers the count of the root
+        let mut shrunk = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+        ]);
+        shrunk.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n%%EOF\n");
+        assert_eq!(max_pages_count(&shrunk), 1);
+    }
+}
@@ -0,0 +1,175 @@
+//! # PostScript Document Structuring Conventions
+//!
+//! Reads the DSC comments of PostScript (`.ps`) and Encapsulated PostScript
+//! (`.eps`) files. Nothing is interpreted; the page count and size come from
+//! the comments conforming writers emit:
+//!
+//! - `%%Pages: N` in the header, or in the trailer when the header says
+//!   `%%Pages: (atend)`
+//! - one `%%Page:` comment per page, used when `%%Pages:` is missing
+//! - `%%DocumentMedia:` and `%%BoundingBox:` for dimensions, in points
+//!
+//! Comments inside embedded documents (`%%BeginDocument` … `%%EndDocument`)
+//! belong to the embedded file and are skipped. DOS EPS files, which wrap the
+//! PostScript in a binary header with optional TIFF/WMF previews, are unwrapped
+//! first.
+
+/// Magic bytes of a DOS EPS binary header (`C5 D0 D3 C6`).
+pub const DOS_EPS_MAGIC: [u8; 4] = [0xC5, 0xD0, 0xD3, 0xC6];
+
+/// DSC information read from a PostScript file.
+#[derive(Debug, Default, PartialEq)]
+pub struct DscSummary {
+    /// Whether the header declares an EPS file (`EPSF-` version) or a DOS EPS header was present.
+    pub is_eps: bool,
+    /// The `%%Pages:` value (the trailer value when the header defers with `(atend)`).
+    pub pages: Option<usize>,
+    /// Number of `%%Page:` comments.
+    pub page_comments: usize,
+    /// Number of `showpage` operators, for files without DSC page comments.
+    pub showpages: usize,
+    /// Width and height of the first `%%DocumentMedia:` entry, in points.
+    pub media: Option<(f64, f64)>,
+    /// Width and height of the `%%BoundingBox:`, in points.
+    pub bounding_box: Option<(f64, f64)>,
+}
+
+/// Returns the PostScript part of a file, unwrapping a DOS EPS binary header.
+///
+/// # Returns
+///
+/// The bytes from the `%!` header on, or `None` if the DOS EPS header points
+/// outside the file or the data doesn't start with `%!`.
+pub fn postscript_section(bytes: &[u8]) -> Option<&[u8]> {
+    let section = if bytes.starts_with(&DOS_EPS_MAGIC) {
+        let offset = u32::from_le_bytes(bytes.get(4..8)?.try_into().ok()?) as usize;
+        let length = u32::from_le_bytes(bytes.get(8..12)?.try_into().ok()?) as usize;
+        bytes.get(offset..offset.checked_add(length)?)?
+    } else {
+        bytes
+    };
+    section.starts_with(b"%!").then_some(section)
+}
+
+/// Scans the DSC comments of a PostScript section (see `postscript_section`).
+pub fn scan_dsc(ps: &[u8], dos_eps: bool) -> DscSummary {
+    let mut summary = DscSummary { is_eps: dos_eps, ..Default::default() };
+    let mut lines = ps.split(|b| *b == b'\n' || *b == b'\r').filter(|line| !line.is_empty());
+    if let Some(header) = lines.next() {
+        summary.is_eps |= header.windows(5).any(|w| w == b"EPSF-");
+    }
+
+    let mut nesting = 0usize;
+    for line in lines {
+        if line.starts_with(b"%%BeginDocument") {
+            nesting += 1;
+        } else if line.starts_with(b"%%EndDocument") {
+            nesting = nesting.saturating_sub(1);
+        } else if nesting > 0 {
+            continue;
+        } else if let Some(value) = line.strip_prefix(b"%%Pages:") {
+            // the trailer repeats the value deferred by "(atend)"; later numbers win
+            if let Some(n) = numbers(value).first() {
+                summary.pages = Some(*n as usize);
+            }
+        } else if line.starts_with(b"%%Page:") {
+            summary.page_comments += 1;
+        } else if let Some(value) = line.strip_prefix(b"%%BoundingBox:") {
+            if let [llx, lly, urx, ury] = numbers(value)[..] {
+                summary.bounding_box = positive_size(urx - llx, ury - lly);
+            }
+        } else if let Some(value) = line.strip_prefix(b"%%DocumentMedia:") {
+            // %%DocumentMedia: name width height weight color type
+            if summary.media.is_none()
+                && let [width, height, ..] = numbers(value)[..]
+            {
+                summary.media = positive_size(width, height);
+            }
+        } else if !line.starts_with(b"%") {
+            summary.showpages += line
+                .split(|b| b.is_ascii_whitespace() || b"(){}[]/".contains(b))
+                .filter(|token| *token == b"showpage")
+                .count();
+        }
+    }
+    summary
+}
+
+/// Parses the numeric tokens of a comment value, skipping names and `(atend)`.
+fn numbers(value: &[u8]) -> Vec<f64> {
+    value
+        .split(|b| b.is_ascii_whitespace())
+        .filter_map(|token| std::str::from_utf8(token).ok()?.parse().ok())
+        .filter(|n: &f64| n.is_finite())
+        .collect()
+}
+
+fn positive_size(width: f64, height: f64) -> Option<(f64, f64)> {
+    (width > 0.0 && height > 0.0).then_some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_utils::{detect_type, mm_from_pt};
+    use crate::fixtures::*;
+    use crate::schema::PageSizeMm;
+
+    #[test]
+    fn pages_from_the_header_or_the_trailer() {
+        for atend in [false, true] {
+            let ps = build_postscript(5, atend);
+            let dsc = scan_dsc(&ps, false);
+            // the embedded EPS's %%Pages, %%Page:, and showpage are skipped
+            assert_eq!((dsc.pages, dsc.page_comments, dsc.showpages), (Some(5), 5, 5));
+            assert_eq!(dsc.media, Some((595.0, 842.0)));
+            assert!(!dsc.is_eps);
+
+            let result = estimate(&ps, Some("job.ps"), "{}").unwrap();
+            assert_eq!(result.page_count, 5);
+            let a4 = PageSizeMm { width_mm: mm_from_pt(595.0), height_mm: mm_from_pt(842.0) };
+            assert_eq!(result.page_sizes, vec![a4; 5]);
+        }
+    }
+
+    #[test]
+    fn page_comments_count_without_a_total() {
+        let ps = String::from_utf8(build_postscript(3, false)).unwrap().replace("%%Pages: 3\n", "");
+        let result = estimate(ps.as_bytes(), Some("job.ps"), "{}").unwrap();
+        assert_eq!(result.page_count, 3);
+        assert!(result.notes[0].contains("counted from %%Page: comments"), "{:?}", result.notes);
+
+        let bare = b"%!PS\n/Helvetica findfont 12 scalefont setfont\n(a) show showpage\n(b) show showpage\n";
+        let result = estimate(bare, Some("job.ps"), "{}").unwrap();
+        assert_eq!(result.page_count, 2);
+        assert!(result.confidence.is_some());
+    }
+
+    #[test]
+    fn dos_eps_is_one_page_of_its_bounding_box() {
+        let eps = build_dos_eps(200, 100);
+        let ps = postscript_section(&eps).unwrap();
+        assert!(ps.starts_with(b"%!PS-Adobe-3.0 EPSF-3.0") && ps.ends_with(b"%%EOF\n"));
+
+        let result = estimate(&eps, Some("logo.eps"), "{}").unwrap();
+        assert_eq!(result.page_count, 1);
+        assert_eq!(result.page_sizes, [PageSizeMm { width_mm: mm_from_pt(200.0), height_mm: mm_from_pt(100.0) }]);
+        assert_eq!(detect_type(None, &eps), "postscript");
+    }
+
+    #[test]
+    fn broken_dos_eps_header_is_an_error() {
+        let mut eps = build_dos_eps(200, 100);
+        eps[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(postscript_section(&eps), None);
+        let err = estimate(&eps, Some("logo.eps"), "{}").unwrap_err();
+        assert_eq!(err.to_string(), "PostScript error: No PostScript program found (missing %! header)");
+    }
+
+    #[test]
+    fn detected_by_header_or_extension() {
+        assert_eq!(detect_type(None, &build_postscript(2, false)), "postscript");
+        assert_eq!(detect_type(Some("job.ps"), &build_postscript(2, false)), "postscript");
+        assert_eq!(detect_type(Some("logo.eps"), b"%!PS-Adobe-3.0 EPSF-3.0\n"), "postscript");
+    }
+}
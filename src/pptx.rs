@@ -0,0 +1,390 @@
+//! # Presentation Text
+//!
+//! Slide counts come from the package metadata; this module reads the text of
+//! the slides and their speaker notes, for callers that estimate reading or
+//! transcript length rather than printed pages.
+//!
+//! ## Parts
+//!
+//! Slides are taken in presentation order (`p:sldIdLst` in
+//! `ppt/presentation.xml`), falling back to the numbering of the
+//! `ppt/slides/slideN.xml` parts. A slide's notes are the `notesSlide` part its
+//! relationships point at; slides without one have no notes.
+//!
+//! ## Text
+//!
+//! Text lives in DrawingML runs (`a:t`) inside paragraphs (`a:p`), whatever
+//! shape holds them (`p:txBody`, tables, groups), so elements are matched by
+//! local name and namespace (see the `ooxml` module), in either conformance
+//! class. On a notes slide only the body placeholder counts; the slide
+//! image, header, and slide number placeholders are left out.
+//!
+//! ## Slide size
+//!
+//! `<p:sldSz>` in `ppt/presentation.xml` gives the slide size in EMUs (`cx`,
+//! `cy`; 36,000 per millimeter) and names it with `type` (`screen16x9`, `A4`,
+//! `custom`, ...). The explicit size wins; the type only fills in a missing
+//! size, and a size more than `SLIDE_SIZE_TOLERANCE` away from its type (in
+//! either orientation) is reported. Sizes aren't bounded, so poster decks keep
+//! their size.
+
+use crate::file_utils::{is_system_entry, note_zip_entry, read_relationship_targets, resolve_part};
+use crate::ooxml::{attribute_value, local_name_in, NamespaceFamily, OoxmlNamespace};
+use crate::schema::TextMetrics;
+use crate::text_layout::count_printed_chars;
+use quick_xml::events::Event;
+use quick_xml::name::QName;
+use quick_xml::NsReader;
+use std::io::{BufRead, BufReader, Cursor};
+use zip::ZipArchive;
+
+/// Part holding the slide list of a presentation.
+pub const PRESENTATION_PART: &str = "ppt/presentation.xml";
+
+/// EMUs (English Metric Units) per millimeter; 914,400 per inch.
+pub const EMU_PER_MM: f64 = 36_000.0;
+
+/// Relative difference between `cx`/`cy` and the size of the `type` above which they disagree.
+pub const SLIDE_SIZE_TOLERANCE: f64 = 0.02;
+
+/// Slide size PowerPoint uses for each `type` of `<p:sldSz>`, in EMUs (width, height).
+///
+/// `custom`, the default when `type` is absent, has no size of its own.
+pub const SLIDE_SIZE_TYPES: [(&str, u64, u64); 16] = [
+    ("screen4x3", 9_144_000, 6_858_000),
+    ("screen16x9", 9_144_000, 5_143_500),
+    ("screen16x10", 9_144_000, 5_715_000),
+    ("letter", 9_144_000, 6_858_000),
+    ("ledger", 12_179_300, 9_134_475),
+    ("A3", 12_801_600, 9_601_200),
+    ("A4", 9_906_000, 6_858_000),
+    ("B4ISO", 10_826_750, 8_120_063),
+    ("B5ISO", 7_169_150, 5_376_863),
+    ("B4JIS", 11_521_440, 8_641_080),
+    ("B5JIS", 7_315_200, 5_486_400),
+    ("35mm", 10_287_000, 6_858_000),
+    ("overhead", 9_144_000, 6_858_000),
+    ("banner", 7_315_200, 914_400),
+    ("hagakiCard", 3_600_450, 5_328_000),
+    ("custom", 0, 0),
+];
+
+/// The slide size declared by `<p:sldSz>`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SlideSize {
+    /// Width and height in EMUs (`cx`, `cy`), when both are valid.
+    pub emu: Option<(u64, u64)>,
+    /// The `type` attribute, when present.
+    pub size_type: Option<String>,
+}
+
+impl SlideSize {
+    /// The size of the slides in millimeters: `cx`/`cy`, else the size of a known `type`.
+    pub fn mm(&self) -> Option<(f64, f64)> {
+        self.emu.or_else(|| self.type_emu()).map(|(cx, cy)| (mm_from_emu(cx), mm_from_emu(cy)))
+    }
+
+    /// The size of `type` in EMUs, or `None` for `custom` and unknown types.
+    pub fn type_emu(&self) -> Option<(u64, u64)> {
+        let size_type = self.size_type.as_deref()?;
+        SLIDE_SIZE_TYPES
+            .iter()
+            .find(|(name, ..)| *name == size_type)
+            .map(|(_, cx, cy)| (*cx, *cy))
+            .filter(|(cx, _)| *cx > 0)
+    }
+
+    /// Warns when `cx`/`cy` differ from the size of `type` by more than
+    /// `SLIDE_SIZE_TOLERANCE` in both orientations (portrait decks swap them).
+    pub fn mismatch_note(&self) -> Option<String> {
+        let (cx, cy) = self.emu?;
+        let (type_cx, type_cy) = self.type_emu()?;
+        let close = |a: u64, b: u64| (a as f64 - b as f64).abs() <= SLIDE_SIZE_TOLERANCE * b as f64;
+        if (close(cx, type_cx) && close(cy, type_cy)) || (close(cx, type_cy) && close(cy, type_cx)) {
+            return None;
+        }
+        Some(format!(
+            "⚠ Slide size {:.1} × {:.1} mm doesn't match its type \"{}\" ({:.1} × {:.1} mm); using the size",
+            mm_from_emu(cx),
+            mm_from_emu(cy),
+            self.size_type.as_deref().unwrap_or_default(),
+            mm_from_emu(type_cx),
+            mm_from_emu(type_cy)
+        ))
+    }
+}
+
+/// Converts EMUs to millimeters.
+pub fn mm_from_emu(emu: u64) -> f64 {
+    emu as f64 / EMU_PER_MM
+}
+
+/// Reads `<p:sldSz>` from a `ppt/presentation.xml` part.
+///
+/// # Returns
+///
+/// The declared size, or `None` when the part has no `<p:sldSz>`.
+pub fn read_slide_size<R: BufRead>(source: R) -> Option<SlideSize> {
+    let mut reader = NsReader::from_reader(source);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if presentation_name(&reader, e.name()) == Some(b"sldSz") =>
+            {
+                let value = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|attr| attr.key.as_ref() == name)
+                        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+                };
+                let emu = |name: &[u8]| value(name).and_then(|v| v.trim().parse::<u64>().ok()).filter(|v| *v > 0);
+                let emu = emu(b"cx").zip(emu(b"cy"));
+                return Some(SlideSize { emu, size_type: value(b"type") });
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Text of one slide and its speaker notes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SlideText {
+    /// The slide part (e.g. `ppt/slides/slide1.xml`).
+    pub part: String,
+    /// Text on the slide itself.
+    pub slide: TextMetrics,
+    /// Text of the speaker notes; `None` when the slide has no notes part.
+    pub notes: Option<TextMetrics>,
+}
+
+/// Lists the slide parts of a presentation in presentation order.
+///
+/// # Returns
+///
+/// The slides listed in `ppt/presentation.xml`, or, when that list can't be
+/// resolved, every `ppt/slides/slideN.xml` part ordered by `N`.
+pub fn slide_parts(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Vec<String> {
+    let targets = read_relationship_targets(archive, "ppt/_rels/presentation.xml.rels");
+    let mut parts = Vec::new();
+    if let Ok(file) = archive.by_name(PRESENTATION_PART) {
+        note_zip_entry(&file);
+        let mut reader = NsReader::from_reader(BufReader::new(file));
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                    if presentation_name(&reader, e.name()) == Some(b"sldId") =>
+                {
+                    let id = attribute_value(&reader, e, OoxmlNamespace::Relationships, b"id");
+                    if let Some(target) = id.and_then(|id| targets.get(&id)) {
+                        parts.push(resolve_part(PRESENTATION_PART, target));
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+    if parts.is_empty() {
+        let mut numbered: Vec<(usize, String)> = archive
+            .file_names()
+            .filter(|name| !is_system_entry(name))
+            .filter_map(|name| {
+                let number = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?.parse().ok()?;
+                Some((number, name.to_string()))
+            })
+            .collect();
+        numbered.sort();
+        parts = numbered.into_iter().map(|(_, name)| name).collect();
+    }
+    parts
+}
+
+/// Reads the text of each slide and of its speaker notes.
+///
+/// # Arguments
+///
+/// * `archive` - The opened PPTX package
+///
+/// # Returns
+///
+/// One entry per slide, in presentation order. Slides or notes parts that are
+/// missing or malformed count the text read before the error.
+pub fn read_slide_texts(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Vec<SlideText> {
+    slide_parts(archive)
+        .into_iter()
+        .map(|part| {
+            let slide = read_part_text(archive, &part, false).unwrap_or_default();
+            let (dir, file) = part.rsplit_once('/').unwrap_or(("", &part));
+            let rels = format!("{}/_rels/{}.rels", dir, file);
+            let notes = read_relationship_targets(archive, &rels)
+                .values()
+                .find(|target| target.contains("notesSlides/"))
+                .map(|target| resolve_part(&part, target))
+                .and_then(|notes_part| read_part_text(archive, &notes_part, true));
+            SlideText { part, slide, notes }
+        })
+        .collect()
+}
+
+/// Reads the text of a slide or notes part, or `None` when the part is missing.
+fn read_part_text(archive: &mut ZipArchive<Cursor<&[u8]>>, part: &str, notes: bool) -> Option<TextMetrics> {
+    let file = archive.by_name(part).ok()?;
+    note_zip_entry(&file);
+    Some(scan_drawing_text(BufReader::new(file), notes))
+}
+
+/// Counts the text of DrawingML paragraphs in a slide or notes part.
+///
+/// `line_count` is the number of paragraphs with text. With `body_only`, only
+/// shapes that are the body placeholder (`<p:ph type="body"/>`) count.
+pub fn scan_drawing_text<R: BufRead>(source: R, body_only: bool) -> TextMetrics {
+    let mut reader = NsReader::from_reader(source);
+    let mut buf = Vec::new();
+    let mut metrics = TextMetrics::default();
+    let mut paragraph = String::new();
+    let mut in_text = false;
+    let mut in_body = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if presentation_name(&reader, e.name()) == Some(b"ph") =>
+            {
+                let placeholder_type = e.try_get_attribute("type").ok().flatten();
+                in_body = placeholder_type.is_some_and(|attr| attr.value.as_ref() == b"body");
+            }
+            Ok(Event::Start(ref e)) if presentation_name(&reader, e.name()) == Some(b"sp") => in_body = false,
+            Ok(Event::Start(ref e)) if drawing_name(&reader, e.name()) == Some(b"t") => in_text = true,
+            Ok(Event::Empty(ref e)) if drawing_name(&reader, e.name()) == Some(b"br") => paragraph.push('\n'),
+            Ok(Event::Text(ref e)) if in_text && (in_body || !body_only) => {
+                if let Ok(text) = e.unescape() {
+                    paragraph.push_str(&text);
+                }
+            }
+            Ok(Event::End(ref e)) => match drawing_name(&reader, e.name()) {
+                Some(b"t") => in_text = false,
+                Some(b"p") => {
+                    if !paragraph.trim().is_empty() {
+                        metrics.line_count += 1;
+                        metrics.word_count += paragraph.split_whitespace().count();
+                        metrics.char_count += count_printed_chars(&paragraph);
+                    }
+                    paragraph.clear();
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    metrics
+}
+
+/// Local name of a PresentationML element (`p:sp`, `p:ph`, ...), or `None` for other namespaces.
+fn presentation_name<'n, R>(reader: &NsReader<R>, name: QName<'n>) -> Option<&'n [u8]> {
+    local_name_in(reader, name, OoxmlNamespace::PresentationMl, NamespaceFamily::Any)
+}
+
+/// Local name of a DrawingML element (`a:t`, `a:p`, ...), or `None` for other namespaces.
+fn drawing_name<'n, R>(reader: &NsReader<R>, name: QName<'n>) -> Option<&'n [u8]> {
+    local_name_in(reader, name, OoxmlNamespace::DrawingMl, NamespaceFamily::Any)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn slide_texts(pptx: &[u8]) -> Vec<SlideText> {
+        read_slide_texts(&mut ZipArchive::new(Cursor::new(pptx)).unwrap())
+    }
+
+    #[test]
+    fn notes_are_read_from_the_body_placeholder() {
+        let pptx = build_notes_pptx(&[Some("Welcome everyone.\nToday: three results."), None, Some("Questions?")]);
+        let slides = slide_texts(&pptx);
+        let parts: Vec<&str> = slides.iter().map(|slide| slide.part.as_str()).collect();
+        assert_eq!(parts, ["ppt/slides/slide1.xml", "ppt/slides/slide2.xml", "ppt/slides/slide3.xml"]);
+        // "Slide N"
+        assert!(slides.iter().all(|slide| (slide.slide.word_count, slide.slide.char_count) == (2, 7)));
+        let notes: Vec<Option<(usize, usize, usize)>> = slides
+            .iter()
+            .map(|slide| slide.notes.as_ref().map(|n| (n.line_count, n.word_count, n.char_count)))
+            .collect();
+        // the slide number placeholder isn't counted
+        assert_eq!(notes, [Some((2, 5, 38)), None, Some((1, 1, 10))]);
+    }
+
+    #[test]
+    fn drawing_text_is_matched_by_namespace() {
+        let xml = format!(
+            concat!(
+                r#"<p:sld xmlns:p="{}" xmlns:x="{}" xmlns:a="urn:other"><p:cSld><p:spTree><p:sp><p:txBody>"#,
+                r#"<x:p><x:r><x:t>Counted text</x:t></x:r><x:br/><x:r><x:t>here</x:t></x:r></x:p>"#,
+                r#"<a:p><a:r><a:t>not DrawingML</a:t></a:r></a:p>"#,
+                r#"</p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#,
+            ),
+            "http://schemas.openxmlformats.org/presentationml/2006/main",
+            "http://schemas.openxmlformats.org/drawingml/2006/main"
+        );
+        let metrics = scan_drawing_text(xml.as_bytes(), false);
+        assert_eq!((metrics.line_count, metrics.word_count), (1, 3));
+        // outside a body placeholder, nothing counts on a notes slide
+        assert_eq!(scan_drawing_text(xml.as_bytes(), true).word_count, 0);
+    }
+
+    fn declared_size(sld_sz: &str) -> SlideSize {
+        let xml = format!(
+            r#"<p:presentation xmlns:p="{}">{}</p:presentation>"#,
+            "http://schemas.openxmlformats.org/presentationml/2006/main", sld_sz
+        );
+        read_slide_size(xml.as_bytes()).unwrap()
+    }
+
+    fn deck_size(pptx: &[u8]) -> SlideSize {
+        let mut archive = ZipArchive::new(Cursor::new(pptx)).unwrap();
+        read_slide_size(BufReader::new(archive.by_name(PRESENTATION_PART).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn slide_sizes_are_read_in_millimeters() {
+        let decks = [
+            (build_sized_pptx(1, 9_144_000, 5_143_500, Some("screen16x9")), (254.0, 142.875)),
+            (build_sized_pptx(1, 6_858_000, 9_906_000, Some("A4")), (190.5, 275.167)),
+            (build_sized_pptx(1, 43_891_200, 32_918_400, Some("custom")), (1219.2, 914.4)),
+            (build_sized_pptx(1, 10_287_000, 6_858_000, Some("35mm")), (285.75, 190.5)),
+            (build_sized_pptx(1, 7_315_200, 914_400, Some("banner")), (203.2, 25.4)),
+        ];
+        for (pptx, (width, height)) in decks {
+            let size = deck_size(&pptx);
+            let (w, h) = size.mm().unwrap();
+            assert!((w - width).abs() < 1e-3 && (h - height).abs() < 1e-3, "{:?}: {} × {}", size, w, h);
+            // portrait decks swap the size of their type
+            assert_eq!(size.mismatch_note(), None);
+        }
+        // the largest size PowerPoint allows, 56 inches, converts exactly
+        assert_eq!(mm_from_emu(51_206_400), 1422.4);
+    }
+
+    #[test]
+    fn slide_size_types_fill_in_and_cross_check_the_size() {
+        let typed = declared_size(r#"<p:sldSz type="ledger" cx="0" cy="x"/>"#);
+        assert_eq!((typed.emu, typed.type_emu()), (None, Some((12_179_300, 9_134_475))));
+        assert_eq!(typed.mm().map(|(w, h)| (w.round(), h.round())), Some((338.0, 254.0)));
+        let untyped = declared_size(r#"<p:sldSz type="custom"/>"#);
+        assert_eq!(untyped.mm(), None);
+        let unknown = deck_size(&build_sized_pptx(1, 9_144_000, 5_143_500, Some("hologram")));
+        assert_eq!((unknown.type_emu(), unknown.mismatch_note()), (None, None));
+
+        // within 2% of the type is close enough, a 16:9 size labelled 4:3 isn't
+        assert_eq!(deck_size(&build_sized_pptx(1, 9_300_000, 6_858_000, Some("screen4x3"))).mismatch_note(), None);
+        let note = deck_size(&build_sized_pptx(1, 9_144_000, 5_143_500, Some("screen4x3"))).mismatch_note();
+        assert!(note.unwrap().contains("254.0 × 142.9 mm doesn't match its type \"screen4x3\" (254.0 × 190.5 mm)"));
+        assert_eq!(read_slide_size(&b"<p:presentation/>"[..]), None);
+    }
+}
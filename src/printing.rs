@@ -0,0 +1,296 @@
+//! # Printing Calculations
+//!
+//! This module turns an estimated page count into the numbers needed to quote
+//! a print job: how many physical sheets of paper are required and what the
+//! job will cost.
+//!
+//! The calculation runs after format-specific estimation, on the final
+//! `page_count`, so the results are consistent across every supported format.
+//!
+//! ## Oversized pages
+//!
+//! Receipt printers and some "save as PDF" flows produce a single page several
+//! meters tall. It counts as one page, but prints on many sheets of ordinary
+//! paper. With `split_oversized_pages`, `apply_page_split` also counts the
+//! pages as split onto the paper of the options, from the size of each page:
+//!
+//! - A page is oversized when it is more than `oversized_page_ratio` (1.5 by
+//!   default) times as tall as the paper
+//! - An oversized page counts as `ceil(height / paper height)` pages; any other
+//!   page as one, even when it's somewhat taller or wider than the paper
+//!
+//! `page_count` and `page_sizes` are left as they are; the split view is
+//! reported in `split_page_count` and `split_paper`.
+
+use crate::schema::{EstimateOptions, EstimateResult, PageSizeMm, PrintSummary};
+
+/// Paper heights a page must exceed to be split, when `oversized_page_ratio` isn't set.
+pub const DEFAULT_OVERSIZED_PAGE_RATIO: f64 = 1.5;
+
+/// Fraction of a paper height ignored when splitting a page, so that sizes
+/// converted from points to millimeters don't gain a page from rounding.
+const SPLIT_TOLERANCE: f64 = 0.001;
+
+/// Most pages of paper a single page is split into, so that a page with an
+/// absurd size (e.g. `/MediaBox [0 0 612 1e300]`) can't overflow the count.
+pub const MAX_SPLIT_PAGES: usize = 10_000;
+
+/// Computes the print summary for an estimate and stores it in the result.
+///
+/// # Arguments
+///
+/// * `result` - The estimation result to enrich; its `page_count` is used as input
+/// * `options` - Estimation options including:
+///   - `duplex`: Print on both sides of each sheet (default: false)
+///   - `pages_per_sheet`: n-up printing, 1, 2 or 4 (default: 1)
+///   - `price_per_page`: Price per printed page
+///   - `price_per_color_page`: Price per printed color page
+///
+/// # Notes
+///
+/// - Nothing is computed unless at least one printing option was supplied
+/// - Unsupported `pages_per_sheet` values fall back to 1 with a note
+/// - Sheets are rounded up: `ceil(pages / (sides × pages_per_sheet))`
+/// - Color pages are priced with `price_per_color_page` only when the estimator
+///   classified them (PDF color analysis); otherwise every page uses `price_per_page`
+pub fn apply_print_summary(result: &mut EstimateResult, options: &EstimateOptions) {
+    if options.duplex.is_none()
+        && options.pages_per_sheet.is_none()
+        && options.price_per_page.is_none()
+        && options.price_per_color_page.is_none()
+    {
+        return;
+    }
+
+    let duplex = options.duplex.unwrap_or(false);
+    let pages_per_sheet = match options.pages_per_sheet.unwrap_or(1) {
+        n @ (1 | 2 | 4) => n,
+        other => {
+            result.notes.push(format!(
+                "⚠ Unsupported pages_per_sheet {}; using 1 (supported values: 1, 2, 4)",
+                other
+            ));
+            1
+        }
+    };
+
+    let sides = if duplex { 2 } else { 1 };
+    let pages_per_physical_sheet = sides * pages_per_sheet as usize;
+    let sheets_of_paper = result.page_count.div_ceil(pages_per_physical_sheet);
+
+    let mut formula = vec![format!(
+        "sheets_of_paper = ceil({} pages / ({} sides × {} pages_per_sheet)) = {}",
+        result.page_count, sides, pages_per_sheet, sheets_of_paper
+    )];
+
+    let estimated_cost = options.price_per_page.map(|price| {
+        match (result.color_pages, options.price_per_color_page) {
+            (Some(color), Some(color_price)) => {
+                let mono = result.page_count.saturating_sub(color);
+                let cost = mono as f64 * price + color as f64 * color_price;
+                formula.push(format!(
+                    "estimated_cost = {} pages × {} + {} color pages × {} = {}",
+                    mono, price, color, color_price, cost
+                ));
+                cost
+            }
+            _ => {
+                let cost = result.page_count as f64 * price;
+                formula.push(format!(
+                    "estimated_cost = {} pages × {} per page = {}",
+                    result.page_count, price, cost
+                ));
+                cost
+            }
+        }
+    });
+
+    result.print_summary = Some(PrintSummary {
+        duplex,
+        pages_per_sheet,
+        sheets_of_paper,
+        estimated_cost,
+        formula,
+    });
+}
+
+/// Counts the pages of a result split onto the paper of the options into
+/// `split_page_count`, when `split_oversized_pages` is set (see the module docs).
+///
+/// The split is made from `page_sizes`, unless the estimator already made it
+/// from the real page sizes (PDF, whose `page_sizes` may be left out or be
+/// A4 stand-ins).
+pub fn apply_page_split(result: &mut EstimateResult, options: &EstimateOptions) {
+    if !options.split_oversized_pages.unwrap_or(false) || result.split_page_count.is_some() {
+        return;
+    }
+    let sizes = std::mem::take(&mut result.page_sizes);
+    split_oversized_pages(result, &sizes, options);
+    result.page_sizes = sizes;
+}
+
+/// Counts the pages of a result split onto the paper of the options, from the
+/// size of each of its pages (see the module docs).
+///
+/// # Arguments
+///
+/// * `result` - The result to fill `split_page_count` and `split_paper` of
+/// * `sizes` - The size of each page, as displayed
+/// * `options` - The options, for the paper and `oversized_page_ratio`
+///
+/// # Notes
+///
+/// - When `sizes` doesn't list every page (e.g. with `include_page_sizes` off),
+///   a warning is added instead
+/// - A note gives the number of oversized pages and the split page count
+pub fn split_oversized_pages(result: &mut EstimateResult, sizes: &[PageSizeMm], options: &EstimateOptions) {
+    if sizes.len() != result.page_count {
+        result.notes.push("⚠ The size of some pages is unknown; oversized pages weren't split".into());
+        return;
+    }
+    let (width_mm, height_mm) = options.paper();
+    let ratio = options.oversized_page_ratio.unwrap_or(DEFAULT_OVERSIZED_PAGE_RATIO);
+    let splits: Vec<usize> = sizes.iter().map(|size| split_page(size.height_mm, height_mm, ratio)).collect();
+    let split_page_count = splits.iter().fold(0usize, |total, &pages| total.saturating_add(pages));
+    let oversized: Vec<usize> = splits.into_iter().filter(|&pages| pages > 1).collect();
+    if !oversized.is_empty() {
+        result.notes.push(format!(
+            "{} oversized pages (over {} × the {} mm paper height) split into {} pages; {} pages in all",
+            oversized.len(),
+            ratio,
+            height_mm,
+            oversized.iter().fold(0usize, |total, &pages| total.saturating_add(pages)),
+            split_page_count
+        ));
+    }
+    result.split_page_count = Some(split_page_count);
+    result.split_paper = Some(PageSizeMm { width_mm, height_mm });
+}
+
+/// Number of pages of paper a page prints on when split.
+///
+/// # Arguments
+///
+/// * `height_mm` - Height of the page
+/// * `paper_height_mm` - Height of the paper it's split onto
+/// * `ratio` - Paper heights the page must exceed to be split (`oversized_page_ratio`)
+///
+/// # Returns
+///
+/// `ceil(height_mm / paper_height_mm)` for an oversized page, at most
+/// [`MAX_SPLIT_PAGES`]; 1 for any other, or for a height that isn't a number.
+pub fn split_page(height_mm: f64, paper_height_mm: f64, ratio: f64) -> usize {
+    if paper_height_mm <= 0.0 || height_mm.is_nan() || height_mm <= ratio * paper_height_mm {
+        return 1;
+    }
+    let pages = (height_mm / paper_height_mm - SPLIT_TOLERANCE).ceil();
+    if pages >= MAX_SPLIT_PAGES as f64 { MAX_SPLIT_PAGES } else { (pages as usize).max(1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn summary(pages: usize, options_json: &str) -> (Option<PrintSummary>, Vec<String>) {
+        let mut result = EstimateResult::uniform(pages, Some((210.0, 297.0)), Vec::new());
+        let options: EstimateOptions = serde_json::from_str(options_json).unwrap();
+        apply_print_summary(&mut result, &options);
+        (result.print_summary, result.notes)
+    }
+
+    #[test]
+    fn no_printing_options_means_no_summary() {
+        assert_eq!(summary(12, "{}").0, None);
+    }
+
+    #[test]
+    fn sheets_round_up_over_sides_and_pages_per_sheet() {
+        let cases =
+            [(9, false, 1, 9), (9, true, 1, 5), (9, false, 2, 5), (9, true, 2, 3), (9, true, 4, 2), (8, true, 4, 1)];
+        for (pages, duplex, per_sheet, sheets) in cases {
+            let json = format!(r#"{{"duplex":{},"pages_per_sheet":{}}}"#, duplex, per_sheet);
+            let summary = summary(pages, &json).0.unwrap();
+            assert_eq!(summary.sheets_of_paper, sheets, "{}", json);
+            assert_eq!(summary.estimated_cost, None);
+            assert!(summary.formula[0].ends_with(&format!("= {}", sheets)), "{:?}", summary.formula);
+        }
+    }
+
+    #[test]
+    fn unsupported_pages_per_sheet_falls_back_to_one() {
+        let (summary, notes) = summary(5, r#"{"pages_per_sheet":3}"#);
+        let summary = summary.unwrap();
+        assert_eq!((summary.pages_per_sheet, summary.sheets_of_paper), (1, 5));
+        assert!(notes[0].contains("Unsupported pages_per_sheet 3"), "{:?}", notes);
+    }
+
+    #[test]
+    fn cost_prices_classified_color_pages_separately() {
+        let (summary, _) = summary(10, r#"{"price_per_page":0.1,"price_per_color_page":0.5}"#);
+        assert!((summary.unwrap().estimated_cost.unwrap() - 1.0).abs() < 1e-9);
+
+        let mut result = EstimateResult::uniform(10, Some((210.0, 297.0)), Vec::new());
+        result.color_pages = Some(3);
+        let options: EstimateOptions =
+            serde_json::from_str(r#"{"price_per_page":0.1,"price_per_color_page":0.5}"#).unwrap();
+        apply_print_summary(&mut result, &options);
+        let summary = result.print_summary.unwrap();
+        assert!((summary.estimated_cost.unwrap() - (7.0 * 0.1 + 3.0 * 0.5)).abs() < 1e-9);
+        assert!(summary.formula[1].contains("3 color pages × 0.5"), "{:?}", summary.formula);
+    }
+
+    #[test]
+    fn summary_is_the_same_for_every_format() {
+        let options = r#"{"duplex":true,"pages_per_sheet":2,"price_per_page":0.25}"#;
+        let pdf = estimate(&build_pdf(9, XrefStyle::Classic, false, false), None, options).unwrap();
+        let docx = estimate(&build_docx(20, 0, Some(9)), None, options).unwrap();
+        let pptx = estimate(&build_pptx(9, 0), None, options).unwrap();
+        assert_eq!(pdf.print_summary.as_ref().unwrap().sheets_of_paper, 3);
+        assert_eq!(pdf.print_summary, docx.print_summary);
+        assert_eq!(pdf.print_summary, pptx.print_summary);
+    }
+
+    #[test]
+    fn pages_split_past_the_oversized_ratio() {
+        assert_eq!(split_page(297.0, 297.0, 1.5), 1);
+        assert_eq!(split_page(445.0, 297.0, 1.5), 1);
+        assert_eq!(split_page(446.0, 297.0, 1.5), 2);
+        assert_eq!(split_page(594.2, 297.0, 1.5), 2);
+        assert_eq!(split_page(595.0, 297.0, 1.5), 3);
+        assert_eq!(split_page(595.0, 297.0, 3.0), 1);
+        assert_eq!(split_page(5000.0, 0.0, 1.5), 1);
+
+        // sizes past any real page are clamped, and sizes that aren't numbers count once
+        assert_eq!(split_page(1e300, 297.0, 1.5), MAX_SPLIT_PAGES);
+        assert_eq!(split_page(f64::INFINITY, 297.0, 1.5), MAX_SPLIT_PAGES);
+        assert_eq!(split_page(f64::NAN, 297.0, 1.5), 1);
+    }
+
+    #[test]
+    fn oversized_pages_are_counted_as_split() {
+        let options = r#"{"split_oversized_pages":true}"#;
+        let receipt = estimate(&build_receipt_pdf(&[200.0, 1200.0, 297.0]), None, options).unwrap();
+        assert_eq!(receipt.page_count, 3);
+        assert_eq!(receipt.split_page_count, Some(1 + 5 + 1));
+        assert_eq!(receipt.split_paper, Some(PageSizeMm { width_mm: 210.0, height_mm: 297.0 }));
+        assert!(receipt.notes.iter().any(|note| note.starts_with("1 oversized pages")), "{:?}", receipt.notes);
+
+        // pages too tall to count add up without overflowing
+        let huge = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 1e300] >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 1e300] >>",
+        ]);
+        let result = estimate(&huge, None, options).unwrap();
+        assert_eq!(result.page_count, 2);
+        let split = result.split_page_count.unwrap();
+        assert!((2..=2 * MAX_SPLIT_PAGES).contains(&split), "{}", split);
+
+        let mut result = EstimateResult::uniform(3, Some((80.0, f64::MAX)), Vec::new());
+        let sizes = result.page_sizes.clone();
+        split_oversized_pages(&mut result, &sizes, &EstimateOptions::default());
+        assert_eq!(result.split_page_count, Some(3 * MAX_SPLIT_PAGES));
+    }
+}
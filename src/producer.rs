@@ -0,0 +1,206 @@
+//! # Producing Application
+//!
+//! Names the application that wrote a document, to help triage estimates
+//! that don't match what the user sees:
+//!
+//! - PDF: `/Producer` and `/Creator` of the document information dictionary
+//! - OOXML: `<Application>` and `<AppVersion>` of `docProps/app.xml`
+//!
+//! Some producers write page or slide counts they never laid out (libraries
+//! that copy them from a template, or leave them at 0). `PRODUCER_CONFIDENCE`
+//! lists them, so that estimates read from their metadata report a lower
+//! confidence. It also lists producers whose counts come from a real layout
+//! (Google Docs exports), with a confidence of at least
+//! `RELIABLE_PRODUCER_CONFIDENCE`.
+
+use crate::file_utils::note_zip_entry;
+use crate::ooxml::{local_name_in, NamespaceFamily, OoxmlNamespace};
+use crate::pdf_crypt::PdfDecryptor;
+use crate::pdf_utils::ObjectIndex;
+use quick_xml::events::Event;
+use quick_xml::NsReader;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// Confidence of metadata page counts by format and producer.
+///
+/// Each entry is `(format, producer, confidence)`: `producer` is matched
+/// case-insensitively as a substring of the producer name. The first matching
+/// entry applies.
+pub const PRODUCER_CONFIDENCE: &[(&str, &str, f64)] = &[
+    // Google Docs lays out the document when it exports it
+    ("docx", "google", 0.95),
+    // libraries that write app.xml without laying out the document
+    ("docx", "python-docx", 0.4),
+    ("docx", "docx4j", 0.5),
+    ("docx", "apache poi", 0.5),
+    ("docx", "openxml sdk", 0.5),
+    ("pptx", "python-pptx", 0.5),
+    ("pptx", "apache poi", 0.5),
+];
+
+/// Confidence from which a producer in `PRODUCER_CONFIDENCE` is known to write accurate counts.
+pub const RELIABLE_PRODUCER_CONFIDENCE: f64 = 0.9;
+
+/// Looks up the confidence of a metadata count written by `producer`.
+///
+/// # Returns
+///
+/// The confidence from `PRODUCER_CONFIDENCE`, or `None` when the producer
+/// isn't listed for `format`.
+pub fn producer_confidence(format: &str, producer: &str) -> Option<f64> {
+    let producer = producer.to_lowercase();
+    PRODUCER_CONFIDENCE
+        .iter()
+        .find(|(entry_format, name, _)| *entry_format == format && producer.contains(name))
+        .map(|(_, _, confidence)| *confidence)
+}
+
+/// Reads the producer of a PDF from its document information dictionary.
+///
+/// # Returns
+///
+/// `/Producer`, followed by `/Creator` in parentheses when it names another
+/// application; `None` when neither is set (or the dictionary sits in an
+/// object stream).
+///
+/// # Notes
+///
+/// The strings of an encrypted PDF are only readable with its `decryptor`.
+pub fn pdf_producer(index: &ObjectIndex, decryptor: Option<&PdfDecryptor>) -> Option<String> {
+    let info = index.info()?;
+    let info_ref = index.info_ref()?;
+    let non_empty = |key: &[u8]| {
+        let value = match decryptor {
+            Some(decryptor) => decryptor.resolve_string(index, info_ref, key)?,
+            None => index.resolve_string(info, key)?,
+        };
+        let value = value.trim().to_string();
+        (!value.is_empty()).then_some(value)
+    };
+    match (non_empty(b"/Producer"), non_empty(b"/Creator")) {
+        (Some(producer), Some(creator)) if producer != creator => Some(format!("{} ({})", producer, creator)),
+        (producer, creator) => producer.or(creator),
+    }
+}
+
+/// Reads the producer of an OOXML package from `docProps/app.xml`.
+///
+/// # Returns
+///
+/// `Application`, followed by `AppVersion` when present (e.g.
+/// `"Microsoft Office Word 16.0000"`); `None` when the part or the
+/// application name is missing.
+pub fn ooxml_producer(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<String> {
+    let mut file = archive.by_name("docProps/app.xml").ok()?;
+    note_zip_entry(&file);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    parse_app_producer(&contents)
+}
+
+/// Reads `Application` and `AppVersion` from the contents of `docProps/app.xml`.
+pub fn parse_app_producer(xml: &str) -> Option<String> {
+    let mut reader = NsReader::from_str(xml);
+    let mut current = None;
+    let mut application = None;
+    let mut version = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => {
+                current = local_name_in(&reader, e.name(), OoxmlNamespace::ExtendedProperties, NamespaceFamily::Any)
+                    .filter(|name| matches!(*name, b"Application" | b"AppVersion"))
+                    .map(<[u8]>::to_vec);
+            }
+            Ok(Event::Text(ref e)) => {
+                let text = e.unescape().map(|text| text.trim().to_string()).unwrap_or_default();
+                match current.as_deref() {
+                    Some(b"Application") if !text.is_empty() => application = Some(text),
+                    Some(b"AppVersion") if !text.is_empty() => version = Some(text),
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => current = None,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    let application = application?;
+    Some(match version {
+        Some(version) => format!("{} {}", application, version),
+        None => application,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn producer_confidence_follows_the_table() {
+        for (format, name, confidence) in PRODUCER_CONFIDENCE {
+            assert!((0.0..=1.0).contains(confidence), "{}", name);
+            // each entry is reachable: no earlier entry shadows it
+            assert_eq!(producer_confidence(format, name), Some(*confidence), "{} {}", format, name);
+        }
+        assert_eq!(producer_confidence("docx", "python-docx 1.1.0"), Some(0.4));
+        assert_eq!(producer_confidence("docx", "Apache POI"), Some(0.5));
+        assert_eq!(producer_confidence("pptx", "python-pptx"), Some(0.5));
+        assert!(producer_confidence("docx", "Google Docs").unwrap() >= RELIABLE_PRODUCER_CONFIDENCE);
+        // entries apply to their format only
+        assert_eq!(producer_confidence("pptx", "python-docx"), None);
+        assert_eq!(producer_confidence("xlsx", "Apache POI"), None);
+        assert_eq!(producer_confidence("docx", "Microsoft Office Word"), None);
+    }
+
+    #[test]
+    fn application_and_version_are_read_from_app_xml() {
+        let app = |ns: &str, body: &str| format!(r#"<Properties xmlns="{}">{}</Properties>"#, ns, body);
+        let transitional = "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties";
+        let strict = "http://purl.oclc.org/ooxml/officeDocument/extendedProperties";
+        let word = "<Application>Microsoft Office Word</Application><AppVersion>16.0000</AppVersion>";
+        assert_eq!(parse_app_producer(&app(transitional, word)).as_deref(), Some("Microsoft Office Word 16.0000"));
+        assert_eq!(parse_app_producer(&app(strict, word)).as_deref(), Some("Microsoft Office Word 16.0000"));
+        let libre = "<Application>LibreOffice/7.6.4.1$Linux_X86_64</Application><Pages>3</Pages>";
+        assert_eq!(parse_app_producer(&app(transitional, libre)).as_deref(), Some("LibreOffice/7.6.4.1$Linux_X86_64"));
+        // a version alone doesn't name the producer
+        assert_eq!(parse_app_producer(&app(transitional, "<AppVersion>16.0000</AppVersion>")), None);
+        assert_eq!(parse_app_producer(&app(transitional, "<Application> </Application>")), None);
+        assert_eq!(parse_app_producer(&app("urn:other", word)), None);
+
+        let docx = build_docx_written_by("python-docx", 3, 1);
+        let producer = ooxml_producer(&mut ZipArchive::new(Cursor::new(docx.as_slice())).unwrap());
+        assert_eq!(producer.as_deref(), Some("python-docx"));
+    }
+
+    /// A one-page PDF whose trailer names `info` as its `/Info` dictionary.
+    fn pdf_with_info(info: &str) -> Vec<u8> {
+        let pdf = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>",
+            info,
+            "(Microsoft Word)",
+        ]);
+        let at = crate::pdf_utils::find_bytes(&pdf, b"/Root 1 0 R").unwrap();
+        [&pdf[..at], b"/Info 4 0 R ", &pdf[at..]].concat()
+    }
+
+    #[test]
+    fn pdf_producer_and_creator_are_combined() {
+        let producer = |info: &str| {
+            let pdf = pdf_with_info(info);
+            pdf_producer(&ObjectIndex::build(&pdf), None)
+        };
+        let both = "<< /Producer (Microsoft: Print To PDF) /Creator 5 0 R >>";
+        assert_eq!(producer(both).as_deref(), Some("Microsoft: Print To PDF (Microsoft Word)"));
+        let same = "<< /Producer (Scanner XYZ) /Creator (Scanner XYZ) >>";
+        assert_eq!(producer(same).as_deref(), Some("Scanner XYZ"));
+        let utf16 = "<< /Producer ( ) /Creator <FEFF00C9006400690074> >>";
+        assert_eq!(producer(utf16).as_deref(), Some("Édit"));
+        assert_eq!(producer("<< /Title (Report) >>"), None);
+        let pdf = build_pdf(1, XrefStyle::Classic, false, false);
+        assert_eq!(pdf_producer(&ObjectIndex::build(&pdf), None), None);
+    }
+}
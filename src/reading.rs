@@ -0,0 +1,127 @@
+//! # Reading Time
+//!
+//! Approximates how long a document takes to read, for displays such as
+//! "7 pages · 12 min read". The estimate is based on the word count in
+//! `text_metrics`, read at `reading_wpm` words per minute (200 by default).
+//!
+//! Markdown and HTML (MHT) documents also add a fixed time for each image
+//! and each code block. Readers stop to look at these for longer than their
+//! word count suggests.
+
+/// Words read per minute when `reading_wpm` isn't set.
+pub const DEFAULT_READING_WPM: usize = 200;
+
+/// Seconds added for each image, the usual heuristic of reading-time estimators.
+pub const SECONDS_PER_IMAGE: f64 = 12.0;
+
+/// Seconds added for each code block.
+pub const SECONDS_PER_CODE_BLOCK: f64 = 20.0;
+
+/// Images and code blocks of a document, which add to its reading time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadingMedia {
+    pub images: usize,
+    pub code_blocks: usize,
+}
+
+/// Computes the reading time of a document.
+///
+/// # Arguments
+///
+/// * `words` - The word count of the document's text
+/// * `media` - Its images and code blocks
+/// * `wpm` - Words read per minute
+///
+/// # Returns
+///
+/// The reading time in minutes, rounded to one decimal.
+pub fn reading_minutes(words: usize, media: ReadingMedia, wpm: usize) -> f64 {
+    let seconds = media.images as f64 * SECONDS_PER_IMAGE + media.code_blocks as f64 * SECONDS_PER_CODE_BLOCK;
+    let minutes = words as f64 / wpm.max(1) as f64 + seconds / 60.0;
+    (minutes * 10.0).round() / 10.0
+}
+
+/// Counts the images (`![alt](src)`) and fenced code blocks of Markdown text.
+///
+/// Image syntax inside code blocks isn't counted.
+pub fn markdown_media(text: &str) -> ReadingMedia {
+    let mut media = ReadingMedia::default();
+    let mut fence: Option<&str> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+                media.code_blocks += 1;
+            }
+            None => media.images += line.matches("![").count(),
+        }
+    }
+    media
+}
+
+/// Counts the images (`<img>`) and preformatted blocks (`<pre>`) of HTML.
+pub fn html_media(html: &str) -> ReadingMedia {
+    let lower = html.to_ascii_lowercase();
+    ReadingMedia { images: lower.matches("<img").count(), code_blocks: lower.matches("<pre").count() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::schema::{EstimateResult, EstimatorError};
+
+    #[test]
+    fn media_add_fixed_times() {
+        assert_eq!(reading_minutes(400, ReadingMedia::default(), DEFAULT_READING_WPM), 2.0);
+        assert_eq!(reading_minutes(400, ReadingMedia { images: 5, code_blocks: 3 }, 200), 4.0);
+        assert_eq!(reading_minutes(250, ReadingMedia::default(), 300), 0.8);
+        // a zero rate is rejected with the options; here it reads as one word a minute
+        assert_eq!(reading_minutes(3, ReadingMedia::default(), 0), 3.0);
+
+        let markdown = "![a](a.png) text ![b](b.png)\n\n~~~\n![in code](c.png)\n~~~\n\n```rust\nfn main() {}\n```\n";
+        assert_eq!(markdown_media(markdown), ReadingMedia { images: 2, code_blocks: 2 });
+        let html = r#"<p><IMG src="a.png"><img src="b.png"></p><PRE>x</PRE><pre class="code">y</pre>"#;
+        assert_eq!(html_media(html), ReadingMedia { images: 2, code_blocks: 2 });
+    }
+
+    #[test]
+    fn text_formats_report_reading_time() {
+        let words = "word ".repeat(2000);
+        let minutes = |bytes: &[u8], name: &str, options: &str| {
+            estimate(bytes, Some(name), options).unwrap().reading_time_minutes
+        };
+        assert_eq!(minutes(words.as_bytes(), "a.txt", "{}"), Some(10.0));
+        assert_eq!(minutes(words.as_bytes(), "a.txt", r#"{"reading_wpm":400}"#), Some(5.0));
+        // 2011 words, two images, and a code block whose image isn't counted
+        let markdown = format!("# Title\n\n{}\n\n![chart](a.png) ![b](b.png)\n\n```\n![x](x.png)\n```\n", words);
+        assert_eq!(minutes(markdown.as_bytes(), "a.md", "{}"), Some(10.8));
+        // 160 words and the picture Word saved alongside
+        assert_eq!(minutes(&build_word_mht(&["Alpha beta gamma delta."; 40], 3000), "a.mht", "{}"), Some(1.0));
+        // DOCX words are counted with the text metrics
+        let docx = build_docx(30, 0, Some(3));
+        assert_eq!(minutes(&docx, "a.docx", "{}"), None);
+        assert_eq!(minutes(&docx, "a.docx", r#"{"extract_text_metrics":true}"#), Some(0.9));
+
+        let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        for (bytes, name) in [(pdf, "a.pdf"), (build_xlsx(&[(5, 5)]), "a.xlsx")] {
+            assert_eq!(minutes(&bytes, name, r#"{"extract_text_metrics":true}"#), None, "{}", name);
+        }
+        let Err(EstimatorError::InvalidOptions(message)) = estimate(b"hi", None, r#"{"reading_wpm":0}"#) else {
+            panic!("a reading rate of 0 was accepted");
+        };
+        assert_eq!(message, "reading_wpm must be greater than 0");
+    }
+
+    #[test]
+    fn merged_reading_times_add_up() {
+        let text = estimate("word ".repeat(600).as_bytes(), Some("a.txt"), "{}").unwrap();
+        let more = estimate("word ".repeat(200).as_bytes(), Some("b.txt"), "{}").unwrap();
+        assert_eq!(EstimateResult::merge(&[text.clone(), more]).reading_time_minutes, Some(4.0));
+        let pdf = estimate(&build_pdf(2, XrefStyle::Classic, false, false), None, "{}").unwrap();
+        assert_eq!(EstimateResult::merge(&[text, pdf]).reading_time_minutes, None);
+    }
+}
@@ -0,0 +1,266 @@
+//! # Contact and Calendar Files
+//!
+//! Reads vCard (`.vcf`) and iCalendar (`.ics`) files, which are lists of
+//! records made of `NAME;PARAMS:value` lines. Printed from an address book or
+//! a calendar, each record becomes a short block of fields, so the estimator
+//! counts records rather than lines (see `estimate_records_pages`).
+//!
+//! ## Lines
+//!
+//! - Long lines are folded: a line break followed by a space or a tab
+//!   continues the previous line (RFC 6350, RFC 5545). Breaks may be CRLF or LF.
+//! - vCard 2.1 values with `ENCODING=QUOTED-PRINTABLE` continue instead with a
+//!   soft line break (`=` ending the line), and their `=XX` bytes are decoded
+//! - Text escapes (`\n`, `\,`, `\;`, `\\`) are decoded
+//!
+//! ## Records
+//!
+//! `BEGIN:VCARD` starts a contact and `BEGIN:VEVENT` an event. Other
+//! components (time zones, to-dos, alarms) aren't records; their lines are
+//! only printed when the file has no contacts or events. Identifiers and
+//! version lines (`SKIPPED_PROPERTIES`) and binary values such as embedded
+//! photos don't print as text and are left out of the listing.
+//!
+//! ## Detection
+//!
+//! Files are routed by extension. Without a filename, text whose first line
+//! is `BEGIN:VCARD` or `BEGIN:VCALENDAR` is taken as a contact or calendar file.
+
+use crate::file_utils::strip_bom;
+
+/// Properties that identify a record or its producer rather than print.
+pub const SKIPPED_PROPERTIES: [&str; 6] = ["VERSION", "PRODID", "UID", "REV", "CALSCALE", "METHOD"];
+
+/// The printed content of a contact or calendar file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordListing {
+    /// Number of contacts (`VCARD`).
+    pub contacts: usize,
+    /// Number of events (`VEVENT`).
+    pub events: usize,
+    /// Number of other components, such as `VTIMEZONE`, `VTODO`, and `VALARM`.
+    pub other_components: usize,
+    /// Number of logical lines once unfolded.
+    pub lines: usize,
+    /// The contacts and events, one `NAME: value` line per property and a
+    /// blank line after each record.
+    pub listing: String,
+    /// The properties of the other components, in the same layout.
+    pub other_listing: String,
+}
+
+impl RecordListing {
+    /// Number of contacts and events.
+    pub fn records(&self) -> usize {
+        self.contacts + self.events
+    }
+}
+
+/// Whether the input looks like a vCard or iCalendar file: its first non-blank line is `BEGIN:VCARD` or
+/// `BEGIN:VCALENDAR`.
+pub fn looks_like_records(bytes: &[u8]) -> bool {
+    let text = strip_bom(bytes);
+    let head = &text[..text.len().min(256)];
+    let first = String::from_utf8_lossy(head);
+    first.lines().map(str::trim).find(|line| !line.is_empty()).is_some_and(|line| {
+        line.eq_ignore_ascii_case("BEGIN:VCARD") || line.eq_ignore_ascii_case("BEGIN:VCALENDAR")
+    })
+}
+
+/// Unfolds the lines of a vCard or iCalendar file (see the module docs).
+///
+/// # Returns
+///
+/// The logical lines, without their line breaks. Quoted-printable values are
+/// joined across soft line breaks but not decoded.
+pub fn unfold_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match lines.last_mut() {
+            // a soft line break keeps the next line whole, leading whitespace included
+            Some(last) if is_quoted_printable(last) && last.ends_with('=') => {
+                last.pop();
+                last.push_str(line);
+            }
+            Some(last) if line.starts_with([' ', '\t']) => last.push_str(&line[1..]),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    if lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Reads the contacts and events of a vCard or iCalendar file.
+///
+/// Unbalanced `BEGIN`/`END` lines are tolerated: a record runs until its
+/// `END`, the next record, or the end of the file.
+pub fn parse_records(bytes: &[u8]) -> RecordListing {
+    let text = String::from_utf8_lossy(strip_bom(bytes));
+    let lines = unfold_lines(&text);
+    let mut records = RecordListing { lines: lines.len(), ..Default::default() };
+    let mut components: Vec<String> = Vec::new();
+    for line in &lines {
+        let Some((name_params, value)) = line.split_once(':') else { continue };
+        let mut params = name_params.split(';');
+        let name = params.next().unwrap_or_default();
+        // properties may be grouped (`item1.EMAIL`)
+        let name = name.rsplit_once('.').map_or(name, |(_, name)| name).trim().to_ascii_uppercase();
+        match name.as_str() {
+            "BEGIN" => {
+                let component = value.trim().to_ascii_uppercase();
+                match component.as_str() {
+                    "VCARD" | "VEVENT" => {
+                        // a record left open ends where the next one starts
+                        if components.last().is_some_and(|open| is_record(open)) {
+                            components.pop();
+                            records.listing.push('\n');
+                        }
+                        if component == "VCARD" {
+                            records.contacts += 1;
+                        } else {
+                            records.events += 1;
+                        }
+                    }
+                    "VCALENDAR" => {}
+                    _ => records.other_components += 1,
+                }
+                components.push(component);
+            }
+            "END" => {
+                let component = value.trim().to_ascii_uppercase();
+                if let Some(open) = components.iter().rposition(|open| *open == component) {
+                    components.truncate(open);
+                    if is_record(&component) {
+                        records.listing.push('\n');
+                    }
+                }
+            }
+            _ if SKIPPED_PROPERTIES.contains(&name.as_str()) => {}
+            _ => {
+                let params: Vec<String> = params.map(|param| param.to_ascii_uppercase()).collect();
+                if is_binary(&params, value) {
+                    continue;
+                }
+                let value = if params.iter().any(|param| param.ends_with("QUOTED-PRINTABLE")) {
+                    decode_quoted_printable(value)
+                } else {
+                    value.to_string()
+                };
+                let listing = match components.last() {
+                    Some(component) if is_record(component) => &mut records.listing,
+                    _ => &mut records.other_listing,
+                };
+                listing.push_str(&format!("{}: {}\n", name, unescape_text(&value)));
+            }
+        }
+    }
+    records
+}
+
+fn is_record(component: &str) -> bool {
+    matches!(component, "VCARD" | "VEVENT")
+}
+
+/// Whether a logical line holds a quoted-printable value, which continues after a trailing `=`.
+fn is_quoted_printable(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(name_params, _)| name_params.to_ascii_uppercase().contains("QUOTED-PRINTABLE"))
+}
+
+/// Whether a property holds binary data (an embedded photo, logo, sound, or attachment).
+fn is_binary(params: &[String], value: &str) -> bool {
+    params.iter().any(|param| {
+        matches!(param.as_str(), "ENCODING=B" | "ENCODING=BASE64" | "BASE64" | "VALUE=BINARY")
+    }) || value.trim_start().starts_with("data:")
+}
+
+/// Decodes the `=XX` bytes of a quoted-printable value, as UTF-8.
+fn decode_quoted_printable(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) if bytes[i] == b'=' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Decodes the backslash escapes of a text value.
+fn unescape_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                chars.next();
+                text.push('\n');
+            }
+            ('\\', Some(escaped @ (',' | ';' | '\\'))) => {
+                chars.next();
+                text.push(escaped);
+            }
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn folded_lines_are_unfolded_across_crlf_and_lf() {
+        let text = "NOTE:one\r\n  two\r\n\tthree\nFN:Ada\n four\r\n\r\nEND:VCARD\r\n";
+        assert_eq!(unfold_lines(text), ["NOTE:one twothree", "FN:Adafour", "", "END:VCARD"]);
+        // soft line breaks keep the whitespace of the next line, decoded or not
+        let text = "NOTE;ENCODING=QUOTED-PRINTABLE:caf=C3=A9 =\r\n au lait\r\nEMAIL:a@example.com";
+        assert_eq!(unfold_lines(text), ["NOTE;ENCODING=QUOTED-PRINTABLE:caf=C3=A9  au lait", "EMAIL:a@example.com"]);
+    }
+
+    #[test]
+    fn records_list_their_printed_fields() {
+        let vcf = concat!(
+            "BEGIN:VCARD\r\nVERSION:2.1\r\nFN:Zoë Martin\r\n",
+            "NOTE;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:Caf=C3=A9 owner=0D=0A=\r\nsecond line\r\n",
+            "PHOTO;ENCODING=BASE64;TYPE=JPEG:/9j/4AAQSkZJRgABAQ\r\n AAAQABAAD\r\n",
+            "item1.EMAIL;TYPE=INTERNET:zoe@example.com\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\r\nFN:Open record\r\n",
+            "BEGIN:VCARD\r\nFN:Last\\, but not least\r\nEND:VCARD\r\n",
+        );
+        let records = parse_records(vcf.as_bytes());
+        assert_eq!((records.contacts, records.events, records.lines), (3, 0, 12));
+        assert_eq!(
+            records.listing,
+            "FN: Zoë Martin\nNOTE: Café owner\r\nsecond line\nEMAIL: zoe@example.com\n\n\
+             FN: Open record\n\nFN: Last, but not least\n\n"
+        );
+
+        let calendar = parse_records(&build_calendar(2));
+        assert_eq!((calendar.events, calendar.other_components, calendar.records()), (2, 1, 2));
+        assert!(calendar.listing.contains("DESCRIPTION: Agenda: open items\nbudget and hiring\n"));
+        assert_eq!(calendar.other_listing, "TZID: Europe/Berlin\n");
+        assert!(!calendar.listing.contains("UID") && !calendar.listing.contains("PRODID"));
+    }
+
+    #[test]
+    fn contact_and_calendar_files_are_recognized() {
+        assert!(looks_like_records(&build_vcards(1)));
+        assert!(looks_like_records(b"\xEF\xBB\xBF\r\n  begin:vcalendar\r\n"));
+        assert!(!looks_like_records(b"Notes\nBEGIN:VCARD\n"));
+        assert!(!looks_like_records(b"BEGIN:VCARDS\n"));
+    }
+}
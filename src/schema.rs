@@ -3,7 +3,11 @@
 //! This module defines the core types used throughout the page counter library,
 //! including error types, configuration options, and result structures.
 
+use crate::file_utils::{a4_mm, letter_mm, mm_from_pt, paper_for_locale, pt_from_mm, PAPER_PRESETS};
+use crate::notes::is_warning;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use thiserror::Error;
 
 /// Errors that can occur during page count estimation.
@@ -21,6 +25,83 @@ pub enum EstimatorError {
     /// An error occurred while parsing an Excel (XLSX) document.
     #[error("XLSX parse error: {0}")]
     XlsxError(String),
+    /// A Word (DOCX) document is missing required parts or its page count can't be read.
+    #[error("DOCX error: {0}")]
+    DocxError(String),
+    /// A PowerPoint (PPTX) presentation is missing required parts or its slide count can't be read.
+    #[error("PPTX error: {0}")]
+    PptxError(String),
+    /// An XPS document is missing required parts or lists no pages.
+    #[error("XPS error: {0}")]
+    XpsError(String),
+    /// A PostScript file has no PostScript program (e.g. a broken DOS EPS header).
+    #[error("PostScript error: {0}")]
+    PostScriptError(String),
+    /// A ZIP container (including OOXML packages) could not be opened or read.
+    #[error("ZIP error: {0}")]
+    ZipError(String),
+    /// An XML part of a document is malformed.
+    #[error("XML parse error: {0}")]
+    XmlError(String),
+    /// The document is password-protected and its content cannot be read.
+    #[error("Document is encrypted or password-protected")]
+    EncryptedDocument,
+    /// The password given in `pdf_password` opens neither as the user nor as the owner password.
+    #[error("The password does not open the document")]
+    WrongPassword,
+    /// The input contains no bytes.
+    #[error("Input is empty")]
+    EmptyInput,
+    /// The input (or its decompressed content) exceeds a size limit.
+    #[error("Input exceeds the size limit of {limit} bytes ({actual} bytes read)")]
+    TooLarge {
+        /// The limit in bytes.
+        limit: usize,
+        /// Bytes read before the limit was hit (at least `limit + 1`).
+        actual: usize,
+    },
+    /// A resource could not be fetched (network failure, blocked by CORS, or an invalid URL).
+    #[error("Network error: {0}")]
+    NetworkError(String),
+    /// A resource was fetched but the server answered with an unsuccessful HTTP status.
+    #[error("HTTP error: status {status}")]
+    HttpError {
+        /// The HTTP status code.
+        status: u16,
+    },
+    /// An estimation option has a value that can't be used.
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+    /// The registered fallback estimator threw or returned an invalid result.
+    #[error("Fallback estimator error: {0}")]
+    CustomEstimatorError(String),
+    /// The input is a URL or a file path rather than the content of a document,
+    /// or base64 that doesn't decode.
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    /// The input is a Google Drive stub or sign-in page standing in for a
+    /// Google Docs, Sheets, or Slides file that wasn't exported.
+    #[error("Not an exported document: {0}")]
+    NotExported(String),
+    /// Parsing a PDF examined more bytes than its scan budget allows
+    /// (`pdf_scan_budget_factor`), as crafted files can make it rescan the same bytes.
+    #[error("PDF parsing exceeded its scan budget of {budget} bytes ({pages_found} pages found before stopping)")]
+    ParseBudgetExceeded {
+        /// The budget in bytes.
+        budget: usize,
+        /// Pages counted before the budget ran out.
+        pages_found: usize,
+    },
+    /// The estimate passed its `deadline_ms` before it had counted anything
+    /// (see the `deadline` module).
+    #[error("Estimation stopped at its deadline of {deadline_ms} ms before anything was counted")]
+    DeadlineExceeded {
+        /// The deadline in milliseconds.
+        deadline_ms: u32,
+    },
+    /// A stored result passed to `parse_result` can't be read.
+    #[error("Invalid stored result: {0}")]
+    InvalidResult(String),
     /// A general error occurred during processing.
     #[error("General error: {0}")]
     General(String),
@@ -30,7 +111,7 @@ pub enum EstimatorError {
 ///
 /// This structure is used to describe the size of individual pages in documents,
 /// particularly useful for PDFs where each page can have different dimensions.
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PageSizeMm {
     /// The width of the page in millimeters.
     pub width_mm: f64,
@@ -38,21 +119,785 @@ pub struct PageSizeMm {
     pub height_mm: f64,
 }
 
+/// The physical dimensions of a page in PostScript points (1/72 inch).
+///
+/// Reported in `page_sizes_pt` next to `PageSizeMm`, for callers that lay out
+/// pages in CSS pixels and would otherwise convert millimeters back to points.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PageSizePt {
+    /// The width of the page in points.
+    pub width_pt: f64,
+    /// The height of the page in points.
+    pub height_pt: f64,
+}
+
+impl PageSizePt {
+    /// Converts a size in millimeters to points.
+    pub fn from_mm(size: &PageSizeMm) -> Self {
+        Self { width_pt: pt_from_mm(size.width_mm), height_pt: pt_from_mm(size.height_mm) }
+    }
+
+    /// Converts the size to millimeters with `mm_from_pt`.
+    pub fn to_mm(&self) -> PageSizeMm {
+        PageSizeMm { width_mm: mm_from_pt(self.width_pt), height_mm: mm_from_pt(self.height_pt) }
+    }
+}
+
+/// The physical dimensions of a page in inches.
+///
+/// Reported in `page_sizes_in` by `EstimateResult::convert_units`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PageSizeIn {
+    /// The width of the page in inches.
+    pub width_in: f64,
+    /// The height of the page in inches.
+    pub height_in: f64,
+}
+
+/// A unit of length for page sizes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    /// Millimeters.
+    Mm,
+    /// PostScript points (1/72 inch).
+    Pt,
+    /// Inches.
+    In,
+}
+
+/// Decimal places converted sizes are rounded to (see `EstimateResult::convert_units`).
+pub const UNIT_DECIMALS: i32 = 3;
+
+impl Unit {
+    /// Parses a unit name: `"mm"`, `"pt"`, or `"in"` (case-insensitive).
+    pub fn parse(name: &str) -> Option<Unit> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "mm" => Some(Unit::Mm),
+            "pt" => Some(Unit::Pt),
+            "in" => Some(Unit::In),
+            _ => None,
+        }
+    }
+
+    /// Converts a length in millimeters to this unit, rounded to `UNIT_DECIMALS` places.
+    pub fn from_mm(self, mm: f64) -> f64 {
+        let value = match self {
+            Unit::Mm => mm,
+            Unit::Pt => pt_from_mm(mm),
+            Unit::In => mm / 25.4,
+        };
+        let scale = 10f64.powi(UNIT_DECIMALS);
+        (value * scale).round() / scale
+    }
+}
+
+/// Dimensions closer than this (in millimeters) are considered the same page size.
+pub const PAGE_SIZE_TOLERANCE_MM: f64 = 0.5;
+
+impl PageSizeMm {
+    /// Checks whether two page sizes match within `PAGE_SIZE_TOLERANCE_MM`.
+    ///
+    /// Sizes converted from points or twips rarely compare equal exactly
+    /// (e.g. A4 is 595 × 842 pt, which is 209.9 × 297.0 mm).
+    pub fn approx_eq(&self, other: &PageSizeMm) -> bool {
+        (self.width_mm - other.width_mm).abs() <= PAGE_SIZE_TOLERANCE_MM
+            && (self.height_mm - other.height_mm).abs() <= PAGE_SIZE_TOLERANCE_MM
+    }
+
+    /// Classifies the page as portrait, landscape, or square.
+    ///
+    /// Pages whose sides differ by at most `SQUARE_PAGE_TOLERANCE` of the
+    /// longer side are square, so that sizes rounded from points or pixels
+    /// (e.g. 210 × 211 mm) aren't split between the other two.
+    pub fn orientation(&self) -> PageOrientation {
+        let longer = self.width_mm.max(self.height_mm);
+        if (self.width_mm - self.height_mm).abs() <= longer * SQUARE_PAGE_TOLERANCE {
+            PageOrientation::Square
+        } else if self.width_mm > self.height_mm {
+            PageOrientation::Landscape
+        } else {
+            PageOrientation::Portrait
+        }
+    }
+}
+
+/// Relative difference between width and height below which a page is square (2%).
+pub const SQUARE_PAGE_TOLERANCE: f64 = 0.02;
+
+/// Orientation of a page, as displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageOrientation {
+    Portrait,
+    Landscape,
+    Square,
+}
+
+/// Relative width of the page count range of heuristic estimates (±15%).
+pub const HEURISTIC_RANGE: f64 = 0.15;
+
+/// Version of the JSON shape of results, reports, and errors, written as their
+/// first key, `schema_version`.
+///
+/// Bumped whenever a serialized field is renamed, removed, or changes type or
+/// meaning; new optional fields don't bump it. Version 1 is every output
+/// written before the field existed.
+pub const SCHEMA_VERSION: u32 = 2;
+
 /// The result of a page count estimation operation.
 ///
 /// Contains the estimated page count, page dimensions, and any relevant notes
 /// about how the estimation was performed. This is the primary output structure
 /// returned to callers.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct EstimateResult {
     /// Estimated total page count for the document.
     pub page_count: usize,
+    /// Lower bound of the page count, set by heuristic estimators (text, Markdown,
+    /// source code, LaTeX, XLSX, and the DOCX content fallback). Absent when the
+    /// count is exact; see `page_count_range`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_count_min: Option<usize>,
+    /// Upper bound of the page count, set together with `page_count_min`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_count_max: Option<usize>,
     /// Per-page sizes when known (e.g., from PDF metadata).
     /// For non-PDF documents, this contains the inferred default size repeated for each page.
     pub page_sizes: Vec<PageSizeMm>,
+    /// Per-page sizes in points, with `include_point_sizes` set; one entry per
+    /// entry of `page_sizes`, which stays the authoritative size. PDF pages
+    /// report their `/MediaBox` as written, sides swapped for quarter-turned
+    /// pages (`page_sizes` is converted from these with `mm_from_pt`); other
+    /// formats convert `page_sizes` with `pt_from_mm`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_sizes_pt: Option<Vec<PageSizePt>>,
+    /// Per-page sizes in inches, set by `convert_units`; one entry per entry of
+    /// `page_sizes`, like `page_sizes_pt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_sizes_in: Option<Vec<PageSizeIn>>,
     /// Textual explanations and notes about the estimation process.
     /// May include information about the method used, assumptions made, or warnings.
     pub notes: Vec<String>,
+    /// Confidence in the estimate between 0.0 and 1.0, when the estimator can judge it.
+    /// Lowered when the result was recovered from a damaged or truncated file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Whether `page_count` is within `expected_tolerance` of `expected_pages`.
+    /// Only present when `expected_pages` was given (see the `verification` module).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches_expected: Option<bool>,
+    /// Bytes of the input the estimate read, counting bytes read more than
+    /// once each time; right to an order of magnitude (see the `stats` module).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_examined: Option<usize>,
+    /// Sheet and cost calculation for printing the document.
+    /// Only present when at least one printing option was supplied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_summary: Option<PrintSummary>,
+    /// Pages once oversized pages are split onto `split_paper`, with
+    /// `split_oversized_pages` (see `printing::apply_page_split`). Pages that
+    /// aren't oversized count as one. Absent without the option, or when the
+    /// size of some page is unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_page_count: Option<usize>,
+    /// The paper of the options, which `split_page_count` splits pages onto.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_paper: Option<PageSizeMm>,
+    /// Number of pages that use color (PDF only, when `analyze_color` is set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_pages: Option<usize>,
+    /// Number of pages printed in black and white (PDF only, when `analyze_color` is set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grayscale_pages: Option<usize>,
+    /// Per-page color flags in page order; `true` means the page uses color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_page_flags: Option<Vec<bool>>,
+    /// Number of pages that aren't blank (PDF only, when `exclude_blank_pages`
+    /// is set). `page_count` still includes the blank pages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_blank_pages: Option<usize>,
+    /// Number of pages taller than wide. PDF pages are measured as displayed,
+    /// with `/Rotate` applied; other formats count their single page size.
+    /// The three orientation counters sum to `page_count`, and are absent when
+    /// the size of some page is unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portrait_pages: Option<usize>,
+    /// Number of pages wider than tall (see `portrait_pages`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub landscape_pages: Option<usize>,
+    /// Number of square pages, within `SQUARE_PAGE_TOLERANCE` (see `portrait_pages`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub square_pages: Option<usize>,
+    /// Number of bookmarks (outline items) (PDF only, when `extended_pdf_info` is set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmark_count: Option<usize>,
+    /// Number of named destinations (PDF only, when `extended_pdf_info` is set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub named_destination_count: Option<usize>,
+    /// Number of attached files in the document's name tree (PDF only, when
+    /// `extended_pdf_info` is set). Files attached to annotations aren't included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment_count: Option<usize>,
+    /// Document properties such as `title`, `author`, `last_modified_by`, `created` and `modified`.
+    /// Dates are ISO 8601 strings as stored in the document. Absent when the document has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+    /// Byte offsets into the input where each estimated page begins (text and Markdown only,
+    /// when `return_boundaries` is set). Offsets always fall on UTF-8 character boundaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_boundaries: Option<Vec<usize>>,
+    /// Index of the paragraph each page starts in, counting body paragraphs
+    /// from 0 like python-docx's `Document.paragraphs` (DOCX only, when
+    /// `return_boundaries` is set). Taken from the page breaks Word recorded
+    /// when it last laid out the document; absent when it recorded none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_start_paragraphs: Option<Vec<usize>>,
+    /// Number of pages of `page_range` in the document, with the range clamped
+    /// to the last page. Only present when `page_range` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_page_count: Option<usize>,
+    /// Byte offset into the input where `page_range` begins (text and Markdown
+    /// only, like `page_boundaries`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_char_start: Option<usize>,
+    /// Byte offset into the input where `page_range` ends (exclusive), so the
+    /// range's text is `input[range_char_start..range_char_end]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_char_end: Option<usize>,
+    /// Short identifier of the estimation method used (e.g. `"code-lines"`), when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Line, word, and character counts of the text the estimate was based on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_metrics: Option<TextMetrics>,
+    /// ISO 639-1 code of the language of the text (e.g. `"de"`), with
+    /// `detect_language`. Absent when the text is too short or in no language
+    /// the classifier knows; its confidence is under `language` in `diagnostics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Approximate reading time in minutes, from the word count in `text_metrics`
+    /// at `reading_wpm`, plus 12 seconds per image and 20 per code block in
+    /// Markdown and HTML. Absent for formats without text metrics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reading_time_minutes: Option<f64>,
+    /// Per-sheet breakdown of a workbook estimate (XLSX only), in workbook order.
+    /// Always complete, even when per-sheet notes are trimmed by `verbosity`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sheets: Option<Vec<SheetEstimate>>,
+    /// Whether the document carries a digital signature (PDF and OOXML only).
+    /// Only the presence of a signature is detected; it is not validated.
+    #[serde(default)]
+    pub is_signed: bool,
+    /// Whether the document is encrypted (PDF only). Pages are counted either
+    /// way; `pdf_password` opens the strings and streams the estimate reads.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Whether the document links to content outside the file: pictures or OLE
+    /// objects linked rather than embedded, or other workbooks (DOCX, PPTX, and
+    /// XLSX only). Hyperlinks don't count. The first targets are listed in the notes.
+    #[serde(default)]
+    pub has_external_references: bool,
+    /// OOXML conformance class of the document, `"strict"` or `"transitional"`
+    /// (DOCX, PPTX, and XLSX only). Strict files use `purl.oclc.org` namespaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ooxml_conformance: Option<String>,
+    /// Application that wrote the document: the PDF `/Producer` (with the
+    /// `/Creator` in parentheses), or the OOXML `Application` and `AppVersion`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<String>,
+    /// The options the estimate was made with, with every default that applies
+    /// to the detected format filled in (`estimate_document` only). Options whose
+    /// default depends on the document itself, such as LaTeX `chars_per_page`
+    /// or XLSX row heights, stay `null`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_options: Option<EstimateOptions>,
+    /// Machine-readable details of how the estimate was made, with `debug` set:
+    /// every PDF strategy's count and timing, or the OOXML parts and metadata
+    /// fields found and the package's ZIP statistics. With `detect_language`,
+    /// `language` holds the guess and its confidence, `debug` or not. The layout
+    /// may change between versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<serde_json::Value>,
+}
+
+impl EstimateResult {
+    /// Builds a result whose pages all share one size.
+    ///
+    /// # Arguments
+    ///
+    /// * `page_count` - The estimated page count
+    /// * `size` - Width and height in millimeters repeated for every page, or
+    ///   `None` to leave `page_sizes` empty (see `EstimateOptions::wants_page_sizes`)
+    /// * `notes` - Notes explaining the estimate
+    pub fn uniform(page_count: usize, size: Option<(f64, f64)>, notes: Vec<String>) -> Self {
+        let page_sizes = match size {
+            Some((width_mm, height_mm)) => vec![PageSizeMm { width_mm, height_mm }; page_count],
+            None => Vec::new(),
+        };
+        let mut result = Self { page_count, ..Default::default() };
+        result.set_orientation_counts(&page_sizes);
+        Self { page_sizes, notes, ..result }
+    }
+
+    /// Sets `portrait_pages`, `landscape_pages`, and `square_pages` from the
+    /// size of each page.
+    ///
+    /// # Arguments
+    ///
+    /// * `sizes` - One size per page, in page order, as displayed
+    ///
+    /// The counters are cleared instead when `sizes` doesn't list exactly
+    /// `page_count` pages, so that they always sum to `page_count`.
+    pub fn set_orientation_counts(&mut self, sizes: &[PageSizeMm]) {
+        if sizes.len() != self.page_count {
+            (self.portrait_pages, self.landscape_pages, self.square_pages) = (None, None, None);
+            return;
+        }
+        let count = |orientation| sizes.iter().filter(|size| size.orientation() == orientation).count();
+        self.portrait_pages = Some(count(PageOrientation::Portrait));
+        self.landscape_pages = Some(count(PageOrientation::Landscape));
+        self.square_pages = Some(count(PageOrientation::Square));
+    }
+
+    /// Sets `page_count_min` and `page_count_max` to a band of ±`HEURISTIC_RANGE`
+    /// around `page_count`, for estimators that have no bounds of their own.
+    ///
+    /// The band is rounded outwards and never drops below 1 page for a non-empty
+    /// estimate, so `page_count_min ≤ page_count ≤ page_count_max` always holds.
+    pub fn with_heuristic_range(self) -> Self {
+        let pages = self.page_count as f64;
+        let min = (pages * (1.0 - HEURISTIC_RANGE)).floor() as usize;
+        let max = (pages * (1.0 + HEURISTIC_RANGE)).ceil() as usize;
+        let min = min.max(self.page_count.min(1));
+        self.with_range(min, max)
+    }
+
+    /// Sets `page_count_min` and `page_count_max`, widened where needed so that
+    /// they enclose `page_count`.
+    pub fn with_range(self, min: usize, max: usize) -> Self {
+        Self {
+            page_count_min: Some(min.min(self.page_count)),
+            page_count_max: Some(max.max(self.page_count)),
+            ..self
+        }
+    }
+
+    /// The range the page count lies in: `(page_count_min, page_count_max)`, or
+    /// `page_count` twice when the estimate is exact.
+    pub fn page_count_range(&self) -> (usize, usize) {
+        (
+            self.page_count_min.unwrap_or(self.page_count),
+            self.page_count_max.unwrap_or(self.page_count),
+        )
+    }
+
+    /// Checks the invariants consumers of a result rely on.
+    ///
+    /// - `page_sizes` is empty or has exactly `page_count` entries, so it can
+    ///   be indexed by page number; `page_sizes_pt` and `page_sizes_in` have
+    ///   one entry per entry of `page_sizes`
+    /// - every page dimension is finite and positive
+    /// - `page_count_min ≤ page_count ≤ page_count_max` when a range is set
+    /// - `confidence` is within 0–1, and so is that of `text_metrics`
+    /// - `split_page_count` is at least `page_count`, every page counting once at least
+    ///
+    /// `estimate_bytes` asserts them in debug builds.
+    ///
+    /// # Errors
+    ///
+    /// A description of the first invariant that doesn't hold.
+    pub fn validate_invariants(&self) -> Result<(), String> {
+        if !self.page_sizes.is_empty() && self.page_sizes.len() != self.page_count {
+            return Err(format!("{} page sizes for {} pages", self.page_sizes.len(), self.page_count));
+        }
+        if let Some(sizes) = &self.page_sizes_pt
+            && sizes.len() != self.page_sizes.len()
+        {
+            return Err(format!("{} point sizes for {} page sizes", sizes.len(), self.page_sizes.len()));
+        }
+        if let Some(sizes) = &self.page_sizes_in
+            && sizes.len() != self.page_sizes.len()
+        {
+            return Err(format!("{} inch sizes for {} page sizes", sizes.len(), self.page_sizes.len()));
+        }
+        let valid = |value: f64| value.is_finite() && value > 0.0;
+        if let Some((page, size)) =
+            self.page_sizes.iter().enumerate().find(|(_, size)| !valid(size.width_mm) || !valid(size.height_mm))
+        {
+            return Err(format!("page {} has an invalid size {} × {} mm", page + 1, size.width_mm, size.height_mm));
+        }
+        let (min, max) = self.page_count_range();
+        if !(min <= self.page_count && self.page_count <= max) {
+            return Err(format!("page count {} is outside its range {}–{}", self.page_count, min, max));
+        }
+        if let Some(confidence) = self.confidence
+            && !(0.0..=1.0).contains(&confidence)
+        {
+            return Err(format!("confidence {} is outside 0–1", confidence));
+        }
+        if let Some(confidence) = self.text_metrics.as_ref().and_then(|metrics| metrics.confidence)
+            && !(0.0..=1.0).contains(&confidence)
+        {
+            return Err(format!("text metrics confidence {} is outside 0–1", confidence));
+        }
+        if let Some(split) = self.split_page_count
+            && split < self.page_count
+        {
+            return Err(format!("split page count {} is below the page count {}", split, self.page_count));
+        }
+        Ok(())
+    }
+
+    /// Formats the result as a single line for logs.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The detected format, which leads the line (e.g. `"pdf"`)
+    ///
+    /// # Returns
+    ///
+    /// A line such as `pdf pages=42 range=36-49 size=210x297mm method=code-lines
+    /// confidence=0.98 warnings=0`: the format, then `key=value` fields
+    /// separated by single spaces, in this order:
+    /// - `pages`: the page count
+    /// - `range`: `page_count_min-page_count_max`, when the estimate has a range
+    /// - `size`: page size in whole millimeters when every page has the same
+    ///   size, `mixed` otherwise; omitted when `page_sizes` is empty
+    /// - `method`: the estimation method, when set
+    /// - `confidence`: with two decimals, when set
+    /// - `warnings`: the number of warning notes (`⚠` or `Note:`)
+    ///
+    /// # Notes
+    ///
+    /// - The format is stable: fields are never renamed, reordered, or removed,
+    ///   and new fields are only appended, so log parsers can rely on it
+    /// - Whitespace inside values is replaced with `_`, so the line never holds
+    ///   a newline and splits cleanly on spaces
+    pub fn summary(&self, format: &str) -> String {
+        let token = |value: &str| value.split_whitespace().collect::<Vec<_>>().join("_");
+        let mut line = format!("{} pages={}", token(format), self.page_count);
+        if self.page_count_min.is_some() || self.page_count_max.is_some() {
+            let (min, max) = self.page_count_range();
+            line.push_str(&format!(" range={}-{}", min, max));
+        }
+        if let Some(first) = self.page_sizes.first() {
+            if self.page_sizes.iter().all(|size| size == first) {
+                line.push_str(&format!(" size={}x{}mm", first.width_mm.round(), first.height_mm.round()));
+            } else {
+                line.push_str(" size=mixed");
+            }
+        }
+        if let Some(method) = &self.method {
+            line.push_str(&format!(" method={}", token(method)));
+        }
+        if let Some(confidence) = self.confidence {
+            line.push_str(&format!(" confidence={:.2}", confidence));
+        }
+        let warnings = self.notes.iter().filter(|note| is_warning(note)).count();
+        line.push_str(&format!(" warnings={}", warnings));
+        line
+    }
+
+    /// Combines the results of several documents into one.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - The results to combine, in the order their pages should appear
+    ///
+    /// # Returns
+    ///
+    /// A result whose pages are those of `results` in order:
+    /// - `page_count` is the sum, and `page_sizes` the concatenation, of the inputs;
+    ///   `page_sizes` is left empty unless every input lists a size for each of its pages
+    /// - `page_count_min` and `page_count_max` are the sums of the inputs' ranges,
+    ///   present only when some input has one
+    /// - `notes` are kept in order, each prefixed with its input's 1-based index (`[2] …`)
+    /// - `confidence` is the lowest confidence reported, and `text_metrics` the sum
+    ///   of the metrics reported (with the lowest of their confidences, and no
+    ///   `words_per_page`)
+    /// - `is_signed`, `encrypted`, and `has_external_references` are set when any input sets them
+    /// - `color_pages`, `grayscale_pages`, `color_page_flags`, `non_blank_pages`, `page_sizes_pt`,
+    ///   `page_sizes_in`, the orientation counters, `bookmark_count`, `named_destination_count`, `attachment_count`,
+    ///   `reading_time_minutes`, and `split_page_count` are combined only when every input has them,
+    ///   and `method`, `language`, and `split_paper` are kept only when all inputs agree
+    ///
+    /// # Notes
+    ///
+    /// - `metadata`, `page_boundaries`, `page_start_paragraphs`, the `page_range` fields, `sheets`, `print_summary`,
+    ///   `ooxml_conformance`, `producer`, and `effective_options` describe a single estimate and are
+    ///   dropped; apply the print summary to the merged result
+    /// - An empty slice yields an empty result (0 pages, no notes)
+    pub fn merge(results: &[EstimateResult]) -> EstimateResult {
+        let mut merged = EstimateResult::default();
+        for (i, result) in results.iter().enumerate() {
+            merged.page_count += result.page_count;
+            merged.is_signed |= result.is_signed;
+            merged.encrypted |= result.encrypted;
+            merged.has_external_references |= result.has_external_references;
+            merged.page_sizes.extend_from_slice(&result.page_sizes);
+            merged.notes.extend(result.notes.iter().map(|note| format!("[{}] {}", i + 1, note)));
+            if let Some(confidence) = result.confidence {
+                merged.confidence = Some(merged.confidence.map_or(confidence, |c: f64| c.min(confidence)));
+            }
+            if let Some(metrics) = &result.text_metrics {
+                let sum = merged.text_metrics.get_or_insert_with(TextMetrics::default);
+                sum.line_count += metrics.line_count;
+                sum.word_count += metrics.word_count;
+                sum.char_count += metrics.char_count;
+                if let Some(records) = metrics.record_count {
+                    *sum.record_count.get_or_insert(0) += records;
+                }
+                if let Some(confidence) = metrics.confidence {
+                    sum.confidence = Some(sum.confidence.map_or(confidence, |c: f64| c.min(confidence)));
+                }
+            }
+        }
+
+        if results.iter().any(|r| r.page_count_min.is_some() || r.page_count_max.is_some()) {
+            let ranges = results.iter().map(EstimateResult::page_count_range);
+            merged.page_count_min = Some(ranges.clone().map(|(min, _)| min).sum());
+            merged.page_count_max = Some(ranges.map(|(_, max)| max).sum());
+        }
+        if !results.is_empty() {
+            merged.color_pages = results.iter().map(|r| r.color_pages).sum();
+            merged.grayscale_pages = results.iter().map(|r| r.grayscale_pages).sum();
+            merged.non_blank_pages = results.iter().map(|r| r.non_blank_pages).sum();
+            merged.portrait_pages = results.iter().map(|r| r.portrait_pages).sum();
+            merged.landscape_pages = results.iter().map(|r| r.landscape_pages).sum();
+            merged.square_pages = results.iter().map(|r| r.square_pages).sum();
+            merged.bookmark_count = results.iter().map(|r| r.bookmark_count).sum();
+            merged.named_destination_count = results.iter().map(|r| r.named_destination_count).sum();
+            merged.reading_time_minutes = results.iter().map(|r| r.reading_time_minutes).sum();
+            merged.attachment_count = results.iter().map(|r| r.attachment_count).sum();
+            merged.split_page_count = results.iter().map(|r| r.split_page_count).sum();
+            merged.color_page_flags = results
+                .iter()
+                .map(|r| r.color_page_flags.clone())
+                .collect::<Option<Vec<_>>>()
+                .map(|flags| flags.concat());
+            merged.page_sizes_pt = results
+                .iter()
+                .map(|r| r.page_sizes_pt.clone())
+                .collect::<Option<Vec<_>>>()
+                .map(|sizes| sizes.concat());
+            merged.page_sizes_in = results
+                .iter()
+                .map(|r| r.page_sizes_in.clone())
+                .collect::<Option<Vec<_>>>()
+                .map(|sizes| sizes.concat());
+            // sizes from only some of the inputs can't be indexed by page number
+            if merged.page_sizes.len() != merged.page_count {
+                merged.page_sizes.clear();
+                merged.page_sizes_pt = None;
+                merged.page_sizes_in = None;
+            }
+            let method = &results[0].method;
+            if results.iter().all(|r| r.method == *method) {
+                merged.method = method.clone();
+            }
+            let language = &results[0].language;
+            if results.iter().all(|r| r.language == *language) {
+                merged.language = language.clone();
+            }
+            let split_paper = &results[0].split_paper;
+            if results.iter().all(|r| r.split_paper == *split_paper) {
+                merged.split_paper = split_paper.clone();
+            }
+        }
+        merged
+    }
+
+    /// Reports the page sizes of a result in another unit, without estimating again.
+    ///
+    /// `page_sizes` stays in millimeters, as it's the authoritative size; the
+    /// result gains the sizes in `to` next to it:
+    ///
+    /// - `Unit::Mm`: no other unit; `page_sizes_pt` and `page_sizes_in` are removed
+    /// - `Unit::Pt`: `page_sizes_pt`, kept when the estimate reported it (PDF
+    ///   `/MediaBox` sizes) and converted from `page_sizes` otherwise
+    /// - `Unit::In`: `page_sizes_in`, converted from `page_sizes`
+    ///
+    /// # Returns
+    ///
+    /// The converted result. Converted sizes are rounded to `UNIT_DECIMALS`
+    /// (3) decimal places. Since they're always derived from `page_sizes`,
+    /// converting again, to the same or another unit, loses nothing.
+    ///
+    /// # Notes
+    ///
+    /// Lengths in `effective_options` (`custom_paper_mm`, `xlsx_margins_mm`,
+    /// `image_height_mm`) are named for their unit and stay in millimeters.
+    pub fn convert_units(&self, to: Unit) -> EstimateResult {
+        let mut converted = self.clone();
+        let sizes = |unit: Unit| {
+            self.page_sizes.iter().map(move |size| (unit.from_mm(size.width_mm), unit.from_mm(size.height_mm)))
+        };
+        converted.page_sizes_pt = match to {
+            Unit::Pt => Some(self.page_sizes_pt.clone().unwrap_or_else(|| {
+                sizes(Unit::Pt).map(|(width_pt, height_pt)| PageSizePt { width_pt, height_pt }).collect()
+            })),
+            Unit::Mm | Unit::In => None,
+        };
+        converted.page_sizes_in = match to {
+            Unit::In => Some(sizes(Unit::In).map(|(width_in, height_in)| PageSizeIn { width_in, height_in }).collect()),
+            Unit::Mm | Unit::Pt => None,
+        };
+        converted
+    }
+}
+
+/// Page estimate for one worksheet of a workbook.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SheetEstimate {
+    /// Name of the sheet.
+    pub name: String,
+    /// The sheet's `sheetId` in `xl/workbook.xml`, which stays with it when it's
+    /// renamed or moved. Sheets are listed in tab order, not by this id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sheet_id: Option<u32>,
+    /// Rows up to and including the last non-empty row.
+    pub rows: usize,
+    /// Columns up to and including the last non-empty column.
+    #[serde(default)]
+    pub columns: usize,
+    /// Estimated pages for the sheet: `fitted_pages` when the sheet is set to
+    /// fit to page, `natural_pages` otherwise.
+    pub pages: usize,
+    /// Pages at 100% scale, from the rows (and the columns when
+    /// `columns_per_page` is set).
+    #[serde(default)]
+    pub natural_pages: usize,
+    /// Pages when the sheet is scaled to fit `fitToWidth` × `fitToHeight` pages;
+    /// `None` when fit to page is off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fitted_pages: Option<usize>,
+    /// Print scale in percent implied by fit to page (10–100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale_percent: Option<u32>,
+    /// Whether the sheet could be read; unreadable sheets count as 0 rows.
+    pub readable: bool,
+    /// Whether the sheet is hidden (or very hidden) in the workbook.
+    #[serde(default)]
+    pub hidden: bool,
+    /// Whether the sheet was left out of the estimate, being hidden or not
+    /// selected by `xlsx_sheets` / `xlsx_sheet_indices`; it then counts as 0 pages.
+    #[serde(default)]
+    pub skipped: bool,
+    /// Whether `pages` was raised to `xlsx_min_pages_per_visible_sheet`, the
+    /// sheet's content filling fewer pages (none, when it holds only formatting).
+    #[serde(default)]
+    pub floored: bool,
+    /// Paper the sheet was paginated on, `(width, height)` in millimeters: its
+    /// `xlsx_sheet_paper` entry, or the paper of the options. Absent for
+    /// skipped and unreadable sheets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_mm: Option<(f64, f64)>,
+}
+
+impl SheetEstimate {
+    /// A readable, visible sheet with no rows and no pages.
+    pub fn empty(name: String) -> Self {
+        Self {
+            name,
+            sheet_id: None,
+            rows: 0,
+            columns: 0,
+            pages: 0,
+            natural_pages: 0,
+            fitted_pages: None,
+            scale_percent: None,
+            readable: true,
+            hidden: false,
+            skipped: false,
+            floored: false,
+            paper_mm: None,
+        }
+    }
+}
+
+/// Counts describing the text content of a document.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct TextMetrics {
+    /// Number of source lines.
+    pub line_count: usize,
+    /// Number of whitespace-separated words.
+    pub word_count: usize,
+    /// Number of Unicode characters.
+    pub char_count: usize,
+    /// Number of records of a contact or calendar file (contacts and events).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_count: Option<usize>,
+    /// Average words per page, for text extracted from a PDF.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words_per_page: Option<f64>,
+    /// How far the counts can be trusted, from 0 to 1, when they are
+    /// approximated (text extracted from a PDF). Independent of the
+    /// result's `confidence`, which is about the page count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+/// Printing calculation derived from the final page count of a document.
+///
+/// The arithmetic is identical for every format because it only depends on
+/// the estimated page count and the printing options. Prices are unit-less;
+/// the caller decides the currency.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PrintSummary {
+    /// Whether both sides of each sheet are printed.
+    pub duplex: bool,
+    /// Number of pages placed on each side of a sheet (n-up printing: 1, 2 or 4).
+    pub pages_per_sheet: u8,
+    /// Physical sheets of paper required.
+    pub sheets_of_paper: usize,
+    /// Estimated printing cost, when a price per page was supplied.
+    pub estimated_cost: Option<f64>,
+    /// Human-readable formulas used to compute the sheet count and cost.
+    pub formula: Vec<String>,
+}
+
+/// The outcome of estimating a single entry of an archive.
+///
+/// Exactly one of `result` or `error` is present.
+#[derive(Serialize, Deserialize)]
+pub struct ArchiveEntryResult {
+    /// Path of the entry inside the archive (nested archives are prefixed with their own path).
+    pub path: String,
+    /// The estimate for this entry when it succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<EstimateResult>,
+    /// The error message when this entry could not be estimated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Stable machine-readable code for `error` (see `error_code`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+}
+
+/// Per-entry results for a container holding several documents.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ArchiveEstimate {
+    /// Results for each estimated entry, in archive order.
+    pub entries: Vec<ArchiveEntryResult>,
+    /// Sum of the page counts of all successfully estimated entries.
+    pub total_page_count: usize,
+    /// Notes about skipped entries and other archive-level observations.
+    pub notes: Vec<String>,
+}
+
+/// The outcome of a structural validation of a document.
+///
+/// Produced without estimating page counts, so it is cheap even for large files.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ValidationReport {
+    /// Detected document format (same values as the estimators report).
+    pub format: String,
+    /// Whether the document's structure is intact for its format.
+    pub valid: bool,
+    /// Whether the document is password-protected or encrypted.
+    pub encrypted: bool,
+    /// Structural problems that were found; empty for a valid document.
+    pub issues: Vec<String>,
 }
 
 /// Configuration options for customizing page count estimation behavior.
@@ -77,22 +922,287 @@ pub struct EstimateResult {
 /// ```json
 /// { "custom_paper_mm": [210.0, 297.0] }
 /// ```
-#[derive(Serialize, Deserialize)]
+///
+/// Choosing the paper from the user's locale:
+/// ```json
+/// { "locale": "en-US" }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct EstimateOptions {
     /// Default page size to assume for non-PDF documents.
     /// Supported values: "A4" (210×297mm) or "Letter" (215.9×279.4mm).
     /// Defaults to "A4" if not specified.
+    #[schemars(extend("enum" = ["A4", "Letter", "letter", null], "default" = "A4"))]
     pub default_paper: Option<String>,
     /// Custom paper size in millimeters as a tuple (width, height).
     /// When provided, this takes precedence over `default_paper`.
+    #[schemars(transform = positive_pair)]
     pub custom_paper_mm: Option<(f64, f64)>,
+    /// BCP-47 locale of the user (e.g. "en-US", "de-DE").
+    /// When neither `default_paper` nor `custom_paper_mm` is set, Letter is used for
+    /// US, CA, MX and PH regions and A4 otherwise.
+    pub locale: Option<String>,
     /// Characters per page heuristic for text-based documents.
     /// Overrides the default heuristic when provided.
     /// Useful for documents with known formatting or character density.
+    #[schemars(range(min = 1), extend("default" = 1800))]
     pub chars_per_page: Option<usize>,
+    /// Characters per printed line for text-based documents.
+    /// When set, text is paginated line by line (wrapping long lines) instead of
+    /// by total character count.
+    #[schemars(range(min = 1))]
+    pub chars_per_line: Option<usize>,
+    /// Display lines per page in line-based text pagination. Defaults to 50.
+    #[schemars(range(min = 1), extend("default" = 50))]
+    pub lines_per_page: Option<usize>,
+    /// Tab stop interval in columns for line-based text pagination. Defaults to 8.
+    #[schemars(extend("default" = 8))]
+    pub tab_width: Option<u8>,
+    /// Line spacing for line-based text pagination: 1.0 single, 1.5, or 2.0 double.
+    /// Lines per page are divided by this factor. Defaults to 1.0.
+    #[schemars(extend("exclusiveMinimum" = 0, "default" = 1.0))]
+    pub line_spacing: Option<f32>,
+    /// Extra space in lines before each blank-line-separated paragraph
+    /// in line-based text pagination. Defaults to 0.
+    #[schemars(extend("default" = 0))]
+    pub paragraph_spacing_lines: Option<f32>,
+    /// Leave blank lines out when paginating source code. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub skip_blank_lines: Option<bool>,
+    /// Lay out minified JSON and XML data files as if pretty-printed with
+    /// 2-space indentation, instead of as the few long lines they are written
+    /// on. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub pretty_print_minified: Option<bool>,
+    /// How heuristic estimates (text, Markdown, source code, data files, XLSX, and the DOCX
+    /// content fallback) round a partly filled last page: `"ceil"` counts it,
+    /// `"nearest"` counts it when at least half full, and `"floor"` drops it.
+    /// Non-empty content is always at least 1 page. Defaults to `"ceil"`.
+    #[schemars(extend("enum" = ["ceil", "nearest", "floor", null], "default" = "ceil"))]
+    pub rounding: Option<String>,
     /// Rows per page for spreadsheet documents.
     /// Used to estimate how many pages a spreadsheet would occupy when printed.
+    /// When unset, rows are paginated by the row heights stored in each sheet,
+    /// and this default applies to sheets that store none.
+    #[schemars(range(min = 1), extend("default" = 40))]
     pub rows_per_page: Option<usize>,
+    /// Columns per page across for spreadsheet documents.
+    /// Used for the width of sheets that are fit to page; when unset, every
+    /// sheet is taken to be one page wide.
+    #[schemars(range(min = 1))]
+    pub columns_per_page: Option<usize>,
+    /// Top and bottom page margins in millimeters as a tuple (top, bottom) for
+    /// paginating spreadsheet rows by height. Defaults to each sheet's own
+    /// margins, or 19.05 mm (0.75") when it declares none.
+    #[schemars(transform = non_negative_pair)]
+    pub xlsx_margins_mm: Option<(f64, f64)>,
+    /// Names of the workbook sheets to estimate, matched case-insensitively.
+    /// Other sheets are skipped; names that don't exist are reported in the notes.
+    /// Combined with `xlsx_sheet_indices`, the sheets listed in either are estimated.
+    /// A selected sheet is estimated even when hidden. An empty list selects all
+    /// visible sheets, as if the option were absent.
+    pub xlsx_sheets: Option<Vec<String>>,
+    /// 0-based positions of the workbook sheets to estimate (see `xlsx_sheets`).
+    pub xlsx_sheet_indices: Option<Vec<usize>>,
+    /// Paper of particular sheets, by sheet name matched case-insensitively: a
+    /// paper name such as `"A3"` or `"Letter"`, optionally followed by
+    /// `"landscape"`, or a size in millimeters such as `"297x420"` (see
+    /// `file_utils::parse_paper`). Those sheets are paginated on it instead of
+    /// the paper of the other options, and without an explicit `rows_per_page`
+    /// their rows per page scale with its height. Names that don't exist are
+    /// reported in the notes.
+    #[schemars(transform = paper_map)]
+    pub xlsx_sheet_paper: Option<BTreeMap<String, String>>,
+    /// Fewest pages each estimated XLSX sheet counts for, even when empty or
+    /// formatting only, as Excel prints a page for many such sheets. Applies
+    /// only to the sheets estimated: hidden and unselected sheets stay at 0
+    /// pages. Defaults to 0, so empty sheets count no pages.
+    #[schemars(extend("default" = 0))]
+    pub xlsx_min_pages_per_visible_sheet: Option<usize>,
+    /// Print on both sides of each sheet when calculating `sheets_of_paper`.
+    /// Defaults to `false` (single-sided).
+    #[schemars(extend("default" = false))]
+    pub duplex: Option<bool>,
+    /// Number of pages printed on each side of a sheet (n-up printing).
+    /// Supported values: 1, 2 or 4. Defaults to 1.
+    #[schemars(extend("enum" = [1, 2, 4, null], "default" = 1))]
+    pub pages_per_sheet: Option<u8>,
+    /// Price of printing a single page, used to compute `estimated_cost`.
+    pub price_per_page: Option<f64>,
+    /// Price of printing a single color page.
+    /// Applied to color pages when the estimator can tell them apart.
+    pub price_per_color_page: Option<f64>,
+    /// Detect color vs black-and-white pages in PDFs.
+    /// Expensive, since every page's content stream is decoded. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub analyze_color: Option<bool>,
+    /// Count the pages of PDFs that aren't effectively blank in `non_blank_pages`
+    /// (see the `pdf_blank` module). Pages holding only an annotation, such as
+    /// a stamp, count as blank. Expensive, since every page's content stream is
+    /// decoded. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub exclude_blank_pages: Option<bool>,
+    /// Estimate files embedded in a PDF (portfolio documents and attachments),
+    /// and PDF, DOCX, XLSX, and PPTX objects embedded in a DOCX estimated from
+    /// its content, and add their pages to the count. Embedded files larger than
+    /// `archive_max_entry_bytes` are skipped. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub estimate_attachments: Option<bool>,
+    /// Pages counted for each object embedded in a DOCX (`<w:object>`: Visio
+    /// drawings, spreadsheets, PDFs, ...) that isn't estimated itself, when the
+    /// DOCX is estimated from its content. At most 100; defaults to 0.5.
+    #[schemars(range(min = 0, max = 100), extend("default" = 0.5))]
+    pub docx_object_page_equivalent: Option<f64>,
+    /// Lines of space added for each display equation (`<m:oMathPara>`) on
+    /// top of the paragraph holding it, when a DOCX is estimated from its
+    /// content. Equations have almost no text to count. At most 50 (a page);
+    /// defaults to 2.
+    #[schemars(range(min = 0, max = 50), extend("default" = 2.0))]
+    pub docx_display_equation_lines: Option<f64>,
+    /// Lines of space added for each inline equation (`<m:oMath>` within
+    /// text), when a DOCX is estimated from its content. At most 50 (a page);
+    /// defaults to 0.5.
+    #[schemars(range(min = 0, max = 50), extend("default" = 0.5))]
+    pub docx_inline_equation_lines: Option<f64>,
+    /// Printed height in millimeters of each image output (plots, rendered
+    /// figures) of a Jupyter notebook, whatever its pixel size. Defaults to 90.
+    #[schemars(range(min = 0), extend("default" = 90.0))]
+    pub image_height_mm: Option<f64>,
+    /// Contacts printed per page when a vCard file is estimated as a
+    /// listing. Defaults to 8.
+    #[schemars(range(min = 1), extend("default" = 8))]
+    pub contacts_per_page: Option<usize>,
+    /// Events printed per page when an iCalendar file is estimated as a
+    /// listing. Defaults to 12.
+    #[schemars(range(min = 1), extend("default" = 12))]
+    pub events_per_page: Option<usize>,
+    /// Report the bookmark, named destination, and attachment counts of PDFs
+    /// (`bookmark_count`, `named_destination_count`, `attachment_count`).
+    /// Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub extended_pdf_info: Option<bool>,
+    /// Maximum uncompressed size in bytes of an archive entry to estimate.
+    /// Larger entries are skipped with a note. Defaults to 100 MB.
+    #[schemars(extend("default" = 104857600))]
+    pub archive_max_entry_bytes: Option<usize>,
+    /// Estimate ZIP archives nested inside an archive (one level deep).
+    /// Defaults to `false`, in which case nested archives are skipped with a note.
+    #[schemars(extend("default" = false))]
+    pub archive_recurse_nested: Option<bool>,
+    /// Estimate the entries operating systems add to archives (`__MACOSX/`,
+    /// `._` files, `.DS_Store`, `Thumbs.db`, `desktop.ini`) in
+    /// `estimate_archive`, instead of skipping them with a note. Directory
+    /// entries are never estimated. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub archive_include_system_entries: Option<bool>,
+    /// Only validate the document's structure instead of estimating it.
+    /// The response is then a `ValidationReport`. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub validate_only: Option<bool>,
+    /// Report the byte offset where each page starts for text and Markdown
+    /// documents, and the paragraph each page starts in for DOCX documents
+    /// Word has laid out (`page_start_paragraphs`). Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub return_boundaries: Option<bool>,
+    /// Pages to estimate, as 1-based first and last page (e.g. `[5, 10]` for
+    /// "pages 5–10"). The result then carries `range_page_count`, with the range
+    /// clamped to the last page, and for text and Markdown the byte span of those
+    /// pages (`range_char_start`, `range_char_end`). A range starting past the
+    /// last page is an error.
+    #[schemars(transform = page_pair)]
+    pub page_range: Option<(usize, usize)>,
+    /// Count the words and characters of PPTX slides and their speaker notes, or
+    /// of the DOCX body, in `text_metrics`, whatever is printed. Reads every slide
+    /// and notes part (or the whole DOCX body, when the page count comes from
+    /// metadata), so it's off by default. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub extract_text_metrics: Option<bool>,
+    /// Count the words and characters of PDFs in `text_metrics`, by decoding
+    /// the text shown by every page's content streams. Approximate (see the
+    /// `pdf_text` module) and slow on large files, so it's off by default.
+    /// Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub extract_pdf_text_metrics: Option<bool>,
+    /// Guess the language of the text of plain text, Markdown, HTML (MHT), and
+    /// DOCX documents, and of PDFs read for `extract_pdf_text_metrics`, into
+    /// `language` (see the `language` module). Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub detect_language: Option<bool>,
+    /// Words a text needs for `detect_language` to guess its language; shorter
+    /// texts report none rather than a guess. Defaults to 20.
+    #[schemars(range(min = 1), extend("default" = 20))]
+    pub language_min_words: Option<usize>,
+    /// Words read per minute for `reading_time_minutes`. Defaults to 200.
+    #[schemars(range(min = 1), extend("default" = 200))]
+    pub reading_wpm: Option<usize>,
+    /// Amount of detail in `notes`: 0 for warnings only, 1 for a summary, and
+    /// 2 for full per-sheet and per-strategy detail. Each level caps the number
+    /// of notes and collapses the rest into "… and N more". Defaults to 1.
+    #[schemars(range(max = 2), extend("default" = 1))]
+    pub verbosity: Option<u8>,
+    /// Attach `diagnostics` to the result: for PDFs every page counting
+    /// strategy is run and timed, not only the ones needed. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub debug: Option<bool>,
+    /// Report per-page dimensions in `page_sizes`. When `false`, dimension lookup
+    /// is skipped and `page_sizes` is an empty array; useful when only the count
+    /// is needed. Defaults to `true`.
+    #[schemars(extend("default" = true))]
+    pub include_page_sizes: Option<bool>,
+    /// Also report per-page sizes in points in `page_sizes_pt`, e.g. for viewers
+    /// that lay out pages in CSS pixels. `page_sizes` is reported in millimeters
+    /// either way. Has no effect when `include_page_sizes` is off. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub include_point_sizes: Option<bool>,
+    /// Also count the pages as printed on the paper of the options, in
+    /// `split_page_count`: a page taller than `oversized_page_ratio` paper
+    /// heights, such as a till receipt or a web page saved as one long page,
+    /// counts as the paper heights it spans. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub split_oversized_pages: Option<bool>,
+    /// Paper heights a page must exceed to be split by `split_oversized_pages`.
+    /// At least 1. Defaults to 1.5.
+    #[schemars(range(min = 1), extend("default" = 1.5))]
+    pub oversized_page_ratio: Option<f64>,
+    /// Maximum size in bytes of an input to estimate. Larger inputs (or downloads
+    /// announcing a larger `Content-Length`) fail with `too_large`. Unlimited by default.
+    pub max_input_bytes: Option<usize>,
+    /// Estimate short one-line inputs that look like a URL or a file path as
+    /// text. Such inputs are otherwise rejected with `invalid_input`, as they're
+    /// usually a locator passed instead of the file's bytes. Defaults to `false`.
+    #[schemars(extend("default" = false))]
+    pub allow_tiny_text: Option<bool>,
+    /// Password of an encrypted PDF, user or owner. Pages are counted without
+    /// it; with it, the producer and the text (`extract_pdf_text_metrics`) of
+    /// the document can be read as well. A password that doesn't open the file
+    /// fails with `wrong_password`. Never echoed in `effective_options`.
+    pub pdf_password: Option<String>,
+    /// Bytes the PDF page count may examine, as a multiple of the input size.
+    /// A file that needs more, such as one crafted to be rescanned over and
+    /// over, fails with `parse_budget_exceeded`. At least 1. Defaults to 8.
+    #[schemars(range(min = 1), extend("default" = 8))]
+    pub pdf_scan_budget_factor: Option<usize>,
+    /// A page count to check the estimate against, such as one stored by
+    /// another system. The result gets `matches_expected`, and on a mismatch
+    /// every counting method of the format is run and reported in
+    /// `diagnostics.verification` (see the `verification` module).
+    pub expected_pages: Option<usize>,
+    /// How far the estimate may be from `expected_pages` and still match, as a
+    /// fraction of `expected_pages` (0.05 for 5%). Defaults to 0, an exact match.
+    #[schemars(range(min = 0), extend("default" = 0.0))]
+    pub expected_tolerance: Option<f64>,
+    /// Milliseconds the estimate may take. When they run out, PDFs and
+    /// workbooks stop with the pages counted so far, flagged with a
+    /// `⚠ deadline_exceeded` warning and a lowered `confidence`, or fail with
+    /// `deadline_exceeded` when nothing was counted (see the `deadline`
+    /// module). At least 1. Unlimited by default.
+    #[schemars(range(min = 1))]
+    pub deadline_ms: Option<u32>,
+    /// MIME type of the input, such as a browser's `File.type` or a response's
+    /// `Content-Type`. It takes precedence over the filename, but not over
+    /// magic bytes that identify the format for certain; types no format
+    /// claims are ignored (see `detect_format`).
+    pub mime_hint: Option<String>,
 }
 
 impl Default for EstimateOptions {
@@ -100,8 +1210,477 @@ impl Default for EstimateOptions {
         Self {
             default_paper: Some("A4".into()),
             custom_paper_mm: None,
+            locale: None,
             chars_per_page: None,
+            chars_per_line: None,
+            lines_per_page: None,
+            tab_width: None,
+            line_spacing: None,
+            paragraph_spacing_lines: None,
+            skip_blank_lines: None,
+            pretty_print_minified: None,
+            rounding: None,
             rows_per_page: None,
+            columns_per_page: None,
+            xlsx_margins_mm: None,
+            xlsx_sheets: None,
+            xlsx_sheet_paper: None,
+            xlsx_sheet_indices: None,
+            xlsx_min_pages_per_visible_sheet: None,
+            duplex: None,
+            pages_per_sheet: None,
+            price_per_page: None,
+            price_per_color_page: None,
+            analyze_color: None,
+            exclude_blank_pages: None,
+            estimate_attachments: None,
+            docx_object_page_equivalent: None,
+            docx_display_equation_lines: None,
+            docx_inline_equation_lines: None,
+            image_height_mm: None,
+            contacts_per_page: None,
+            events_per_page: None,
+            extended_pdf_info: None,
+            archive_max_entry_bytes: None,
+            archive_recurse_nested: None,
+            archive_include_system_entries: None,
+            validate_only: None,
+            return_boundaries: None,
+            page_range: None,
+            extract_text_metrics: None,
+            extract_pdf_text_metrics: None,
+            detect_language: None,
+            language_min_words: None,
+            reading_wpm: None,
+            verbosity: None,
+            debug: None,
+            include_page_sizes: None,
+            include_point_sizes: None,
+            split_oversized_pages: None,
+            oversized_page_ratio: None,
+            max_input_bytes: None,
+            allow_tiny_text: None,
+            pdf_password: None,
+            pdf_scan_budget_factor: None,
+            expected_pages: None,
+            expected_tolerance: None,
+            deadline_ms: None,
+            mime_hint: None,
+        }
+    }
+}
+
+impl EstimateOptions {
+    /// Resolves the paper size for non-PDF documents, in millimeters.
+    ///
+    /// Precedence: `custom_paper_mm`, then `default_paper` ("Letter" or A4),
+    /// then the paper implied by `locale`, then A4.
+    pub fn paper(&self) -> (f64, f64) {
+        if let Some(custom) = self.custom_paper_mm {
+            custom
+        } else if let Some(ref paper) = self.default_paper {
+            match paper.as_str() {
+                "Letter" | "letter" => letter_mm(),
+                _ => a4_mm(),
+            }
+        } else if let Some(ref locale) = self.locale {
+            paper_for_locale(locale).size_mm
+        } else {
+            a4_mm()
+        }
+    }
+
+    /// Whether per-page dimensions should be reported (`include_page_sizes`, default `true`).
+    pub fn wants_page_sizes(&self) -> bool {
+        self.include_page_sizes.unwrap_or(true)
+    }
+
+    /// The rounding mode of heuristic page counts (`rounding`, default ceil).
+    ///
+    /// Unknown names fall back to ceil; `estimate_bytes` rejects them before
+    /// any estimator runs.
+    pub fn rounding(&self) -> PageRounding {
+        self.rounding.as_deref().and_then(PageRounding::from_name).unwrap_or_default()
+    }
+}
+
+/// How a fractional page count is turned into whole pages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PageRounding {
+    /// Count a partly filled last page (worst case, for capacity planning).
+    #[default]
+    Ceil,
+    /// Count the last page when it is at least half full (for budgeting).
+    Nearest,
+    /// Drop a partly filled last page.
+    Floor,
+}
+
+impl PageRounding {
+    /// Parses a `rounding` option value (`"ceil"`, `"nearest"`, or `"floor"`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ceil" => Some(PageRounding::Ceil),
+            "nearest" => Some(PageRounding::Nearest),
+            "floor" => Some(PageRounding::Floor),
+            _ => None,
+        }
+    }
+
+    /// Name of the mode, as accepted by `from_name`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PageRounding::Ceil => "ceil",
+            PageRounding::Nearest => "nearest",
+            PageRounding::Floor => "floor",
+        }
+    }
+
+    /// Rounds a fractional page count to whole pages.
+    ///
+    /// Any content (`pages > 0`) is at least 1 page, whatever the mode. A
+    /// small tolerance keeps exact multiples (e.g. 3600 characters at 1800 per
+    /// page) from gaining or losing a page to floating-point error.
+    pub fn round(self, pages: f64) -> usize {
+        if pages.is_nan() || pages <= 0.0 {
+            return 0;
+        }
+        let rounded = match self {
+            PageRounding::Ceil => (pages - 1e-9).ceil(),
+            PageRounding::Nearest => (pages + 1e-9).round(),
+            PageRounding::Floor => (pages + 1e-9).floor(),
+        };
+        (rounded as usize).max(1)
+    }
+
+    /// Rounds a paginated layout: `pages` started pages, the last of which is
+    /// `last_page_fill` full (0 to 1).
+    pub fn round_layout(self, pages: usize, last_page_fill: f64) -> usize {
+        match pages {
+            0 => 0,
+            _ => self.round((pages - 1) as f64 + last_page_fill.clamp(0.0, 1.0).max(1e-6)),
+        }
+    }
+}
+
+/// Requires both numbers of a `(width, height)` pair to be positive, as `validate_options` does.
+fn positive_pair(schema: &mut schemars::Schema) {
+    let positive = serde_json::json!({ "type": "number", "exclusiveMinimum": 0 });
+    if let Some(items) = schema.get_mut("prefixItems").and_then(|v| v.as_array_mut()) {
+        items.iter_mut().for_each(|item| *item = positive.clone());
+    }
+}
+
+/// Requires both numbers of a `(top, bottom)` pair to be at least 0, as `validate_options` does.
+fn non_negative_pair(schema: &mut schemars::Schema) {
+    let non_negative = serde_json::json!({ "type": "number", "minimum": 0 });
+    if let Some(items) = schema.get_mut("prefixItems").and_then(|v| v.as_array_mut()) {
+        items.iter_mut().for_each(|item| *item = non_negative.clone());
+    }
+}
+
+/// Restricts the values of a map of papers to what `file_utils::parse_paper` reads.
+fn paper_map(schema: &mut schemars::Schema) {
+    schema.insert("additionalProperties".into(), serde_json::json!({ "type": "string", "pattern": paper_pattern() }));
+}
+
+/// A pattern for the paper specifications `file_utils::parse_paper` accepts:
+/// a preset name or a `WxH` size of positive numbers, optionally followed by
+/// `portrait` or `landscape`.
+///
+/// JSON Schema patterns have no case-insensitive flag, so each letter is
+/// spelled as a class of both cases.
+pub fn paper_pattern() -> String {
+    let any_case = |word: &str| -> String {
+        word.chars()
+            .map(|c| {
+                if c.is_ascii_alphabetic() {
+                    format!("[{}{}]", c.to_ascii_uppercase(), c.to_ascii_lowercase())
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect()
+    };
+    let names: Vec<String> = PAPER_PRESETS.iter().map(|(name, _)| any_case(name)).collect();
+    let number = r"(0*[1-9][0-9]*(\.[0-9]*)?|0*\.[0-9]*[1-9][0-9]*)";
+    format!(
+        r"^\s*({}|{}\s*[xX×]\s*{})(\s+({}|{}))?\s*$",
+        names.join("|"),
+        number,
+        number,
+        any_case("portrait"),
+        any_case("landscape")
+    )
+}
+
+/// Requires both pages of a `(first, last)` page range to be at least 1.
+fn page_pair(schema: &mut schemars::Schema) {
+    let page = serde_json::json!({ "type": "integer", "minimum": 1 });
+    if let Some(items) = schema.get_mut("prefixItems").and_then(|v| v.as_array_mut()) {
+        items.iter_mut().for_each(|item| *item = page.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn size((width_mm, height_mm): (f64, f64)) -> PageSizeMm {
+        PageSizeMm { width_mm, height_mm }
+    }
+
+    #[test]
+    fn sizes_within_the_tolerance_are_the_same_size() {
+        let a4_from_points = PageSizeMm { width_mm: mm_from_pt(595.0), height_mm: mm_from_pt(842.0) };
+        assert_ne!(a4_from_points, size(a4_mm()));
+        assert!(a4_from_points.approx_eq(&size(a4_mm())));
+        assert!(!size(a4_mm()).approx_eq(&size(letter_mm())));
+        assert!(!size((210.0, 297.0)).approx_eq(&size((210.0, 297.6))));
+    }
+
+    #[test]
+    fn uniform_results_repeat_one_size() {
+        let notes = vec!["estimated".to_string()];
+        let result = EstimateResult::uniform(3, Some((297.0, 210.0)), notes.clone());
+        assert_eq!(result.page_sizes, vec![size((297.0, 210.0)); 3]);
+        assert_eq!((result.portrait_pages, result.landscape_pages, result.square_pages), (Some(0), Some(3), Some(0)));
+        assert_eq!(result.notes, notes);
+        assert_eq!(result.clone(), result);
+
+        let counted = EstimateResult::uniform(3, None, notes);
+        assert!(counted.page_sizes.is_empty());
+        assert_eq!(counted.landscape_pages, None);
+        assert_ne!(counted, result);
+    }
+
+    #[test]
+    fn paper_resolution_order() {
+        let options = |json: &str| serde_json::from_str::<EstimateOptions>(json).unwrap();
+        assert_eq!(options("{}").paper(), a4_mm());
+        assert_eq!(options(r#"{"default_paper":"Letter"}"#).paper(), letter_mm());
+        assert_eq!(options(r#"{"default_paper":"A4","locale":"en-US"}"#).paper(), a4_mm());
+        assert_eq!(options(r#"{"locale":"en-US"}"#).paper(), letter_mm());
+        let custom = options(r#"{"custom_paper_mm":[100.0,150.0],"default_paper":"Letter"}"#);
+        assert_eq!(custom.paper(), (100.0, 150.0));
+        assert_eq!(custom.clone(), custom);
+    }
+
+    #[test]
+    fn estimators_use_the_resolved_paper() {
+        let letter = estimate(&build_docx(60, 0, None), Some("report.docx"), r#"{"default_paper":"Letter"}"#).unwrap();
+        assert_eq!(letter.page_sizes, vec![size(letter_mm()); letter.page_count]);
+        let custom = r#"{"custom_paper_mm":[100.0,150.0]}"#;
+        let book = estimate(&build_xlsx(&[(80, 2)]), Some("book.xlsx"), custom).unwrap();
+        assert_eq!(book.page_sizes, vec![size((100.0, 150.0)); 2]);
+    }
+
+    #[test]
+    fn merging_nothing_gives_an_empty_result() {
+        assert_eq!(EstimateResult::merge(&[]), EstimateResult::default());
+    }
+
+    #[test]
+    fn merging_one_result_keeps_its_pages() {
+        let report = estimate(&build_docx(60, 0, None), Some("report.docx"), "{}").unwrap();
+        let merged = EstimateResult::merge(std::slice::from_ref(&report));
+        assert_eq!(merged.page_count, report.page_count);
+        assert_eq!((merged.page_count_min, merged.page_count_max), (report.page_count_min, report.page_count_max));
+        assert_eq!(merged.page_sizes, report.page_sizes);
+        assert_eq!(merged.method, report.method);
+        assert_eq!(merged.confidence, report.confidence);
+        let notes: Vec<String> = report.notes.iter().map(|note| format!("[1] {}", note)).collect();
+        assert_eq!(merged.notes, notes);
+    }
+
+    #[test]
+    fn merging_mixed_sizes_keeps_input_order() {
+        let mut letter = EstimateResult::uniform(2, Some(letter_mm()), vec!["letter".to_string()]);
+        letter.confidence = Some(0.9);
+        letter.page_count_min = Some(1);
+        letter.page_count_max = Some(3);
+        let mut mixed = EstimateResult::uniform(1, Some((297.0, 210.0)), vec!["landscape".to_string()]);
+        mixed.page_sizes.push(size(a4_mm()));
+        mixed.page_count = 2;
+        mixed.portrait_pages = Some(1);
+        mixed.confidence = Some(0.5);
+
+        let merged = EstimateResult::merge(&[letter.clone(), mixed.clone()]);
+        assert_eq!(merged.page_count, 4);
+        assert_eq!(merged.page_sizes, [letter.page_sizes.as_slice(), mixed.page_sizes.as_slice()].concat());
+        assert_eq!((merged.portrait_pages, merged.landscape_pages), (Some(3), Some(1)));
+        assert_eq!((merged.page_count_min, merged.page_count_max), (Some(3), Some(5)));
+        assert_eq!(merged.confidence, Some(0.5));
+        assert_eq!(merged.notes, ["[1] letter", "[2] landscape"]);
+
+        // sizes known for only some inputs can't be matched to page numbers
+        let counted = EstimateResult::uniform(3, None, Vec::new());
+        let merged = EstimateResult::merge(&[mixed, counted, letter]);
+        assert_eq!(merged.page_count, 7);
+        assert!(merged.page_sizes.is_empty());
+        assert_eq!(merged.notes, ["[1] landscape", "[3] letter"]);
+    }
+
+    #[test]
+    fn summary_fields_are_omitted_when_absent() {
+        let notes = vec!["⚠ damaged".to_string(), "Note: guessed".to_string(), "plain".to_string()];
+        let mut result = EstimateResult::uniform(3, Some((210.4, 296.6)), notes).with_range(2, 4);
+        result.method = Some("two words\nand a line".into());
+        result.confidence = Some(0.875);
+        assert_eq!(
+            result.summary("pdf"),
+            "pdf pages=3 range=2-4 size=210x297mm method=two_words_and_a_line confidence=0.88 warnings=2"
+        );
+
+        let bare = EstimateResult::uniform(0, None, Vec::new());
+        assert_eq!(bare.summary("plain text"), "plain_text pages=0 warnings=0");
+
+        let mut mixed = EstimateResult::uniform(2, Some((210.0, 297.0)), Vec::new());
+        mixed.page_sizes[1] = size((297.0, 210.0));
+        assert_eq!(mixed.summary("xps"), "xps pages=2 size=mixed warnings=0");
+    }
+
+    #[test]
+    fn near_square_pages_are_square() {
+        let orientation = |size_mm| size(size_mm).orientation();
+        assert_eq!(orientation((210.0, 297.0)), PageOrientation::Portrait);
+        assert_eq!(orientation((297.0, 210.0)), PageOrientation::Landscape);
+        assert_eq!(orientation((210.0, 210.0)), PageOrientation::Square);
+        // within SQUARE_PAGE_TOLERANCE of the longer side either way
+        assert_eq!(orientation((210.0, 214.0)), PageOrientation::Square);
+        assert_eq!(orientation((214.0, 210.0)), PageOrientation::Square);
+        assert_eq!(orientation((210.0, 215.0)), PageOrientation::Portrait);
+    }
+
+    #[test]
+    fn orientation_counts_sum_to_the_page_count() {
+        let a4 = EstimateResult::uniform(3, Some((210.0, 297.0)), Vec::new());
+        assert_eq!((a4.portrait_pages, a4.landscape_pages, a4.square_pages), (Some(3), Some(0), Some(0)));
+        let sizeless = EstimateResult::uniform(3, None, Vec::new());
+        assert_eq!((sizeless.portrait_pages, sizeless.landscape_pages, sizeless.square_pages), (None, None, None));
+
+        // sizes for only some pages say nothing
+        let mut result = EstimateResult::uniform(3, None, Vec::new());
+        result.set_orientation_counts(&[size((297.0, 210.0))]);
+        assert_eq!((result.portrait_pages, result.landscape_pages, result.square_pages), (None, None, None));
+        result.set_orientation_counts(&[size((297.0, 210.0)), size((100.0, 100.0)), size((210.0, 297.0))]);
+        assert_eq!((result.portrait_pages, result.landscape_pages, result.square_pages), (Some(1), Some(1), Some(1)));
+    }
+
+    #[test]
+    fn page_rounding_keeps_exact_multiples_and_a_page_of_content() {
+        let modes = [PageRounding::Ceil, PageRounding::Nearest, PageRounding::Floor];
+        let round = |pages: f64| modes.map(|mode| mode.round(pages));
+        assert_eq!(round(3.0), [3, 3, 3]);
+        // floating-point error doesn't make an exact multiple gain or lose a page
+        assert_eq!(round((0.1 + 0.2) * 10.0), [3, 3, 3]);
+        assert_eq!(round(0.7 * 10.0), [7, 7, 7]);
+        assert_eq!(round(3.01), [4, 3, 3]);
+        assert_eq!(round(3.5), [4, 4, 3]);
+        assert_eq!(round(0.2), [1, 1, 1]);
+        assert_eq!(round(0.0), [0, 0, 0]);
+        assert_eq!(round(f64::NAN), [0, 0, 0]);
+
+        let layout = |pages: usize, fill: f64| modes.map(|mode| mode.round_layout(pages, fill));
+        assert_eq!(layout(3, 1.0), [3, 3, 3]);
+        assert_eq!(layout(4, 0.01), [4, 3, 3]);
+        assert_eq!(layout(4, 0.5), [4, 4, 3]);
+        assert_eq!(layout(1, 0.0), [1, 1, 1]);
+        assert_eq!(layout(0, 1.0), [0, 0, 0]);
+
+        for mode in modes {
+            assert_eq!(PageRounding::from_name(mode.as_str()), Some(mode));
         }
+        assert_eq!(PageRounding::from_name("Ceil"), None);
+    }
+
+    #[test]
+    fn broken_invariants_are_reported() {
+        let valid = EstimateResult::uniform(3, Some(a4_mm()), Vec::new());
+        assert_eq!(valid.validate_invariants(), Ok(()));
+        assert_eq!(EstimateResult::uniform(3, None, Vec::new()).validate_invariants(), Ok(()));
+
+        type Breaks = fn(&mut EstimateResult);
+        let broken: [(Breaks, &str); 8] = [
+            (|r| r.page_count = 4, "3 page sizes for 4 pages"),
+            (|r| r.page_sizes_pt = Some(vec![PageSizePt { width_pt: 595.0, height_pt: 842.0 }]), "1 point sizes"),
+            (|r| r.page_sizes_in = Some(Vec::new()), "0 inch sizes for 3 page sizes"),
+            (|r| r.page_sizes[1].height_mm = f64::NAN, "page 2 has an invalid size"),
+            (|r| r.page_sizes[2].width_mm = 0.0, "page 3 has an invalid size"),
+            (|r| r.page_count_max = Some(2), "page count 3 is outside its range"),
+            (|r| r.confidence = Some(1.5), "confidence 1.5 is outside 0–1"),
+            (|r| r.split_page_count = Some(2), "split page count 2 is below the page count 3"),
+        ];
+        for (breaks, message) in broken {
+            let mut result = valid.clone();
+            breaks(&mut result);
+            let violation = result.validate_invariants().unwrap_err();
+            assert!(violation.contains(message), "{}: {}", message, violation);
+        }
+    }
+
+    #[test]
+    fn merged_sizes_cover_every_page_or_none() {
+        let sized = EstimateResult::uniform(2, Some(letter_mm()), Vec::new());
+        let unsized_ = EstimateResult::uniform(3, None, Vec::new());
+        let merged = EstimateResult::merge(&[sized.clone(), unsized_]);
+        assert_eq!((merged.page_count, merged.page_sizes.len()), (5, 0));
+        assert_eq!(merged.validate_invariants(), Ok(()));
+        let both = EstimateResult::merge(&[sized.clone(), sized]);
+        assert_eq!(both.page_sizes.len(), 4);
+        assert_eq!(both.validate_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn units_are_parsed_and_rounded() {
+        assert_eq!(["mm", " PT ", "In"].map(Unit::parse), [Some(Unit::Mm), Some(Unit::Pt), Some(Unit::In)]);
+        assert_eq!(Unit::parse("cm"), None);
+        assert_eq!((Unit::In.from_mm(215.9), Unit::In.from_mm(210.0)), (8.5, 8.268));
+        assert_eq!((Unit::Pt.from_mm(25.4), Unit::Pt.from_mm(297.0)), (72.0, 841.89));
+        assert_eq!(Unit::Mm.from_mm(210.00049), 210.0);
+    }
+
+    #[test]
+    fn converting_units_is_idempotent_and_lossless() {
+        let letter = estimate(&build_docx(40, 2, Some(3)), Some("a.docx"), r#"{"default_paper":"Letter"}"#).unwrap();
+        let inches = letter.convert_units(Unit::In);
+        let page_sizes_in = inches.page_sizes_in.as_ref().unwrap();
+        assert_eq!(page_sizes_in.len(), 3);
+        assert_eq!((page_sizes_in[0].width_in, page_sizes_in[0].height_in), (8.5, 11.0));
+        assert_eq!((&inches.page_sizes, inches.page_sizes_pt.as_ref()), (&letter.page_sizes, None));
+        assert_eq!(inches.convert_units(Unit::In), inches);
+        assert_eq!(inches.validate_invariants(), Ok(()));
+
+        // round trips through every unit land on the same result
+        let points = inches.convert_units(Unit::Pt);
+        assert_eq!(points.page_sizes_in, None);
+        let page_sizes_pt = points.page_sizes_pt.as_ref().unwrap();
+        assert!((page_sizes_pt[0].width_pt - 612.0).abs() < 1e-3 && (page_sizes_pt[0].height_pt - 792.0).abs() < 1e-3);
+        assert_eq!(points.convert_units(Unit::Pt), points);
+        assert_eq!(points.convert_units(Unit::Mm), letter.convert_units(Unit::Mm));
+        assert_eq!(points.convert_units(Unit::In), inches);
+
+        // point sizes read from the PDF are kept as they are
+        let pdf = estimate(&build_receipt_pdf(&[300.0]), Some("r.pdf"), r#"{"include_point_sizes":true}"#).unwrap();
+        let original = pdf.page_sizes_pt.clone().unwrap();
+        assert_eq!(pdf.convert_units(Unit::Pt).page_sizes_pt, Some(original));
+        let mm = pdf.convert_units(Unit::Mm);
+        assert_eq!((mm.page_sizes_pt, mm.page_sizes_in, mm.page_sizes), (None, None, pdf.page_sizes));
+    }
+
+    #[test]
+    fn sheet_paper_is_read_from_options_json() {
+        let json = r#"{"xlsx_sheet_paper": {"Summary": "A4", "Detail 2024": "297x420", "Notes": "A3 landscape"}}"#;
+        let options: EstimateOptions = serde_json::from_str(json).unwrap();
+        let papers = options.xlsx_sheet_paper.as_ref().unwrap();
+        let entries: Vec<(&str, &str)> = papers.iter().map(|(name, paper)| (name.as_str(), paper.as_str())).collect();
+        assert_eq!(entries, [("Detail 2024", "297x420"), ("Notes", "A3 landscape"), ("Summary", "A4")]);
+        // the map is written back in name order, so equal options serialize alike
+        let again = serde_json::to_value(&options).unwrap();
+        let written: Vec<&String> = again["xlsx_sheet_paper"].as_object().unwrap().keys().collect();
+        assert_eq!(written, ["Detail 2024", "Notes", "Summary"]);
+        assert!(serde_json::from_str::<EstimateOptions>(r#"{"xlsx_sheet_paper": {"Summary": 4}}"#).is_err());
+        assert!(serde_json::from_str::<EstimateOptions>(r#"{"xlsx_sheet_paper": ["A4"]}"#).is_err());
     }
 }
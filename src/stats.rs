@@ -0,0 +1,350 @@
+//! # Usage Statistics
+//!
+//! `memory_stats` reports how much memory the module holds and how it has
+//! been used, so that long-running pages can watch how close they get to
+//! their limits. It only reads counters, so it is cheap enough to poll.
+//!
+//! ## Counters
+//!
+//! - `estimates`: estimates served, counting an archive as one and including
+//!   results served from the cache
+//! - `cache.hits` / `cache.misses`: lookups of `estimate_document_cached`
+//! - `last_peak_buffer_bytes`: the largest buffer the last estimate is known to
+//!   have held, its input or a decompressed gzip stream or archive entry. It is
+//!   a lower bound: parsers allocate more than the buffers they read.
+//!
+//! ## Bytes Examined
+//!
+//! Each estimate reports in `bytes_examined` how much of its input it read,
+//! so fast paths that read a small part of a file can be told from ones that
+//! read all of it. Readers record what they touch with `note_bytes_examined`:
+//!
+//! - PDF: the raw scans of `pdf_utils`, the objects they resolve, and the
+//!   tail window holding `startxref`
+//! - ZIP packages: the central directory, and the local header and compressed
+//!   data of every entry read (`note_zip_entry`); entries read by calamine
+//!   (XLSX cells) are not seen
+//! - Other formats are read whole, and report their input size
+//!
+//! Bytes read more than once count each time, so a figure above the input
+//! size means some bytes were read again. It is meant to be right to an order
+//! of magnitude, not exact.
+//!
+//! Counters live in the module instance and start again from 0 when the
+//! module is instantiated again.
+
+use crate::assembly::to_versioned_json;
+use crate::cache::with_estimate_cache;
+use crate::schema::EstimateOptions;
+use serde::Serialize;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+/// Size of a WebAssembly memory page in bytes.
+pub const WASM_PAGE_BYTES: usize = 64 * 1024;
+
+thread_local! {
+    static USAGE: RefCell<UsageCounters> = RefCell::new(UsageCounters::default());
+}
+
+/// Counters updated by the entry points (see the module docs).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UsageCounters {
+    /// Estimates served.
+    pub estimates: u64,
+    /// Results served from the estimate cache.
+    pub cache_hits: u64,
+    /// Cached estimates that had to be computed.
+    pub cache_misses: u64,
+    /// Largest buffer held by the last estimate, in bytes.
+    pub last_peak_buffer_bytes: usize,
+    /// `max_input_bytes` of the last estimate.
+    pub last_max_input_bytes: Option<usize>,
+    /// Input bytes examined by every estimate so far (see `note_bytes_examined`).
+    pub bytes_examined: usize,
+    /// Estimates in progress; nested ones (archive entries) belong to the outer one.
+    depth: usize,
+}
+
+/// Tracks an estimate from its start until it's dropped.
+///
+/// Estimates started while another is in progress, such as the entries of an
+/// archive, are part of the outer one: they are not counted again and their
+/// buffers add to its peak.
+pub struct EstimateScope {
+    /// `bytes_examined` when the estimate started.
+    examined_at_start: usize,
+}
+
+impl EstimateScope {
+    /// Starts tracking an estimate of an input of `input_len` bytes.
+    pub fn begin(input_len: usize, options: &EstimateOptions) -> Self {
+        USAGE.with(|usage| {
+            let mut usage = usage.borrow_mut();
+            if usage.depth == 0 {
+                usage.estimates += 1;
+                usage.last_peak_buffer_bytes = 0;
+                usage.last_max_input_bytes = options.max_input_bytes;
+            }
+            usage.depth += 1;
+            usage.last_peak_buffer_bytes = usage.last_peak_buffer_bytes.max(input_len);
+            EstimateScope { examined_at_start: usage.bytes_examined }
+        })
+    }
+
+    /// Bytes of input examined since the estimate started, nested estimates included.
+    pub fn bytes_examined(&self) -> usize {
+        USAGE.with(|usage| usage.borrow().bytes_examined.saturating_sub(self.examined_at_start))
+    }
+}
+
+impl Drop for EstimateScope {
+    fn drop(&mut self) {
+        USAGE.with(|usage| {
+            let mut usage = usage.borrow_mut();
+            usage.depth = usage.depth.saturating_sub(1);
+        });
+    }
+}
+
+/// Records a buffer of `len` bytes held by the current estimate, such as a
+/// decompressed stream.
+pub fn note_buffer(len: usize) {
+    USAGE.with(|usage| {
+        let mut usage = usage.borrow_mut();
+        usage.last_peak_buffer_bytes = usage.last_peak_buffer_bytes.max(len);
+    });
+}
+
+/// Records `len` bytes of input read by the current estimate (see the module docs).
+pub fn note_bytes_examined(len: usize) {
+    USAGE.with(|usage| {
+        let mut usage = usage.borrow_mut();
+        usage.bytes_examined = usage.bytes_examined.saturating_add(len);
+    });
+}
+
+/// Records a lookup of the estimate cache; a hit is an estimate served.
+pub fn note_cache_lookup(hit: bool) {
+    USAGE.with(|usage| {
+        let mut usage = usage.borrow_mut();
+        if hit {
+            usage.cache_hits += 1;
+            usage.estimates += 1;
+        } else {
+            usage.cache_misses += 1;
+        }
+    });
+}
+
+/// A copy of the counters.
+pub fn usage_counters() -> UsageCounters {
+    USAGE.with(|usage| usage.borrow().clone())
+}
+
+/// The report returned by `memory_stats`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MemoryStats {
+    /// Size of the module's linear memory in 64 KiB pages; `None` outside WebAssembly.
+    pub memory_pages: Option<usize>,
+    /// Size of the module's linear memory in bytes; `None` outside WebAssembly.
+    pub memory_bytes: Option<usize>,
+    /// Estimates served.
+    pub estimates: u64,
+    /// Largest buffer the last estimate is known to have held, in bytes.
+    pub last_peak_buffer_bytes: usize,
+    /// `max_input_bytes` of the last estimate; `None` when it had no limit.
+    pub max_input_bytes: Option<usize>,
+    /// The estimate cache.
+    pub cache: CacheStats,
+}
+
+/// Contents, limits, and use of the estimate cache.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CacheStats {
+    /// Cached results.
+    pub entries: usize,
+    /// Total size of the cached result JSON, in bytes.
+    pub bytes: usize,
+    /// Maximum number of cached results (see `configure_estimate_cache`).
+    pub max_entries: usize,
+    /// Maximum total size of the cached result JSON, in bytes.
+    pub max_bytes: usize,
+    /// Results served from the cache.
+    pub hits: u64,
+    /// Lookups that missed.
+    pub misses: u64,
+    /// `hits` over all lookups; `None` before the first lookup.
+    pub hit_rate: Option<f64>,
+}
+
+/// Gathers the report of `memory_stats`.
+pub fn collect_memory_stats() -> MemoryStats {
+    let usage = usage_counters();
+    let memory_pages = linear_memory_pages();
+    let lookups = usage.cache_hits + usage.cache_misses;
+    let cache = with_estimate_cache(|cache| CacheStats {
+        entries: cache.len(),
+        bytes: cache.total_bytes(),
+        max_entries: cache.max_entries(),
+        max_bytes: cache.max_bytes(),
+        hits: usage.cache_hits,
+        misses: usage.cache_misses,
+        hit_rate: (lookups > 0).then(|| usage.cache_hits as f64 / lookups as f64),
+    });
+    MemoryStats {
+        memory_pages,
+        memory_bytes: memory_pages.map(|pages| pages * WASM_PAGE_BYTES),
+        estimates: usage.estimates,
+        last_peak_buffer_bytes: usage.last_peak_buffer_bytes,
+        max_input_bytes: usage.last_max_input_bytes,
+        cache,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn linear_memory_pages() -> Option<usize> {
+    Some(core::arch::wasm32::memory_size::<0>())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn linear_memory_pages() -> Option<usize> {
+    None
+}
+
+/// Reports the module's memory size, limits, and usage counters (see the module docs).
+///
+/// # Returns
+///
+/// A `JsValue` containing a JSON string with:
+/// - `memory_pages`, `memory_bytes`: Current size of the WebAssembly memory
+/// - `estimates`: Estimates served so far
+/// - `last_peak_buffer_bytes`: Largest buffer held by the last estimate
+/// - `max_input_bytes`: The input size limit of the last estimate, or `null`
+/// - `cache`: `{entries, bytes, max_entries, max_bytes, hits, misses, hit_rate}`
+///
+/// # Example
+///
+/// ```javascript
+/// const stats = JSON.parse(memory_stats());
+/// metrics.gauge('wasm_memory_bytes', stats.memory_bytes);
+/// ```
+#[wasm_bindgen]
+pub fn memory_stats() -> JsValue {
+    match to_versioned_json(&collect_memory_stats()) {
+        Ok(s) | Err(s) => JsValue::from_str(&s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::{estimate_archive_bytes, estimate_bytes};
+    use crate::fixtures::*;
+
+    #[test]
+    fn estimates_are_counted_once_with_their_largest_buffer() {
+        let before = usage_counters().estimates;
+        let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        let limited = EstimateOptions { max_input_bytes: Some(1 << 20), ..Default::default() };
+        let _ = estimate_bytes(&pdf, None, &limited);
+        let stats = collect_memory_stats();
+        assert_eq!((stats.estimates, stats.last_peak_buffer_bytes), (before + 1, pdf.len()));
+        assert_eq!(stats.max_input_bytes, Some(1 << 20));
+        assert_eq!((stats.memory_pages, stats.memory_bytes), (None, None));
+
+        // an archive and its entries are one estimate, whose peak is its largest buffer
+        let docx = build_docx(200, 0, Some(4));
+        let zip = build_zip(&[("a.pdf", &pdf), ("b.docx", &docx)]);
+        let _ = estimate_archive_bytes(&zip, &EstimateOptions::default());
+        let counters = usage_counters();
+        assert_eq!(counters.estimates, before + 2);
+        assert_eq!(counters.last_peak_buffer_bytes, zip.len());
+        assert_eq!(counters.last_max_input_bytes, None);
+
+        // a decompressed stream is larger than the input it came from
+        let text = "A few words. ".repeat(5000);
+        let _ = estimate_bytes(&gzip(text.as_bytes(), 1), Some("notes.txt.gz"), &EstimateOptions::default());
+        assert_eq!((usage_counters().estimates, usage_counters().last_peak_buffer_bytes), (before + 3, text.len()));
+    }
+
+    #[test]
+    fn cache_lookups_give_the_hit_rate() {
+        let before = usage_counters();
+        note_cache_lookup(false);
+        note_cache_lookup(true);
+        note_cache_lookup(true);
+        note_cache_lookup(false);
+        let stats = collect_memory_stats();
+        assert_eq!((stats.cache.hits - before.cache_hits, stats.cache.misses - before.cache_misses), (2, 2));
+        // only hits are estimates served
+        assert_eq!(stats.estimates, before.estimates + 2);
+        if before.cache_hits + before.cache_misses == 0 {
+            assert_eq!(stats.cache.hit_rate, Some(0.5));
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&to_versioned_json(&stats).unwrap()).unwrap();
+        assert_eq!(json["cache"]["hits"], stats.cache.hits);
+        assert!(json["memory_bytes"].is_null());
+    }
+
+    /// `len` letters that don't compress, so a package part holding them is as large as they are.
+    fn incompressible(len: usize) -> String {
+        let mut state = 1u64;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                char::from(b'a' + (state >> 59) as u8 % 26)
+            })
+            .collect()
+    }
+
+    fn examined(bytes: &[u8]) -> usize {
+        estimate(bytes, None, "{}").unwrap().bytes_examined.unwrap()
+    }
+
+    #[test]
+    fn nested_estimates_add_to_the_outer_one() {
+        let outer = EstimateScope::begin(100, &EstimateOptions::default());
+        note_bytes_examined(10);
+        {
+            let inner = EstimateScope::begin(40, &EstimateOptions::default());
+            note_bytes_examined(5);
+            assert_eq!(inner.bytes_examined(), 5);
+        }
+        assert_eq!(outer.bytes_examined(), 15);
+    }
+
+    #[test]
+    fn packages_report_the_entries_they_read() {
+        let unread = incompressible(200_000);
+        for (package, part) in [
+            (build_docx(30, 2, Some(4)), "word/media/notes.txt"),
+            (build_pptx(5, 0), "ppt/media/notes.txt"),
+            (build_xlsx(&[(20, 3)]), "xl/media/notes.txt"),
+        ] {
+            let padded = with_part(&package, part, &unread);
+            assert!(padded.len() > 100_000, "{} bytes", padded.len());
+            let (examined, padded_examined) = (examined(&package), examined(&padded));
+            // the central directory grows by one entry; the part itself isn't read
+            assert!(padded_examined > 0 && padded_examined < examined + 2048, "{} then {}", examined, padded_examined);
+        }
+        // estimators without hooks read all of their input
+        let text = "A few words. ".repeat(400);
+        assert_eq!(examined(text.as_bytes()), text.len());
+    }
+
+    #[test]
+    fn pdf_reads_grow_with_the_file() {
+        let ratio = |pages: usize| {
+            let pdf = build_pdf(pages, XrefStyle::Classic, false, false);
+            let examined = examined(&pdf);
+            // the type scan reads every byte
+            assert!(examined >= pdf.len(), "{} bytes examined of {}", examined, pdf.len());
+            examined as f64 / pdf.len() as f64
+        };
+        let (small, large) = (ratio(100), ratio(1000));
+        assert!(large < small * 1.5, "{:.1}x at 100 pages, {:.1}x at 1000", small, large);
+    }
+}
@@ -0,0 +1,672 @@
+//! # Text Layout
+//!
+//! Line-based pagination for plain text. Used by the text estimator when
+//! `chars_per_line` is set: every source line is laid out on a fixed-width
+//! page, tabs are expanded to the next tab stop, and lines longer than the
+//! page width wrap onto additional display lines. Line and paragraph spacing
+//! reduce how many display lines fit on a page.
+//!
+//! ## Character Width
+//!
+//! Widths follow Unicode East Asian Width: Wide and Fullwidth characters (CJK
+//! ideographs, kana, Hangul, fullwidth forms) occupy two cells, combining marks
+//! and zero-width characters occupy none, and everything else occupies one.
+//! The same cell widths drive the character-count heuristic.
+//!
+//! ## Normalization
+//!
+//! Characters that take no print space are not counted, in cell widths or in
+//! the character counts of `text_metrics` (`printed_chars`):
+//!
+//! - Unicode default-ignorable code points: bidi controls (U+200E, U+200F,
+//!   U+202A–U+202E, U+2066–U+2069), joiners, the byte order mark, soft
+//!   hyphens, and variation selectors
+//! - The Arabic tatweel (U+0640), which only stretches the word it's in
+//!
+//! Arabic presentation forms, the shaped letters and ligatures some
+//! converters write instead of plain letters, count as the letters they stand
+//! for: lam-alef (U+FEFB) as two, a three-letter ligature as three.
+//!
+//! Layout assumes left-to-right line metrics. `rtl_note` flags text with a
+//! significant share of right-to-left letters (Hebrew, Arabic, Syriac,
+//! Thaana, N'Ko), whose line lengths the character heuristics fit less well.
+//!
+//! ## Limits
+//!
+//! Single lines longer than `MAX_LAYOUT_LINE_BYTES` (e.g. minified output) are
+//! not laid out character by character; their display line count is derived
+//! from their byte length instead, and a note is added.
+
+/// Default number of display lines on a printed page.
+pub const DEFAULT_LINES_PER_PAGE: usize = 50;
+
+/// Default tab stop interval in columns.
+pub const DEFAULT_TAB_WIDTH: u8 = 8;
+
+/// Lines longer than this many bytes are approximated instead of laid out.
+const MAX_LAYOUT_LINE_BYTES: usize = 1024 * 1024;
+
+/// Combining marks (sorted, inclusive ranges).
+const ZERO_WIDTH_RANGES: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x20D0, 0x20FF),
+    (0x3099, 0x309A),
+    (0xFE20, 0xFE2F),
+];
+
+/// Unicode default-ignorable code points (sorted, inclusive ranges).
+const DEFAULT_IGNORABLE_RANGES: &[(u32, u32)] = &[
+    (0x00AD, 0x00AD),
+    (0x034F, 0x034F),
+    (0x061C, 0x061C),
+    (0x115F, 0x1160),
+    (0x17B4, 0x17B5),
+    (0x180B, 0x180F),
+    (0x200B, 0x200F),
+    (0x202A, 0x202E),
+    (0x2060, 0x206F),
+    (0x3164, 0x3164),
+    (0xFE00, 0xFE0F),
+    (0xFEFF, 0xFEFF),
+    (0xFFA0, 0xFFA0),
+    (0xFFF0, 0xFFF8),
+    (0x1BCA0, 0x1BCA3),
+    (0x1D173, 0x1D17A),
+    (0xE0000, 0xE0FFF),
+];
+
+/// The Arabic tatweel (kashida), which lengthens a word without adding a letter.
+pub const ARABIC_TATWEEL: char = '\u{0640}';
+
+/// Arabic presentation forms by the number of letters they stand for (sorted,
+/// inclusive ranges). Ranges not listed, such as the isolated and final forms
+/// of single letters, stand for one letter.
+const PRESENTATION_FORM_LETTERS: &[(u32, u32, usize)] = &[
+    (0xFBEA, 0xFBFB, 2),
+    (0xFC00, 0xFC5D, 2),
+    (0xFC64, 0xFCF1, 2),
+    (0xFCF5, 0xFD3D, 2),
+    (0xFD50, 0xFDC7, 3),
+    (0xFDF0, 0xFDF1, 3),
+    (0xFDF2, 0xFDF2, 4),
+    (0xFDF3, 0xFDF9, 3),
+    (0xFDFA, 0xFDFA, 18),
+    (0xFDFB, 0xFDFB, 8),
+    (0xFEF5, 0xFEFC, 2),
+];
+
+/// Right-to-left scripts: Hebrew, Arabic, Syriac, Thaana, N'Ko, and their
+/// presentation forms (sorted, inclusive ranges).
+const RTL_RANGES: &[(u32, u32)] = &[
+    (0x0590, 0x05FF),
+    (0x0600, 0x07FF),
+    (0x0860, 0x08FF),
+    (0xFB1D, 0xFDFF),
+    (0xFE70, 0xFEFC),
+];
+
+/// Share of the letters that makes `rtl_note` flag a text as right-to-left.
+pub const RTL_NOTE_SHARE: f64 = 0.1;
+
+/// Checks whether `code` falls in one of the sorted, inclusive `ranges`.
+fn in_ranges(code: u32, ranges: &[(u32, u32)]) -> bool {
+    ranges
+        .binary_search_by(|(start, end)| {
+            if code < *start {
+                std::cmp::Ordering::Greater
+            } else if code > *end {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Returns the number of characters `ch` prints as (see the module docs).
+///
+/// Default-ignorable code points and the tatweel print as none, Arabic
+/// presentation forms as the letters they stand for, and anything else as one.
+pub fn printed_chars(ch: char) -> usize {
+    let code = ch as u32;
+    if ch == ARABIC_TATWEEL || in_ranges(code, DEFAULT_IGNORABLE_RANGES) {
+        return 0;
+    }
+    if !(0xFB50..=0xFEFC).contains(&code) {
+        return 1;
+    }
+    PRESENTATION_FORM_LETTERS
+        .iter()
+        .find(|(start, end, _)| (*start..=*end).contains(&code))
+        .map_or(1, |(_, _, letters)| *letters)
+}
+
+/// Counts the characters a text prints as, with `printed_chars`.
+pub fn count_printed_chars(text: &str) -> usize {
+    text.chars().map(printed_chars).sum()
+}
+
+/// A note for text whose letters are at least `RTL_NOTE_SHARE` right-to-left,
+/// saying that the estimate assumes left-to-right line metrics.
+pub fn rtl_note(text: &str) -> Option<String> {
+    let (mut letters, mut rtl) = (0usize, 0usize);
+    for ch in text.chars().filter(|ch| ch.is_alphabetic()) {
+        letters += 1;
+        if in_ranges(ch as u32, RTL_RANGES) {
+            rtl += 1;
+        }
+    }
+    let share = if letters == 0 { 0.0 } else { rtl as f64 / letters as f64 };
+    (share >= RTL_NOTE_SHARE).then(|| {
+        format!(
+            "{:.0}% of the letters are right-to-left (Hebrew, Arabic, ...); {}",
+            share * 100.0,
+            "the estimate assumes left-to-right line metrics"
+        )
+    })
+}
+
+/// East Asian Wide and Fullwidth characters (sorted, inclusive ranges).
+const WIDE_RANGES: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE30, 0xFE4F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F300, 0x1F64F),
+    (0x1F900, 0x1F9FF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+/// Returns the number of cells a character occupies when printed (0, 1 or 2;
+/// an Arabic ligature as many as its letters).
+///
+/// Tabs are reported as one cell; tab expansion depends on the column and is
+/// handled by `layout_lines`.
+pub fn char_width(ch: char) -> usize {
+    let code = ch as u32;
+    let printed = printed_chars(ch);
+    if printed != 1 {
+        // ignorable, or a ligature of several letters
+        printed
+    } else if in_ranges(code, ZERO_WIDTH_RANGES) {
+        0
+    } else if in_ranges(code, WIDE_RANGES) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Splits text into pages of `cells_per_page` cells using the character-count heuristic.
+///
+/// # Returns
+///
+/// The total cell count and the byte offset where each page starts. A wide
+/// character that doesn't fit on the current page starts the next one, so the
+/// number of boundaries is the page count.
+pub fn paginate_cells(text: &str, cells_per_page: usize) -> (usize, Vec<usize>) {
+    let cells_per_page = cells_per_page.max(1);
+    let mut total = 0;
+    let mut on_page = 0;
+    let mut boundaries = Vec::new();
+    for (offset, ch) in text.char_indices() {
+        let width = char_width(ch);
+        if boundaries.is_empty() || (on_page + width > cells_per_page && on_page > 0) {
+            boundaries.push(offset);
+            on_page = 0;
+        }
+        on_page += width;
+        total += width;
+    }
+    (total, boundaries)
+}
+
+/// Page geometry for line-based layout.
+pub struct LineMetrics {
+    /// Number of columns on a printed line.
+    pub chars_per_line: usize,
+    /// Number of single-spaced lines that fit on a printed page.
+    pub lines_per_page: usize,
+    /// Tab stop interval; a tab advances to the next multiple of it.
+    pub tab_width: usize,
+    /// Height of each display line in single-spaced lines (1.0 single, 2.0 double).
+    pub line_spacing: f64,
+    /// Extra space before each paragraph after the first, in single-spaced lines.
+    /// Paragraphs are separated by blank lines.
+    pub paragraph_spacing_lines: f64,
+}
+
+impl LineMetrics {
+    /// Whole display lines that fit on a page after applying line spacing.
+    pub fn effective_lines_per_page(&self) -> usize {
+        ((self.lines_per_page as f64 / self.line_spacing.max(0.1)).floor() as usize).max(1)
+    }
+}
+
+/// The result of laying out text on fixed-width lines.
+pub struct LineLayout {
+    /// Total number of display lines after wrapping.
+    pub display_lines: usize,
+    /// Byte offsets where each page starts (always on a character boundary).
+    pub page_boundaries: Vec<usize>,
+    /// Notes about approximations made during layout.
+    pub notes: Vec<String>,
+    /// Height used on the current page, in single-spaced lines.
+    used: f64,
+}
+
+/// Lays out text on fixed-width lines and splits it into pages.
+///
+/// # Arguments
+///
+/// * `text` - The text to lay out
+/// * `metrics` - Line width, page height, tab width, and spacing
+///
+/// # Returns
+///
+/// The display line count and the byte offset where each page starts. Page
+/// boundaries fall at the start of a display line. Each display line takes
+/// `line_spacing` lines of height, and each new paragraph additionally takes
+/// `paragraph_spacing_lines`.
+pub fn layout_lines(text: &str, metrics: &LineMetrics) -> LineLayout {
+    let chars_per_line = metrics.chars_per_line.max(1);
+    let tab_width = metrics.tab_width.max(1);
+    let mut layout =
+        LineLayout { display_lines: 0, page_boundaries: Vec::new(), notes: Vec::new(), used: 0.0 };
+
+    let mut line_start = 0;
+    let mut after_blank = false;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let is_blank = content.trim().is_empty();
+        let spacing = if after_blank && !is_blank && layout.display_lines > 0 {
+            metrics.paragraph_spacing_lines.max(0.0)
+        } else {
+            0.0
+        };
+        after_blank = is_blank;
+
+        if content.len() > MAX_LAYOUT_LINE_BYTES {
+            let lines = content.len().div_ceil(chars_per_line);
+            for i in 0..lines {
+                let offset = floor_char_boundary(content, i * chars_per_line);
+                let extra = if i == 0 { spacing } else { 0.0 };
+                layout.start_display_line(line_start + offset, extra, metrics);
+            }
+            layout.notes.push(format!(
+                "Line at byte {} is {} bytes long; wrapped by length instead of laid out",
+                line_start,
+                content.len()
+            ));
+        } else {
+            layout.start_display_line(line_start, spacing, metrics);
+            let mut column = 0;
+            for (offset, ch) in content.char_indices() {
+                let width = if ch == '\t' { tab_width - column % tab_width } else { char_width(ch) };
+                if column + width > chars_per_line && column > 0 {
+                    layout.start_display_line(line_start + offset, 0.0, metrics);
+                    column = 0;
+                }
+                // a tab that wraps only fills up to the next stop on the new line
+                column += if ch == '\t' { tab_width - column % tab_width } else { width };
+            }
+        }
+
+        line_start += line.len();
+    }
+
+    layout
+}
+
+impl LineLayout {
+    /// How full the last page is, from 0 to 1, in lines of height.
+    pub fn last_page_fill(&self, metrics: &LineMetrics) -> f64 {
+        self.used / metrics.lines_per_page.max(1) as f64
+    }
+
+    /// Places a display line starting at `offset`, preceded by `extra` lines of spacing.
+    fn start_display_line(&mut self, offset: usize, extra: f64, metrics: &LineMetrics) {
+        let height = metrics.line_spacing.max(0.1);
+        let capacity = metrics.lines_per_page.max(1) as f64;
+        if self.page_boundaries.is_empty() || self.used + extra + height > capacity + f64::EPSILON {
+            // spacing before a paragraph is dropped at the top of a page
+            self.page_boundaries.push(offset);
+            self.used = height;
+        } else {
+            self.used += extra + height;
+        }
+        self.display_lines += 1;
+    }
+}
+
+/// Text with terminal control sequences removed.
+pub struct CleanedText {
+    /// The text as it would appear on a terminal after all repaints.
+    pub text: String,
+    /// Number of bytes removed from ANSI escape sequences.
+    pub escape_bytes: usize,
+    /// Number of lines that were overwritten by carriage returns.
+    pub collapsed_lines: usize,
+}
+
+/// Removes ANSI escape sequences and collapses carriage-return repaints.
+///
+/// CSI sequences (`ESC [ ... final`) and OSC sequences (`ESC ] ... BEL` or
+/// `ESC ] ... ESC \\`) are stripped. A line containing bare `\r` characters
+/// (e.g. a progress bar) keeps only its last non-empty segment, i.e. its
+/// final state on screen.
+///
+/// # Returns
+///
+/// `None` when the text contains neither escape sequences nor bare carriage
+/// returns, so callers can keep working with the original text.
+pub fn clean_terminal_output(text: &str) -> Option<CleanedText> {
+    let has_bare_cr = text
+        .split('\n')
+        .any(|line| line.trim_end_matches('\r').contains('\r'));
+    if !text.contains('\x1b') && !has_bare_cr {
+        return None;
+    }
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            stripped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                // parameters and intermediates, then one final byte in @..~
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let escape_bytes = text.len() - stripped.len();
+
+    let mut collapsed_lines = 0;
+    let mut cleaned = String::with_capacity(stripped.len());
+    for line in stripped.split_inclusive('\n') {
+        let (content, ending) = match line.strip_suffix("\r\n") {
+            Some(content) => (content, "\r\n"),
+            None => match line.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (line, ""),
+            },
+        };
+        if content.contains('\r') {
+            collapsed_lines += 1;
+            let last = content.rsplit('\r').find(|s| !s.is_empty()).unwrap_or("");
+            cleaned.push_str(last);
+        } else {
+            cleaned.push_str(content);
+        }
+        cleaned.push_str(ending);
+    }
+
+    Some(CleanedText { text: cleaned, escape_bytes, collapsed_lines })
+}
+
+/// Returns the largest character boundary of `s` that is not after `index`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn metrics(chars_per_line: usize, lines_per_page: usize, tab_width: usize) -> LineMetrics {
+        LineMetrics { chars_per_line, lines_per_page, tab_width, line_spacing: 1.0, paragraph_spacing_lines: 0.0 }
+    }
+
+    fn display_lines(text: &str, chars_per_line: usize, tab_width: usize) -> usize {
+        layout_lines(text, &metrics(chars_per_line, DEFAULT_LINES_PER_PAGE, tab_width)).display_lines
+    }
+
+    /// Width of a line with its tabs expanded to stops every `tab_width` columns.
+    fn expanded_width(line: &str, tab_width: usize) -> usize {
+        line.chars().fold(0, |column, ch| if ch == '\t' { column + tab_width - column % tab_width } else { column + 1 })
+    }
+
+    #[test]
+    fn east_asian_wide_characters_take_two_cells() {
+        let widths = [
+            ('a', 1),
+            ('é', 1),
+            ('\u{0301}', 0),
+            ('\u{200B}', 0),
+            ('漢', 2),
+            ('か', 2),
+            ('カ', 2),
+            ('한', 2),
+            ('Ａ', 2),
+            ('。', 2),
+            ('ｶ', 1),
+            ('😀', 2),
+        ];
+        for (ch, width) in widths {
+            assert_eq!(char_width(ch), width, "U+{:04X}", ch as u32);
+        }
+        assert_eq!(paginate_cells("e\u{0301}漢字", 10), (5, vec![0]));
+        // a wide character that doesn't fit starts the next page
+        assert_eq!(paginate_cells("ab漢", 3), (4, vec![0, 2]));
+    }
+
+    #[test]
+    fn japanese_prose_counts_about_twice_its_characters() {
+        let (_, japanese) = LANGUAGE_SAMPLES.iter().find(|(code, _)| *code == "ja").unwrap();
+        let text = japanese.repeat(60);
+        let chars = text.chars().count();
+        let (cells, boundaries) = paginate_cells(&text, 1800);
+        assert_eq!(cells, 2 * chars);
+        assert_eq!(boundaries.len(), (2 * chars).div_ceil(1800));
+
+        let result = estimate(text.as_bytes(), Some("notice.txt"), "{}").unwrap();
+        assert_eq!(result.page_count, boundaries.len());
+        assert!(result.notes.contains(&format!("chars: {}, cells: {}, chars_per_page: 1800", chars, cells)));
+        // the same weighting applies to line wrapping: twenty characters fill 40 columns
+        assert_eq!(display_lines(japanese, 40, 8), japanese.chars().count().div_ceil(20));
+    }
+
+    #[test]
+    fn line_spacing_divides_the_lines_on_a_page() {
+        let spaced = |line_spacing| LineMetrics { line_spacing, ..metrics(80, 50, 8) };
+        assert_eq!(spaced(1.0).effective_lines_per_page(), 50);
+        assert_eq!(spaced(1.5).effective_lines_per_page(), 33);
+        assert_eq!(spaced(2.0).effective_lines_per_page(), 25);
+        assert_eq!(spaced(100.0).effective_lines_per_page(), 1);
+
+        let text = "A line of text that fits.\n".repeat(500);
+        let single = layout_lines(&text, &spaced(1.0)).page_boundaries.len();
+        let double = layout_lines(&text, &spaced(2.0)).page_boundaries.len();
+        assert_eq!((single, double), (10, 20));
+
+        let options = r#"{"chars_per_line":80,"line_spacing":2.0}"#;
+        let result = estimate(text.as_bytes(), Some("a.txt"), options).unwrap();
+        assert_eq!(result.page_count, 20);
+        let note = "display lines: 500, chars_per_line: 80, lines_per_page: 50 (25 after 2x line spacing)";
+        assert!(result.notes.contains(&format!("{}, tab_width: 8", note)), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn paragraph_spacing_is_added_between_paragraphs() {
+        // ten paragraphs of four lines, separated by blank lines: 49 lines
+        let text = "One line.\nTwo.\nThree.\nFour.\n".repeat(10).replace("Four.\nOne", "Four.\n\nOne");
+        let paragraphs = |paragraph_spacing_lines| LineMetrics { paragraph_spacing_lines, ..metrics(80, 50, 8) };
+        assert_eq!(layout_lines(&text, &paragraphs(0.0)).page_boundaries.len(), 1);
+        let layout = layout_lines(&text, &paragraphs(1.0));
+        assert_eq!(layout.display_lines, 49);
+        // 49 lines plus 9 lines of spacing: the ninth paragraph breaks after two lines,
+        // leaving two of its lines, a blank line, and the last spaced paragraph on page 2
+        assert_eq!(layout.page_boundaries.len(), 2);
+        assert_eq!(layout.last_page_fill(&paragraphs(1.0)), 8.0 / 50.0);
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_stop() {
+        assert_eq!(display_lines("a\tb", 8, 8), 2);
+        assert_eq!(display_lines("a\tb", 8, 4), 1);
+        assert_eq!(display_lines("abcdefg\tb", 8, 8), 2);
+        // the third tab wraps and then only reaches the first stop of the new line
+        assert_eq!(display_lines("\t\t\tx", 20, 8), 2);
+        assert_eq!(display_lines("\t\t\tx", 25, 8), 1);
+    }
+
+    #[test]
+    fn tsv_log_lines_are_as_wide_as_their_expanded_tabs() {
+        let log = build_tsv_log(40);
+        for line in log.lines() {
+            for tab_width in [4, 8] {
+                let width = expanded_width(line, tab_width);
+                assert!(width > line.chars().count(), "{:?}", line);
+                assert_eq!(display_lines(line, width, tab_width), 1, "{:?}", line);
+                assert_eq!(display_lines(line, width - 1, tab_width), 2, "{:?}", line);
+            }
+        }
+        // records are 47 columns wide with one-column tabs and 57 with 8-column stops
+        assert_eq!(display_lines(&log, 50, 1), 40);
+        assert_eq!(display_lines(&log, 50, 8), 79);
+    }
+
+    #[test]
+    fn long_lines_wrap_onto_display_lines() {
+        assert_eq!(display_lines(&"x".repeat(250), 100, 8), 3);
+        assert_eq!(display_lines(&"x".repeat(200), 100, 8), 2);
+        // wide characters wrap when the second cell wouldn't fit
+        assert_eq!(display_lines(&"漢".repeat(50), 99, 8), 2);
+
+        let json = build_minified_json(400);
+        assert_eq!(json.lines().count(), 1);
+        let layout = layout_lines(&json, &metrics(100, 60, 8));
+        assert_eq!(layout.display_lines, json.len().div_ceil(100));
+        assert_eq!(layout.page_boundaries.len(), layout.display_lines.div_ceil(60));
+        assert!(layout.notes.is_empty());
+    }
+
+    #[test]
+    fn lines_over_the_limit_are_wrapped_by_length() {
+        let line = "é".repeat(MAX_LAYOUT_LINE_BYTES / 2 + 1000);
+        let text = format!("first\n{}\nlast\n", line);
+        let layout = layout_lines(&text, &metrics(100, 50, 8));
+        assert_eq!(layout.display_lines, 2 + line.len().div_ceil(100));
+        assert_eq!(
+            layout.notes,
+            [format!("Line at byte 6 is {} bytes long; wrapped by length instead of laid out", line.len())]
+        );
+        assert!(layout.page_boundaries.iter().all(|offset| text.is_char_boundary(*offset)));
+    }
+
+    #[test]
+    fn ci_log_is_cleaned_to_what_the_terminal_showed() {
+        let cleaned = clean_terminal_output(CI_LOG).unwrap();
+        assert_eq!(
+            cleaned.text,
+            "==> Checking out repository\n==> Restoring cache\nDownloading toolchain 100%\n   Compiling \
+             page-counter-wasm v0.1.0\nwarning: unused variable: `x`\n    Finished `test` profile in 41.2s\n\
+             test result: ok. 67 passed; 0 failed\n"
+        );
+        assert_eq!(cleaned.collapsed_lines, 1);
+        let removed: usize = ["\x1b]0;build #1432\x07", "\x1b[36m", "\x1b[0m", "\x1b[1m", "\x1b[32m", "\x1b[33m"]
+            .iter()
+            .map(|sequence| CI_LOG.matches(sequence).count() * sequence.len())
+            .sum();
+        assert_eq!(cleaned.escape_bytes, removed);
+    }
+
+    #[test]
+    fn plain_text_needs_no_cleaning() {
+        assert!(clean_terminal_output("line one\r\nline two\n").is_none());
+        let cleaned = clean_terminal_output("a\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ b").unwrap();
+        assert_eq!(cleaned.text, "alink b");
+        // a repainted line keeps its last state and its CRLF ending
+        assert_eq!(clean_terminal_output("50%\r100%\r\n").unwrap().text, "100%\r\n");
+    }
+
+    #[test]
+    fn ci_log_is_estimated_as_text_without_its_escapes() {
+        assert_eq!(crate::file_utils::detect_type(None, CI_LOG.as_bytes()), "txt");
+        let cleaned = clean_terminal_output(CI_LOG).unwrap();
+        let options = r#"{"chars_per_page":100}"#;
+        let log = estimate(CI_LOG.as_bytes(), Some("job.log"), options).unwrap();
+        let plain = estimate(cleaned.text.as_bytes(), Some("job.txt"), options).unwrap();
+        assert_eq!(log.page_count, plain.page_count);
+        assert_eq!(log.text_metrics.unwrap().char_count, plain.text_metrics.unwrap().char_count);
+        let note = format!(
+            "Removed {} bytes of ANSI escape sequences; collapsed 1 carriage-return repainted lines",
+            cleaned.escape_bytes
+        );
+        assert_eq!(log.notes[0], note);
+    }
+
+    #[test]
+    fn bidi_controls_and_tatweels_print_as_nothing() {
+        for text in [ARABIC_TEXT, HEBREW_TEXT] {
+            let marked = with_bidi_marks(text);
+            // every word gains a mark, and the line an embedding and a newline
+            assert!(marked.chars().count() > text.chars().count() + text.split(' ').count());
+            assert_eq!(count_printed_chars(&marked), count_printed_chars(&format!("{}\n", text)));
+            assert_eq!(count_printed_chars(text), text.chars().count());
+        }
+        for ch in ['\u{200E}', '\u{200F}', '\u{202A}', '\u{202E}', '\u{0640}', '\u{200B}', '\u{FEFF}', '\u{00AD}'] {
+            assert_eq!((printed_chars(ch), char_width(ch)), (0, 0), "U+{:04X}", ch as u32);
+        }
+    }
+
+    #[test]
+    fn presentation_forms_count_as_their_letters() {
+        // lam-alef ligatures stand for two letters, isolated and final forms for one
+        for ligature in ['\u{FEF5}', '\u{FEFB}', '\u{FEFC}'] {
+            assert_eq!((printed_chars(ligature), char_width(ligature)), (2, 2), "U+{:04X}", ligature as u32);
+        }
+        assert_eq!(printed_chars('\u{FE8D}'), 1);
+        // "salam" written with presentation forms prints as many letters as the plain word
+        assert_eq!(count_printed_chars("\u{FEB3}\u{FEFC}\u{FEE1}"), count_printed_chars("سلام"));
+    }
+
+    #[test]
+    fn mostly_right_to_left_text_is_noted() {
+        let note = rtl_note(HEBREW_TEXT).unwrap();
+        assert!(note.starts_with("100% of the letters are right-to-left"), "{}", note);
+        assert!(rtl_note(&with_bidi_marks(ARABIC_TEXT)).is_some());
+        // a quoted word in English prose is too little to matter
+        let english = "The printer reviewed the order and will send the documents by Friday. ".repeat(3);
+        assert_eq!(rtl_note(&format!("{} שלום", english)), None);
+        assert_eq!(rtl_note(&english), None);
+        assert_eq!(rtl_note("12345 ..."), None);
+    }
+}
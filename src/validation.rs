@@ -0,0 +1,318 @@
+//! # Document Validation
+//!
+//! Cheap structural checks that answer "is this file what it claims to be, and
+//! is it intact?" without estimating a page count. They are meant to run before
+//! queueing an expensive conversion.
+//!
+//! ## Checks
+//!
+//! - **PDF**: `%PDF-` header, `%%EOF` marker, and a `startxref` offset that
+//!   points at a cross-reference table or stream (reporting the shift when all
+//!   offsets are off by the same number of bytes); `/Encrypt` marks encryption
+//! - **DOCX / PPTX / XLSX**: the ZIP central directory opens and the main part
+//!   (`word/document.xml`, `ppt/presentation.xml`, `xl/workbook.xml`) exists;
+//!   password-protected files (an OLE container holding an `EncryptedPackage`)
+//!   and ZIP-encrypted entries are reported as encrypted
+//! - **XPS / OXPS**: the ZIP opens, `[Content_Types].xml` exists, and a
+//!   FixedDocumentSequence part can be found
+//! - **PostScript / EPS**: a `%!` header, after unwrapping a DOS EPS binary header
+//! - **CBZ**: the ZIP opens and contains at least one page image
+//! - **iWork**: the ZIP opens and contains a QuickLook preview PDF
+//! - **ZIP / TAR / gzip**: the container headers can be read
+//! - **Text-based formats** (text, Markdown, RST, AsciiDoc, LaTeX, code): the content is valid UTF-8
+//!
+//! No sheets are scanned and no content is extracted or decompressed.
+
+use crate::comic::page_images;
+use crate::docbook::read_docbook;
+use crate::file_utils::{
+    detect_type, is_encrypted_office_package, open_zip_archive, read_tar_entries, OLE_MAGIC,
+};
+use crate::iwork::read_quicklook_preview;
+use crate::notebook::parse_notebook;
+use crate::mime::{looks_like_email, looks_like_mht, mht_root, parse_mime};
+use crate::pdf_utils::{find_bytes, startxref_offset, xref_at, xref_shift};
+use crate::postscript::postscript_section;
+use crate::records::looks_like_records;
+use crate::schema::ValidationReport;
+use crate::xps::find_document_sequence;
+
+/// How far from the start (or end) of a PDF its header (or `%%EOF`) may appear.
+const PDF_MARKER_WINDOW: usize = 1024;
+
+/// Validates the structure of a document without estimating it.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw document bytes
+/// * `filename` - Optional filename used as a type hint, as for estimation
+///
+/// # Returns
+///
+/// A `ValidationReport` with the detected format, whether the structure is
+/// intact, whether the document is encrypted, and any issues found.
+pub fn validate_bytes(bytes: &[u8], filename: Option<&str>) -> ValidationReport {
+    let format = detect_type(filename, bytes);
+    let mut report = ValidationReport { format, ..Default::default() };
+
+    if bytes.is_empty() {
+        report.issues.push("File is empty".into());
+        return report;
+    }
+
+    match report.format.as_str() {
+        "pdf" => validate_pdf(bytes, &mut report),
+        "docx" => validate_ooxml(bytes, "word/document.xml", &mut report),
+        "pptx" => validate_ooxml(bytes, "ppt/presentation.xml", &mut report),
+        "xlsx" => validate_ooxml(bytes, "xl/workbook.xml", &mut report),
+        "xps" => validate_xps(bytes, &mut report),
+        "cbz" => match open_zip_archive(bytes, "CBZ") {
+            Ok(archive) if page_images(&archive).is_empty() => {
+                report.issues.push("Comic archive contains no page images".into())
+            }
+            Ok(_) => {}
+            Err(err) => report.issues.push(err.to_string()),
+        },
+        "iwork" => match open_zip_archive(bytes, "iWork") {
+            Ok(mut archive) => {
+                if read_quicklook_preview(&mut archive).is_none() {
+                    report.issues.push("iWork document has no QuickLook preview; its pages can't be counted".into());
+                }
+            }
+            Err(err) => report.issues.push(err.to_string()),
+        },
+        "postscript" => {
+            if postscript_section(bytes).is_none() {
+                report.issues.push("Missing %! header or invalid DOS EPS header".into());
+            }
+        }
+        "zip" => {
+            if let Err(err) = open_zip_archive(bytes, "archive") {
+                report.issues.push(err.to_string());
+            }
+        }
+        "tar" => {
+            if let Err(err) = read_tar_entries(bytes) {
+                report.issues.push(err.to_string());
+            }
+        }
+        "gzip" => validate_gzip(bytes, &mut report),
+        "eml" => {
+            if !looks_like_email(bytes) {
+                report.issues.push("Missing RFC 822 message headers".into());
+            }
+        }
+        "mht" => {
+            if !looks_like_mht(bytes) {
+                report.issues.push("Missing MIME-Version and multipart/related headers".into());
+            } else if mht_root(&parse_mime(bytes)).is_none() {
+                report.issues.push("MHT archive has no HTML or text part".into());
+            }
+        }
+        "notebook" => {
+            if let Err(err) = parse_notebook(bytes) {
+                report.issues.push(err);
+            }
+        }
+        "records" => {
+            if !looks_like_records(bytes) {
+                report.issues.push("Missing BEGIN:VCARD or BEGIN:VCALENDAR line".into());
+            }
+        }
+        "docbook" => {
+            if let Err(err) = read_docbook(bytes) {
+                report.issues.push(format!("DocBook XML is malformed: {}", err));
+            }
+        }
+        "txt" | "markdown" | "rst" | "asciidoc" | "latex" | "code" | "data" => {
+            if let Err(err) = std::str::from_utf8(bytes) {
+                report
+                    .issues
+                    .push(format!("Text is not valid UTF-8 (at byte {})", err.valid_up_to()));
+            }
+        }
+        other => report.issues.push(format!("Unsupported or unrecognized format: {}", other)),
+    }
+
+    report.valid = report.issues.is_empty();
+    report
+}
+
+fn validate_pdf(bytes: &[u8], report: &mut ValidationReport) {
+    let head = &bytes[..bytes.len().min(PDF_MARKER_WINDOW)];
+    if find_bytes(head, b"%PDF-").is_none() {
+        report.issues.push("Missing %PDF- header".into());
+        return;
+    }
+
+    let tail = &bytes[bytes.len().saturating_sub(PDF_MARKER_WINDOW)..];
+    if find_bytes(tail, b"%%EOF").is_none() {
+        report
+            .issues
+            .push("Missing %%EOF marker; the file may be truncated".into());
+    }
+
+    match startxref_offset(bytes) {
+        None => report.issues.push("Missing startxref offset".into()),
+        Some(offset) if !xref_at(bytes, offset) => report.issues.push(match xref_shift(bytes) {
+            Some(shift) => format!(
+                "startxref offset {} does not point to a cross-reference table; \
+                 all offsets appear shifted by {} bytes",
+                offset, shift
+            ),
+            None if offset >= bytes.len() => {
+                format!("startxref offset {} is past the end of the file ({} bytes)", offset, bytes.len())
+            }
+            None => format!("startxref offset {} does not point to a cross-reference table", offset),
+        }),
+        Some(_) => {}
+    }
+
+    report.encrypted = find_bytes(bytes, b"/Encrypt").is_some();
+}
+
+fn validate_ooxml(bytes: &[u8], main_part: &str, report: &mut ValidationReport) {
+    if bytes.starts_with(OLE_MAGIC) {
+        if is_encrypted_office_package(bytes) {
+            report.encrypted = true;
+        } else {
+            report
+                .issues
+                .push("File is a legacy binary Office document, not Office Open XML".into());
+        }
+        return;
+    }
+
+    let mut archive = match open_zip_archive(bytes, &report.format.to_uppercase()) {
+        Ok(archive) => archive,
+        Err(err) => {
+            report.issues.push(err.to_string());
+            return;
+        }
+    };
+
+    for part in ["[Content_Types].xml", main_part] {
+        if archive.index_for_name(part).is_none() {
+            report.issues.push(format!("Missing required part {}", part));
+        }
+    }
+
+    report.encrypted = (0..archive.len())
+        .any(|i| archive.by_index_raw(i).is_ok_and(|file| file.encrypted()));
+}
+
+fn validate_xps(bytes: &[u8], report: &mut ValidationReport) {
+    let mut archive = match open_zip_archive(bytes, "XPS") {
+        Ok(archive) => archive,
+        Err(err) => {
+            report.issues.push(err.to_string());
+            return;
+        }
+    };
+
+    if archive.index_for_name("[Content_Types].xml").is_none() {
+        report.issues.push("Missing required part [Content_Types].xml".into());
+    }
+    if find_document_sequence(&mut archive).is_none() {
+        report.issues.push("Missing FixedDocumentSequence part".into());
+    }
+}
+
+fn validate_gzip(bytes: &[u8], report: &mut ValidationReport) {
+    // 10-byte header plus the 8-byte CRC32/ISIZE trailer
+    if bytes.len() < 18 {
+        report.issues.push("gzip data is truncated".into());
+    } else if bytes[2] != 8 {
+        report
+            .issues
+            .push(format!("Unsupported gzip compression method {}", bytes[2]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn issues(bytes: &[u8], filename: Option<&str>) -> (bool, bool, Vec<String>) {
+        let report = validate_bytes(bytes, filename);
+        (report.valid, report.encrypted, report.issues)
+    }
+
+    #[test]
+    fn intact_documents_are_valid() {
+        let documents = [
+            ("pdf", build_pdf(3, XrefStyle::Classic, false, false)),
+            ("pdf", build_pdf(3, XrefStyle::Stream, true, false)),
+            ("docx", build_docx(5, 1, Some(2))),
+            ("xlsx", build_xlsx(&[(4, 4)])),
+            ("pptx", build_pptx(3, 1)),
+        ];
+        for (format, bytes) in documents {
+            let report = validate_bytes(&bytes, None);
+            assert_eq!(report.format, format);
+            assert!(report.valid && !report.encrypted, "{}: {:?}", format, report.issues);
+        }
+    }
+
+    #[test]
+    fn truncated_documents_are_invalid() {
+        let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+        let (valid, _, found) = issues(&pdf[..pdf.len() / 2], None);
+        assert!(!valid);
+        assert_eq!(found, ["Missing %%EOF marker; the file may be truncated", "Missing startxref offset"]);
+
+        let packages = [
+            ("docx", truncate_in_part(&build_docx(5, 1, None), "word/document.xml")),
+            ("xlsx", truncate_in_part(&build_xlsx(&[(4, 4)]), "xl/worksheets/sheet1.xml")),
+            ("pptx", truncate_in_part(&build_pptx(3, 0), "ppt/slides/slide2.xml")),
+        ];
+        for (format, bytes) in packages {
+            let report = validate_bytes(&bytes, Some(&format!("upload.{}", format)));
+            assert_eq!(report.format, format);
+            assert!(!report.valid);
+            assert_eq!(report.issues.len(), 1, "{:?}", report.issues);
+            assert!(report.issues[0].starts_with("ZIP error"), "{:?}", report.issues);
+        }
+    }
+
+    #[test]
+    fn encrypted_documents_are_reported() {
+        for style in [XrefStyle::Classic, XrefStyle::Stream] {
+            assert_eq!(issues(&build_pdf(2, style, false, true), None), (true, true, vec![]));
+        }
+        let protected = build_protected_pdf(2, "user", "owner", PdfCipher::Aes128);
+        assert_eq!(issues(&protected, None), (true, true, vec![]));
+
+        let package = build_encrypted_office_package();
+        for format in ["docx", "xlsx", "pptx"] {
+            let report = validate_bytes(&package, Some(&format!("locked.{}", format)));
+            assert_eq!(report.format, format);
+            assert!(report.valid && report.encrypted, "{:?}", report.issues);
+        }
+    }
+
+    #[test]
+    fn missing_parts_and_legacy_files_are_named() {
+        let (valid, _, found) = issues(&build_zip(&[("[Content_Types].xml", b"<Types/>")]), Some("a.docx"));
+        assert!(!valid);
+        assert_eq!(found, ["Missing required part word/document.xml"]);
+
+        let mut legacy = build_encrypted_office_package();
+        legacy.truncate(512);
+        let (valid, encrypted, found) = issues(&legacy, Some("old.xlsx"));
+        assert!(!valid && !encrypted);
+        assert_eq!(found, ["File is a legacy binary Office document, not Office Open XML"]);
+    }
+
+    #[test]
+    fn shifted_pdf_offsets_are_explained() {
+        let mut pdf = build_pdf(1, XrefStyle::Classic, false, false);
+        // a comment line added after the header moves every object
+        let header_end = pdf.iter().position(|b| *b == b'\n').unwrap() + 1;
+        pdf.splice(header_end..header_end, *b"%ab\n");
+        let (valid, _, found) = issues(&pdf, None);
+        assert!(!valid);
+        assert!(found[0].ends_with("all offsets appear shifted by 4 bytes"), "{:?}", found);
+    }
+}
@@ -0,0 +1,212 @@
+//! # Expected Page Counts
+//!
+//! With `expected_pages`, an estimate doubles as a check of a page count the
+//! caller already has, such as one stored by another system: the result gets
+//! `matches_expected`, and a mismatch is looked into before it is reported.
+//!
+//! ## Tolerance
+//!
+//! `expected_tolerance` is a fraction of the expected count: with 0.05, a
+//! document expected to have 100 pages matches from 95 to 105. It defaults to
+//! 0, an exact match.
+//!
+//! ## Escalation
+//!
+//! When the estimate doesn't match, every way the estimator has of counting the
+//! format is run, not only the one it settled on:
+//!
+//! - PDF: each `PdfStrategy` (type scan, page tree, and `/Count` scan)
+//! - DOCX: `<Pages>` in `docProps/app.xml` and the estimate from the body
+//! - Other formats: the estimate only
+//!
+//! Every count, whether it matches, and the one the estimate used are reported
+//! in `diagnostics.verification`, with a note naming the counts that agree with
+//! the expected one. `page_count` is left as the estimator made it.
+
+use crate::estimators::docx_page_count_candidates;
+use crate::pdf::{PdfCounter, PdfStrategy};
+use crate::schema::{EstimateOptions, EstimateResult};
+use serde_json::{json, Value};
+
+/// Every PDF strategy, in the order they are run.
+const PDF_STRATEGIES: [PdfStrategy; 3] = [PdfStrategy::TypeScan, PdfStrategy::PageTree, PdfStrategy::CountScan];
+
+/// Checks whether `count` is within `tolerance` (a fraction of `expected`) of `expected`.
+pub fn within_tolerance(count: usize, expected: usize, tolerance: f64) -> bool {
+    count.abs_diff(expected) as f64 <= expected as f64 * tolerance
+}
+
+/// Counts the pages of a document every way the estimator can (see the module docs).
+///
+/// # Arguments
+///
+/// * `bytes` - The document
+/// * `detected` - Its detected format
+/// * `options` - The estimation options
+/// * `estimate` - The page count of the estimate
+///
+/// # Returns
+///
+/// One `(source, pages)` pair per count, and the source the estimate used,
+/// when it can be told.
+pub fn page_count_candidates(
+    bytes: &[u8],
+    detected: &str,
+    options: &EstimateOptions,
+    estimate: usize,
+) -> (Vec<(&'static str, usize)>, Option<&'static str>) {
+    match detected {
+        "pdf" => {
+            let candidates: Vec<(&'static str, usize)> = PDF_STRATEGIES
+                .iter()
+                .map(|&strategy| (strategy.name(), PdfCounter::new(vec![strategy]).count(bytes).count))
+                .collect();
+            // the strategy the estimator's pipeline stops at
+            let chosen = PdfCounter::default().strategies().iter().find_map(|strategy| {
+                candidates.iter().find(|(name, count)| *name == strategy.name() && *count > 0).map(|(name, _)| *name)
+            });
+            (candidates, chosen)
+        }
+        "docx" => {
+            let candidates = docx_page_count_candidates(bytes, options);
+            let chosen = candidates.first().filter(|(_, count)| *count == estimate).map(|(source, _)| *source);
+            (candidates, chosen)
+        }
+        _ => (vec![("estimate", estimate)], Some("estimate")),
+    }
+}
+
+/// Sets `matches_expected` when `expected_pages` is given, and looks into a
+/// mismatch (see the module docs).
+///
+/// # Arguments
+///
+/// * `result` - The estimate to check
+/// * `bytes` - The document
+/// * `detected` - Its detected format
+/// * `options` - The estimation options, with `expected_pages` and `expected_tolerance`
+pub fn apply_expected_pages(result: &mut EstimateResult, bytes: &[u8], detected: &str, options: &EstimateOptions) {
+    let Some(expected) = options.expected_pages else { return };
+    let tolerance = options.expected_tolerance.unwrap_or(0.0);
+    let matches = within_tolerance(result.page_count, expected, tolerance);
+    result.matches_expected = Some(matches);
+    if matches {
+        return;
+    }
+
+    let (candidates, chosen) = page_count_candidates(bytes, detected, options, result.page_count);
+    let agreeing: Vec<&str> = candidates
+        .iter()
+        .filter(|(_, count)| within_tolerance(*count, expected, tolerance))
+        .map(|(source, _)| *source)
+        .collect();
+    result.notes.push(if agreeing.is_empty() {
+        format!("⚠ Expected {} pages, estimated {}; no counting method agrees", expected, result.page_count)
+    } else {
+        format!(
+            "⚠ Expected {} pages, estimated {}; the {} count agrees",
+            expected,
+            result.page_count,
+            agreeing.join(" and ")
+        )
+    });
+    let verification = json!({
+        "expected_pages": expected,
+        "expected_tolerance": tolerance,
+        "candidates": candidates
+            .iter()
+            .map(|(source, count)| json!({
+                "source": source,
+                "count": count,
+                "matches_expected": within_tolerance(*count, expected, tolerance),
+            }))
+            .collect::<Vec<Value>>(),
+        "chosen": chosen,
+    });
+    let diagnostics = result.diagnostics.get_or_insert_with(|| json!({}));
+    if let Some(diagnostics) = diagnostics.as_object_mut() {
+        diagnostics.insert("verification".into(), verification);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    /// The `diagnostics.verification` of a result, as JSON.
+    fn verification(result: &EstimateResult) -> &Value {
+        &result.diagnostics.as_ref().expect("a mismatch is looked into")["verification"]
+    }
+
+    #[test]
+    fn tolerance_is_a_fraction_of_the_expected_count() {
+        assert!(within_tolerance(100, 100, 0.0));
+        assert!(!within_tolerance(101, 100, 0.0));
+        assert!(within_tolerance(95, 100, 0.05) && within_tolerance(105, 100, 0.05));
+        assert!(!within_tolerance(94, 100, 0.05) && !within_tolerance(106, 100, 0.05));
+        assert!(within_tolerance(0, 0, 0.0) && !within_tolerance(1, 0, 0.5));
+    }
+
+    #[test]
+    fn right_and_nearly_right_expectations_match() {
+        let pdf = build_pdf(50, XrefStyle::Classic, false, false);
+        for options in [r#"{"expected_pages":50}"#, r#"{"expected_pages":52,"expected_tolerance":0.05}"#] {
+            let result = estimate(&pdf, None, options).unwrap();
+            assert_eq!((result.page_count, result.matches_expected), (50, Some(true)), "{}", options);
+            assert_eq!(result.diagnostics, None);
+            assert!(!result.notes.iter().any(|n| n.contains("Expected")), "{:?}", result.notes);
+        }
+        assert_eq!(estimate(&pdf, None, "{}").unwrap().matches_expected, None);
+        let err = estimate(&pdf, None, r#"{"expected_pages":50,"expected_tolerance":-0.1}"#).unwrap_err();
+        assert!(err.to_string().contains("expected_tolerance"), "{}", err);
+    }
+
+    #[test]
+    fn badly_wrong_expectations_report_every_count() {
+        let pdf = build_pdf(5, XrefStyle::Classic, false, false);
+        let result = estimate(&pdf, None, r#"{"expected_pages":20}"#).unwrap();
+        assert_eq!((result.page_count, result.matches_expected), (5, Some(false)));
+        assert!(result.notes.iter().any(|n| n == "⚠ Expected 20 pages, estimated 5; no counting method agrees"));
+        let verification = verification(&result);
+        assert_eq!(verification["chosen"], "type-scan");
+        let counts: Vec<(&str, u64, bool)> = verification["candidates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| (c["source"].as_str().unwrap(), c["count"].as_u64().unwrap(), c["matches_expected"] == true))
+            .collect();
+        assert_eq!(counts, [("type-scan", 5, false), ("page-tree", 5, false), ("count-scan", 5, false)]);
+    }
+
+    #[test]
+    fn mismatches_name_the_counts_that_agree() {
+        // a page tree of two pages whose /Count claims five
+        let miscounted = build_raw_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 5 >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+            "<< /Type /Page /Parent 2 0 R >>",
+        ]);
+        let (candidates, _) = page_count_candidates(&miscounted, "pdf", &EstimateOptions::default(), 2);
+        let expected = candidates.iter().find(|(_, count)| *count != 2).map(|(_, count)| *count).unwrap();
+        let options = format!(r#"{{"expected_pages":{}}}"#, expected);
+        let result = estimate(&miscounted, None, &options).unwrap();
+        assert_eq!(result.matches_expected, Some(false));
+        assert!(result.notes.last().unwrap().contains("count agrees"), "{:?}", result.notes);
+
+        // DOCX metadata saying 9 pages for a body of 3
+        let docx = build_docx(30, 2, Some(9));
+        let result = estimate(&docx, None, r#"{"expected_pages":3}"#).unwrap();
+        assert_eq!((result.page_count, result.matches_expected), (9, Some(false)));
+        assert!(result.notes.iter().any(|n| n == "⚠ Expected 3 pages, estimated 9; the content count agrees"));
+        assert_eq!(verification(&result)["chosen"], "metadata");
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["diagnostics"]["verification"]["candidates"][1]["matches_expected"], true);
+
+        // other formats only have their estimate
+        let text = "A few words. ".repeat(400);
+        let (candidates, chosen) = page_count_candidates(text.as_bytes(), "txt", &EstimateOptions::default(), 3);
+        assert_eq!((candidates, chosen), (vec![("estimate", 3)], Some("estimate")));
+    }
+}
@@ -0,0 +1,139 @@
+//! # Web Worker Protocol
+//!
+//! Estimating large files blocks the thread it runs on, so it belongs in a Web
+//! Worker. `handle_worker_message` implements one message protocol for that,
+//! so the worker itself is a few lines of JavaScript
+//! (`js_bridges/estimate_worker.js` is ready to use).
+//!
+//! ## Messages
+//!
+//! A request is a plain object:
+//!
+//! - `id`: any value, echoed back so responses can be matched to requests
+//! - `op`: `"estimate"`, `"detect"`, or `"validate"`
+//! - `bytes`: the document, as an `ArrayBuffer` or a `Uint8Array`
+//! - `filename`: optional filename, used as a type hint
+//! - `mime_hint`: optional MIME type, such as the `type` of a `File`; it takes
+//!   precedence over `filename` (see `detect_format`), and over the
+//!   `mime_hint` of `options` (`estimate` and `detect` only)
+//! - `options`: optional estimation options, as an object or a JSON string
+//!   (`estimate` only)
+//!
+//! The response is `{id, ok: true, result}` or `{id, ok: false, error}`:
+//!
+//! - `estimate`: `result` is the `estimate_document` result (or the validation
+//!   report with `validate_only`), and `error` its error object
+//! - `detect`: `result` is `{format}`
+//! - `validate`: `result` is the `validate_document` report
+//!
+//! Malformed requests fail with the `error_code` `invalid_message`.
+//!
+//! ## Copies
+//!
+//! Post the `ArrayBuffer` in the transfer list
+//! (`worker.postMessage(message, [message.bytes])`), so it is moved to the
+//! worker instead of being cloned. The worker passes it on as is, and a
+//! `Uint8Array` view over it is copied once into WASM memory, as for every
+//! other entry point. The main thread's buffer is detached after the transfer.
+
+use crate::assembly::{estimate_to_json, parse_options_checked, to_versioned_json, with_schema_version};
+use crate::file_utils::detect_format;
+use crate::validation::validate_bytes;
+use js_sys::{ArrayBuffer, Object, Promise, Reflect, Uint8Array, JSON};
+use serde_json::json;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Handles a worker request (see the module docs for the protocol).
+///
+/// # Parameters
+///
+/// * `msg` - The request, usually `event.data` of the worker's `message` event
+///
+/// # Returns
+///
+/// A `Promise` resolving to the response object, ready for `postMessage`. It
+/// never rejects; failures are reported with `ok: false`.
+///
+/// # Example
+///
+/// ```javascript
+/// // in the worker
+/// self.onmessage = async (event) => self.postMessage(await handle_worker_message(event.data));
+/// ```
+#[wasm_bindgen]
+pub fn handle_worker_message(msg: JsValue) -> Promise {
+    let id = Reflect::get(&msg, &"id".into()).unwrap_or(JsValue::UNDEFINED);
+    let (ok, body) = match run_request(&msg) {
+        Ok(result) => (true, result),
+        Err(error) => (false, error),
+    };
+    let response: JsValue = Object::new().into();
+    let _ = Reflect::set(&response, &"id".into(), &id);
+    let _ = Reflect::set(&response, &"ok".into(), &JsValue::from_bool(ok));
+    let _ = Reflect::set(&response, &(if ok { "result" } else { "error" }).into(), &parse_json(&body));
+    Promise::resolve(&response)
+}
+
+/// Runs the operation of a request.
+///
+/// # Returns
+///
+/// `Ok` with the result JSON, or `Err` with the error JSON.
+fn run_request(msg: &JsValue) -> Result<String, String> {
+    let field = |name: &str| Reflect::get(msg, &name.into()).ok().filter(|value| !value.is_undefined());
+    let op = field("op").and_then(|op| op.as_string()).ok_or_else(|| invalid_message("op is missing"))?;
+    let bytes = field("bytes").and_then(|bytes| message_bytes(&bytes)).ok_or_else(|| {
+        invalid_message("bytes must be an ArrayBuffer or a Uint8Array")
+    })?;
+    let filename = field("filename").and_then(|name| name.as_string());
+    let mime_hint = field("mime_hint").and_then(|mime| mime.as_string());
+
+    match op.as_str() {
+        "estimate" => {
+            let options_json = field("options").filter(|options| !options.is_null()).map(options_json).transpose()?;
+            let (mut options, warnings) = parse_options_checked(options_json.as_deref());
+            if mime_hint.is_some() {
+                options.mime_hint = mime_hint;
+            }
+            estimate_to_json(&bytes, filename.as_deref(), &options, &warnings)
+        }
+        "detect" => {
+            let format = detect_format(filename.as_deref(), mime_hint.as_deref(), &bytes).format;
+            Ok(with_schema_version(&json!({ "format": format }).to_string()))
+        }
+        "validate" => to_versioned_json(&validate_bytes(&bytes, filename.as_deref())),
+        other => Err(invalid_message(&format!(
+            "unknown op \"{}\"; expected \"estimate\", \"detect\", or \"validate\"",
+            other
+        ))),
+    }
+}
+
+/// Copies the document bytes of a request, given as an `ArrayBuffer` or a `Uint8Array`.
+fn message_bytes(value: &JsValue) -> Option<Vec<u8>> {
+    if let Some(buffer) = value.dyn_ref::<ArrayBuffer>() {
+        Some(Uint8Array::new(buffer).to_vec())
+    } else {
+        value.dyn_ref::<Uint8Array>().map(Uint8Array::to_vec)
+    }
+}
+
+/// Reads the options of a request, given as a JSON string or as an object.
+fn options_json(options: JsValue) -> Result<String, String> {
+    options
+        .as_string()
+        .or_else(|| JSON::stringify(&options).ok().and_then(|json| json.as_string()))
+        .ok_or_else(|| invalid_message("options could not be serialized"))
+}
+
+/// The error JSON of a malformed request.
+fn invalid_message(message: &str) -> String {
+    let error = json!({"error": format!("Invalid worker message: {}", message), "error_code": "invalid_message"});
+    with_schema_version(&error.to_string())
+}
+
+/// Parses JSON built by the estimator into a JavaScript value.
+fn parse_json(json: &str) -> JsValue {
+    JSON::parse(json).unwrap_or_else(|_| JsValue::from_str(json))
+}
@@ -0,0 +1,474 @@
+//! # Workbook Print Layout
+//!
+//! calamine reads cell values but not the print settings of a worksheet, so
+//! they are read here straight from the sheet XML. Sheets are located through
+//! `xl/workbook.xml` and its relationships.
+//!
+//! ## Sheet order
+//!
+//! The `<sheets>` element of `xl/workbook.xml` lists the sheets in the order of
+//! their tabs in Excel, which is the order estimates report them in. Neither
+//! the order of the worksheet parts in the ZIP nor their numbering follows it
+//! once tabs have been moved, and `sheetId` (which stays with a sheet when it's
+//! renamed or moved) doesn't either.
+//!
+//! ## Fit to page
+//!
+//! A sheet with `<pageSetUpPr fitToPage="1"/>` is scaled down until it fits
+//! `fitToWidth` pages across and `fitToHeight` pages down (`<pageSetup>`), where
+//! 0 leaves that direction unconstrained. Excel never scales below 10% or above
+//! 100%, so a sheet that is already small enough prints at its natural size.
+//!
+//! ## Row heights
+//!
+//! Rows are as tall as `<sheetFormatPr defaultRowHeight>` unless a `<row>`
+//! states its own `ht` (wrapped text, larger fonts), in points. Heights are
+//! clamped to `MAX_ROW_HEIGHT_PT`, and negative or non-finite ones ignored.
+//! Hidden rows take no space. Pages are filled row by row up to the printable height,
+//! the paper height less the top and bottom `<pageMargins>` (in inches).
+
+use crate::file_utils::{mm_from_pt, note_zip_entry, read_relationship_targets, resolve_part};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use std::collections::BTreeMap;
+use std::io::{BufReader, Cursor};
+use std::ops::RangeInclusive;
+use zip::ZipArchive;
+
+/// Part holding the sheet list of a workbook.
+pub const WORKBOOK_PART: &str = "xl/workbook.xml";
+
+/// Row height Excel uses when a sheet declares none (Calibri 11), in points.
+pub const DEFAULT_ROW_HEIGHT_PT: f64 = 15.0;
+
+/// Tallest row Excel allows, in points; larger stored heights are clamped to it.
+pub const MAX_ROW_HEIGHT_PT: f64 = 409.0;
+
+/// Top and bottom page margin Excel uses when a sheet declares none, in millimeters (0.75").
+pub const DEFAULT_SHEET_MARGIN_MM: f64 = 19.05;
+
+/// Smallest print scale Excel applies when fitting a sheet, as a fraction.
+pub const MIN_FIT_SCALE: f64 = 0.10;
+
+/// Print settings of a worksheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetLayout {
+    /// Whether the sheet is scaled to fit `fit_to_width` × `fit_to_height` pages.
+    pub fit_to_page: bool,
+    /// Pages across to fit the sheet to; 0 for unconstrained.
+    pub fit_to_width: u32,
+    /// Pages down to fit the sheet to; 0 for unconstrained.
+    pub fit_to_height: u32,
+    /// Height of rows without an explicit height, in points (`defaultRowHeight`).
+    pub default_row_height_pt: Option<f64>,
+    /// Explicit heights in points by 1-based row number; hidden rows are 0.
+    pub row_heights_pt: BTreeMap<usize, f64>,
+    /// Top and bottom page margins in millimeters (`pageMargins`).
+    pub margins_mm: Option<(f64, f64)>,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        // Excel's defaults when pageSetup omits the attributes
+        Self {
+            fit_to_page: false,
+            fit_to_width: 1,
+            fit_to_height: 1,
+            default_row_height_pt: None,
+            row_heights_pt: BTreeMap::new(),
+            margins_mm: None,
+        }
+    }
+}
+
+impl SheetLayout {
+    /// Whether the sheet states any row height, default or explicit.
+    pub fn has_row_heights(&self) -> bool {
+        self.default_row_height_pt.is_some() || !self.row_heights_pt.is_empty()
+    }
+
+    /// Height of a 1-based row in points.
+    pub fn row_height_pt(&self, row: usize) -> f64 {
+        self.row_heights_pt
+            .get(&row)
+            .copied()
+            .unwrap_or_else(|| self.default_row_height_pt.unwrap_or(DEFAULT_ROW_HEIGHT_PT))
+    }
+}
+
+/// Rows of a sheet paginated by their heights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RowPagination {
+    /// Pages the rows fill at 100% scale.
+    pub pages: usize,
+    /// Height of the sheet in pages (fractional), for fit-to-page scaling.
+    pub pages_tall: f64,
+    /// Average height of the visible paginated rows, in millimeters.
+    pub average_row_height_mm: f64,
+    /// How full the last page is, from 0 to 1, for rounding (see `PageRounding`).
+    pub last_page_fill: f64,
+}
+
+/// Pages and scale of a sheet printed with fit to page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FittedPages {
+    /// Pages the sheet prints on at `scale_percent`.
+    pub pages: usize,
+    /// Print scale in whole percent (10–100).
+    pub scale_percent: u32,
+}
+
+/// A sheet as declared in `xl/workbook.xml`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkbookSheet {
+    /// Name shown on the sheet's tab.
+    pub name: String,
+    /// The sheet's `sheetId`.
+    pub sheet_id: Option<u32>,
+    /// The worksheet part (e.g. `xl/worksheets/sheet1.xml`), when its relationship resolves.
+    pub part: Option<String>,
+}
+
+/// Lists the sheets of a workbook in tab order (see the module docs).
+///
+/// # Returns
+///
+/// The sheets of the `<sheets>` element of `xl/workbook.xml`, in order; empty
+/// when the workbook part is missing or unreadable.
+pub fn workbook_sheets(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Vec<WorkbookSheet> {
+    let targets = read_relationship_targets(archive, "xl/_rels/workbook.xml.rels");
+    let mut sheets = Vec::new();
+    let Ok(file) = archive.by_name(WORKBOOK_PART) else { return sheets };
+    note_zip_entry(&file);
+    let mut reader = XmlReader::from_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"sheet" => {
+                // the relationship id is the namespaced `r:id`, not the numeric `sheetId`
+                let id = e
+                    .attributes()
+                    .flatten()
+                    .find(|attr| attr.key.prefix().is_some() && attr.key.local_name().as_ref() == b"id")
+                    .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()));
+                if let Some(name) = attribute(e, b"name") {
+                    sheets.push(WorkbookSheet {
+                        name,
+                        sheet_id: number_attribute(e, b"sheetId"),
+                        part: id.and_then(|id| targets.get(&id)).map(|target| resolve_part(WORKBOOK_PART, target)),
+                    });
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    sheets
+}
+
+/// Maps each sheet name to its worksheet part (e.g. `xl/worksheets/sheet1.xml`).
+///
+/// # Returns
+///
+/// The sheets listed in `xl/workbook.xml` whose relationship could be resolved;
+/// empty when the workbook part is missing or unreadable.
+pub fn worksheet_parts(archive: &mut ZipArchive<Cursor<&[u8]>>) -> BTreeMap<String, String> {
+    workbook_sheets(archive).into_iter().filter_map(|sheet| Some((sheet.name, sheet.part?))).collect()
+}
+
+/// Reads the print settings of a worksheet part.
+///
+/// # Returns
+///
+/// The sheet's layout, or the defaults (no fit to page) when the part is
+/// missing or has no print settings.
+pub fn read_sheet_layout(archive: &mut ZipArchive<Cursor<&[u8]>>, part: &str) -> SheetLayout {
+    let mut layout = SheetLayout::default();
+    let Ok(file) = archive.by_name(part) else { return layout };
+    note_zip_entry(&file);
+    let mut reader = XmlReader::from_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    let mut row = 0usize;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.local_name().as_ref() {
+                b"sheetFormatPr" => {
+                    layout.default_row_height_pt = row_height_attribute(e, b"defaultRowHeight");
+                }
+                b"row" => {
+                    // `r` is optional; rows without it follow the previous one
+                    row = number_attribute(e, b"r").unwrap_or(row + 1);
+                    if attribute(e, b"hidden").is_some_and(|v| v == "1" || v == "true") {
+                        layout.row_heights_pt.insert(row, 0.0);
+                    } else if let Some(height) = row_height_attribute(e, b"ht") {
+                        layout.row_heights_pt.insert(row, height);
+                    }
+                }
+                b"pageMargins" => {
+                    let mm = |name: &[u8]| {
+                        let inches = number_attribute(e, name).filter(|m: &f64| m.is_finite() && *m >= 0.0);
+                        inches.map_or(DEFAULT_SHEET_MARGIN_MM, |m| m * 25.4)
+                    };
+                    layout.margins_mm = Some((mm(b"top"), mm(b"bottom")));
+                }
+                b"pageSetUpPr" => {
+                    layout.fit_to_page = attribute(e, b"fitToPage").is_some_and(|v| v == "1" || v == "true");
+                }
+                b"pageSetup" => {
+                    layout.fit_to_width = number_attribute(e, b"fitToWidth").unwrap_or(1);
+                    layout.fit_to_height = number_attribute(e, b"fitToHeight").unwrap_or(1);
+                    // pageSetup follows the cell data; nothing after it matters
+                    break;
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    layout
+}
+
+/// Paginates rows of a sheet by their heights.
+///
+/// # Arguments
+///
+/// * `layout` - The sheet's layout, with its row heights
+/// * `rows` - 1-based numbers of the rows to print (the used range up to the last non-empty row)
+/// * `printable_height_mm` - Page height available to rows (paper height less margins)
+///
+/// # Returns
+///
+/// `None` when the sheet states no row heights (callers then fall back to a
+/// fixed number of rows per page) or the printable height isn't positive.
+/// A row taller than the printable height takes a page of its own.
+pub fn paginate_rows(
+    layout: &SheetLayout,
+    rows: RangeInclusive<usize>,
+    printable_height_mm: f64,
+) -> Option<RowPagination> {
+    if !layout.has_row_heights() || printable_height_mm <= 0.0 {
+        return None;
+    }
+    let mut visible = 0usize;
+    let mut pages = 0usize;
+    let mut used = 0.0;
+    let mut total = 0.0;
+    for row in rows {
+        let height = mm_from_pt(layout.row_height_pt(row));
+        if height > 0.0 {
+            visible += 1;
+        }
+        total += height;
+        if pages == 0 || (used + height > printable_height_mm && used > 0.0) {
+            pages += 1;
+            used = 0.0;
+        }
+        used += height;
+    }
+    Some(RowPagination {
+        pages,
+        pages_tall: total / printable_height_mm,
+        average_row_height_mm: if visible > 0 { total / visible as f64 } else { 0.0 },
+        last_page_fill: used / printable_height_mm,
+    })
+}
+
+/// Computes the pages and scale of a sheet fitted to `fit_width` × `fit_height` pages.
+///
+/// # Arguments
+///
+/// * `natural_wide` - Width of the sheet at 100% scale, in pages (may be fractional)
+/// * `natural_tall` - Height of the sheet at 100% scale, in pages (may be fractional)
+/// * `fit_width` - Pages across to fit to; 0 for unconstrained
+/// * `fit_height` - Pages down to fit to; 0 for unconstrained
+///
+/// # Returns
+///
+/// The largest scale (at most 100%, at least `MIN_FIT_SCALE`) at which the
+/// sheet meets both constraints, and the pages it then prints on. With
+/// `fit_width = 1, fit_height = 0` the sheet is one page wide and as tall as
+/// needed at that scale. At the 10% floor a very large sheet may still need
+/// more pages than requested, but never more than it prints on at 100%. An
+/// empty sheet prints on 0 pages at 100%.
+pub fn fit_to_page(natural_wide: f64, natural_tall: f64, fit_width: u32, fit_height: u32) -> FittedPages {
+    if !(natural_wide > 0.0 && natural_tall > 0.0) {
+        return FittedPages { pages: 0, scale_percent: 100 };
+    }
+    let limit = |target: u32, natural: f64| if target == 0 { 1.0 } else { (target as f64 / natural).min(1.0) };
+    let scale = limit(fit_width, natural_wide).min(limit(fit_height, natural_tall)).max(MIN_FIT_SCALE);
+    // the epsilon keeps exact fits (2.0 pages at 50%) from rounding up to an extra page;
+    // the casts saturate, so absurd extents can't overflow the product
+    let pages = |natural: f64, scale: f64| ((natural * scale - 1e-9).ceil() as usize).max(1);
+    let fitted = pages(natural_wide, scale).saturating_mul(pages(natural_tall, scale));
+    let natural = pages(natural_wide, 1.0).saturating_mul(pages(natural_tall, 1.0));
+    FittedPages { pages: fitted.min(natural), scale_percent: (scale * 100.0 + 1e-9).floor() as u32 }
+}
+
+/// Reads a row height in points, clamped to `MAX_ROW_HEIGHT_PT`; `None` when
+/// missing, negative, or not finite.
+fn row_height_attribute(e: &BytesStart, name: &[u8]) -> Option<f64> {
+    number_attribute(e, name).filter(|h: &f64| h.is_finite() && *h >= 0.0).map(|h| h.min(MAX_ROW_HEIGHT_PT))
+}
+
+fn number_attribute<T: std::str::FromStr>(e: &BytesStart, name: &[u8]) -> Option<T> {
+    attribute(e, name)?.trim().parse().ok()
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn fit(natural_wide: f64, natural_tall: f64, fit_width: u32, fit_height: u32) -> (usize, u32) {
+        let fitted = fit_to_page(natural_wide, natural_tall, fit_width, fit_height);
+        (fitted.pages, fitted.scale_percent)
+    }
+
+    #[test]
+    fn fit_to_page_arithmetic() {
+        // one page wide, as tall as needed
+        assert_eq!(fit(3.0, 10.0, 1, 0), (4, 33));
+        assert_eq!(fit(1.0, 10.0, 1, 0), (10, 100));
+        // as wide as needed, one page tall
+        assert_eq!(fit(1.0, 3.0, 0, 1), (1, 33));
+        assert_eq!(fit(1.0, 1.0, 1, 1), (1, 100));
+        // exact fits don't round up to an extra page
+        assert_eq!(fit(4.0, 4.0, 2, 2), (4, 50));
+        // small sheets aren't enlarged, and 0 × 0 leaves the sheet as it is
+        assert_eq!(fit(0.5, 0.5, 1, 1), (1, 100));
+        assert_eq!(fit(2.0, 2.5, 0, 0), (6, 100));
+        // the 10% floor can leave more pages than asked for
+        assert_eq!(fit(100.0, 1.0, 1, 1), (10, 10));
+        assert_eq!(fit(0.0, 0.0, 1, 1), (0, 100));
+    }
+
+    #[test]
+    fn print_settings_are_read_from_the_sheet() {
+        let xlsx = build_print_layout_xlsx(
+            r#"<sheetPr><pageSetUpPr fitToPage="1"/></sheetPr><sheetFormatPr defaultRowHeight="20"/>"#,
+            &number_rows(3, 2).replacen(r#"<row r="2">"#, r#"<row r="2" ht="30" customHeight="1">"#, 1),
+            r#"<pageMargins left="0.7" right="0.7" top="1" bottom="0.5"/><pageSetup fitToWidth="1" fitToHeight="0"/>"#,
+        );
+        let mut archive = ZipArchive::new(Cursor::new(xlsx.as_slice())).unwrap();
+        let part = worksheet_parts(&mut archive).remove("Sheet1").unwrap();
+        let layout = read_sheet_layout(&mut archive, &part);
+        assert!(layout.fit_to_page);
+        assert_eq!((layout.fit_to_width, layout.fit_to_height), (1, 0));
+        assert_eq!((layout.row_height_pt(1), layout.row_height_pt(2)), (20.0, 30.0));
+        assert_eq!(layout.margins_mm, Some((25.4, 12.7)));
+
+        let plain = build_xlsx(&[(3, 2)]);
+        let mut archive = ZipArchive::new(Cursor::new(plain.as_slice())).unwrap();
+        assert_eq!(read_sheet_layout(&mut archive, "xl/worksheets/sheet1.xml"), SheetLayout::default());
+    }
+
+    #[test]
+    fn fitted_sheets_count_their_fitted_pages() {
+        let fitted = |fit_width: u32, fit_height: u32| {
+            let xlsx = build_print_layout_xlsx(
+                r#"<sheetPr><pageSetUpPr fitToPage="1"/></sheetPr>"#,
+                &number_rows(120, 12),
+                &format!(r#"<pageSetup fitToWidth="{}" fitToHeight="{}"/>"#, fit_width, fit_height),
+            );
+            estimate(&xlsx, Some("wide.xlsx"), r#"{"columns_per_page":4,"verbosity":2}"#).unwrap()
+        };
+        // 3 pages wide and 3 tall at 100%
+        let result = fitted(1, 0);
+        let sheet = &result.sheets.as_ref().unwrap()[0];
+        assert_eq!((sheet.natural_pages, sheet.fitted_pages, sheet.scale_percent), (9, Some(1), Some(33)));
+        assert_eq!((sheet.pages, result.page_count), (1, 1));
+        assert!(result.notes.iter().any(|n| n.starts_with("⚠ Sheet 'Sheet1' prints at 33%")), "{:?}", result.notes);
+
+        let result = fitted(2, 0);
+        let sheet = &result.sheets.as_ref().unwrap()[0];
+        assert_eq!((sheet.fitted_pages, sheet.scale_percent, result.page_count), (Some(4), Some(66), 4));
+        assert!(!result.notes.iter().any(|n| n.contains("prints at")), "{:?}", result.notes);
+    }
+
+    #[test]
+    fn row_heights_are_clamped_and_sanitized() {
+        let rows = number_rows(4, 1)
+            .replacen(r#"<row r="1">"#, r#"<row r="1" ht="1e300">"#, 1)
+            .replacen(r#"<row r="2">"#, r#"<row r="2" ht="NaN">"#, 1)
+            .replacen(r#"<row r="3">"#, r#"<row r="3" ht="inf">"#, 1)
+            .replacen(r#"<row r="4">"#, r#"<row r="4" ht="-5">"#, 1);
+        let xlsx = build_print_layout_xlsx(r#"<sheetFormatPr defaultRowHeight="1e400"/>"#, &rows, "");
+        let mut archive = ZipArchive::new(Cursor::new(xlsx.as_slice())).unwrap();
+        let layout = read_sheet_layout(&mut archive, "xl/worksheets/sheet1.xml");
+        assert_eq!(layout.default_row_height_pt, None);
+        assert_eq!(layout.row_heights_pt.into_iter().collect::<Vec<_>>(), [(1, MAX_ROW_HEIGHT_PT)]);
+    }
+
+    #[test]
+    fn wrapped_text_rows_are_paginated_by_height() {
+        // every other row wraps its text onto three lines
+        let rows: String = (1..=60)
+            .map(|row| {
+                let height = if row % 2 == 0 { r#" ht="45" customHeight="1""# } else { "" };
+                format!(r#"<row r="{0}"{1}><c r="A{0}"><v>{0}</v></c></row>"#, row, height)
+            })
+            .collect();
+        let xlsx = build_print_layout_xlsx(r#"<sheetFormatPr defaultRowHeight="20"/>"#, &rows, "");
+        let result = estimate(&xlsx, Some("wrapped.xlsx"), r#"{"verbosity":2}"#).unwrap();
+        // 60 rows of 32.5 pt on average fill 688 mm, on pages of 259 mm between the margins
+        assert_eq!(result.page_count, 3);
+        let note = "Sheet 'Sheet1' rows: 60, pages: 3 (average row height 11.5 mm)";
+        assert!(result.notes.iter().any(|n| n == note), "{:?}", result.notes);
+        // without heights, or with rows_per_page given, rows are counted
+        assert_eq!(estimate(&build_xlsx(&[(60, 1)]), None, "{}").unwrap().page_count, 2);
+        assert_eq!(estimate(&xlsx, None, r#"{"rows_per_page":60}"#).unwrap().page_count, 1);
+    }
+
+    #[test]
+    fn absurd_sheet_extents_fit_on_their_natural_pages() {
+        assert_eq!(fit(f64::INFINITY, 1e300, 1, 1), (usize::MAX, 10));
+        assert_eq!(fit(f64::NAN, 2.0, 1, 1), (0, 100));
+        assert_eq!(fit(1e300, 1e300, 0, 0).0, usize::MAX);
+
+        let rows = number_rows(2, 3)
+            .replacen(r#"<row r="1">"#, r#"<row r="1" ht="1e300" customHeight="1">"#, 1)
+            .replacen(r#"<row r="2">"#, r#"<row r="2" ht="1e300" customHeight="1">"#, 1);
+        let xlsx = build_print_layout_xlsx(
+            r#"<sheetPr><pageSetUpPr fitToPage="1"/></sheetPr>"#,
+            &rows,
+            r#"<pageSetup fitToWidth="1" fitToHeight="1"/>"#,
+        );
+        for options in ["{}", r#"{"include_page_sizes":false}"#] {
+            let result = estimate(&xlsx, Some("tall.xlsx"), options).unwrap();
+            let sheet = &result.sheets.as_ref().unwrap()[0];
+            // two 409 pt rows don't fit one A4 page between the default margins
+            assert_eq!((sheet.natural_pages, sheet.fitted_pages, result.page_count), (2, Some(1), 1), "{}", options);
+        }
+    }
+
+    #[test]
+    fn sheets_are_listed_in_tab_order() {
+        let xlsx = build_reordered_xlsx(&[(10, 2), (50, 2), (90, 2)]);
+        let mut archive = ZipArchive::new(Cursor::new(xlsx.as_slice())).unwrap();
+        // the ZIP holds the last tab's part first
+        let first = archive.file_names().find(|name| name.contains("worksheets/")).map(String::from);
+        assert_eq!(first.as_deref(), Some("xl/worksheets/sheet1.xml"));
+
+        let sheets = workbook_sheets(&mut archive);
+        let sheet = |name: &str, id: u32| WorkbookSheet {
+            name: name.into(),
+            sheet_id: Some(id),
+            part: Some(format!("xl/worksheets/sheet{}.xml", id)),
+        };
+        assert_eq!(sheets, [sheet("Sheet1", 3), sheet("Sheet2", 2), sheet("Sheet3", 1)]);
+        assert_eq!(worksheet_parts(&mut archive)["Sheet1"], "xl/worksheets/sheet3.xml");
+
+        let unreadable = without_part(&xlsx, "xl/workbook.xml");
+        assert!(workbook_sheets(&mut ZipArchive::new(Cursor::new(unreadable.as_slice())).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+//! # Lazy Shared Strings
+//!
+//! Text cells of a workbook hold an index into `xl/sharedStrings.xml` rather
+//! than their text. Resolving every shared string of a large workbook is slow
+//! and holds all of its text in memory, while estimating column widths only
+//! needs the text of a few rows.
+//!
+//! `SharedStringIndex` reads the part once and records where each item
+//! (`<si>`) starts and ends, without decoding it. The text of an item is
+//! decoded when its width is asked for. `sample_column_widths` asks only for
+//! the cells of the first populated rows of a sheet, and keeps the widest
+//! cell of each column.
+//!
+//! ## Widths
+//!
+//! Widths are in character cells, as in text estimation: East Asian wide
+//! characters count as two (see `char_width`). Numbers, formula results, and
+//! booleans are measured as stored, not as formatted.
+
+use crate::file_utils::note_zip_entry;
+use crate::text_layout::char_width;
+use memchr::memmem;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use std::io::{BufReader, Cursor, Read};
+use std::ops::Range;
+use zip::ZipArchive;
+
+/// Part holding the shared strings of a workbook.
+pub const SHARED_STRINGS_PART: &str = "xl/sharedStrings.xml";
+
+/// Populated rows of a sheet sampled for column widths.
+pub const COLUMN_WIDTH_SAMPLE_ROWS: usize = 200;
+
+/// Byte ranges of the items of a shared strings part, decoded on demand.
+#[derive(Clone, Debug, Default)]
+pub struct SharedStringIndex {
+    xml: Vec<u8>,
+    items: Vec<Range<usize>>,
+}
+
+impl SharedStringIndex {
+    /// Reads and indexes the shared strings part of a workbook.
+    ///
+    /// # Returns
+    ///
+    /// The index, or `None` when the workbook has no readable shared strings part.
+    pub fn read(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<Self> {
+        let mut file = archive.by_name(SHARED_STRINGS_PART).ok()?;
+        note_zip_entry(&file);
+        let mut xml = Vec::new();
+        file.read_to_end(&mut xml).ok()?;
+        Some(Self::from_xml(xml))
+    }
+
+    /// Indexes shared strings XML by scanning for `<si>` items, without parsing them.
+    pub fn from_xml(xml: Vec<u8>) -> Self {
+        let mut items = Vec::new();
+        let close = memmem::Finder::new(b"</si>");
+        let mut pos = 0;
+        while let Some(found) = memmem::find(&xml[pos..], b"<si") {
+            let tag = pos + found;
+            let after = tag + 3;
+            let Some(tag_end) = memchr::memchr(b'>', &xml[after..]).map(|end| after + end) else { break };
+            match xml.get(after) {
+                Some(b'/' | b'>' | b' ' | b'\t' | b'\r' | b'\n') if xml[tag_end - 1] == b'/' => {
+                    // `<si/>` is an empty item
+                    items.push(tag_end..tag_end);
+                    pos = tag_end + 1;
+                }
+                Some(b'>' | b' ' | b'\t' | b'\r' | b'\n') => {
+                    let body = tag_end + 1;
+                    let end = close.find(&xml[body..]).map_or(xml.len(), |end| body + end);
+                    items.push(body..end);
+                    pos = end;
+                }
+                // another element starting with `si`
+                _ => pos = after,
+            }
+        }
+        Self { xml, items }
+    }
+
+    /// Number of items in the part.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the part has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Decodes the text of an item, leaving out phonetic runs (`<rPh>`), which don't print.
+    ///
+    /// # Returns
+    ///
+    /// The text, or `None` when the index is out of range.
+    pub fn text(&self, index: usize) -> Option<String> {
+        let range = self.items.get(index)?.clone();
+        let mut reader = XmlReader::from_reader(&self.xml[range]);
+        let mut text = String::new();
+        let (mut in_text, mut phonetic_depth) = (false, 0usize);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                    b"t" => in_text = true,
+                    b"rPh" => phonetic_depth += 1,
+                    _ => {}
+                },
+                Ok(Event::End(ref e)) => match e.local_name().as_ref() {
+                    b"t" => in_text = false,
+                    b"rPh" => phonetic_depth = phonetic_depth.saturating_sub(1),
+                    _ => {}
+                },
+                Ok(Event::Text(e)) if in_text && phonetic_depth == 0 => {
+                    if let Ok(unescaped) = e.unescape() {
+                        text.push_str(&unescaped);
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+        Some(text)
+    }
+
+    /// Width of an item in character cells (see the module docs).
+    pub fn width(&self, index: usize) -> Option<usize> {
+        self.text(index).map(|text| text_width(&text))
+    }
+}
+
+/// Finds the widest cell of each column in the first populated rows of a worksheet.
+///
+/// # Arguments
+///
+/// * `archive` - The workbook package
+/// * `part` - The worksheet part (see `worksheet_parts`)
+/// * `strings` - The workbook's shared strings, if it has any
+/// * `sample_rows` - How many populated rows to read, usually `COLUMN_WIDTH_SAMPLE_ROWS`
+///
+/// # Returns
+///
+/// The maximum width in character cells by 0-based column, up to the last
+/// column with content in the sample; empty when the part is missing.
+///
+/// # Notes
+///
+/// Only the shared strings used by the sampled cells are decoded, so the cost
+/// doesn't grow with the size of the sheet or of the shared strings part
+/// (beyond indexing it once).
+pub fn sample_column_widths(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    part: &str,
+    strings: Option<&SharedStringIndex>,
+    sample_rows: usize,
+) -> Vec<usize> {
+    let mut widths: Vec<usize> = Vec::new();
+    let Ok(file) = archive.by_name(part) else { return widths };
+    note_zip_entry(&file);
+    let mut reader = XmlReader::from_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    let mut populated_rows = 0usize;
+    let mut row_populated = false;
+    // the open cell: its column, type, and width so far, and whether its value is open
+    let mut column = 0usize;
+    let mut cell_type = String::new();
+    let mut cell_width = 0usize;
+    let mut in_value = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"row" => {
+                if row_populated {
+                    populated_rows += 1;
+                }
+                if populated_rows >= sample_rows {
+                    break;
+                }
+                row_populated = false;
+                // cells without `r` follow the previous one
+                column = usize::MAX;
+            }
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"c" => {
+                column = cell_column(e).unwrap_or(column.wrapping_add(1));
+                cell_type = attribute(e, b"t").unwrap_or_default();
+                cell_width = 0;
+            }
+            // values are in `<v>`, inline strings in `<is><t>`
+            Ok(Event::Start(ref e)) if matches!(e.local_name().as_ref(), b"v" | b"t") => in_value = true,
+            Ok(Event::End(ref e)) => match e.local_name().as_ref() {
+                b"v" | b"t" => in_value = false,
+                b"c" if cell_width > 0 => {
+                    if widths.len() <= column {
+                        widths.resize(column + 1, 0);
+                    }
+                    widths[column] = widths[column].max(cell_width);
+                    row_populated = true;
+                }
+                b"sheetData" => break,
+                _ => {}
+            },
+            Ok(Event::Text(e)) if in_value => {
+                let text = e.unescape().unwrap_or_default();
+                cell_width += match cell_type.as_str() {
+                    "s" => {
+                        let index = text.trim().parse::<usize>().ok();
+                        index.and_then(|index| strings.and_then(|strings| strings.width(index))).unwrap_or(0)
+                    }
+                    "b" if text.trim() == "1" => "TRUE".len(),
+                    "b" => "FALSE".len(),
+                    _ => text_width(&text),
+                };
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    widths
+}
+
+/// Width of text in character cells, taking its widest line.
+fn text_width(text: &str) -> usize {
+    text.lines().map(|line| line.chars().map(char_width).sum()).max().unwrap_or(0)
+}
+
+/// The 0-based column of a cell, from the letters of its `r` reference (e.g. `AB12`).
+fn cell_column(e: &BytesStart) -> Option<usize> {
+    let reference = attribute(e, b"r")?;
+    let letters = reference.bytes().take_while(u8::is_ascii_alphabetic);
+    let column = letters.fold(0usize, |column, letter| column * 26 + (letter.to_ascii_uppercase() - b'A') as usize + 1);
+    column.checked_sub(1)
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    const SHARED_STRINGS: &str = concat!(
+        r#"<sst xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" count="5">"#,
+        r#"<si><t>Region</t></si>"#,
+        r#"<si><r><t>North </t></r><r><rPr><b/></rPr><t>&amp; East</t></r></si>"#,
+        r#"<si/>"#,
+        r#"<si><t>東京</t><rPh sb="0" eb="2"><t>トウキョウ</t></rPh></si>"#,
+        r#"<si><t xml:space="preserve">two
+lines of text</t></si>"#,
+        r#"</sst>"#,
+    );
+
+    #[test]
+    fn items_are_decoded_on_demand() {
+        let strings = SharedStringIndex::from_xml(SHARED_STRINGS.as_bytes().to_vec());
+        assert_eq!(strings.len(), 5);
+        let texts: Vec<String> = (0..5).map(|i| strings.text(i).unwrap()).collect();
+        assert_eq!(texts, ["Region", "North & East", "", "東京", "two\nlines of text"]);
+        // wide characters take two cells, and the widest line counts
+        let widths: Vec<usize> = (0..5).map(|i| strings.width(i).unwrap()).collect();
+        assert_eq!(widths, [6, 12, 0, 4, 13]);
+        assert_eq!(strings.text(5), None);
+        // elements merely starting with `si` aren't items
+        assert!(SharedStringIndex::from_xml(b"<sst><sis><t>x</t></sis></sst>".to_vec()).is_empty());
+    }
+
+    #[test]
+    fn columns_take_their_widest_sampled_cell() {
+        let rows = concat!(
+            r#"<row r="1"><c r="A1" t="s"><v>0</v></c><c r="C1" t="s"><v>1</v></c></row>"#,
+            r#"<row r="2"><c r="A2" t="s"><v>3</v></c><c t="b"><v>0</v></c><c><v>1234.5</v></c></row>"#,
+            r#"<row r="3"><c r="A3" t="inlineStr"><is><t>Inline text cell</t></is></c></row>"#,
+            r#"<row r="4"/>"#,
+            r#"<row r="5"><c r="AA5"><v>7</v></c></row>"#,
+        );
+        let xlsx = with_part(&build_print_layout_xlsx("", rows, ""), SHARED_STRINGS_PART, SHARED_STRINGS);
+        let mut archive = ZipArchive::new(Cursor::new(&xlsx[..])).unwrap();
+        let strings = SharedStringIndex::read(&mut archive).unwrap();
+        let part = "xl/worksheets/sheet1.xml";
+
+        let widths = sample_column_widths(&mut archive, part, Some(&strings), COLUMN_WIDTH_SAMPLE_ROWS);
+        assert_eq!(widths.len(), 27);
+        assert_eq!(widths[..3], [16, 5, 12]);
+        assert_eq!(widths[26], 1);
+        // the empty row isn't one of the sampled rows
+        assert_eq!(sample_column_widths(&mut archive, part, Some(&strings), 3).len(), 3);
+        assert_eq!(sample_column_widths(&mut archive, part, Some(&strings), 1), [6, 0, 12]);
+        // without the shared strings the first row has nothing to measure, so isn't sampled
+        assert_eq!(sample_column_widths(&mut archive, part, None, 2), [16, 5, 6]);
+        assert!(sample_column_widths(&mut archive, "xl/worksheets/sheet9.xml", None, 2).is_empty());
+
+        let plain = build_xlsx(&[(2, 2)]);
+        assert!(SharedStringIndex::read(&mut ZipArchive::new(Cursor::new(&plain[..])).unwrap()).is_none());
+    }
+}
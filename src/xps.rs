@@ -0,0 +1,198 @@
+//! # XPS Packages
+//!
+//! Reads the fixed-layout structure of XPS and OpenXPS (OXPS) documents. The
+//! package relationship (`_rels/.rels`) points at a FixedDocumentSequence,
+//! which references one or more FixedDocuments, each of which lists its pages
+//! as `<PageContent>` elements. Counting those references is more reliable
+//! than counting `.fpage` parts, since a package may carry pages that no
+//! document references.
+//!
+//! ## Notes
+//!
+//! - XPS and OpenXPS use different namespaces for the same elements, so
+//!   elements and attributes are matched by local name
+//! - Page sizes come from each FixedPage's `Width`/`Height`, in 1/96 inch units
+
+use crate::file_utils::{is_system_entry, note_zip_entry, resolve_part};
+use crate::schema::EstimatorError;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use std::io::{BufReader, Cursor};
+use zip::ZipArchive;
+
+/// XPS lengths are expressed in 1/96 inch.
+pub const XPS_UNITS_PER_INCH: f64 = 96.0;
+
+/// Converts an XPS length (1/96 inch) to millimeters.
+pub fn mm_from_xps_units(units: f64) -> f64 {
+    units / XPS_UNITS_PER_INCH * 25.4
+}
+
+/// A page listed by a FixedDocument.
+pub struct XpsPageRef {
+    /// Part name of the FixedPage (without a leading `/`).
+    pub part: String,
+    /// `Width` hint on the `<PageContent>` element, if present.
+    pub width: Option<f64>,
+    /// `Height` hint on the `<PageContent>` element, if present.
+    pub height: Option<f64>,
+}
+
+/// Finds the FixedDocumentSequence part of an XPS package.
+///
+/// # Returns
+///
+/// The part name of the sequence, taken from the package's
+/// `fixedrepresentation` relationship, or else the first `.fdseq` part.
+/// `None` if the package is not an XPS document.
+pub fn find_document_sequence(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Option<String> {
+    let mut target = None;
+    let _ = visit_elements(archive, "_rels/.rels", |e| {
+        if e.local_name().as_ref() == b"Relationship"
+            && attribute(e, b"Type").is_some_and(|t| t.ends_with("/fixedrepresentation"))
+        {
+            target = attribute(e, b"Target");
+            return false;
+        }
+        true
+    });
+    if let Some(target) = target {
+        return Some(resolve_part("", &target));
+    }
+
+    archive
+        .file_names()
+        .find(|name| !is_system_entry(name) && name.to_ascii_lowercase().ends_with(".fdseq"))
+        .map(str::to_string)
+}
+
+/// Checks whether a ZIP package is an XPS document.
+pub fn is_xps_package(archive: &mut ZipArchive<Cursor<&[u8]>>) -> bool {
+    find_document_sequence(archive).is_some()
+}
+
+/// Lists the FixedDocument parts referenced by a FixedDocumentSequence, in order.
+pub fn read_document_references(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    sequence: &str,
+) -> Result<Vec<String>, EstimatorError> {
+    let mut documents = Vec::new();
+    visit_elements(archive, sequence, |e| {
+        if e.local_name().as_ref() == b"DocumentReference"
+            && let Some(source) = attribute(e, b"Source")
+        {
+            documents.push(resolve_part(sequence, &source));
+        }
+        true
+    })?;
+    Ok(documents)
+}
+
+/// Lists the pages of a FixedDocument from its `<PageContent>` elements, in order.
+pub fn read_page_refs(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    document: &str,
+) -> Result<Vec<XpsPageRef>, EstimatorError> {
+    let mut pages = Vec::new();
+    visit_elements(archive, document, |e| {
+        if e.local_name().as_ref() == b"PageContent" {
+            pages.push(XpsPageRef {
+                part: attribute(e, b"Source").map_or_else(String::new, |s| resolve_part(document, &s)),
+                width: attribute(e, b"Width").and_then(|v| v.parse().ok()),
+                height: attribute(e, b"Height").and_then(|v| v.parse().ok()),
+            });
+        }
+        true
+    })?;
+    Ok(pages)
+}
+
+/// Reads `Width` and `Height` (in 1/96 inch) from the root `<FixedPage>` element of a page part.
+///
+/// Only the start of the part is parsed; the page content is never read.
+pub fn read_fixed_page_size(archive: &mut ZipArchive<Cursor<&[u8]>>, page: &str) -> Option<(f64, f64)> {
+    let mut size = None;
+    visit_elements(archive, page, |e| {
+        if e.local_name().as_ref() == b"FixedPage" {
+            let width = attribute(e, b"Width").and_then(|v| v.parse::<f64>().ok());
+            let height = attribute(e, b"Height").and_then(|v| v.parse::<f64>().ok());
+            size = width.zip(height).filter(|(w, h)| *w > 0.0 && *h > 0.0);
+        }
+        false
+    })
+    .ok()?;
+    size
+}
+
+/// Streams the elements of a part, calling `visit` for each start or empty
+/// element until it returns `false`.
+fn visit_elements(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    part: &str,
+    mut visit: impl FnMut(&BytesStart) -> bool,
+) -> Result<(), EstimatorError> {
+    let file = archive
+        .by_name(part)
+        .map_err(|e| EstimatorError::XpsError(format!("Failed to read part {}: {:?}", part, e)))?;
+    note_zip_entry(&file);
+    let mut reader = XmlReader::from_reader(BufReader::new(file));
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if !visit(e) => break,
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(EstimatorError::XmlError(format!("{:?}", e))),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// Returns the unescaped value of the attribute with the given local name.
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|attr| attr.key.local_name().as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_utils::detect_type;
+    use crate::fixtures::*;
+    use crate::schema::PageSizeMm;
+
+    /// US Letter and A4 landscape, as a print driver writes them.
+    const LETTER: (f64, f64) = (816.0, 1056.0);
+    const A4_LANDSCAPE: (f64, f64) = (1122.5, 793.7);
+
+    #[test]
+    fn pages_and_sizes_of_a_two_page_print() {
+        for oxps in [false, true] {
+            let xps = build_xps(&[LETTER, A4_LANDSCAPE], oxps);
+            let result = estimate(&xps, Some("print.xps"), "{}").unwrap();
+            // the unreferenced page part isn't counted
+            assert_eq!(result.page_count, 2);
+            assert_eq!(result.page_sizes[0], PageSizeMm { width_mm: 8.5 * 25.4, height_mm: 11.0 * 25.4 });
+            assert!(result.page_sizes[1].approx_eq(&PageSizeMm { width_mm: 297.0, height_mm: 210.0 }));
+            assert_eq!((result.portrait_pages, result.landscape_pages), (Some(1), Some(1)));
+        }
+    }
+
+    #[test]
+    fn detected_by_extension_or_content() {
+        let xps = build_xps(&[LETTER], false);
+        assert_eq!(detect_type(Some("print.xps"), &xps), "xps");
+        assert_eq!(detect_type(Some("print.oxps"), &build_xps(&[LETTER], true)), "xps");
+        assert_eq!(detect_type(None, &xps), "xps");
+        assert_eq!(detect_type(Some("upload.zip"), &xps), "xps");
+    }
+
+    #[test]
+    fn package_without_pages_is_an_error() {
+        let xps = build_xps(&[], false);
+        let err = estimate(&xps, Some("print.xps"), "{}").unwrap_err();
+        assert_eq!(err.to_string(), "XPS error: No pages found in XPS document");
+    }
+}
@@ -0,0 +1,120 @@
+//! Performance regression checks for the PDF paths, on the large fixture of
+//! the `pdf` benchmark.
+//!
+//! The checks are ignored by default because they time a 100 MB input; run
+//! them with `cargo test --release --test pdf_regression -- --ignored`. The
+//! release workflow does before building. `PDF_BENCH_LARGE_MB` resizes the
+//! fixture as it does for the benchmark.
+//!
+//! - The structured path (object index + page tree traversal) is timed against
+//!   the `/Type /Page` scan and fails if their ratio is more than twice the
+//!   recorded baseline. Comparing against the scan keeps the check independent
+//!   of the machine's absolute speed.
+//! - `bytes_examined` is reported for every fixture, and the check fails if the
+//!   large fixture's examined-to-size ratio is more than twice its recorded
+//!   baseline, which catches a path that starts reading the file again.
+
+#[path = "../benches/fixtures/mod.rs"]
+mod fixtures;
+
+use page_counter_wasm::assembly::estimate_bytes;
+use page_counter_wasm::estimators::estimate_pdf_pages;
+use page_counter_wasm::pdf::{PdfCounter, PdfStrategy};
+use page_counter_wasm::schema::EstimateOptions;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Structured-path time divided by scan time on the large fixture, as last measured.
+const STRUCTURED_TO_SCAN_BASELINE: f64 = 1.2;
+
+/// Bytes examined divided by input size for the large fixture (count only), as last measured.
+const EXAMINED_TO_SIZE_BASELINE: f64 = 4.3;
+
+/// Fail when the ratio exceeds the baseline by this factor.
+const REGRESSION_FACTOR: f64 = 2.0;
+
+fn large_fixture_bytes() -> usize {
+    std::env::var("PDF_BENCH_LARGE_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100)
+        * 1024
+        * 1024
+}
+
+fn structured_page_count(bytes: &[u8]) -> usize {
+    PdfCounter::new(vec![PdfStrategy::PageTree]).count(bytes).count
+}
+
+/// Median wall time of `runs` calls.
+fn median_time(runs: usize, mut f: impl FnMut()) -> Duration {
+    let mut times: Vec<Duration> = (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[runs / 2]
+}
+
+#[test]
+#[ignore = "times a 100 MB fixture; run in release with --ignored"]
+fn structured_path_keeps_pace_with_the_scan() {
+    let bytes = fixtures::large(large_fixture_bytes());
+    let options = EstimateOptions::default();
+    assert_eq!(structured_page_count(&bytes), estimate_pdf_pages(&bytes, &options).unwrap().page_count);
+
+    let scan = median_time(5, || {
+        black_box(estimate_pdf_pages(black_box(&bytes), &options).ok());
+    });
+    let structured = median_time(5, || {
+        black_box(structured_page_count(black_box(&bytes)));
+    });
+
+    let ratio = structured.as_secs_f64() / scan.as_secs_f64();
+    let limit = STRUCTURED_TO_SCAN_BASELINE * REGRESSION_FACTOR;
+    println!(
+        "structured/scan ratio: {:.2} (scan {:?}, structured {:?}, limit {:.2})",
+        ratio, scan, structured, limit
+    );
+    assert!(
+        ratio <= limit,
+        "structured PDF path regressed: {:.2}x the scan time, limit is {:.2}x",
+        ratio,
+        limit
+    );
+}
+
+#[test]
+#[ignore = "reads a 100 MB fixture; run in release with --ignored"]
+fn estimate_examines_each_byte_a_bounded_number_of_times() {
+    let options = EstimateOptions { include_page_sizes: Some(false), ..Default::default() };
+    let inputs = [
+        ("small_classic", fixtures::small_classic(10)),
+        ("xref_stream", fixtures::xref_stream(10)),
+        ("bookmark_heavy", fixtures::bookmark_heavy(200, 5000)),
+        ("large", fixtures::large(large_fixture_bytes())),
+    ];
+    let mut large_ratio = 0.0;
+    for (name, bytes) in inputs {
+        let examined = estimate_bytes(&bytes, Some("fixture.pdf"), &options)
+            .1
+            .ok()
+            .and_then(|result| result.bytes_examined)
+            .unwrap_or(0);
+        let ratio = examined as f64 / bytes.len() as f64;
+        println!("bytes examined ({}): {} of {} ({:.2}x)", name, examined, bytes.len(), ratio);
+        if name == "large" {
+            large_ratio = ratio;
+        }
+    }
+    let limit = EXAMINED_TO_SIZE_BASELINE * REGRESSION_FACTOR;
+    assert!(
+        large_ratio <= limit,
+        "PDF estimate examined {:.2}x the large fixture's size, limit is {:.2}x",
+        large_ratio,
+        limit
+    );
+}
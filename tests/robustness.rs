@@ -0,0 +1,140 @@
+//! Malformed input through the PDF entry points, in a form `cargo test` runs.
+//!
+//! The fuzz targets find crashes but need nightly and run for as long as
+//! they're given; these checks replay the inputs in `fuzz/regressions/` and
+//! run a bounded number of random and mutated inputs on every build.
+//!
+//! - Every file in `fuzz/regressions/` goes through the same calls as the
+//!   `pdf` fuzz target.
+//! - Arbitrary bytes and byte-level mutations of the benchmark PDFs must
+//!   neither panic nor take pathologically long.
+
+#[allow(dead_code)]
+#[path = "../benches/fixtures/mod.rs"]
+mod fixtures;
+
+use page_counter_wasm::assembly::estimate_bytes;
+use page_counter_wasm::estimators::estimate_pdf_pages;
+use page_counter_wasm::pdf::{PdfCounter, PdfStrategy};
+use page_counter_wasm::pdf_color::analyze_page_colors;
+use page_counter_wasm::pdf_utils::{xref_shift, ObjectIndex};
+use page_counter_wasm::schema::EstimateOptions;
+use page_counter_wasm::validation::validate_bytes;
+use proptest::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Longest any single input may take through [`check_pdf`], generous enough
+/// for unoptimized builds.
+const MAX_ELAPSED: Duration = Duration::from_secs(10);
+
+/// Tokens spliced in by [`mutated_pdfs_never_panic`], as in the `pdf_fixtures` fuzz target.
+const TOKENS: &[&[u8]] = &[
+    b"<<", b">>", b"[", b"]", b" obj", b"endobj", b"stream\n", b"endstream", b" 0 R",
+    b"/Type /Page", b"/Type /Pages", b"/Kids [", b"/Parent", b"/Resources", b"/XObject",
+    b"/Subtype /Form", b"/ColorSpace", b"/Filter /FlateDecode", b"/Root", b"startxref",
+    b"xref", b"%%EOF", b"/Encrypt",
+];
+
+/// Runs `data` through every PDF entry point, failing if that takes longer than [`MAX_ELAPSED`].
+fn check_pdf(data: &[u8]) {
+    let start = Instant::now();
+    let options = EstimateOptions {
+        analyze_color: Some(true),
+        extract_pdf_text_metrics: Some(true),
+        ..Default::default()
+    };
+    let _ = estimate_pdf_pages(data, &options);
+    let _ = ObjectIndex::build(data).page_refs();
+    let _ = PdfCounter::new(vec![PdfStrategy::PageTree, PdfStrategy::CountScan, PdfStrategy::TypeScan]).count(data);
+    let _ = PdfCounter::default().page_sizes(data);
+    let _ = analyze_page_colors(data);
+    let _ = validate_bytes(data, Some("fuzz.pdf"));
+    let _ = estimate_bytes(data, None, &EstimateOptions::default());
+    assert!(start.elapsed() < MAX_ELAPSED, "input took {:?}", start.elapsed());
+}
+
+#[test]
+fn regression_inputs_run_through_every_pdf_entry_point() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/regressions");
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pdf"))
+        .collect();
+    paths.sort();
+    assert!(paths.len() >= 5, "expected the checked-in regressions, found {:?}", paths);
+    for path in paths {
+        check_pdf(&std::fs::read(&path).unwrap());
+    }
+}
+
+#[test]
+fn xref_subsections_at_the_last_object_number_are_read() {
+    // `startxref` misses a table whose subsection starts at object 4294967295
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/regressions/xref_subsection_at_max_object_number.pdf");
+    let pdf = std::fs::read(path).unwrap();
+    assert_eq!(xref_shift(&pdf), None);
+    assert_eq!(estimate_pdf_pages(&pdf, &EstimateOptions::default()).unwrap().page_count, 1);
+}
+
+/// One edit applied by [`mutated_pdfs_never_panic`]: at a position (taken
+/// modulo the length), overwrite a byte, splice in a token, or cut the rest.
+#[derive(Debug, Clone)]
+enum Edit {
+    Byte(usize, u8),
+    Token(usize, usize),
+    Truncate(usize),
+}
+
+fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        4 => (any::<usize>(), any::<u8>()).prop_map(|(at, b)| Edit::Byte(at, b)),
+        4 => (any::<usize>(), 0..TOKENS.len()).prop_map(|(at, t)| Edit::Token(at, t)),
+        1 => any::<usize>().prop_map(Edit::Truncate),
+    ]
+}
+
+fn apply(mut data: Vec<u8>, edits: &[Edit]) -> Vec<u8> {
+    for edit in edits {
+        let len = data.len().max(1);
+        match *edit {
+            Edit::Byte(at, b) => {
+                if let Some(slot) = data.get_mut(at % len) {
+                    *slot = b;
+                }
+            }
+            Edit::Token(at, t) => {
+                let at = (at % len).min(data.len());
+                data.splice(at..at, TOKENS[t].iter().copied());
+            }
+            Edit::Truncate(at) => data.truncate(at % len),
+        }
+    }
+    data
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn arbitrary_bytes_never_panic(data in proptest::collection::vec(any::<u8>(), 0..4096)) {
+        check_pdf(&data);
+    }
+
+    #[test]
+    fn arbitrary_bytes_after_a_pdf_header_never_panic(data in proptest::collection::vec(any::<u8>(), 0..4096)) {
+        let mut pdf = b"%PDF-1.7\n".to_vec();
+        pdf.extend_from_slice(&data);
+        check_pdf(&pdf);
+    }
+
+    #[test]
+    fn mutated_pdfs_never_panic(
+        pages in 1..6usize,
+        xref_stream in any::<bool>(),
+        edits in proptest::collection::vec(edit(), 1..8),
+    ) {
+        let pdf = if xref_stream { fixtures::xref_stream(pages) } else { fixtures::small_classic(pages) };
+        check_pdf(&apply(pdf, &edits));
+    }
+}
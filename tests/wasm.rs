@@ -0,0 +1,566 @@
+//! The wasm-bindgen exports, called the way JavaScript calls them, in a browser:
+//!
+//! ```sh
+//! wasm-pack test --headless --firefox -- --features test-fixtures,fetch
+//! ```
+//!
+//! Native `cargo test` skips this file; the unit tests cover the estimators,
+//! while these checks cover what crosses into JavaScript.
+
+#![cfg(target_arch = "wasm32")]
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use js_sys::{Array, Function, Reflect, Uint8Array};
+use page_counter_wasm::assembly::{
+    convert_result_units, estimate_archive, estimate_document, estimate_document_base64, estimate_document_cached,
+    estimate_document_summary, estimate_pdf_with_pdfjs, merge_results, options_schema, parse_result_json,
+    supported_formats, validate_document,
+};
+use page_counter_wasm::cache::{clear_estimate_cache, configure_estimate_cache};
+use page_counter_wasm::deadline::{DEADLINE_CONFIDENCE, DEADLINE_WARNING};
+use page_counter_wasm::diagnostics::set_clock;
+use page_counter_wasm::fallback::{register_fallback_estimator, FALLBACK_NOTE};
+use page_counter_wasm::fixtures::*;
+use page_counter_wasm::logging::{log_level, set_log_level, DEFAULT_LOG_LEVEL};
+use page_counter_wasm::pdf_page_iterator::PdfPageSizeIterator;
+use page_counter_wasm::schema::SCHEMA_VERSION;
+use page_counter_wasm::stats::memory_stats;
+use serde_json::{json, Value};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Bytes no built-in estimator supports.
+const ACME: &[u8] = &[0x41, 0x43, 0x4d, 0x45, 0, 1, 2, 0xff, 0xfe, 7, 0, 0x80];
+
+/// Parses the JSON string an export returned.
+fn json(value: JsValue) -> Value {
+    serde_json::from_str(&value.as_string().expect("exports return JSON strings")).unwrap()
+}
+
+/// A JavaScript `function (bytes, detected, filename)` with the given body.
+fn callback(body: &str) -> Function {
+    Function::new_with_args("bytes, detected, filename", body)
+}
+
+#[wasm_bindgen_test]
+fn fallback_estimator_handles_what_it_accepts() {
+    register_fallback_estimator(Some(callback(
+        "return filename && filename.endsWith('.acme') \
+            ? { page_count: bytes.length, page_sizes: [], notes: [detected] } : null;",
+    )));
+    let handled = json(estimate_document(ACME, Some("plan.acme".into()), None));
+    let declined = json(estimate_document(ACME, Some("plan.bin".into()), None));
+    register_fallback_estimator(None);
+    let unregistered = json(estimate_document(ACME, Some("plan.acme".into()), None));
+
+    assert_eq!(handled["page_count"], ACME.len());
+    assert_eq!(handled["notes"], serde_json::json!(["unknown", FALLBACK_NOTE]));
+    assert_eq!(declined["error_code"], "unsupported_format");
+    assert_eq!(unregistered["error_code"], "unsupported_format");
+}
+
+#[wasm_bindgen_test]
+fn fallback_results_are_validated() {
+    register_fallback_estimator(Some(callback(
+        "return { page_count: 2, page_sizes: [{ width_mm: 210, height_mm: 297 }], notes: [] };",
+    )));
+    let result = json(estimate_document(ACME, Some("plan.acme".into()), None));
+    register_fallback_estimator(None);
+    assert_eq!(result["error_code"], "custom_estimator_error");
+    assert!(result["error"].as_str().unwrap().contains("lists 1 page sizes for 2 pages"), "{}", result);
+}
+
+#[wasm_bindgen_test]
+fn fallback_exceptions_become_errors() {
+    register_fallback_estimator(Some(callback("throw new Error('acme reader failed');")));
+    let result = json(estimate_document(ACME, Some("plan.acme".into()), None));
+    register_fallback_estimator(None);
+    assert_eq!(result["error_code"], "custom_estimator_error");
+    assert!(result["error"].as_str().unwrap().contains("fallback estimator threw: acme reader failed"), "{}", result);
+}
+
+#[wasm_bindgen_test]
+fn cached_estimates_are_marked_and_keyed_by_options() {
+    clear_estimate_cache();
+    let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+    let estimate = |options: &str| json(estimate_document_cached(&pdf, Some("a.pdf".into()), Some(options.into())));
+    let first = estimate(r#"{"analyze_color":false,"verbosity":1}"#);
+    assert_eq!(first.get("cached"), None);
+    // the same options, written differently
+    let again = estimate(r#"{ "verbosity": 1, "analyze_color": false }"#);
+    assert_eq!(again["cached"], true);
+    assert_eq!(again["page_count"], first["page_count"]);
+    assert_eq!(estimate(r#"{"analyze_color":true,"verbosity":1}"#).get("cached"), None);
+    clear_estimate_cache();
+    assert_eq!(estimate(r#"{"analyze_color":false,"verbosity":1}"#).get("cached"), None);
+}
+
+/// Reads `key` of a JavaScript object.
+fn get(object: &JsValue, key: &str) -> JsValue {
+    Reflect::get(object, &JsValue::from_str(key)).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn page_sizes_are_iterated_lazily() {
+    let mut pages = PdfPageSizeIterator::new(&build_pdf(500, XrefStyle::Classic, false, false)).unwrap();
+    let read_before = pages.objects_read();
+    let first = pages.next_step();
+    assert_eq!(get(&first, "done"), JsValue::FALSE);
+    let value = get(&first, "value");
+    assert_eq!(get(&value, "index").as_f64(), Some(0.0));
+    assert_eq!(get(&value, "width_mm").as_f64().map(f64::round), Some(210.0));
+    assert_eq!(get(&value, "height_mm").as_f64().map(f64::round), Some(297.0));
+    // one more object per page consumed, not the whole tree
+    assert_eq!(pages.objects_read(), read_before + 2);
+    pages.next_step();
+    assert_eq!(pages.objects_read(), read_before + 3);
+
+    let mut rotated = PdfPageSizeIterator::new(&build_mixed_orientation_pdf()).unwrap();
+    let steps: Vec<JsValue> = std::iter::repeat_with(|| rotated.next_step().into()).take(7).collect();
+    let widths: Vec<Option<f64>> =
+        steps[..6].iter().map(|step| get(&get(step, "value"), "width_mm").as_f64().map(f64::round)).collect();
+    assert_eq!(widths, [Some(210.0), Some(297.0), Some(297.0), Some(210.0), Some(210.0), Some(210.0)]);
+    assert_eq!(get(&steps[6], "done"), JsValue::TRUE);
+    assert!(get(&steps[6], "value").is_undefined());
+}
+
+#[wasm_bindgen_test]
+fn page_size_iterators_need_a_page_tree() {
+    let err = PdfPageSizeIterator::new(b"%PDF-1.7\nno objects\n%%EOF").err().unwrap();
+    assert_eq!(json(err)["error_code"], "pdf_error");
+}
+
+/// Replaces the console's methods with recorders of `[method, message]`
+/// until the returned function is called, which restores them.
+fn patch_console(calls: &Array) -> Function {
+    let patch = Function::new_with_args(
+        "calls",
+        "const methods = ['log', 'info', 'warn', 'error', 'debug'];
+        const originals = methods.map(method => console[method]);
+        methods.forEach(method => { console[method] = message => calls.push([method, String(message)]); });
+        return () => methods.forEach((method, i) => { console[method] = originals[i]; });",
+    );
+    patch.call1(&JsValue::NULL, calls).unwrap().into()
+}
+
+#[wasm_bindgen_test]
+async fn silent_mode_writes_nothing_to_the_console() {
+    let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+    let calls = Array::new();
+    let restore = patch_console(&calls);
+
+    set_log_level("silent").unwrap();
+    let result = json(estimate_pdf_with_pdfjs(pdf.clone()).await);
+    let _ = estimate_document(ACME, Some("notes.acme".into()), None);
+    assert_eq!(calls.length(), 0);
+    // the fallback is reported in the result rather than on the console
+    assert_eq!(result["page_count"], 3);
+    assert!(result["notes"][0].as_str().unwrap().starts_with("PDF.js not available"), "{}", result);
+
+    set_log_level("debug").unwrap();
+    let _ = estimate_pdf_with_pdfjs(pdf).await;
+    set_log_level("error").unwrap();
+    restore.call0(&JsValue::NULL).unwrap();
+    let methods: Vec<JsValue> = calls.iter().map(|call| Array::from(&call).get(0)).collect();
+    assert_eq!(methods, [JsValue::from_str("debug")]);
+}
+
+#[wasm_bindgen_test]
+fn unknown_log_levels_are_rejected() {
+    let err = set_log_level("verbose").unwrap_err();
+    assert_eq!(json(err)["error_code"], "invalid_options");
+    assert_eq!(log_level(), DEFAULT_LOG_LEVEL);
+}
+
+/// One document of every kind the builders make, with its file name.
+fn corpus() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("report.pdf", build_pdf(4, XrefStyle::Classic, false, false)),
+        ("letter.docx", build_docx(20, 2, Some(3))),
+        ("budget.xlsx", build_xlsx(&[(90, 4), (10, 2)])),
+        ("deck.pptx", build_pptx(6, 1)),
+        ("notes.txt", "A few words. ".repeat(800).into_bytes()),
+        ("readme.md", "# Title\n\nSome *text*.\n".repeat(200).into_bytes()),
+        ("scan.xps", build_xps(&[(210.0, 297.0), (297.0, 210.0)], false)),
+        ("poster.ps", build_postscript(3, false)),
+        ("comic.cbz", build_cbz(5, None)),
+        ("contacts.vcf", build_vcards(30)),
+        ("talks.ics", build_calendar(12)),
+        ("analysis.ipynb", build_notebook(4)),
+        ("book.xml", build_docbook_book(3, 2)),
+    ]
+}
+
+/// The result JSON without the keys only the JSON exports add.
+fn without_envelope(mut result: Value) -> Value {
+    let object = result.as_object_mut().unwrap();
+    assert_eq!(object.remove("schema_version"), Some(json!(SCHEMA_VERSION)));
+    assert!(object.remove("effective_options").is_some());
+    result
+}
+
+#[wasm_bindgen_test]
+fn every_format_crosses_the_boundary_unchanged() {
+    for (name, bytes) in corpus() {
+        let native = estimate(&bytes, Some(name), "{}").unwrap();
+        let result = json(estimate_document(&bytes, Some(name.into()), None));
+        assert_eq!(without_envelope(result), serde_json::to_value(&native).unwrap(), "{}", name);
+
+        let report = json(validate_document(&bytes, Some(name.into())));
+        assert_eq!(report["valid"], true, "{}: {}", name, report);
+        let format = report["format"].as_str().unwrap();
+        assert_eq!(estimate_document_summary(&bytes, Some(name.into()), None), native.summary(format));
+
+        // the file name is only a hint
+        let unnamed = json(estimate_document(&bytes, None, None));
+        assert_eq!(unnamed["page_count"], native.page_count, "{}", name);
+    }
+}
+
+#[wasm_bindgen_test]
+fn base64_inputs_decode_in_either_alphabet() {
+    let docx = build_docx(20, 2, Some(3));
+    let expected = estimate_document(&docx, Some("letter.docx".into()), None).as_string();
+    let url_safe = URL_SAFE_NO_PAD.encode(&docx);
+    assert!(url_safe.contains(['-', '_']), "the fixture should need the URL-safe characters");
+    for encoded in [STANDARD.encode(&docx), url_safe] {
+        let result = estimate_document_base64(&encoded, Some("letter.docx".into()), None);
+        assert_eq!(result.as_string(), expected);
+    }
+
+    let err = json(estimate_document_base64("not base64!", Some("letter.docx".into()), None));
+    assert_eq!(err["error_code"], "invalid_input");
+    assert!(err["error"].as_str().unwrap().contains("base64 decode failed"), "{}", err);
+}
+
+#[wasm_bindgen_test]
+fn options_json_problems_are_reported() {
+    let text = "A few words. ".repeat(400).into_bytes();
+    let defaults = json(estimate_document(&text, Some("a.txt".into()), None));
+
+    // unparsable options fall back to the defaults, with a warning first
+    let result = json(estimate_document(&text, Some("a.txt".into()), Some("{chars_per_page: 10".into())));
+    assert_eq!(result["page_count"], defaults["page_count"]);
+    assert!(result["notes"][0].as_str().unwrap().starts_with("⚠ Options could not be parsed"), "{}", result);
+
+    let result = json(estimate_document(&text, Some("a.txt".into()), Some(r#"{"chars_per_pag": 10}"#.into())));
+    assert_eq!(result["notes"][0], "⚠ Unknown option 'chars_per_pag' ignored");
+    let summary = estimate_document_summary(&text, Some("a.txt".into()), Some(r#"{"chars_per_pag": 10}"#.into()));
+    assert!(summary.ends_with("warnings=1"), "{}", summary);
+
+    // values no estimator can use are errors
+    let result = json(estimate_document(&text, Some("a.txt".into()), Some(r#"{"chars_per_page": 0}"#.into())));
+    assert_eq!(result["error_code"], "invalid_options");
+}
+
+#[wasm_bindgen_test]
+async fn errors_have_the_same_shape_in_every_export() {
+    let keys = |error: &Value| -> Vec<String> {
+        let mut keys: Vec<String> = error.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+    let full = ["detected", "error", "error_code", "schema_version"];
+    let errors = [
+        json(estimate_document(b"", Some("empty.pdf".into()), None)),
+        json(estimate_document_cached(b"", Some("empty.pdf".into()), None)),
+        json(estimate_document_base64("%%%", None, None)),
+        json(estimate_archive(b"not an archive", None)),
+        json(estimate_pdf_with_pdfjs(b"%PDF-1.7\nno objects\n%%EOF".to_vec()).await),
+    ];
+    for error in &errors {
+        assert_eq!(keys(error), full, "{}", error);
+        assert!(!error["error"].as_str().unwrap().is_empty());
+    }
+    assert_eq!(errors[0]["error_code"], "empty_input");
+    assert_eq!(errors[3]["error_code"], "zip_error");
+    assert_eq!(errors[4]["error_code"], "pdf_error");
+
+    // the exports reading results have nothing to detect
+    let errors = [
+        (json(merge_results("{}".into(), None)), "general"),
+        (json(parse_result_json(r#"{"error": "Input is empty"}"#.into())), "invalid_result"),
+        (json(convert_result_units(V1_RESULT_JSON.into(), "cm")), "invalid_options"),
+    ];
+    for (error, code) in errors {
+        assert_eq!(keys(&error), ["error", "error_code", "schema_version"], "{}", error);
+        assert_eq!(error["error_code"], code);
+    }
+    assert_eq!(estimate_document_summary(b"", Some("empty.pdf".into()), None), "pdf error=empty_input");
+}
+
+#[wasm_bindgen_test]
+async fn async_exports_resolve_instead_of_rejecting() {
+    // without PDF.js the Rust parser answers, and its failures resolve too
+    let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+    let result = json(estimate_pdf_with_pdfjs(pdf).await);
+    assert_eq!(result["page_count"], 3);
+    let error = json(estimate_pdf_with_pdfjs(Vec::new()).await);
+    assert_eq!((&error["error_code"], &error["detected"]), (&json!("pdf_error"), &json!("pdf")));
+
+    // Result-returning exports throw their error JSON
+    assert_eq!(json(set_log_level("loud").unwrap_err())["error_code"], "invalid_options");
+    assert_eq!(json(PdfPageSizeIterator::new(b"").err().unwrap())["error_code"], "pdf_error");
+}
+
+#[wasm_bindgen_test]
+fn stored_results_are_read_and_converted() {
+    let upgraded = json(parse_result_json(V1_RESULT_JSON.into()));
+    assert_eq!((&upgraded["schema_version"], &upgraded["page_count"]), (&json!(SCHEMA_VERSION), &json!(2)));
+
+    let current = estimate_document(&build_pptx(6, 1), Some("deck.pptx".into()), None).as_string().unwrap();
+    assert_eq!(json(parse_result_json(current.clone())), serde_json::from_str::<Value>(&current).unwrap());
+
+    let in_points = json(convert_result_units(V1_RESULT_JSON.into(), "pt"));
+    let widths: Vec<f64> =
+        in_points["page_sizes_pt"].as_array().unwrap().iter().map(|size| size["width_pt"].as_f64().unwrap()).collect();
+    assert_eq!(widths.iter().map(|w| w.round()).collect::<Vec<_>>(), [595.0, 612.0]);
+    let again = convert_result_units(in_points.to_string(), "pt");
+    assert_eq!(json(again), in_points);
+
+    let in_inches = json(convert_result_units(V1_RESULT_JSON.into(), "in"));
+    assert_eq!(in_inches["page_sizes_in"][1]["height_in"], 11.0);
+    assert_eq!(in_inches["page_sizes"], upgraded["page_sizes"]);
+}
+
+#[wasm_bindgen_test]
+fn archives_and_merged_results_cross_the_boundary() {
+    let pdf = build_pdf(4, XrefStyle::Classic, false, false);
+    let docx = build_docx(20, 2, Some(3));
+    let archive = json(estimate_archive(&build_zip(&[("a.pdf", &pdf), ("b.docx", &docx)]), None));
+    assert_eq!(archive["schema_version"], SCHEMA_VERSION);
+    assert_eq!(archive["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(archive["total_page_count"], 7);
+
+    let results = format!(
+        "[{}, {}]",
+        estimate_document(&pdf, None, None).as_string().unwrap(),
+        estimate_document(&docx, None, None).as_string().unwrap()
+    );
+    let merged = json(merge_results(results, Some(r#"{"duplex": true}"#.into())));
+    assert_eq!(merged["page_count"], 7);
+    assert_eq!(merged["print_summary"]["sheets_of_paper"], 4);
+}
+
+#[wasm_bindgen_test]
+fn formats_and_options_are_described() {
+    let formats = json(supported_formats());
+    let pdf = formats.as_array().unwrap().iter().find(|format| format["id"] == "pdf").unwrap();
+    assert!(pdf["extensions"].as_array().unwrap().contains(&json!("pdf")));
+    assert!(pdf["mime_types"].as_array().unwrap().contains(&json!("application/pdf")));
+
+    let schema = json(options_schema());
+    assert_eq!(schema["properties"]["chars_per_page"]["minimum"], 1);
+    assert_eq!(schema["properties"]["debug"]["default"], false);
+}
+
+#[wasm_bindgen_test]
+fn cache_limits_show_in_memory_stats() {
+    clear_estimate_cache();
+    configure_estimate_cache(1, None);
+    let (pdf, docx) = (build_pdf(2, XrefStyle::Classic, false, false), build_docx(20, 2, Some(3)));
+    let estimates = json(memory_stats())["estimates"].as_u64().unwrap();
+    let _ = estimate_document_cached(&pdf, None, None);
+    let _ = estimate_document_cached(&docx, None, None);
+    // the PDF made way for the DOCX
+    assert_eq!(json(estimate_document_cached(&pdf, None, None)).get("cached"), None);
+
+    let stats = json(memory_stats());
+    assert_eq!((&stats["cache"]["entries"], &stats["cache"]["max_entries"]), (&json!(1), &json!(1)));
+    assert_eq!(stats["estimates"].as_u64().unwrap(), estimates + 3);
+    assert!(stats["memory_bytes"].as_u64().unwrap() > 0);
+    configure_estimate_cache(16, None);
+    clear_estimate_cache();
+}
+
+#[wasm_bindgen_test]
+fn memory_stats_follow_estimates_and_are_cheap() {
+    let before = json(memory_stats());
+    let pdf = build_pdf(40, XrefStyle::Classic, false, false);
+    let _ = estimate_document(&pdf, None, Some(r#"{"max_input_bytes": 10000000}"#.into()));
+    let after = json(memory_stats());
+    assert_eq!(after["estimates"].as_u64().unwrap(), before["estimates"].as_u64().unwrap() + 1);
+    assert_eq!(after["last_peak_buffer_bytes"], pdf.len());
+    assert_eq!(after["max_input_bytes"], 10000000);
+    let pages = after["memory_pages"].as_u64().unwrap();
+    assert_eq!(after["memory_bytes"].as_u64().unwrap(), pages * 65536);
+
+    // polling only copies counters
+    let start = js_sys::Date::now();
+    for _ in 0..1000 {
+        let _ = memory_stats();
+    }
+    assert!(js_sys::Date::now() - start < 500.0, "1000 calls took {} ms", js_sys::Date::now() - start);
+    assert_eq!(json(memory_stats())["estimates"], after["estimates"]);
+}
+
+/// A clock that starts at 0 and moves `step` ms on at every reading.
+fn stepping_clock(step: f64) -> Function {
+    Function::new_with_args("step", "let now = 0; return () => (now += step);")
+        .call1(&JsValue::NULL, &step.into())
+        .unwrap()
+        .into()
+}
+
+#[wasm_bindgen_test]
+fn timings_come_from_the_injected_clock() {
+    let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+    set_clock(Some(stepping_clock(5.0)));
+    let result = json(estimate_document(&pdf, None, Some(r#"{"debug": true}"#.into())));
+    set_clock(None);
+    let diagnostics = &result["diagnostics"];
+    assert_eq!(diagnostics["index_ms"], 5.0);
+    let timings: Vec<&Value> = diagnostics["strategies"].as_array().unwrap().iter().map(|s| &s["ms"]).collect();
+    assert_eq!(timings, [&json!(5.0); 3]);
+}
+
+#[wasm_bindgen_test]
+fn deadlines_pass_by_the_injected_clock() {
+    let xlsx = build_xlsx(&[(90, 4), (90, 4), (90, 4)]);
+    let full = json(estimate_document(&xlsx, None, None))["page_count"].as_u64().unwrap();
+    let options = Some(r#"{"deadline_ms": 500}"#.to_string());
+
+    // every reading is a second later, so only the first sheet is read
+    set_clock(Some(stepping_clock(1000.0)));
+    let partial = json(estimate_document(&xlsx, None, options.clone()));
+    assert!(partial["page_count"].as_u64().unwrap() < full, "{}", partial);
+    assert!(partial["notes"][0].as_str().unwrap().starts_with(DEADLINE_WARNING), "{}", partial);
+    assert!(partial["confidence"].as_f64().unwrap() <= DEADLINE_CONFIDENCE);
+
+    // a clock that stands still never passes it, and one that throws is ignored
+    set_clock(Some(Function::new_no_args("return 0;")));
+    assert_eq!(json(estimate_document(&xlsx, None, options.clone()))["page_count"], full);
+    set_clock(Some(Function::new_no_args("throw new Error('no clock');")));
+    assert_eq!(json(estimate_document(&xlsx, None, options))["page_count"], full);
+    set_clock(None);
+}
+
+#[wasm_bindgen_test]
+fn results_cut_short_are_not_cached() {
+    clear_estimate_cache();
+    let xlsx = build_xlsx(&[(90, 4), (90, 4), (90, 4)]);
+    let estimate = || json(estimate_document_cached(&xlsx, None, Some(r#"{"deadline_ms": 500}"#.into())));
+    set_clock(Some(stepping_clock(1000.0)));
+    let partial = estimate();
+    set_clock(None);
+    assert!(partial["notes"][0].as_str().unwrap().starts_with(DEADLINE_WARNING), "{}", partial);
+
+    // the next call has time to finish, and that result is kept
+    let full = estimate();
+    assert!(full["page_count"].as_u64() > partial["page_count"].as_u64(), "{}", full);
+    assert_eq!(full.get("cached"), None);
+    assert_eq!(estimate()["cached"], true);
+
+    // pages a late PDF never reached fail with their own code
+    let untyped = build_raw_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Parent 2 0 R >>",
+    ]);
+    set_clock(Some(stepping_clock(1000.0)));
+    let late = json(estimate_document(&untyped, None, Some(r#"{"deadline_ms": 500}"#.into())));
+    set_clock(None);
+    assert_eq!(late["error_code"], "deadline_exceeded", "{}", late);
+    clear_estimate_cache();
+}
+
+/// `estimate_url` against a `fetch` replaced by a server in the test.
+#[cfg(feature = "fetch")]
+mod url {
+    use super::*;
+    use page_counter_wasm::fetch::estimate_url;
+    use page_counter_wasm::pdf::LINEARIZATION_HEADER_BYTES;
+
+    /// Replaces the global `fetch` with a server of `body`, answering with
+    /// `status`, and returns the `Range` header of each request it gets.
+    fn serve(body: &[u8], status: u16, honors_range: bool) -> Array {
+        let requests = Array::new();
+        let server = Function::new_with_args(
+            "body, status, honorsRange, requests",
+            "return request => {
+                const range = request.headers.get('Range');
+                requests.push(range);
+                if (status !== 200) return Promise.resolve(new Response('', { status }));
+                if (range && honorsRange) {
+                    const end = Math.min(Number(range.split('-')[1]) + 1, body.length);
+                    const headers = { 'Content-Range': `bytes 0-${end - 1}/${body.length}` };
+                    return Promise.resolve(new Response(body.slice(0, end), { status: 206, headers }));
+                }
+                const headers = { 'Content-Length': String(body.length) };
+                return Promise.resolve(new Response(body, { headers }));
+            };",
+        );
+        let args = Array::of4(&Uint8Array::from(body), &status.into(), &honors_range.into(), &requests);
+        let fetch = server.apply(&JsValue::NULL, &args).unwrap();
+        Reflect::set(&js_sys::global(), &"fetch".into(), &fetch).unwrap();
+        requests
+    }
+
+    fn ranges(requests: &Array) -> Vec<Option<String>> {
+        requests.iter().map(|range| range.as_string()).collect()
+    }
+
+    fn first_bytes() -> Option<String> {
+        Some(format!("bytes=0-{}", LINEARIZATION_HEADER_BYTES - 1))
+    }
+
+    async fn estimate(url: &str, options: Option<&str>) -> Value {
+        json(estimate_url(url.into(), None, options.map(String::from)).await)
+    }
+
+    #[wasm_bindgen_test]
+    async fn linearized_pdfs_are_counted_from_their_first_bytes() {
+        let requests = serve(&build_linearized_pdf(12), 200, true);
+        let result = estimate("https://cdn.example.com/manual.pdf", None).await;
+        assert_eq!(result["page_count"], 12);
+        assert!(result["notes"][0].as_str().unwrap().contains("only the first 2048 bytes"), "{}", result);
+        assert_eq!(ranges(&requests), [first_bytes()]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn other_pdfs_are_downloaded_in_full() {
+        let requests = serve(&build_pdf(5, XrefStyle::Classic, false, false), 200, true);
+        let result = estimate("https://cdn.example.com/manual.pdf", None).await;
+        assert_eq!(result["page_count"], 5);
+        assert_eq!(ranges(&requests), [first_bytes(), None]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn servers_ignoring_the_range_send_the_whole_file_once() {
+        let requests = serve(&build_linearized_pdf(12), 200, false);
+        let result = estimate("https://cdn.example.com/manual.pdf", None).await;
+        assert_eq!(result["page_count"], 12);
+        assert_eq!(ranges(&requests), [first_bytes()]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn other_formats_skip_the_range_request() {
+        let requests = serve(&build_docx(60, 2, Some(4)), 200, true);
+        let result = estimate("https://cdn.example.com/plan.docx?sig=abc", None).await;
+        assert_eq!(result["page_count"], 4);
+        assert_eq!(ranges(&requests), [None]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn failed_downloads_have_error_codes() {
+        serve(b"", 404, true);
+        let missing = estimate("https://cdn.example.com/manual.pdf", None).await;
+        assert_eq!((&missing["error_code"], &missing["status"]), (&"http_error".into(), &404.into()));
+
+        let limit = Some(r#"{"max_input_bytes":1000}"#);
+        let pdf = build_pdf(20, XrefStyle::Classic, false, false);
+        serve(&pdf, 200, true);
+        assert_eq!(estimate("https://cdn.example.com/manual.pdf", limit).await["error_code"], "too_large");
+        serve(&pdf, 200, false);
+        assert_eq!(estimate("https://cdn.example.com/manual.pdf", limit).await["error_code"], "too_large");
+
+        let offline = Function::new_no_args("return Promise.reject(new TypeError('Failed to fetch'));");
+        Reflect::set(&js_sys::global(), &"fetch".into(), &offline).unwrap();
+        let result = estimate("https://cdn.example.com/manual.pdf", None).await;
+        assert_eq!(result["error_code"], "network_error");
+        assert!(result["error"].as_str().unwrap().contains("Failed to fetch"), "{}", result);
+    }
+}
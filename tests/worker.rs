@@ -0,0 +1,110 @@
+//! The Web Worker protocol of `handle_worker_message`, run inside a dedicated worker:
+//!
+//! ```sh
+//! wasm-pack test --headless --firefox -- --features test-fixtures --test worker
+//! ```
+//!
+//! Requests are built the way `js_bridges/estimate_worker_client.js` posts
+//! them, and responses are read back as the main thread would receive them.
+
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::{ArrayBuffer, Function, Object, Reflect, Uint8Array, JSON};
+use page_counter_wasm::fixtures::*;
+use page_counter_wasm::schema::SCHEMA_VERSION;
+use page_counter_wasm::worker::handle_worker_message;
+use serde_json::{json, Value};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+/// A request object with the given fields, `bytes` set separately.
+fn request(fields: Value, bytes: &JsValue) -> JsValue {
+    let msg = JSON::parse(&fields.to_string()).unwrap();
+    Reflect::set(&msg, &"bytes".into(), bytes).unwrap();
+    msg
+}
+
+/// The bytes as a standalone `ArrayBuffer`, as a `File` reads them.
+fn buffer(bytes: &[u8]) -> JsValue {
+    Uint8Array::from(bytes).buffer().into()
+}
+
+/// Handles `msg` and reads the response back as JSON.
+async fn respond(msg: JsValue) -> Value {
+    let response = JsFuture::from(handle_worker_message(msg)).await.expect("responses never reject");
+    serde_json::from_str(&JSON::stringify(&response).unwrap().as_string().unwrap()).unwrap()
+}
+
+#[wasm_bindgen_test]
+async fn estimate_requests_are_answered() {
+    let pdf = build_pdf(3, XrefStyle::Classic, false, false);
+    let options = json!({"include_point_sizes": true});
+    let msg = request(json!({"id": 7, "op": "estimate", "filename": "a.pdf", "options": options}), &buffer(&pdf));
+    let response = respond(msg).await;
+    assert_eq!((&response["id"], &response["ok"]), (&json!(7), &json!(true)));
+    assert_eq!(response["result"]["page_count"], 3);
+    assert_eq!(response["result"]["page_sizes_pt"].as_array().unwrap().len(), 3);
+
+    // options may be a JSON string, and bytes a view
+    let text = "A few words. ".repeat(400);
+    let view: JsValue = Uint8Array::from(text.as_bytes()).into();
+    let msg = request(json!({"id": "b", "op": "estimate", "options": "{\"chars_per_page\": 1000}"}), &view);
+    let response = respond(msg).await;
+    assert_eq!((&response["id"], &response["result"]["page_count"]), (&json!("b"), &json!(6)));
+}
+
+#[wasm_bindgen_test]
+async fn transferred_buffers_are_read_in_the_worker() {
+    // the client posts the buffer in the transfer list, which detaches it on its side
+    let transfer = Function::new_with_args("msg", "return structuredClone(msg, { transfer: [msg.bytes] });");
+    let original = buffer(&build_docx(20, 2, Some(4)));
+    let msg = request(json!({"id": 1, "op": "estimate", "filename": "letter.docx"}), &original);
+    let moved = transfer.call1(&JsValue::NULL, &msg).unwrap();
+    assert_eq!(ArrayBuffer::from(original).byte_length(), 0);
+    let response = respond(moved).await;
+    assert_eq!(response["result"]["page_count"], 4);
+}
+
+#[wasm_bindgen_test]
+async fn validate_and_detect_requests_are_answered() {
+    let pdf = build_pdf(2, XrefStyle::Stream, false, false);
+    let response = respond(request(json!({"id": 2, "op": "validate", "filename": "a.pdf"}), &buffer(&pdf))).await;
+    assert_eq!(response["ok"], true);
+    assert_eq!((&response["result"]["format"], &response["result"]["valid"]), (&json!("pdf"), &json!(true)));
+
+    let broken = truncate_in_part(&build_xlsx(&[(10, 2)]), "xl/worksheets/sheet1.xml");
+    let response = respond(request(json!({"id": 3, "op": "validate", "filename": "a.xlsx"}), &buffer(&broken))).await;
+    assert_eq!((&response["ok"], &response["result"]["valid"]), (&json!(true), &json!(false)));
+    assert!(!response["result"]["issues"].as_array().unwrap().is_empty());
+
+    // the MIME type of the File outranks its name
+    let notes = b"Notes\n\nSome text.\n";
+    let fields = json!({"id": 4, "op": "detect", "filename": "notes.txt", "mime_hint": "text/markdown"});
+    let response = respond(request(fields, &buffer(notes))).await;
+    assert_eq!(response["result"], json!({"schema_version": SCHEMA_VERSION, "format": "markdown"}));
+}
+
+#[wasm_bindgen_test]
+async fn malformed_requests_fail_with_invalid_message() {
+    let pdf = buffer(&build_pdf(1, XrefStyle::Classic, false, false));
+    let cases = [
+        (request(json!({"id": 5}), &pdf), "op is missing"),
+        (request(json!({"id": 5, "op": "estimate"}), &JsValue::from_str("not bytes")), "bytes must be"),
+        (request(json!({"id": 5, "op": "print"}), &pdf), "unknown op \"print\""),
+    ];
+    for (msg, message) in cases {
+        let response = respond(msg).await;
+        assert_eq!((&response["id"], &response["ok"]), (&json!(5), &json!(false)));
+        assert_eq!(response["error"]["error_code"], "invalid_message");
+        assert!(response["error"]["error"].as_str().unwrap().contains(message), "{}", response);
+    }
+
+    // estimation errors come back as the estimator's error object
+    let msg = request(json!({"id": 6, "op": "estimate", "options": {"chars_per_page": 0}}), &pdf);
+    let response = respond(msg).await;
+    assert_eq!((&response["ok"], &response["error"]["error_code"]), (&json!(false), &json!("invalid_options")));
+    assert!(respond(Object::new().into()).await["id"].is_null());
+}